@@ -0,0 +1,209 @@
+// Benchmarks for the miner's hot paths: admitting transactions to the mempool, walking the
+// mempool for candidates, and assembling an anchored block from them.  Each runs against a
+// generated workload of token transfers, contract-calls and contract publishes from many senders.
+//
+// Criterion writes its estimates as JSON under target/criterion/.  To check a change for
+// regressions, save a baseline before it and compare against that baseline after it:
+//
+//   cargo bench --bench mempool_bench -- --save-baseline before
+//   cargo bench --bench mempool_bench -- --baseline before
+
+#[macro_use]
+extern crate criterion;
+extern crate blockstack_lib;
+extern crate rand;
+
+use blockstack_lib::address::AddressHashMode;
+use blockstack_lib::burnchains::{Address, PublicKey};
+use blockstack_lib::chainstate::stacks::{
+    StacksAddress, StacksPrivateKey, StacksPublicKey, StacksTransaction, StacksTransactionSigner,
+    TransactionAuth, TransactionPayload, TransactionSpendingCondition, TransactionSmartContract,
+    TransactionContractCall, TransactionVersion, TransactionAnchorMode, TransactionPostConditionMode,
+    TokenTransferMemo, CoinbasePayload, C32_ADDRESS_VERSION_TESTNET_SINGLESIG, Error as ChainstateError
+};
+use blockstack_lib::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo, STACKS_BOOT_CODE_CONTRACT_ADDRESS};
+use blockstack_lib::chainstate::stacks::index::TrieHash;
+use blockstack_lib::chainstate::stacks::miner::StacksBlockBuilder;
+use blockstack_lib::core::{FIRST_BURNCHAIN_BLOCK_HASH, FIRST_STACKS_BLOCK_HASH};
+use blockstack_lib::core::mempool::MemPoolDB;
+use blockstack_lib::net::loadgen::{TransactionKind, TransactionMix};
+use blockstack_lib::util::hash::{hex_bytes, Hash160};
+use blockstack_lib::util::strings::StacksString;
+use blockstack_lib::util::vrf::VRFProof;
+use blockstack_lib::vm::{ClarityName, ContractName, Value};
+use blockstack_lib::vm::costs::ExecutionCost;
+use blockstack_lib::vm::types::PrincipalData;
+
+use criterion::Criterion;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use std::convert::TryFrom;
+use std::fs;
+
+const CHAIN_ID : u32 = 0x80000000;
+const NUM_SENDERS : usize = 50;
+const TXS_PER_SENDER : u64 = 12;
+const SENDER_BALANCE : u64 = 1_000_000_000;
+
+const BENCH_CONTRACT : &'static str = "
+    (define-data-var counter uint u0)
+    (define-public (incr)
+        (begin
+            (var-set counter (+ (var-get counter) u1))
+            (ok (var-get counter))))";
+
+/// A chainstate whose boot block funds every sender, and the signed transactions they send
+struct Workload {
+    chainstate: StacksChainState,
+    chainstate_path: String,
+    txs: Vec<(TransactionKind, StacksTransaction)>,
+}
+
+fn sender_key(i: usize) -> StacksPrivateKey {
+    let mut seed = [0x11u8; 32];
+    seed[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+    let mut privk = StacksPrivateKey::from_slice(&seed).unwrap();
+    privk.set_compress_public(true);
+    privk
+}
+
+fn sender_address(privk: &StacksPrivateKey) -> StacksAddress {
+    StacksAddress::from_public_keys(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, &AddressHashMode::SerializeP2PKH, 1, &vec![StacksPublicKey::from_private(privk)]).unwrap()
+}
+
+fn sign_tx(privk: &StacksPrivateKey, nonce: u64, fee_rate: u64, payload: TransactionPayload) -> StacksTransaction {
+    let mut spending_condition = TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(privk)).unwrap();
+    spending_condition.set_nonce(nonce);
+    spending_condition.set_fee_rate(fee_rate);
+
+    let mut unsigned_tx = StacksTransaction::new(TransactionVersion::Testnet, TransactionAuth::Standard(spending_condition), payload);
+    unsigned_tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+    unsigned_tx.post_condition_mode = TransactionPostConditionMode::Allow;
+    unsigned_tx.chain_id = CHAIN_ID;
+
+    let mut signer = StacksTransactionSigner::new(&unsigned_tx);
+    signer.sign_origin(privk).unwrap();
+    signer.get_tx().unwrap()
+}
+
+fn make_payload(kind: TransactionKind, sender: usize, nonce: u64, recipient: &StacksAddress) -> TransactionPayload {
+    match kind {
+        TransactionKind::TokenTransfer => TransactionPayload::TokenTransfer(PrincipalData::from(recipient.clone()), 100, TokenTransferMemo([0u8; 34])),
+        TransactionKind::ContractCall => TransactionPayload::ContractCall(TransactionContractCall {
+            address: StacksAddress::from_string(STACKS_BOOT_CODE_CONTRACT_ADDRESS).unwrap(),
+            contract_name: ContractName::try_from("miner-rewards".to_string()).unwrap(),
+            function_name: ClarityName::try_from("get-participant-reward".to_string()).unwrap(),
+            function_args: vec![Value::Principal(PrincipalData::from(recipient.clone()))],
+        }),
+        TransactionKind::Publish => TransactionPayload::SmartContract(TransactionSmartContract {
+            name: ContractName::try_from(format!("bench-{}-{}", sender, nonce)).unwrap(),
+            code_body: StacksString::from_str(BENCH_CONTRACT).unwrap(),
+        }),
+    }
+}
+
+/// Make a fresh chainstate and a workload for it.  Each sender sends a mix of transfers,
+/// contract-calls and publishes, in nonce order, at a random fee rate.
+fn make_workload(name: &str) -> Workload {
+    let chainstate_path = format!("/tmp/blockstack-bench-chainstate-{}", name);
+    if fs::metadata(&chainstate_path).is_ok() {
+        fs::remove_dir_all(&chainstate_path).unwrap();
+    }
+
+    let senders : Vec<StacksPrivateKey> = (0..NUM_SENDERS).map(sender_key).collect();
+    let addresses : Vec<StacksAddress> = senders.iter().map(sender_address).collect();
+    let balances = addresses.iter().map(|addr| (PrincipalData::from(addr.clone()), SENDER_BALANCE)).collect();
+
+    let chainstate = StacksChainState::open_testnet(CHAIN_ID, &chainstate_path, Some(balances), |_| {}, ExecutionCost::max_value()).unwrap();
+
+    let mix = TransactionMix { token_transfer: 50, contract_call: 30, publish: 20 };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut txs = vec![];
+    for nonce in 0..TXS_PER_SENDER {
+        for (i, privk) in senders.iter().enumerate() {
+            let kind = mix.pick(rng.gen_range(0, 100));
+            let recipient = &addresses[(i + 1) % addresses.len()];
+            let fee_rate = rng.gen_range(1, 100);
+            txs.push((kind, sign_tx(privk, nonce, fee_rate, make_payload(kind, i, nonce, recipient))));
+        }
+    }
+
+    Workload {
+        chainstate,
+        chainstate_path,
+        txs
+    }
+}
+
+/// Make a workload, and submit all of its transactions to the chainstate's mempool
+fn make_mempool_workload(name: &str) -> (Workload, MemPoolDB) {
+    let workload = make_workload(name);
+    let mut mempool = MemPoolDB::open(false, CHAIN_ID, &workload.chainstate_path).unwrap();
+    for (_, tx) in workload.txs.iter() {
+        mempool.submit(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, tx.clone()).unwrap();
+    }
+    (workload, mempool)
+}
+
+pub fn will_admit_mempool_tx_benchmark(c: &mut Criterion) {
+    let mut workload = make_workload("will-admit");
+
+    let mut group = c.benchmark_group("will_admit_mempool_tx");
+    for kind in [TransactionKind::TokenTransfer, TransactionKind::ContractCall, TransactionKind::Publish].iter() {
+        let txs : Vec<(StacksTransaction, u64)> = workload.txs.iter()
+            .filter(|(tx_kind, _)| tx_kind == kind)
+            .map(|(_, tx)| (tx.clone(), tx.tx_len()))
+            .collect();
+
+        let chainstate = &mut workload.chainstate;
+        let mut next = 0;
+        group.bench_function(&kind.to_string(), |b| b.iter(|| {
+            let (ref tx, tx_len) = txs[next % txs.len()];
+            next += 1;
+            chainstate.will_admit_mempool_tx(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, tx, tx_len).unwrap();
+        }));
+    }
+    group.finish();
+}
+
+pub fn mempool_iteration_benchmark(c: &mut Criterion) {
+    let (mut workload, mempool) = make_mempool_workload("iterate");
+    let num_txs = workload.txs.len();
+    let chainstate = &mut workload.chainstate;
+
+    c.bench_function("mempool_iterate_candidates", |b| b.iter(|| {
+        let mut considered = 0;
+        mempool.iterate_candidates(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, 0, chainstate, |txs| -> Result<(), ChainstateError> {
+            considered += txs.len();
+            Ok(())
+        }).unwrap();
+        assert_eq!(considered, num_txs);
+    }));
+}
+
+pub fn block_assembly_benchmark(c: &mut Criterion) {
+    let (workload, mempool) = make_mempool_workload("assemble");
+
+    let miner_privk = sender_key(NUM_SENDERS);
+    let pubkey_hash = Hash160::from_data(&StacksPublicKey::from_private(&miner_privk).to_bytes());
+    let coinbase_tx = sign_tx(&miner_privk, 0, 0, TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])));
+
+    // the proof isn't checked during assembly
+    let proof = VRFProof::from_bytes(&hex_bytes("9275df67a68c8745c0ff97b48201ee6db447f7c93b23ae24cdc2400f52fdb08a1a6ac7ec71bf9c9c76e96ee4675ebff60625af28718501047bfd87b810c2d2139b73c23bd69de66360953a642c2a330a").unwrap()).unwrap();
+    let parent_header = StacksHeaderInfo::genesis_block_header_info(TrieHash([0u8; 32]));
+
+    c.bench_function("build_anchored_block", |b| b.iter(|| {
+        let (block, _, _) = StacksBlockBuilder::build_anchored_block(&workload.chainstate, &mempool, &parent_header, 0, proof.clone(), pubkey_hash.clone(),
+                                                                     &coinbase_tx, ExecutionCost::max_value()).unwrap();
+        // the coinbase, and then some
+        assert!(block.txs.len() > 1);
+    }));
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = will_admit_mempool_tx_benchmark, mempool_iteration_benchmark, block_assembly_benchmark
+}
+criterion_main!(benches);
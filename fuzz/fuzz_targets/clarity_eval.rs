@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use blockstack_lib::vm::fuzz::{self, ArbitraryContract};
+
+fuzz_target!(|contract: ArbitraryContract| {
+    fuzz::check_eval(&contract);
+});
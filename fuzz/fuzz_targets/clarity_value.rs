@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use blockstack_lib::vm::fuzz::{self, ArbitraryValue};
+
+fuzz_target!(|value: ArbitraryValue| {
+    fuzz::check_value_serialization(&value.0);
+});
@@ -5,6 +5,7 @@
 #![allow(non_upper_case_globals)]
 
 extern crate blockstack_lib;
+#[macro_use] extern crate serde_json;
 
 use std::{io, fs, env};
 use std::io::prelude::*;
@@ -23,7 +24,8 @@ use blockstack_lib::chainstate::stacks::{
     StacksTransaction, TransactionSmartContract, TransactionContractCall, StacksAddress, TokenTransferMemo };
 use blockstack_lib::burnchains::Address;
 use blockstack_lib::address::AddressHashMode;
-use blockstack_lib::net::{Error as NetError, StacksMessageCodec};
+use blockstack_lib::net::{Error as NetError, StacksMessageCodec, PeerHost};
+use blockstack_lib::net::loadgen::{Workload, LoadGenerator};
 
 const TESTNET_CHAIN_ID : u32 = 0x80000000;
 const MAINNET_CHAIN_ID : u32 = 0x00000001;
@@ -39,6 +41,7 @@ This CLI has these methods:
   contract-call    used to generate and sign a contract-call transaction
   generate-sk      used to generate a secret key for transaction signing
   token-transfer   used to generate and sign a transfer transaction
+  load-test        used to run a JSON workload of transactions against a node
 
 For usage information on those methods, call `blockstack-cli [method] -h`
 
@@ -87,6 +90,32 @@ const GENERATE_USAGE: &str = "blockstack-cli (options) generate-sk
 This method generates a secret key, outputting the hex encoding of the
 secret key, the corresponding public key, and the corresponding P2PKH Stacks address.";
 
+const LOAD_TEST_USAGE: &str = "blockstack-cli (options) load-test [workload.json] [node-host:port]
+blockstack-cli (options) load-test -a [workload.json]
+
+The load-test command sends the transactions described by a JSON workload to a node's RPC
+interface at the target rate, waits for each one to be anchored, and outputs a JSON report of
+throughput and latency. With -a, it instead outputs the workload's sending accounts, which must
+be funded before the test runs.
+
+A workload looks like this (fields with defaults shown are optional):
+
+   {
+     \"accounts\": 10,
+     \"seed\": \"load-test\",
+     \"mix\": { \"token_transfer\": 70, \"contract_call\": 20, \"publish\": 10 },
+     \"contracts\": [ { \"name\": \"counter\", \"source\": \"(define-data-var n int {n})\" } ],
+     \"calls\": [ { \"contract\": \"ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter\", \"function\": \"add\", \"args\": [\"u1\"] } ],
+     \"target_tps\": 5,
+     \"duration\": 300,
+     \"fee_rate\": 1000,
+     \"transfer_amount\": 1,
+     \"wait_timeout\": 60
+   }
+
+The mix percentages must add up to 100. Token transfers go to the next account in the workload.
+Each publish gets a fresh contract name of the form `{name}-{n}`, with `{n}` substituted into the
+source as well. Call arguments are Clarity expressions.";
 
 #[derive(Debug)]
 enum CliError {
@@ -360,6 +389,35 @@ fn generate_secret_key(args: &[String], version: TransactionVersion) -> Result<S
              address.to_string()))
 }
 
+fn handle_load_test(args: &[String], version: TransactionVersion, chain_id: u32) -> Result<String, CliError> {
+    if args.len() >= 1 && args[0] == "-h" {
+        return Err(CliError::Message(format!("USAGE:\n {}", LOAD_TEST_USAGE)))
+    }
+    if args.len() != 2 {
+        return Err(CliError::Message(format!("Incorrect argument count supplied \n\nUSAGE:\n {}", LOAD_TEST_USAGE)))
+    }
+
+    let (list_accounts, workload_file, node) = if args[0] == "-a" {
+        (true, &args[1], "127.0.0.1:20443")
+    } else {
+        (false, &args[0], args[1].as_str())
+    };
+
+    let workload = Workload::from_json(&fs::read_to_string(workload_file)?)?;
+    let peer = node.parse::<PeerHost>()?;
+    let mut generator = LoadGenerator::new(workload, peer, version, chain_id)?;
+
+    if list_accounts {
+        let accounts : Vec<_> = generator.account_list().into_iter()
+            .map(|(secret_key, address)| json!({ "secretKey": secret_key, "stacksAddress": address }))
+            .collect();
+        return Ok(serde_json::to_string_pretty(&accounts).expect("FATAL: failed to serialize accounts"));
+    }
+
+    let report = generator.run()?;
+    Ok(serde_json::to_string_pretty(&report).expect("FATAL: failed to serialize load test report"))
+}
+
 fn main() {
     log::set_loglevel(log::LOG_DEBUG).unwrap();
     let mut argv : Vec<String> = env::args().collect();
@@ -399,6 +457,7 @@ fn main_handler(mut argv: Vec<String>) -> Result<String, CliError> {
             "publish" => handle_contract_publish(args, tx_version, chain_id),
             "token-transfer" => handle_token_transfer(args, tx_version, chain_id),
             "generate-sk" => generate_secret_key(args, tx_version),
+            "load-test" => handle_load_test(args, tx_version, chain_id),
             _ => Err(CliError::Usage)
         }
     } else {
@@ -445,6 +504,26 @@ mod test {
 
     }
 
+    #[test]
+    fn load_test_accounts() {
+        let workload_path = env::temp_dir().join("blockstack-cli-load-test-accounts.json");
+        fs::write(&workload_path, r#"{ "accounts": 3, "mix": { "token_transfer": 100 }, "target_tps": 1, "duration": 1 }"#).unwrap();
+        let workload_path = workload_path.to_str().unwrap();
+
+        let accounts_1 = main_handler(to_string_vec(&["load-test", "--testnet", "-a", workload_path])).unwrap();
+        let accounts_2 = main_handler(to_string_vec(&["load-test", "--testnet", "-a", workload_path])).unwrap();
+        assert_eq!(accounts_1, accounts_2);
+
+        let accounts : serde_json::Value = serde_json::from_str(&accounts_1).unwrap();
+        assert_eq!(accounts.as_array().unwrap().len(), 3);
+        assert!(accounts[0]["stacksAddress"].as_str().unwrap().starts_with("ST"));
+
+        assert!(format!("{}", main_handler(to_string_vec(&["load-test", workload_path])).unwrap_err())
+                .contains("Incorrect argument count"));
+        assert!(format!("{}", main_handler(to_string_vec(&["load-test", "-a", "./sample-contracts/tokens.clar"])).unwrap_err())
+                .contains("Failed to parse workload"));
+    }
+
     #[test]
     fn simple_token_transfer() {
         let tt_args = [
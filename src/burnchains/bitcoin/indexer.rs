@@ -104,7 +104,10 @@ pub struct BitcoinIndexerRuntime {
     pub block_height: u64,
     pub last_getdata_send_time: u64,
     pub last_getheaders_send_time: u64,
-    pub timeout: u64
+    pub timeout: u64,
+    /// How many times to try to connect to the peer before giving up, so another can be tried.
+    /// None to keep trying.
+    pub max_connect_attempts: Option<u64>,
 }
 
 pub struct BitcoinIndexer {
@@ -275,6 +278,7 @@ impl BitcoinIndexerRuntime {
             last_getdata_send_time: 0,
             last_getheaders_send_time: 0,
             timeout: 300,
+            max_connect_attempts: None,
         }
     }
 }
@@ -379,7 +383,11 @@ impl BitcoinIndexer {
                         // connection established!
                         do_handshake = false;
                     }
-                    Err(_) => {
+                    Err(e) => {
+                        if self.runtime.max_connect_attempts.is_some() {
+                            // gave up on this peer
+                            return Err(e);
+                        }
                         // need to try again 
                         continue;
                     }
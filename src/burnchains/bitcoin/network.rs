@@ -175,13 +175,23 @@ impl BitcoinIndexer {
 
     /// Connect to a remote peer, do a handshake with the remote peer, and use exponential backoff until we
     /// succeed in establishing a connection.
-    /// This method masks ConnectionBroken errors, but does not mask other network errors.
+    /// This method masks ConnectionBroken errors, but does not mask other network errors, and
+    /// gives up with a ConnectionError after the runtime's max_connect_attempts, if set.
     /// Returns the remote peer's block height on success
     pub fn connect_handshake_backoff(&mut self) -> Result<u64, btc_error> {
         let mut backoff: f64 = 1.0;
         let mut rng = thread_rng();
+        let mut attempts: u64 = 0;
 
         loop {
+            if let Some(max_connect_attempts) = self.runtime.max_connect_attempts {
+                if attempts >= max_connect_attempts {
+                    warn!("Giving up on connecting to {}:{} after {} attempts", &self.config.peer_host, self.config.peer_port, attempts);
+                    return Err(btc_error::ConnectionError);
+                }
+            }
+            attempts += 1;
+
             let connection_result = self.connect();
             match connection_result {
                 Ok(()) => {
@@ -82,6 +82,7 @@ use util::hash::to_hex;
 use util::get_epoch_time_ms;
 use util::db::DBConn;
 use util::db::DBTx;
+use util::db::SqlitePragmas;
 use util::vrf::VRFPublicKey;
 use util::get_epoch_time_secs;
 
@@ -400,7 +401,7 @@ impl Burnchain {
         db_path
     }
 
-    fn connect_db<I: BurnchainIndexer>(&self, indexer: &I, readwrite: bool) -> Result<BurnDB, burnchain_error> {
+    fn connect_db<I: BurnchainIndexer>(&self, indexer: &I, readwrite: bool, pragmas: &SqlitePragmas) -> Result<BurnDB, burnchain_error> {
         Burnchain::setup_chainstate_dirs(&self.working_dir, &self.chain_name, &self.network_name)?;
 
         let first_block_height = indexer.get_first_block_height();
@@ -408,19 +409,24 @@ impl Burnchain {
         let first_block_header_timestamp = indexer.get_first_block_header_timestamp()?;
         
         let db_path = self.get_db_path();
-        BurnDB::connect(&db_path, first_block_height, &first_block_header_hash, first_block_header_timestamp, readwrite)
+        BurnDB::connect_with_pragmas(&db_path, first_block_height, &first_block_header_hash, first_block_header_timestamp, readwrite, pragmas)
             .map_err(burnchain_error::DBError)
     }
 
     /// Open the burn database.  It must already exist.
     pub fn open_db(&self, readwrite: bool) -> Result<BurnDB, burnchain_error> {
+        self.open_db_with_pragmas(readwrite, &SqlitePragmas::default())
+    }
+
+    /// Open the burn database, tuned with `pragmas`.  It must already exist.
+    pub fn open_db_with_pragmas(&self, readwrite: bool, pragmas: &SqlitePragmas) -> Result<BurnDB, burnchain_error> {
         let db_path = self.get_db_path();
         let db_pathbuf = PathBuf::from(db_path.clone());
         if !db_pathbuf.exists() {
             return Err(burnchain_error::DBError(db_error::NoDBError));
         }
 
-        BurnDB::open(&db_path, readwrite)
+        BurnDB::open_with_pragmas(&db_path, readwrite, pragmas)
             .map_err(burnchain_error::DBError)
     }
 
@@ -815,8 +821,13 @@ impl Burnchain {
     /// Returns (snapshot of new burnchain tip, last state-transition processed if any)
     /// If this method returns Err(burnchain_error::TrySyncAgain), then call this method again.
     pub fn sync_with_indexer<I: BurnchainIndexer + 'static>(&mut self, indexer: &mut I) -> Result<(BlockSnapshot, Option<BurnchainStateTransition>), burnchain_error> {
+        self.sync_with_indexer_and_pragmas(indexer, &SqlitePragmas::default())
+    }
+
+    /// Like sync_with_indexer(), but the burn database is tuned with `pragmas`
+    pub fn sync_with_indexer_and_pragmas<I: BurnchainIndexer + 'static>(&mut self, indexer: &mut I, pragmas: &SqlitePragmas) -> Result<(BlockSnapshot, Option<BurnchainStateTransition>), burnchain_error> {
         self.setup_chainstate(indexer)?;
-        let mut burndb = self.connect_db(indexer, true)?;
+        let mut burndb = self.connect_db(indexer, true, pragmas)?;
         let burn_chain_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn())
             .map_err(|e| {
                 error!("Failed to query burn chain tip from burn DB");
@@ -32,7 +32,7 @@ use std::convert::From;
 use std::ops::Deref;
 use std::ops::DerefMut;
 
-use util::db::{FromRow, FromColumn, u64_to_sql, query_rows, query_row, query_row_columns, query_count, IndexDBTx, IndexDBConn, db_mkdirs};
+use util::db::{FromRow, FromColumn, u64_to_sql, query_rows, query_row, query_row_columns, query_count, IndexDBTx, IndexDBConn, db_mkdirs, hex_prefix_bounds};
 use util::db::Error as db_error;
 use util::db::tx_begin_immediate;
 use util::get_epoch_time_secs;
@@ -69,6 +69,7 @@ use chainstate::stacks::index::TrieHash;
 use chainstate::stacks::index::MarfTrieId;
 use chainstate::stacks::index::storage::TrieFileStorage;
 use chainstate::stacks::index::marf::MARF;
+use chainstate::stacks::index::backend::TrieBackend;
 use chainstate::stacks::index::MARFValue;
 use chainstate::stacks::index::Error as MARFError;
 
@@ -79,7 +80,8 @@ use util::vrf::*;
 use util::secp256k1::MessageSignature;
 use util::hash::{to_hex, hex_bytes, Hash160, Sha512Trunc256Sum};
 use util::strings::StacksString;
-use util::db::tx_busy_handler;
+use util::db::{sqlite_open, SqlitePragmas};
+use util::migrations::{SchemaMigration, check_schema_version, migrate_schema};
 
 use net::neighbors::MAX_NEIGHBOR_BLOCK_DELAY;
 
@@ -453,8 +455,12 @@ fn burndb_get_ancestor_block_hash<'a>(iconn: &BurnDBConn<'a>, block_height: u64,
     }
 }
 
+/// Changes to the sortition DB's schema, applied when it's opened for writing (see
+/// util::migrations).  Add a migration here instead of changing BURNDB_SETUP.
+pub const BURNDB_MIGRATIONS : &'static [SchemaMigration] = &[];
+
 impl BurnDB {
-    fn instantiate(conn: &mut Connection, index_path: &str, first_block_height: u64, first_burn_header_hash: &BurnchainHeaderHash, first_burn_header_timestamp: u64) -> Result<(), db_error> {
+    fn instantiate(conn: &mut Connection, index_path: &str, first_block_height: u64, first_burn_header_hash: &BurnchainHeaderHash, first_burn_header_timestamp: u64, pragmas: &SqlitePragmas) -> Result<(), db_error> {
         let tx = tx_begin_immediate(conn)?;
 
         // create first (sentinel) snapshot
@@ -469,7 +475,7 @@ impl BurnDB {
 
         tx.execute("INSERT INTO db_config (version) VALUES (?1)", &[&CHAINSTATE_VERSION]).map_err(db_error::SqliteError)?;
         
-        let mut marf = BurnDB::open_index(index_path)?;
+        let mut marf = BurnDB::open_index(index_path, pragmas)?;
         let mut burndbtx = BurnDBTx::new(tx, &mut marf, BurnDBTxContext { first_block_height: first_block_height });
         
         burndbtx.instantiate_index()?;
@@ -532,6 +538,12 @@ impl BurnDB {
     /// Open the burn database at the given path.  Open read-only or read/write.
     /// If opened for read/write and it doesn't exist, instantiate it.
     pub fn connect(path: &String, first_block_height: u64, first_burn_hash: &BurnchainHeaderHash, first_burn_header_timestamp: u64, readwrite: bool) -> Result<BurnDB, db_error> {
+        BurnDB::connect_with_pragmas(path, first_block_height, first_burn_hash, first_burn_header_timestamp, readwrite, &SqlitePragmas::default())
+    }
+
+    /// Like connect(), but the database and its index are tuned with `pragmas`
+    pub fn connect_with_pragmas(path: &String, first_block_height: u64, first_burn_hash: &BurnchainHeaderHash, first_burn_header_timestamp: u64,
+                                readwrite: bool, pragmas: &SqlitePragmas) -> Result<BurnDB, db_error> {
         let mut create_flag = false;
         let open_flags = match fs::metadata(path) {
             Err(e) => {
@@ -564,12 +576,11 @@ impl BurnDB {
         debug!("Connect/Open burndb '{}' as '{}', with index as '{}'",
                db_path, if readwrite { "readwrite" } else { "readonly" }, index_path);
 
-        let mut conn = Connection::open_with_flags(&db_path, open_flags).map_err(db_error::SqliteError)?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(db_error::SqliteError)?;
+        let mut conn = sqlite_open(&db_path, open_flags, Some(pragmas)).map_err(db_error::SqliteError)?;
 
         if create_flag {
             // instantiate!
-            BurnDB::instantiate(&mut conn, &index_path, first_block_height, first_burn_hash, first_burn_header_timestamp)?;
+            BurnDB::instantiate(&mut conn, &index_path, first_block_height, first_burn_hash, first_burn_header_timestamp, pragmas)?;
         }
         else {
             // validate -- must contain the given first block and first block hash 
@@ -588,7 +599,14 @@ impl BurnDB {
             };
         }
 
-        let marf = BurnDB::open_index(&index_path)?;
+        if readwrite {
+            migrate_schema(&mut conn, BURNDB_MIGRATIONS, None, false)?;
+        }
+        else {
+            check_schema_version(&conn, BURNDB_MIGRATIONS)?;
+        }
+
+        let marf = BurnDB::open_index(&index_path, pragmas)?;
 
         let db = BurnDB {
             conn: conn,
@@ -611,12 +629,12 @@ impl BurnDB {
         let (db_path, index_path) = db_mkdirs(&db_path_dir)?;
 
         debug!("Open {}", &db_path);
-        let mut conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE).map_err(db_error::SqliteError)?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(db_error::SqliteError)?;
+        let pragmas = SqlitePragmas::default();
+        let mut conn = sqlite_open(&db_path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE, Some(&pragmas)).map_err(db_error::SqliteError)?;
 
-        BurnDB::instantiate(&mut conn, &index_path, first_block_height, first_burn_hash, get_epoch_time_secs())?;
+        BurnDB::instantiate(&mut conn, &index_path, first_block_height, first_burn_hash, get_epoch_time_secs(), &pragmas)?;
 
-        let marf = BurnDB::open_index(&index_path)?;
+        let marf = BurnDB::open_index(&index_path, &pragmas)?;
 
         let db = BurnDB {
             conn: conn,
@@ -633,6 +651,11 @@ impl BurnDB {
     /// It's best not to call this if you are able to call connect().  If you must call this, do so
     /// after you call connect() somewhere else, since connect() performs additional validations.
     pub fn open(path: &str, readwrite: bool) -> Result<BurnDB, db_error> {
+        BurnDB::open_with_pragmas(path, readwrite, &SqlitePragmas::default())
+    }
+
+    /// Like open(), but the database and its index are tuned with `pragmas`
+    pub fn open_with_pragmas(path: &str, readwrite: bool, pragmas: &SqlitePragmas) -> Result<BurnDB, db_error> {
         let open_flags =
             if readwrite {
                 OpenFlags::SQLITE_OPEN_READ_WRITE
@@ -645,10 +668,9 @@ impl BurnDB {
         debug!("Open burndb '{}' as '{}', with index as '{}'",
                db_path, if readwrite { "readwrite" } else { "readonly" }, index_path);
         
-        let conn = Connection::open_with_flags(&db_path, open_flags).map_err(db_error::SqliteError)?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(db_error::SqliteError)?;
+        let conn = sqlite_open(&db_path, open_flags, Some(pragmas)).map_err(db_error::SqliteError)?;
 
-        let marf = BurnDB::open_index(&index_path)?;
+        let marf = BurnDB::open_index(&index_path, pragmas)?;
         let first_snapshot = BurnDB::get_first_block_snapshot(&conn)?;
 
         let db = BurnDB {
@@ -665,9 +687,15 @@ impl BurnDB {
         &self.conn
     }
 
-    pub fn open_index(index_path: &str) -> Result<MARF<BurnchainHeaderHash>, db_error> {
+    /// Keep up to this many bytes of the sortition MARF's nodes in RAM.  0 (the default) turns
+    /// the cache off.
+    pub fn set_marf_node_cache_size(&mut self, max_bytes: usize) {
+        self.marf.set_node_cache_size(max_bytes)
+    }
+
+    pub fn open_index(index_path: &str, pragmas: &SqlitePragmas) -> Result<MARF<BurnchainHeaderHash>, db_error> {
         test_debug!("Open index at {}", index_path);
-        let marf = MARF::from_path(index_path, None).map_err(|_e| db_error::Corruption)?;
+        let marf = MARF::from_path_with_backend(index_path, None, TrieBackend::Sqlite, pragmas).map_err(|_e| db_error::Corruption)?;
         Ok(marf)
     }
 
@@ -1308,6 +1336,15 @@ impl BurnDB {
         }
     }
     
+    /// Find up to `limit` snapshots, across all burn chain forks, whose burn header hash starts
+    /// with the given lowercase hex prefix.
+    pub fn find_snapshots_by_hash_prefix(conn: &Connection, prefix: &str, limit: u64) -> Result<Vec<BlockSnapshot>, db_error> {
+        let (lower, upper) = hex_prefix_bounds(prefix);
+        let qry = "SELECT * FROM snapshots WHERE burn_header_hash >= ?1 AND burn_header_hash < ?2 ORDER BY burn_header_hash LIMIT ?3".to_string();
+        let args : &[&dyn ToSql] = &[&lower, &upper, &u64_to_sql(limit)?];
+        query_rows::<BlockSnapshot, _>(conn, &qry, args)
+    }
+
     /// Get a snapshot for an existing burn chain block given its consensus hash.
     pub fn get_block_snapshot_consensus(conn: &Connection, consensus_hash: &ConsensusHash) -> Result<Option<BlockSnapshot>, db_error> {
         let qry = "SELECT * FROM snapshots WHERE consensus_hash = ?1".to_string();
@@ -3537,4 +3574,34 @@ mod tests {
         assert_eq!(last_snapshot.canonical_stacks_tip_hash, BlockHeaderHash([0x48; 32]));
         assert_eq!(last_snapshot.canonical_stacks_tip_height, 8);
     }
+
+    #[test]
+    fn test_concurrent_readonly_handle() {
+        let first_burn_hash = BurnchainHeaderHash::from_hex("10000000000000000000000000000000000000000000000000000000000000ff").unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buf = [0u8; 32];
+        rng.fill_bytes(&mut buf);
+        let path = format!("/tmp/test-blockstack-burndb-concurrent-{}", to_hex(&buf));
+
+        let mut db = BurnDB::connect(&path, 0, &first_burn_hash, get_epoch_time_secs(), true).unwrap();
+        let journal_mode : String = db.conn().query_row("PRAGMA journal_mode", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        // a read-only handle in the middle of a read sees a snapshot of the DB, and doesn't stop
+        // the writer from committing
+        let reader = BurnDB::open(&path, false).unwrap();
+        reader.conn().execute_batch("BEGIN").unwrap();
+        let count_snapshots = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM snapshots", NO_PARAMS, |row| row.get(0)).unwrap()
+        };
+        let num_snapshots = count_snapshots(reader.conn());
+
+        let first_snapshot = BurnDB::get_first_block_snapshot(db.conn()).unwrap();
+        make_fork_run(&mut db, &first_snapshot, 3, 0);
+        assert_eq!(count_snapshots(db.conn()), num_snapshots + 3);
+        assert_eq!(count_snapshots(reader.conn()), num_snapshots);
+
+        reader.conn().execute_batch("COMMIT").unwrap();
+        assert_eq!(count_snapshots(reader.conn()), num_snapshots + 3);
+    }
 }
@@ -0,0 +1,258 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use rusqlite::Row;
+use rusqlite::Connection;
+use rusqlite::types::ToSql;
+
+use burnchains::Txid;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::events::*;
+use chainstate::stacks::db::contract_costs::charged_contract;
+
+use util::db::Error as db_error;
+use util::db::{
+    FromRow,
+    FromColumn,
+    query_row,
+    query_rows,
+    u64_to_sql,
+};
+
+use vm::types::PrincipalData;
+
+/// The transactions each principal took part in, for wallets that have no indexer of their own.
+/// This is NOT consensus data:  it's derived from blocks as they're processed, and only if the
+/// node is configured to keep it.  Created on open if missing.  `address_index_blocks` records
+/// which blocks' transactions are indexed, so a query at a tip that isn't can say so instead of
+/// returning a partial history.
+pub const ADDRESS_INDEX_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS address_transactions(
+        principal TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,     -- NOTE: a transaction can be mined in a block on each fork
+        block_height INTEGER NOT NULL,
+        tx_index INTEGER NOT NULL,          -- position among the transactions the block mined
+
+        PRIMARY KEY(principal,txid,index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS address_transactions_by_height ON address_transactions(principal,block_height);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS address_index_blocks(
+        index_block_hash TEXT NOT NULL,     -- every transaction this block mined is indexed
+        block_height INTEGER NOT NULL,
+
+        PRIMARY KEY(index_block_hash)
+    );
+    "#,
+];
+
+/// A transaction a principal took part in
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressTransactionEntry {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub tx_index: u64,
+}
+
+impl FromRow<AddressTransactionEntry> for AddressTransactionEntry {
+    fn from_row<'a>(row: &'a Row) -> Result<AddressTransactionEntry, db_error> {
+        Ok(AddressTransactionEntry {
+            txid: Txid::from_column(row, "txid")?,
+            index_block_hash: StacksBlockId::from_column(row, "index_block_hash")?,
+            block_height: u64::from_column(row, "block_height")?,
+            tx_index: u64::from_column(row, "tx_index")?
+        })
+    }
+}
+
+/// The principals a mined transaction involves:  its sender and sponsor, the recipient of a
+/// token transfer, the contract it calls or deploys, and whoever its STX, fungible token and
+/// non-fungible token events moved assets to or from
+pub fn address_transaction_principals(receipt: &StacksTransactionReceipt) -> Vec<PrincipalData> {
+    let mut principals = HashSet::new();
+    principals.insert(PrincipalData::from(receipt.transaction.origin_address()));
+    if let Some(sponsor_address) = receipt.transaction.sponsor_address() {
+        principals.insert(PrincipalData::from(sponsor_address));
+    }
+    if let TransactionPayload::TokenTransfer(ref recipient, _, _) = receipt.transaction.payload {
+        principals.insert(recipient.clone());
+    }
+    if let Some(contract_id) = charged_contract(&receipt.transaction) {
+        principals.insert(PrincipalData::from(contract_id));
+    }
+    for event in receipt.events.iter() {
+        let (sender, recipient) = match event {
+            StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(ref data)) => (Some(&data.sender), Some(&data.recipient)),
+            StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(ref data)) => (None, Some(&data.recipient)),
+            StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(ref data)) => (Some(&data.sender), None),
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(ref data)) => (Some(&data.sender), Some(&data.recipient)),
+            StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(ref data)) => (None, Some(&data.recipient)),
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(ref data)) => (Some(&data.sender), Some(&data.recipient)),
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(ref data)) => (None, Some(&data.recipient)),
+            StacksTransactionEvent::SmartContractEvent(_) => (None, None)
+        };
+        principals.extend(sender.into_iter().chain(recipient.into_iter()).cloned());
+    }
+    let mut principals : Vec<PrincipalData> = principals.into_iter().collect();
+    principals.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    principals
+}
+
+impl StacksChainState {
+    /// Index the transactions processed in a block and the microblock stream it confirms by the
+    /// principals they involve, in the order they're given, and mark the block as indexed
+    pub fn insert_address_transactions<'a>(tx: &mut StacksDBTx<'a>, tip_info: &StacksHeaderInfo, receipts: &Vec<StacksTransactionReceipt>) -> Result<(), Error> {
+        assert!(tip_info.block_height < (i64::max_value() as u64));
+
+        let index_block_hash = tip_info.index_block_hash();
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let txid = receipt.transaction.txid();
+            for principal in address_transaction_principals(receipt).iter() {
+                let args: &[&dyn ToSql] = &[&principal.to_string(), &txid, &index_block_hash, &(tip_info.block_height as i64), &(tx_index as i64)];
+                tx.execute("INSERT OR REPLACE INTO address_transactions (principal, txid, index_block_hash, block_height, tx_index) VALUES (?1, ?2, ?3, ?4, ?5)", args)
+                    .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+            }
+        }
+
+        let args: &[&dyn ToSql] = &[&index_block_hash, &(tip_info.block_height as i64)];
+        tx.execute("INSERT OR REPLACE INTO address_index_blocks (index_block_hash, block_height) VALUES (?1, ?2)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Get the height of a block whose transactions are in the address index, if they are
+    pub fn get_address_index_height(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<Option<u64>, Error> {
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_row::<u64, _>(conn, "SELECT block_height FROM address_index_blocks WHERE index_block_hash = ?1", args)
+            .map_err(Error::DBError)
+    }
+
+    /// Get up to `limit` of the transactions a principal took part in, in the fork ending at the
+    /// given tip, newest first.  None if the tip's transactions aren't indexed.  See
+    /// address_transaction_principals() for what involves a principal.
+    pub fn get_address_transactions<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, principal: &PrincipalData, limit: u64) -> Result<Option<Vec<AddressTransactionEntry>>, Error> {
+        let tip_height = match StacksChainState::get_address_index_height(tx, tip)? {
+            Some(height) => height,
+            None => return Ok(None)
+        };
+
+        let sql = "SELECT * FROM address_transactions WHERE principal = ?1 AND block_height <= ?2 ORDER BY block_height DESC, tx_index DESC".to_string();
+        let args: &[&dyn ToSql] = &[&principal.to_string(), &u64_to_sql(tip_height).map_err(Error::DBError)?];
+        let candidates = query_rows::<AddressTransactionEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        let mut blocks_in_fork : HashMap<StacksBlockId, bool> = HashMap::new();
+        let mut entries = vec![];
+        for entry in candidates.into_iter() {
+            if entries.len() as u64 >= limit {
+                break;
+            }
+            // only the transactions mined in this fork
+            let in_fork = match blocks_in_fork.get(&entry.index_block_hash) {
+                Some(in_fork) => *in_fork,
+                None => {
+                    let in_fork = StacksChainState::get_index_tip_ancestor(tx, tip, entry.block_height)?
+                        .map(|ancestor| ancestor.index_block_hash() == entry.index_block_hash)
+                        .unwrap_or(false);
+                    blocks_in_fork.insert(entry.index_block_hash.clone(), in_fork);
+                    in_fork
+                }
+            };
+            if in_fork {
+                entries.push(entry);
+            }
+        }
+        Ok(Some(entries))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use chainstate::stacks::db::test::{instantiate_chainstate, make_tx, make_tx_with_privk};
+    use util::strings::ContractName;
+    use vm::costs::ExecutionCost;
+    use vm::types::{QualifiedContractIdentifier, StandardPrincipalData, Value};
+
+    #[test]
+    fn test_address_transactions() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_address_transactions");
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+
+        let other_privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+
+        let recipient = PrincipalData::from(StacksAddress { version: 26, bytes: Hash160([2u8; 20]) });
+        let contract_address = StacksAddress { version: 26, bytes: Hash160([1u8; 20]) };
+        let contract_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(contract_address.clone()), ContractName::try_from("exchange").unwrap());
+
+        // a transfer, and a call that pays the recipient
+        let transfer = make_tx(TransactionPayload::TokenTransfer(recipient.clone(), 100, TokenTransferMemo([0u8; 34])));
+        let payout = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(STXTransferEventData {
+            sender: PrincipalData::from(contract_id.clone()),
+            recipient: recipient.clone(),
+            amount: 10
+        }));
+        let sender = StacksAccount { principal: PrincipalData::from(transfer.origin_address()), nonce: 0, stx_balance: 1000 };
+        let call = make_tx_with_privk(&other_privk, TransactionPayload::new_contract_call(contract_address.clone(), "exchange", "swap", vec![]).unwrap());
+        let receipts = vec![
+            StacksTransactionReceipt::from_stx_transfer(transfer.clone(), &sender, recipient.clone(), 100, ExecutionCost::zero()),
+            StacksTransactionReceipt::from_contract_call(call.clone(), vec![payout], Value::okay_true(), 0, ExecutionCost::zero()),
+        ];
+
+        let mut expected = vec![sender.principal.clone(), recipient.clone()];
+        expected.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+        assert_eq!(address_transaction_principals(&receipts[0]), expected);
+        assert!(address_transaction_principals(&receipts[1]).contains(&PrincipalData::from(contract_id.clone())));
+
+        // nothing's indexed until the block is
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            assert_eq!(StacksChainState::get_address_transactions(&mut tx, &genesis.index_block_hash(), &recipient, 10).unwrap(), None);
+        }
+
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::insert_address_transactions(&mut tx, &genesis, &receipts).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let mut tx = chainstate.headers_tx_begin().unwrap();
+        let entries = StacksChainState::get_address_transactions(&mut tx, &genesis.index_block_hash(), &recipient, 10).unwrap().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.txid.clone()).collect::<Vec<_>>(), vec![call.txid(), transfer.txid()]);
+        assert_eq!(entries[0].tx_index, 1);
+
+        let entries = StacksChainState::get_address_transactions(&mut tx, &genesis.index_block_hash(), &recipient, 1).unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+
+        let entries = StacksChainState::get_address_transactions(&mut tx, &genesis.index_block_hash(), &PrincipalData::from(contract_id), 10).unwrap().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.txid.clone()).collect::<Vec<_>>(), vec![call.txid()]);
+    }
+}
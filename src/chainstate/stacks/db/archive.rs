@@ -0,0 +1,197 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::cmp;
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+
+use util::db::Error as db_error;
+use util::db::{
+    query_count,
+    query_int,
+    query_rows,
+    u64_to_sql,
+};
+
+/// Whether this chainstate is an archive, and how far checking its history has got.  Created on
+/// open if missing; it has a row once archive mode is turned on, and that row is never removed.
+pub const ARCHIVE_MODE_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS archive_mode(
+        -- every processed block below this height has been checked to have its state
+        next_block_height INTEGER NOT NULL
+    );
+    "#,
+];
+
+/// Lets an archive find the blocks at a height without walking a fork
+const HEADERS_HEIGHT_INDEX_SQL : &'static str =
+    "CREATE INDEX IF NOT EXISTS block_headers_height_index ON block_headers(block_height);";
+
+/// How many block heights to check in one call to verify_archive(), so that turning archive
+/// mode on for a long chain doesn't stall block processing while the check catches up.
+const MAX_VERIFY_BATCH : u64 = 100;
+
+impl StacksChainState {
+    /// Has archive mode been turned on for this chainstate?
+    pub fn is_archive(blocks_conn: &Connection) -> Result<bool, Error> {
+        let qry = "SELECT COUNT(*) FROM archive_mode".to_string();
+        let cnt = query_count(blocks_conn, &qry, NO_PARAMS).map_err(Error::DBError)?;
+        Ok(cnt > 0)
+    }
+
+    /// The lowest block height whose blocks haven't been checked to have their state yet
+    pub fn get_archive_verified_height(blocks_conn: &Connection) -> Result<u64, Error> {
+        let sql = "SELECT IFNULL(MAX(next_block_height), 0) FROM archive_mode".to_string();
+        let height = query_int(blocks_conn, &sql, NO_PARAMS).map_err(Error::DBError)?;
+        Ok(height as u64)
+    }
+
+    fn set_archive_verified_height<'a>(tx: &mut BlocksDBTx<'a>, block_height: u64) -> Result<(), Error> {
+        let block_height = u64_to_sql(block_height).map_err(Error::DBError)?;
+        tx.execute("DELETE FROM archive_mode", NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT INTO archive_mode (next_block_height) VALUES (?1)", &[&block_height as &dyn ToSql])
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Turn on archive mode: from now on, this chainstate keeps every block it processes, and
+    /// block pruning is refused.  A chainstate that has already pruned blocks, or was ever
+    /// light-synced, can't be an archive.  Doing this again does nothing.
+    pub fn enable_archive_mode(&mut self) -> Result<(), Error> {
+        if StacksChainState::is_archive(&self.blocks_db)? {
+            return Ok(());
+        }
+
+        if StacksChainState::is_light_sync(&self.blocks_db)? {
+            error!("Cannot turn on archive mode: blocks have been processed without their state");
+            return Err(Error::InvalidChainstateDB);
+        }
+
+        let qry = "SELECT COUNT(*) FROM pruned_blocks".to_string();
+        let num_pruned = query_count(&self.blocks_db, &qry, NO_PARAMS).map_err(Error::DBError)?;
+        if num_pruned > 0 || StacksChainState::get_pruned_burn_height(&self.blocks_db)? > 0 {
+            error!("Cannot turn on archive mode: {} blocks have been pruned", num_pruned);
+            return Err(Error::InvalidChainstateDB);
+        }
+
+        self.headers_db.execute(HEADERS_HEIGHT_INDEX_SQL, NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        let mut tx = self.blocks_tx_begin()?;
+        StacksChainState::set_archive_verified_height(&mut tx, 0)?;
+        tx.commit().map_err(Error::DBError)?;
+
+        info!("Archive mode is on");
+        Ok(())
+    }
+
+    /// Check that the Clarity state has the state root of every block processed before archive
+    /// mode was turned on, so that queries pinned to any of them can be answered.  Picks up
+    /// where the last call left off, and checks at most MAX_VERIFY_BATCH heights per call.
+    /// Heights at or above the highest processed block are left for later, since more blocks
+    /// can still arrive there.  Returns the number of blocks checked, or an error if a block's
+    /// state is missing.
+    pub fn verify_archive(&mut self) -> Result<u64, Error> {
+        if !StacksChainState::is_archive(&self.blocks_db)? {
+            return Ok(0);
+        }
+
+        let start_height = StacksChainState::get_archive_verified_height(&self.blocks_db)?;
+        let max_height = query_int(&self.headers_db, "SELECT IFNULL(MAX(block_height), 0) FROM block_headers", NO_PARAMS)
+            .map_err(Error::DBError)? as u64;
+        let end_height = cmp::min(max_height, start_height + MAX_VERIFY_BATCH);
+        if end_height <= start_height {
+            return Ok(0);
+        }
+
+        let sql = "SELECT * FROM block_headers WHERE block_height >= ?1 AND block_height < ?2 ORDER BY block_height";
+        let args : &[&dyn ToSql] = &[&u64_to_sql(start_height).map_err(Error::DBError)?, &u64_to_sql(end_height).map_err(Error::DBError)?];
+        let headers : Vec<StacksHeaderInfo> = query_rows(&self.headers_db, sql, args).map_err(Error::DBError)?;
+
+        for header_info in headers.iter() {
+            let index_block_hash = header_info.index_block_hash();
+            let state_root = self.clarity_state.get_marf().get_root_hash_at(&index_block_hash)
+                .map_err(|e| {
+                    error!("Archive is missing the state of block {} at height {}: {:?}", &index_block_hash, header_info.block_height, &e);
+                    Error::MARFError(e)
+                })?;
+            if state_root != header_info.anchored_header.state_index_root {
+                error!("Archive has state root {} for block {} at height {}, but its header has {}",
+                       &state_root, &index_block_hash, header_info.block_height, &header_info.anchored_header.state_index_root);
+                return Err(Error::InvalidChainstateDB);
+            }
+        }
+
+        let mut tx = self.blocks_tx_begin()?;
+        StacksChainState::set_archive_verified_height(&mut tx, end_height)?;
+        tx.commit().map_err(Error::DBError)?;
+
+        debug!("Checked the state of {} blocks at heights {}-{}", headers.len(), start_height, end_height - 1);
+        Ok(headers.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use chainstate::burn::db::burndb::BurnDB;
+    use burnchains::BurnchainHeaderHash;
+
+    #[test]
+    fn test_archive_mode() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_archive_mode");
+        assert!(!StacksChainState::is_archive(&chainstate.blocks_db).unwrap());
+        assert_eq!(chainstate.verify_archive().unwrap(), 0);
+
+        chainstate.enable_archive_mode().unwrap();
+        chainstate.enable_archive_mode().unwrap();
+        assert!(StacksChainState::is_archive(&chainstate.blocks_db).unwrap());
+        assert_eq!(StacksChainState::get_archive_verified_height(&chainstate.blocks_db).unwrap(), 0);
+
+        // the boot block is the only block, and is at the highest height, so it's left for later
+        assert_eq!(chainstate.verify_archive().unwrap(), 0);
+
+        // archives can't be pruned
+        let burndb = BurnDB::connect_test(0, &BurnchainHeaderHash([0u8; 32])).unwrap();
+        assert!(chainstate.prune_blocks(&burndb, 1000).is_err());
+        assert!(chainstate.prune_fork_blocks(&burndb, 1000).is_err());
+    }
+
+    #[test]
+    fn test_archive_mode_after_pruning() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_archive_mode_after_pruning");
+        {
+            let mut tx = chainstate.blocks_tx_begin().unwrap();
+            tx.execute("INSERT INTO pruned_blocks (index_block_hash, burn_block_height) VALUES (?1, ?2)",
+                       &[&StacksBlockId([1u8; 32]) as &dyn ToSql, &5i64]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(chainstate.enable_archive_mode().is_err());
+        assert!(!StacksChainState::is_archive(&chainstate.blocks_db).unwrap());
+    }
+}
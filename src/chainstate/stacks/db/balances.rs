@@ -0,0 +1,494 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use rusqlite::Row;
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::events::*;
+
+use util::db::Error as db_error;
+use util::db::{
+    FromRow,
+    FromColumn,
+    query_row,
+    query_rows,
+    query_row_columns,
+    u64_to_sql,
+};
+
+use vm::clarity::ClarityConnection;
+use vm::types::PrincipalData;
+
+/// The STX balance and nonce of every account whose balance or nonce a block changed, as of
+/// that block, so balances can be looked up (and ranked, and traced back) without walking the
+/// Clarity MARF.  This is NOT consensus data:  it's derived from blocks as they're processed,
+/// and nothing the node validates reads it.  Created on open if missing.  A block's balances are
+/// only indexed if its parent's were, so the index is complete back to the boot block wherever
+/// it's known; chainstates from before this index existed have none.
+///
+/// `latest_balances` holds every indexed account's balance as of one tip, kept up to date as
+/// blocks that extend it are processed and moved over to a higher block on another fork.  Its
+/// balances are zero-padded to 39 digits, so they sort as numbers.
+pub const ACCOUNT_BALANCES_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS account_balances(
+        principal TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,     -- NOTE: a block on each fork can change the same account
+        block_height INTEGER NOT NULL,
+        stx_balance TEXT NOT NULL,          -- encodes u128
+        nonce INTEGER NOT NULL,
+
+        PRIMARY KEY(principal,index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS account_balances_by_height ON account_balances(principal,block_height);
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS account_balances_by_block ON account_balances(index_block_hash);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS account_balance_blocks(
+        index_block_hash TEXT NOT NULL,     -- every balance this block changed is indexed
+        block_height INTEGER NOT NULL,
+
+        PRIMARY KEY(index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS latest_balances(
+        principal TEXT NOT NULL,
+        stx_balance TEXT NOT NULL,          -- encodes u128, zero-padded
+        nonce INTEGER NOT NULL,
+
+        PRIMARY KEY(principal)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS latest_balances_by_balance ON latest_balances(stx_balance);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS latest_balances_tip(
+        index_block_hash TEXT NOT NULL,     -- the balances in latest_balances are as of this block
+        block_height INTEGER NOT NULL
+    );
+    "#,
+];
+
+/// An account's balance and nonce as of a block that changed them
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountBalanceEntry {
+    pub principal: PrincipalData,
+    pub stx_balance: u128,
+    pub nonce: u64,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+}
+
+impl FromRow<StacksAccount> for StacksAccount {
+    fn from_row<'a>(row: &'a Row) -> Result<StacksAccount, db_error> {
+        let principal_str : String = row.get("principal");
+        let balance_str : String = row.get("stx_balance");
+        let nonce = u64::from_column(row, "nonce")?;
+
+        let principal = PrincipalData::parse(&principal_str).map_err(|_e| db_error::ParseError)?;
+        let stx_balance = balance_str.parse::<u128>().map_err(|_e| db_error::ParseError)?;
+
+        Ok(StacksAccount {
+            principal,
+            stx_balance,
+            nonce
+        })
+    }
+}
+
+impl FromRow<AccountBalanceEntry> for AccountBalanceEntry {
+    fn from_row<'a>(row: &'a Row) -> Result<AccountBalanceEntry, db_error> {
+        let account = StacksAccount::from_row(row)?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+
+        Ok(AccountBalanceEntry {
+            principal: account.principal,
+            stx_balance: account.stx_balance,
+            nonce: account.nonce,
+            index_block_hash,
+            block_height
+        })
+    }
+}
+
+impl StacksChainState {
+    /// The accounts whose balance or nonce the transactions with these receipts changed:  the
+    /// senders and sponsors, who pay fees and whose nonces go up, and whoever the STX events
+    /// moved STX to or from
+    pub fn balance_changed_principals<'r, I: IntoIterator<Item = &'r StacksTransactionReceipt>>(receipts: I) -> Vec<PrincipalData> {
+        let mut principals = HashSet::new();
+        for receipt in receipts.into_iter() {
+            principals.insert(PrincipalData::from(receipt.transaction.origin_address()));
+            if let Some(sponsor_address) = receipt.transaction.sponsor_address() {
+                principals.insert(PrincipalData::from(sponsor_address));
+            }
+            for event in receipt.events.iter() {
+                match event {
+                    StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(ref data)) => {
+                        principals.insert(data.sender.clone());
+                        principals.insert(data.recipient.clone());
+                    },
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(ref data)) => {
+                        principals.insert(data.recipient.clone());
+                    },
+                    StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(ref data)) => {
+                        principals.insert(data.sender.clone());
+                    },
+                    _ => {}
+                }
+            }
+        }
+        principals.into_iter().collect()
+    }
+
+    /// Read the balances and nonces of these accounts from a block's Clarity state
+    pub fn get_accounts<T: ClarityConnection>(clarity_tx: &mut T, principals: &Vec<PrincipalData>) -> Vec<StacksAccount> {
+        principals.iter()
+            .map(|principal| StacksChainState::get_account(clarity_tx, principal))
+            .collect()
+    }
+
+    /// Get the height of a block whose balances are indexed, if they are
+    pub fn get_account_balances_height(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<Option<u64>, Error> {
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_row::<u64, _>(conn, "SELECT block_height FROM account_balance_blocks WHERE index_block_hash = ?1", args)
+            .map_err(Error::DBError)
+    }
+
+    /// Get the block the latest balances are as of, and its height
+    pub fn get_latest_balances_tip(conn: &Connection) -> Result<Option<(StacksBlockId, u64)>, Error> {
+        let mut tips = query_row_columns::<StacksBlockId, _>(conn, &"SELECT index_block_hash FROM latest_balances_tip".to_string(), NO_PARAMS, "index_block_hash")
+            .map_err(Error::DBError)?;
+        let height = query_row::<u64, _>(conn, "SELECT block_height FROM latest_balances_tip", NO_PARAMS)
+            .map_err(Error::DBError)?;
+        Ok(tips.pop().and_then(|tip| height.map(|height| (tip, height))))
+    }
+
+    /// Index the balances and nonces of the accounts a block changed, as of that block.  The
+    /// boot block has no parent; any other block's balances are only indexed if its parent's
+    /// were.  Returns whether they were.
+    pub fn insert_account_balances<'a>(tx: &mut StacksDBTx<'a>, parent_index_block_hash: Option<&StacksBlockId>, index_block_hash: &StacksBlockId,
+                                       block_height: u64, accounts: &Vec<StacksAccount>) -> Result<bool, Error> {
+        if let Some(parent_index_block_hash) = parent_index_block_hash {
+            if StacksChainState::get_account_balances_height(tx, parent_index_block_hash)?.is_none() {
+                return Ok(false);
+            }
+        }
+
+        let height_sql = u64_to_sql(block_height).map_err(Error::DBError)?;
+        for account in accounts.iter() {
+            let args: &[&dyn ToSql] = &[&account.principal.to_string(), index_block_hash, &height_sql, &account.stx_balance.to_string(), &u64_to_sql(account.nonce).map_err(Error::DBError)?];
+            tx.execute("INSERT OR REPLACE INTO account_balances (principal, index_block_hash, block_height, stx_balance, nonce) VALUES (?1, ?2, ?3, ?4, ?5)", args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+
+        let args: &[&dyn ToSql] = &[index_block_hash, &height_sql];
+        tx.execute("INSERT OR REPLACE INTO account_balance_blocks (index_block_hash, block_height) VALUES (?1, ?2)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        StacksChainState::update_latest_balances(tx, parent_index_block_hash, index_block_hash, block_height, accounts)?;
+        Ok(true)
+    }
+
+    fn put_latest_balance<'a>(tx: &mut StacksDBTx<'a>, account: &StacksAccount) -> Result<(), Error> {
+        let args: &[&dyn ToSql] = &[&account.principal.to_string(), &format!("{:039}", account.stx_balance), &u64_to_sql(account.nonce).map_err(Error::DBError)?];
+        tx.execute("INSERT OR REPLACE INTO latest_balances (principal, stx_balance, nonce) VALUES (?1, ?2, ?3)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Get the highest block that's an ancestor of both tips
+    fn get_fork_point_height<'a>(tx: &mut StacksDBTx<'a>, tip_a: &StacksBlockId, height_a: u64, tip_b: &StacksBlockId, height_b: u64) -> Result<u64, Error> {
+        let mut height = cmp::min(height_a, height_b);
+        while height > 0 {
+            let ancestor_a = StacksChainState::get_index_tip_ancestor(tx, tip_a, height)?.map(|info| info.index_block_hash());
+            let ancestor_b = StacksChainState::get_index_tip_ancestor(tx, tip_b, height)?.map(|info| info.index_block_hash());
+            if ancestor_a.is_some() && ancestor_a == ancestor_b {
+                break;
+            }
+            height -= 1;
+        }
+        Ok(height)
+    }
+
+    /// Bring the latest balances up to a newly-indexed block.  If it extends their tip, the
+    /// accounts it changed are updated; if it's on another fork and higher than the tip, the
+    /// accounts either fork changed since they split are looked up again as of the block.
+    /// Blocks on forks that are no higher are left out.
+    fn update_latest_balances<'a>(tx: &mut StacksDBTx<'a>, parent_index_block_hash: Option<&StacksBlockId>, new_tip: &StacksBlockId, new_tip_height: u64,
+                                  accounts: &Vec<StacksAccount>) -> Result<(), Error> {
+        match (StacksChainState::get_latest_balances_tip(tx)?, parent_index_block_hash) {
+            (None, None) => {
+                // the boot block
+                for account in accounts.iter() {
+                    StacksChainState::put_latest_balance(tx, account)?;
+                }
+            },
+            (Some((ref tip, _)), Some(parent)) if tip == parent => {
+                for account in accounts.iter() {
+                    StacksChainState::put_latest_balance(tx, account)?;
+                }
+            },
+            (Some((_, tip_height)), _) if new_tip_height <= tip_height => {
+                return Ok(());
+            },
+            (Some((tip, tip_height)), _) => {
+                let fork_height = StacksChainState::get_fork_point_height(tx, &tip, tip_height, new_tip, new_tip_height)?;
+                let mut branch_blocks = StacksChainState::get_index_tip_ancestors(tx, &tip, fork_height + 1, tip_height)?;
+                branch_blocks.append(&mut StacksChainState::get_index_tip_ancestors(tx, new_tip, fork_height + 1, new_tip_height)?);
+
+                let mut principals = HashSet::new();
+                for header_info in branch_blocks.iter() {
+                    let args: &[&dyn ToSql] = &[&header_info.index_block_hash()];
+                    let entries = query_rows::<AccountBalanceEntry, _>(tx, "SELECT * FROM account_balances WHERE index_block_hash = ?1", args)
+                        .map_err(Error::DBError)?;
+                    principals.extend(entries.into_iter().map(|entry| entry.principal));
+                }
+
+                debug!("Moving the latest balances from {} to {} (fork at height {}): {} accounts", &tip, new_tip, fork_height, principals.len());
+                for principal in principals.into_iter() {
+                    match StacksChainState::get_account_balance_entry(tx, new_tip, new_tip_height, &principal)? {
+                        Some(entry) => {
+                            let account = StacksAccount { principal: entry.principal, stx_balance: entry.stx_balance, nonce: entry.nonce };
+                            StacksChainState::put_latest_balance(tx, &account)?;
+                        },
+                        None => {
+                            let args: &[&dyn ToSql] = &[&principal.to_string()];
+                            tx.execute("DELETE FROM latest_balances WHERE principal = ?1", args)
+                                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+                        }
+                    }
+                }
+            },
+            (None, Some(_)) => {
+                // only indexed once its parent is, so there's always a tip by then
+                return Ok(());
+            }
+        }
+
+        let args: &[&dyn ToSql] = &[new_tip, &u64_to_sql(new_tip_height).map_err(Error::DBError)?];
+        tx.execute("DELETE FROM latest_balances_tip", NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT INTO latest_balances_tip (index_block_hash, block_height) VALUES (?1, ?2)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Is this indexed block in the fork ending at the given tip?
+    fn in_fork<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, index_block_hash: &StacksBlockId, block_height: u64) -> Result<bool, Error> {
+        Ok(StacksChainState::get_index_tip_ancestor(tx, tip, block_height)?
+           .map(|ancestor| ancestor.index_block_hash() == *index_block_hash)
+           .unwrap_or(false))
+    }
+
+    /// Get the newest indexed balance of an account in the fork ending at the given tip
+    fn get_account_balance_entry<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, tip_height: u64, principal: &PrincipalData) -> Result<Option<AccountBalanceEntry>, Error> {
+        let sql = "SELECT * FROM account_balances WHERE principal = ?1 AND block_height <= ?2 ORDER BY block_height DESC".to_string();
+        let args: &[&dyn ToSql] = &[&principal.to_string(), &u64_to_sql(tip_height).map_err(Error::DBError)?];
+        let candidates = query_rows::<AccountBalanceEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+        for entry in candidates.into_iter() {
+            if StacksChainState::in_fork(tx, tip, &entry.index_block_hash, entry.block_height)? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get an account's balance and nonce as of the given tip from the index, or None if the
+    /// tip's balances aren't indexed.  Accounts the index has never seen have nothing.  At the
+    /// latest balances' tip this is a single lookup.
+    pub fn get_indexed_account<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, principal: &PrincipalData) -> Result<Option<StacksAccount>, Error> {
+        let tip_height = match StacksChainState::get_account_balances_height(tx, tip)? {
+            Some(height) => height,
+            None => return Ok(None)
+        };
+        let empty = StacksAccount { principal: principal.clone(), stx_balance: 0, nonce: 0 };
+
+        match StacksChainState::get_latest_balances_tip(tx)? {
+            Some((ref latest_tip, _)) if latest_tip == tip => {
+                let args: &[&dyn ToSql] = &[&principal.to_string()];
+                let account = query_row::<StacksAccount, _>(tx, "SELECT * FROM latest_balances WHERE principal = ?1", args)
+                    .map_err(Error::DBError)?;
+                Ok(Some(account.unwrap_or(empty)))
+            },
+            _ => {
+                let account = StacksChainState::get_account_balance_entry(tx, tip, tip_height, principal)?
+                    .map(|entry| StacksAccount { principal: entry.principal, stx_balance: entry.stx_balance, nonce: entry.nonce });
+                Ok(Some(account.unwrap_or(empty)))
+            }
+        }
+    }
+
+    /// Get the balances an account had in the fork ending at the given tip, newest first:  one
+    /// per block that changed its balance or nonce.  None if the tip's balances aren't indexed.
+    pub fn get_account_balance_history<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, principal: &PrincipalData, limit: u64) -> Result<Option<Vec<AccountBalanceEntry>>, Error> {
+        let tip_height = match StacksChainState::get_account_balances_height(tx, tip)? {
+            Some(height) => height,
+            None => return Ok(None)
+        };
+
+        let sql = "SELECT * FROM account_balances WHERE principal = ?1 AND block_height <= ?2 ORDER BY block_height DESC".to_string();
+        let args: &[&dyn ToSql] = &[&principal.to_string(), &u64_to_sql(tip_height).map_err(Error::DBError)?];
+        let candidates = query_rows::<AccountBalanceEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        let mut entries = vec![];
+        for entry in candidates.into_iter() {
+            if entries.len() as u64 >= limit {
+                break;
+            }
+            if StacksChainState::in_fork(tx, tip, &entry.index_block_hash, entry.block_height)? {
+                entries.push(entry);
+            }
+        }
+        Ok(Some(entries))
+    }
+
+    /// Get the `limit` accounts with the most STX as of the given tip, richest first, or None if
+    /// the tip's balances aren't indexed.  At the latest balances' tip this is one indexed query;
+    /// at any other tip, every indexed balance up to it is read.
+    pub fn get_top_balances<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, limit: u64) -> Result<Option<Vec<StacksAccount>>, Error> {
+        let tip_height = match StacksChainState::get_account_balances_height(tx, tip)? {
+            Some(height) => height,
+            None => return Ok(None)
+        };
+
+        match StacksChainState::get_latest_balances_tip(tx)? {
+            Some((ref latest_tip, _)) if latest_tip == tip => {
+                let sql = "SELECT * FROM latest_balances ORDER BY stx_balance DESC, principal ASC LIMIT ?1".to_string();
+                let args: &[&dyn ToSql] = &[&u64_to_sql(limit).map_err(Error::DBError)?];
+                let accounts = query_rows::<StacksAccount, _>(tx, &sql, args).map_err(Error::DBError)?;
+                return Ok(Some(accounts));
+            },
+            _ => {}
+        }
+
+        let sql = "SELECT * FROM account_balances WHERE block_height <= ?1 ORDER BY block_height DESC".to_string();
+        let args: &[&dyn ToSql] = &[&u64_to_sql(tip_height).map_err(Error::DBError)?];
+        let candidates = query_rows::<AccountBalanceEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        let mut blocks_in_fork : HashMap<StacksBlockId, bool> = HashMap::new();
+        let mut accounts : HashMap<PrincipalData, StacksAccount> = HashMap::new();
+        for entry in candidates.into_iter() {
+            if accounts.contains_key(&entry.principal) {
+                continue;
+            }
+            let in_fork = match blocks_in_fork.get(&entry.index_block_hash) {
+                Some(in_fork) => *in_fork,
+                None => {
+                    let in_fork = StacksChainState::in_fork(tx, tip, &entry.index_block_hash, entry.block_height)?;
+                    blocks_in_fork.insert(entry.index_block_hash.clone(), in_fork);
+                    in_fork
+                }
+            };
+            if in_fork {
+                let account = StacksAccount { principal: entry.principal.clone(), stx_balance: entry.stx_balance, nonce: entry.nonce };
+                accounts.insert(entry.principal, account);
+            }
+        }
+
+        let mut accounts : Vec<StacksAccount> = accounts.into_iter().map(|(_, account)| account).collect();
+        accounts.sort_by(|a, b| b.stx_balance.cmp(&a.stx_balance).then_with(|| a.principal.to_string().cmp(&b.principal.to_string())));
+        accounts.truncate(limit as usize);
+        Ok(Some(accounts))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate_with_balances;
+
+    #[test]
+    fn test_account_balances() {
+        let rich = StacksAddress { version: 26, bytes: Hash160([1u8; 20]) };
+        let poor = StacksAddress { version: 26, bytes: Hash160([2u8; 20]) };
+        let unknown = PrincipalData::from(StacksAddress { version: 26, bytes: Hash160([3u8; 20]) });
+        let mut chainstate = instantiate_chainstate_with_balances(false, 0x80000000, "test_account_balances", vec![(rich.clone(), 1000), (poor.clone(), 10)]);
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap().index_block_hash();
+        let rich = PrincipalData::from(rich);
+        let poor = PrincipalData::from(poor);
+
+        // the boot block's initial balances are indexed
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            assert_eq!(StacksChainState::get_indexed_account(&mut tx, &genesis, &rich).unwrap().unwrap().stx_balance, 1000);
+            assert_eq!(StacksChainState::get_indexed_account(&mut tx, &genesis, &unknown).unwrap().unwrap().stx_balance, 0);
+
+            let top = StacksChainState::get_top_balances(&mut tx, &genesis, 2).unwrap().unwrap();
+            assert_eq!(top.iter().map(|account| account.principal.clone()).collect::<Vec<_>>(), vec![rich.clone(), poor.clone()]);
+        }
+
+        // a child block moves the latest balances along
+        let child = StacksBlockId([1u8; 32]);
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            let accounts = vec![
+                StacksAccount { principal: rich.clone(), stx_balance: 5, nonce: 1 },
+                StacksAccount { principal: poor.clone(), stx_balance: 1005, nonce: 0 },
+            ];
+            assert!(StacksChainState::insert_account_balances(&mut tx, Some(&genesis), &child, 1, &accounts).unwrap());
+            tx.commit().unwrap();
+        }
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            assert_eq!(StacksChainState::get_latest_balances_tip(&tx).unwrap(), Some((child.clone(), 1)));
+            let account = StacksChainState::get_indexed_account(&mut tx, &child, &rich).unwrap().unwrap();
+            assert_eq!((account.stx_balance, account.nonce), (5, 1));
+
+            let top = StacksChainState::get_top_balances(&mut tx, &child, 1).unwrap().unwrap();
+            assert_eq!(top.len(), 1);
+            assert_eq!(top[0].principal, poor);
+            assert_eq!(top[0].stx_balance, 1005);
+
+            // the boot block's balances are still there, back in the history
+            assert_eq!(StacksChainState::get_indexed_account(&mut tx, &genesis, &rich).unwrap().unwrap().stx_balance, 1000);
+            let history = StacksChainState::get_account_balance_history(&mut tx, &genesis, &rich, 10).unwrap().unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].block_height, 0);
+            assert_eq!(history[0].stx_balance, 1000);
+        }
+
+        // a block whose parent's balances aren't indexed isn't indexed either
+        let orphan = StacksBlockId([2u8; 32]);
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            assert!(!StacksChainState::insert_account_balances(&mut tx, Some(&StacksBlockId([3u8; 32])), &orphan, 2, &vec![]).unwrap());
+            assert_eq!(StacksChainState::get_indexed_account(&mut tx, &orphan, &rich).unwrap(), None);
+            assert_eq!(StacksChainState::get_top_balances(&mut tx, &orphan, 10).unwrap(), None);
+            assert_eq!(StacksChainState::get_account_balance_history(&mut tx, &orphan, &rich, 10).unwrap(), None);
+        }
+    }
+}
@@ -25,6 +25,7 @@ use std::fs;
 use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
+use std::sync::Arc;
 
 use rusqlite::Connection;
 use rusqlite::DatabaseName;
@@ -35,9 +36,15 @@ use chainstate::burn::operations::*;
 
 use chainstate::stacks::Error;
 use chainstate::stacks::db::accounts::MinerReward;
+use chainstate::stacks::db::archive::ARCHIVE_MODE_SQL;
+use chainstate::stacks::db::compression::{is_compressed_chunk, is_block_compression_available, stream_decompressed_chunk};
+use chainstate::stacks::db::contract_costs::contract_costs;
+use chainstate::stacks::db::light::LIGHT_SYNC_SQL;
+use chainstate::stacks::db::prune::BLOCK_PRUNING_SQL;
 use chainstate::stacks::*;
 use chainstate::stacks::db::*;
 use chainstate::stacks::db::transactions::TransactionNonceMismatch;
+use chainstate::stacks::db::verify::SignatureVerifier;
 
 use chainstate::burn::BlockSnapshot;
 
@@ -53,13 +60,15 @@ use util::db::{
     query_row_columns,
     query_count,
     query_int,
-    tx_busy_handler,
+    sqlite_open,
+    SqlitePragmas,
 };
 
 use util::strings::StacksString;
 use util::get_epoch_time_secs;
 use util::hash::to_hex;
 use util::db::u64_to_sql;
+use util::migrations::{SchemaMigration, migrate_schema};
 
 use util::retry::BoundReader;
 
@@ -69,6 +78,7 @@ use net::MAX_MESSAGE_LEN;
 use net::BLOCKS_INV_DATA_MAX_BITLEN;
 use net::BlocksInvData;
 use net::Error as net_error;
+use net::{BlockExportFormat, ExportedBlockEntry};
 
 use vm::types::{
     Value,
@@ -95,7 +105,8 @@ use vm::clarity::{
 
 pub use vm::analysis::errors::{CheckErrors, CheckError};
 
-use vm::database::ClarityDatabase;
+use vm::database::{ClarityDatabase, ClaritySerializable};
+use vm::statetrace::{self, StateTrace};
 
 use vm::contracts::Contract;
 
@@ -146,6 +157,48 @@ pub struct StagingUserBurnSupport {
     pub vtxindex: u32,
 }
 
+/// How many Stacks blocks below the canonical Stacks tip to look for accepted blocks that the burn
+/// DB never recorded.  Blocks get recorded in the burn DB in batches, so a node that stops
+/// mid-batch only loses the most recent ones.
+pub const CHAINSTATE_CHECK_DEPTH : u64 = 32;
+
+/// A recoverable inconsistency between the staging blocks, the headers, the chunk store, and the
+/// burn DB.  These are left behind if the node stops partway through processing a block, since
+/// each of these is committed separately.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainstateInconsistency {
+    /// The block's header was committed, but it is still queued for processing.  Repaired by
+    /// marking it processed.
+    UnmarkedProcessedBlock(StagingBlock),
+    /// The block was accepted, but the burn DB does not know it.  Repaired by recording it in the
+    /// burn DB.
+    UnrecordedAcceptedBlock(StagingBlock),
+    /// The block is queued for processing, but its data is missing or unreadable.  Repaired by
+    /// dropping it, so it gets downloaded again.
+    MissingBlockData(StagingBlock),
+}
+
+impl ChainstateInconsistency {
+    pub fn staging_block(&self) -> &StagingBlock {
+        match self {
+            ChainstateInconsistency::UnmarkedProcessedBlock(ref block) => block,
+            ChainstateInconsistency::UnrecordedAcceptedBlock(ref block) => block,
+            ChainstateInconsistency::MissingBlockData(ref block) => block,
+        }
+    }
+}
+
+impl fmt::Display for ChainstateInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let block = self.staging_block();
+        match self {
+            ChainstateInconsistency::UnmarkedProcessedBlock(_) => write!(f, "block {}/{} was processed but is still staging", &block.burn_header_hash, &block.anchored_block_hash),
+            ChainstateInconsistency::UnrecordedAcceptedBlock(_) => write!(f, "block {}/{} was accepted but is not recorded in the burn DB", &block.burn_header_hash, &block.anchored_block_hash),
+            ChainstateInconsistency::MissingBlockData(_) => write!(f, "block {}/{} is staging but its data is missing", &block.burn_header_hash, &block.anchored_block_hash),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MemPoolRejection {
     SerializationFailure(net_error),
@@ -346,7 +399,8 @@ impl BlockStreamData {
             total_bytes: 0,
             is_microblock: false,
             seq: 0,
-            in_staging: false
+            in_staging: false,
+            export: None
         }
     }
 
@@ -358,7 +412,8 @@ impl BlockStreamData {
             total_bytes: 0,
             is_microblock: true,
             seq: 0,
-            in_staging: false
+            in_staging: false,
+            export: None
         }
     }
     
@@ -370,12 +425,35 @@ impl BlockStreamData {
             total_bytes: 0,
             is_microblock: true,
             seq: seq,
-            in_staging: true
+            in_staging: true,
+            export: None
+        }
+    }
+
+    /// Stream the given blocks one after the other, each encoded in the given format
+    pub fn new_block_export(headers: Vec<StacksHeaderInfo>, format: BlockExportFormat) -> BlockStreamData {
+        let block_hash = headers.first().map(|header_info| header_info.index_block_hash()).unwrap_or(StacksBlockId([0u8; 32]));
+        BlockStreamData {
+            block_hash: block_hash,
+            rowid: None,
+            offset: 0,
+            total_bytes: 0,
+            is_microblock: false,
+            seq: 0,
+            in_staging: false,
+            export: Some(BlockExportCursor {
+                headers: headers.into_iter().collect(),
+                format: format,
+                buf: vec![]
+            })
         }
     }
 
     pub fn stream_to<W: Write>(&mut self, chainstate: &mut StacksChainState, fd: &mut W, count: u64) -> Result<u64, Error> {
-        if self.is_microblock {
+        if self.export.is_some() {
+            chainstate.stream_block_export(fd, self, count)
+        }
+        else if self.is_microblock {
             if self.in_staging {
                 chainstate.stream_microblocks_unconfirmed(fd, self, count)
             }
@@ -444,6 +522,31 @@ const STACKS_BLOCK_INDEX_SQL : &'static [&'static str]= &[
 ];
 
 
+/// Changes to the staging blocks DB's schema, applied when it's opened (see util::migrations).
+/// Add a migration here instead of changing STACKS_BLOCK_INDEX_SQL.  Blocks DBs from before
+/// migrations were kept track of may already have some of these tables, so they're created only
+/// if they aren't there.
+pub const BLOCKS_DB_MIGRATIONS : &'static [SchemaMigration] = &[
+    SchemaMigration {
+        version: 1,
+        description: "add the block pruning tables",
+        up: BLOCK_PRUNING_SQL,
+        down: Some(&["DROP TABLE pruned_blocks; DROP TABLE block_pruning_progress; DROP TABLE fork_pruning_progress;"]),
+    },
+    SchemaMigration {
+        version: 2,
+        description: "add the archive mode table",
+        up: ARCHIVE_MODE_SQL,
+        down: Some(&["DROP TABLE archive_mode;"]),
+    },
+    SchemaMigration {
+        version: 3,
+        description: "add the light sync table",
+        up: LIGHT_SYNC_SQL,
+        down: Some(&["DROP TABLE light_sync;"]),
+    },
+];
+
 impl StacksChainState {
     fn instantiate_blocks_db(conn: &mut DBConn) -> Result<(), Error> {
         let tx = tx_begin_immediate(conn)?;
@@ -456,7 +559,7 @@ impl StacksChainState {
         Ok(())
     }
     
-    pub fn open_blocks_db(db_path: &str) -> Result<DBConn, Error> {
+    pub fn open_blocks_db(db_path: &str, pragmas: &SqlitePragmas) -> Result<DBConn, Error> {
         let mut create_flag = false;
         let open_flags =
             if fs::metadata(db_path).is_err() {
@@ -469,14 +572,14 @@ impl StacksChainState {
                 OpenFlags::SQLITE_OPEN_READ_WRITE
             };
 
-        let mut conn = DBConn::open_with_flags(db_path, open_flags).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        let mut conn = sqlite_open(db_path, open_flags, Some(pragmas)).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
         if create_flag {
             // instantiate!
             StacksChainState::instantiate_blocks_db(&mut conn)?;
         }
-        
+
+        migrate_schema(&mut conn, BLOCKS_DB_MIGRATIONS, None, false).map_err(Error::DBError)?;
         Ok(conn)
     }
     
@@ -580,20 +683,8 @@ impl StacksChainState {
     }
 
     pub fn consensus_load<T: StacksMessageCodec>(path: &String) -> Result<T, Error> {
-        let mut fd = fs::OpenOptions::new()
-                    .read(true)
-                    .write(false)
-                    .open(path)
-                    .map_err(|e| {
-                        if e.kind() == io::ErrorKind::NotFound {
-                            Error::DBError(db_error::NotFoundError)
-                        }
-                        else {
-                            Error::DBError(db_error::IOError(e))
-                        }
-                    })?;
-
-        let mut bound_reader = BoundReader::from_reader(&mut fd, MAX_MESSAGE_LEN as u64);
+        let bytes = StacksChainState::read_chunk(path)?;
+        let mut bound_reader = BoundReader::from_reader(&mut &bytes[..], MAX_MESSAGE_LEN as u64);
         let inst = T::consensus_deserialize(&mut bound_reader).map_err(Error::NetError)?;
         Ok(inst)
     }
@@ -645,15 +736,16 @@ impl StacksChainState {
         }
     }
 
-    /// Store a block to the chunk store, named by its hash
-    pub fn store_block(blocks_dir: &String, burn_header_hash: &BurnchainHeaderHash, block: &StacksBlock) -> Result<(), Error> {
+    /// Store a block to the chunk store, named by its hash, compressed at `level` if given
+    pub fn store_block(blocks_dir: &String, burn_header_hash: &BurnchainHeaderHash, block: &StacksBlock, level: Option<i32>) -> Result<(), Error> {
         let block_hash = block.block_hash();
         let block_path = StacksChainState::make_block_dir(blocks_dir, burn_header_hash, &block_hash)?;
         
+        let mut block_bytes = vec![];
+        block.consensus_serialize(&mut block_bytes).map_err(Error::NetError)?;
+
         test_debug!("Store {}/{} to {}", burn_header_hash, &block_hash, &block_path);
-        StacksChainState::atomic_file_store(&block_path, true, |ref mut fd| {
-            block.consensus_serialize(fd).map_err(Error::NetError)
-        })
+        StacksChainState::atomic_chunk_write(&block_path, true, &block_bytes, level)
     }
     
     /// Store an empty block to the chunk store, named by its hash.
@@ -726,21 +818,7 @@ impl StacksChainState {
             return Ok(None);
         }
 
-        let mut fd = fs::OpenOptions::new()
-                    .read(true)
-                    .write(false)
-                    .open(&block_path)
-                    .map_err(|e| {
-                        if e.kind() == io::ErrorKind::NotFound {
-                            Error::DBError(db_error::NotFoundError)
-                        }
-                        else {
-                            Error::DBError(db_error::IOError(e))
-                        }
-                    })?;
-
-        let mut ret = vec![];
-        fd.read_to_end(&mut ret).map_err(|e| Error::DBError(db_error::IOError(e)))?;
+        let ret = StacksChainState::read_chunk(&block_path)?;
         Ok(Some(ret))
     }
 
@@ -785,8 +863,8 @@ impl StacksChainState {
     /// Regardless, the file contains the longest stream built on by any anchored block discovered
     /// so far.
     ///
-    /// The file stored is a serialized Vec<StacksMicroblock> 
-    pub fn store_microblock_stream(blocks_path: &String, burn_header_hash: &BurnchainHeaderHash, microblocks: &Vec<StacksMicroblock>) -> Result<(), Error> {
+    /// The file stored is a serialized Vec<StacksMicroblock>, compressed at `level` if given
+    pub fn store_microblock_stream(blocks_path: &String, burn_header_hash: &BurnchainHeaderHash, microblocks: &Vec<StacksMicroblock>, level: Option<i32>) -> Result<(), Error> {
         if microblocks.len() == 0 {
             StacksChainState::store_empty_block(blocks_path, burn_header_hash, &EMPTY_MICROBLOCK_PARENT_HASH)?;
             return Ok(())
@@ -795,9 +873,9 @@ impl StacksChainState {
         let block_hash = microblocks[0].block_hash();
         let block_path = StacksChainState::make_block_dir(blocks_path, burn_header_hash, &block_hash)?;
 
-        StacksChainState::atomic_file_store(&block_path, false, |ref mut fd| {
-            microblocks.consensus_serialize(fd).map_err(Error::NetError)
-        })
+        let mut microblocks_bytes = vec![];
+        microblocks.consensus_serialize(&mut microblocks_bytes).map_err(Error::NetError)?;
+        StacksChainState::atomic_chunk_write(&block_path, false, &microblocks_bytes, level)
     }
 
     /// Load a stream of microblocks from the chunk store, given its first block's hash.
@@ -1216,7 +1294,7 @@ impl StacksChainState {
         tx.execute(&sql, args)
             .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
-        StacksChainState::store_block(tx.get_blocks_path(), burn_hash, block)?;
+        StacksChainState::store_block(tx.get_blocks_path(), burn_hash, block, tx.get_block_compression_level())?;
 
         // mark all children of this new block as unattachable -- need to attach this block first!
         // this should be done across all burnchains.
@@ -1749,7 +1827,7 @@ impl StacksChainState {
         let microblocks = StacksChainState::merge_microblock_streams(staging_microblocks, stored_microblocks)?;
 
         debug!("Accept microblock stream {}/{}({}) as {}", StacksBlockHeader::make_index_block_hash(burn_hash, anchored_block_hash), microblocks[0].block_hash(), last_seq, StacksBlockHeader::make_index_block_hash(burn_hash, &microblocks[0].block_hash()));
-        StacksChainState::store_microblock_stream(tx.get_blocks_path(), burn_hash, &microblocks)?;
+        StacksChainState::store_microblock_stream(tx.get_blocks_path(), burn_hash, &microblocks, tx.get_block_compression_level())?;
 
         // clear out of staging
         let sql = "UPDATE staging_microblocks SET processed = 1 WHERE burn_header_hash = ?1 AND anchored_block_hash = ?2 AND sequence <= ?3".to_string();
@@ -1872,6 +1950,78 @@ impl StacksChainState {
         }
     }
 
+    /// Find a stored anchored block by its block hash alone.  The same block can be stored once
+    /// for each burnchain fork that selected it, but every copy has the same bytes, so the first
+    /// one found in the chunk store is returned.  Processed copies are preferred.
+    pub fn get_stored_block_index_hash(&self, block_hash: &BlockHeaderHash) -> Result<Option<StacksBlockId>, Error> {
+        let sql = "SELECT index_block_hash FROM staging_blocks WHERE anchored_block_hash = ?1 AND orphaned = 0 ORDER BY processed DESC";
+        let args = [block_hash as &dyn ToSql];
+        let candidates = query_row_columns::<StacksBlockId, _>(&self.blocks_db, sql, &args, "index_block_hash").map_err(Error::DBError)?;
+
+        for index_block_hash in candidates.into_iter() {
+            if StacksChainState::has_block_indexed(&self.blocks_path, &index_block_hash)? {
+                return Ok(Some(index_block_hash));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Load a microblock by its hash alone, whether it is still in staging or has already been
+    /// confirmed and moved to the chunk store.
+    pub fn load_microblock_by_hash(&self, microblock_hash: &BlockHeaderHash) -> Result<Option<StacksMicroblock>, Error> {
+        let sql = "SELECT * FROM staging_microblocks WHERE microblock_hash = ?1 AND orphaned = 0 ORDER BY processed".to_string();
+        let args = [microblock_hash as &dyn ToSql];
+        let candidates = query_rows::<StagingMicroblock, _>(&self.blocks_db, &sql, &args).map_err(Error::DBError)?;
+
+        for candidate in candidates.into_iter() {
+            if !candidate.processed {
+                // still in staging
+                if let Some(microblock_bytes) = StacksChainState::load_staging_microblock_bytes(&self.blocks_db, microblock_hash)? {
+                    let microblock = StacksMicroblock::consensus_deserialize(&mut &microblock_bytes[..])
+                        .map_err(Error::NetError)?;
+                    return Ok(Some(microblock));
+                }
+            }
+            else {
+                // confirmed, so it's in its stream's file in the chunk store
+                let head_hash = match StacksChainState::get_microblock_stream_head_hash(&self.blocks_db, &candidate.burn_header_hash, &candidate.anchored_block_hash)? {
+                    Some(head_hash) => head_hash,
+                    None => continue
+                };
+                let microblocks = StacksChainState::load_microblock_stream(&self.blocks_path, &candidate.burn_header_hash, &head_hash)
+                    .or_else(StacksChainState::empty_stream)?
+                    .unwrap_or(vec![]);
+
+                if let Some(microblock) = microblocks.into_iter().find(|mblock| mblock.block_hash() == *microblock_hash) {
+                    return Ok(Some(microblock));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Given a block's burn header hash and block hash, get its parent's burn header hash and
+    /// block hash
+    pub fn get_parent_block_header_hashes(&self, burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash) -> Result<Option<(BurnchainHeaderHash, BlockHeaderHash)>, Error> {
+        let sql = "SELECT parent_burn_header_hash,parent_anchored_block_hash FROM staging_blocks WHERE burn_header_hash = ?1 AND anchored_block_hash = ?2";
+        let args : &[&dyn ToSql] = &[burn_header_hash, block_hash];
+
+        let row_data_opt = self.blocks_db.query_row(sql, args,
+            |row| {
+                let parent_anchored_block_hash = BlockHeaderHash::from_column(row, "parent_anchored_block_hash")?;
+                let parent_burn_header_hash = BurnchainHeaderHash::from_column(row, "parent_burn_header_hash")?;
+                Ok((parent_burn_header_hash, parent_anchored_block_hash))
+            })
+            .optional()
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        match row_data_opt {
+            Some(Ok(x)) => Ok(Some(x)),
+            Some(Err(e)) => Err(e),
+            None => Ok(None)
+        }
+    }
+
     /// Get the sqlite rowid for a staging microblock.
     /// Returns None if no such microblock.
     fn stream_microblock_get_rowid(blocks_conn: &DBConn, index_block_hash: &StacksBlockId, seq: u16) -> Result<Option<i64>, Error> {
@@ -2015,6 +2165,17 @@ impl StacksChainState {
                         }
                     })?;
 
+        // a compressed file is decompressed as it's streamed
+        let mut magic = vec![];
+        (&mut file_fd).take(4).read_to_end(&mut magic).map_err(Error::ReadError)?;
+        if is_compressed_chunk(&magic) && is_block_compression_available() {
+            file_fd.seek(SeekFrom::Start(0)).map_err(Error::ReadError)?;
+            let nw = stream_decompressed_chunk(&mut file_fd, stream.offset, count, fd)?;
+            stream.offset += nw;
+            stream.total_bytes += nw;
+            return Ok(nw);
+        }
+
         StacksChainState::stream_data(fd, stream, &mut file_fd, count)
     }
 
@@ -2026,6 +2187,75 @@ impl StacksChainState {
         StacksChainState::stream_data_from_chunk_store(&self.blocks_path, fd, stream, count)
     }
 
+    /// Encode a block the way a block export sends it
+    fn encode_exported_block(header_info: &StacksHeaderInfo, block: &StacksBlock, format: BlockExportFormat) -> Result<Vec<u8>, Error> {
+        let mut block_bytes = vec![];
+        block.consensus_serialize(&mut block_bytes).map_err(Error::NetError)?;
+
+        match format {
+            BlockExportFormat::Binary => {
+                let mut buf = Vec::with_capacity(4 + block_bytes.len());
+                buf.extend_from_slice(&(block_bytes.len() as u32).to_be_bytes());
+                buf.extend_from_slice(&block_bytes);
+                Ok(buf)
+            },
+            BlockExportFormat::NDJSON => {
+                let entry = ExportedBlockEntry {
+                    block_height: header_info.block_height,
+                    index_block_hash: header_info.index_block_hash().to_hex(),
+                    block_hash: block.block_hash().to_hex(),
+                    parent_block_hash: block.header.parent_block.to_hex(),
+                    burn_header_hash: header_info.burn_header_hash.to_hex(),
+                    burn_header_timestamp: header_info.burn_header_timestamp,
+                    block: to_hex(&block_bytes)
+                };
+                let mut buf = serde_json::to_vec(&entry)
+                    .map_err(|e| Error::NetError(net_error::SerializeError(format!("Failed to serialize exported block: {:?}", &e))))?;
+                buf.push(b'\n');
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Stream a block export from the chunk store.  Each block is loaded and encoded once the
+    /// previous one has been sent.
+    /// Returns the number of bytes written, and updates `stream` to point to the next point to
+    /// read.  Writes the bytes streamed to `fd`.
+    pub fn stream_block_export<W: Write>(&mut self, fd: &mut W, stream: &mut BlockStreamData, count: u64) -> Result<u64, Error> {
+        let export = stream.export.as_mut().ok_or(Error::NoSuchBlockError)?;
+
+        let mut to_write = count;
+        while to_write > 0 {
+            if stream.offset >= export.buf.len() as u64 {
+                // done with this block; move to the next one
+                let header_info = match export.headers.pop_front() {
+                    Some(header_info) => header_info,
+                    None => {
+                        // out of blocks
+                        break;
+                    }
+                };
+
+                let block = StacksChainState::load_block(&self.blocks_path, &header_info.burn_header_hash, &header_info.anchored_header.block_hash())?
+                    .ok_or(Error::NoSuchBlockError)?;
+
+                export.buf = StacksChainState::encode_exported_block(&header_info, &block, export.format)?;
+                stream.block_hash = header_info.index_block_hash();
+                stream.offset = 0;
+            }
+
+            let start = stream.offset as usize;
+            let end = cmp::min(export.buf.len() as u64, stream.offset + to_write) as usize;
+            fd.write_all(&export.buf[start..end]).map_err(Error::WriteError)?;
+
+            let nw = (end - start) as u64;
+            stream.offset += nw;
+            stream.total_bytes += nw;
+            to_write -= nw;
+        }
+        Ok(count - to_write)
+    }
+
     /// Stream confirmed microblocks from the chain state.  Pull from only the chunk store.
     /// Returns the number of bytes written, and updates `stream` to point to the next point to
     /// read.  Writes the bytes streamed to `fd`.
@@ -2721,7 +2951,7 @@ impl StacksChainState {
 
     /// Process a single anchored block.
     /// Return the fees and burns.
-    fn process_block_transactions<'a>(clarity_tx: &mut ClarityTx<'a>, block: &StacksBlock) -> Result<(u128, u128, Vec<StacksTransactionReceipt>), Error> {
+    pub fn process_block_transactions<'a>(clarity_tx: &mut ClarityTx<'a>, block: &StacksBlock) -> Result<(u128, u128, Vec<StacksTransactionReceipt>), Error> {
         let mut fees = 0u128;
         let mut burns = 0u128;
         let mut receipts = vec![];
@@ -2830,7 +3060,10 @@ impl StacksChainState {
                         microblocks: &Vec<StacksMicroblock>,  // parent microblocks 
                         burnchain_commit_burn: u64, 
                         burnchain_sortition_burn: u64, 
-                        user_burns: &Vec<StagingUserBurnSupport>) -> Result<(StacksHeaderInfo, Vec<StacksTransactionReceipt>), Error>
+                        user_burns: &Vec<StagingUserBurnSupport>,
+                        assume_valid: bool,
+                        trace_contract_calls: bool,
+                        signatures: Option<SignatureVerifier>) -> Result<(StacksHeaderInfo, Vec<StacksTransactionReceipt>), Error>
     {
 
         debug!("Process block {:?} with {} transactions", &block.block_hash().to_hex(), block.txs.len());
@@ -2843,7 +3076,7 @@ impl StacksChainState {
             StacksChainState::find_mature_miner_rewards(&mut chainstate_tx.headers_tx, parent_chain_tip, Some(chainstate_tx.miner_payment_cache))?
         };
 
-        let (scheduled_miner_reward, txs_receipts) = {
+        let (scheduled_miner_reward, txs_receipts, stx_fees, stx_burns, changed_accounts) = {
             let (parent_burn_header_hash, parent_block_hash) = 
                 if block.is_first_mined() {
                     // has to be the sentinal hashes if this block has no parent
@@ -2874,6 +3107,12 @@ impl StacksChainState {
             }
             
             let mut clarity_tx = StacksChainState::chainstate_block_begin(chainstate_tx, clarity_instance, &parent_burn_header_hash, &parent_block_hash, &MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH);
+            clarity_tx.assume_valid = assume_valid;
+            if assume_valid {
+                clarity_tx.connection().disable_cost_tracking();
+            }
+            clarity_tx.trace_contract_calls = trace_contract_calls;
+            clarity_tx.signatures = signatures;
 
             // process microblock stream
             let (microblock_fees, microblock_burns, mut microblock_txs_receipts) = match StacksChainState::process_microblocks_transactions(&mut clarity_tx, &microblocks) {
@@ -2907,9 +3146,9 @@ impl StacksChainState {
             };
 
             // grant matured miner rewards
-            if let Some(mature_miner_rewards) = matured_miner_rewards_opt {
+            if let Some(ref mature_miner_rewards) = matured_miner_rewards_opt {
                 // grant in order by miner, then users
-                StacksChainState::process_matured_miner_rewards(&mut clarity_tx, &mature_miner_rewards)?;
+                StacksChainState::process_matured_miner_rewards(&mut clarity_tx, mature_miner_rewards)?;
             }
 
             let root_hash = clarity_tx.get_root_hash();
@@ -2922,6 +3161,10 @@ impl StacksChainState {
             }
 
             debug!("Reached state root {}", root_hash);
+
+            // the accounts this block changed, as of this block, for the balance index
+            let changed_principals = StacksChainState::balance_changed_principals(txs_receipts.iter().chain(microblock_txs_receipts.iter()));
+            let changed_accounts = StacksChainState::get_accounts(&mut clarity_tx, &changed_principals);
             
             // good to go!
             clarity_tx.commit_to_block(chain_tip_burn_header_hash, &block.block_hash());
@@ -2943,7 +3186,10 @@ impl StacksChainState {
 
             txs_receipts.append(&mut microblock_txs_receipts);
 
-            (scheduled_miner_reward, txs_receipts)
+            let stx_fees = block_fees.checked_add(microblock_fees).expect("Overflow: Too many STX fees");
+            let stx_burns = block_burns.checked_add(microblock_burns).expect("Overflow: Too many STX burnt");
+
+            (scheduled_miner_reward, txs_receipts, stx_fees, stx_burns, changed_accounts)
         };
 
         let microblock_tail_opt = match microblocks.len() {
@@ -2962,9 +3208,121 @@ impl StacksChainState {
                                                     user_burns)
             .expect("FATAL: failed to advance chain tip");
 
+        StacksChainState::insert_transfer_memos(&mut chainstate_tx.headers_tx, &new_tip, block, microblocks)
+            .expect("FATAL: failed to index transfer memos");
+
+        StacksChainState::insert_transaction_receipts(&mut chainstate_tx.headers_tx, &new_tip, &txs_receipts)
+            .expect("FATAL: failed to store transaction receipts");
+
+        if assume_valid {
+            // its costs weren't counted
+            StacksChainState::insert_unmetered_block(&mut chainstate_tx.headers_tx, &new_tip.index_block_hash())
+                .expect("FATAL: failed to mark block unmetered");
+        }
+        else {
+            StacksChainState::insert_contract_costs(&mut chainstate_tx.headers_tx, &new_tip.index_block_hash(), new_tip.block_height, &contract_costs(&txs_receipts))
+                .expect("FATAL: failed to store contract costs");
+        }
+
+        // the supply is only known if it was known at the parent
+        let parent_index_block_hash = 
+            if block.is_first_mined() {
+                StacksBlockHeader::make_index_block_hash(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH)
+            }
+            else {
+                parent_chain_tip.index_block_hash()
+            };
+
+        let parent_supply_opt = StacksChainState::get_stx_supply(&chainstate_tx.headers_tx, &parent_index_block_hash)
+            .expect("FATAL: failed to query STX supply");
+
+        if let Some(parent_supply) = parent_supply_opt {
+            let matured_miner_rewards = matured_miner_rewards_opt.unwrap_or(vec![]);
+            let supply = parent_supply.next(stx_fees, stx_burns, &matured_miner_rewards);
+            StacksChainState::insert_stx_supply(&mut chainstate_tx.headers_tx, &new_tip.index_block_hash(), &supply)
+                .expect("FATAL: failed to store STX supply");
+        }
+
+        StacksChainState::update_nonce_cache(&mut chainstate_tx.headers_tx, &parent_index_block_hash, &new_tip.index_block_hash(), new_tip.block_height, block, microblocks)
+            .expect("FATAL: failed to update the nonce cache");
+
+        // the balances are only indexed if they were at the parent
+        StacksChainState::insert_account_balances(&mut chainstate_tx.headers_tx, Some(&parent_index_block_hash), &new_tip.index_block_hash(), new_tip.block_height, &changed_accounts)
+            .expect("FATAL: failed to index account balances");
+
         Ok((new_tip, txs_receipts))
     }
 
+    /// Attach a block to the chain state without executing it, for light sync.  Its header is
+    /// indexed and stored, and its miner's reward scheduled, as append_block() does, but none of
+    /// its transactions (nor its confirmed microblocks') are run:  its state root is taken from
+    /// its header on trust, and no Clarity state is kept for it.  The scheduled reward counts the
+    /// transactions' fees, but not the STX they burn, which only running them would tell.
+    fn append_block_header<'a>(chainstate_tx: &mut ChainstateTx<'a>,
+                               parent_chain_tip: &StacksHeaderInfo,
+                               chain_tip_burn_header_hash: &BurnchainHeaderHash,
+                               chain_tip_burn_header_timestamp: u64,
+                               block: &StacksBlock,
+                               microblocks: &Vec<StacksMicroblock>,  // parent microblocks
+                               burnchain_commit_burn: u64,
+                               burnchain_sortition_burn: u64,
+                               user_burns: &Vec<StagingUserBurnSupport>) -> Result<(StacksHeaderInfo, Vec<StacksTransactionReceipt>), Error>
+    {
+        debug!("Attach block {:?} with {} transactions without executing it", &block.block_hash().to_hex(), block.txs.len());
+
+        let mainnet = chainstate_tx.get_config().mainnet;
+        let next_block_height = block.header.total_work.work;
+
+        let (parent_burn_header_hash, parent_block_hash) =
+            if block.is_first_mined() {
+                (FIRST_BURNCHAIN_BLOCK_HASH.clone(), FIRST_STACKS_BLOCK_HASH.clone())
+            }
+            else {
+                (parent_chain_tip.burn_header_hash.clone(), parent_chain_tip.anchored_header.block_hash())
+            };
+
+        let block_fees = block.txs.iter()
+            .fold(0u128, |fees, tx| fees.checked_add(tx.get_fee_rate() as u128).expect("Overflow: Too many STX fees"));
+        let microblock_fees = microblocks.iter()
+            .flat_map(|microblock| microblock.txs.iter())
+            .fold(0u128, |fees, tx| fees.checked_add(tx.get_fee_rate() as u128).expect("Overflow: Too many STX fees"));
+
+        let scheduled_miner_reward = StacksChainState::make_scheduled_miner_reward(mainnet,
+                                                                                   &parent_block_hash,
+                                                                                   &parent_burn_header_hash,
+                                                                                   &block,
+                                                                                   chain_tip_burn_header_hash,
+                                                                                   next_block_height,
+                                                                                   block_fees,
+                                                                                   microblock_fees,
+                                                                                   0,
+                                                                                   burnchain_commit_burn,
+                                                                                   burnchain_sortition_burn,
+                                                                                   0xffffffffffffffff)?;
+
+        let microblock_tail_opt = match microblocks.len() {
+            0 => None,
+            x => Some(microblocks[x - 1].header.clone())
+        };
+
+        let new_tip = StacksChainState::advance_tip(&mut chainstate_tx.headers_tx,
+                                                    &parent_chain_tip.anchored_header,
+                                                    &parent_chain_tip.burn_header_hash,
+                                                    &block.header,
+                                                    chain_tip_burn_header_hash,
+                                                    chain_tip_burn_header_timestamp,
+                                                    microblock_tail_opt,
+                                                    &scheduled_miner_reward,
+                                                    user_burns)
+            .expect("FATAL: failed to advance chain tip");
+
+        StacksChainState::insert_transfer_memos(&mut chainstate_tx.headers_tx, &new_tip, block, microblocks)
+            .expect("FATAL: failed to index transfer memos");
+
+        // no receipts, contract costs or STX supply:  only executing the block tells them
+        Ok((new_tip, vec![]))
+    }
+
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
@@ -2982,6 +3340,87 @@ impl StacksChainState {
         return true;
     }
 
+    /// Does a block at this height pass the trusted checkpoints?  It does unless there's a
+    /// checkpoint at its height for some other block.
+    fn check_block_checkpoint(checkpoints: &Vec<StacksBlockCheckpoint>, block_height: u64, index_block_hash: &StacksBlockId) -> bool {
+        checkpoints.iter()
+            .all(|checkpoint| checkpoint.block_height != block_height || checkpoint.index_block_hash == *index_block_hash)
+    }
+
+    /// Can a block skip transaction signature checks and cost tracking?  Only in assume-valid
+    /// mode, and only if the highest checkpoint descends from it.  A block on some other fork
+    /// is fully validated, whatever its height.
+    fn is_block_assumed_valid(checkpoints: &Vec<StacksBlockCheckpoint>, assume_valid: bool, checkpoint_ancestors: &HashSet<StacksBlockId>, block_height: u64, index_block_hash: &StacksBlockId) -> bool {
+        assume_valid
+            && checkpoints.last().map(|checkpoint| block_height <= checkpoint.block_height).unwrap_or(false)
+            && checkpoint_ancestors.contains(index_block_hash)
+    }
+
+    /// Find more of the blocks the highest checkpoint descends from, by walking back through the
+    /// staging blocks from the checkpointed block -- once we have it -- until we reach the
+    /// first-ever block, or a block we haven't downloaded yet.  In the latter case, the walk
+    /// picks up from there the next time this is called.
+    fn find_checkpoint_ancestors(&mut self) -> Result<(), Error> {
+        let checkpoint = match self.checkpoints.last() {
+            Some(checkpoint) if self.assume_valid => checkpoint.clone(),
+            _ => {
+                return Ok(());
+            }
+        };
+
+        let mut next = if self.checkpoint_ancestors.len() == 0 {
+            // haven't started -- do we have the checkpointed block yet?
+            if !StacksChainState::has_block_indexed(&self.blocks_path, &checkpoint.index_block_hash)? {
+                return Ok(());
+            }
+            let block_path = StacksChainState::get_index_block_path(&self.blocks_path, &checkpoint.index_block_hash)?;
+            if StacksChainState::get_file_size(&block_path)? == 0 {
+                return Ok(());
+            }
+            let block_header : StacksBlockHeader = StacksChainState::consensus_load(&block_path)?;
+            let block_hash = block_header.block_hash();
+
+            let sql = "SELECT burn_header_hash FROM staging_blocks WHERE anchored_block_hash = ?1 AND index_block_hash = ?2";
+            let args: &[&dyn ToSql] = &[&block_hash, &checkpoint.index_block_hash];
+            match query_row_columns::<BurnchainHeaderHash, _>(&self.blocks_db, sql, args, "burn_header_hash").map_err(Error::DBError)?.pop() {
+                Some(burn_header_hash) => (burn_header_hash, block_hash),
+                None => {
+                    return Ok(());
+                }
+            }
+        }
+        else {
+            match self.checkpoint_ancestors_next.take() {
+                Some(next) => next,
+                None => {
+                    // already walked back to the first block
+                    return Ok(());
+                }
+            }
+        };
+
+        let checkpoint_ancestors = Arc::make_mut(&mut self.checkpoint_ancestors);
+        loop {
+            let sql = "SELECT * FROM staging_blocks WHERE anchored_block_hash = ?1 AND burn_header_hash = ?2";
+            let args: &[&dyn ToSql] = &[&next.1, &next.0];
+            let staging_block = match query_rows::<StagingBlock, _>(&self.blocks_db, sql, args).map_err(Error::DBError)?.pop() {
+                Some(staging_block) => staging_block,
+                None => {
+                    debug!("Found checkpoint ancestors back to {}/{}", &next.0, &next.1);
+                    self.checkpoint_ancestors_next = Some(next);
+                    return Ok(());
+                }
+            };
+
+            checkpoint_ancestors.insert(StacksBlockHeader::make_index_block_hash(&next.0, &next.1));
+            if staging_block.is_first_mined() {
+                debug!("Found checkpoint ancestors back to the first block");
+                return Ok(());
+            }
+            next = (staging_block.parent_burn_header_hash, staging_block.parent_anchored_block_hash);
+        }
+    }
+
     /// Find and process the next staging block.
     /// Return the next chain tip if we processed this block, or None if we couldn't.
     /// Return a poison microblock transaction payload if the microblock stream contains a
@@ -2990,6 +3429,15 @@ impl StacksChainState {
     /// Occurs as a single, atomic transaction against the (marf'ed) headers database and
     /// (un-marf'ed) staging block database, as well as against the chunk store.
     fn process_next_staging_block<'a>(&mut self, burn_tx: &mut BurnDBTx<'a>) -> Result<(Option<(StacksHeaderInfo, Vec<StacksTransactionReceipt>)>, Option<TransactionPayload>), Error> {
+        self.find_checkpoint_ancestors()?;
+        let checkpoints = self.checkpoints.clone();
+        let checkpoint_ancestors = self.checkpoint_ancestors.clone();
+        let assume_valid = self.assume_valid;
+        let trace_contract_calls = self.trace_contract_calls;
+        let block_validation_threads = self.block_validation_threads;
+        let execution_trace_path = if self.light_sync { None } else { self.execution_trace_path.clone() };
+        let light_sync = self.light_sync;
+        let index_addresses = self.index_addresses && !light_sync;
         let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
 
         let blocks_path = chainstate_tx.blocks_tx.get_blocks_path().clone();
@@ -3080,6 +3528,40 @@ impl StacksChainState {
             return Err(Error::InvalidStacksBlock(msg));
         }
 
+        // validation check -- the block must match any trusted checkpoint at its height
+        let index_block_hash = StacksBlockHeader::make_index_block_hash(&next_staging_block.burn_header_hash, &block_hash);
+        if !StacksChainState::check_block_checkpoint(&checkpoints, block.header.total_work.work, &index_block_hash) {
+            let msg = format!("Invalid stacks block {}/{} -- does not match the checkpoint at height {}", &next_staging_block.burn_header_hash, &block_hash, block.header.total_work.work);
+            warn!("{}", &msg);
+
+            // clear out
+            StacksChainState::set_block_processed(&mut chainstate_tx.blocks_tx, None, &next_staging_block.burn_header_hash, &next_staging_block.anchored_block_hash, false)?; 
+            chainstate_tx.commit()
+                .map_err(Error::DBError)?;
+
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        let block_assumed_valid = StacksChainState::is_block_assumed_valid(&checkpoints, assume_valid, &checkpoint_ancestors, block.header.total_work.work, &index_block_hash);
+        if block_assumed_valid {
+            debug!("Block {}/{} is an ancestor of the last checkpoint; not checking its transactions' signatures or counting their costs", &next_staging_block.burn_header_hash, &block_hash);
+        }
+
+        // start checking the transactions' signatures, so they're (mostly) checked by the time
+        // each transaction is executed.  Transactions in microblocks this block turns out not to
+        // confirm get checked for nothing, but that's rare.
+        let signatures = if !light_sync && !block_assumed_valid && block_validation_threads > 1 {
+            let mut txs = vec![];
+            for microblock in next_microblocks.iter() {
+                txs.extend(microblock.txs.iter().cloned());
+            }
+            txs.extend(block.txs.iter().cloned());
+            Some(SignatureVerifier::spawn(txs, block_validation_threads))
+        }
+        else {
+            None
+        };
+
         // validate microblocks
         // NOTE: since we got the microblocks from staging, where their signatures were already
         // validated, we don't need to validate them again.
@@ -3128,16 +3610,44 @@ impl StacksChainState {
         // attach the block to the chain state and calculate the next chain tip.
         // Execute the confirmed microblocks' transactions against the chain state, and then
         // execute the anchored block's transactions against the chain state.
-        let (next_chain_tip, receipts) = match StacksChainState::append_block(&mut chainstate_tx, 
-                                                                              clarity_instance, 
-                                                                              &parent_block_header_info, 
-                                                                              &next_staging_block.burn_header_hash, 
-                                                                              next_staging_block.burn_header_timestamp,
-                                                                              &block,
-                                                                              &next_microblocks,
-                                                                              next_staging_block.commit_burn,
-                                                                              next_staging_block.sortition_burn,
-                                                                              &user_supports) {
+        if execution_trace_path.is_some() {
+            statetrace::begin();
+        }
+        let append_result =
+            if light_sync {
+                // light sync attaches the block without executing it
+                StacksChainState::append_block_header(&mut chainstate_tx,
+                                                      &parent_block_header_info,
+                                                      &next_staging_block.burn_header_hash,
+                                                      next_staging_block.burn_header_timestamp,
+                                                      &block,
+                                                      &next_microblocks,
+                                                      next_staging_block.commit_burn,
+                                                      next_staging_block.sortition_burn,
+                                                      &user_supports)
+            }
+            else {
+                StacksChainState::append_block(&mut chainstate_tx,
+                                               clarity_instance,
+                                               &parent_block_header_info,
+                                               &next_staging_block.burn_header_hash,
+                                               next_staging_block.burn_header_timestamp,
+                                               &block,
+                                               &next_microblocks,
+                                               next_staging_block.commit_burn,
+                                               next_staging_block.sortition_burn,
+                                               &user_supports,
+                                               block_assumed_valid,
+                                               trace_contract_calls,
+                                               signatures)
+            };
+        // the accesses the block made outside of its transactions
+        let block_state_trace = match execution_trace_path {
+            Some(_) => Some(statetrace::finish()),
+            None => None
+        };
+
+        let (next_chain_tip, receipts) = match append_result {
             Ok(next_chain_tip) => next_chain_tip,
             Err(e) => {
                 // something's wrong with this epoch -- either a microblock was invalid, or the
@@ -3181,13 +3691,82 @@ impl StacksChainState {
             StacksChainState::set_microblocks_confirmed(&mut chainstate_tx.blocks_tx, &next_staging_block.parent_burn_header_hash, &next_staging_block.parent_anchored_block_hash, last_microblock_seq)?;
         }
         StacksChainState::set_block_processed(&mut chainstate_tx.blocks_tx, Some(burn_tx), &next_chain_tip.burn_header_hash, &next_chain_tip.anchored_header.block_hash(), true)?;
+
+        if index_addresses {
+            StacksChainState::insert_address_transactions(&mut chainstate_tx.headers_tx, &next_chain_tip, &receipts)
+                .expect("FATAL: failed to index transactions by address");
+        }
        
         chainstate_tx.commit()
             .map_err(Error::DBError)?;
 
+        if let (Some(path), Some(block_state_trace)) = (execution_trace_path, block_state_trace) {
+            let trace = StacksChainState::make_execution_trace(&next_chain_tip, &block, &next_microblocks, &receipts, !block_assumed_valid, &block_state_trace);
+            StacksChainState::write_execution_trace(&path, &next_chain_tip.index_block_hash(), &trace);
+        }
+
         Ok((Some((next_chain_tip, receipts)), None))
     }
 
+    /// The execution trace of a processed block:  its transactions in the order they ran (the
+    /// confirmed microblocks' first), each with its result, events, cost, and what it read from
+    /// and wrote to the chain state, and then what the block itself read and wrote, such as
+    /// matured miner rewards.  Values are hex-encoded as they are stored, so the trace can be
+    /// checked against the parent block's state without running the block.  If the block's costs
+    /// weren't counted (it's not `metered`), the transactions' costs are null.
+    pub fn make_execution_trace(header_info: &StacksHeaderInfo, block: &StacksBlock, microblocks: &Vec<StacksMicroblock>,
+                                receipts: &Vec<StacksTransactionReceipt>, metered: bool, block_state_trace: &StateTrace) -> serde_json::Value {
+        // receipts list the anchored block's transactions first
+        let (anchored_receipts, microblock_receipts) = receipts.split_at(cmp::min(block.txs.len(), receipts.len()));
+        let microblock_hashes = microblocks.iter()
+            .flat_map(|microblock| microblock.txs.iter().map(move |_| Some(microblock.block_hash())));
+        let anchored_hashes = block.txs.iter().map(|_| None);
+
+        let transactions : Vec<serde_json::Value> = microblock_receipts.iter().chain(anchored_receipts.iter())
+            .zip(microblock_hashes.chain(anchored_hashes))
+            .enumerate()
+            .map(|(tx_index, (receipt, microblock_hash))| {
+                let txid = receipt.transaction.txid();
+                let events : Vec<serde_json::Value> = receipt.events.iter().map(|event| event.json_serialize(&txid, !receipt.post_condition_aborted)).collect();
+                let state_trace = receipt.state_trace.as_ref()
+                    .map(|trace| trace.json_serialize())
+                    .unwrap_or(json!(null));
+                json!({
+                    "txid": format!("0x{}", txid),
+                    "tx_index": tx_index,
+                    "microblock_hash": microblock_hash.map(|hash| format!("0x{}", hash)),
+                    "raw_result": format!("0x{}", receipt.result.serialize()),
+                    "post_condition_aborted": receipt.post_condition_aborted,
+                    "abort_code": receipt.abort_code.map(|code| code.code()),
+                    "execution_cost": if metered { json!(receipt.execution_cost) } else { json!(null) },
+                    "events": events,
+                    "state": state_trace,
+                })
+            })
+            .collect();
+
+        json!({
+            "index_block_hash": format!("0x{}", header_info.index_block_hash()),
+            "block_hash": format!("0x{}", block.block_hash()),
+            "burn_header_hash": format!("0x{}", header_info.burn_header_hash),
+            "block_height": header_info.block_height,
+            "state_index_root": format!("0x{}", block.header.state_index_root),
+            "transactions": transactions,
+            "block_state": block_state_trace.json_serialize(),
+        })
+    }
+
+    /// Write a block's execution trace to `<path>/<index block hash>.json`.  A trace is only a
+    /// record, so failing to write it doesn't fail the block.
+    fn write_execution_trace(path: &PathBuf, index_block_hash: &StacksBlockId, trace: &serde_json::Value) {
+        let trace_path = path.join(format!("{}.json", index_block_hash));
+        let result = fs::create_dir_all(path)
+            .and_then(|_| fs::write(&trace_path, serde_json::to_string_pretty(trace).expect("FATAL: failed to serialize execution trace")));
+        if let Err(e) = result {
+            warn!("Failed to write the execution trace of {} to {}: {:?}", index_block_hash, trace_path.display(), &e);
+        }
+    }
+
     /// Process some staging blocks, up to max_blocks.
     /// Return new chain tips, and optionally any poison microblock payloads for each chain tip
     /// found.
@@ -3278,6 +3857,120 @@ impl StacksChainState {
         query_row(&self.blocks_db, sql, args).map_err(Error::DBError)
     }
 
+    /// Find recoverable inconsistencies between the staging blocks, headers, chunk store, and burn
+    /// DB, in the order they should be repaired.  Reads are safe to serve from the burn DB's
+    /// canonical Stacks tip while these are repaired, since it only ever points to a block whose
+    /// processing was fully recorded.  Returns a Corruption error if that tip's header is missing,
+    /// since then there is no good tip to fall back to.
+    pub fn check_consistency(&self, burndb: &BurnDB) -> Result<Vec<ChainstateInconsistency>, Error> {
+        let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn())?;
+        if burn_tip.canonical_stacks_tip_hash != FIRST_STACKS_BLOCK_HASH &&
+           StacksChainState::get_anchored_block_header_info(&self.headers_db, &burn_tip.canonical_stacks_tip_burn_hash, &burn_tip.canonical_stacks_tip_hash)?.is_none() {
+            error!("Canonical Stacks tip {}/{} has no header", &burn_tip.canonical_stacks_tip_burn_hash, &burn_tip.canonical_stacks_tip_hash);
+            return Err(Error::DBError(db_error::Corruption));
+        }
+
+        let mut ret = vec![];
+
+        // queued blocks that were already processed, or whose data is gone
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 0 AND orphaned = 0";
+        let staging_blocks = query_rows::<StagingBlock, _>(&self.blocks_db, sql, NO_PARAMS).map_err(Error::DBError)?;
+        for block in staging_blocks.into_iter() {
+            if StacksChainState::get_anchored_block_header_info(&self.headers_db, &block.burn_header_hash, &block.anchored_block_hash)?.is_some() {
+                ret.push(ChainstateInconsistency::UnmarkedProcessedBlock(block));
+                continue;
+            }
+
+            let readable = match StacksChainState::load_block_bytes(&self.blocks_path, &block.burn_header_hash, &block.anchored_block_hash) {
+                Ok(Some(bytes)) => StacksBlock::consensus_deserialize(&mut &bytes[..]).is_ok(),
+                Ok(None) | Err(Error::DBError(db_error::NotFoundError)) => false,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            if !readable {
+                ret.push(ChainstateInconsistency::MissingBlockData(block));
+            }
+        }
+
+        // recently-accepted blocks the burn DB never heard about
+        let min_height = burn_tip.canonical_stacks_tip_height.saturating_sub(CHAINSTATE_CHECK_DEPTH);
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 1 AND orphaned = 0 AND height >= ?1";
+        let args : &[&dyn ToSql] = &[&u64_to_sql(min_height)?];
+        let processed_blocks = query_rows::<StagingBlock, _>(&self.blocks_db, sql, args).map_err(Error::DBError)?;
+        for block in processed_blocks.into_iter() {
+            if StacksChainState::get_anchored_block_header_info(&self.headers_db, &block.burn_header_hash, &block.anchored_block_hash)?.is_none() {
+                // rejected
+                continue;
+            }
+            let recorded = match BurnDB::get_block_snapshot(burndb.conn(), &block.burn_header_hash)? {
+                Some(sn) => sn.stacks_block_accepted && sn.winning_stacks_block_hash == block.anchored_block_hash,
+                None => true
+            };
+            if !recorded {
+                ret.push(ChainstateInconsistency::UnrecordedAcceptedBlock(block));
+            }
+        }
+
+        // parents before children, so each block is recorded on top of its parent
+        ret.sort_by_key(|inconsistency| inconsistency.staging_block().height);
+        Ok(ret)
+    }
+
+    /// Repair an inconsistency found by check_consistency().  Repairs are idempotent, and commit
+    /// the staging blocks DB before the burn DB just as block processing does, so a repair that is
+    /// interrupted is found and repaired again on the next check.
+    pub fn repair_inconsistency(&mut self, burndb: &mut BurnDB, inconsistency: &ChainstateInconsistency) -> Result<(), Error> {
+        info!("Repair chainstate: {}", inconsistency);
+        match inconsistency {
+            ChainstateInconsistency::UnmarkedProcessedBlock(ref block) => {
+                let mut burn_tx = burndb.tx_begin()?;
+                let mut blocks_tx = self.blocks_tx_begin()?;
+                if block.parent_microblock_hash != EMPTY_MICROBLOCK_PARENT_HASH || block.parent_microblock_seq != 0 {
+                    StacksChainState::set_microblocks_confirmed(&mut blocks_tx, &block.parent_burn_header_hash, &block.parent_anchored_block_hash, block.parent_microblock_seq)?;
+                }
+                StacksChainState::set_block_processed(&mut blocks_tx, Some(&mut burn_tx), &block.burn_header_hash, &block.anchored_block_hash, true)?;
+                blocks_tx.commit().map_err(Error::DBError)?;
+                burn_tx.commit().map_err(Error::DBError)?;
+            },
+            ChainstateInconsistency::UnrecordedAcceptedBlock(ref block) => {
+                let mut burn_tx = burndb.tx_begin()?;
+                BurnDB::set_stacks_block_accepted(&mut burn_tx, &block.burn_header_hash, &block.parent_anchored_block_hash, &block.anchored_block_hash, block.height)
+                    .map_err(Error::DBError)?;
+                burn_tx.commit().map_err(Error::DBError)?;
+            },
+            ChainstateInconsistency::MissingBlockData(ref block) => {
+                let mut blocks_tx = self.blocks_tx_begin()?;
+                StacksChainState::drop_staging_block(&mut blocks_tx, &block.burn_header_hash, &block.anchored_block_hash)?;
+                blocks_tx.commit().map_err(Error::DBError)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget a staging block and its data entirely, so it can be stored again if it gets
+    /// re-downloaded.
+    fn drop_staging_block<'a>(tx: &mut BlocksDBTx<'a>, burn_hash: &BurnchainHeaderHash, anchored_block_hash: &BlockHeaderHash) -> Result<(), Error> {
+        let args: &[&dyn ToSql] = &[&burn_hash, &anchored_block_hash];
+        tx.execute("DELETE FROM staging_blocks WHERE burn_header_hash = ?1 AND anchored_block_hash = ?2", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("DELETE FROM staging_user_burn_support WHERE burn_header_hash = ?1 AND anchored_block_hash = ?2", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        let block_path = StacksChainState::get_block_path(tx.get_blocks_path(), burn_hash, anchored_block_hash)?;
+        match fs::remove_file(&block_path) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    Ok(())
+                }
+                else {
+                    Err(Error::DBError(db_error::IOError(e)))
+                }
+            }
+        }
+    }
+
     /// Get the height of a staging block
     pub fn get_stacks_block_height(&self, burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash) -> Result<Option<u64>, Error> {
         let sql = "SELECT height FROM staging_blocks WHERE burn_header_hash = ?1 AND anchored_block_hash = ?2";
@@ -3317,14 +4010,32 @@ impl StacksChainState {
             _ => false      // unused
         };
         
+        StacksChainState::can_include_tx_precheck(&conf, tx, tx_size)?;
+
+        // the nonce cache turns away transactions with the wrong nonces without a MARF lookup
+        let tip = StacksBlockHeader::make_index_block_hash(current_burn, current_block);
+        StacksChainState::check_cached_nonces(&self.headers_db, &tip, tx)
+            .map_err(|e| MemPoolRejection::BadNonces(e))?;
+
         self.with_read_only_clarity_tx(current_burn, current_block, |conn| {
-            StacksChainState::can_include_tx(conn, &conf, has_microblock_pubk, tx, tx_size)
-        })
+            StacksChainState::can_include_prechecked_tx(conn, &conf, has_microblock_pubk, tx)
+        })?;
+
+        if let Err(e) = StacksChainState::cache_admitted_nonces(&self.headers_db, &tip, tx) {
+            warn!("Failed to cache the nonces of {}: {:?}", &tx.txid(), &e);
+        }
+        Ok(())
     }
 
     /// Given an outstanding clarity connection, can we append the tx to the chain state?
     /// Used when mining transactions.
     pub fn can_include_tx<T: ClarityConnection>(clarity_connection: &mut T, chainstate_config: &DBConfig, has_microblock_pubkey: bool, tx: &StacksTransaction, tx_size: u64) -> Result<(), MemPoolRejection> {
+        StacksChainState::can_include_tx_precheck(chainstate_config, tx, tx_size)?;
+        StacksChainState::can_include_prechecked_tx(clarity_connection, chainstate_config, has_microblock_pubkey, tx)
+    }
+
+    /// The checks of can_include_tx() that don't need the chain state
+    pub fn can_include_tx_precheck(chainstate_config: &DBConfig, tx: &StacksTransaction, tx_size: u64) -> Result<(), MemPoolRejection> {
         // 1: must parse (done)
 
         // 2: it must be validly signed.
@@ -3339,6 +4050,14 @@ impl StacksChainState {
             return Err(MemPoolRejection::FeeTooLow(fee, cmp::max(MINIMUM_TX_FEE, tx_size * MINIMUM_TX_FEE_RATE_PER_BYTE)))
         }
 
+        Ok(())
+    }
+
+    /// The checks of can_include_tx() that need the chain state, for a transaction that passed
+    /// can_include_tx_precheck()
+    pub fn can_include_prechecked_tx<T: ClarityConnection>(clarity_connection: &mut T, chainstate_config: &DBConfig, has_microblock_pubkey: bool, tx: &StacksTransaction) -> Result<(), MemPoolRejection> {
+        let fee = tx.get_fee_rate();
+
         // 4: the account nonces must be correct
         let (origin, payer) = StacksChainState::check_transaction_nonces(clarity_connection, &tx)
             .map_err(|e| MemPoolRejection::BadNonces(e))?;
@@ -3695,6 +4414,95 @@ pub mod test {
         tx.commit().unwrap();
     }
 
+    #[test]
+    fn stacks_db_block_checkpoints() {
+        let checkpoints = vec![
+            StacksBlockCheckpoint { block_height: 10, index_block_hash: StacksBlockId([0x10; 32]) },
+            StacksBlockCheckpoint { block_height: 20, index_block_hash: StacksBlockId([0x20; 32]) },
+        ];
+
+        // only the checkpointed block is allowed at a checkpoint's height
+        assert!(StacksChainState::check_block_checkpoint(&checkpoints, 10, &StacksBlockId([0x10; 32])));
+        assert!(!StacksChainState::check_block_checkpoint(&checkpoints, 10, &StacksBlockId([0x11; 32])));
+        assert!(!StacksChainState::check_block_checkpoint(&checkpoints, 20, &StacksBlockId([0x10; 32])));
+        assert!(StacksChainState::check_block_checkpoint(&checkpoints, 15, &StacksBlockId([0x11; 32])));
+        assert!(StacksChainState::check_block_checkpoint(&vec![], 10, &StacksBlockId([0x11; 32])));
+
+        // signature checks are skipped for the last checkpoint's ancestors, and only in
+        // assume-valid mode
+        let ancestors : HashSet<StacksBlockId> = vec![StacksBlockId([0x01; 32]), StacksBlockId([0x10; 32]), StacksBlockId([0x20; 32])].into_iter().collect();
+        assert!(StacksChainState::is_block_assumed_valid(&checkpoints, true, &ancestors, 1, &StacksBlockId([0x01; 32])));
+        assert!(StacksChainState::is_block_assumed_valid(&checkpoints, true, &ancestors, 20, &StacksBlockId([0x20; 32])));
+        assert!(!StacksChainState::is_block_assumed_valid(&checkpoints, true, &ancestors, 1, &StacksBlockId([0x02; 32])));
+        assert!(!StacksChainState::is_block_assumed_valid(&checkpoints, true, &ancestors, 21, &StacksBlockId([0x20; 32])));
+        assert!(!StacksChainState::is_block_assumed_valid(&checkpoints, false, &ancestors, 1, &StacksBlockId([0x01; 32])));
+        assert!(!StacksChainState::is_block_assumed_valid(&vec![], true, &ancestors, 1, &StacksBlockId([0x01; 32])));
+    }
+
+    #[test]
+    fn stacks_db_block_checkpoint_ancestors() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "stx_db_block_checkpoint_ancestors");
+        let privk = StacksPrivateKey::from_hex("eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01").unwrap();
+
+        let mut block_1 = make_empty_coinbase_block(&privk);
+        let mut block_2 = make_empty_coinbase_block(&privk);
+        let mut block_3 = make_empty_coinbase_block(&privk);
+        let mut block_4 = make_empty_coinbase_block(&privk);
+
+        //            block_3 -- block_4 (checkpoint)
+        // block_1 --/
+        //           \
+        //            block_2
+        //
+        // block_2 is below the checkpoint's height, but the checkpoint doesn't descend from it
+
+        block_1.header.parent_block = FIRST_STACKS_BLOCK_HASH.clone();
+        block_2.header.parent_block = block_1.block_hash();
+        block_3.header.parent_block = block_1.block_hash();
+        block_3.header.total_work.work = block_2.header.total_work.work + 1;
+        block_4.header.parent_block = block_3.block_hash();
+
+        let burn_headers = vec![
+            BurnchainHeaderHash([2u8; 32]),
+            BurnchainHeaderHash([3u8; 32]),
+            BurnchainHeaderHash([4u8; 32]),
+            BurnchainHeaderHash([5u8; 32]),
+        ];
+
+        let parent_burn_headers = vec![
+            FIRST_BURNCHAIN_BLOCK_HASH.clone(),
+            BurnchainHeaderHash([2u8; 32]),
+            BurnchainHeaderHash([2u8; 32]),
+            BurnchainHeaderHash([4u8; 32]),
+        ];
+
+        let blocks = vec![&block_1, &block_2, &block_3, &block_4];
+        let index_block_hashes : Vec<_> = blocks.iter().zip(burn_headers.iter()).map(|(block, burn_header)| StacksBlockHeader::make_index_block_hash(burn_header, &block.block_hash())).collect();
+
+        chainstate.set_checkpoints(vec![StacksBlockCheckpoint { block_height: block_4.header.total_work.work, index_block_hash: index_block_hashes[3].clone() }], true);
+
+        // the checkpointed block arrives before its parent
+        for i in [1, 3].iter() {
+            store_staging_block(&mut chainstate, &burn_headers[*i], get_epoch_time_secs(), blocks[*i], &parent_burn_headers[*i], 1, 2);
+        }
+        chainstate.find_checkpoint_ancestors().unwrap();
+        assert_eq!(*chainstate.checkpoint_ancestors, vec![index_block_hashes[3].clone()].into_iter().collect::<HashSet<_>>());
+
+        // the walk picks up where it left off as the rest arrive
+        for i in [0, 2].iter() {
+            store_staging_block(&mut chainstate, &burn_headers[*i], get_epoch_time_secs(), blocks[*i], &parent_burn_headers[*i], 1, 2);
+        }
+        chainstate.find_checkpoint_ancestors().unwrap();
+        assert_eq!(*chainstate.checkpoint_ancestors, vec![index_block_hashes[0].clone(), index_block_hashes[2].clone(), index_block_hashes[3].clone()].into_iter().collect::<HashSet<_>>());
+        assert!(chainstate.checkpoint_ancestors_next.is_none());
+
+        // the fork block is fully validated, even though it's below the checkpoint
+        for (i, (block, index_block_hash)) in blocks.iter().zip(index_block_hashes.iter()).enumerate() {
+            let assumed_valid = StacksChainState::is_block_assumed_valid(&chainstate.checkpoints, chainstate.assume_valid, &chainstate.checkpoint_ancestors, block.header.total_work.work, index_block_hash);
+            assert_eq!(assumed_valid, i != 1);
+        }
+    }
+
     #[test]
     fn stacks_db_block_load_store_empty() {
         let chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_block_load_store_empty");
@@ -3724,7 +4532,7 @@ pub mod test {
         assert!(fs::metadata(&path).is_err());
         assert!(!StacksChainState::has_stored_block(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([1u8; 32]), &block.block_hash()).unwrap());
 
-        StacksChainState::store_block(&chainstate.blocks_path, &BurnchainHeaderHash([1u8; 32]), &block).unwrap();
+        StacksChainState::store_block(&chainstate.blocks_path, &BurnchainHeaderHash([1u8; 32]), &block, None).unwrap();
         assert!(fs::metadata(&path).is_ok());
         assert!(StacksChainState::has_stored_block(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([1u8; 32]), &block.block_hash()).unwrap());
         assert!(StacksChainState::load_block(&chainstate.blocks_path, &BurnchainHeaderHash([1u8; 32]), &block.block_hash()).unwrap().is_some());
@@ -3762,6 +4570,37 @@ pub mod test {
         assert_block_stored_not_staging(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), &block);
     }
     
+    #[test]
+    fn stacks_db_repair_missing_staging_block_data() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_repair_missing_staging_block_data");
+        let mut burndb = BurnDB::connect_test(0, &BurnchainHeaderHash([0u8; 32])).unwrap();
+        let privk = StacksPrivateKey::from_hex("eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01").unwrap();
+      
+        let block = make_empty_coinbase_block(&privk);
+
+        store_staging_block(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), get_epoch_time_secs(), &block, &BurnchainHeaderHash([1u8; 32]), 1, 2);
+        assert_eq!(chainstate.check_consistency(&burndb).unwrap(), vec![]);
+
+        // lose the block data
+        let path = StacksChainState::get_block_path(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &block.block_hash()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let inconsistencies = chainstate.check_consistency(&burndb).unwrap();
+        assert_eq!(inconsistencies.len(), 1);
+        match inconsistencies[0] {
+            ChainstateInconsistency::MissingBlockData(ref staging_block) => assert_eq!(staging_block.anchored_block_hash, block.block_hash()),
+            _ => panic!("Unexpected inconsistency {:?}", &inconsistencies[0])
+        }
+
+        chainstate.repair_inconsistency(&mut burndb, &inconsistencies[0]).unwrap();
+        assert_eq!(chainstate.check_consistency(&burndb).unwrap(), vec![]);
+        assert!(!StacksChainState::has_staging_block(&chainstate.blocks_db, &BurnchainHeaderHash([2u8; 32]), &block.block_hash()).unwrap());
+
+        // can be stored again once re-downloaded
+        store_staging_block(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), get_epoch_time_secs(), &block, &BurnchainHeaderHash([1u8; 32]), 1, 2);
+        assert_block_staging_not_processed(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), &block);
+    }
+
     #[test]
     fn stacks_db_staging_block_load_store_reject() {
         let mut chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_staging_block_load_store_reject");
@@ -3799,7 +4638,7 @@ pub mod test {
         assert!(!StacksChainState::has_stored_block(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks[0].block_hash()).unwrap());
         assert!(StacksChainState::load_microblock_stream(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks[0].block_hash()).is_err());
         
-        StacksChainState::store_microblock_stream(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks).unwrap();
+        StacksChainState::store_microblock_stream(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks, None).unwrap();
 
         assert!(fs::metadata(&path).is_ok());
         assert!(StacksChainState::has_stored_block(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks[0].block_hash()).unwrap());
@@ -3844,6 +4683,10 @@ pub mod test {
         assert!(!StacksChainState::has_stored_microblocks(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks[0].block_hash()).unwrap());
         assert!(StacksChainState::load_microblock_stream(&chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &microblocks[0].block_hash()).is_err());
 
+        // staging blocks and microblocks can be found by hash alone
+        assert_eq!(chainstate.get_stored_block_index_hash(&block.block_hash()).unwrap(), Some(StacksBlockHeader::make_index_block_hash(&BurnchainHeaderHash([2u8; 32]), &block.block_hash())));
+        assert_eq!(chainstate.load_microblock_by_hash(&microblocks[1].block_hash()).unwrap(), Some(microblocks[1].clone()));
+
         set_block_processed(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), &block.block_hash(), true);
         set_microblocks_confirmed(&mut chainstate, &BurnchainHeaderHash([2u8; 32]), &block.block_hash(), (microblocks.len() - 1) as u16);
 
@@ -3863,6 +4706,14 @@ pub mod test {
         // but we should still load the full stream if asked
         assert!(StacksChainState::load_staging_microblock_stream(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &block.block_hash(), u16::max_value()).unwrap().is_some());
         assert_eq!(StacksChainState::load_staging_microblock_stream(&chainstate.blocks_db, &chainstate.blocks_path, &BurnchainHeaderHash([2u8; 32]), &block.block_hash(), u16::max_value()).unwrap().unwrap(), microblocks);
+
+        // blocks and microblocks can still be found by hash alone
+        assert_eq!(chainstate.get_stored_block_index_hash(&block.block_hash()).unwrap(), Some(StacksBlockHeader::make_index_block_hash(&BurnchainHeaderHash([2u8; 32]), &block.block_hash())));
+        for mb in microblocks.iter() {
+            assert_eq!(chainstate.load_microblock_by_hash(&mb.block_hash()).unwrap(), Some(mb.clone()));
+        }
+        assert_eq!(chainstate.get_stored_block_index_hash(&BlockHeaderHash([0x11; 32])).unwrap(), None);
+        assert_eq!(chainstate.load_microblock_by_hash(&BlockHeaderHash([0x11; 32])).unwrap(), None);
     }
 
     #[test]
@@ -4808,6 +5659,112 @@ pub mod test {
         assert_eq!(staging_block, block);
     }
 
+    #[test]
+    #[cfg(feature = "block_compression")]
+    fn stacks_db_stream_compressed_block() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_stream_compressed_block");
+        let privk = StacksPrivateKey::from_hex("eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01").unwrap();
+
+        let block = make_empty_coinbase_block(&privk);
+
+        let burn_header = BurnchainHeaderHash([2u8; 32]);
+        let parent_burn_header = BurnchainHeaderHash([1u8; 32]);
+        let index_block_header = StacksBlockHeader::make_index_block_hash(&burn_header, &block.block_hash());
+
+        // only this chainstate compresses the blocks it stores
+        chainstate.set_block_compression_level(Some(3));
+        store_staging_block(&mut chainstate, &burn_header, get_epoch_time_secs(), &block, &parent_burn_header, 1, 2);
+        set_block_processed(&mut chainstate, &burn_header, &block.block_hash(), true);
+
+        let block_path = StacksChainState::get_index_block_path(&chainstate.blocks_path, &index_block_header).unwrap();
+        assert!(is_compressed_chunk(&fs::read(&block_path).unwrap()));
+
+        // loads and streams the same as before
+        assert_eq!(StacksChainState::load_block(&chainstate.blocks_path, &burn_header, &block.block_hash()).unwrap().unwrap(), block);
+
+        let mut stream = BlockStreamData::new_block(index_block_header.clone());
+        let mut all_block_bytes = vec![];
+        loop {
+            let mut next_bytes = stream_chunk_to_vec(&chainstate.blocks_path, &mut stream, 16).unwrap();
+            if next_bytes.len() == 0 {
+                break;
+            }
+            all_block_bytes.append(&mut next_bytes);
+        }
+
+        let mut block_bytes = vec![];
+        block.consensus_serialize(&mut block_bytes).unwrap();
+        assert_eq!(all_block_bytes, block_bytes);
+    }
+
+    #[test]
+    fn stacks_db_stream_block_export() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_stream_block_export");
+        let privks = vec![
+            StacksPrivateKey::from_hex("eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01").unwrap(),
+            StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap(),
+        ];
+
+        let mut blocks = vec![];
+        let mut headers = vec![];
+        for (i, privk) in privks.iter().enumerate() {
+            let block = make_empty_coinbase_block(privk);
+            let burn_header = BurnchainHeaderHash([(i + 2) as u8; 32]);
+            let parent_burn_header = BurnchainHeaderHash([(i + 1) as u8; 32]);
+            store_staging_block(&mut chainstate, &burn_header, get_epoch_time_secs(), &block, &parent_burn_header, 1, 2);
+            set_block_processed(&mut chainstate, &burn_header, &block.block_hash(), true);
+
+            headers.push(StacksHeaderInfo {
+                anchored_header: block.header.clone(),
+                microblock_tail: None,
+                block_height: (i + 1) as u64,
+                index_root: TrieHash([0u8; 32]),
+                burn_header_hash: burn_header,
+                burn_header_timestamp: 0
+            });
+            blocks.push(block);
+        }
+
+        // length-prefixed blocks, streamed a few bytes at a time
+        let mut stream = BlockStreamData::new_block_export(headers.clone(), BlockExportFormat::Binary);
+        let mut all_bytes = vec![];
+        loop {
+            let mut next_bytes = vec![];
+            let nw = stream.stream_to(&mut chainstate, &mut next_bytes, 16).unwrap();
+            assert_eq!(nw as usize, next_bytes.len());
+            if nw == 0 {
+                break;
+            }
+            all_bytes.append(&mut next_bytes);
+        }
+
+        let mut cursor = &all_bytes[..];
+        for block in blocks.iter() {
+            let block_len = u32::consensus_deserialize(&mut cursor).unwrap();
+            let exported_block = StacksBlock::consensus_deserialize(&mut &cursor[0..(block_len as usize)]).unwrap();
+            assert_eq!(exported_block, *block);
+            cursor = &cursor[(block_len as usize)..];
+        }
+        assert_eq!(cursor.len(), 0);
+
+        // one JSON object per line
+        let mut stream = BlockStreamData::new_block_export(headers.clone(), BlockExportFormat::NDJSON);
+        let mut all_bytes = vec![];
+        while stream.stream_to(&mut chainstate, &mut all_bytes, 1024).unwrap() > 0 {}
+
+        let lines : Vec<&[u8]> = all_bytes.split(|b| *b == b'\n').filter(|line| line.len() > 0).collect();
+        assert_eq!(lines.len(), blocks.len());
+        for ((line, block), header_info) in lines.iter().zip(blocks.iter()).zip(headers.iter()) {
+            let entry : ExportedBlockEntry = serde_json::from_slice(line).unwrap();
+            assert_eq!(entry.block_height, header_info.block_height);
+            assert_eq!(entry.index_block_hash, header_info.index_block_hash().to_hex());
+            assert_eq!(entry.block_hash, block.block_hash().to_hex());
+
+            let exported_block = StacksBlock::consensus_deserialize(&mut &hex_bytes(&entry.block).unwrap()[..]).unwrap();
+            assert_eq!(exported_block, *block);
+        }
+    }
+
     #[test]
     fn stacks_db_stream_staging_microblocks() {
         let mut chainstate = instantiate_chainstate(false, 0x80000000, "stacks_db_stream_staging_microblocks");
@@ -0,0 +1,216 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt;
+
+use rusqlite::NO_PARAMS;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::index::TrieHash;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use util::db::Error as db_error;
+use util::db::{
+    query_row_columns,
+    query_rows,
+};
+
+/// Damage check_integrity() found in a chainstate.  Unlike a ChainstateInconsistency, which an
+/// unclean shutdown can leave behind and the node repairs on startup, none of these can be
+/// repaired in place.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityError {
+    /// The block's parent, as the headers MARF has it, is missing or isn't the block its header
+    /// names
+    BrokenParentLink(StacksBlockId, u64),
+    /// The Clarity MARF has no state for the block
+    MissingState(StacksBlockId, u64),
+    /// The Clarity MARF's root at the block isn't the state root in its header
+    StateRootMismatch(StacksBlockId, u64, TrieHash),
+    /// The block's file is missing, and the block wasn't pruned
+    MissingBlockFile(StacksBlockId, u64),
+    /// The block's file doesn't hold the block its header describes
+    CorruptBlockFile(StacksBlockId, u64, String),
+    /// The sortition DB doesn't have the block winning the sortition it was mined in
+    NotInSortitionDB(StacksBlockId, u64),
+    /// The block was processed and accepted, but has no header
+    MissingHeader(StacksBlockId),
+}
+
+impl IntegrityError {
+    /// What to do about it
+    pub fn remedy(&self) -> &'static str {
+        match self {
+            IntegrityError::BrokenParentLink(..) |
+            IntegrityError::MissingState(..) |
+            IntegrityError::StateRootMismatch(..) |
+            IntegrityError::MissingHeader(..) => "restore the chainstate from a snapshot, or sync it again from scratch",
+            IntegrityError::MissingBlockFile(..) |
+            IntegrityError::CorruptBlockFile(..) => "restore the chainstate from a snapshot, or sync it again from scratch; the node can keep running, but can't serve this block to peers",
+            IntegrityError::NotInSortitionDB(..) => "the sortition DB and chainstate are from different nodes or points in time; restore both from the same snapshot, or sync both again from scratch",
+        }
+    }
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityError::BrokenParentLink(ref block, height) => write!(f, "block {} at height {} is not linked to its parent", block, height),
+            IntegrityError::MissingState(ref block, height) => write!(f, "block {} at height {} has no Clarity state", block, height),
+            IntegrityError::StateRootMismatch(ref block, height, ref root) => write!(f, "block {} at height {} has Clarity state root {}, not the one in its header", block, height, root),
+            IntegrityError::MissingBlockFile(ref block, height) => write!(f, "block {} at height {} has no block file", block, height),
+            IntegrityError::CorruptBlockFile(ref block, height, ref reason) => write!(f, "block {} at height {} has a corrupt block file: {}", block, height, reason),
+            IntegrityError::NotInSortitionDB(ref block, height) => write!(f, "block {} at height {} did not win a sortition in the sortition DB", block, height),
+            IntegrityError::MissingHeader(ref block) => write!(f, "block {} was accepted but has no header", block),
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Check every processed block, in every fork:  that its header is linked to its parent's,
+    /// that the Clarity MARF has its state with the state root in its header, that its block
+    /// file holds it (unless it was pruned), and that it won its sortition in the sortition DB.
+    /// Blocks that were light-synced have no Clarity state, so theirs isn't checked.
+    /// Also checks that every accepted block has a header.  Reads only; returns what's damaged,
+    /// in height order.
+    pub fn check_integrity(&mut self, burndb: &BurnDB) -> Result<Vec<IntegrityError>, Error> {
+        let mut ret = vec![];
+
+        let headers : Vec<StacksHeaderInfo> = query_rows(&self.headers_db, "SELECT * FROM block_headers ORDER BY block_height", NO_PARAMS)
+            .map_err(Error::DBError)?;
+
+        // header linkage, through the headers MARF
+        {
+            let mut headers_tx = self.headers_tx_begin()?;
+            for header_info in headers.iter() {
+                if header_info.block_height == 0 {
+                    continue;
+                }
+                let index_block_hash = header_info.index_block_hash();
+                let linked = match StacksChainState::get_index_tip_ancestor(&mut headers_tx, &index_block_hash, header_info.block_height - 1) {
+                    Ok(Some(parent_info)) => {
+                        header_info.anchored_header.is_first_mined() || parent_info.anchored_header.block_hash() == header_info.anchored_header.parent_block
+                    },
+                    Ok(None) => false,
+                    Err(e) => {
+                        warn!("Failed to find the parent of block {}: {:?}", &index_block_hash, &e);
+                        false
+                    }
+                };
+                if !linked {
+                    ret.push(IntegrityError::BrokenParentLink(index_block_hash, header_info.block_height));
+                }
+            }
+        }
+
+        // light-synced blocks have no Clarity state to check
+        let light_sync_height = StacksChainState::get_light_sync_height(&self.blocks_db)?.unwrap_or(u64::max_value());
+
+        for header_info in headers.iter() {
+            let index_block_hash = header_info.index_block_hash();
+
+            // Clarity state
+            if header_info.block_height < light_sync_height {
+                match self.clarity_state.get_marf().get_root_hash_at(&index_block_hash) {
+                    Ok(root_hash) => {
+                        if root_hash != header_info.anchored_header.state_index_root {
+                            ret.push(IntegrityError::StateRootMismatch(index_block_hash.clone(), header_info.block_height, root_hash));
+                        }
+                    },
+                    Err(e) => {
+                        warn!("Failed to read the Clarity state root of block {}: {:?}", &index_block_hash, &e);
+                        ret.push(IntegrityError::MissingState(index_block_hash.clone(), header_info.block_height));
+                    }
+                }
+            }
+
+            if header_info.block_height == 0 {
+                // the boot block has no block file, and wasn't mined in a sortition
+                continue;
+            }
+
+            // block file
+            let block_hash = header_info.anchored_header.block_hash();
+            if !StacksChainState::is_block_pruned(&self.blocks_db, &index_block_hash)? {
+                match StacksChainState::load_block(&self.blocks_path, &header_info.burn_header_hash, &block_hash) {
+                    Ok(Some(block)) => {
+                        if block.block_hash() != block_hash {
+                            let reason = format!("it holds block {}", block.block_hash());
+                            ret.push(IntegrityError::CorruptBlockFile(index_block_hash.clone(), header_info.block_height, reason));
+                        }
+                    },
+                    Ok(None) => {
+                        ret.push(IntegrityError::CorruptBlockFile(index_block_hash.clone(), header_info.block_height, "it is empty".to_string()));
+                    },
+                    Err(Error::DBError(db_error::NotFoundError)) => {
+                        ret.push(IntegrityError::MissingBlockFile(index_block_hash.clone(), header_info.block_height));
+                    },
+                    Err(e) => {
+                        ret.push(IntegrityError::CorruptBlockFile(index_block_hash.clone(), header_info.block_height, format!("{}", e)));
+                    }
+                }
+            }
+
+            // sortition
+            let won = match BurnDB::get_block_snapshot(burndb.conn(), &header_info.burn_header_hash).map_err(Error::DBError)? {
+                Some(sn) => sn.sortition && sn.winning_stacks_block_hash == block_hash,
+                None => false
+            };
+            if !won {
+                ret.push(IntegrityError::NotInSortitionDB(index_block_hash, header_info.block_height));
+            }
+        }
+
+        // accepted blocks without headers
+        let accepted_blocks = query_row_columns::<StacksBlockId, _>(&self.blocks_db, &"SELECT index_block_hash FROM staging_blocks WHERE processed = 1 AND orphaned = 0".to_string(), NO_PARAMS, "index_block_hash")
+            .map_err(Error::DBError)?;
+        for index_block_hash in accepted_blocks.into_iter() {
+            if StacksChainState::get_stacks_block_header_info_by_index_block_hash(&self.headers_db, &index_block_hash)?.is_none() {
+                ret.push(IntegrityError::MissingHeader(index_block_hash));
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use burnchains::BurnchainHeaderHash;
+    use rusqlite::types::ToSql;
+
+    #[test]
+    fn test_check_integrity() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_check_integrity");
+        let burndb = BurnDB::connect_test(0, &BurnchainHeaderHash([0u8; 32])).unwrap();
+        assert_eq!(chainstate.check_integrity(&burndb).unwrap(), vec![]);
+
+        // the boot block's header no longer matches its state
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+        chainstate.headers_db.execute("UPDATE block_headers SET state_index_root = ?1 WHERE block_height = 0",
+                                      &[&TrieHash([1u8; 32]) as &dyn ToSql]).unwrap();
+        let problems = chainstate.check_integrity(&burndb).unwrap();
+        assert_eq!(problems, vec![IntegrityError::StateRootMismatch(genesis.index_block_hash(), 0, genesis.anchored_header.state_index_root.clone())]);
+    }
+}
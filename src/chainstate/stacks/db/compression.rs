@@ -0,0 +1,331 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use chainstate::stacks::Error;
+use chainstate::stacks::db::*;
+
+use net::MAX_MESSAGE_LEN;
+
+use util::db::Error as db_error;
+
+#[cfg(feature = "block_compression")]
+use zstd;
+
+/// Every zstd frame starts with these bytes
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// zstd level to compress at when none is given
+pub const DEFAULT_BLOCK_COMPRESSION_LEVEL: i32 = 3;
+
+/// Is zstd built in?  Without the `block_compression` feature, blocks and microblock streams are
+/// only stored uncompressed, and compressed ones can't be read.
+pub fn is_block_compression_available() -> bool {
+    cfg!(feature = "block_compression")
+}
+
+/// Does this chunk start like a zstd frame?
+pub fn is_compressed_chunk(bytes: &[u8]) -> bool {
+    bytes.len() >= ZSTD_MAGIC.len() && bytes[0..ZSTD_MAGIC.len()] == ZSTD_MAGIC
+}
+
+/// Compress a block or microblock stream for the chunk store.  The frame carries a checksum, so
+/// an uncompressed chunk that happens to start like a zstd frame isn't mistaken for one.
+#[cfg(feature = "block_compression")]
+pub fn compress_chunk(bytes: &[u8], level: i32) -> Result<Vec<u8>, Error> {
+    let mut encoder = zstd::stream::write::Encoder::new(vec![], level)
+        .map_err(|e| Error::DBError(db_error::IOError(e)))?;
+    encoder.include_checksum(true)
+        .map_err(|e| Error::DBError(db_error::IOError(e)))?;
+    encoder.write_all(bytes)
+        .map_err(|e| Error::DBError(db_error::IOError(e)))?;
+    encoder.finish()
+        .map_err(|e| Error::DBError(db_error::IOError(e)))
+}
+
+#[cfg(not(feature = "block_compression"))]
+pub fn compress_chunk(_bytes: &[u8], _level: i32) -> Result<Vec<u8>, Error> {
+    error!("Can't compress blocks: this build doesn't have the `block_compression` feature");
+    Err(Error::DBError(db_error::NotImplemented))
+}
+
+/// Decompress a chunk, if it's compressed.  Returns None if it isn't, including if it starts like
+/// a zstd frame but doesn't decompress:  a stored block can start with any bytes.
+#[cfg(feature = "block_compression")]
+fn try_decompress_chunk(bytes: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    if !is_compressed_chunk(bytes) {
+        return Ok(None);
+    }
+    let decoder = match zstd::stream::read::Decoder::new(bytes) {
+        Ok(decoder) => decoder,
+        Err(_) => return Ok(None)
+    };
+
+    // no more than a block can hold
+    let mut ret = vec![];
+    if decoder.take(MAX_MESSAGE_LEN as u64 + 1).read_to_end(&mut ret).is_err() {
+        return Ok(None);
+    }
+    if ret.len() > MAX_MESSAGE_LEN as usize {
+        warn!("Compressed chunk decompresses to more than {} bytes", MAX_MESSAGE_LEN);
+        return Err(Error::DBError(db_error::Corruption));
+    }
+    Ok(Some(ret))
+}
+
+#[cfg(not(feature = "block_compression"))]
+fn try_decompress_chunk(bytes: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    if is_compressed_chunk(bytes) {
+        warn!("Chunk may be compressed, but this build doesn't have the `block_compression` feature to read it");
+    }
+    Ok(None)
+}
+
+/// Write up to `count` bytes of a compressed chunk's decompressed contents to `fd`, starting
+/// `offset` bytes in.  The chunk is decompressed from its start on each call, and the bytes
+/// before `offset` are thrown away, so a stream that's part way through a chunk holds none of
+/// it.  Returns the number of bytes written, which is 0 once there are none left.
+#[cfg(feature = "block_compression")]
+pub fn stream_decompressed_chunk<R: Read, W: Write>(input: R, offset: u64, count: u64, fd: &mut W) -> Result<u64, Error> {
+    let decoder = zstd::stream::read::Decoder::new(input).map_err(Error::ReadError)?;
+
+    // no more than a block can hold
+    let mut decoder = decoder.take(MAX_MESSAGE_LEN as u64);
+    let skipped = io::copy(&mut (&mut decoder).take(offset), &mut io::sink()).map_err(Error::ReadError)?;
+    if skipped < offset {
+        return Ok(0);
+    }
+
+    let mut buf = vec![0u8; count as usize];
+    let nr = decoder.read(&mut buf).map_err(Error::ReadError)?;
+    fd.write_all(&buf[0..nr]).map_err(Error::WriteError)?;
+    Ok(nr as u64)
+}
+
+#[cfg(not(feature = "block_compression"))]
+pub fn stream_decompressed_chunk<R: Read, W: Write>(_input: R, _offset: u64, _count: u64, _fd: &mut W) -> Result<u64, Error> {
+    error!("Can't stream a compressed block: this build doesn't have the `block_compression` feature");
+    Err(Error::DBError(db_error::NotImplemented))
+}
+
+/// The bytes of a block or microblock stream, as read from its file in the chunk store
+pub fn decompress_chunk(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match try_decompress_chunk(&bytes)? {
+        Some(decompressed) => Ok(decompressed),
+        None => Ok(bytes)
+    }
+}
+
+/// What rewriting the chunk store came to
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChunkStoreMigration {
+    /// files rewritten
+    pub num_rewritten: u64,
+    /// files already stored the way they were to be, or empty
+    pub num_skipped: u64,
+    /// bytes the rewritten files took up before
+    pub bytes_before: u64,
+    /// bytes the rewritten files take up now
+    pub bytes_after: u64,
+}
+
+/// Is this a directory of the chunk store, named for the first or next two bytes of an index
+/// block hash?
+fn is_chunk_dir(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    path.is_dir() && name.len() == 4 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Is this a block's or microblock stream's file, named for its index block hash?
+fn is_chunk_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    path.is_file() && name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn list_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).map_err(|e| Error::DBError(db_error::IOError(e)))? {
+        paths.push(entry.map_err(|e| Error::DBError(db_error::IOError(e)))?.path());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+impl StacksChainState {
+    /// Atomically store a block's or microblock stream's bytes at `path` in the chunk store,
+    /// compressed at `level` if given
+    pub fn atomic_chunk_write(path: &String, delete_on_error: bool, bytes: &[u8], level: Option<i32>) -> Result<(), Error> {
+        let compressed = match level {
+            Some(level) => Some(compress_chunk(bytes, level)?),
+            None => None
+        };
+        let bytes = compressed.as_ref().map(|compressed| compressed.as_slice()).unwrap_or(bytes);
+        StacksChainState::atomic_file_store(path, delete_on_error, |ref mut fd| {
+            fd.write_all(bytes).map_err(|e| Error::DBError(db_error::IOError(e)))
+        })
+    }
+
+    /// Read a block's or microblock stream's bytes from the chunk store, decompressing them if
+    /// they're compressed.  Files bigger than a block can be are cut short.
+    pub fn read_chunk(path: &String) -> Result<Vec<u8>, Error> {
+        let fd = fs::OpenOptions::new()
+                    .read(true)
+                    .write(false)
+                    .open(path)
+                    .map_err(|e| {
+                        if e.kind() == io::ErrorKind::NotFound {
+                            Error::DBError(db_error::NotFoundError)
+                        }
+                        else {
+                            Error::DBError(db_error::IOError(e))
+                        }
+                    })?;
+
+        let mut bytes = vec![];
+        fd.take(MAX_MESSAGE_LEN as u64).read_to_end(&mut bytes).map_err(|e| Error::DBError(db_error::IOError(e)))?;
+        decompress_chunk(bytes)
+    }
+
+    /// Rewrite every block and microblock stream in the chunk store under `blocks_path`,
+    /// compressed at `level`, or uncompressed if None.  Files already stored that way (at any
+    /// level, if compressed) and the empty files of invalid and pruned blocks are left alone.
+    /// Each file is replaced atomically, but the node must be stopped, since it could store a
+    /// file again while it's rewritten.  Needs the `block_compression` feature either way.
+    pub fn migrate_chunk_store(blocks_path: &String, level: Option<i32>) -> Result<ChunkStoreMigration, Error> {
+        if !is_block_compression_available() {
+            error!("Can't rewrite the chunk store: this build doesn't have the `block_compression` feature");
+            return Err(Error::DBError(db_error::NotImplemented));
+        }
+
+        let mut migration = ChunkStoreMigration::default();
+        for dir in list_dir(Path::new(blocks_path))?.into_iter().filter(|path| is_chunk_dir(path)) {
+            for subdir in list_dir(&dir)?.into_iter().filter(|path| is_chunk_dir(path)) {
+                for path in list_dir(&subdir)?.into_iter().filter(|path| is_chunk_file(path)) {
+                    let path = path.to_str().ok_or_else(|| Error::DBError(db_error::ParseError))?.to_string();
+                    let mut bytes = vec![];
+                    fs::File::open(&path)
+                        .and_then(|mut fd| fd.read_to_end(&mut bytes))
+                        .map_err(|e| Error::DBError(db_error::IOError(e)))?;
+
+                    let decompressed = try_decompress_chunk(&bytes)?;
+                    if bytes.len() == 0 || decompressed.is_some() == level.is_some() {
+                        migration.num_skipped += 1;
+                        continue;
+                    }
+
+                    let raw = decompressed.unwrap_or_else(|| bytes.clone());
+                    StacksChainState::atomic_chunk_write(&path, false, &raw, level)?;
+                    migration.num_rewritten += 1;
+                    migration.bytes_before += bytes.len() as u64;
+                    migration.bytes_after += StacksChainState::get_file_size(&path)?;
+                }
+            }
+        }
+        Ok(migration)
+    }
+}
+
+#[cfg(all(test, feature = "block_compression"))]
+mod test {
+    use super::*;
+    use chainstate::stacks::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use burnchains::BurnchainHeaderHash;
+    use net::StacksMessageCodec;
+
+    #[test]
+    fn test_compress_chunk() {
+        let bytes : Vec<u8> = (0..10000).map(|i| (i % 7) as u8).collect();
+        let compressed = compress_chunk(&bytes, DEFAULT_BLOCK_COMPRESSION_LEVEL).unwrap();
+        assert!(is_compressed_chunk(&compressed));
+        assert!(compressed.len() < bytes.len());
+        assert_eq!(decompress_chunk(compressed).unwrap(), bytes);
+
+        // uncompressed chunks read as they are, even if they start like a zstd frame
+        assert_eq!(decompress_chunk(bytes.clone()).unwrap(), bytes);
+        let mut lookalike = ZSTD_MAGIC.to_vec();
+        lookalike.extend_from_slice(&bytes);
+        assert_eq!(decompress_chunk(lookalike.clone()).unwrap(), lookalike);
+        assert_eq!(decompress_chunk(vec![]).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_stream_decompressed_chunk() {
+        let bytes : Vec<u8> = (0..10000).map(|i| (i % 7) as u8).collect();
+        let compressed = compress_chunk(&bytes, DEFAULT_BLOCK_COMPRESSION_LEVEL).unwrap();
+
+        let mut streamed = vec![];
+        loop {
+            let offset = streamed.len() as u64;
+            let nw = stream_decompressed_chunk(&compressed[..], offset, 1000, &mut streamed).unwrap();
+            if nw == 0 {
+                break;
+            }
+            assert!(nw <= 1000);
+            assert_eq!(streamed.len() as u64, offset + nw);
+        }
+        assert_eq!(streamed, bytes);
+
+        // past the end, there's nothing left
+        let mut past_end = vec![];
+        assert_eq!(stream_decompressed_chunk(&compressed[..], bytes.len() as u64 + 1, 1000, &mut past_end).unwrap(), 0);
+        assert_eq!(past_end.len(), 0);
+    }
+
+    #[test]
+    fn test_migrate_chunk_store() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "test_migrate_chunk_store");
+        let burn_header_hash = BurnchainHeaderHash([1u8; 32]);
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+
+        // a stand-in block, and an invalid one
+        let mut block_bytes = vec![];
+        genesis.anchored_header.consensus_serialize(&mut block_bytes).unwrap();
+        let block_path = StacksChainState::get_block_path(&chainstate.blocks_path, &burn_header_hash, &BlockHeaderHash([2u8; 32])).unwrap();
+        fs::create_dir_all(Path::new(&block_path).parent().unwrap()).unwrap();
+        StacksChainState::atomic_chunk_write(&block_path, false, &block_bytes, None).unwrap();
+        StacksChainState::store_empty_block(&chainstate.blocks_path, &burn_header_hash, &BlockHeaderHash([3u8; 32])).unwrap();
+
+        let migration = StacksChainState::migrate_chunk_store(&chainstate.blocks_path, Some(DEFAULT_BLOCK_COMPRESSION_LEVEL)).unwrap();
+        assert_eq!(migration.num_rewritten, 1);
+        assert_eq!(migration.num_skipped, 1);
+        assert_eq!(migration.bytes_before, block_bytes.len() as u64);
+        let mut stored = vec![];
+        fs::File::open(&block_path).unwrap().read_to_end(&mut stored).unwrap();
+        assert!(is_compressed_chunk(&stored));
+        assert_eq!(StacksChainState::read_chunk(&block_path).unwrap(), block_bytes);
+        let header : StacksBlockHeader = StacksChainState::consensus_load(&block_path).unwrap();
+        assert_eq!(header, genesis.anchored_header);
+
+        // compressing again does nothing
+        let migration = StacksChainState::migrate_chunk_store(&chainstate.blocks_path, Some(DEFAULT_BLOCK_COMPRESSION_LEVEL)).unwrap();
+        assert_eq!(migration.num_rewritten, 0);
+
+        let migration = StacksChainState::migrate_chunk_store(&chainstate.blocks_path, None).unwrap();
+        assert_eq!(migration.num_rewritten, 1);
+        let mut stored = vec![];
+        fs::File::open(&block_path).unwrap().read_to_end(&mut stored).unwrap();
+        assert_eq!(stored, block_bytes);
+    }
+}
@@ -0,0 +1,258 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+
+use rusqlite::Row;
+use rusqlite::Connection;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::events::StacksTransactionReceipt;
+
+use vm::costs::ExecutionCost;
+use vm::types::{QualifiedContractIdentifier, StandardPrincipalData};
+
+use util::db::Error as db_error;
+use util::db::{
+    FromRow,
+    FromColumn,
+    query_rows,
+    query_count,
+    u64_to_sql,
+};
+
+/// What each contract cost to run in each processed block.  Created on open if missing; blocks
+/// processed before this table existed have no rows, just like blocks that ran no contracts.
+pub const CONTRACT_COSTS_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS contract_costs(
+        index_block_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        contract_id TEXT NOT NULL,
+        transactions INTEGER NOT NULL,
+        runtime INTEGER NOT NULL,
+        read_count INTEGER NOT NULL,
+        read_length INTEGER NOT NULL,
+        write_count INTEGER NOT NULL,
+        write_length INTEGER NOT NULL,
+
+        PRIMARY KEY(index_block_hash, contract_id)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS index_contract_costs_by_contract ON contract_costs(contract_id, block_height);
+    "#,
+];
+
+/// Blocks whose costs weren't counted when they were processed, because they're ancestors of a
+/// trusted checkpoint.  They have no contract costs rows, though they may have run contracts.
+pub const UNMETERED_BLOCKS_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS unmetered_blocks(
+        index_block_hash TEXT PRIMARY KEY NOT NULL
+    );
+    "#,
+];
+
+/// What a block's transactions spent running one contract: the transactions that called it or
+/// deployed it, and their execution cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractCost {
+    pub contract_id: QualifiedContractIdentifier,
+    pub transactions: u64,
+    pub cost: ExecutionCost,
+}
+
+impl FromRow<ContractCost> for ContractCost {
+    fn from_row<'a>(row: &'a Row) -> Result<ContractCost, db_error> {
+        let contract_id_text : String = row.get("contract_id");
+        let contract_id = QualifiedContractIdentifier::parse(&contract_id_text).map_err(|_e| db_error::ParseError)?;
+
+        Ok(ContractCost {
+            contract_id,
+            transactions: u64::from_column(row, "transactions")?,
+            cost: ExecutionCost {
+                runtime: u64::from_column(row, "runtime")?,
+                read_count: u64::from_column(row, "read_count")?,
+                read_length: u64::from_column(row, "read_length")?,
+                write_count: u64::from_column(row, "write_count")?,
+                write_length: u64::from_column(row, "write_length")?,
+            }
+        })
+    }
+}
+
+/// The contract a transaction's execution cost is charged to, if any: the one it calls, or the
+/// one it deploys
+pub fn charged_contract(tx: &StacksTransaction) -> Option<QualifiedContractIdentifier> {
+    match tx.payload {
+        TransactionPayload::ContractCall(ref contract_call) => Some(contract_call.to_clarity_contract_id()),
+        TransactionPayload::SmartContract(ref smart_contract) => Some(QualifiedContractIdentifier::new(StandardPrincipalData::from(tx.origin_address()), smart_contract.name.clone())),
+        _ => None
+    }
+}
+
+/// Add up what a block's transactions cost, per contract.  A contract call is charged to the
+/// contract it calls, including whatever that contract calls in turn.  The most expensive
+/// contracts (by runtime) come first.
+pub fn contract_costs(receipts: &[StacksTransactionReceipt]) -> Vec<ContractCost> {
+    let mut costs : HashMap<QualifiedContractIdentifier, ContractCost> = HashMap::new();
+    for receipt in receipts.iter() {
+        if let Some(contract_id) = charged_contract(&receipt.transaction) {
+            let entry = costs.entry(contract_id.clone())
+                .or_insert_with(|| ContractCost { contract_id, transactions: 0, cost: ExecutionCost::zero() });
+            entry.transactions += 1;
+            entry.cost.add(&receipt.execution_cost).expect("FATAL: block execution cost overflow");
+        }
+    }
+
+    let mut costs : Vec<ContractCost> = costs.into_iter().map(|(_, cost)| cost).collect();
+    costs.sort_by(|a, b| b.cost.runtime.cmp(&a.cost.runtime)
+                  .then_with(|| a.contract_id.to_string().cmp(&b.contract_id.to_string())));
+    costs
+}
+
+impl StacksChainState {
+    /// Store what each contract cost to run in a block.  The block is no longer unmetered, if it
+    /// was.
+    pub fn insert_contract_costs<'a>(tx: &mut StacksDBTx<'a>, index_block_hash: &StacksBlockId, block_height: u64, costs: &[ContractCost]) -> Result<(), Error> {
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        tx.execute("DELETE FROM unmetered_blocks WHERE index_block_hash = ?1", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        let block_height = u64_to_sql(block_height).map_err(Error::DBError)?;
+        for contract_cost in costs.iter() {
+            let args: &[&dyn ToSql] = &[index_block_hash, &block_height, &contract_cost.contract_id.to_string(),
+                                        &u64_to_sql(contract_cost.transactions).map_err(Error::DBError)?,
+                                        &u64_to_sql(contract_cost.cost.runtime).map_err(Error::DBError)?,
+                                        &u64_to_sql(contract_cost.cost.read_count).map_err(Error::DBError)?,
+                                        &u64_to_sql(contract_cost.cost.read_length).map_err(Error::DBError)?,
+                                        &u64_to_sql(contract_cost.cost.write_count).map_err(Error::DBError)?,
+                                        &u64_to_sql(contract_cost.cost.write_length).map_err(Error::DBError)?];
+            tx.execute("INSERT OR REPLACE INTO contract_costs (index_block_hash, block_height, contract_id, transactions, runtime, read_count, read_length, write_count, write_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)", args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+        Ok(())
+    }
+
+    /// Record that a block's costs weren't counted, so it has no contract costs
+    pub fn insert_unmetered_block<'a>(tx: &mut StacksDBTx<'a>, index_block_hash: &StacksBlockId) -> Result<(), Error> {
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        tx.execute("DELETE FROM contract_costs WHERE index_block_hash = ?1", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT OR REPLACE INTO unmetered_blocks (index_block_hash) VALUES (?1)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Were a block's costs left uncounted when it was processed?
+    pub fn is_block_unmetered(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<bool, Error> {
+        let sql = "SELECT COUNT(*) FROM unmetered_blocks WHERE index_block_hash = ?1".to_string();
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_count(conn, &sql, args)
+            .map(|count| count > 0)
+            .map_err(Error::DBError)
+    }
+
+    /// Get what each contract cost to run in a block, most expensive (by runtime) first
+    pub fn get_contract_costs(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<Vec<ContractCost>, Error> {
+        let sql = "SELECT * FROM contract_costs WHERE index_block_hash = ?1 ORDER BY runtime DESC, contract_id ASC".to_string();
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_rows::<ContractCost, _>(conn, &sql, args).map_err(Error::DBError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use util::strings::ContractName;
+    use vm::types::Value;
+    use chainstate::stacks::db::test::{instantiate_chainstate, make_tx};
+
+    fn make_call(contract_name: &str, runtime: u64) -> StacksTransactionReceipt {
+        let address = StacksAddress { version: 26, bytes: Hash160([1u8; 20]) };
+        let payload = TransactionPayload::new_contract_call(address, contract_name, "run", vec![]).unwrap();
+        StacksTransactionReceipt::from_contract_call(make_tx(payload), vec![], Value::okay_true(), 0, ExecutionCost::runtime(runtime))
+    }
+
+    #[test]
+    fn test_contract_costs() {
+        let deploy_tx = make_tx(TransactionPayload::new_smart_contract(&"deployed".to_string(), &"(+ 1 2)".to_string()).unwrap());
+        let deployed_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(deploy_tx.origin_address()), ContractName::try_from("deployed").unwrap());
+
+        let receipts = vec![
+            make_call("cheap", 10),
+            StacksTransactionReceipt::from_coinbase(make_tx(TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])))),
+            make_call("pricey", 100),
+            StacksTransactionReceipt::from_analysis_failure(deploy_tx, ExecutionCost::runtime(50)),
+            make_call("cheap", 15),
+        ];
+
+        // transactions that don't run a contract aren't charged to any
+        let costs = contract_costs(&receipts);
+        assert_eq!(costs.len(), 3);
+
+        assert_eq!(costs[0].contract_id.name.to_string(), "pricey");
+        assert_eq!(costs[0].transactions, 1);
+        assert_eq!(costs[0].cost, ExecutionCost::runtime(100));
+
+        assert_eq!(costs[1].contract_id, deployed_id);
+        assert_eq!(costs[1].cost, ExecutionCost::runtime(50));
+
+        assert_eq!(costs[2].contract_id.name.to_string(), "cheap");
+        assert_eq!(costs[2].transactions, 2);
+        assert_eq!(costs[2].cost, ExecutionCost::runtime(25));
+    }
+
+    #[test]
+    fn test_unmetered_blocks() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_unmetered_blocks");
+        let index_block_hash = StacksBlockId([0x11; 32]);
+        let costs = contract_costs(&vec![make_call("cheap", 10)]);
+
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::insert_contract_costs(&mut tx, &index_block_hash, 1, &costs).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(!StacksChainState::is_block_unmetered(&chainstate.headers_db, &index_block_hash).unwrap());
+
+        // marking a block unmetered drops its contract costs
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::insert_unmetered_block(&mut tx, &index_block_hash).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(StacksChainState::is_block_unmetered(&chainstate.headers_db, &index_block_hash).unwrap());
+        assert_eq!(StacksChainState::get_contract_costs(&chainstate.headers_db, &index_block_hash).unwrap(), vec![]);
+
+        // and storing its costs again, as reindexing does, makes it metered
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::insert_contract_costs(&mut tx, &index_block_hash, 1, &costs).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(!StacksChainState::is_block_unmetered(&chainstate.headers_db, &index_block_hash).unwrap());
+        assert_eq!(StacksChainState::get_contract_costs(&chainstate.headers_db, &index_block_hash).unwrap(), costs);
+    }
+}
@@ -55,8 +55,9 @@ use vm::contexts::{
     AssetMap
 };
 
-use vm::ast::build_ast;
-use vm::analysis::run_analysis;
+use vm::ast::{build_ast, ContractAST};
+use vm::analysis::{run_analysis, ContractAnalysis};
+use vm::costs::{ExecutionCost, LimitedCostTracker};
 use vm::types::{
     Value,
     AssetIdentifier
@@ -73,6 +74,18 @@ use vm::database::ClarityDatabase;
 
 use vm::contracts::Contract;
 
+/// What deploying a contract would produce, found without storing anything
+pub struct ContractDeployDryRun {
+    pub contract_analysis: ContractAnalysis,
+    /// what parsing and analyzing the contract cost
+    pub analysis_cost: ExecutionCost,
+    /// what the whole deploy would cost: the analysis, and running the contract's top-level
+    /// expressions.  Unknown if the name is taken.
+    pub deploy_cost: Option<ExecutionCost>,
+    /// whether the deployer already has a contract with this name, which makes a deploy invalid
+    pub name_collision: bool,
+}
+
 impl StacksChainState {
     pub fn get_contract<T: ClarityConnection>(clarity_tx: &mut T, contract_id: &QualifiedContractIdentifier) -> Result<Option<Contract>, Error> {
         clarity_tx.with_clarity_db_readonly(|ref mut db| {
@@ -93,4 +106,59 @@ impl StacksChainState {
             }
         }).map_err(Error::ClarityError)
     }
+
+    /// Run the checks that deploying a contract would: that it isn't deployed already, that it
+    /// parses, and that it passes analysis within `cost_limit`.  Nothing is stored.  Returns
+    /// the contract's analysis and what it cost, or why deploying it would fail.
+    pub fn analyze_contract<T: ClarityConnection>(clarity_tx: &mut T, contract_id: &QualifiedContractIdentifier, source: &str,
+                                                  cost_limit: ExecutionCost) -> Result<(ContractAnalysis, ExecutionCost), Error> {
+        if StacksChainState::get_contract(clarity_tx, contract_id)?.is_some() {
+            return Err(Error::InvalidStacksTransaction(format!("Duplicate contract '{}'", contract_id)));
+        }
+
+        let (_contract_ast, mut contract_analysis) = StacksChainState::parse_and_analyze_contract(clarity_tx, contract_id, source, cost_limit)?;
+        let cost = contract_analysis.take_contract_cost_tracker().get_total();
+        Ok((contract_analysis, cost))
+    }
+
+    /// Do everything deploying a contract would, within `cost_limit`: parse and analyze it, and
+    /// run its top-level expressions.  Nothing is stored.  A deployer that already has a
+    /// contract with that name gets a report with `name_collision` set, and no deploy cost.
+    pub fn dry_run_contract_deploy<T: ClarityConnection>(clarity_tx: &mut T, contract_id: &QualifiedContractIdentifier, source: &str,
+                                                         cost_limit: ExecutionCost) -> Result<ContractDeployDryRun, Error> {
+        let name_collision = StacksChainState::get_contract(clarity_tx, contract_id)?.is_some();
+
+        let (contract_ast, mut contract_analysis) = StacksChainState::parse_and_analyze_contract(clarity_tx, contract_id, source, cost_limit)?;
+        let cost_track = contract_analysis.take_contract_cost_tracker();
+        let analysis_cost = cost_track.get_total();
+
+        if name_collision {
+            return Ok(ContractDeployDryRun { contract_analysis, analysis_cost, deploy_cost: None, name_collision });
+        }
+
+        // the deploy's own writes are rolled back with the rest
+        let deploy_cost = clarity_tx.with_clarity_db_readonly_owned(|clarity_db| {
+            let mut vm_env = OwnedEnvironment::new_cost_limited(clarity_db, cost_track);
+            let result = vm_env.initialize_contract_from_ast(contract_id.clone(), &contract_ast, source);
+            let (db, cost_track) = vm_env.destruct()
+                .expect("Failed to recover database reference after initializing contract");
+            (result.map(|_| cost_track.get_total()), db)
+        }).map_err(|e| Error::ClarityError(e.into()))?;
+
+        Ok(ContractDeployDryRun { contract_analysis, analysis_cost, deploy_cost: Some(deploy_cost), name_collision })
+    }
+
+    fn parse_and_analyze_contract<T: ClarityConnection>(clarity_tx: &mut T, contract_id: &QualifiedContractIdentifier, source: &str,
+                                                        cost_limit: ExecutionCost) -> Result<(ContractAST, ContractAnalysis), Error> {
+        clarity_tx.with_analysis_db_readonly(|db| {
+            let mut cost_track = LimitedCostTracker::new(cost_limit);
+            let mut contract_ast = build_ast(contract_id, source, &mut cost_track)
+                .map_err(|e| Error::ClarityError(e.into()))?;
+
+            match run_analysis(contract_id, &mut contract_ast.expressions, db, false, cost_track) {
+                Ok(contract_analysis) => Ok((contract_ast, contract_analysis)),
+                Err((e, _cost_track)) => Err(Error::ClarityError(e.into()))
+            }
+        })
+    }
 }
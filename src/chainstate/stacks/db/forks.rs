@@ -0,0 +1,171 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::NO_PARAMS;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use core::FIRST_STACKS_BLOCK_HASH;
+
+use util::db::query_rows;
+
+/// A processed block that no other processed block builds on:  the tip of a Stacks fork
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForkTip {
+    /// the tip's header.  Its total work is the fork's weight:  the burn spent on it and its
+    /// length.
+    pub header_info: StacksHeaderInfo,
+    /// is this the canonical Stacks chain tip?
+    pub canonical: bool,
+    /// was the tip mined in a sortition on the canonical burn chain?  Only forks whose tips were
+    /// can be the canonical Stacks chain.
+    pub on_canonical_burn_chain: bool,
+    /// the last block this fork shares with the canonical Stacks chain
+    pub fork_point: StacksBlockId,
+    /// the height of that block
+    pub fork_point_height: u64,
+}
+
+impl ForkTip {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        self.header_info.index_block_hash()
+    }
+
+    /// How many blocks this fork has since it left the canonical chain
+    pub fn fork_length(&self) -> u64 {
+        self.header_info.block_height - self.fork_point_height
+    }
+}
+
+impl StacksChainState {
+    /// Get the tip of every Stacks fork the node has processed blocks in, with the canonical tip
+    /// first and then the rest from the highest down.  The canonical tip is the one the sortition
+    /// DB names:  the highest processed block mined on the canonical burn chain.  Reads only, but
+    /// looks up the parent of every processed block, so it takes a while on a long chain.
+    pub fn get_fork_tips(&mut self, burndb: &BurnDB) -> Result<Vec<ForkTip>, Error> {
+        let headers : Vec<StacksHeaderInfo> = query_rows(&self.headers_db, "SELECT * FROM block_headers ORDER BY block_height, index_block_hash", NO_PARAMS)
+            .map_err(Error::DBError)?;
+
+        // each block's parent, through the headers MARF
+        let mut parents : HashMap<StacksBlockId, (StacksBlockId, u64)> = HashMap::new();
+        {
+            let mut headers_tx = self.headers_tx_begin()?;
+            for header_info in headers.iter() {
+                if header_info.block_height == 0 {
+                    continue;
+                }
+                let index_block_hash = header_info.index_block_hash();
+                match headers_tx.get_ancestor_block_hash(header_info.block_height - 1, &index_block_hash).map_err(Error::DBError)? {
+                    Some(parent) => {
+                        parents.insert(index_block_hash, (parent, header_info.block_height - 1));
+                    },
+                    None => {
+                        warn!("Block {} at height {} has no parent in the headers MARF", &index_block_hash, header_info.block_height);
+                    }
+                }
+            }
+        }
+
+        let canonical_tip = {
+            let (burn_header_hash, block_hash) = BurnDB::get_canonical_stacks_chain_tip_hash(burndb.conn())?;
+            if block_hash == FIRST_STACKS_BLOCK_HASH {
+                StacksChainState::get_genesis_header_info(&self.headers_db)?.index_block_hash()
+            }
+            else {
+                StacksBlockHeader::make_index_block_hash(&burn_header_hash, &block_hash)
+            }
+        };
+
+        let mut canonical_chain = HashSet::new();
+        let mut cursor = Some(canonical_tip.clone());
+        while let Some(index_block_hash) = cursor {
+            cursor = parents.get(&index_block_hash).map(|(parent, _)| parent.clone());
+            canonical_chain.insert(index_block_hash);
+        }
+
+        let has_children : HashSet<StacksBlockId> = parents.values().map(|(parent, _)| parent.clone()).collect();
+        let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn())?;
+        let burn_conn = burndb.index_conn();
+
+        let mut ret = vec![];
+        for header_info in headers.into_iter() {
+            let index_block_hash = header_info.index_block_hash();
+            if has_children.contains(&index_block_hash) {
+                continue;
+            }
+
+            // walk back to the canonical chain.  A fork whose blocks' parents are missing from
+            // the headers MARF ends at its lowest block.
+            let (mut fork_point, mut fork_point_height) = (index_block_hash.clone(), header_info.block_height);
+            while !canonical_chain.contains(&fork_point) {
+                match parents.get(&fork_point) {
+                    Some((parent, parent_height)) => {
+                        fork_point = parent.clone();
+                        fork_point_height = *parent_height;
+                    },
+                    None => break
+                }
+            }
+
+            let on_canonical_burn_chain = BurnDB::get_ancestor_snapshot_by_hash(&burn_conn, &header_info.burn_header_hash, &burn_tip.burn_header_hash)?.is_some();
+
+            ret.push(ForkTip {
+                canonical: index_block_hash == canonical_tip,
+                on_canonical_burn_chain,
+                fork_point,
+                fork_point_height,
+                header_info,
+            });
+        }
+
+        ret.sort_by(|a, b| b.canonical.cmp(&a.canonical)
+                    .then(b.header_info.block_height.cmp(&a.header_info.block_height))
+                    .then(a.index_block_hash().cmp(&b.index_block_hash())));
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use burnchains::BurnchainHeaderHash;
+
+    #[test]
+    fn test_get_fork_tips_boot() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_get_fork_tips_boot");
+        let burndb = BurnDB::connect_test(0, &BurnchainHeaderHash([0u8; 32])).unwrap();
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+
+        // only the boot block, which is the canonical chain
+        let tips = chainstate.get_fork_tips(&burndb).unwrap();
+        assert_eq!(tips.len(), 1);
+        assert_eq!(tips[0].index_block_hash(), genesis.index_block_hash());
+        assert!(tips[0].canonical);
+        assert_eq!(tips[0].fork_point, genesis.index_block_hash());
+        assert_eq!(tips[0].fork_length(), 0);
+    }
+}
@@ -42,7 +42,9 @@ use util::db::{
     query_row,
     query_rows,
     query_row_columns,
-    query_count
+    query_count,
+    u64_to_sql,
+    hex_prefix_bounds
 };
 
 use core::FIRST_STACKS_BLOCK_HASH;
@@ -186,6 +188,26 @@ impl StacksChainState {
         Ok(rows.pop())
     }
 
+    /// Find up to `limit` headers, across all forks, whose block hash or index block hash starts
+    /// with the given lowercase hex prefix.
+    pub fn find_headers_by_hash_prefix(conn: &Connection, prefix: &str, limit: u64) -> Result<Vec<StacksHeaderInfo>, Error> {
+        let (lower, upper) = hex_prefix_bounds(prefix);
+        let args: &[&dyn ToSql] = &[&lower, &upper, &u64_to_sql(limit).map_err(Error::DBError)?];
+
+        let sql = "SELECT * FROM block_headers WHERE block_hash >= ?1 AND block_hash < ?2 ORDER BY block_hash LIMIT ?3".to_string();
+        let mut headers = query_rows::<StacksHeaderInfo, _>(conn, &sql, args).map_err(Error::DBError)?;
+
+        let sql = "SELECT * FROM block_headers WHERE index_block_hash >= ?1 AND index_block_hash < ?2 ORDER BY index_block_hash LIMIT ?3".to_string();
+        for header in query_rows::<StacksHeaderInfo, _>(conn, &sql, args).map_err(Error::DBError)?.into_iter() {
+            if !headers.iter().any(|h| h.index_block_hash() == header.index_block_hash()) {
+                headers.push(header);
+            }
+        }
+
+        headers.truncate(limit as usize);
+        Ok(headers)
+    }
+
     /// Get an ancestor block header
     pub fn get_tip_ancestor<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksHeaderInfo, height: u64) -> Result<Option<StacksHeaderInfo>, Error> {
         assert!(tip.block_height >= height);
@@ -211,6 +233,19 @@ impl StacksChainState {
         }
     }
 
+    /// Get the block headers at heights `start_height` through `end_height` (inclusive) in the
+    /// fork ending at the given index hash, in height order.  Stops early at the tip.
+    pub fn get_index_tip_ancestors<'a>(tx: &mut StacksDBTx<'a>, tip_index_hash: &StacksBlockId, start_height: u64, end_height: u64) -> Result<Vec<StacksHeaderInfo>, Error> {
+        let mut ancestors = vec![];
+        for height in start_height..=end_height {
+            match StacksChainState::get_index_tip_ancestor(tx, tip_index_hash, height)? {
+                Some(header_info) => ancestors.push(header_info),
+                None => break
+            }
+        }
+        Ok(ancestors)
+    }
+
     /// Get the genesis (boot code) block header
     pub fn get_genesis_header_info(conn: &Connection) -> Result<StacksHeaderInfo, Error> {
         // by construction, only one block can have height 0 in this DB
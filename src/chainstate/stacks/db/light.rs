@@ -0,0 +1,127 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::db::*;
+
+use util::db::Error as db_error;
+use util::db::{
+    query_count,
+    query_int,
+    u64_to_sql,
+};
+
+/// Whether this chainstate is light-synced, and from which height.  Created on open if missing;
+/// it has a row once light sync is turned on, and that row is never removed.
+pub const LIGHT_SYNC_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS light_sync(
+        -- blocks at or above this height were processed without being executed, so they have no Clarity state
+        block_height INTEGER NOT NULL
+    );
+    "#,
+];
+
+impl StacksChainState {
+    /// Has light sync ever been turned on for this chainstate?
+    pub fn is_light_sync(blocks_conn: &Connection) -> Result<bool, Error> {
+        let cnt = query_count(blocks_conn, "SELECT COUNT(*) FROM light_sync", NO_PARAMS).map_err(Error::DBError)?;
+        Ok(cnt > 0)
+    }
+
+    /// Is light sync on for this instance?  If so, there's no Clarity state at the chain tip to
+    /// check transactions against.
+    pub fn light_sync_enabled(&self) -> bool {
+        self.light_sync
+    }
+
+    /// The lowest height of the blocks processed without their state, if light sync was ever
+    /// turned on
+    pub fn get_light_sync_height(blocks_conn: &Connection) -> Result<Option<u64>, Error> {
+        if !StacksChainState::is_light_sync(blocks_conn)? {
+            return Ok(None);
+        }
+        let height = query_int(blocks_conn, &"SELECT MIN(block_height) FROM light_sync".to_string(), NO_PARAMS).map_err(Error::DBError)?;
+        Ok(Some(height as u64))
+    }
+
+    /// Turn on light sync:  from now on, blocks are checked against the burnchain and attached
+    /// to the headers, but not executed, so no Clarity state is kept for them.  This can't be
+    /// undone, since the chainstate can't execute blocks on top of state it doesn't have.
+    /// Archives can't be light-synced.  Doing this again only turns it on for this instance.
+    pub fn enable_light_sync(&mut self) -> Result<(), Error> {
+        if StacksChainState::is_archive(&self.blocks_db)? {
+            error!("Cannot turn on light sync: this chainstate is an archive");
+            return Err(Error::InvalidChainstateDB);
+        }
+
+        self.light_sync = true;
+        if StacksChainState::is_light_sync(&self.blocks_db)? {
+            return Ok(());
+        }
+
+        let highest_height = query_int(&self.headers_db, &"SELECT IFNULL(MAX(block_height), 0) FROM block_headers".to_string(), NO_PARAMS)
+            .map_err(Error::DBError)?;
+        let block_height = u64_to_sql((highest_height as u64) + 1).map_err(Error::DBError)?;
+
+        let tx = self.blocks_tx_begin()?;
+        tx.execute("INSERT INTO light_sync (block_height) VALUES (?1)", &[&block_height as &dyn ToSql])
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.commit().map_err(Error::DBError)?;
+
+        info!("Light sync is on from block height {}", block_height);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_enable_light_sync() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_enable_light_sync");
+        assert!(!StacksChainState::is_light_sync(&chainstate.blocks_db).unwrap());
+        assert_eq!(StacksChainState::get_light_sync_height(&chainstate.blocks_db).unwrap(), None);
+
+        // only the boot block has been processed
+        chainstate.enable_light_sync().unwrap();
+        assert!(StacksChainState::is_light_sync(&chainstate.blocks_db).unwrap());
+        assert_eq!(StacksChainState::get_light_sync_height(&chainstate.blocks_db).unwrap(), Some(1));
+
+        chainstate.enable_light_sync().unwrap();
+        assert_eq!(StacksChainState::get_light_sync_height(&chainstate.blocks_db).unwrap(), Some(1));
+
+        // a light-synced chainstate is missing state, so it can't be an archive
+        assert!(chainstate.enable_archive_mode().is_err());
+    }
+
+    #[test]
+    fn test_light_sync_archive() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_light_sync_archive");
+        chainstate.enable_archive_mode().unwrap();
+        assert!(chainstate.enable_light_sync().is_err());
+        assert!(!StacksChainState::is_light_sync(&chainstate.blocks_db).unwrap());
+    }
+}
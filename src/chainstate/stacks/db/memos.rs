@@ -0,0 +1,164 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rusqlite::Row;
+use rusqlite::types::ToSql;
+
+use burnchains::Txid;
+use burnchains::BurnchainHeaderHash;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+
+use util::db::Error as db_error;
+use util::db::{
+    FromRow,
+    FromColumn,
+    query_rows,
+};
+use util::hash::{hex_bytes, to_hex};
+
+use vm::types::PrincipalData;
+
+/// Mined STX transfers that carried a memo, so recipients (i.e. exchanges attributing deposits)
+/// can look them up without scanning every block.  Created on open if missing, so transfers
+/// mined before this table existed are not indexed.
+pub const TRANSFER_MEMOS_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS transfer_memos(
+        recipient TEXT NOT NULL,
+        memo TEXT NOT NULL,                 -- hex-encoded
+        txid TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        amount TEXT NOT NULL,               -- encodes u64
+        block_hash TEXT NOT NULL,
+        burn_header_hash TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,     -- NOTE: the same transfer can be mined in blocks on different forks
+        block_height INTEGER NOT NULL,
+
+        PRIMARY KEY(txid,index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS transfer_memos_recipient ON transfer_memos(recipient,memo);
+    "#,
+];
+
+/// A mined STX transfer with a memo
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferMemoEntry {
+    pub txid: Txid,
+    pub sender: StacksAddress,
+    pub recipient: PrincipalData,
+    pub amount: u64,
+    pub memo: TokenTransferMemo,
+    pub block_hash: BlockHeaderHash,
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub block_height: u64,
+}
+
+impl TransferMemoEntry {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        StacksBlockHeader::make_index_block_hash(&self.burn_header_hash, &self.block_hash)
+    }
+}
+
+impl FromRow<TransferMemoEntry> for TransferMemoEntry {
+    fn from_row<'a>(row: &'a Row) -> Result<TransferMemoEntry, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let sender_str : String = row.get("sender");
+        let recipient_str : String = row.get("recipient");
+        let amount_str : String = row.get("amount");
+        let memo_str : String = row.get("memo");
+        let block_hash = BlockHeaderHash::from_column(row, "block_hash")?;
+        let burn_header_hash = BurnchainHeaderHash::from_column(row, "burn_header_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+
+        let sender = StacksAddress::from_string(&sender_str).ok_or(db_error::ParseError)?;
+        let recipient = PrincipalData::parse(&recipient_str).map_err(|_e| db_error::ParseError)?;
+        let amount = amount_str.parse::<u64>().map_err(|_e| db_error::ParseError)?;
+        let memo_bytes = hex_bytes(&memo_str).map_err(|_e| db_error::ParseError)?;
+        let memo = TokenTransferMemo::from_bytes(&memo_bytes).ok_or(db_error::ParseError)?;
+
+        Ok(TransferMemoEntry {
+            txid,
+            sender,
+            recipient,
+            amount,
+            memo,
+            block_hash,
+            burn_header_hash,
+            block_height
+        })
+    }
+}
+
+impl StacksChainState {
+    /// Index the memos of the STX transfers mined in a block and the microblock stream it confirms.
+    /// Transfers with an all-zero memo are skipped.
+    pub fn insert_transfer_memos<'a>(tx: &mut StacksDBTx<'a>, tip_info: &StacksHeaderInfo, block: &StacksBlock, microblocks: &Vec<StacksMicroblock>) -> Result<(), Error> {
+        assert!(tip_info.block_height < (i64::max_value() as u64));
+
+        let block_hash = tip_info.anchored_header.block_hash();
+        let index_block_hash = tip_info.index_block_hash();
+        let mined_txs = block.txs.iter().chain(microblocks.iter().flat_map(|mblock| mblock.txs.iter()));
+
+        for mined_tx in mined_txs {
+            if let TransactionPayload::TokenTransfer(ref recipient, ref amount, ref memo) = mined_tx.payload {
+                if memo.0.iter().all(|b| *b == 0) {
+                    continue;
+                }
+
+                let args: &[&dyn ToSql] = &[
+                    &recipient.to_string(), &to_hex(memo.as_bytes()), &mined_tx.txid(), &mined_tx.origin_address().to_string(), &format!("{}", amount),
+                    &block_hash, &tip_info.burn_header_hash, &index_block_hash, &(tip_info.block_height as i64)];
+
+                tx.execute("INSERT OR REPLACE INTO transfer_memos \
+                            (recipient, memo, txid, sender, amount, block_hash, burn_header_hash, index_block_hash, block_height) \
+                            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)", args)
+                    .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the STX transfers to the given recipient with exactly the given memo, in the fork
+    /// ending at the given tip.  Returned in the order they were mined.
+    pub fn get_transfer_memos<'a>(tx: &mut StacksDBTx<'a>, tip_index_hash: &StacksBlockId, recipient: &PrincipalData, memo: &TokenTransferMemo) -> Result<Vec<TransferMemoEntry>, Error> {
+        let sql = "SELECT * FROM transfer_memos WHERE recipient = ?1 AND memo = ?2 ORDER BY block_height ASC".to_string();
+        let args: &[&dyn ToSql] = &[&recipient.to_string(), &to_hex(memo.as_bytes())];
+        let candidates = query_rows::<TransferMemoEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        let mut entries = vec![];
+        for entry in candidates.into_iter() {
+            // only keep the transfers mined in this fork
+            match StacksChainState::get_index_tip_ancestor(tx, tip_index_hash, entry.block_height)? {
+                Some(ancestor) => {
+                    if ancestor.index_block_hash() == entry.index_block_hash() {
+                        entries.push(entry);
+                    }
+                },
+                None => {}
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -18,10 +18,27 @@
 */
 
 pub mod accounts;
+pub mod address_index;
+pub mod archive;
+pub mod balances;
 pub mod blocks;
+pub mod check;
+pub mod compression;
 pub mod contracts;
+pub mod contract_costs;
+pub mod forks;
 pub mod headers;
+pub mod light;
+pub mod memos;
+pub mod nonce_cache;
+pub mod prune;
+pub mod receipts;
+pub mod reindex;
+pub mod replay;
+pub mod state_diff;
+pub mod supply;
 pub mod transactions;
+pub mod verify;
 
 use rusqlite::Transaction;
 use rusqlite::Connection;
@@ -34,6 +51,9 @@ use std::io;
 use std::io::prelude::*;
 use std::fmt;
 use std::fs;
+use std::cmp;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
 
 use core::*;
 
@@ -45,6 +65,8 @@ use chainstate::stacks::*;
 use chainstate::stacks::events::*;
 use chainstate::stacks::db::accounts::*;
 use chainstate::stacks::db::blocks::*;
+use chainstate::stacks::db::supply::StxSupply;
+use chainstate::stacks::db::verify::SignatureVerifier;
 use chainstate::stacks::index::{
     TrieHash,
     MARFValue
@@ -56,6 +78,7 @@ use chainstate::stacks::index::marf::{
 };
 
 use chainstate::stacks::index::storage::TrieFileStorage;
+use chainstate::stacks::index::backend::TrieBackend;
 
 use chainstate::burn::db::burndb::{
     BlockHeaderCache,
@@ -75,14 +98,17 @@ use util::db::{
     FromColumn,
     db_mkdirs,
     tx_begin_immediate,
-    tx_busy_handler,
+    sqlite_open,
+    SqlitePragmas,
 };
 
 use util::hash::to_hex;
+use util::migrations::{SchemaMigration, migrate_schema};
 
 use chainstate::burn::db::burndb::*;
 
 use net::Error as net_error;
+use net::BlockExportFormat;
 
 use vm::analysis::run_analysis;
 use vm::analysis::analysis_db::AnalysisDatabase;
@@ -104,6 +130,14 @@ use vm::representations::ClarityName;
 use vm::representations::ContractName;
 use vm::costs::ExecutionCost;
 
+use chainstate::stacks::db::address_index::ADDRESS_INDEX_SQL;
+use chainstate::stacks::db::balances::ACCOUNT_BALANCES_SQL;
+use chainstate::stacks::db::contract_costs::{CONTRACT_COSTS_SQL, UNMETERED_BLOCKS_SQL};
+use chainstate::stacks::db::memos::TRANSFER_MEMOS_SQL;
+use chainstate::stacks::db::nonce_cache::NONCE_CACHE_SQL;
+use chainstate::stacks::db::receipts::TRANSACTION_RECEIPTS_SQL;
+use chainstate::stacks::db::supply::STX_SUPPLY_SQL;
+
 use core::CHAINSTATE_VERSION;
 
 pub struct StacksChainState {
@@ -118,6 +152,38 @@ pub struct StacksChainState {
     pub root_path: String,
     cached_header_hashes: BlockHeaderCache,
     cached_miner_payments: MinerPaymentCache,
+    /// trusted blocks the canonical chain must pass through, in height order
+    checkpoints: Vec<StacksBlockCheckpoint>,
+    /// skip transaction signature checks in blocks the last checkpoint descends from
+    assume_valid: bool,
+    /// the blocks found so far that the last checkpoint descends from (including itself)
+    checkpoint_ancestors: Arc<HashSet<StacksBlockId>>,
+    /// the (burn header hash, block hash) of the next ancestor to look for in the staging
+    /// blocks, if the walk back from the last checkpoint stopped at a block we don't have yet
+    checkpoint_ancestors_next: Option<(BurnchainHeaderHash, BlockHeaderHash)>,
+    /// record the contract calls of processed transactions in their receipts
+    trace_contract_calls: bool,
+    /// how many threads check transaction signatures while a block is processed; 1 checks
+    /// them one at a time as each transaction is executed
+    block_validation_threads: usize,
+    /// directory to write the execution traces of processed blocks to, if any
+    execution_trace_path: Option<PathBuf>,
+    /// attach processed blocks to the headers without executing them
+    light_sync: bool,
+    /// index processed transactions by the principals they involve
+    index_addresses: bool,
+    /// zstd level to compress the blocks and microblock streams stored from now on at, if at all
+    block_compression_level: Option<i32>,
+    /// how the chainstate's sqlite databases are tuned, including by reopen()
+    sqlite_pragmas: SqlitePragmas,
+}
+
+/// A trusted point on the Stacks chain:  the only block this node will accept at `block_height`
+/// is the one with this index block hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StacksBlockCheckpoint {
+    pub block_height: u64,
+    pub index_block_hash: StacksBlockId,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -227,6 +293,9 @@ pub type StacksDBTx<'a> = IndexDBTx<'a, (), StacksBlockId>;
 pub struct BlocksDBTx<'a> {
     pub tx: DBTx<'a>,
     pub blocks_path: String,
+    /// zstd level to compress the blocks and microblock streams stored in the transaction at, if
+    /// at all
+    pub block_compression_level: Option<i32>,
 }
 
 impl<'a> Deref for BlocksDBTx<'a> {
@@ -243,16 +312,21 @@ impl<'a> DerefMut for BlocksDBTx<'a> {
 }
 
 impl<'a> BlocksDBTx<'a> {
-    pub fn new(tx: DBTx, blocks_path: String) -> BlocksDBTx {
+    pub fn new(tx: DBTx, blocks_path: String, block_compression_level: Option<i32>) -> BlocksDBTx {
         BlocksDBTx {
             tx,
-            blocks_path
+            blocks_path,
+            block_compression_level
         }
     }
 
     pub fn get_blocks_path(&self) -> &String {
         &self.blocks_path
     }
+
+    pub fn get_block_compression_level(&self) -> Option<i32> {
+        self.block_compression_level
+    }
     
     pub fn commit(self) -> Result<(), db_error> {
         self.tx.commit().map_err(db_error::SqliteError)
@@ -261,7 +335,14 @@ impl<'a> BlocksDBTx<'a> {
 
 pub struct ClarityTx<'a> {
     block: ClarityBlockConnection<'a>,
-    pub config: DBConfig
+    pub config: DBConfig,
+    /// if set, transaction signatures are not checked and execution costs are not held to the
+    /// block limit, because this block is an ancestor of a trusted checkpoint
+    pub assume_valid: bool,
+    /// if set, the contract calls each transaction makes are recorded in its receipt
+    pub trace_contract_calls: bool,
+    /// if set, transaction signatures that this has already checked are not checked again
+    pub signatures: Option<SignatureVerifier>,
 }
 
 impl ClarityConnection for ClarityTx<'_> {
@@ -338,7 +419,19 @@ pub struct BlockStreamData {
     // used only for microblocks
     is_microblock: bool,
     seq: u16,
-    in_staging: bool
+    in_staging: bool,
+
+    // used only for block exports
+    export: Option<BlockExportCursor>
+}
+
+/// The blocks left to send in a block export.  `offset` in the BlockStreamData is the offset into
+/// `buf`.
+#[derive(Debug, PartialEq, Clone)]
+struct BlockExportCursor {
+    headers: VecDeque<StacksHeaderInfo>,    // headers of the blocks not yet loaded, in height order
+    format: BlockExportFormat,
+    buf: Vec<u8>                            // the encoded block being sent
 }
 
 // TODO: write code to populate the microblock_headers table too!
@@ -444,6 +537,63 @@ const STACKS_CHAIN_STATE_SQL : &'static [&'static str]= &[
     )"#
 ];
 
+/// Changes to the headers DB's schema, applied when it's opened (see util::migrations).  Add a
+/// migration here instead of changing STACKS_CHAIN_STATE_SQL.  These are for changes an existing
+/// chainstate can be brought forward through; a change that needs the chainstate to be rebuilt
+/// bumps CHAINSTATE_VERSION instead, which the headers DB's db_config is checked against.
+/// Headers DBs from before migrations were kept track of may already have some of these tables,
+/// so they're created only if they aren't there.
+pub const HEADERS_DB_MIGRATIONS : &'static [SchemaMigration] = &[
+    SchemaMigration {
+        version: 1,
+        description: "add the transfer memo index",
+        up: TRANSFER_MEMOS_SQL,
+        down: Some(&["DROP TABLE transfer_memos;"]),
+    },
+    SchemaMigration {
+        version: 2,
+        description: "add the transaction receipt index",
+        up: TRANSACTION_RECEIPTS_SQL,
+        down: Some(&["DROP TABLE transaction_receipts; DROP TABLE transaction_receipt_contracts;"]),
+    },
+    SchemaMigration {
+        version: 3,
+        description: "add the STX supply table",
+        up: STX_SUPPLY_SQL,
+        down: Some(&["DROP TABLE stx_supply;"]),
+    },
+    SchemaMigration {
+        version: 4,
+        description: "add the contract costs table",
+        up: CONTRACT_COSTS_SQL,
+        down: Some(&["DROP TABLE contract_costs;"]),
+    },
+    SchemaMigration {
+        version: 5,
+        description: "add the nonce cache",
+        up: NONCE_CACHE_SQL,
+        down: Some(&["DROP TABLE nonce_cache; DROP TABLE nonce_cache_tip;"]),
+    },
+    SchemaMigration {
+        version: 6,
+        description: "add the account balance index",
+        up: ACCOUNT_BALANCES_SQL,
+        down: Some(&["DROP TABLE account_balances; DROP TABLE account_balance_blocks; DROP TABLE latest_balances; DROP TABLE latest_balances_tip;"]),
+    },
+    SchemaMigration {
+        version: 7,
+        description: "add the address index",
+        up: ADDRESS_INDEX_SQL,
+        down: Some(&["DROP TABLE address_transactions; DROP TABLE address_index_blocks;"]),
+    },
+    SchemaMigration {
+        version: 8,
+        description: "add the unmetered blocks table",
+        up: UNMETERED_BLOCKS_SQL,
+        down: Some(&["DROP TABLE unmetered_blocks;"]),
+    },
+];
+
 /// Built-in "system-level" smart contracts that are there from the beginning.
 /// Includes BNS and the miner trust fund.
 #[cfg(test)]
@@ -503,7 +653,7 @@ pub const MINER_FEE_MINIMUM_BLOCK_USAGE : u64 = 80;         // miner must share
 pub const MINER_FEE_WINDOW : u64 = 24;                      // number of blocks (B) used to smooth over the fraction of tx fees they share from anchored blocks
 
 impl StacksChainState {
-    fn instantiate_headers_db(conn: &mut DBConn, mainnet: bool, chain_id: u32, marf_path: &str) -> Result<(), Error> {
+    fn instantiate_headers_db(conn: &mut DBConn, mainnet: bool, chain_id: u32, marf_path: &str, pragmas: &SqlitePragmas) -> Result<(), Error> {
         let tx = tx_begin_immediate(conn)?;
         
         for cmd in STACKS_CHAIN_STATE_SQL {
@@ -513,7 +663,7 @@ impl StacksChainState {
         tx.execute("INSERT INTO db_config (version,mainnet,chain_id) VALUES (?1,?2,?3)", &[&CHAINSTATE_VERSION, &(if mainnet { 1 } else { 0 }) as &dyn ToSql, &chain_id as &dyn ToSql])
             .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
-        let mut marf = StacksChainState::open_index(marf_path, None)?;
+        let mut marf = StacksChainState::open_index(marf_path, None, pragmas)?;
         let mut dbtx = StacksDBTx::new(tx, &mut marf, ());
         
         dbtx.instantiate_index().map_err(Error::DBError)?;
@@ -521,7 +671,7 @@ impl StacksChainState {
         Ok(())
     }
     
-    fn open_headers_db(mainnet: bool, chain_id: u32, headers_path: &str, index_path: &str) -> Result<DBConn, Error> {
+    fn open_headers_db(mainnet: bool, chain_id: u32, headers_path: &str, index_path: &str, pragmas: &SqlitePragmas) -> Result<DBConn, Error> {
         let mut create_flag = false;
         let open_flags =
             if fs::metadata(headers_path).is_err() {
@@ -534,12 +684,11 @@ impl StacksChainState {
                 OpenFlags::SQLITE_OPEN_READ_WRITE
             };
 
-        let mut conn = DBConn::open_with_flags(headers_path, open_flags).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        let mut conn = sqlite_open(headers_path, open_flags, Some(pragmas)).map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
         if create_flag {
             // instantiate!
-            StacksChainState::instantiate_headers_db(&mut conn, mainnet, chain_id, index_path)?;
+            StacksChainState::instantiate_headers_db(&mut conn, mainnet, chain_id, index_path, pragmas)?;
         }
         else {
             // sanity check
@@ -562,14 +711,17 @@ impl StacksChainState {
                 error!("Invalid chain ID: expected {}, got {}", chain_id, db_config.chain_id);
                 return Err(Error::InvalidChainstateDB);
             }
+
+            StacksChainState::upgrade_legacy_transaction_receipts(&mut conn)?;
         }
 
+        migrate_schema(&mut conn, HEADERS_DB_MIGRATIONS, None, false).map_err(Error::DBError)?;
         Ok(conn)
     }
     
-    pub fn open_index(marf_path: &str, miner_tip: Option<&StacksBlockId>) -> Result<MARF<StacksBlockId>, Error> {
+    pub fn open_index(marf_path: &str, miner_tip: Option<&StacksBlockId>, pragmas: &SqlitePragmas) -> Result<MARF<StacksBlockId>, Error> {
         test_debug!("Open MARF index at {}, set miner tip = {:?}", marf_path, miner_tip);
-        let marf = MARF::from_path(marf_path, miner_tip).map_err(|e| Error::DBError(db_error::IndexError(e)))?;
+        let marf = MARF::from_path_with_backend(marf_path, miner_tip, TrieBackend::Sqlite, pragmas).map_err(|e| Error::DBError(db_error::IndexError(e)))?;
         Ok(marf)
     }
 
@@ -624,13 +776,18 @@ impl StacksChainState {
             signature: MessageSignature::empty()
         }));
 
+        // the boot block's STX are the initial balances
+        let mut initial_balances_total = 0u128;
+
         let mut boot_code_account = StacksAccount {
             principal: PrincipalData::Standard(StandardPrincipalData::from(boot_code_address.clone())),
             nonce: 0,
             stx_balance: 0
         };
 
-        {
+        // the boot block's accounts, for the balance index
+        let mut boot_principals = vec![boot_code_account.principal.clone()];
+        let boot_accounts = {
             let mut clarity_tx = chainstate.block_begin(&BURNCHAIN_BOOT_BLOCK_HASH, &BOOT_BLOCK_HASH, &FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH);
             for i in 0..STACKS_BOOT_CODE.len() {
                 let smart_contract = TransactionPayload::SmartContract(
@@ -668,6 +825,10 @@ impl StacksChainState {
 
             if let Some(initial_balances) = initial_balances {
                 for (address, amount) in initial_balances {
+                    initial_balances_total += amount as u128;
+                    if !boot_principals.contains(&address) {
+                        boot_principals.push(address.clone());
+                    }
                     clarity_tx.connection().as_transaction(|clarity| {
                         StacksChainState::account_credit(clarity, &address, amount)
                     })
@@ -676,8 +837,11 @@ impl StacksChainState {
 
             f(&mut clarity_tx);
 
+            let boot_accounts = StacksChainState::get_accounts(&mut clarity_tx, &boot_principals);
+
             clarity_tx.commit_to_block(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH);
-        }
+            boot_accounts
+        };
         
         {
             // add a block header entry for the boot code
@@ -697,6 +861,11 @@ impl StacksChainState {
             let first_tip_info = StacksHeaderInfo::genesis_block_header_info(first_root_hash);
 
             StacksChainState::insert_stacks_block_header(&mut headers_tx, &first_tip_info)?;
+
+            StacksChainState::insert_stx_supply(&mut headers_tx, &first_index_hash, &StxSupply::genesis(initial_balances_total))?;
+
+            StacksChainState::insert_account_balances(&mut headers_tx, None, &first_index_hash, 0, &boot_accounts)?;
+
             headers_tx.commit()
                 .map_err(Error::DBError)?;
         }
@@ -712,13 +881,13 @@ impl StacksChainState {
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<StacksChainState, Error> {
-        StacksChainState::open(self.mainnet, self.chain_id, &self.root_path)
+        StacksChainState::open_with_pragmas(self.mainnet, self.chain_id, &self.root_path, ExecutionCost::max_value(), &self.sqlite_pragmas)
     }
     
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters, but with a block limit
     pub fn reopen_limited(&self, budget: ExecutionCost) -> Result<StacksChainState, Error> {
-        StacksChainState::open_and_exec(self.mainnet, self.chain_id, &self.root_path, None, |_| {}, budget)
+        StacksChainState::open_with_pragmas(self.mainnet, self.chain_id, &self.root_path, budget, &self.sqlite_pragmas)
     }
 
     pub fn open_testnet<F>(chain_id: u32, path_str: &str, initial_balances: Option<Vec<(PrincipalData, u64)>>,
@@ -731,9 +900,30 @@ impl StacksChainState {
         StacksChainState::open_and_exec(mainnet, chain_id, path_str, None, |_| {}, block_limit)
     }
 
+    /// Like open_with_block_limit(), but the chainstate's sqlite databases are tuned with
+    /// `sqlite_pragmas`
+    pub fn open_with_pragmas(mainnet: bool, chain_id: u32, path_str: &str, block_limit: ExecutionCost,
+                             sqlite_pragmas: &SqlitePragmas) -> Result<StacksChainState, Error> {
+        StacksChainState::open_and_exec_with_marf_backend(mainnet, chain_id, path_str, None, |_| {}, block_limit,
+                                                          TrieBackend::Sqlite, sqlite_pragmas)
+    }
+
     pub fn open_and_exec<F>(mainnet: bool, chain_id: u32, path_str: &str,
                             initial_balances: Option<Vec<(PrincipalData, u64)>>,
                             in_boot_block: F, block_limit: ExecutionCost) -> Result<StacksChainState, Error> 
+    where F: FnOnce(&mut ClarityTx) -> () {
+        StacksChainState::open_and_exec_with_marf_backend(mainnet, chain_id, path_str, initial_balances, in_boot_block, block_limit,
+                                                          TrieBackend::Sqlite, &SqlitePragmas::default())
+    }
+
+    /// Like open_and_exec(), but if the chainstate is new, its Clarity MARF is created with
+    /// `marf_backend`.  An existing Clarity MARF is opened with the backend it was created with.
+    /// The chainstate's sqlite databases are tuned with `sqlite_pragmas`.
+    pub fn open_and_exec_with_marf_backend<F>(mainnet: bool, chain_id: u32, path_str: &str,
+                                              initial_balances: Option<Vec<(PrincipalData, u64)>>,
+                                              in_boot_block: F, block_limit: ExecutionCost,
+                                              marf_backend: TrieBackend,
+                                              sqlite_pragmas: &SqlitePragmas) -> Result<StacksChainState, Error> 
     where F: FnOnce(&mut ClarityTx) -> () {
         let mut path = PathBuf::from(path_str);
 
@@ -784,12 +974,12 @@ impl StacksChainState {
             Err(_) => false
         };
 
-        let headers_db = StacksChainState::open_headers_db(mainnet, chain_id, &headers_db_path, &header_index_root)?;
-        let blocks_db = StacksChainState::open_blocks_db(&blocks_db_path)?;
+        let headers_db = StacksChainState::open_headers_db(mainnet, chain_id, &headers_db_path, &header_index_root, sqlite_pragmas)?;
+        let blocks_db = StacksChainState::open_blocks_db(&blocks_db_path, sqlite_pragmas)?;
 
-        let headers_state_index = StacksChainState::open_index(&header_index_root, None)?;
+        let headers_state_index = StacksChainState::open_index(&header_index_root, None, sqlite_pragmas)?;
 
-        let vm_state = MarfedKV::open(&clarity_state_index_root, Some(&StacksBlockHeader::make_index_block_hash(&MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH)))
+        let vm_state = MarfedKV::open_with_backend(&clarity_state_index_root, Some(&StacksBlockHeader::make_index_block_hash(&MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH)), marf_backend, sqlite_pragmas)
             .map_err(|e| Error::ClarityError(e.into()))?;
 
         let clarity_state = ClarityInstance::new(vm_state, block_limit);
@@ -806,6 +996,17 @@ impl StacksChainState {
             root_path: path_str.to_string(),
             cached_header_hashes: BlockHeaderCache::new(),
             cached_miner_payments: MinerPaymentCache::new(),
+            checkpoints: vec![],
+            assume_valid: false,
+            checkpoint_ancestors: Arc::new(HashSet::new()),
+            checkpoint_ancestors_next: None,
+            trace_contract_calls: false,
+            block_validation_threads: 1,
+            execution_trace_path: None,
+            light_sync: false,
+            index_addresses: false,
+            block_compression_level: None,
+            sqlite_pragmas: sqlite_pragmas.clone(),
         };
 
         if !index_exists {
@@ -823,6 +1024,64 @@ impl StacksChainState {
         }
     }
 
+    /// Set the trusted checkpoints the chain must pass through.  With `assume_valid`, the
+    /// highest checkpoint and the blocks it descends from are processed without checking their
+    /// transactions' signatures, or counting their execution costs; they're stored as unmetered.
+    /// Ancestry is only known once the checkpointed block has been downloaded, so blocks
+    /// processed before then are fully validated.  Only use checkpoints from a source you trust.
+    pub fn set_checkpoints(&mut self, mut checkpoints: Vec<StacksBlockCheckpoint>, assume_valid: bool) {
+        checkpoints.sort_by_key(|checkpoint| checkpoint.block_height);
+        self.checkpoints = checkpoints;
+        self.assume_valid = assume_valid;
+        self.checkpoint_ancestors = Arc::new(HashSet::new());
+        self.checkpoint_ancestors_next = None;
+    }
+
+    /// Record the public function calls each processed transaction makes, and the calls those
+    /// make, in its receipt
+    pub fn set_contract_call_tracing(&mut self, trace_contract_calls: bool) {
+        self.trace_contract_calls = trace_contract_calls;
+    }
+
+    /// Check the transaction signatures of each processed block on this many threads, while its
+    /// transactions are executed.  Execution itself stays sequential.  1 (the default) checks
+    /// each signature just before its transaction is executed.
+    pub fn set_block_validation_threads(&mut self, num_threads: usize) {
+        self.block_validation_threads = cmp::max(num_threads, 1);
+    }
+
+    /// Compress the blocks and microblock streams this chainstate stores in the chunk store from
+    /// now on at this zstd level, or store them uncompressed if None (the default).  Either way,
+    /// both kinds of file are read.  Needs the `block_compression` feature to compress.
+    pub fn set_block_compression_level(&mut self, level: Option<i32>) {
+        self.block_compression_level = level;
+    }
+
+    /// Keep up to this many bytes of trie nodes in RAM for each of this chainstate's MARFs, the
+    /// headers MARF and the Clarity MARF.  0 (the default) turns the caches off.
+    pub fn set_marf_node_cache_size(&mut self, max_bytes: usize) {
+        self.headers_state_index.set_node_cache_size(max_bytes);
+        self.clarity_state.get_marf().set_node_cache_size(max_bytes);
+    }
+
+    /// Write the execution trace of each processed block to `<path>/<index block hash>.json`:
+    /// what its transactions read from and wrote to the chain state, with their results,
+    /// events and costs.  Nothing is written if `path` is None.
+    pub fn set_execution_trace_path(&mut self, path: Option<PathBuf>) {
+        self.execution_trace_path = path;
+    }
+
+    /// Index the transactions of each processed block by the principals they involve, for
+    /// get_address_transactions().  Blocks processed while this is off aren't indexed.
+    pub fn set_address_indexing(&mut self, index_addresses: bool) {
+        self.index_addresses = index_addresses;
+    }
+
+    /// Get the trusted checkpoints, in height order
+    pub fn get_checkpoints(&self) -> &Vec<StacksBlockCheckpoint> {
+        &self.checkpoints
+    }
+
     /// Get stacks header hashes cache reference
     pub fn get_block_header_cache(&self) -> &BlockHeaderCache {
         &self.cached_header_hashes
@@ -842,7 +1101,7 @@ impl StacksChainState {
     /// Begin a transaction against our staging block index DB.
     pub fn blocks_tx_begin<'a>(&'a mut self) -> Result<BlocksDBTx<'a>, Error> {
         let tx = tx_begin_immediate(&mut self.blocks_db)?;
-        Ok(BlocksDBTx::new(tx, self.blocks_path.clone()))
+        Ok(BlocksDBTx::new(tx, self.blocks_path.clone(), self.block_compression_level))
     }
 
     /// Simultaneously begin a transaction against both the headers and blocks.
@@ -855,7 +1114,7 @@ impl StacksChainState {
         let blocks_path = self.blocks_path.clone();
         let clarity_instance = &mut self.clarity_state;
         let headers_tx = StacksDBTx::new(headers_inner_tx, &mut self.headers_state_index, ());
-        let blocks_tx = BlocksDBTx::new(blocks_inner_tx, blocks_path);
+        let blocks_tx = BlocksDBTx::new(blocks_inner_tx, blocks_path, self.block_compression_level);
 
         let chainstate_tx = ChainstateTx {
             config: config,
@@ -899,6 +1158,11 @@ impl StacksChainState {
         self.clarity_state.read_only_connection(&index_block, &self.headers_db)
     }
 
+    /// The execution cost limit of each block
+    pub fn block_limit(&self) -> ExecutionCost {
+        self.clarity_state.block_limit()
+    }
+
     pub fn with_read_only_clarity_tx<F, R>(&mut self, parent_burn_hash: &BurnchainHeaderHash, parent_block: &BlockHeaderHash, to_do: F) -> R
     where F: FnOnce(&mut ClarityReadOnlyConnection) -> R {
         let mut conn = self.begin_read_only_clarity_tx(parent_burn_hash, parent_block);
@@ -947,7 +1211,10 @@ impl StacksChainState {
         test_debug!("Got clarity TX!");
         ClarityTx {
             block: inner_clarity_tx,
-            config: conf
+            config: conf,
+            assume_valid: false,
+            trace_contract_calls: false,
+            signatures: None,
         }
     }
 
@@ -1074,6 +1341,18 @@ pub mod test {
         StacksChainState::open(mainnet, chain_id, &path).unwrap()
     }
 
+    /// An unsigned testnet transaction with the given payload, from a single-sig account
+    pub fn make_tx(payload: TransactionPayload) -> StacksTransaction {
+        let privk = StacksPrivateKey::from_hex("59e4d5e18351d6027a37920efe53c2f1cbadc50dca7d77169b7291dff936ed6d01").unwrap();
+        make_tx_with_privk(&privk, payload)
+    }
+
+    /// An unsigned testnet transaction with the given payload, from the given key's single-sig account
+    pub fn make_tx_with_privk(privk: &StacksPrivateKey, payload: TransactionPayload) -> StacksTransaction {
+        let auth = TransactionAuth::from_p2pkh(privk).unwrap();
+        StacksTransaction::new(TransactionVersion::Testnet, auth, payload)
+    }
+
     pub fn chainstate_path(test_name: &str) -> String {
         format!("/tmp/blockstack-test-chainstate-{}", test_name)
     }
@@ -0,0 +1,249 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::db::transactions::TransactionNonceMismatch;
+
+use util::db::Error as db_error;
+use util::db::{
+    query_row,
+    query_row_columns,
+    u64_to_sql,
+};
+
+/// The account nonces as of one chain tip, so mempool admission can check a transaction's
+/// nonces without a MARF lookup.  Kept up to date as blocks that extend the tip are processed,
+/// and started over at a block that overtakes it on another fork.  Only accounts that sent or
+/// sponsored a transaction since then are in it; the rest are looked up in the MARF.  Created
+/// on open if missing.
+pub const NONCE_CACHE_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS nonce_cache(
+        address TEXT NOT NULL,
+        nonce INTEGER NOT NULL,
+
+        PRIMARY KEY(address)
+    );
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS nonce_cache_tip(
+        index_block_hash TEXT NOT NULL,     -- the nonces in nonce_cache are as of this block
+        block_height INTEGER NOT NULL
+    );
+    "#,
+];
+
+impl StacksChainState {
+    /// Get the block the nonce cache is as of, and its height
+    pub fn get_nonce_cache_tip(conn: &Connection) -> Result<Option<(StacksBlockId, u64)>, Error> {
+        let mut tips = query_row_columns::<StacksBlockId, _>(conn, &"SELECT index_block_hash FROM nonce_cache_tip".to_string(), NO_PARAMS, "index_block_hash")
+            .map_err(Error::DBError)?;
+        let height = query_row::<u64, _>(conn, "SELECT block_height FROM nonce_cache_tip", NO_PARAMS)
+            .map_err(Error::DBError)?;
+        Ok(tips.pop().and_then(|tip| height.map(|height| (tip, height))))
+    }
+
+    /// Get an account's cached nonce as of the given tip, if the cache is as of that tip and has it
+    pub fn get_cached_nonce(conn: &Connection, tip: &StacksBlockId, address: &StacksAddress) -> Result<Option<u64>, Error> {
+        match StacksChainState::get_nonce_cache_tip(conn)? {
+            Some((cache_tip, _)) if cache_tip == *tip => {},
+            _ => return Ok(None)
+        }
+        let args: &[&dyn ToSql] = &[&address.to_string()];
+        query_row::<u64, _>(conn, "SELECT nonce FROM nonce_cache WHERE address = ?1", args)
+            .map_err(Error::DBError)
+    }
+
+    /// The nonces the senders and sponsors of a block's transactions (and those of the microblock
+    /// stream it confirms) have once it's processed.  Every mined transaction bumps its origin's
+    /// nonce, and its sponsor's if it has one, so each account's next nonce is one past the last
+    /// it used.
+    fn mined_nonces(block: &StacksBlock, microblocks: &Vec<StacksMicroblock>) -> HashMap<StacksAddress, u64> {
+        let mut nonces = HashMap::new();
+        let mined_txs = microblocks.iter().flat_map(|mblock| mblock.txs.iter()).chain(block.txs.iter());
+        for mined_tx in mined_txs {
+            let mut spenders = vec![(mined_tx.origin_address(), mined_tx.get_origin().nonce())];
+            if let Some(sponsor_address) = mined_tx.sponsor_address() {
+                spenders.push((sponsor_address, mined_tx.get_payer().nonce()));
+            }
+            for (address, nonce) in spenders.into_iter() {
+                let next_nonce = nonce.saturating_add(1);
+                let entry = nonces.entry(address).or_insert(next_nonce);
+                if *entry < next_nonce {
+                    *entry = next_nonce;
+                }
+            }
+        }
+        nonces
+    }
+
+    /// Bring the nonce cache up to a newly-processed block.  If the block extends the cache's
+    /// tip, the nonces its transactions used are bumped; if it's on another fork and higher than
+    /// the tip, the cache starts over from it.  Blocks on forks that are no higher are left out.
+    pub fn update_nonce_cache<'a>(tx: &mut StacksDBTx<'a>, parent_index_block_hash: &StacksBlockId, new_tip: &StacksBlockId, new_tip_height: u64,
+                                  block: &StacksBlock, microblocks: &Vec<StacksMicroblock>) -> Result<(), Error> {
+        match StacksChainState::get_nonce_cache_tip(tx)? {
+            Some((cache_tip, _)) if cache_tip == *parent_index_block_hash => {},
+            Some((_, cache_tip_height)) if new_tip_height <= cache_tip_height => {
+                return Ok(());
+            },
+            _ => {
+                tx.execute("DELETE FROM nonce_cache", NO_PARAMS)
+                    .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+            }
+        }
+
+        for (address, nonce) in StacksChainState::mined_nonces(block, microblocks).into_iter() {
+            let args: &[&dyn ToSql] = &[&address.to_string(), &u64_to_sql(nonce).map_err(Error::DBError)?];
+            tx.execute("INSERT OR REPLACE INTO nonce_cache (address, nonce) VALUES (?1, ?2)", args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+
+        let args: &[&dyn ToSql] = &[new_tip, &u64_to_sql(new_tip_height).map_err(Error::DBError)?];
+        tx.execute("DELETE FROM nonce_cache_tip", NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT INTO nonce_cache_tip (index_block_hash, block_height) VALUES (?1, ?2)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Check a transaction's nonces against the ones cached as of the given tip.  Only a nonce
+    /// that's cached and different is a mismatch; the rest are left for the MARF lookup.
+    pub fn check_cached_nonces(conn: &Connection, tip: &StacksBlockId, tx: &StacksTransaction) -> Result<(), TransactionNonceMismatch> {
+        let mut spenders = vec![(tx.origin_address(), tx.get_origin().nonce(), true)];
+        if let Some(sponsor_address) = tx.sponsor_address() {
+            spenders.push((sponsor_address, tx.get_payer().nonce(), false));
+        }
+        for (address, nonce, is_origin) in spenders.into_iter() {
+            let cached_nonce = match StacksChainState::get_cached_nonce(conn, tip, &address) {
+                Ok(cached_nonce) => cached_nonce,
+                Err(e) => {
+                    warn!("Failed to read the nonce cache: {:?}", &e);
+                    None
+                }
+            };
+            if let Some(expected) = cached_nonce {
+                if expected != nonce {
+                    return Err(TransactionNonceMismatch { expected,
+                                                          actual: nonce,
+                                                          txid: tx.txid(),
+                                                          principal: address.into(),
+                                                          is_origin });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Cache the nonces of a transaction that was just admitted to the mempool against the given
+    /// tip, which matched its senders' nonces there.  Nothing is cached if the cache has since
+    /// moved on from the tip.
+    pub fn cache_admitted_nonces(conn: &Connection, tip: &StacksBlockId, tx: &StacksTransaction) -> Result<(), Error> {
+        let mut spenders = vec![(tx.origin_address(), tx.get_origin().nonce())];
+        if let Some(sponsor_address) = tx.sponsor_address() {
+            spenders.push((sponsor_address, tx.get_payer().nonce()));
+        }
+        for (address, nonce) in spenders.into_iter() {
+            let args: &[&dyn ToSql] = &[&address.to_string(), &u64_to_sql(nonce).map_err(Error::DBError)?, tip];
+            conn.execute("INSERT OR IGNORE INTO nonce_cache (address, nonce) \
+                          SELECT ?1, ?2 FROM nonce_cache_tip WHERE index_block_hash = ?3", args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::{instantiate_chainstate, make_tx};
+
+    fn make_transfer(nonce: u64) -> StacksTransaction {
+        let recipient = StacksAddress { version: 26, bytes: Hash160([1u8; 20]) };
+        let mut tx = make_tx(TransactionPayload::TokenTransfer(recipient.into(), 100, TokenTransferMemo([0u8; 34])));
+        tx.set_origin_nonce(nonce);
+        tx
+    }
+
+    fn make_block(txs: Vec<StacksTransaction>) -> StacksBlock {
+        StacksBlock { header: StacksBlockHeader::genesis_block_header(), txs }
+    }
+
+    #[test]
+    fn test_nonce_cache() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_nonce_cache");
+        let sender = make_transfer(0).origin_address();
+        let tip_1 = StacksBlockId([1u8; 32]);
+        let tip_2 = StacksBlockId([2u8; 32]);
+        let fork_2 = StacksBlockId([3u8; 32]);
+        let fork_3 = StacksBlockId([4u8; 32]);
+
+        // nothing is cached until a block is processed
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &tip_1, &sender).unwrap(), None);
+        assert!(StacksChainState::check_cached_nonces(&chainstate.headers_db, &tip_1, &make_transfer(5)).is_ok());
+
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::update_nonce_cache(&mut tx, &StacksBlockId([0u8; 32]), &tip_1, 1, &make_block(vec![make_transfer(0), make_transfer(1)]), &vec![]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &tip_1, &sender).unwrap(), Some(2));
+        let mismatch = StacksChainState::check_cached_nonces(&chainstate.headers_db, &tip_1, &make_transfer(1)).unwrap_err();
+        assert_eq!(mismatch.expected, 2);
+        assert_eq!(mismatch.actual, 1);
+        assert!(StacksChainState::check_cached_nonces(&chainstate.headers_db, &tip_1, &make_transfer(2)).is_ok());
+
+        // the cache is only as of its tip
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &tip_2, &sender).unwrap(), None);
+
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::update_nonce_cache(&mut tx, &tip_1, &tip_2, 2, &make_block(vec![make_transfer(2)]), &vec![]).unwrap();
+
+            // a fork block that doesn't overtake the tip is left out
+            StacksChainState::update_nonce_cache(&mut tx, &tip_1, &fork_2, 2, &make_block(vec![make_transfer(2), make_transfer(3)]), &vec![]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(StacksChainState::get_nonce_cache_tip(&chainstate.headers_db).unwrap(), Some((tip_2.clone(), 2)));
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &tip_2, &sender).unwrap(), Some(3));
+
+        // a fork that overtakes it starts the cache over
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::update_nonce_cache(&mut tx, &fork_2, &fork_3, 3, &make_block(vec![]), &vec![]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &fork_3, &sender).unwrap(), None);
+
+        // admitted transactions fill it in, but only as of its tip
+        StacksChainState::cache_admitted_nonces(&chainstate.headers_db, &tip_2, &make_transfer(7)).unwrap();
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &fork_3, &sender).unwrap(), None);
+        StacksChainState::cache_admitted_nonces(&chainstate.headers_db, &fork_3, &make_transfer(4)).unwrap();
+        assert_eq!(StacksChainState::get_cached_nonce(&chainstate.headers_db, &fork_3, &sender).unwrap(), Some(4));
+    }
+}
@@ -0,0 +1,557 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::cmp;
+use std::fs;
+use std::io;
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use util::db::Error as db_error;
+use util::db::{
+    query_count,
+    query_int,
+    query_rows,
+    u64_to_sql,
+};
+
+/// Which blocks have had their data discarded, and how far pruning has got.  Created on open if
+/// missing.  A pruned block keeps its header and its staging_blocks row; only the block and the
+/// microblock stream it produced are gone.
+pub const BLOCK_PRUNING_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS pruned_blocks(
+        index_block_hash TEXT NOT NULL,
+        burn_block_height INTEGER NOT NULL,
+
+        PRIMARY KEY(index_block_hash)
+    );
+    "#,
+    r#"
+    -- every burn block at or below this height has been considered for pruning
+    CREATE TABLE IF NOT EXISTS block_pruning_progress(
+        pruned_burn_height INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    -- every processed block at or below this Stacks block height has been considered for fork pruning
+    CREATE TABLE IF NOT EXISTS fork_pruning_progress(
+        pruned_height INTEGER NOT NULL
+    );
+    "#,
+];
+
+/// Keep at least this many burn blocks' worth of block data, so the node can still process
+/// Stacks forks that start in the recent past.
+#[cfg(test)]
+pub const MIN_PRUNE_BURN_BLOCKS : u64 = 2;      // small for testing purposes
+
+#[cfg(not(test))]
+pub const MIN_PRUNE_BURN_BLOCKS : u64 = 144;
+
+/// How many burn blocks to consider in one call to prune_blocks(), so a node that turns pruning
+/// on with a long chain behind it doesn't stall block processing while it catches up.
+#[cfg(test)]
+const MAX_PRUNE_BATCH : u64 = 2;                // small for testing purposes
+
+#[cfg(not(test))]
+const MAX_PRUNE_BATCH : u64 = 100;
+
+/// Keep the data of fork blocks at least this many Stacks blocks below the canonical tip, so a
+/// fork that's still in contention can be served to peers, and can take over without losing it.
+#[cfg(test)]
+pub const MIN_PRUNE_FORK_DEPTH : u64 = 2;       // small for testing purposes
+
+#[cfg(not(test))]
+pub const MIN_PRUNE_FORK_DEPTH : u64 = 144;
+
+/// How many Stacks block heights to consider in one call to prune_fork_blocks()
+#[cfg(test)]
+const MAX_PRUNE_FORK_BATCH : u64 = 2;           // small for testing purposes
+
+#[cfg(not(test))]
+const MAX_PRUNE_FORK_BATCH : u64 = 100;
+
+/// How far one call to prune_blocks() or prune_fork_blocks() got.  A call can scan heights
+/// without finding anything to prune, so only a call that scanned nothing means pruning has
+/// caught up.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PruneProgress {
+    /// how many heights were considered -- burn block heights for prune_blocks(), Stacks block
+    /// heights for prune_fork_blocks()
+    pub heights_scanned: u64,
+    /// how many blocks had their data discarded
+    pub blocks_pruned: u64,
+}
+
+impl StacksChainState {
+    /// The highest burn block height pruning has got to, or 0 if nothing has been pruned
+    pub fn get_pruned_burn_height(blocks_conn: &Connection) -> Result<u64, Error> {
+        let sql = "SELECT IFNULL(MAX(pruned_burn_height), 0) FROM block_pruning_progress".to_string();
+        let height = query_int(blocks_conn, &sql, NO_PARAMS).map_err(Error::DBError)?;
+        Ok(height as u64)
+    }
+
+    fn set_pruned_burn_height<'a>(tx: &mut BlocksDBTx<'a>, burn_height: u64) -> Result<(), Error> {
+        let burn_height = u64_to_sql(burn_height).map_err(Error::DBError)?;
+        tx.execute("DELETE FROM block_pruning_progress", NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT INTO block_pruning_progress (pruned_burn_height) VALUES (?1)", &[&burn_height as &dyn ToSql])
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// The highest Stacks block height fork pruning has got to, or 0 if nothing has been pruned
+    pub fn get_pruned_fork_height(blocks_conn: &Connection) -> Result<u64, Error> {
+        let sql = "SELECT IFNULL(MAX(pruned_height), 0) FROM fork_pruning_progress".to_string();
+        let height = query_int(blocks_conn, &sql, NO_PARAMS).map_err(Error::DBError)?;
+        Ok(height as u64)
+    }
+
+    fn set_pruned_fork_height<'a>(tx: &mut BlocksDBTx<'a>, height: u64) -> Result<(), Error> {
+        let height = u64_to_sql(height).map_err(Error::DBError)?;
+        tx.execute("DELETE FROM fork_pruning_progress", NO_PARAMS)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.execute("INSERT INTO fork_pruning_progress (pruned_height) VALUES (?1)", &[&height as &dyn ToSql])
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Has this block's data been pruned?
+    pub fn is_block_pruned(blocks_conn: &Connection, index_block_hash: &StacksBlockId) -> Result<bool, Error> {
+        let sql = "SELECT COUNT(*) FROM pruned_blocks WHERE index_block_hash = ?1".to_string();
+        let args = [index_block_hash as &dyn ToSql];
+        let cnt = query_count(blocks_conn, &sql, &args).map_err(Error::DBError)?;
+        Ok(cnt > 0)
+    }
+
+    /// Is this block processed and part of the chain state (i.e. not orphaned)?
+    fn is_processed_block(blocks_conn: &Connection, index_block_hash: &StacksBlockId) -> Result<bool, Error> {
+        let sql = "SELECT COUNT(*) FROM staging_blocks WHERE index_block_hash = ?1 AND processed = 1 AND orphaned = 0".to_string();
+        let args = [index_block_hash as &dyn ToSql];
+        let cnt = query_count(blocks_conn, &sql, &args).map_err(Error::DBError)?;
+        Ok(cnt > 0)
+    }
+
+    /// Truncate a file in the chunk store, if it's there.  Like an invalid block, a truncated
+    /// block reads as absent, and isn't reported in our inventory or downloaded again.
+    fn truncate_chunk(blocks_path: &String, index_hash: &StacksBlockId) -> Result<(), Error> {
+        let path = StacksChainState::get_index_block_path(blocks_path, index_hash)?;
+        match fs::OpenOptions::new().read(false).write(true).truncate(true).open(&path) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    Ok(())
+                }
+                else {
+                    Err(Error::DBError(db_error::IOError(e)))
+                }
+            }
+        }
+    }
+
+    /// Discard a processed block's data, along with the microblock stream it produced: the
+    /// confirmed stream in the chunk store, if any, and any of its microblocks still in staging.
+    /// The block's header stays in the headers DB.
+    fn prune_block<'a>(tx: &mut BlocksDBTx<'a>, index_block_hash: &StacksBlockId, confirmed_stream: Option<&StacksBlockId>, burn_block_height: u64) -> Result<(), Error> {
+        let blocks_path = tx.get_blocks_path().clone();
+
+        // truncate first, so if we crash before committing, the next pass does it again
+        StacksChainState::truncate_chunk(&blocks_path, index_block_hash)?;
+        if let Some(index_microblock_hash) = confirmed_stream {
+            StacksChainState::truncate_chunk(&blocks_path, index_microblock_hash)?;
+        }
+
+        let clear_sql = "DELETE FROM staging_microblocks_data WHERE block_hash IN \
+                         (SELECT microblock_hash FROM staging_microblocks WHERE index_block_hash = ?1 AND processed = 1)";
+        tx.execute(clear_sql, &[index_block_hash as &dyn ToSql])
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        let args : &[&dyn ToSql] = &[index_block_hash, &u64_to_sql(burn_block_height).map_err(Error::DBError)?];
+        tx.execute("INSERT OR REPLACE INTO pruned_blocks (index_block_hash, burn_block_height) VALUES (?1, ?2)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        Ok(())
+    }
+
+    /// Discard the data of processed blocks that were selected more than keep_burn_blocks burn
+    /// blocks ago in the canonical burnchain fork, along with their microblock streams.  Blocks
+    /// after the canonical Stacks tip's sortition are never pruned, nor are blocks we haven't
+    /// processed.  Picks up where the last call left off, and considers at most MAX_PRUNE_BATCH
+    /// burn blocks per call.  Archives are never pruned.  Returns how many burn blocks were
+    /// considered and how many blocks were pruned.
+    pub fn prune_blocks(&mut self, burndb: &BurnDB, keep_burn_blocks: u64) -> Result<PruneProgress, Error> {
+        if StacksChainState::is_archive(&self.blocks_db)? {
+            error!("Refusing to prune blocks from an archive");
+            return Err(Error::InvalidChainstateDB);
+        }
+
+        let keep_burn_blocks = cmp::max(keep_burn_blocks, MIN_PRUNE_BURN_BLOCKS);
+        let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn()).map_err(Error::DBError)?;
+        let stacks_tip_burn_height = match BurnDB::get_block_snapshot(burndb.conn(), &burn_tip.canonical_stacks_tip_burn_hash).map_err(Error::DBError)? {
+            Some(sn) => sn.block_height,
+            None => {
+                // no Stacks blocks yet
+                return Ok(PruneProgress::default());
+            }
+        };
+
+        let prune_height = cmp::min(burn_tip.block_height.saturating_sub(keep_burn_blocks), stacks_tip_burn_height);
+        let pruned_height = StacksChainState::get_pruned_burn_height(&self.blocks_db)?;
+        if prune_height <= pruned_height {
+            return Ok(PruneProgress::default());
+        }
+        let last_height = cmp::min(prune_height, pruned_height + MAX_PRUNE_BATCH);
+
+        let mut to_prune = vec![];
+        {
+            let ic = burndb.index_conn();
+            for burn_height in (pruned_height + 1)..(last_height + 1) {
+                let snapshot = match BurnDB::get_block_snapshot_in_fork(&ic, burn_height, &burn_tip.burn_header_hash).map_err(Error::DBError)? {
+                    Some(sn) => sn,
+                    None => continue
+                };
+                if !snapshot.sortition {
+                    continue;
+                }
+
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(&snapshot.burn_header_hash, &snapshot.winning_stacks_block_hash);
+                if !StacksChainState::is_processed_block(&self.blocks_db, &index_block_hash)? {
+                    continue;
+                }
+                let confirmed_stream = self.get_confirmed_microblock_index_hash(&index_block_hash)?;
+                to_prune.push((index_block_hash, confirmed_stream, burn_height));
+            }
+        }
+
+        let mut tx = self.blocks_tx_begin()?;
+        for (index_block_hash, confirmed_stream, burn_height) in to_prune.iter() {
+            StacksChainState::prune_block(&mut tx, index_block_hash, confirmed_stream.as_ref(), *burn_height)?;
+        }
+        StacksChainState::set_pruned_burn_height(&mut tx, last_height)?;
+        tx.commit().map_err(Error::DBError)?;
+
+        if to_prune.len() > 0 {
+            debug!("Pruned {} blocks in burn blocks {}-{}", to_prune.len(), pruned_height + 1, last_height);
+        }
+        Ok(PruneProgress {
+            heights_scanned: last_height - pruned_height,
+            blocks_pruned: to_prune.len() as u64
+        })
+    }
+
+    /// Discard the data of processed blocks that aren't on the canonical Stacks chain and are
+    /// more than keep_depth blocks below the canonical tip, along with their microblock streams.
+    /// Their headers and Clarity state stay, so the node can still follow their fork if it takes
+    /// over.  Picks up where the last call left off, and considers at most MAX_PRUNE_FORK_BATCH
+    /// block heights per call, so a fork block processed below the height pruning has got to is
+    /// kept.  Archives are never pruned.  Returns how many block heights were considered and how
+    /// many blocks were pruned.
+    pub fn prune_fork_blocks(&mut self, burndb: &BurnDB, keep_depth: u64) -> Result<PruneProgress, Error> {
+        if StacksChainState::is_archive(&self.blocks_db)? {
+            error!("Refusing to prune fork blocks from an archive");
+            return Err(Error::InvalidChainstateDB);
+        }
+
+        let keep_depth = cmp::max(keep_depth, MIN_PRUNE_FORK_DEPTH);
+        let (tip_burn_hash, tip_block_hash) = BurnDB::get_canonical_stacks_chain_tip_hash(burndb.conn()).map_err(Error::DBError)?;
+        let tip_info = match StacksChainState::get_anchored_block_header_info(&self.headers_db, &tip_burn_hash, &tip_block_hash)? {
+            Some(tip_info) => tip_info,
+            None => {
+                // no Stacks blocks yet
+                return Ok(PruneProgress::default());
+            }
+        };
+        let tip_index_hash = tip_info.index_block_hash();
+
+        let prune_height = tip_info.block_height.saturating_sub(keep_depth);
+        let pruned_height = StacksChainState::get_pruned_fork_height(&self.blocks_db)?;
+        if prune_height <= pruned_height {
+            return Ok(PruneProgress::default());
+        }
+        let last_height = cmp::min(prune_height, pruned_height + MAX_PRUNE_FORK_BATCH);
+
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 1 AND orphaned = 0 AND height > ?1 AND height <= ?2 ORDER BY height".to_string();
+        let args : &[&dyn ToSql] = &[&u64_to_sql(pruned_height).map_err(Error::DBError)?, &u64_to_sql(last_height).map_err(Error::DBError)?];
+        let candidates = query_rows::<StagingBlock, _>(&self.blocks_db, &sql, args).map_err(Error::DBError)?;
+
+        // fork blocks are the ones that aren't the canonical tip's ancestor at their height
+        let mut fork_blocks = vec![];
+        {
+            let mut headers_tx = self.headers_tx_begin()?;
+            for candidate in candidates.into_iter() {
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(&candidate.burn_header_hash, &candidate.anchored_block_hash);
+                let canonical = headers_tx.get_ancestor_block_hash(candidate.height, &tip_index_hash).map_err(Error::DBError)?;
+                if canonical.as_ref() != Some(&index_block_hash) {
+                    fork_blocks.push((index_block_hash, candidate.burn_header_hash));
+                }
+            }
+        }
+
+        let mut to_prune = vec![];
+        for (index_block_hash, burn_header_hash) in fork_blocks.into_iter() {
+            if StacksChainState::is_block_pruned(&self.blocks_db, &index_block_hash)? {
+                continue;
+            }
+            let burn_block_height = match BurnDB::get_block_snapshot(burndb.conn(), &burn_header_hash).map_err(Error::DBError)? {
+                Some(sn) => sn.block_height,
+                None => continue
+            };
+            let confirmed_stream = self.get_confirmed_microblock_index_hash(&index_block_hash)?;
+            to_prune.push((index_block_hash, confirmed_stream, burn_block_height));
+        }
+
+        let mut tx = self.blocks_tx_begin()?;
+        for (index_block_hash, confirmed_stream, burn_height) in to_prune.iter() {
+            StacksChainState::prune_block(&mut tx, index_block_hash, confirmed_stream.as_ref(), *burn_height)?;
+        }
+        StacksChainState::set_pruned_fork_height(&mut tx, last_height)?;
+        tx.commit().map_err(Error::DBError)?;
+
+        if to_prune.len() > 0 {
+            debug!("Pruned {} fork blocks at heights {}-{}", to_prune.len(), pruned_height + 1, last_height);
+        }
+        Ok(PruneProgress {
+            heights_scanned: last_height - pruned_height,
+            blocks_pruned: to_prune.len() as u64
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use chainstate::stacks::miner::test::mine_empty_anchored_block;
+    use chainstate::burn::operations::BlockstackOperationType;
+    use burnchains::BurnchainHeaderHash;
+    use burnchains::test::TestBurnchainBlock;
+    use core::FIRST_BURNCHAIN_BLOCK_HASH;
+    use net::test::*;
+
+    /// Mine an empty block on top of the given parent (or the first block, if there's none) in a
+    /// burn block of its own, and process it.  Returns the block and its index hash.
+    fn mine_block(peer: &mut TestPeer, parent: Option<&StacksBlock>) -> (StacksBlockId, StacksBlock) {
+        let network_id = peer.config.network_id;
+        let chainstate_path = peer.chainstate_path.clone();
+        let (burn_ops, block) = peer.with_mining_state(|burndb, miner, _spending_account, stacks_node| {
+            let sn = BurnDB::get_canonical_burn_chain_tip(burndb.conn()).unwrap();
+            let mut burn_block = TestBurnchainBlock::new(&sn, 0);
+            let burn_block_height = burn_block.block_height;
+            let last_key = stacks_node.get_last_key(miner);
+
+            let (block, _, block_commit_op) = stacks_node.mine_stacks_block(burndb, miner, &mut burn_block, &last_key, parent, 1000, |mut builder, ref mut miner| {
+                let mut miner_chainstate = StacksChainState::open(false, network_id, &chainstate_path).unwrap();
+                let mut epoch = builder.epoch_begin(&mut miner_chainstate).unwrap();
+                let (block, microblocks) = mine_empty_anchored_block(&mut epoch, &mut builder, miner, burn_block_height as usize, None);
+                builder.epoch_finish(epoch);
+                (block, microblocks)
+            });
+            let leader_key_op = stacks_node.add_key_register(&mut burn_block, miner);
+            Ok((vec![BlockstackOperationType::LeaderKeyRegister(leader_key_op), BlockstackOperationType::LeaderBlockCommit(block_commit_op)], block))
+        }).unwrap();
+        peer.next_burnchain_block(burn_ops);
+
+        // the parent isn't necessarily the previous sortition's winner, so this can't go through
+        // process_stacks_epoch_at_tip()
+        let mut burndb = peer.burndb.take().unwrap();
+        let index_block_hash = {
+            let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+            let ic = burndb.index_conn();
+            let tip = BurnDB::get_canonical_burn_chain_tip(&ic).unwrap();
+            let sn = BurnDB::get_block_snapshot_for_winning_stacks_block(&ic, &tip.burn_header_hash, &block.block_hash()).unwrap().unwrap();
+            let parent_burn_header_hash = match parent {
+                Some(parent) => BurnDB::get_block_snapshot_for_winning_stacks_block(&ic, &tip.burn_header_hash, &parent.block_hash()).unwrap().unwrap().burn_header_hash,
+                None => FIRST_BURNCHAIN_BLOCK_HASH.clone()
+            };
+            assert!(chainstate.preprocess_anchored_block(&ic, &sn.burn_header_hash, sn.burn_header_timestamp, &block, &parent_burn_header_hash).unwrap());
+            StacksBlockHeader::make_index_block_hash(&sn.burn_header_hash, &block.block_hash())
+        };
+        {
+            let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+            chainstate.process_blocks(&mut burndb, 1).unwrap();
+            assert!(StacksChainState::is_processed_block(&chainstate.blocks_db, &index_block_hash).unwrap());
+        }
+        peer.burndb = Some(burndb);
+        (index_block_hash, block)
+    }
+
+    /// Is the block's data still in the chunk store?
+    fn has_block_data(chainstate: &StacksChainState, index_block_hash: &StacksBlockId) -> bool {
+        let path = StacksChainState::get_index_block_path(&chainstate.blocks_path, index_block_hash).unwrap();
+        StacksChainState::get_file_size(&path).unwrap() > 0
+    }
+
+    #[test]
+    fn test_prune_block() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_prune_block");
+        let index_block_hash = StacksBlockId([1u8; 32]);
+        let other_block_hash = StacksBlockId([2u8; 32]);
+
+        for ibh in [&index_block_hash, &other_block_hash].iter() {
+            let path = StacksChainState::get_index_block_path(&chainstate.blocks_path, ibh).unwrap();
+            fs::create_dir_all(::std::path::Path::new(&path).parent().unwrap()).unwrap();
+            fs::write(&path, &[0xaa; 100]).unwrap();
+        }
+
+        assert_eq!(StacksChainState::get_pruned_burn_height(&chainstate.blocks_db).unwrap(), 0);
+        assert!(!StacksChainState::is_block_pruned(&chainstate.blocks_db, &index_block_hash).unwrap());
+
+        {
+            let mut tx = chainstate.blocks_tx_begin().unwrap();
+            StacksChainState::prune_block(&mut tx, &index_block_hash, None, 5).unwrap();
+            StacksChainState::set_pruned_burn_height(&mut tx, 7).unwrap();
+            tx.commit().unwrap();
+        }
+
+        // pruned blocks read as absent, but are remembered as pruned
+        let path = StacksChainState::get_index_block_path(&chainstate.blocks_path, &index_block_hash).unwrap();
+        assert_eq!(StacksChainState::get_file_size(&path).unwrap(), 0);
+        assert!(StacksChainState::is_block_pruned(&chainstate.blocks_db, &index_block_hash).unwrap());
+        assert_eq!(StacksChainState::get_pruned_burn_height(&chainstate.blocks_db).unwrap(), 7);
+
+        let path = StacksChainState::get_index_block_path(&chainstate.blocks_path, &other_block_hash).unwrap();
+        assert_eq!(StacksChainState::get_file_size(&path).unwrap(), 100);
+        assert!(!StacksChainState::is_block_pruned(&chainstate.blocks_db, &other_block_hash).unwrap());
+    }
+
+    #[test]
+    fn test_prune_fork_blocks_boot() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_prune_fork_blocks_boot");
+        let burndb = BurnDB::connect_test(0, &BurnchainHeaderHash([0u8; 32])).unwrap();
+
+        // nothing is deep enough below the boot block to prune
+        assert_eq!(chainstate.prune_fork_blocks(&burndb, MIN_PRUNE_FORK_DEPTH).unwrap(), PruneProgress::default());
+        assert_eq!(StacksChainState::get_pruned_fork_height(&chainstate.blocks_db).unwrap(), 0);
+
+        {
+            let mut tx = chainstate.blocks_tx_begin().unwrap();
+            StacksChainState::set_pruned_fork_height(&mut tx, 12).unwrap();
+            StacksChainState::set_pruned_fork_height(&mut tx, 15).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(StacksChainState::get_pruned_fork_height(&chainstate.blocks_db).unwrap(), 15);
+        // fork pruning's progress is separate from block pruning's
+        assert_eq!(StacksChainState::get_pruned_burn_height(&chainstate.blocks_db).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_prune_fork_blocks() {
+        let mut peer = TestPeer::new(TestPeerConfig::new("test_prune_fork_blocks", 4240, 4241));
+
+        // A1 <- A2 <- A3 <- A4 <- A5 <- A6
+        //    \                 \
+        //     B2                 B5
+        let (a1, block_a1) = mine_block(&mut peer, None);
+        let (a2, block_a2) = mine_block(&mut peer, Some(&block_a1));
+        let (b2, _) = mine_block(&mut peer, Some(&block_a1));
+        let (a3, block_a3) = mine_block(&mut peer, Some(&block_a2));
+        let (a4, block_a4) = mine_block(&mut peer, Some(&block_a3));
+        let (b5, _) = mine_block(&mut peer, Some(&block_a4));
+        let (a5, block_a5) = mine_block(&mut peer, Some(&block_a4));
+        let (a6, block_a6) = mine_block(&mut peer, Some(&block_a5));
+
+        let burndb = peer.burndb.take().unwrap();
+        let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+        let (_, tip_block_hash) = BurnDB::get_canonical_stacks_chain_tip_hash(burndb.conn()).unwrap();
+        assert_eq!(tip_block_hash, block_a6.block_hash());
+
+        // the tip is at height 6, so heights 1-4 are deep enough to prune, in batches of two.
+        // B2 is pruned in the first batch, and the second batch scans heights but prunes nothing.
+        assert_eq!(chainstate.prune_fork_blocks(&burndb, MIN_PRUNE_FORK_DEPTH).unwrap(), PruneProgress { heights_scanned: 2, blocks_pruned: 1 });
+        assert_eq!(chainstate.prune_fork_blocks(&burndb, MIN_PRUNE_FORK_DEPTH).unwrap(), PruneProgress { heights_scanned: 2, blocks_pruned: 0 });
+        assert_eq!(chainstate.prune_fork_blocks(&burndb, MIN_PRUNE_FORK_DEPTH).unwrap(), PruneProgress::default());
+        assert_eq!(StacksChainState::get_pruned_fork_height(&chainstate.blocks_db).unwrap(), 4);
+
+        assert!(StacksChainState::is_block_pruned(&chainstate.blocks_db, &b2).unwrap());
+        assert!(!has_block_data(chainstate, &b2));
+
+        // the canonical chain is untouched, and so is B5, which is too close to the tip
+        for ibh in [&a1, &a2, &a3, &a4, &a5, &a6, &b5].iter() {
+            assert!(!StacksChainState::is_block_pruned(&chainstate.blocks_db, ibh).unwrap());
+            assert!(has_block_data(chainstate, ibh));
+        }
+        assert_eq!(chainstate.check_integrity(&burndb).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_prune_blocks() {
+        let mut peer = TestPeer::new(TestPeerConfig::new("test_prune_blocks", 4242, 4243));
+
+        let mut blocks = vec![];
+        let mut parent : Option<StacksBlock> = None;
+        for _ in 0..6 {
+            let (index_block_hash, block) = mine_block(&mut peer, parent.as_ref());
+            blocks.push((index_block_hash, block.clone()));
+            parent = Some(block);
+        }
+
+        let burndb = peer.burndb.take().unwrap();
+        let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+        let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn()).unwrap();
+        let prune_height = burn_tip.block_height - MIN_PRUNE_BURN_BLOCKS;
+
+        // keep going until a call has nothing left to scan
+        let mut num_pruned = 0;
+        loop {
+            let progress = chainstate.prune_blocks(&burndb, MIN_PRUNE_BURN_BLOCKS).unwrap();
+            if progress.heights_scanned == 0 {
+                assert_eq!(progress.blocks_pruned, 0);
+                break;
+            }
+            assert!(progress.heights_scanned <= MAX_PRUNE_BATCH);
+            num_pruned += progress.blocks_pruned;
+        }
+        assert_eq!(StacksChainState::get_pruned_burn_height(&chainstate.blocks_db).unwrap(), prune_height);
+
+        // exactly the blocks selected at or below the prune height are pruned
+        let mut expected_pruned = 0;
+        for (index_block_hash, block) in blocks.iter() {
+            let sn = BurnDB::get_block_snapshot_for_winning_stacks_block(&burndb.index_conn(), &burn_tip.burn_header_hash, &block.block_hash()).unwrap().unwrap();
+            if sn.block_height <= prune_height {
+                expected_pruned += 1;
+
+                // a pruned block is remembered as pruned, and is still a processed, accepted
+                // block -- it isn't mistaken for an invalid one
+                assert!(StacksChainState::is_block_pruned(&chainstate.blocks_db, index_block_hash).unwrap());
+                assert!(StacksChainState::is_processed_block(&chainstate.blocks_db, index_block_hash).unwrap());
+                assert!(!has_block_data(chainstate, index_block_hash));
+                assert!(StacksChainState::load_block(&chainstate.blocks_path, &sn.burn_header_hash, &block.block_hash()).unwrap().is_none());
+            }
+            else {
+                assert!(!StacksChainState::is_block_pruned(&chainstate.blocks_db, index_block_hash).unwrap());
+                assert!(has_block_data(chainstate, index_block_hash));
+                assert_eq!(StacksChainState::load_block(&chainstate.blocks_path, &sn.burn_header_hash, &block.block_hash()).unwrap().unwrap(), *block);
+            }
+        }
+        assert_eq!(expected_pruned, 4);
+        assert_eq!(num_pruned, expected_pruned);
+
+        // the integrity check doesn't report pruned blocks as missing or corrupt
+        assert_eq!(chainstate.check_integrity(&burndb).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,410 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashSet;
+
+use rusqlite::Row;
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use burnchains::Txid;
+use burnchains::BurnchainHeaderHash;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::events::*;
+use chainstate::stacks::db::contract_costs::charged_contract;
+
+use net::StacksMessageCodec;
+
+use util::db::Error as db_error;
+use util::db::{
+    DBConn,
+    FromRow,
+    FromColumn,
+    query_rows,
+    u64_to_sql,
+    hex_prefix_bounds,
+    tx_begin_immediate,
+};
+use util::hash::{hex_bytes, to_hex};
+use util::migrations::get_schema_version;
+
+use vm::types::{QualifiedContractIdentifier, Value};
+
+/// Outcome of a mined transaction whose result was committed
+pub const TX_STATUS_SUCCESS : &'static str = "success";
+/// Outcome of a mined transaction whose result was an `err`, so its changes were rolled back
+pub const TX_STATUS_ABORT_BY_RESPONSE : &'static str = "abort_by_response";
+/// Outcome of a mined transaction whose post-conditions failed, so its changes were rolled back
+pub const TX_STATUS_ABORT_BY_POST_CONDITION : &'static str = "abort_by_post_condition";
+
+/// Receipts of mined transactions -- their outcome, return value, and emitted events -- so
+/// clients can look them up by txid, by block, or by the contracts they involve.  Created on
+/// open if missing, so transactions mined before this table existed have no receipts.  Receipts
+/// stored before they were indexed by contract or kept in block order aren't, until they're
+/// reindexed.
+pub const TRANSACTION_RECEIPTS_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS transaction_receipts(
+        txid TEXT NOT NULL,
+        status TEXT NOT NULL,
+        result TEXT NOT NULL,               -- hex-encoded, consensus-serialized Clarity value
+        events TEXT NOT NULL,               -- JSON list, in the format the event observer gets
+        block_hash TEXT NOT NULL,
+        burn_header_hash TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,     -- NOTE: the same transaction can be mined in blocks on different forks
+        block_height INTEGER NOT NULL,
+        abort_code TEXT,                    -- NULL unless aborted by something other than returning an `err`
+        tx_index INTEGER,                   -- position among the transactions the block and its microblock stream mined
+
+        PRIMARY KEY(txid,index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS transaction_receipts_by_block ON transaction_receipts(index_block_hash);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS transaction_receipt_contracts(
+        contract_id TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+
+        PRIMARY KEY(contract_id,txid,index_block_hash)
+    );
+    "#,
+    r#"
+    CREATE INDEX IF NOT EXISTS transaction_receipt_contracts_by_height ON transaction_receipt_contracts(contract_id,block_height);
+    "#,
+];
+
+/// The contracts a mined transaction involves:  the one it calls or deploys, and the ones whose
+/// events it emitted or whose tokens it moved
+pub fn receipt_contracts(receipt: &StacksTransactionReceipt) -> Vec<QualifiedContractIdentifier> {
+    let mut contracts = HashSet::new();
+    if let Some(contract_id) = charged_contract(&receipt.transaction) {
+        contracts.insert(contract_id);
+    }
+    for event in receipt.events.iter() {
+        let contract_id = match event {
+            StacksTransactionEvent::SmartContractEvent(ref data) => &data.key.0,
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(ref data)) => &data.asset_identifier.contract_identifier,
+            StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(ref data)) => &data.asset_identifier.contract_identifier,
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(ref data)) => &data.asset_identifier.contract_identifier,
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(ref data)) => &data.asset_identifier.contract_identifier,
+            StacksTransactionEvent::STXEvent(_) => continue
+        };
+        contracts.insert(contract_id.clone());
+    }
+    let mut contracts : Vec<QualifiedContractIdentifier> = contracts.into_iter().collect();
+    contracts.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    contracts
+}
+
+/// The receipt of a mined transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionReceiptEntry {
+    pub txid: Txid,
+    /// one of "success", "abort_by_response" or "abort_by_post_condition"
+    pub status: String,
+    pub result: Value,
+    pub events: Vec<serde_json::Value>,
+    /// why the transaction was aborted, if it was aborted by something other than returning an
+    /// `err` -- see `TransactionAbortCode::code()`
+    pub abort_code: Option<String>,
+    pub block_hash: BlockHeaderHash,
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub block_height: u64,
+    /// position among the transactions the block and its microblock stream mined, if known
+    pub tx_index: Option<u64>,
+}
+
+impl TransactionReceiptEntry {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        StacksBlockHeader::make_index_block_hash(&self.burn_header_hash, &self.block_hash)
+    }
+}
+
+impl FromRow<TransactionReceiptEntry> for TransactionReceiptEntry {
+    fn from_row<'a>(row: &'a Row) -> Result<TransactionReceiptEntry, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let status : String = row.get("status");
+        let result_str : String = row.get("result");
+        let events_str : String = row.get("events");
+        let block_hash = BlockHeaderHash::from_column(row, "block_hash")?;
+        let burn_header_hash = BurnchainHeaderHash::from_column(row, "burn_header_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+        let abort_code : Option<String> = row.get("abort_code");
+        let tx_index : Option<i64> = row.get("tx_index");
+
+        let result_bytes = hex_bytes(&result_str).map_err(|_e| db_error::ParseError)?;
+        let result = Value::consensus_deserialize(&mut &result_bytes[..]).map_err(|_e| db_error::ParseError)?;
+        let events = serde_json::from_str(&events_str).map_err(|_e| db_error::ParseError)?;
+
+        Ok(TransactionReceiptEntry {
+            txid,
+            status,
+            result,
+            events,
+            abort_code,
+            block_hash,
+            burn_header_hash,
+            block_height,
+            tx_index: tx_index.map(|i| i as u64)
+        })
+    }
+}
+
+/// Where a receipt is stored
+struct ReceiptLocation {
+    txid: Txid,
+    index_block_hash: StacksBlockId,
+    block_height: u64,
+}
+
+impl FromRow<ReceiptLocation> for ReceiptLocation {
+    fn from_row<'a>(row: &'a Row) -> Result<ReceiptLocation, db_error> {
+        Ok(ReceiptLocation {
+            txid: Txid::from_column(row, "txid")?,
+            index_block_hash: StacksBlockId::from_column(row, "index_block_hash")?,
+            block_height: u64::from_column(row, "block_height")?,
+        })
+    }
+}
+
+impl StacksChainState {
+    /// Bring a transaction receipt index made before the headers DB's migrations were kept track
+    /// of up to date, so that its migration can create it only if it isn't there.  Does nothing
+    /// (and doesn't write) once the headers DB has been migrated.
+    pub fn upgrade_legacy_transaction_receipts(conn: &mut DBConn) -> Result<(), Error> {
+        if get_schema_version(conn).map_err(Error::DBError)? > 0 ||
+            conn.prepare("SELECT txid FROM transaction_receipts LIMIT 1").is_err() {
+            return Ok(());
+        }
+
+        // tables made before abort codes were recorded don't have the column, nor do tables
+        // made before receipts were kept in block order
+        let missing_columns : Vec<&str> = ["abort_code TEXT", "tx_index INTEGER"].iter()
+            .filter(|column| {
+                let name = column.split(' ').next().unwrap_or("");
+                conn.prepare(&format!("SELECT {} FROM transaction_receipts LIMIT 1", name)).is_err()
+            })
+            .cloned()
+            .collect();
+        if missing_columns.is_empty() {
+            return Ok(());
+        }
+
+        let tx = tx_begin_immediate(conn).map_err(Error::DBError)?;
+        for column in missing_columns.iter() {
+            tx.execute(&format!("ALTER TABLE transaction_receipts ADD COLUMN {}", column), NO_PARAMS)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+        tx.commit().map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Outcome of a processed transaction
+    pub fn transaction_receipt_status(receipt: &StacksTransactionReceipt) -> &'static str {
+        match receipt.result {
+            _ if receipt.post_condition_aborted => TX_STATUS_ABORT_BY_POST_CONDITION,
+            Value::Response(ref response_data) if !response_data.committed => TX_STATUS_ABORT_BY_RESPONSE,
+            _ => TX_STATUS_SUCCESS
+        }
+    }
+
+    /// Store the receipts of the transactions processed in a block and the microblock stream it
+    /// confirms, in the order they're given, and index them by the contracts they involve.
+    pub fn insert_transaction_receipts<'a>(tx: &mut StacksDBTx<'a>, tip_info: &StacksHeaderInfo, receipts: &Vec<StacksTransactionReceipt>) -> Result<(), Error> {
+        assert!(tip_info.block_height < (i64::max_value() as u64));
+
+        let block_hash = tip_info.anchored_header.block_hash();
+        let index_block_hash = tip_info.index_block_hash();
+
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let txid = receipt.transaction.txid();
+
+            let mut result_bytes = vec![];
+            receipt.result.consensus_serialize(&mut result_bytes).map_err(Error::NetError)?;
+
+            let events : Vec<serde_json::Value> = receipt.events
+                .iter()
+                .map(|event| event.json_serialize(&txid, !receipt.post_condition_aborted))
+                .collect();
+
+            let abort_code = receipt.abort_code.map(|code| code.code());
+
+            let args: &[&dyn ToSql] = &[
+                &txid, &StacksChainState::transaction_receipt_status(receipt), &to_hex(&result_bytes), &serde_json::Value::Array(events).to_string(),
+                &block_hash, &tip_info.burn_header_hash, &index_block_hash, &(tip_info.block_height as i64), &abort_code, &(tx_index as i64)];
+
+            tx.execute("INSERT OR REPLACE INTO transaction_receipts \
+                        (txid, status, result, events, block_hash, burn_header_hash, index_block_hash, block_height, abort_code, tx_index) \
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)", args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+            for contract_id in receipt_contracts(receipt).iter() {
+                let args: &[&dyn ToSql] = &[&contract_id.to_string(), &txid, &index_block_hash, &(tip_info.block_height as i64)];
+                tx.execute("INSERT OR REPLACE INTO transaction_receipt_contracts (contract_id, txid, index_block_hash, block_height) VALUES (?1, ?2, ?3, ?4)", args)
+                    .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find up to `limit` receipts, across all forks, of transactions whose txid starts with the
+    /// given lowercase hex prefix.
+    pub fn find_transaction_receipts_by_prefix(conn: &Connection, prefix: &str, limit: u64) -> Result<Vec<TransactionReceiptEntry>, Error> {
+        let (lower, upper) = hex_prefix_bounds(prefix);
+        let sql = "SELECT * FROM transaction_receipts WHERE txid >= ?1 AND txid < ?2 ORDER BY txid LIMIT ?3".to_string();
+        let args: &[&dyn ToSql] = &[&lower, &upper, &u64_to_sql(limit).map_err(Error::DBError)?];
+        query_rows::<TransactionReceiptEntry, _>(conn, &sql, args).map_err(Error::DBError)
+    }
+
+    /// Get the receipts of the transactions mined in a block, in the order they were stored:  the
+    /// block's own transactions, then those of the microblocks it confirms.
+    pub fn get_block_transaction_receipts(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<Vec<TransactionReceiptEntry>, Error> {
+        let sql = "SELECT * FROM transaction_receipts WHERE index_block_hash = ?1 ORDER BY tx_index, txid".to_string();
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_rows::<TransactionReceiptEntry, _>(conn, &sql, args).map_err(Error::DBError)
+    }
+
+    /// Get the receipts of up to `limit` transactions involving the given contract in the fork
+    /// ending at the given tip, newest first.  See receipt_contracts() for what involves a
+    /// contract.
+    pub fn get_contract_transaction_receipts<'a>(tx: &mut StacksDBTx<'a>, tip_index_hash: &StacksBlockId, contract_id: &QualifiedContractIdentifier, limit: u64) -> Result<Vec<TransactionReceiptEntry>, Error> {
+        // find the receipts in this fork first, and only then load them
+        let sql = "SELECT transaction_receipts.txid, transaction_receipts.index_block_hash, transaction_receipts.block_height \
+                   FROM transaction_receipt_contracts JOIN transaction_receipts \
+                   ON transaction_receipts.txid = transaction_receipt_contracts.txid AND transaction_receipts.index_block_hash = transaction_receipt_contracts.index_block_hash \
+                   WHERE transaction_receipt_contracts.contract_id = ?1 \
+                   ORDER BY transaction_receipts.block_height DESC, transaction_receipts.tx_index DESC".to_string();
+        let args: &[&dyn ToSql] = &[&contract_id.to_string()];
+        let candidates = query_rows::<ReceiptLocation, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        let mut entries = vec![];
+        for candidate in candidates.into_iter() {
+            if entries.len() as u64 >= limit {
+                break;
+            }
+            // only the receipts from this fork
+            let in_fork = match StacksChainState::get_index_tip_ancestor(tx, tip_index_hash, candidate.block_height)? {
+                Some(ancestor) => ancestor.index_block_hash() == candidate.index_block_hash,
+                None => false
+            };
+            if in_fork {
+                let args: &[&dyn ToSql] = &[&candidate.txid, &candidate.index_block_hash];
+                let mut rows = query_rows::<TransactionReceiptEntry, _>(tx, "SELECT * FROM transaction_receipts WHERE txid = ?1 AND index_block_hash = ?2", args)
+                    .map_err(Error::DBError)?;
+                if let Some(entry) = rows.pop() {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Find the receipt of the given transaction in the fork ending at the given tip, if it was
+    /// mined there.
+    pub fn get_transaction_receipt<'a>(tx: &mut StacksDBTx<'a>, tip_index_hash: &StacksBlockId, txid: &Txid) -> Result<Option<TransactionReceiptEntry>, Error> {
+        let sql = "SELECT * FROM transaction_receipts WHERE txid = ?1".to_string();
+        let args: &[&dyn ToSql] = &[txid];
+        let candidates = query_rows::<TransactionReceiptEntry, _>(tx, &sql, args).map_err(Error::DBError)?;
+
+        for entry in candidates.into_iter() {
+            // only the receipt from this fork
+            match StacksChainState::get_index_tip_ancestor(tx, tip_index_hash, entry.block_height)? {
+                Some(ancestor) => {
+                    if ancestor.index_block_hash() == entry.index_block_hash() {
+                        return Ok(Some(entry));
+                    }
+                },
+                None => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use chainstate::stacks::db::test::{instantiate_chainstate, make_tx};
+    use util::strings::{ClarityName, ContractName};
+    use vm::costs::ExecutionCost;
+    use vm::types::{AssetIdentifier, PrincipalData, StandardPrincipalData};
+
+    #[test]
+    fn test_receipts_by_block_and_contract() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_receipts_by_block_and_contract");
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+
+        let address = StacksAddress { version: 26, bytes: Hash160([1u8; 20]) };
+        let exchange_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(address.clone()), ContractName::try_from("exchange").unwrap());
+        let token_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(address.clone()), ContractName::try_from("token").unwrap());
+        let other_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(address.clone()), ContractName::try_from("other").unwrap());
+
+        // a call to the exchange that moves the token's tokens involves both
+        let transfer = StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(FTTransferEventData {
+            asset_identifier: AssetIdentifier { contract_identifier: token_id.clone(), asset_name: ClarityName::try_from("coin".to_string()).unwrap() },
+            sender: PrincipalData::from(address.clone()),
+            recipient: PrincipalData::from(exchange_id.clone()),
+            amount: 10
+        }));
+        let call = make_tx(TransactionPayload::new_contract_call(address.clone(), "exchange", "swap", vec![]).unwrap());
+        let receipts = vec![
+            StacksTransactionReceipt::from_coinbase(make_tx(TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])))),
+            StacksTransactionReceipt::from_contract_call(call.clone(), vec![transfer], Value::okay_true(), 0, ExecutionCost::zero()),
+        ];
+        assert!(receipt_contracts(&receipts[0]).is_empty());
+        assert_eq!(receipt_contracts(&receipts[1]), vec![exchange_id.clone(), token_id.clone()]);
+
+        {
+            let mut tx = chainstate.headers_tx_begin().unwrap();
+            StacksChainState::insert_transaction_receipts(&mut tx, &genesis, &receipts).unwrap();
+            tx.commit().unwrap();
+        }
+
+        // a block's receipts come back in the order they were stored
+        let block_receipts = StacksChainState::get_block_transaction_receipts(&chainstate.headers_db, &genesis.index_block_hash()).unwrap();
+        assert_eq!(block_receipts.iter().map(|entry| entry.txid.clone()).collect::<Vec<_>>(),
+                   receipts.iter().map(|receipt| receipt.transaction.txid()).collect::<Vec<_>>());
+
+        let mut tx = chainstate.headers_tx_begin().unwrap();
+
+        // a single transaction's receipt remembers where in the block it was
+        let entry = StacksChainState::get_transaction_receipt(&mut tx, &genesis.index_block_hash(), &call.txid()).unwrap().unwrap();
+        assert_eq!(entry.index_block_hash(), genesis.index_block_hash());
+        assert_eq!(entry.tx_index, Some(1));
+
+        for contract_id in [exchange_id.clone(), token_id.clone()].iter() {
+            let entries = StacksChainState::get_contract_transaction_receipts(&mut tx, &genesis.index_block_hash(), contract_id, 10).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].txid, call.txid());
+            assert_eq!(entries[0].events.len(), 1);
+        }
+        assert!(StacksChainState::get_contract_transaction_receipts(&mut tx, &genesis.index_block_hash(), &other_id, 10).unwrap().is_empty());
+        assert!(StacksChainState::get_contract_transaction_receipts(&mut tx, &genesis.index_block_hash(), &token_id, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::db::contract_costs::contract_costs;
+
+use util::db::query_rows;
+use util::db::u64_to_sql;
+
+/// The tables in the headers DB that are derived from blocks the node has already processed, and
+/// can be rebuilt from them without syncing again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivedIndex {
+    /// transfer memos, by recipient
+    TransferMemos,
+    /// transaction receipts, with their events
+    TransactionReceipts,
+    /// the execution costs of contract calls
+    ContractCosts,
+    /// the STX supply as of each block
+    StxSupply,
+    /// the balances of the accounts each block changed
+    AccountBalances,
+    /// transactions, by the principals they involve
+    AddressTransactions,
+}
+
+impl DerivedIndex {
+    /// Every derived index, in the order they're rebuilt
+    pub fn all() -> Vec<DerivedIndex> {
+        vec![DerivedIndex::TransferMemos, DerivedIndex::TransactionReceipts, DerivedIndex::ContractCosts, DerivedIndex::StxSupply, DerivedIndex::AccountBalances, DerivedIndex::AddressTransactions]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DerivedIndex::TransferMemos => "memos",
+            DerivedIndex::TransactionReceipts => "receipts",
+            DerivedIndex::ContractCosts => "contract-costs",
+            DerivedIndex::StxSupply => "supply",
+            DerivedIndex::AccountBalances => "balances",
+            DerivedIndex::AddressTransactions => "addresses",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DerivedIndex> {
+        DerivedIndex::all().into_iter().find(|index| index.name() == name)
+    }
+
+    /// Does rebuilding this index for a block mean executing the block again?
+    pub fn needs_execution(&self) -> bool {
+        match self {
+            DerivedIndex::TransferMemos => false,
+            _ => true
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Get the headers of the processed blocks at heights `from` through `to`, in every fork, in
+    /// height order
+    pub fn get_processed_headers_in_range(&self, from: u64, to: u64) -> Result<Vec<StacksHeaderInfo>, Error> {
+        let sql = "SELECT * FROM block_headers WHERE block_height >= ?1 AND block_height <= ?2 ORDER BY block_height, index_block_hash".to_string();
+        let args: &[&dyn ToSql] = &[&u64_to_sql(from).map_err(Error::DBError)?, &u64_to_sql(to).map_err(Error::DBError)?];
+        query_rows(&self.headers_db, &sql, args).map_err(Error::DBError)
+    }
+
+    /// Rebuild `indexes` for one processed block, from the block and the microblocks it confirms
+    /// as they're stored.  Transfer memos only need the block's data; the other indexes need it
+    /// to be executed again, on top of its parent's state, which must come to the state root in
+    /// its header.  Rows already there are replaced.  The STX supply is only stored if it's known
+    /// at the block's parent, as when the block was first processed, and so are the account
+    /// balances.  Errors if the block, or the microblocks it confirms, aren't stored anymore, or
+    /// if it was light-synced and an index needs it executed.
+    pub fn reindex_block(&mut self, index_block_hash: &StacksBlockId, indexes: &[DerivedIndex]) -> Result<(), Error> {
+        let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(&self.headers_db, index_block_hash)?
+            .ok_or(Error::NoSuchBlockError)?;
+        if header_info.block_height == 0 {
+            // the boot block isn't a block anyone mined, so nothing is derived from it
+            return Err(Error::NoSuchBlockError);
+        }
+
+        if indexes.iter().any(|index| index.needs_execution()) {
+            if let Some(light_sync_height) = StacksChainState::get_light_sync_height(&self.blocks_db)? {
+                if header_info.block_height >= light_sync_height {
+                    return Err(Error::InvalidStacksBlock(format!("Block {} was light-synced, so it can't be executed again", index_block_hash)));
+                }
+            }
+
+            let executed = self.execute_stored_block(index_block_hash)?;
+            if executed.state_root != header_info.anchored_header.state_index_root {
+                let msg = format!("Executing block {} again comes to state root {}, but its header has {}",
+                                  index_block_hash, &executed.state_root, &header_info.anchored_header.state_index_root);
+                return Err(Error::InvalidStacksBlock(msg));
+            }
+
+            let mut headers_tx = self.headers_tx_begin()?;
+            for index in indexes.iter() {
+                match index {
+                    DerivedIndex::TransferMemos => {
+                        StacksChainState::insert_transfer_memos(&mut headers_tx, &header_info, &executed.stored.block, &executed.stored.microblocks)?;
+                    },
+                    DerivedIndex::TransactionReceipts => {
+                        StacksChainState::insert_transaction_receipts(&mut headers_tx, &header_info, &executed.receipts)?;
+                    },
+                    DerivedIndex::ContractCosts => {
+                        StacksChainState::insert_contract_costs(&mut headers_tx, index_block_hash, header_info.block_height, &contract_costs(&executed.receipts))?;
+                    },
+                    DerivedIndex::StxSupply => {
+                        if let Some(parent_supply) = StacksChainState::get_stx_supply(&headers_tx, &executed.stored.parent_index_block_hash)? {
+                            let supply = parent_supply.next(executed.stx_fees, executed.stx_burns, &executed.matured_miner_rewards);
+                            StacksChainState::insert_stx_supply(&mut headers_tx, index_block_hash, &supply)?;
+                        }
+                    },
+                    DerivedIndex::AccountBalances => {
+                        StacksChainState::insert_account_balances(&mut headers_tx, Some(&executed.stored.parent_index_block_hash), index_block_hash,
+                                                                  header_info.block_height, &executed.accounts)?;
+                    },
+                    DerivedIndex::AddressTransactions => {
+                        StacksChainState::insert_address_transactions(&mut headers_tx, &header_info, &executed.receipts)?;
+                    }
+                }
+            }
+            headers_tx.commit().map_err(Error::DBError)?;
+        }
+        else {
+            let blocks_path = self.blocks_path.clone();
+            let (mut chainstate_tx, _) = self.chainstate_tx_begin()?;
+            let stored = StacksChainState::load_stored_block(&mut chainstate_tx, &blocks_path, index_block_hash)?;
+            StacksChainState::insert_transfer_memos(&mut chainstate_tx.headers_tx, &header_info, &stored.block, &stored.microblocks)?;
+            chainstate_tx.commit().map_err(Error::DBError)?;
+        }
+
+        debug!("Reindexed block {} at height {}", index_block_hash, header_info.block_height);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_derived_index_names() {
+        for index in DerivedIndex::all().into_iter() {
+            assert_eq!(DerivedIndex::from_name(index.name()), Some(index));
+        }
+        assert_eq!(DerivedIndex::from_name("tx-by-address"), None);
+    }
+
+    #[test]
+    fn test_reindex_boot_block() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_reindex_boot_block");
+        let headers = chainstate.get_processed_headers_in_range(0, 10).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].block_height, 0);
+
+        match chainstate.reindex_block(&headers[0].index_block_hash(), &DerivedIndex::all()) {
+            Err(Error::NoSuchBlockError) => {},
+            x => panic!("Reindexed the boot block: {:?}", &x)
+        }
+        assert!(chainstate.get_processed_headers_in_range(1, 10).unwrap().is_empty());
+    }
+}
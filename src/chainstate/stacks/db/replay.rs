@@ -0,0 +1,210 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::index::TrieHash;
+use chainstate::stacks::events::StacksTransactionReceipt;
+use chainstate::stacks::db::accounts::MinerReward;
+
+/// A processed block as it's stored, with the microblocks it confirms
+pub struct StoredBlock {
+    pub header_info: StacksHeaderInfo,
+    pub parent_info: StacksHeaderInfo,
+    /// the index hash its parent's derived data is stored under (the boot block's, if it's the
+    /// first mined block)
+    pub parent_index_block_hash: StacksBlockId,
+    pub block: StacksBlock,
+    pub microblocks: Vec<StacksMicroblock>,
+}
+
+/// What executing a processed block again came to
+pub struct ExecutedBlock {
+    pub stored: StoredBlock,
+    pub state_root: TrieHash,
+    /// the block's transactions' receipts, then its microblocks' transactions'
+    pub receipts: Vec<StacksTransactionReceipt>,
+    pub stx_fees: u128,
+    pub stx_burns: u128,
+    pub matured_miner_rewards: Vec<MinerReward>,
+    /// the accounts the block changed, as of the block
+    pub accounts: Vec<StacksAccount>,
+}
+
+impl StacksChainState {
+    /// Load a processed block, and the microblocks it confirms, from the chunk store.  Errors if
+    /// either isn't stored anymore.  The boot block isn't a block anyone mined, so it can't be
+    /// loaded.
+    pub fn load_stored_block<'a>(chainstate_tx: &mut ChainstateTx<'a>, blocks_path: &String, index_block_hash: &StacksBlockId) -> Result<StoredBlock, Error> {
+        let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(&chainstate_tx.headers_tx, index_block_hash)?
+            .ok_or(Error::NoSuchBlockError)?;
+        if header_info.block_height == 0 {
+            // the boot block isn't a block anyone mined
+            return Err(Error::NoSuchBlockError);
+        }
+        let parent_info = StacksChainState::get_index_tip_ancestor(&mut chainstate_tx.headers_tx, index_block_hash, header_info.block_height - 1)?
+            .ok_or(Error::NoSuchBlockError)?;
+
+        let block_hash = header_info.anchored_header.block_hash();
+        let block = match StacksChainState::load_block(blocks_path, &header_info.burn_header_hash, &block_hash)? {
+            Some(block) => block,
+            None => {
+                warn!("Block {} is no longer stored", index_block_hash);
+                return Err(Error::NoSuchBlockError);
+            }
+        };
+
+        let microblocks =
+            if block.header.parent_microblock == EMPTY_MICROBLOCK_PARENT_HASH && block.header.parent_microblock_sequence == 0 {
+                vec![]
+            }
+            else {
+                let mut microblocks = match StacksChainState::load_staging_microblock_stream(&chainstate_tx.blocks_tx, blocks_path, &parent_info.burn_header_hash,
+                                                                                             &parent_info.anchored_header.block_hash(), block.header.parent_microblock_sequence)? {
+                    Some(microblocks) => microblocks,
+                    None => {
+                        warn!("The microblocks block {} confirms are no longer stored", index_block_hash);
+                        return Err(Error::NoSuchBlockError);
+                    }
+                };
+                match StacksChainState::validate_parent_microblock_stream(&parent_info.anchored_header, &block.header, &microblocks, false) {
+                    Some((terminus, _)) => microblocks.truncate(terminus),
+                    None => {
+                        let msg = format!("Block {} does not confirm a stored microblock stream", index_block_hash);
+                        return Err(Error::InvalidStacksMicroblock(msg, block.header.parent_microblock.clone()));
+                    }
+                }
+                microblocks
+            };
+
+        let parent_index_block_hash =
+            if block.is_first_mined() {
+                StacksBlockHeader::make_index_block_hash(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH)
+            }
+            else {
+                parent_info.index_block_hash()
+            };
+
+        Ok(StoredBlock {
+            header_info,
+            parent_info,
+            parent_index_block_hash,
+            block,
+            microblocks,
+        })
+    }
+
+    /// Execute a processed block again, on top of its parent's state:  the transactions of the
+    /// microblocks it confirms, then its own, then the miner rewards that mature in it.  Nothing
+    /// is kept; the new state is rolled back.  Errors if the block, or the microblocks it
+    /// confirms, aren't stored anymore, or if executing them fails.
+    pub fn execute_stored_block(&mut self, index_block_hash: &StacksBlockId) -> Result<ExecutedBlock, Error> {
+        let blocks_path = self.blocks_path.clone();
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+        let stored = StacksChainState::load_stored_block(&mut chainstate_tx, &blocks_path, index_block_hash)?;
+        let (block, microblocks, parent_info) = (&stored.block, &stored.microblocks, &stored.parent_info);
+
+        let matured_miner_rewards_opt = StacksChainState::find_mature_miner_rewards(&mut chainstate_tx.headers_tx, parent_info, None)?;
+
+        let (parent_burn_header_hash, parent_block_hash) =
+            if block.is_first_mined() {
+                (FIRST_BURNCHAIN_BLOCK_HASH.clone(), FIRST_STACKS_BLOCK_HASH.clone())
+            }
+            else {
+                (parent_info.burn_header_hash.clone(), parent_info.anchored_header.block_hash())
+            };
+
+        let mut clarity_tx = StacksChainState::chainstate_block_begin(&chainstate_tx, clarity_instance, &parent_burn_header_hash, &parent_block_hash, &MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH);
+
+        let (microblock_fees, microblock_burns, mut microblock_receipts) = match StacksChainState::process_microblocks_transactions(&mut clarity_tx, microblocks) {
+            Ok(processed) => processed,
+            Err((e, offending_mblock_header_hash)) => {
+                clarity_tx.rollback_block();
+                let msg = format!("Invalid Stacks microblocks confirmed by {} (offender {}): {:?}", index_block_hash, offending_mblock_header_hash, &e);
+                return Err(Error::InvalidStacksMicroblock(msg, offending_mblock_header_hash));
+            }
+        };
+
+        let (block_fees, block_burns, mut receipts) = match StacksChainState::process_block_transactions(&mut clarity_tx, block) {
+            Ok(processed) => processed,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(Error::InvalidStacksBlock(format!("Invalid Stacks block {}: {:?}", index_block_hash, &e)));
+            }
+        };
+
+        if let Some(ref matured_miner_rewards) = matured_miner_rewards_opt {
+            if let Err(e) = StacksChainState::process_matured_miner_rewards(&mut clarity_tx, matured_miner_rewards) {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        }
+
+        let state_root = clarity_tx.get_root_hash();
+        let changed_principals = StacksChainState::balance_changed_principals(receipts.iter().chain(microblock_receipts.iter()));
+        let accounts = StacksChainState::get_accounts(&mut clarity_tx, &changed_principals);
+        clarity_tx.rollback_block();
+
+        receipts.append(&mut microblock_receipts);
+        let stx_fees = block_fees.checked_add(microblock_fees).expect("Overflow: Too many STX fees");
+        let stx_burns = block_burns.checked_add(microblock_burns).expect("Overflow: Too many STX burnt");
+
+        Ok(ExecutedBlock {
+            stored,
+            state_root,
+            receipts,
+            stx_fees,
+            stx_burns,
+            matured_miner_rewards: matured_miner_rewards_opt.unwrap_or(vec![]),
+            accounts,
+        })
+    }
+
+    /// Execute a processed block again, as execute_stored_block() does, and return the state
+    /// root that comes to, for comparing with the one in the block's header.
+    pub fn replay_block(&mut self, index_block_hash: &StacksBlockId) -> Result<TrieHash, Error> {
+        let executed = self.execute_stored_block(index_block_hash)?;
+        debug!("Replayed block {} at height {}: state root {}", index_block_hash, executed.stored.header_info.block_height, &executed.state_root);
+        Ok(executed.state_root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_replay_missing_blocks() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_replay_missing_blocks");
+
+        match chainstate.replay_block(&StacksBlockId([1u8; 32])) {
+            Err(Error::NoSuchBlockError) => {},
+            _ => panic!("Replayed a block that doesn't exist")
+        }
+
+        // the boot block has no block to replay
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+        match chainstate.replay_block(&genesis.index_block_hash()) {
+            Err(Error::NoSuchBlockError) => {},
+            _ => panic!("Replayed the boot block")
+        }
+    }
+}
@@ -0,0 +1,169 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::BTreeSet;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+
+/// What a key in the Clarity MARF holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateKey {
+    /// an account's STX balance or nonce, by principal
+    Account(String),
+    /// a deployed contract, by contract identifier
+    Contract(String),
+    /// an entry in a contract's data map, by contract identifier and map name
+    DataMapEntry(String, String),
+    /// a contract's data var, token balance, token supply or NFT owner, by contract identifier
+    /// and var, token or asset name
+    ContractData(String, String),
+    /// anything else
+    Other,
+}
+
+impl StateKey {
+    /// Work out what a Clarity MARF key holds from how it's made (see ClarityDatabase)
+    pub fn parse(key: &str) -> StateKey {
+        let parts : Vec<&str> = key.split("::").collect();
+        match parts.as_slice() {
+            ["vm-account", principal, _] => StateKey::Account(principal.to_string()),
+            ["clarity-contract", contract] => StateKey::Contract(contract.to_string()),
+            ["vm", contract, "0", map_name, _] => StateKey::DataMapEntry(contract.to_string(), map_name.to_string()),
+            ["vm", contract, _, name] | ["vm", contract, _, name, _] => StateKey::ContractData(contract.to_string(), name.to_string()),
+            _ => StateKey::Other
+        }
+    }
+}
+
+/// A key whose value differs between two blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateChange {
+    pub key: String,
+    pub state_key: StateKey,
+    /// the serialized value at the earlier block, if it was set
+    pub before: Option<String>,
+    /// the serialized value at the later block, if it's set
+    pub after: Option<String>,
+}
+
+impl StacksChainState {
+    /// Find the Clarity state that differs between the processed block `from` and its descendant
+    /// `to`:  every key written in a block after `from`, up to and including `to`, whose value at
+    /// `to` isn't its value at `from`.  Keys are in order.  Only blocks processed since the
+    /// Clarity side store started noting the keys each block writes are covered; keys written
+    /// by older blocks aren't found.
+    pub fn get_state_diff(&mut self, from: &StacksBlockId, to: &StacksBlockId) -> Result<Vec<StateChange>, Error> {
+        let from_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(&self.headers_db, from)?
+            .ok_or(Error::NoSuchBlockError)?;
+        let to_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(&self.headers_db, to)?
+            .ok_or(Error::NoSuchBlockError)?;
+
+        if let Some(light_sync_height) = StacksChainState::get_light_sync_height(&self.blocks_db)? {
+            if to_info.block_height >= light_sync_height {
+                return Err(Error::InvalidStacksBlock(format!("Block {} was light-synced, so it has no Clarity state", to)));
+            }
+        }
+
+        let ancestors = {
+            let mut headers_tx = self.headers_tx_begin()?;
+            let start_ancestor = StacksChainState::get_index_tip_ancestor(&mut headers_tx, to, from_info.block_height)?;
+            if to_info.block_height < from_info.block_height || start_ancestor.map(|info| info.index_block_hash()).as_ref() != Some(from) {
+                return Err(Error::InvalidStacksBlock(format!("Block {} is not an ancestor of block {}", from, to)));
+            }
+            StacksChainState::get_index_tip_ancestors(&mut headers_tx, to, from_info.block_height + 1, to_info.block_height)?
+        };
+
+        let mut keys = BTreeSet::new();
+        for header_info in ancestors.iter() {
+            keys.extend(self.clarity_state.get_written_keys(&header_info.index_block_hash()).into_iter());
+        }
+
+        let mut changes = vec![];
+        for key in keys.into_iter() {
+            let before = self.clarity_state.get_value_at(from, &key);
+            let after = self.clarity_state.get_value_at(to, &key);
+            if before != after {
+                changes.push(StateChange {
+                    state_key: StateKey::parse(&key),
+                    key, before, after
+                });
+            }
+        }
+
+        debug!("State diff {} ({}) to {} ({}): {} keys changed", from, from_info.block_height, to, to_info.block_height, changes.len());
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::db::test::instantiate_chainstate;
+    use vm::database::marf::MarfedKV;
+
+    #[test]
+    fn test_parse_state_key() {
+        assert_eq!(StateKey::parse("vm-account::SP000000000000000000002Q6VF78::19"),
+                   StateKey::Account("SP000000000000000000002Q6VF78".to_string()));
+        assert_eq!(StateKey::parse("clarity-contract::SP000000000000000000002Q6VF78.names"),
+                   StateKey::Contract("SP000000000000000000002Q6VF78.names".to_string()));
+        assert_eq!(StateKey::parse("vm::SP000000000000000000002Q6VF78.names::0::owners::0c00"),
+                   StateKey::DataMapEntry("SP000000000000000000002Q6VF78.names".to_string(), "owners".to_string()));
+        assert_eq!(StateKey::parse("vm::SP000000000000000000002Q6VF78.names::1::counter"),
+                   StateKey::ContractData("SP000000000000000000002Q6VF78.names".to_string(), "counter".to_string()));
+        assert_eq!(StateKey::parse("vm::SP000000000000000000002Q6VF78.names::2::stackaroo::0c00"),
+                   StateKey::ContractData("SP000000000000000000002Q6VF78.names".to_string(), "stackaroo".to_string()));
+        assert_eq!(StateKey::parse("something-else"), StateKey::Other);
+    }
+
+    #[test]
+    fn test_written_keys() {
+        let mut marf = MarfedKV::temporary();
+        let block = StacksBlockId([1u8; 32]);
+        marf.begin(&StacksBlockId::sentinel(), &block);
+        marf.put("b", "1");
+        marf.put("a", "2");
+        marf.commit_to(&block);
+
+        assert_eq!(marf.get_written_keys(&block), vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(marf.get_at(&block, "a"), Some("2".to_string()));
+        assert_eq!(marf.get_written_keys(&StacksBlockId([2u8; 32])), Vec::<String>::new());
+
+        // keys written in a block that's thrown away aren't kept
+        let dropped = StacksBlockId([3u8; 32]);
+        marf.begin(&block, &dropped);
+        marf.put("c", "3");
+        marf.rollback();
+        assert_eq!(marf.get_written_keys(&dropped), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_state_diff_bad_blocks() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_state_diff_bad_blocks");
+        let genesis = StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap();
+
+        assert_eq!(chainstate.get_state_diff(&genesis.index_block_hash(), &genesis.index_block_hash()).unwrap(), vec![]);
+        match chainstate.get_state_diff(&genesis.index_block_hash(), &StacksBlockId([1u8; 32])) {
+            Err(Error::NoSuchBlockError) => {},
+            _ => panic!("Found the state diff to a block that doesn't exist")
+        }
+    }
+}
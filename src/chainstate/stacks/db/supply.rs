@@ -0,0 +1,167 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rusqlite::Row;
+use rusqlite::Connection;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::Error;
+use chainstate::stacks::*;
+use chainstate::stacks::db::*;
+use chainstate::stacks::db::accounts::MinerReward;
+
+use util::db::Error as db_error;
+use util::db::{
+    FromRow,
+    FromColumn,
+    query_rows,
+    u64_to_sql,
+};
+
+/// The STX supply as of each processed block.  Created on open if missing; blocks processed
+/// before this table existed have no supply, and neither do their descendants, since each
+/// block's supply is its parent's plus what the block changed.
+pub const STX_SUPPLY_SQL : &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS stx_supply(
+        index_block_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        liquid_ustx TEXT NOT NULL,          -- encodes u128
+        locked_ustx TEXT NOT NULL,          -- encodes u128
+
+        PRIMARY KEY(index_block_hash)
+    );
+    "#,
+];
+
+/// The STX supply as of a block, in microSTX.  Liquid STX are the ones in account balances.
+/// Locked STX are the ones miners have earned but can't spend yet: matured rewards granted to
+/// the miner trust fund contract, and transaction fees that will be paid out once their
+/// block's rewards mature.  Coinbases count once they mature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StxSupply {
+    pub block_height: u64,
+    pub liquid_ustx: u128,
+    pub locked_ustx: u128,
+}
+
+impl FromRow<StxSupply> for StxSupply {
+    fn from_row<'a>(row: &'a Row) -> Result<StxSupply, db_error> {
+        let block_height = u64::from_column(row, "block_height")?;
+        let liquid_text : String = row.get("liquid_ustx");
+        let locked_text : String = row.get("locked_ustx");
+
+        let liquid_ustx = liquid_text.parse::<u128>().map_err(|_e| db_error::ParseError)?;
+        let locked_ustx = locked_text.parse::<u128>().map_err(|_e| db_error::ParseError)?;
+
+        Ok(StxSupply {
+            block_height,
+            liquid_ustx,
+            locked_ustx
+        })
+    }
+}
+
+impl StxSupply {
+    /// The supply of the boot block, whose initial balances are all liquid
+    pub fn genesis(initial_balances: u128) -> StxSupply {
+        StxSupply {
+            block_height: 0,
+            liquid_ustx: initial_balances,
+            locked_ustx: 0
+        }
+    }
+
+    /// The supply after a child block that collects `fees` and burns `burns` from account
+    /// balances, and grants `matured_rewards` to the miner trust fund.  The fees in the matured
+    /// rewards were already counted as locked when their block collected them.
+    pub fn next(&self, fees: u128, burns: u128, matured_rewards: &[MinerReward]) -> StxSupply {
+        let matured_coinbase = matured_rewards.iter()
+            .fold(0u128, |total, reward| total.checked_add(reward.coinbase).expect("FATAL: STX supply overflow"));
+
+        StxSupply {
+            block_height: self.block_height + 1,
+            liquid_ustx: self.liquid_ustx
+                .checked_sub(fees).expect("FATAL: STX supply underflow")
+                .checked_sub(burns).expect("FATAL: STX supply underflow"),
+            locked_ustx: self.locked_ustx
+                .checked_add(fees).expect("FATAL: STX supply overflow")
+                .checked_add(matured_coinbase).expect("FATAL: STX supply overflow"),
+        }
+    }
+
+    /// All STX in existence
+    pub fn total_ustx(&self) -> u128 {
+        self.liquid_ustx + self.locked_ustx
+    }
+}
+
+impl StacksChainState {
+    /// Store the STX supply as of a block
+    pub fn insert_stx_supply<'a>(tx: &mut StacksDBTx<'a>, index_block_hash: &StacksBlockId, supply: &StxSupply) -> Result<(), Error> {
+        let args: &[&dyn ToSql] = &[index_block_hash, &u64_to_sql(supply.block_height).map_err(Error::DBError)?, &supply.liquid_ustx.to_string(), &supply.locked_ustx.to_string()];
+        tx.execute("INSERT OR REPLACE INTO stx_supply (index_block_hash, block_height, liquid_ustx, locked_ustx) VALUES (?1, ?2, ?3, ?4)", args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Get the STX supply as of a block, if it's known
+    pub fn get_stx_supply(conn: &Connection, index_block_hash: &StacksBlockId) -> Result<Option<StxSupply>, Error> {
+        let sql = "SELECT * FROM stx_supply WHERE index_block_hash = ?1".to_string();
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        let mut rows = query_rows::<StxSupply, _>(conn, &sql, args).map_err(Error::DBError)?;
+        Ok(rows.pop())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_reward(coinbase: u128, fees: u128) -> MinerReward {
+        MinerReward {
+            address: StacksAddress { version: 1, bytes: Hash160([0u8; 20]) },
+            coinbase,
+            tx_fees_anchored_shared: fees,
+            tx_fees_anchored_exclusive: 0,
+            tx_fees_streamed_produced: 0,
+            tx_fees_streamed_confirmed: 0,
+            vtxindex: 0
+        }
+    }
+
+    #[test]
+    fn test_stx_supply_next() {
+        let genesis = StxSupply::genesis(1000);
+        assert_eq!(genesis.total_ustx(), 1000);
+
+        // fees move from liquid to locked; burns leave the supply
+        let supply = genesis.next(10, 5, &[]);
+        assert_eq!(supply.block_height, 1);
+        assert_eq!(supply.liquid_ustx, 985);
+        assert_eq!(supply.locked_ustx, 10);
+        assert_eq!(supply.total_ustx(), 995);
+
+        // matured coinbases are new STX, but their fees were already locked
+        let supply = supply.next(0, 0, &[make_reward(500, 10), make_reward(100, 0)]);
+        assert_eq!(supply.liquid_ustx, 985);
+        assert_eq!(supply.locked_ustx, 610);
+        assert_eq!(supply.total_ustx(), 1595);
+    }
+}
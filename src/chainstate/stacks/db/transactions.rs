@@ -54,12 +54,16 @@ use vm::types::{
 
 use vm::contexts::{
     AssetMap,
-    AssetMapEntry
+    AssetMapEntry,
+    OwnedEnvironment
 };
+use vm::representations::{SymbolicExpression, ClarityName};
 
 use vm::ast::build_ast;
+use vm::calltrace;
+use vm::statetrace;
 use vm::analysis::run_analysis;
-use vm::costs::ExecutionCost;
+use vm::costs::{ExecutionCost, LimitedCostTracker};
 
 use vm::types::{
     Value,
@@ -103,7 +107,10 @@ impl StacksTransactionReceipt {
             post_condition_aborted: false,
             contract_analysis: None,
             transaction: tx,
-            execution_cost: cost
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: None,
         }
     }
 
@@ -115,7 +122,10 @@ impl StacksTransactionReceipt {
             result,
             stx_burned: burned,
             contract_analysis: None,
-            execution_cost: cost
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: None,
         }
     }
 
@@ -127,7 +137,25 @@ impl StacksTransactionReceipt {
             result,
             stx_burned: burned,
             contract_analysis: None,
-            execution_cost: cost
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: Some(TransactionAbortCode::PostCondition),
+        }
+    }
+
+    pub fn from_runtime_error_contract_call(tx: StacksTransaction, abort_code: TransactionAbortCode, cost: ExecutionCost) -> StacksTransactionReceipt {
+        StacksTransactionReceipt {
+            transaction: tx,
+            post_condition_aborted: false,
+            events: vec![],
+            result: Value::err_none(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: Some(abort_code),
         }
     }
 
@@ -139,7 +167,10 @@ impl StacksTransactionReceipt {
             result: Value::okay_true(),
             stx_burned: burned,
             contract_analysis: Some(analysis),
-            execution_cost: cost
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: None,
         }
     }
 
@@ -151,7 +182,25 @@ impl StacksTransactionReceipt {
             result: Value::okay_true(),
             stx_burned: burned,
             contract_analysis: Some(analysis),
-            execution_cost: cost
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: Some(TransactionAbortCode::PostCondition),
+        }
+    }
+
+    pub fn from_runtime_error_smart_contract(tx: StacksTransaction, analysis: ContractAnalysis, abort_code: TransactionAbortCode, cost: ExecutionCost) -> StacksTransactionReceipt {
+        StacksTransactionReceipt {
+            transaction: tx,
+            events: vec![],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: Some(analysis),
+            execution_cost: cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: Some(abort_code),
         }
     }
 
@@ -163,7 +212,10 @@ impl StacksTransactionReceipt {
             result: Value::okay_true(),
             stx_burned: 0,
             contract_analysis: None,
-            execution_cost: ExecutionCost::zero()
+            execution_cost: ExecutionCost::zero(),
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: None,
         }
     }
 
@@ -176,10 +228,72 @@ impl StacksTransactionReceipt {
             stx_burned: 0,
             contract_analysis: None,
             execution_cost: analysis_cost,
+            call_trace: vec![],
+            state_trace: None,
+            abort_code: Some(TransactionAbortCode::AnalysisFailure),
         }
     }
 }
 
+/// What running a transaction's payload would cost, found by running it without storing anything
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadCostEstimate {
+    /// what the payload cost to run, up to where it stopped
+    pub cost: ExecutionCost,
+    /// a contract call's return value
+    pub result: Option<Value>,
+    /// the runtime error that stopped the payload, if any.  A transaction that hits one is still
+    /// mined, and still pays its fee.
+    pub runtime_error: Option<String>,
+}
+
+/// A change to make to chain state before simulating a transaction, to see what it would do if
+/// the chain were in that state.  Overrides only last as long as the simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateOverride {
+    /// set an account's STX balance
+    StxBalance(PrincipalData, u128),
+    /// set an account's balance of a contract's fungible token
+    FungibleTokenBalance(QualifiedContractIdentifier, ClarityName, PrincipalData, u128),
+    /// set an entry of a contract's map, or delete it if there's no value
+    MapEntry(QualifiedContractIdentifier, ClarityName, Value, Option<Value>),
+}
+
+impl StateOverride {
+    fn apply(&self, clarity_db: &mut ClarityDatabase) -> Result<(), InterpreterError> {
+        match *self {
+            StateOverride::StxBalance(ref principal, balance) => {
+                clarity_db.set_account_stx_balance(principal, balance);
+            },
+            StateOverride::FungibleTokenBalance(ref contract_id, ref token_name, ref principal, balance) => {
+                // make sure the token exists
+                clarity_db.get_ft_balance(contract_id, token_name, principal)?;
+                clarity_db.set_ft_balance(contract_id, token_name, principal, balance)?;
+            },
+            StateOverride::MapEntry(ref contract_id, ref map_name, ref key, Some(ref value)) => {
+                clarity_db.set_entry(contract_id, map_name, key.clone(), value.clone())?;
+            },
+            StateOverride::MapEntry(ref contract_id, ref map_name, ref key, None) => {
+                clarity_db.delete_entry(contract_id, map_name, key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What simulating a transaction's payload against a set of state overrides did
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadSimulation {
+    /// what the payload cost to run, up to where it stopped
+    pub cost: ExecutionCost,
+    /// the contract call's return value
+    pub result: Option<Value>,
+    /// the events the payload emitted, if it ran to completion
+    pub events: Vec<StacksTransactionEvent>,
+    /// the runtime error that stopped the payload, if any
+    pub runtime_error: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct TransactionNonceMismatch {
     pub expected: u64,
@@ -283,6 +397,11 @@ impl StacksChainState {
         // valid auth?
         tx.verify().map_err(Error::NetError)?;
 
+        StacksChainState::process_transaction_precheck_network(config, tx)
+    }
+
+    /// Pre-check that a transaction is for this chain, without checking its signatures
+    fn process_transaction_precheck_network(config: &DBConfig, tx: &StacksTransaction) -> Result<(), Error> {
         // destined for us?
         if config.chain_id != tx.chain_id {
             let msg = format!("Invalid tx {}: invalid chain ID {} (expected {})", tx.txid(), tx.chain_id, config.chain_id);
@@ -561,7 +680,9 @@ impl StacksChainState {
                             // runtime errors are okay -- we just have an empty asset map
                             clarity_error::Interpreter(InterpreterError::Runtime(ref runtime_error, ref stack)) => {
                                 info!("Runtime error {:?} on contract-call {}.{:?} {:?}, stack trace {:?}", runtime_error, &contract_id, &contract_call.function_name, &contract_call.function_args, stack);
-                                Ok((Value::err_none(), AssetMap::new(), vec![]))
+                                let receipt = StacksTransactionReceipt::from_runtime_error_contract_call(
+                                    tx.clone(), TransactionAbortCode::from_runtime_error(runtime_error), total_cost);
+                                return Ok(receipt);
                             },
                             clarity_error::AbortedByCallback(value, assets, events) => {
                                 let receipt = StacksTransactionReceipt::from_condition_aborted_contract_call(
@@ -673,7 +794,12 @@ impl StacksChainState {
                             // runtime errors are okay -- we just have an empty asset map
                             clarity_error::Interpreter(InterpreterError::Runtime(ref runtime_error, ref stack)) => {
                                 info!("Runtime error {:?} on instantiating {}, code {:?}, stack trace {:?}", runtime_error, &contract_id, &contract_code_str, stack);
-                                Ok((AssetMap::new(), vec![]))
+                                clarity_tx.save_analysis(&contract_id, &contract_analysis)
+                                    .expect("FATAL: failed to store contract analysis");
+
+                                let receipt = StacksTransactionReceipt::from_runtime_error_smart_contract(
+                                    tx.clone(), contract_analysis, TransactionAbortCode::from_runtime_error(runtime_error), total_cost);
+                                return Ok(receipt);
                             },
                             _ => Err(e)
                         }
@@ -715,16 +841,134 @@ impl StacksChainState {
         }
     }
 
-    /// Process a transaction.  Return the fee and the transaction receipt
+    /// Run a transaction payload sent by `sender` as of this connection's chain tip, within
+    /// `cost_limit`, to see what it would cost.  Nothing is stored.  Token transfers don't run
+    /// any Clarity code, so they cost nothing; coinbases and poison-microblock reports can't be
+    /// estimated.
+    pub fn estimate_payload_cost<T: ClarityConnection>(clarity_tx: &mut T, sender: &StacksAddress, payload: &TransactionPayload,
+                                                       cost_limit: ExecutionCost) -> Result<PayloadCostEstimate, Error> {
+        match payload {
+            TransactionPayload::TokenTransfer(..) => {
+                Ok(PayloadCostEstimate { cost: ExecutionCost::zero(), result: None, runtime_error: None })
+            },
+            TransactionPayload::ContractCall(ref contract_call) => {
+                let contract_id = contract_call.to_clarity_contract_id();
+                let args: Vec<_> = contract_call.function_args.iter()
+                    .map(|arg| SymbolicExpression::atom_value(arg.clone()))
+                    .collect();
+                let sender = Value::Principal(sender.clone().into());
+
+                let (result, cost) = clarity_tx.with_clarity_db_readonly_owned(|clarity_db| {
+                    let mut vm_env = OwnedEnvironment::new_cost_limited(clarity_db, LimitedCostTracker::new(cost_limit));
+                    let result = vm_env.execute_transaction(sender, contract_id, &contract_call.function_name, &args);
+                    let (db, cost_track) = vm_env.destruct()
+                        .expect("Failed to recover database reference after contract-call");
+                    ((result, cost_track.get_total()), db)
+                });
+
+                match result {
+                    Ok((value, _, _)) => Ok(PayloadCostEstimate { cost, result: Some(value), runtime_error: None }),
+                    // the transaction would still be mined
+                    Err(e @ InterpreterError::Runtime(..)) => Ok(PayloadCostEstimate { cost, result: None, runtime_error: Some(e.to_string()) }),
+                    Err(e) => Err(Error::ClarityError(e.into()))
+                }
+            },
+            TransactionPayload::SmartContract(ref smart_contract) => {
+                let contract_id = QualifiedContractIdentifier::new(sender.clone().into(), smart_contract.name.clone());
+                let dry_run = StacksChainState::dry_run_contract_deploy(clarity_tx, &contract_id, &smart_contract.code_body.to_string(), cost_limit)?;
+                match dry_run.deploy_cost {
+                    Some(cost) => Ok(PayloadCostEstimate { cost, result: None, runtime_error: None }),
+                    None => Err(Error::InvalidStacksTransaction(format!("Duplicate contract '{}'", &contract_id)))
+                }
+            },
+            TransactionPayload::Coinbase(_) | TransactionPayload::PoisonMicroblock(..) => {
+                Err(Error::InvalidStacksTransaction("Only token transfers, contract calls and smart contracts can be estimated".to_string()))
+            }
+        }
+    }
+
+    /// Run a contract call sent by `sender` as of this connection's chain tip, within
+    /// `cost_limit`, after making the given changes to chain state.  Nothing is stored, overrides
+    /// included.  Overrides that don't apply (e.g., to a map that doesn't exist) make the whole
+    /// simulation invalid.
+    pub fn simulate_payload<T: ClarityConnection>(clarity_tx: &mut T, sender: &StacksAddress, payload: &TransactionPayload,
+                                                  overrides: &[StateOverride], cost_limit: ExecutionCost) -> Result<PayloadSimulation, Error> {
+        let contract_call = match payload {
+            TransactionPayload::ContractCall(ref contract_call) => contract_call,
+            _ => return Err(Error::InvalidStacksTransaction("Only contract calls can be simulated".to_string()))
+        };
+
+        let contract_id = contract_call.to_clarity_contract_id();
+        let args: Vec<_> = contract_call.function_args.iter()
+            .map(|arg| SymbolicExpression::atom_value(arg.clone()))
+            .collect();
+        let sender = Value::Principal(sender.clone().into());
+
+        let result = clarity_tx.with_clarity_db_readonly_owned(|mut clarity_db| {
+            for (i, state_override) in overrides.iter().enumerate() {
+                if let Err(e) = state_override.apply(&mut clarity_db) {
+                    let msg = format!("Invalid state override {}: {}", i, e);
+                    return (Err(Error::InvalidStacksTransaction(msg)), clarity_db);
+                }
+            }
+
+            let mut vm_env = OwnedEnvironment::new_cost_limited(clarity_db, LimitedCostTracker::new(cost_limit));
+            let result = vm_env.execute_transaction(sender, contract_id, &contract_call.function_name, &args);
+            let (db, cost_track) = vm_env.destruct()
+                .expect("Failed to recover database reference after contract-call");
+            (Ok((result, cost_track.get_total())), db)
+        });
+
+        let (result, cost) = result?;
+        match result {
+            Ok((value, _, events)) => Ok(PayloadSimulation { cost, result: Some(value), events, runtime_error: None }),
+            // the transaction would still be mined
+            Err(e @ InterpreterError::Runtime(..)) => Ok(PayloadSimulation { cost, result: None, events: vec![], runtime_error: Some(e.to_string()) }),
+            Err(e) => Err(Error::ClarityError(e.into()))
+        }
+    }
+
+    /// Process a transaction.  Return the fee and the transaction receipt.
+    /// If this thread is recording a state trace, the transaction's reads and writes go in the
+    /// receipt, and not in the enclosing trace.
     pub fn process_transaction(clarity_block: &mut ClarityTx, tx: &StacksTransaction) -> Result<(u64, StacksTransactionReceipt), Error> {
+        if !statetrace::is_tracing() {
+            return StacksChainState::inner_process_transaction(clarity_block, tx);
+        }
+
+        statetrace::begin();
+        let result = StacksChainState::inner_process_transaction(clarity_block, tx);
+        let state_trace = statetrace::finish();
+        let (fee, mut tx_receipt) = result?;
+        tx_receipt.state_trace = Some(state_trace);
+        Ok((fee, tx_receipt))
+    }
+
+    fn inner_process_transaction(clarity_block: &mut ClarityTx, tx: &StacksTransaction) -> Result<(u64, StacksTransactionReceipt), Error> {
         debug!("Process transaction {}", tx.txid());
 
-        StacksChainState::process_transaction_precheck(&clarity_block.config, tx)?;
+        let signature_checked = match clarity_block.signatures {
+            Some(ref mut verifier) => verifier.is_verified(&tx.txid()),
+            None => false
+        };
+
+        if clarity_block.assume_valid || signature_checked {
+            StacksChainState::process_transaction_precheck_network(&clarity_block.config, tx)?;
+        }
+        else {
+            StacksChainState::process_transaction_precheck(&clarity_block.config, tx)?;
+        }
 
         let mut transaction = clarity_block.connection().start_transaction_processing();
         let (origin_account, payer_account) = StacksChainState::check_transaction_nonces(&mut transaction, tx)?;
 
-        let tx_receipt = StacksChainState::process_transaction_payload(&mut transaction, tx, &origin_account)?;
+        if clarity_block.trace_contract_calls {
+            calltrace::begin();
+        }
+        let tx_receipt = StacksChainState::process_transaction_payload(&mut transaction, tx, &origin_account);
+        let call_trace = calltrace::finish();
+        let mut tx_receipt = tx_receipt?;
+        tx_receipt.call_trace = call_trace;
 
         // pay fee borne by runtime costs.
         // NOTE: the fee must be paid _after_ we run the payload, because we will (eventually) be
@@ -762,6 +1006,7 @@ pub mod test {
 
     use vm::contracts::Contract;
     use vm::types::*;
+    use vm::database::ClaritySerializable;
     use vm::representations::ContractName;
     use vm::representations::ClarityName;
 
@@ -1429,11 +1674,12 @@ pub mod test {
         let contract_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(addr.clone()), ContractName::from("hello-world"));
         let (_fee, _) = StacksChainState::process_transaction(&mut conn, &signed_tx).unwrap();
 
-        // contract-calls that don't commit
+        // contract-calls that don't commit, how their traces end, and why they were aborted
         let contract_calls = vec![
-            ("hello-world", "set-bar", vec![Value::Int(1), Value::Int(0)]),     // divide-by-zero
-            ("hello-world", "return-error", vec![]),                            // returns an (err ...)
+            ("hello-world", "set-bar", vec![Value::Int(1), Value::Int(0)], Err("DivisionByZero".to_string()), Some(TransactionAbortCode::Arithmetic)),  // divide-by-zero
+            ("hello-world", "return-error", vec![], Ok(Value::error(Value::Int(1)).unwrap()), None),                                                // returns an (err ...)
         ];
+        conn.trace_contract_calls = true;
 
         // do contract-calls
         let privk_2 = StacksPrivateKey::from_hex("d2c340ebcc0794b6fabdd8ac8b1c983e363b05dc8adcdf7e30db205a3fa54c1601").unwrap();
@@ -1443,10 +1689,10 @@ pub mod test {
         let mut next_nonce = 0;
 
         for contract_call in contract_calls {
-            let (contract_name, contract_function, contract_args) = contract_call;
+            let (contract_name, contract_function, contract_args, expected_result, expected_abort_code) = contract_call;
             let mut tx_contract_call = StacksTransaction::new(TransactionVersion::Testnet,
                                                               auth_2.clone(),
-                                                              TransactionPayload::new_contract_call(addr.clone(), contract_name, contract_function, contract_args).unwrap());
+                                                              TransactionPayload::new_contract_call(addr.clone(), contract_name, contract_function, contract_args.clone()).unwrap());
 
             tx_contract_call.chain_id = 0x80000000;
             tx_contract_call.set_fee_rate(0);
@@ -1460,7 +1706,15 @@ pub mod test {
             let account_2 = StacksChainState::get_account(&mut conn, &addr_2.to_account_principal());
             assert_eq!(account_2.nonce, next_nonce);
         
-            let (_fee, _) = StacksChainState::process_transaction(&mut conn, &signed_tx_2).unwrap();
+            let (_fee, receipt) = StacksChainState::process_transaction(&mut conn, &signed_tx_2).unwrap();
+
+            // the receipt says how the call ended
+            assert_eq!(receipt.call_trace.len(), 1);
+            assert_eq!(receipt.call_trace[0].contract_identifier, contract_id);
+            assert_eq!(receipt.call_trace[0].function, contract_function);
+            assert_eq!(receipt.call_trace[0].args, contract_args);
+            assert_eq!(receipt.call_trace[0].result, expected_result);
+            assert_eq!(receipt.abort_code, expected_abort_code);
 
             // nonce should have incremented
             next_nonce += 1;
@@ -1475,6 +1729,213 @@ pub mod test {
         conn.commit_block();
     }
 
+    #[test]
+    fn process_smart_contract_state_trace() {
+        let contract = "
+        (define-data-var bar int 1)
+        (define-public (set-bar (x int) (y int))
+          (begin (var-set bar (/ x y)) (ok (var-get bar))))";
+
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "process-smart-contract-state-trace");
+
+        let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+
+        let mut tx_contract = StacksTransaction::new(TransactionVersion::Testnet,
+                                                     auth.clone(),
+                                                     TransactionPayload::new_smart_contract(&"hello-world".to_string(), &contract.to_string()).unwrap());
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let mut tx_contract_call = StacksTransaction::new(TransactionVersion::Testnet,
+                                                          auth.clone(),
+                                                          TransactionPayload::new_contract_call(addr.clone(), "hello-world", "set-bar", vec![Value::Int(6), Value::Int(2)]).unwrap());
+        tx_contract_call.chain_id = 0x80000000;
+        tx_contract_call.set_fee_rate(0);
+        tx_contract_call.set_origin_nonce(1);
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract_call);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx_call = signer.get_tx().unwrap();
+
+        let mut conn = chainstate.block_begin(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, &BurnchainHeaderHash([1u8; 32]), &BlockHeaderHash([1u8; 32]));
+
+        // not traced unless this thread is tracing
+        let (_, receipt) = StacksChainState::process_transaction(&mut conn, &signed_tx).unwrap();
+        assert_eq!(receipt.state_trace, None);
+
+        statetrace::begin();
+        let (_, receipt) = StacksChainState::process_transaction(&mut conn, &signed_tx_call).unwrap();
+        // the transaction's accesses go in its receipt, not the enclosing trace
+        assert_eq!(statetrace::finish(), statetrace::StateTrace::default());
+        conn.commit_block();
+
+        let contract_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(addr.clone()), ContractName::from("hello-world"));
+        let bar_key = format!("vm::{}::1::bar", contract_id);
+        let state_trace = receipt.state_trace.unwrap();
+
+        // `bar` is only read after it's written, so it's read from the transaction's own writes
+        assert!(state_trace.reads.iter().all(|(key, _)| *key != bar_key));
+        assert!(state_trace.writes.contains(&(bar_key, Value::Int(3).serialize())));
+        assert!(state_trace.metadata_reads.iter().any(|(contract, _, _)| *contract == contract_id));
+        assert_eq!(state_trace.metadata_writes, vec![]);
+    }
+
+    #[test]
+    fn estimate_payload_cost() {
+        let contract = "
+        (define-data-var bar int 1)
+        (define-public (set-bar (x int) (y int))
+          (begin (var-set bar (/ x y)) (ok (var-get bar))))";
+
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "estimate-payload-cost");
+
+        let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+
+        let contract_payload = TransactionPayload::new_smart_contract(&"hello-world".to_string(), &contract.to_string()).unwrap();
+        let mut tx_contract = StacksTransaction::new(TransactionVersion::Testnet, auth.clone(), contract_payload.clone());
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let mut conn = chainstate.block_begin(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, &BurnchainHeaderHash([1u8; 32]), &BlockHeaderHash([1u8; 32]));
+        let cost_limit = ExecutionCost::max_value();
+
+        let estimate = StacksChainState::estimate_payload_cost(&mut conn, &addr, &contract_payload, cost_limit.clone()).unwrap();
+        assert!(estimate.cost.runtime > 0);
+        assert_eq!(estimate.runtime_error, None);
+        StacksChainState::process_transaction(&mut conn, &signed_tx).unwrap();
+
+        // a deployed contract can't be deployed again
+        match StacksChainState::estimate_payload_cost(&mut conn, &addr, &contract_payload, cost_limit.clone()) {
+            Err(Error::InvalidStacksTransaction(_)) => {},
+            x => panic!("Expected a duplicate contract error, got {:?}", &x)
+        }
+
+        let call = TransactionPayload::new_contract_call(addr.clone(), "hello-world", "set-bar", vec![Value::Int(6), Value::Int(2)]).unwrap();
+        let estimate = StacksChainState::estimate_payload_cost(&mut conn, &addr, &call, cost_limit.clone()).unwrap();
+        assert_eq!(estimate.result, Some(Value::okay(Value::Int(3)).unwrap()));
+        assert!(estimate.cost.runtime > 0);
+        assert_eq!(estimate.runtime_error, None);
+
+        // runtime errors still have a cost
+        let bad_call = TransactionPayload::new_contract_call(addr.clone(), "hello-world", "set-bar", vec![Value::Int(6), Value::Int(0)]).unwrap();
+        let estimate = StacksChainState::estimate_payload_cost(&mut conn, &addr, &bad_call, cost_limit.clone()).unwrap();
+        assert_eq!(estimate.result, None);
+        assert!(estimate.cost.runtime > 0);
+        assert!(estimate.runtime_error.is_some());
+
+        // nothing was written
+        let contract_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(addr.clone()), ContractName::from("hello-world"));
+        let bar = conn.with_clarity_db_readonly(|db| db.lookup_variable(&contract_id, "bar").unwrap());
+        assert_eq!(bar, Value::Int(1));
+
+        let missing = TransactionPayload::new_contract_call(addr.clone(), "goodbye-world", "set-bar", vec![Value::Int(6), Value::Int(2)]).unwrap();
+        match StacksChainState::estimate_payload_cost(&mut conn, &addr, &missing, cost_limit.clone()) {
+            Err(Error::ClarityError(_)) => {},
+            x => panic!("Expected a Clarity error, got {:?}", &x)
+        }
+
+        let transfer = TransactionPayload::TokenTransfer(addr.clone().into(), 123, TokenTransferMemo([0u8; 34]));
+        let estimate = StacksChainState::estimate_payload_cost(&mut conn, &addr, &transfer, cost_limit.clone()).unwrap();
+        assert_eq!(estimate.cost, ExecutionCost::zero());
+
+        conn.commit_block();
+    }
+
+    #[test]
+    fn simulate_payload() {
+        let contract = "
+        (define-fungible-token coin)
+        (define-map limits ((owner principal)) ((amount uint)))
+        (define-public (spend (amount uint))
+          (let ((limit (default-to u0 (get amount (map-get? limits { owner: tx-sender })))))
+            (asserts! (<= amount limit) (err u1))
+            (try! (stx-transfer? amount tx-sender 'ST000000000000000000002AMW42H))
+            (ft-transfer? coin amount tx-sender 'ST000000000000000000002AMW42H)))";
+
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "simulate-payload");
+
+        let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+        let principal = PrincipalData::from(addr.clone());
+        let contract_id = QualifiedContractIdentifier::new(StandardPrincipalData::from(addr.clone()), ContractName::from("spender"));
+
+        let contract_payload = TransactionPayload::new_smart_contract(&"spender".to_string(), &contract.to_string()).unwrap();
+        let mut tx_contract = StacksTransaction::new(TransactionVersion::Testnet, auth.clone(), contract_payload.clone());
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let mut conn = chainstate.block_begin(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH, &BurnchainHeaderHash([1u8; 32]), &BlockHeaderHash([1u8; 32]));
+        let cost_limit = ExecutionCost::max_value();
+        StacksChainState::process_transaction(&mut conn, &signed_tx).unwrap();
+
+        let call = TransactionPayload::new_contract_call(addr.clone(), "spender", "spend", vec![Value::UInt(100)]).unwrap();
+
+        // without overrides, the sender has no limit
+        let simulation = StacksChainState::simulate_payload(&mut conn, &addr, &call, &[], cost_limit.clone()).unwrap();
+        assert_eq!(simulation.result, Some(Value::err_uint(1)));
+        assert!(simulation.events.is_empty());
+
+        let limit_key = Value::Tuple(TupleData::from_data(vec![("owner".into(), Value::Principal(principal.clone()))]).unwrap());
+        let limit = Value::Tuple(TupleData::from_data(vec![("amount".into(), Value::UInt(1000))]).unwrap());
+        let overrides = vec![
+            StateOverride::MapEntry(contract_id.clone(), "limits".into(), limit_key.clone(), Some(limit)),
+            StateOverride::StxBalance(principal.clone(), 500),
+            StateOverride::FungibleTokenBalance(contract_id.clone(), "coin".into(), principal.clone(), 200),
+        ];
+        let simulation = StacksChainState::simulate_payload(&mut conn, &addr, &call, &overrides, cost_limit.clone()).unwrap();
+        assert_eq!(simulation.result, Some(Value::okay_true()));
+        assert_eq!(simulation.events.len(), 2);
+        assert!(simulation.cost.runtime > 0);
+
+        // deleting the entry takes the limit away again
+        let mut overrides = overrides;
+        overrides.push(StateOverride::MapEntry(contract_id.clone(), "limits".into(), limit_key.clone(), None));
+        let simulation = StacksChainState::simulate_payload(&mut conn, &addr, &call, &overrides, cost_limit.clone()).unwrap();
+        assert_eq!(simulation.result, Some(Value::err_uint(1)));
+
+        // nothing was written, overrides included
+        let (stx_balance, ft_balance, entry) = conn.with_clarity_db_readonly(|db| {
+            (db.get_account_stx_balance(&principal),
+             db.get_ft_balance(&contract_id, "coin", &principal).unwrap(),
+             db.fetch_entry(&contract_id, "limits", &limit_key).unwrap())
+        });
+        assert_eq!(stx_balance, 0);
+        assert_eq!(ft_balance, 0);
+        assert_eq!(entry, Value::none());
+
+        // overrides have to apply
+        let bad_override = StateOverride::MapEntry(contract_id.clone(), "no-such-map".into(), limit_key.clone(), None);
+        match StacksChainState::simulate_payload(&mut conn, &addr, &call, &[bad_override], cost_limit.clone()) {
+            Err(Error::InvalidStacksTransaction(_)) => {},
+            x => panic!("Expected an invalid override error, got {:?}", &x)
+        }
+
+        let transfer = TransactionPayload::TokenTransfer(addr.clone().into(), 123, TokenTransferMemo([0u8; 34]));
+        match StacksChainState::simulate_payload(&mut conn, &addr, &transfer, &[], cost_limit.clone()) {
+            Err(Error::InvalidStacksTransaction(_)) => {},
+            x => panic!("Expected only contract calls to be simulated, got {:?}", &x)
+        }
+
+        conn.commit_block();
+    }
+
     #[test]
     fn process_smart_contract_contract_call_invalid() {
         let contract = "
@@ -0,0 +1,145 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use chainstate::stacks::*;
+
+/// Checks the signatures of a block's transactions on worker threads, while the block's
+/// transactions are executed.  The workers take transactions in block order, so the first
+/// transactions are checked first, and execution only waits when it catches up to them.
+///
+/// Signature checks don't depend on the chain state, so checking them early can't change
+/// whether a block is valid: a transaction that fails its check here, or that wasn't checked
+/// for any reason, is checked again when it's executed, and fails the same way.
+pub struct SignatureVerifier {
+    results: Receiver<(Txid, bool)>,
+    verified: HashMap<Txid, bool>,
+}
+
+impl SignatureVerifier {
+    /// Start checking the signatures of these transactions on num_threads threads
+    pub fn spawn(txs: Vec<StacksTransaction>, num_threads: usize) -> SignatureVerifier {
+        let (sender, receiver) = channel();
+        let txs = Arc::new(txs);
+        let next_tx = Arc::new(AtomicUsize::new(0));
+
+        for i in 0..num_threads {
+            let txs = txs.clone();
+            let next_tx = next_tx.clone();
+            let sender = sender.clone();
+            let spawn_res = thread::Builder::new()
+                .name(format!("tx-signatures-{}", i))
+                .spawn(move || {
+                    loop {
+                        let tx_index = next_tx.fetch_add(1, Ordering::SeqCst);
+                        if tx_index >= txs.len() {
+                            break;
+                        }
+                        let tx = &txs[tx_index];
+                        let valid = tx.verify().is_ok();
+                        if sender.send((tx.txid(), valid)).is_err() {
+                            // the block is done with, one way or another
+                            break;
+                        }
+                    }
+                });
+            if let Err(e) = spawn_res {
+                // the other threads, or execution, will check the rest
+                warn!("Failed to start a signature checking thread: {:?}", &e);
+            }
+        }
+
+        SignatureVerifier {
+            results: receiver,
+            verified: HashMap::new(),
+        }
+    }
+
+    /// Has this transaction's signature been checked and found valid?  Waits for the check if
+    /// it hasn't finished yet.  False if the signature is invalid, or wasn't checked.
+    pub fn is_verified(&mut self, txid: &Txid) -> bool {
+        loop {
+            if let Some(valid) = self.verified.get(txid) {
+                return *valid;
+            }
+            match self.results.recv() {
+                Ok((checked_txid, valid)) => {
+                    self.verified.insert(checked_txid, valid);
+                },
+                Err(_) => {
+                    // every worker has exited without checking it
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::hash::Hash160;
+    use chainstate::stacks::db::test::make_tx_with_privk;
+
+    fn make_signed_transfer(amount: u64) -> StacksTransaction {
+        let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+        let recv_addr = StacksAddress { version: 1, bytes: Hash160([0xff; 20]) };
+
+        let mut tx = make_tx_with_privk(&privk, TransactionPayload::TokenTransfer(recv_addr.into(), amount, TokenTransferMemo([0u8; 34])));
+        tx.chain_id = 0x80000000;
+        tx.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx);
+        signer.sign_origin(&privk).unwrap();
+        signer.get_tx().unwrap()
+    }
+
+    #[test]
+    fn test_signature_verifier() {
+        let mut txs = vec![];
+        for amount in 1..50 {
+            txs.push(make_signed_transfer(amount));
+        }
+
+        // tampering with a signed transaction invalidates its signature
+        let mut bad_tx = make_signed_transfer(50);
+        if let TransactionPayload::TokenTransfer(_, ref mut amount, _) = bad_tx.payload {
+            *amount = 51;
+        }
+        txs.push(bad_tx.clone());
+
+        let unchecked_tx = make_signed_transfer(52);
+
+        let mut verifier = SignatureVerifier::spawn(txs.clone(), 4);
+        for tx in txs.iter().rev() {
+            assert_eq!(verifier.is_verified(&tx.txid()), tx.txid() != bad_tx.txid());
+        }
+        assert!(!verifier.is_verified(&unchecked_tx.txid()));
+
+        // no threads means nothing is checked
+        let mut verifier = SignatureVerifier::spawn(txs.clone(), 0);
+        assert!(!verifier.is_verified(&txs[0].txid()));
+    }
+}
@@ -11,6 +11,9 @@ use vm::types::{
 };
 use vm::costs::ExecutionCost;
 use vm::analysis::ContractAnalysis;
+use vm::calltrace::ContractCallTrace;
+use vm::statetrace::StateTrace;
+use vm::errors::RuntimeErrorType;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StacksTransactionReceipt {
@@ -20,7 +23,65 @@ pub struct StacksTransactionReceipt {
     pub result: Value,
     pub stx_burned: u128,
     pub contract_analysis: Option<ContractAnalysis>,
-    pub execution_cost: ExecutionCost
+    pub execution_cost: ExecutionCost,
+    /// the public function calls the transaction made, if the chainstate traces them
+    pub call_trace: Vec<ContractCallTrace>,
+    /// what the transaction read from and wrote to the chain state, if the chainstate traces it
+    pub state_trace: Option<StateTrace>,
+    /// why the transaction was aborted, if it was aborted by something other than returning an `err`
+    pub abort_code: Option<TransactionAbortCode>,
+}
+
+/// Why a mined transaction was aborted.  Each has a stable code, so clients can branch on the
+/// kind of failure instead of parsing error messages.  Transactions that would exceed the
+/// block's cost budget aren't mined at all, so they never get a receipt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionAbortCode {
+    /// arithmetic overflow or underflow, division by zero, or a bad `pow` argument
+    Arithmetic,
+    /// `unwrap-panic` or `unwrap-err-panic` on a value that didn't hold what it expected
+    UnwrapFailure,
+    /// the code went past the runtime's limit on call stack or context depth
+    RuntimeLimitExceeded,
+    /// a token transfer, mint or burn the runtime doesn't allow, like overflowing a token's supply
+    AssetOperation,
+    /// any other runtime error
+    Runtime,
+    /// the transaction's post-conditions didn't hold
+    PostCondition,
+    /// the contract in a smart-contract transaction failed to parse or type-check
+    AnalysisFailure,
+}
+
+impl TransactionAbortCode {
+    pub fn from_runtime_error(error: &RuntimeErrorType) -> TransactionAbortCode {
+        match error {
+            RuntimeErrorType::Arithmetic(_) | RuntimeErrorType::ArithmeticOverflow |
+            RuntimeErrorType::ArithmeticUnderflow | RuntimeErrorType::DivisionByZero => TransactionAbortCode::Arithmetic,
+            RuntimeErrorType::UnwrapFailure => TransactionAbortCode::UnwrapFailure,
+            RuntimeErrorType::MaxStackDepthReached | RuntimeErrorType::MaxContextDepthReached => TransactionAbortCode::RuntimeLimitExceeded,
+            RuntimeErrorType::SupplyOverflow(..) | RuntimeErrorType::TransferNonPositiveAmount |
+            RuntimeErrorType::NoSuchToken | RuntimeErrorType::NonPositiveTokenSupply => TransactionAbortCode::AssetOperation,
+            RuntimeErrorType::ParseError(_) | RuntimeErrorType::ASTError(_) | RuntimeErrorType::ListDimensionTooHigh |
+            RuntimeErrorType::BadTypeConstruction | RuntimeErrorType::ValueTooLarge | RuntimeErrorType::BadBlockHeight(_) |
+            RuntimeErrorType::NotImplemented | RuntimeErrorType::NoSenderInContext | RuntimeErrorType::JSONParseError(_) |
+            RuntimeErrorType::AttemptToFetchInTransientContext | RuntimeErrorType::BadNameValue(..) |
+            RuntimeErrorType::UnknownBlockHeaderHash(_) | RuntimeErrorType::BadBlockHash(_) => TransactionAbortCode::Runtime,
+        }
+    }
+
+    /// The code reported to clients.  These never change meaning once released.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransactionAbortCode::Arithmetic => "arithmetic",
+            TransactionAbortCode::UnwrapFailure => "unwrap_failure",
+            TransactionAbortCode::RuntimeLimitExceeded => "runtime_limit_exceeded",
+            TransactionAbortCode::AssetOperation => "asset_operation",
+            TransactionAbortCode::Runtime => "runtime",
+            TransactionAbortCode::PostCondition => "post_condition",
+            TransactionAbortCode::AnalysisFailure => "analysis_failure",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,52 +94,45 @@ pub enum StacksTransactionEvent {
 
 impl StacksTransactionEvent {
     pub fn json_serialize(&self, txid: &Txid, committed: bool) -> serde_json::Value {
+        let mut event = self.json_serialize_event();
+        event["txid"] = json!(format!("0x{:?}", txid));
+        event["committed"] = json!(committed);
+        event
+    }
+
+    /// The event's type and data, for events that aren't part of a transaction (e.g., ones
+    /// from a simulation)
+    pub fn json_serialize_event(&self) -> serde_json::Value {
         match self {
             StacksTransactionEvent::SmartContractEvent(event_data) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "contract_event",
                 "contract_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "stx_transfer_event",
                 "stx_transfer_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "stx_mint_event",
                 "stx_mint_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "stx_burn_event",
                 "stx_burn_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "nft_transfer_event",
                 "nft_transfer_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "nft_mint_event",
                 "nft_mint_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "ft_transfer_event",
                 "ft_transfer_event": event_data.json_serialize()
             }),
             StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => json!({
-                "txid": format!("0x{:?}", txid),
-                "committed": committed,
                 "type": "ft_mint_event",
                 "ft_mint_event": event_data.json_serialize()
             }),
@@ -0,0 +1,213 @@
+/*
+ copyright: (c) 2013-2019 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Golden-file tests for the consensus serialization of transactions, blocks, and microblocks.
+//!
+//! `current/` holds artifacts serialized by this tree, and pins its wire format: if a change
+//! alters how any of them serialize, `golden_serialization` fails.  `golden_round_trip` checks
+//! that each of them decodes, and re-encodes to the same bytes.  Both compare this tree against
+//! itself; there are no artifacts from other releases here, so they don't check compatibility
+//! between releases.
+//!
+//! To regenerate the artifacts after an _intended_ format change, run the tests with
+//! `BLOCKSTACK_UPDATE_GOLDEN=1`.
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use chainstate::burn::BlockHeaderHash;
+use chainstate::stacks::*;
+use chainstate::stacks::test::{codec_all_transactions, make_codec_test_block};
+
+use net::StacksMessageCodec;
+
+use util::hash::{hex_bytes, to_hex};
+
+const GOLDEN_CURRENT : &str = "current";
+
+const GOLDEN_TRANSACTIONS : &str = "transactions.txt";
+const GOLDEN_BLOCKS : &str = "blocks.txt";
+const GOLDEN_MICROBLOCKS : &str = "microblocks.txt";
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/chainstate/stacks/golden")
+}
+
+fn serialize<T: StacksMessageCodec>(obj: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    obj.consensus_serialize(&mut bytes).unwrap();
+    bytes
+}
+
+/// codec_all_transactions() is the cross product of every auth, post-condition set, and payload
+/// we test.  Keep just enough of them to cover each one at least once.
+fn representative_transactions(all_txs: Vec<StacksTransaction>) -> Vec<StacksTransaction> {
+    let mut seen_auths = HashSet::new();
+    let mut seen_post_conditions = HashSet::new();
+    let mut seen_payloads = HashSet::new();
+
+    all_txs.into_iter()
+        .filter(|tx| {
+            let new_auth = seen_auths.insert(format!("{:?}", &tx.auth));
+            let new_post_conditions = seen_post_conditions.insert(format!("{:?}", &tx.post_conditions));
+            let new_payload = seen_payloads.insert(format!("{:?}", &tx.payload));
+            new_auth || new_post_conditions || new_payload
+        })
+        .collect()
+}
+
+fn golden_transactions() -> Vec<(String, Vec<u8>)> {
+    let mut txs = representative_transactions(codec_all_transactions(&TransactionVersion::Mainnet, 0x00000000, &TransactionAnchorMode::OnChainOnly, &TransactionPostConditionMode::Deny));
+    txs.append(&mut representative_transactions(codec_all_transactions(&TransactionVersion::Testnet, 0x80000000, &TransactionAnchorMode::Any, &TransactionPostConditionMode::Allow)));
+
+    txs.iter()
+        .enumerate()
+        .map(|(i, tx)| (format!("tx-{:04}", i), serialize(tx)))
+        .collect()
+}
+
+fn golden_blocks() -> Vec<(String, Vec<u8>)> {
+    vec![
+        ("block-1-tx".to_string(), serialize(&make_codec_test_block(1))),
+        ("block-4-txs".to_string(), serialize(&make_codec_test_block(4))),
+        ("block-16-txs".to_string(), serialize(&make_codec_test_block(16))),
+    ]
+}
+
+fn golden_microblocks() -> Vec<(String, Vec<u8>)> {
+    let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+    let txs : Vec<StacksTransaction> = codec_all_transactions(&TransactionVersion::Testnet, 0x80000000, &TransactionAnchorMode::OffChainOnly, &TransactionPostConditionMode::Allow)
+        .into_iter()
+        .filter(|tx| match tx.payload {
+            TransactionPayload::Coinbase(_) => false,
+            _ => true
+        })
+        .collect();
+
+    let mut first = StacksMicroblock::first_unsigned(&BlockHeaderHash([0x11; 32]), txs[0..3].to_vec());
+    first.sign(&privk).unwrap();
+
+    let mut second = StacksMicroblock::from_parent_unsigned(&first.header, txs[3..8].to_vec()).unwrap();
+    second.sign(&privk).unwrap();
+
+    vec![
+        ("microblock-0".to_string(), serialize(&first)),
+        ("microblock-1".to_string(), serialize(&second)),
+    ]
+}
+
+/// This tree's artifacts, by golden file name
+fn current_artifacts() -> Vec<(&'static str, Vec<(String, Vec<u8>)>)> {
+    vec![
+        (GOLDEN_TRANSACTIONS, golden_transactions()),
+        (GOLDEN_BLOCKS, golden_blocks()),
+        (GOLDEN_MICROBLOCKS, golden_microblocks()),
+    ]
+}
+
+/// Golden files have one `name hex` artifact per line.  Blank lines and lines starting with `#`
+/// are ignored.
+fn read_golden_file(path: &PathBuf) -> Vec<(String, Vec<u8>)> {
+    let text = fs::read_to_string(path).expect(&format!("Failed to read golden file {:?}", path));
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| line.len() > 0 && !line.starts_with("#"))
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap().to_string();
+            let hex = parts.next().expect(&format!("No data for {} in {:?}", &name, path));
+            let bytes = hex_bytes(hex).expect(&format!("Invalid hex for {} in {:?}", &name, path));
+            (name, bytes)
+        })
+        .collect()
+}
+
+fn write_golden_file(path: &PathBuf, artifacts: &Vec<(String, Vec<u8>)>) {
+    let mut text = "# Consensus-serialized artifacts from this tree.  Do not edit by hand.\n".to_string();
+    for (name, bytes) in artifacts.iter() {
+        text.push_str(&format!("{} {}\n", name, to_hex(bytes)));
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+/// Decode an artifact, and confirm that it consumes all of its bytes and encodes back to them.
+fn check_roundtrip<T: StacksMessageCodec + fmt::Debug>(bytes: &Vec<u8>) -> Result<(), String> {
+    let mut cursor = Cursor::new(bytes);
+    let obj = T::consensus_deserialize(&mut cursor)
+        .map_err(|e| format!("failed to decode: {:?}", &e))?;
+
+    if cursor.position() != bytes.len() as u64 {
+        return Err(format!("decoded only {} of {} bytes", cursor.position(), bytes.len()));
+    }
+
+    let reencoded = serialize(&obj);
+    if reencoded != *bytes {
+        return Err(format!("re-encoded to different bytes: {}", to_hex(&reencoded)));
+    }
+    Ok(())
+}
+
+#[test]
+fn golden_serialization() {
+    let golden_current = golden_dir().join(GOLDEN_CURRENT);
+    let update = env::var("BLOCKSTACK_UPDATE_GOLDEN") == Ok("1".to_string());
+
+    for (file_name, artifacts) in current_artifacts().iter() {
+        let path = golden_current.join(file_name);
+        if update {
+            write_golden_file(&path, artifacts);
+            continue;
+        }
+
+        let expected = read_golden_file(&path);
+        let expected_names : Vec<&String> = expected.iter().map(|(name, _)| name).collect();
+        let names : Vec<&String> = artifacts.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, expected_names, "Artifacts in {:?} changed; regenerate with BLOCKSTACK_UPDATE_GOLDEN=1 if intended", &path);
+
+        for ((name, bytes), (_, expected_bytes)) in artifacts.iter().zip(expected.iter()) {
+            assert!(bytes == expected_bytes, "Serialization of {} in {:?} drifted:\nexpected {}\n     got {}", name, &path, to_hex(expected_bytes), to_hex(bytes));
+        }
+    }
+}
+
+#[test]
+fn golden_round_trip() {
+    let mut failures = vec![];
+    for file_name in [GOLDEN_TRANSACTIONS, GOLDEN_BLOCKS, GOLDEN_MICROBLOCKS].iter() {
+        let path = golden_dir().join(GOLDEN_CURRENT).join(file_name);
+        for (name, bytes) in read_golden_file(&path).iter() {
+            let res = match *file_name {
+                GOLDEN_TRANSACTIONS => check_roundtrip::<StacksTransaction>(bytes),
+                GOLDEN_BLOCKS => check_roundtrip::<StacksBlock>(bytes),
+                GOLDEN_MICROBLOCKS => check_roundtrip::<StacksMicroblock>(bytes),
+                _ => unreachable!()
+            };
+            if let Err(msg) = res {
+                failures.push(format!("{} {}: {}", file_name, name, msg));
+            }
+        }
+    }
+
+    assert!(failures.len() == 0, "Golden artifacts don't round-trip:\n{}", failures.join("\n"));
+}
@@ -0,0 +1,153 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fs;
+
+use chainstate::stacks::index::{
+    TrieHash,
+    MarfTrieId,
+};
+
+use chainstate::stacks::index::node::{
+    TrieNodeType,
+    TriePtr,
+};
+
+use chainstate::stacks::index::trie_sql::SqliteTrieStore;
+
+#[cfg(feature = "marf_rocksdb")]
+use chainstate::stacks::index::trie_rocksdb::RocksDBTrieStore;
+
+use chainstate::stacks::index::Error as Error;
+
+use util::db::SqlitePragmas;
+
+/// The kinds of store a MARF's tries can be kept in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrieBackend {
+    /// a sqlite database file, with each trie in a blob
+    Sqlite,
+    /// a RocksDB directory, with each trie split into pages (needs the `marf_rocksdb` feature)
+    RocksDB,
+}
+
+impl TrieBackend {
+    pub fn from_name(name: &str) -> Option<TrieBackend> {
+        match name {
+            "sqlite" => Some(TrieBackend::Sqlite),
+            "rocksdb" => Some(TrieBackend::RocksDB),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TrieBackend::Sqlite => "sqlite",
+            TrieBackend::RocksDB => "rocksdb",
+        }
+    }
+
+    /// Is this backend built in?
+    pub fn is_available(&self) -> bool {
+        match *self {
+            TrieBackend::Sqlite => true,
+            TrieBackend::RocksDB => cfg!(feature = "marf_rocksdb"),
+        }
+    }
+
+    /// The backend of the MARF already at this path, if there is one.  A sqlite MARF is a file,
+    /// and a RocksDB MARF is a directory.
+    pub fn detect(path: &str) -> Option<TrieBackend> {
+        match fs::metadata(path) {
+            Ok(md) => {
+                if md.is_dir() {
+                    Some(TrieBackend::RocksDB)
+                }
+                else {
+                    Some(TrieBackend::Sqlite)
+                }
+            },
+            Err(_) => None
+        }
+    }
+}
+
+/// Where a MARF keeps its tries once they're flushed.  Each block's trie is written once, as one
+/// serialized blob, and given a local block identifier that back-pointers refer to it by.  Blocks
+/// being extended are locked, so two writers can't extend the same block.
+pub trait TrieStore<T: MarfTrieId>: Send {
+    fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error>;
+    fn get_block_hash(&self, local_id: u32) -> Result<T, Error>;
+
+    /// Read the hash of the node at ptr in a stored trie
+    fn read_node_hash(&self, block_id: u32, ptr: &TriePtr) -> Result<TrieHash, Error>;
+    /// Read the node at ptr in a stored trie, and its hash
+    fn read_node_type(&self, block_id: u32, ptr: &TriePtr) -> Result<(TrieNodeType, TrieHash), Error>;
+
+    /// Lock a block for extension, unless it's already stored or locked.  Returns whether the
+    /// lock was taken.
+    fn lock_for_extension(&mut self, bhh: &T) -> Result<bool, Error>;
+    fn drop_lock(&mut self, bhh: &T) -> Result<(), Error>;
+    fn clear_locks(&mut self) -> Result<(), Error>;
+
+    /// Atomically store the trie built while extending extended_bhh as final_bhh's (or, if mined,
+    /// as a mined block's, which isn't part of the MARF), and drop the lock on extended_bhh.
+    /// Returns the trie's block identifier.
+    fn commit_trie(&mut self, extended_bhh: &T, final_bhh: &T, data: &[u8], mined: bool) -> Result<u32, Error>;
+
+    /// How many tries are stored
+    fn count_blocks(&self) -> Result<u32, Error>;
+    /// Delete every trie and lock
+    fn clear(&mut self) -> Result<(), Error>;
+
+    /// Open another, read-only, handle on this store
+    fn reopen_readonly(&self) -> Result<Box<dyn TrieStore<T>>, Error>;
+
+    #[cfg(test)]
+    fn read_all_block_hashes_and_roots(&self) -> Result<Vec<(TrieHash, T)>, Error>;
+}
+
+/// Open the trie store at a path.  A store that's already there is opened with the backend it
+/// was created with; otherwise, a new one is created with the given backend.  A sqlite store is
+/// tuned with `pragmas`.
+pub fn open_trie_store<T: MarfTrieId>(path: &str, new_backend: TrieBackend, pragmas: &SqlitePragmas) -> Result<Box<dyn TrieStore<T>>, Error> {
+    let backend =
+        if path == ":memory:" {
+            TrieBackend::Sqlite
+        }
+        else {
+            TrieBackend::detect(path).unwrap_or(new_backend)
+        };
+
+    match backend {
+        TrieBackend::Sqlite => Ok(Box::new(SqliteTrieStore::open(path, pragmas)?)),
+        TrieBackend::RocksDB => open_rocksdb_trie_store(path)
+    }
+}
+
+#[cfg(feature = "marf_rocksdb")]
+fn open_rocksdb_trie_store<T: MarfTrieId>(path: &str) -> Result<Box<dyn TrieStore<T>>, Error> {
+    Ok(Box::new(RocksDBTrieStore::open(path)?))
+}
+
+#[cfg(not(feature = "marf_rocksdb"))]
+fn open_rocksdb_trie_store<T: MarfTrieId>(path: &str) -> Result<Box<dyn TrieStore<T>>, Error> {
+    error!("MARF at {} is stored in RocksDB, but this build doesn't have the `marf_rocksdb` feature", path);
+    Err(Error::BackendError(format!("RocksDB support not built in; cannot open {}", path)))
+}
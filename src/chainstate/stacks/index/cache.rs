@@ -0,0 +1,207 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+
+use chainstate::stacks::index::TrieHash;
+
+use chainstate::stacks::index::node::{
+    TrieNodeType,
+    TriePtr,
+    TrieLeaf,
+    TrieNode4,
+    TrieNode16,
+    TrieNode48,
+    TrieNode256,
+};
+
+/// Where a node is stored:  its trie's local block identifier, its node ID, and its offset in
+/// the trie
+type TrieNodeKey = (u32, u8, u32);
+
+/// Roughly how many bytes a cached node takes up
+fn cached_node_size(node: &TrieNodeType) -> usize {
+    let node_size = match node {
+        TrieNodeType::Leaf(_) => mem::size_of::<TrieLeaf>(),
+        TrieNodeType::Node4(_) => mem::size_of::<TrieNode4>(),
+        TrieNodeType::Node16(_) => mem::size_of::<TrieNode16>(),
+        TrieNodeType::Node48(_) => mem::size_of::<TrieNode48>(),
+        TrieNodeType::Node256(_) => mem::size_of::<TrieNode256>(),
+    };
+    // the node, its path, its hash, and its key in both maps
+    node_size + node.path_bytes().len() + mem::size_of::<TrieHash>() + 2 * mem::size_of::<TrieNodeKey>() + 2 * mem::size_of::<u64>()
+}
+
+/// The trie nodes most recently read from a MARF's stored tries, up to a byte budget.  A trie is
+/// never changed once it's stored, so a cached node stays valid until the store is cleared.
+/// Nodes of the trie being extended are in RAM already, and aren't cached.
+pub struct TrieNodeCache {
+    max_bytes: usize,
+    size_bytes: usize,
+    /// each node, its hash, and when it was last used
+    nodes: HashMap<TrieNodeKey, (TrieNodeType, TrieHash, u64)>,
+    /// the nodes in the order they were last used
+    lru: BTreeMap<u64, TrieNodeKey>,
+    next_use: u64,
+
+    hits: u64,
+    misses: u64,
+}
+
+impl TrieNodeCache {
+    pub fn new(max_bytes: usize) -> TrieNodeCache {
+        TrieNodeCache {
+            max_bytes,
+            size_bytes: 0,
+            nodes: HashMap::new(),
+            lru: BTreeMap::new(),
+            next_use: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// Get a cached node and its hash, and mark it as the most recently used
+    pub fn get(&mut self, block_id: u32, ptr: &TriePtr) -> Option<(TrieNodeType, TrieHash)> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = (block_id, ptr.id(), ptr.ptr());
+        match self.nodes.get_mut(&key) {
+            Some(entry) => {
+                self.lru.remove(&entry.2);
+                entry.2 = self.next_use;
+                self.lru.insert(self.next_use, key);
+                self.next_use += 1;
+                self.hits += 1;
+                Some((entry.0.clone(), entry.1.clone()))
+            },
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Cache a node read from a stored trie, evicting the least recently used nodes to stay
+    /// within the budget.  A node bigger than the whole budget isn't cached.
+    pub fn insert(&mut self, block_id: u32, ptr: &TriePtr, node: &TrieNodeType, hash: &TrieHash) {
+        let size = cached_node_size(node);
+        if size > self.max_bytes {
+            return;
+        }
+        let key = (block_id, ptr.id(), ptr.ptr());
+        if self.nodes.contains_key(&key) {
+            return;
+        }
+        while self.size_bytes + size > self.max_bytes {
+            let (oldest_use, oldest_key) = match self.lru.iter().next() {
+                Some((oldest_use, oldest_key)) => (*oldest_use, *oldest_key),
+                None => break
+            };
+            self.lru.remove(&oldest_use);
+            if let Some((evicted, _, _)) = self.nodes.remove(&oldest_key) {
+                self.size_bytes -= cached_node_size(&evicted);
+            }
+        }
+        self.nodes.insert(key, (node.clone(), hash.clone(), self.next_use));
+        self.lru.insert(self.next_use, key);
+        self.next_use += 1;
+        self.size_bytes += size;
+    }
+
+    /// Forget every node, as when the store is cleared
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.lru.clear();
+        self.size_bytes = 0;
+    }
+
+    /// Roughly how many bytes the cached nodes take up
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// How many reads the cache has answered, and how many it hasn't
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::index::node::TrieNodeID;
+
+    fn make_leaf(path_byte: u8) -> TrieNodeType {
+        TrieNodeType::Leaf(TrieLeaf::new(&vec![path_byte; 32], &vec![path_byte; 40]))
+    }
+
+    fn make_ptr(ptr: u32) -> TriePtr {
+        TriePtr::new(TrieNodeID::Leaf as u8, 0, ptr)
+    }
+
+    #[test]
+    fn test_node_cache_lru() {
+        let leaf_size = cached_node_size(&make_leaf(0));
+        let mut cache = TrieNodeCache::new(3 * leaf_size);
+
+        for i in 0..3 {
+            cache.insert(1, &make_ptr(i), &make_leaf(i as u8), &TrieHash([i as u8; 32]));
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.size_bytes(), 3 * leaf_size);
+
+        // same offset in another trie is another node
+        assert!(cache.get(2, &make_ptr(0)).is_none());
+        assert_eq!(cache.get(1, &make_ptr(0)), Some((make_leaf(0), TrieHash([0u8; 32]))));
+
+        // 1 is now the least recently used, so it makes way for 3
+        cache.insert(1, &make_ptr(3), &make_leaf(3), &TrieHash([3u8; 32]));
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(1, &make_ptr(1)).is_none());
+        assert!(cache.get(1, &make_ptr(0)).is_some());
+        assert!(cache.get(1, &make_ptr(2)).is_some());
+        assert!(cache.get(1, &make_ptr(3)).is_some());
+        assert_eq!(cache.stats(), (4, 2));
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
+    #[test]
+    fn test_node_cache_disabled() {
+        let mut cache = TrieNodeCache::new(0);
+        assert!(!cache.is_enabled());
+        cache.insert(1, &make_ptr(0), &make_leaf(0), &TrieHash([0u8; 32]));
+        assert_eq!(cache.len(), 0);
+        assert!(cache.get(1, &make_ptr(0)).is_none());
+    }
+}
@@ -63,6 +63,8 @@ use chainstate::stacks::index::node::{
     clear_backptr,
 };
 
+use chainstate::stacks::index::backend::TrieBackend;
+
 use chainstate::stacks::index::storage::{
     TrieFileStorage
 };
@@ -82,6 +84,7 @@ use chainstate::stacks::index::trie::{
 use chainstate::stacks::index::Error as Error;
 
 use util::log;
+use util::db::SqlitePragmas;
 
 pub const BLOCK_HASH_TO_HEIGHT_MAPPING_KEY: &str = "__MARF_BLOCK_HASH_TO_HEIGHT";
 pub const BLOCK_HEIGHT_TO_HASH_MAPPING_KEY: &str = "__MARF_BLOCK_HEIGHT_TO_HASH";
@@ -505,7 +508,14 @@ impl <T: MarfTrieId> MARF <T> {
     /// This will have the side-effect of instantiating a new fork table from the tries encoded on
     /// disk. Performant code should call this method sparingly.
     pub fn from_path(path: &str, miner_tip: Option<&T>) -> Result<MARF<T>, Error> {
-        let mut file_storage = TrieFileStorage::new(path)?;
+        MARF::from_path_with_backend(path, miner_tip, TrieBackend::Sqlite, &SqlitePragmas::default())
+    }
+
+    /// Instantiate the MARF from the given path on disk, creating it with the given backend if
+    /// it isn't there yet.  An existing MARF is opened with whichever backend it was created with.
+    /// A sqlite MARF is tuned with `pragmas`.
+    pub fn from_path_with_backend(path: &str, miner_tip: Option<&T>, backend: TrieBackend, pragmas: &SqlitePragmas) -> Result<MARF<T>, Error> {
+        let mut file_storage = TrieFileStorage::new_with_backend(path, backend, pragmas)?;
         match fs::metadata(path) {
             Ok(_) => {},
             Err(e) => {
@@ -897,6 +907,11 @@ impl <T: MarfTrieId> MARF <T> {
         &mut self.storage
     }
 
+    /// Keep up to this many bytes of trie nodes in RAM.  0 (the default) turns the cache off.
+    pub fn set_node_cache_size(&mut self, max_bytes: usize) {
+        self.storage.set_node_cache_size(max_bytes)
+    }
+
     /// Reopen storage read-only
     pub fn reopen_storage_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
         self.storage.reopen_readonly()
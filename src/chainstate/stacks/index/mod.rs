@@ -17,13 +17,17 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+pub mod backend;
 pub mod bits;
+pub mod cache;
 pub mod marf;
 pub mod node;
 pub mod proofs;
 pub mod storage;
 pub mod trie;
 pub mod trie_sql;
+#[cfg(feature = "marf_rocksdb")]
+pub mod trie_rocksdb;
 
 use std::fmt;
 use std::error;
@@ -268,7 +272,8 @@ pub enum Error {
     WriteNotBegunError,
     CursorError(node::CursorError),
     RestoreMarfBlockError(Box<Error>),
-    NonMatchingForks([u8; 32], [u8; 32])
+    NonMatchingForks([u8; 32], [u8; 32]),
+    BackendError(String),
 }
 
 impl From<io::Error> for Error {
@@ -303,6 +308,7 @@ impl fmt::Display for Error {
             Error::IOError(ref e) => fmt::Display::fmt(e, f),
             Error::SQLError(ref e) => fmt::Display::fmt(e, f),
             Error::CorruptionError(ref s) => fmt::Display::fmt(s, f),
+            Error::BackendError(ref s) => write!(f, "Trie store error: {}", s),
             Error::CursorError(ref e) => fmt::Display::fmt(e, f),
             Error::BlockHashMapCorruptionError(ref opt_e) => {
                 f.write_str("Corrupted MARF BlockHashMap")?;
@@ -33,6 +33,7 @@ use std::char::from_digit;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::collections::{VecDeque, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 use std::fs;
 use std::path::{
@@ -52,10 +53,17 @@ use chainstate::stacks::index::{
     TrieHash,
     TRIEHASH_ENCODED_SIZE,
     BlockMap,
-    trie_sql,
     MarfTrieId,
 };
 
+use chainstate::stacks::index::backend::{
+    TrieBackend,
+    TrieStore,
+    open_trie_store,
+};
+
+use chainstate::stacks::index::cache::TrieNodeCache;
+
 use chainstate::stacks::index::bits::{
     get_node_byte_len,
     write_nodetype_bytes,
@@ -83,12 +91,11 @@ use chainstate::stacks::index::node::{
 };
 
 use rusqlite::{
-    Connection, OptionalExtension,
+    OptionalExtension,
     types::{ FromSql,
              ToSql },
     NO_PARAMS,
     Error as SqliteError,
-    OpenFlags
 };
 
 use std::convert::{
@@ -99,9 +106,8 @@ use std::convert::{
 use chainstate::stacks::index::Error as Error;
 
 use util::log;
-use util::db::tx_begin_immediate;
-use util::db::tx_busy_handler;
 use util::db::Error as db_error;
+use util::db::SqlitePragmas;
 
 pub fn ftell<F: Seek>(f: &mut F) -> Result<u64, Error> {
     f.seek(SeekFrom::Current(0))
@@ -126,7 +132,7 @@ impl <T: MarfTrieId> BlockMap for TrieFileStorage<T> {
     type TrieId = T;
 
     fn get_block_hash(&self, id: u32) -> Result<T, Error> {
-        trie_sql::get_block_hash(&self.db, id)
+        self.db.get_block_hash(id)
     }
 
     fn get_block_hash_caching(&mut self, id: u32) -> Result<&T, Error> {
@@ -137,11 +143,11 @@ impl <T: MarfTrieId> BlockMap for TrieFileStorage<T> {
     }
 }
 
-impl <T: MarfTrieId> BlockMap for TrieSqlHashMapCursor<'_, T> {
+impl <T: MarfTrieId> BlockMap for TrieStoreHashMapCursor<'_, T> {
     type TrieId = T;
 
     fn get_block_hash(&self, id: u32) -> Result<T, Error> {
-        trie_sql::get_block_hash(&self.db, id)
+        self.db.get_block_hash(id)
     }
 
     fn get_block_hash_caching(&mut self, id: u32) -> Result<&T, Error> {
@@ -452,19 +458,21 @@ impl <T: MarfTrieId> NodeHashReader for TrieRAM <T> {
     }
 }
 
-pub struct TrieSqlCursor <'a> {
-    db: &'a Connection,
+pub struct TrieStoreCursor <'a, T: MarfTrieId> {
+    db: &'a dyn TrieStore<T>,
     block_id: u32
 }
 
-pub struct TrieSqlHashMapCursor <'a, T: MarfTrieId> {
-    db: &'a Connection,
+pub struct TrieStoreHashMapCursor <'a, T: MarfTrieId> {
+    db: &'a dyn TrieStore<T>,
     cache: &'a mut HashMap<u32, T>
 }
 
-impl NodeHashReader for TrieSqlCursor<'_> {
+impl <T: MarfTrieId> NodeHashReader for TrieStoreCursor<'_, T> {
     fn read_node_hash_bytes<W: Write>(&mut self, ptr: &TriePtr, w: &mut W) -> Result<(), Error> {
-        trie_sql::read_node_hash_bytes(self.db, w, self.block_id, ptr)
+        let hash = self.db.read_node_hash(self.block_id, ptr)?;
+        w.write_all(hash.as_bytes())
+            .map_err(|e| e.into())
     }
 }
 
@@ -476,7 +484,7 @@ pub struct TrieFileStorage <T: MarfTrieId> {
 
     last_extended: Option<(T, TrieRAM<T>)>,
 
-    db: Connection,
+    db: Box<dyn TrieStore<T>>,
     cur_block: T,
     cur_block_id: Option<u32>,
 
@@ -495,6 +503,9 @@ pub struct TrieFileStorage <T: MarfTrieId> {
 
     block_hash_cache: HashMap<u32, T>,
 
+    // recently-read nodes of stored tries, shared with read-only views of this storage
+    node_cache: Arc<Mutex<TrieNodeCache>>,
+
     pub readonly: bool,
 
     // used in testing in order to short-circuit block-height lookups
@@ -505,12 +516,15 @@ pub struct TrieFileStorage <T: MarfTrieId> {
 
 impl <T: MarfTrieId> TrieFileStorage <T> {
     pub fn new(dir_path: &str) -> Result<TrieFileStorage<T>, Error> {
-        let mut db = Connection::open(dir_path)?;
-        db.busy_handler(Some(tx_busy_handler))?;
+        TrieFileStorage::new_with_backend(dir_path, TrieBackend::Sqlite, &SqlitePragmas::default())
+    }
 
-        let dir_path = dir_path.to_string();
+    /// Open the storage at dir_path with whatever backend it was created with, or create it with
+    /// the given backend if it doesn't exist yet.  A sqlite store is tuned with `pragmas`.
+    pub fn new_with_backend(dir_path: &str, new_backend: TrieBackend, pragmas: &SqlitePragmas) -> Result<TrieFileStorage<T>, Error> {
+        let db = open_trie_store(dir_path, new_backend, pragmas)?;
 
-        trie_sql::create_tables_if_needed(&mut db)?;
+        let dir_path = dir_path.to_string();
 
         test_debug!("Opened TrieFileStorage {};", dir_path);
 
@@ -533,6 +547,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
 
             trie_ancestor_hash_bytes_cache: None,
             block_hash_cache: HashMap::new(),
+            node_cache: Arc::new(Mutex::new(TrieNodeCache::new(0))),
   
             miner_tip: None,
             readonly: false,
@@ -552,8 +567,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
             return Err(Error::InProgressError);
         }
 
-        let db = Connection::open_with_flags(&self.dir_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-        db.busy_handler(Some(tx_busy_handler))?;
+        let db = self.db.reopen_readonly()?;
 
         trace!("Make read-only view of TrieFileStorage: {}", &self.dir_path);
         
@@ -576,6 +590,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
 
             trie_ancestor_hash_bytes_cache: self.trie_ancestor_hash_bytes_cache.clone(),
             block_hash_cache: self.block_hash_cache.clone(),
+            node_cache: self.node_cache.clone(),
   
             miner_tip: None,
             readonly: true,
@@ -600,6 +615,13 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         self.miner_tip.clone()
     }
 
+    /// Keep up to this many bytes of the nodes read from stored tries in RAM, dropping whatever
+    /// is cached now.  0 (the default) turns the cache off.  Read-only views of this storage,
+    /// whenever they were made, share the cache.
+    pub fn set_node_cache_size(&mut self, max_bytes: usize) {
+        *self.node_cache.lock().expect("FATAL: MARF node cache lock poisoned") = TrieNodeCache::new(max_bytes);
+    }
+
     pub fn set_cached_ancestor_hashes_bytes(&mut self, bhh: &T, bytes: Vec<TrieHash>) {
         self.trie_ancestor_hash_bytes_cache = Some((bhh.clone(), bytes));
     }
@@ -660,10 +682,8 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
     /// Recover from partially-written state -- i.e. blow it away.
     /// Doesn't get called automatically.
     pub fn recover(dir_path: &String) -> Result<(), Error> {
-        let conn = Connection::open(dir_path)?;
-        conn.busy_handler(Some(tx_busy_handler))?;
-
-        trie_sql::clear_lock_data(&conn)
+        let mut db = open_trie_store::<T>(dir_path, TrieBackend::Sqlite, &SqlitePragmas::default())?;
+        db.clear_locks()
     }
 
     /// Read the Trie root node's hash from the block table.
@@ -671,13 +691,14 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
     pub fn read_block_root_hash(&self, bhh: &T) -> Result<TrieHash, Error> {
         let root_hash_ptr =
             TriePtr::new(TrieNodeID::Node256 as u8, 0, TrieFileStorage::<T>::root_ptr_disk());
-        trie_sql::get_node_hash_bytes_by_bhh(&self.db, bhh, &root_hash_ptr)
+        let block_id = self.db.get_block_identifier(bhh)?;
+        self.db.read_node_hash(block_id, &root_hash_ptr)
     }
 
     /// Generate a mapping between Trie root hashes and the blocks that contain them
     #[cfg(test)]
     pub fn read_root_to_block_table(&mut self) -> Result<HashMap<TrieHash, T>, Error> {
-        let mut ret = HashMap::from_iter(trie_sql::read_all_block_hashes_and_roots(&self.db)?
+        let mut ret = HashMap::from_iter(self.db.read_all_block_hashes_and_roots()?
                                          .into_iter());
 
         let last_extended = match self.last_extended.take() {
@@ -715,7 +736,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         let trie_buf = TrieRAM::new(bhh, size_hint, &self.cur_block);
 
         // place a lock on this block, so we can't extend to it again
-        if !trie_sql::lock_bhh_for_extension(&mut self.db, bhh)? {
+        if !self.db.lock_for_extension(bhh)? {
             warn!("Block already extended: {}", &bhh);
             return Err(Error::ExistsError);
         }
@@ -773,7 +794,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
             // just reset to newly opened state
             self.cur_block = sentinel;
             // did we write to the sentinel ?
-            self.cur_block_id = trie_sql::get_block_identifier(&self.db, bhh)
+            self.cur_block_id = self.db.get_block_identifier(bhh)
                 .ok();
             return Ok(());
         }
@@ -789,14 +810,14 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         }
 
         // opening a different Trie than the one we're extending
-        self.cur_block_id = Some(trie_sql::get_block_identifier(&self.db, bhh)?);
+        self.cur_block_id = Some(self.db.get_block_identifier(bhh)?);
         self.cur_block = bhh.clone();
 
         Ok(())
     }
 
     pub fn get_block_identifier(&self, bhh: &T) -> Option<u32> {
-        trie_sql::get_block_identifier(&self.db, bhh).ok()
+        self.db.get_block_identifier(bhh).ok()
     }
 
     pub fn get_cur_block_identifier(&mut self) -> Result<u32, Error> {
@@ -851,7 +872,8 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         debug!("Format TrieFileStorage {}", &self.dir_path);
 
         // blow away db
-        trie_sql::clear_tables(&mut self.db)?;
+        self.db.clear()?;
+        self.node_cache.lock().expect("FATAL: MARF node cache lock poisoned").clear();
 
         match self.last_extended {
             Some((_, ref mut trie_storage)) => trie_storage.format()?,
@@ -891,7 +913,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
 
         trace!("get_children_hashes_bytes for {:?}", node);
 
-        let mut map = TrieSqlHashMapCursor { db: &self.db,
+        let mut map = TrieStoreHashMapCursor { db: &*self.db,
                                              cache: &mut self.block_hash_cache };
 
         if let Some((ref last_extended, ref mut last_extended_trie)) = self.last_extended {
@@ -902,7 +924,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         }
 
         // otherwise, the current block is open as an FD
-        let mut cursor = TrieSqlCursor { db: &self.db,
+        let mut cursor = TrieStoreCursor { db: &*self.db,
                                          block_id: self.cur_block_id.ok_or_else(|| {
                                              error!("Failed to get cur block as hash reader");
                                              Error::NotFoundError
@@ -946,7 +968,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         // some other block or ptr, or cache miss
         match self.cur_block_id {
             Some(block_id) => {
-                trie_sql::get_node_hash_bytes(&self.db, block_id, ptr)
+                self.db.read_node_hash(block_id, ptr)
             },
             None => {
                 error!("Not found (no file is open)");
@@ -979,9 +1001,20 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
             }
         }
 
-        // some other block
+        // some other block, whose nodes never change once it's stored
         match self.cur_block_id {
-            Some(id) => trie_sql::read_node_type(&self.db, id, &clear_ptr),
+            Some(id) => {
+                let mut node_cache = self.node_cache.lock().expect("FATAL: MARF node cache lock poisoned");
+                if !node_cache.is_enabled() {
+                    return self.db.read_node_type(id, &clear_ptr);
+                }
+                if let Some(cached) = node_cache.get(id, &clear_ptr) {
+                    return Ok(cached);
+                }
+                let (node, hash) = self.db.read_node_type(id, &clear_ptr)?;
+                node_cache.insert(id, &clear_ptr, &node, &hash);
+                Ok((node, hash))
+            },
             None => {
                 error!("Not found (no file is open)");
                 Err(Error::NotFoundError)
@@ -1047,10 +1080,9 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
 
             debug!("Flush: {} to {}", bhh, flush_options);
 
-            let tx = tx_begin_immediate(&mut self.db)?;
             let block_id = match flush_options {
                 FlushOptions::CurrentHeader => {
-                    trie_sql::write_trie_blob(&tx, bhh, &buffer)?
+                    self.db.commit_trie(bhh, bhh, &buffer, false)?
                 },
                 FlushOptions::NewHeader(real_bhh) => {
                     // If we opened a block with a given hash, but want to store it as a block with a *different*
@@ -1067,16 +1099,13 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
                         self.trie_ancestor_hash_bytes_cache = None;
                         self.cur_block = real_bhh.clone();
                     }
-                    trie_sql::write_trie_blob(&tx, real_bhh, &buffer)?
+                    self.db.commit_trie(bhh, real_bhh, &buffer, false)?
                 },
                 FlushOptions::MinedTable(real_bhh) => {
-                    trie_sql::write_trie_blob_to_mined(&tx, real_bhh, &buffer)?
+                    self.db.commit_trie(bhh, real_bhh, &buffer, true)?
                 },
             };
 
-            trie_sql::drop_lock(&tx, bhh)?;
-            tx.commit()?;
-
             debug!("Flush: identifier of {} is {}", flush_options, block_id);
         }
 
@@ -1098,12 +1127,8 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
     pub fn drop_extending_trie(&mut self) {
         if !self.readonly {
             if let Some((ref bhh, _)) = self.last_extended.take() {
-                let tx = tx_begin_immediate(&mut self.db)
-                    .expect("Corruption: Failed to obtain db transaction");
-                trie_sql::drop_lock(&tx, bhh)
+                self.db.drop_lock(bhh)
                     .expect("Corruption: Failed to drop the extended trie lock");
-                tx.commit()
-                    .expect("Corruption: Failed to drop the extended trie");
             }
             self.last_extended = None;
         }
@@ -1124,7 +1149,7 @@ impl <T: MarfTrieId> TrieFileStorage <T> {
         } else {
             0
         };
-        result + (trie_sql::count_blocks(&self.db)
+        result + (self.db.count_blocks()
                   .expect("Corruption: SQL Error on a non-fallible query.") as usize)
     }
 }
@@ -0,0 +1,522 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A MARF's tries in a RocksDB directory.
+//!
+//! Keys are a one-byte tag followed by a block hash or a big-endian block identifier:
+//! * `i` + block hash => block identifier
+//! * `b` + block identifier => block hash
+//! * `s` + block identifier => size of the trie's data
+//! * `d` + block identifier + page number => a TRIE_PAGE_SIZE page of the trie's data
+//! * `m` + block hash => a mined block's trie data
+//! * `l` + block hash => extension lock
+//! * `n` => number of tries stored, which is also the last block identifier given out
+//!
+//! Tries are split into pages so that reading a node only reads the pages it's in.
+
+use std::cmp;
+use std::fs;
+use std::io;
+use std::io::{
+    Read,
+    Seek,
+    SeekFrom,
+};
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+    Weak,
+};
+
+use rocksdb::{
+    DB,
+    Direction,
+    IteratorMode,
+    Options,
+    WriteBatch,
+};
+
+use chainstate::stacks::index::{
+    TrieHash,
+    MarfTrieId,
+};
+
+use chainstate::stacks::index::backend::TrieStore;
+
+use chainstate::stacks::index::bits::{
+    read_node_hash_bytes,
+    read_nodetype,
+    read_hash_bytes,
+};
+
+use chainstate::stacks::index::node::{
+    TrieNodeType,
+    TriePtr,
+};
+
+use chainstate::stacks::index::storage::TrieFileStorage;
+
+use chainstate::stacks::index::Error as Error;
+
+pub const TRIE_PAGE_SIZE : u64 = 4096;
+
+const BLOCK_ID_TAG : u8 = b'i';
+const BLOCK_HASH_TAG : u8 = b'b';
+const TRIE_SIZE_TAG : u8 = b's';
+const TRIE_DATA_TAG : u8 = b'd';
+const MINED_TAG : u8 = b'm';
+const LOCK_TAG : u8 = b'l';
+const COUNT_KEY : &'static [u8] = b"n";
+
+/// One open RocksDB database, shared by every store handle on the same path, since RocksDB
+/// only lets one handle per process open a database.
+struct SharedDB {
+    db: DB,
+    /// held while checking and then writing, so two writers can't take the same lock or
+    /// block identifier
+    write_lock: Mutex<()>,
+}
+
+lazy_static! {
+    static ref OPEN_DATABASES: Mutex<HashMap<String, Weak<SharedDB>>> = Mutex::new(HashMap::new());
+}
+
+pub struct RocksDBTrieStore {
+    path: String,
+    shared: Arc<SharedDB>,
+    readonly: bool,
+}
+
+fn rocksdb_error(e: rocksdb::Error) -> Error {
+    Error::BackendError(e.to_string())
+}
+
+fn hash_key(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + bytes.len());
+    key.push(tag);
+    key.extend_from_slice(bytes);
+    key
+}
+
+fn block_id_key(tag: u8, block_id: u32) -> Vec<u8> {
+    hash_key(tag, &block_id.to_be_bytes())
+}
+
+fn page_key(block_id: u32, page_no: u32) -> Vec<u8> {
+    let mut key = block_id_key(TRIE_DATA_TAG, block_id);
+    key.extend_from_slice(&page_no.to_be_bytes());
+    key
+}
+
+fn decode_u32(bytes: &[u8]) -> Result<u32, Error> {
+    if bytes.len() != 4 {
+        return Err(Error::CorruptionError(format!("Expected 4 bytes, got {}", bytes.len())));
+    }
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(bytes);
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn decode_u64(bytes: &[u8]) -> Result<u64, Error> {
+    if bytes.len() != 8 {
+        return Err(Error::CorruptionError(format!("Expected 8 bytes, got {}", bytes.len())));
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_be_bytes(buf))
+}
+
+/// Reads a stored trie's data a page at a time
+struct TrieDataReader<'a> {
+    db: &'a DB,
+    block_id: u32,
+    size: u64,
+    pos: u64,
+    page: Option<(u64, Vec<u8>)>,
+}
+
+impl <'a> TrieDataReader<'a> {
+    fn open(db: &'a DB, block_id: u32) -> Result<TrieDataReader<'a>, Error> {
+        let size = match db.get(&block_id_key(TRIE_SIZE_TAG, block_id)).map_err(rocksdb_error)? {
+            Some(bytes) => decode_u64(&bytes)?,
+            None => {
+                return Err(Error::NotFoundError);
+            }
+        };
+        Ok(TrieDataReader {
+            db,
+            block_id,
+            size,
+            pos: 0,
+            page: None
+        })
+    }
+
+    fn load_page(&mut self, page_no: u64) -> io::Result<()> {
+        if let Some((cur_page_no, _)) = self.page {
+            if cur_page_no == page_no {
+                return Ok(());
+            }
+        }
+        let data = self.db.get(&page_key(self.block_id, page_no as u32))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof,
+                                          format!("Missing page {} of trie {}", page_no, self.block_id)))?;
+        self.page = Some((page_no, data));
+        Ok(())
+    }
+}
+
+impl Read for TrieDataReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.size || buf.len() == 0 {
+            return Ok(0);
+        }
+        self.load_page(self.pos / TRIE_PAGE_SIZE)?;
+        let page = &self.page.as_ref().expect("BUG: no page loaded").1;
+
+        let offset = (self.pos % TRIE_PAGE_SIZE) as usize;
+        if offset >= page.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                      format!("Short page in trie {}", self.block_id)));
+        }
+        let n = cmp::min(buf.len(), page.len() - offset);
+        buf[0..n].copy_from_slice(&page[offset..(offset + n)]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for TrieDataReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::End(n) => (self.size as i64) + n,
+            SeekFrom::Current(n) => (self.pos as i64) + n
+        };
+        if new_pos < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Seek to a negative offset"));
+        }
+        self.pos = new_pos as u64;
+        Ok(self.pos)
+    }
+}
+
+impl RocksDBTrieStore {
+    /// Open the store at path, creating it if it isn't there.  Handles on the same path share one
+    /// database.
+    pub fn open(path: &str) -> Result<RocksDBTrieStore, Error> {
+        let mut open_databases = OPEN_DATABASES.lock().expect("FATAL: RocksDB registry lock poisoned");
+
+        if let Ok(canonical_path) = fs::canonicalize(path) {
+            let key = canonical_path.to_string_lossy().to_string();
+            if let Some(shared) = open_databases.get(&key).and_then(|weak| weak.upgrade()) {
+                return Ok(RocksDBTrieStore {
+                    path: path.to_string(),
+                    shared,
+                    readonly: false
+                });
+            }
+        }
+
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let db = DB::open(&opts, path).map_err(rocksdb_error)?;
+        let shared = Arc::new(SharedDB {
+            db,
+            write_lock: Mutex::new(())
+        });
+
+        let key = fs::canonicalize(path)?.to_string_lossy().to_string();
+        open_databases.retain(|_, weak| weak.upgrade().is_some());
+        open_databases.insert(key, Arc::downgrade(&shared));
+
+        debug!("Opened RocksDB trie store {}", path);
+        Ok(RocksDBTrieStore {
+            path: path.to_string(),
+            shared,
+            readonly: false
+        })
+    }
+
+    fn db(&self) -> &DB {
+        &self.shared.db
+    }
+
+    fn check_writable(&self) -> Result<(), Error> {
+        if self.readonly {
+            Err(Error::ReadOnlyError)
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    fn has_key(&self, key: &[u8]) -> Result<bool, Error> {
+        Ok(self.db().get(key).map_err(rocksdb_error)?.is_some())
+    }
+
+    /// Delete every key that starts with the given prefix
+    fn delete_prefix(&self, prefix: &[u8]) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        for (key, _) in self.db().iterator(IteratorMode::From(prefix, Direction::Forward)) {
+            if !key.starts_with(prefix) {
+                break;
+            }
+            batch.delete(&key);
+        }
+        self.db().write(batch).map_err(rocksdb_error)
+    }
+}
+
+impl <T: MarfTrieId> TrieStore<T> for RocksDBTrieStore {
+    fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error> {
+        match self.db().get(&hash_key(BLOCK_ID_TAG, bhh.as_bytes())).map_err(rocksdb_error)? {
+            Some(bytes) => decode_u32(&bytes),
+            None => Err(Error::NotFoundError)
+        }
+    }
+
+    fn get_block_hash(&self, local_id: u32) -> Result<T, Error> {
+        match self.db().get(&block_id_key(BLOCK_HASH_TAG, local_id)).map_err(rocksdb_error)? {
+            Some(bytes) => {
+                if bytes.len() != 32 {
+                    return Err(Error::CorruptionError(format!("Block hash of local ID {} is {} bytes", local_id, bytes.len())));
+                }
+                let mut buf = [0u8; 32];
+                buf.copy_from_slice(&bytes);
+                Ok(T::from(buf))
+            },
+            None => {
+                error!("Failed to get block header hash of local ID {}", local_id);
+                Err(Error::NotFoundError)
+            }
+        }
+    }
+
+    fn read_node_hash(&self, block_id: u32, ptr: &TriePtr) -> Result<TrieHash, Error> {
+        let mut reader = TrieDataReader::open(self.db(), block_id)?;
+        let hash_buff = read_node_hash_bytes(&mut reader, ptr)?;
+        Ok(TrieHash(hash_buff))
+    }
+
+    fn read_node_type(&self, block_id: u32, ptr: &TriePtr) -> Result<(TrieNodeType, TrieHash), Error> {
+        let mut reader = TrieDataReader::open(self.db(), block_id)?;
+        read_nodetype(&mut reader, ptr)
+    }
+
+    fn lock_for_extension(&mut self, bhh: &T) -> Result<bool, Error> {
+        self.check_writable()?;
+        let _guard = self.shared.write_lock.lock().expect("FATAL: RocksDB write lock poisoned");
+
+        if self.has_key(&hash_key(BLOCK_ID_TAG, bhh.as_bytes()))? {
+            return Ok(false);
+        }
+        let lock_key = hash_key(LOCK_TAG, bhh.as_bytes());
+        if self.has_key(&lock_key)? {
+            return Ok(false);
+        }
+
+        self.db().put(&lock_key, b"").map_err(rocksdb_error)?;
+        Ok(true)
+    }
+
+    fn drop_lock(&mut self, bhh: &T) -> Result<(), Error> {
+        self.check_writable()?;
+        self.db().delete(&hash_key(LOCK_TAG, bhh.as_bytes())).map_err(rocksdb_error)
+    }
+
+    fn clear_locks(&mut self) -> Result<(), Error> {
+        self.check_writable()?;
+        let _guard = self.shared.write_lock.lock().expect("FATAL: RocksDB write lock poisoned");
+        self.delete_prefix(&[LOCK_TAG])
+    }
+
+    fn commit_trie(&mut self, extended_bhh: &T, final_bhh: &T, data: &[u8], mined: bool) -> Result<u32, Error> {
+        self.check_writable()?;
+        let _guard = self.shared.write_lock.lock().expect("FATAL: RocksDB write lock poisoned");
+
+        let mut batch = WriteBatch::default();
+        batch.delete(&hash_key(LOCK_TAG, extended_bhh.as_bytes()));
+
+        if mined {
+            // mined tries aren't part of the MARF, so they don't get a block identifier
+            batch.put(&hash_key(MINED_TAG, final_bhh.as_bytes()), data);
+            self.db().write(batch).map_err(rocksdb_error)?;
+            return Ok(0);
+        }
+
+        let id_key = hash_key(BLOCK_ID_TAG, final_bhh.as_bytes());
+        if self.has_key(&id_key)? {
+            error!("Trie for {} is already stored", final_bhh);
+            return Err(Error::ExistsError);
+        }
+
+        let block_id = TrieStore::<T>::count_blocks(self)? + 1;
+        batch.put(&id_key, &block_id.to_be_bytes());
+        batch.put(&block_id_key(BLOCK_HASH_TAG, block_id), final_bhh.as_bytes());
+        batch.put(&block_id_key(TRIE_SIZE_TAG, block_id), &(data.len() as u64).to_be_bytes());
+        for (page_no, page) in data.chunks(TRIE_PAGE_SIZE as usize).enumerate() {
+            batch.put(&page_key(block_id, page_no as u32), page);
+        }
+        batch.put(COUNT_KEY, &block_id.to_be_bytes());
+
+        self.db().write(batch).map_err(rocksdb_error)?;
+        Ok(block_id)
+    }
+
+    fn count_blocks(&self) -> Result<u32, Error> {
+        match self.db().get(COUNT_KEY).map_err(rocksdb_error)? {
+            Some(bytes) => decode_u32(&bytes),
+            None => Ok(0)
+        }
+    }
+
+    fn clear(&mut self) -> Result<(), Error> {
+        self.check_writable()?;
+        let _guard = self.shared.write_lock.lock().expect("FATAL: RocksDB write lock poisoned");
+
+        let mut batch = WriteBatch::default();
+        for (key, _) in self.db().iterator(IteratorMode::Start) {
+            batch.delete(&key);
+        }
+        self.db().write(batch).map_err(rocksdb_error)
+    }
+
+    fn reopen_readonly(&self) -> Result<Box<dyn TrieStore<T>>, Error> {
+        Ok(Box::new(RocksDBTrieStore {
+            path: self.path.clone(),
+            shared: self.shared.clone(),
+            readonly: true
+        }))
+    }
+
+    #[cfg(test)]
+    fn read_all_block_hashes_and_roots(&self) -> Result<Vec<(TrieHash, T)>, Error> {
+        let mut ret = vec![];
+        for block_id in 1..(TrieStore::<T>::count_blocks(self)? + 1) {
+            let block_hash : T = self.get_block_hash(block_id)?;
+            let mut reader = TrieDataReader::open(self.db(), block_id)?;
+            reader.seek(SeekFrom::Start(TrieFileStorage::<T>::root_ptr_disk() as u64))?;
+            let trie_hash = TrieHash(read_hash_bytes(&mut reader)?);
+            ret.push((trie_hash, block_hash));
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+
+    use chainstate::burn::BlockHeaderHash;
+    use chainstate::stacks::index::MARFValue;
+    use chainstate::stacks::index::backend::TrieBackend;
+    use chainstate::stacks::index::marf::MARF;
+    use util::db::SqlitePragmas;
+
+    fn make_test_path(name: &str) -> String {
+        let path = format!("/tmp/test-trie-rocksdb-{}", name);
+        if fs::metadata(&path).is_ok() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_rocksdb_trie_store() {
+        let path = make_test_path("store");
+        let mut store = RocksDBTrieStore::open(&path).unwrap();
+        let bhh = BlockHeaderHash([1u8; 32]);
+        let next_bhh = BlockHeaderHash([2u8; 32]);
+
+        assert_eq!(TrieStore::<BlockHeaderHash>::count_blocks(&store).unwrap(), 0);
+        assert!(store.lock_for_extension(&bhh).unwrap());
+        assert!(!store.lock_for_extension(&bhh).unwrap());
+
+        // a second handle on the same path shares the database, and the locks
+        let mut other_store = RocksDBTrieStore::open(&path).unwrap();
+        assert!(!other_store.lock_for_extension(&bhh).unwrap());
+
+        let data : Vec<u8> = (0..(3 * TRIE_PAGE_SIZE + 100)).map(|i| (i % 251) as u8).collect();
+        assert_eq!(store.commit_trie(&bhh, &bhh, &data, false).unwrap(), 1);
+
+        // stored blocks can't be locked or stored again
+        assert!(!store.lock_for_extension(&bhh).unwrap());
+        assert!(store.commit_trie(&bhh, &bhh, &data, false).is_err());
+
+        assert!(store.lock_for_extension(&next_bhh).unwrap());
+        assert_eq!(store.commit_trie(&next_bhh, &next_bhh, &data[0..10], false).unwrap(), 2);
+        assert_eq!(TrieStore::<BlockHeaderHash>::count_blocks(&store).unwrap(), 2);
+        assert_eq!(store.get_block_identifier(&next_bhh).unwrap(), 2);
+        assert_eq!(TrieStore::<BlockHeaderHash>::get_block_hash(&store, 1).unwrap(), bhh);
+
+        // data reads back across page boundaries
+        let mut reader = TrieDataReader::open(store.db(), 1).unwrap();
+        reader.seek(SeekFrom::Start(TRIE_PAGE_SIZE - 50)).unwrap();
+        let mut buf = vec![0u8; 200];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[((TRIE_PAGE_SIZE - 50) as usize)..((TRIE_PAGE_SIZE + 150) as usize)]);
+
+        let mut all_data = vec![];
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        reader.read_to_end(&mut all_data).unwrap();
+        assert_eq!(all_data, data);
+
+        // read-only handles can't write
+        let mut readonly : Box<dyn TrieStore<BlockHeaderHash>> = store.reopen_readonly().unwrap();
+        assert_eq!(readonly.count_blocks().unwrap(), 2);
+        assert!(readonly.lock_for_extension(&BlockHeaderHash([3u8; 32])).is_err());
+
+        TrieStore::<BlockHeaderHash>::clear(&mut store).unwrap();
+        assert_eq!(TrieStore::<BlockHeaderHash>::count_blocks(&store).unwrap(), 0);
+        assert!(store.get_block_identifier(&bhh).is_err());
+    }
+
+    #[test]
+    fn test_rocksdb_marf() {
+        let path = make_test_path("marf");
+        let block_0 = BlockHeaderHash([0u8; 32]);
+        let block_1 = BlockHeaderHash([1u8; 32]);
+        let root_hash = {
+            let mut marf = MARF::from_path_with_backend(&path, None, TrieBackend::RocksDB, &SqlitePragmas::default()).unwrap();
+            marf.begin(&BlockHeaderHash::sentinel(), &block_0).unwrap();
+            marf.insert("foo", MARFValue::from_value("bar")).unwrap();
+            marf.commit().unwrap();
+
+            marf.begin(&block_0, &block_1).unwrap();
+            for i in 0..1000 {
+                marf.insert(&format!("key-{}", i), MARFValue::from_value(&format!("value-{}", i))).unwrap();
+            }
+            marf.commit().unwrap();
+            marf.get_root_hash_at(&block_1).unwrap()
+        };
+
+        assert_eq!(TrieBackend::detect(&path), Some(TrieBackend::RocksDB));
+
+        // reopening finds the RocksDB store, whatever backend is asked for
+        let mut marf = MARF::from_path(&path, None).unwrap();
+        assert_eq!(marf.get_root_hash_at(&block_1).unwrap(), root_hash);
+        assert_eq!(marf.get(&block_1, "foo").unwrap(), Some(MARFValue::from_value("bar")));
+        assert_eq!(marf.get(&block_1, "key-999").unwrap(), Some(MARFValue::from_value("value-999")));
+        assert_eq!(marf.get(&block_0, "key-999").unwrap(), None);
+    }
+}
@@ -60,6 +60,8 @@ use chainstate::stacks::index::storage::{
     TrieFileStorage,
 };
 
+use chainstate::stacks::index::backend::TrieStore;
+
 use chainstate::stacks::index::bits::{
     get_node_byte_len,
     write_nodetype_bytes,
@@ -87,7 +89,7 @@ use chainstate::stacks::index::node::{
 };
 
 use rusqlite::{
-    Connection, OptionalExtension,
+    Connection, OpenFlags, OptionalExtension,
     types::{ FromSql,
              ToSql },
     NO_PARAMS,
@@ -103,6 +105,7 @@ use chainstate::stacks::index::Error as Error;
 
 use util::log;
 use util::db::tx_begin_immediate;
+use util::db::{sqlite_open, SqlitePragmas};
 
 static SQL_MARF_DATA_TABLE: &str = "
 CREATE TABLE IF NOT EXISTS marf_data (
@@ -261,3 +264,94 @@ pub fn clear_tables(conn: &mut Connection) -> Result<(), Error> {
     tx.execute("DELETE FROM mined_blocks", NO_PARAMS)?;
     tx.commit().map_err(|e| e.into())
 }
+
+/// A MARF's tries in a sqlite database file, one blob per trie
+pub struct SqliteTrieStore {
+    path: String,
+    pragmas: SqlitePragmas,
+    db: Connection,
+}
+
+impl SqliteTrieStore {
+    pub fn open(path: &str, pragmas: &SqlitePragmas) -> Result<SqliteTrieStore, Error> {
+        // in WAL mode, so that readers of committed tries don't block the MARF's writer, or
+        // wait on it
+        let mut db = sqlite_open(path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE, Some(pragmas))?;
+        create_tables_if_needed(&mut db)?;
+
+        Ok(SqliteTrieStore {
+            path: path.to_string(),
+            pragmas: pragmas.clone(),
+            db
+        })
+    }
+}
+
+impl <T: MarfTrieId> TrieStore<T> for SqliteTrieStore {
+    fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error> {
+        get_block_identifier(&self.db, bhh)
+    }
+
+    fn get_block_hash(&self, local_id: u32) -> Result<T, Error> {
+        get_block_hash(&self.db, local_id)
+    }
+
+    fn read_node_hash(&self, block_id: u32, ptr: &TriePtr) -> Result<TrieHash, Error> {
+        get_node_hash_bytes(&self.db, block_id, ptr)
+    }
+
+    fn read_node_type(&self, block_id: u32, ptr: &TriePtr) -> Result<(TrieNodeType, TrieHash), Error> {
+        read_node_type(&self.db, block_id, ptr)
+    }
+
+    fn lock_for_extension(&mut self, bhh: &T) -> Result<bool, Error> {
+        lock_bhh_for_extension(&mut self.db, bhh)
+    }
+
+    fn drop_lock(&mut self, bhh: &T) -> Result<(), Error> {
+        let tx = tx_begin_immediate(&mut self.db)?;
+        drop_lock(&tx, bhh)?;
+        tx.commit().map_err(|e| e.into())
+    }
+
+    fn clear_locks(&mut self) -> Result<(), Error> {
+        clear_lock_data(&self.db)
+    }
+
+    fn commit_trie(&mut self, extended_bhh: &T, final_bhh: &T, data: &[u8], mined: bool) -> Result<u32, Error> {
+        let tx = tx_begin_immediate(&mut self.db)?;
+        let block_id =
+            if mined {
+                write_trie_blob_to_mined(&tx, final_bhh, data)?
+            }
+            else {
+                write_trie_blob(&tx, final_bhh, data)?
+            };
+        drop_lock(&tx, extended_bhh)?;
+        tx.commit()?;
+        Ok(block_id)
+    }
+
+    fn count_blocks(&self) -> Result<u32, Error> {
+        count_blocks(&self.db)
+    }
+
+    fn clear(&mut self) -> Result<(), Error> {
+        clear_tables(&mut self.db)
+    }
+
+    fn reopen_readonly(&self) -> Result<Box<dyn TrieStore<T>>, Error> {
+        let db = sqlite_open(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY, Some(&self.pragmas))?;
+
+        Ok(Box::new(SqliteTrieStore {
+            path: self.path.clone(),
+            pragmas: self.pragmas.clone(),
+            db
+        }))
+    }
+
+    #[cfg(test)]
+    fn read_all_block_hashes_and_roots(&self) -> Result<Vec<(TrieHash, T)>, Error> {
+        read_all_block_hashes_and_roots(&self.db)
+    }
+}
@@ -720,6 +720,7 @@ pub mod test {
     use net::test::*;
 
     use util::sleep_ms;
+    use util::db::SqlitePragmas;
 
     pub const COINBASE : u128 = 500 * 100_000;
 
@@ -1260,7 +1261,7 @@ pub mod test {
     /// Verify that the stacks block's state root matches the state root in the chain state
     fn check_block_state_index_root(chainstate: &mut StacksChainState, burn_header_hash: &BurnchainHeaderHash, stacks_header: &StacksBlockHeader) -> bool {
         let index_block_hash = StacksBlockHeader::make_index_block_hash(burn_header_hash, &stacks_header.block_hash());
-        let mut state_root_index = StacksChainState::open_index(&chainstate.clarity_state_index_path, Some(&StacksBlockHeader::make_index_block_hash(&MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH))).unwrap();
+        let mut state_root_index = StacksChainState::open_index(&chainstate.clarity_state_index_path, Some(&StacksBlockHeader::make_index_block_hash(&MINER_BLOCK_BURN_HEADER_HASH, &MINER_BLOCK_HEADER_HASH)), &SqlitePragmas::default()).unwrap();
         let state_root = state_root_index.borrow_storage_backend().read_block_root_hash(&index_block_hash).unwrap();
         state_root == stacks_header.state_index_root
     }
@@ -3500,6 +3501,302 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_build_anchored_blocks_stx_transfers_memo_index() {
+        let privk = StacksPrivateKey::from_hex("42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01").unwrap();
+        let addr = StacksAddress::from_public_keys(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, &AddressHashMode::SerializeP2PKH, 1, &vec![StacksPublicKey::from_private(&privk)]).unwrap();
+
+        let mut peer_config = TestPeerConfig::new("test_build_anchored_blocks_stx_transfers_memo_index", 2014, 2015);
+        peer_config.initial_balances = vec![
+            (addr.to_account_principal(), 1000000000)
+        ];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 6;
+
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        // every other transfer carries the memo; the rest carry none
+        let mut deposit_memo = TokenTransferMemo([0u8; 34]);
+        deposit_memo.0[0..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef, 0x00, 0x00, 0x00, 0x01]);
+        let no_memo = TokenTransferMemo([0u8; 34]);
+
+        let mut tips = vec![];
+        for tenure_id in 0..num_blocks {
+            // send transactions to the mempool
+            let tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(|ref mut miner, ref mut burndb, ref mut chainstate, vrf_proof, ref parent_opt, ref parent_microblock_header_opt| {
+                let parent_tip = match parent_opt {
+                    None => {
+                        StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap()
+                    }
+                    Some(block) => {
+                        let ic = burndb.index_conn();
+                        let snapshot = BurnDB::get_block_snapshot_for_winning_stacks_block(&ic, &tip.burn_header_hash, &block.block_hash()).unwrap().unwrap();      // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(&chainstate.headers_db, &snapshot.burn_header_hash, &snapshot.winning_stacks_block_hash).unwrap().unwrap()
+                    }
+                };
+
+                let parent_header_hash = parent_tip.anchored_header.block_hash();
+                let parent_tip_bhh = parent_tip.burn_header_hash.clone();
+
+                let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                // initial balances aren't materialized if the tip is the genesis header
+                if tenure_id > 0 {
+                    let memo = if tenure_id % 2 == 1 { deposit_memo.clone() } else { no_memo.clone() };
+                    let stx_transfer = sign_standard_singlesig_tx(TransactionPayload::TokenTransfer(recipient.to_account_principal(), tenure_id as u64, memo),
+                                                                  &privk, sender_nonce, 200);
+                    sender_nonce += 1;
+
+                    mempool.submit(&parent_tip_bhh, &parent_header_hash, stx_transfer).unwrap();
+                } 
+                let anchored_block = StacksBlockBuilder::build_anchored_block(chainstate, &mempool, &parent_tip, tip.total_burn, vrf_proof, Hash160([tenure_id as u8; 20]), &coinbase_tx, ExecutionCost::max_value()).unwrap();
+                (anchored_block.0, vec![])
+            });
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let burn_tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+            tips.push(StacksBlockHeader::make_index_block_hash(&burn_tip.burn_header_hash, &stacks_block.block_hash()));
+
+            if tenure_id > 0 {
+                assert_eq!(stacks_block.txs.len(), 2);
+            }
+        }
+
+        let chainstate = peer.chainstate();
+        let mut tx = chainstate.headers_tx_begin().unwrap();
+
+        // the transfers with the memo, as of the last block
+        let transfers = StacksChainState::get_transfer_memos(&mut tx, &tips[num_blocks - 1], &recipient.to_account_principal(), &deposit_memo).unwrap();
+        assert_eq!(transfers.len(), 3);
+        for (i, transfer) in transfers.iter().enumerate() {
+            assert_eq!(transfer.amount, (2 * i + 1) as u64);
+            assert_eq!(transfer.sender, addr);
+            assert_eq!(transfer.recipient, recipient.to_account_principal());
+            assert_eq!(transfer.memo, deposit_memo);
+            assert_eq!(transfer.index_block_hash(), tips[2 * i + 1]);
+        }
+
+        // only the transfers mined by then, as of an earlier block
+        let transfers = StacksChainState::get_transfer_memos(&mut tx, &tips[2], &recipient.to_account_principal(), &deposit_memo).unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].amount, 1);
+
+        // memo-less transfers aren't indexed
+        let transfers = StacksChainState::get_transfer_memos(&mut tx, &tips[num_blocks - 1], &recipient.to_account_principal(), &no_memo).unwrap();
+        assert_eq!(transfers.len(), 0);
+
+        // the memo only matches for its recipient
+        let transfers = StacksChainState::get_transfer_memos(&mut tx, &tips[num_blocks - 1], &addr.to_account_principal(), &deposit_memo).unwrap();
+        assert_eq!(transfers.len(), 0);
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_stx_transfers_receipt_index() {
+        let privk = StacksPrivateKey::from_hex("42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01").unwrap();
+        let addr = StacksAddress::from_public_keys(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, &AddressHashMode::SerializeP2PKH, 1, &vec![StacksPublicKey::from_private(&privk)]).unwrap();
+
+        let mut peer_config = TestPeerConfig::new("test_build_anchored_blocks_stx_transfers_receipt_index", 2016, 2017);
+        peer_config.initial_balances = vec![
+            (addr.to_account_principal(), 1000000000)
+        ];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 4;
+
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        let mut tips = vec![];
+        let mut transfer_txids = vec![];
+        for tenure_id in 0..num_blocks {
+            // send transactions to the mempool
+            let tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(|ref mut miner, ref mut burndb, ref mut chainstate, vrf_proof, ref parent_opt, ref parent_microblock_header_opt| {
+                let parent_tip = match parent_opt {
+                    None => {
+                        StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap()
+                    }
+                    Some(block) => {
+                        let ic = burndb.index_conn();
+                        let snapshot = BurnDB::get_block_snapshot_for_winning_stacks_block(&ic, &tip.burn_header_hash, &block.block_hash()).unwrap().unwrap();      // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(&chainstate.headers_db, &snapshot.burn_header_hash, &snapshot.winning_stacks_block_hash).unwrap().unwrap()
+                    }
+                };
+
+                let parent_header_hash = parent_tip.anchored_header.block_hash();
+                let parent_tip_bhh = parent_tip.burn_header_hash.clone();
+
+                let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                // initial balances aren't materialized if the tip is the genesis header
+                if tenure_id > 0 {
+                    let stx_transfer = sign_standard_singlesig_tx(TransactionPayload::TokenTransfer(recipient.to_account_principal(), tenure_id as u64, TokenTransferMemo([0u8; 34])),
+                                                                  &privk, sender_nonce, 200);
+                    sender_nonce += 1;
+
+                    transfer_txids.push(stx_transfer.txid());
+                    mempool.submit(&parent_tip_bhh, &parent_header_hash, stx_transfer).unwrap();
+                } 
+                let anchored_block = StacksBlockBuilder::build_anchored_block(chainstate, &mempool, &parent_tip, tip.total_burn, vrf_proof, Hash160([tenure_id as u8; 20]), &coinbase_tx, ExecutionCost::max_value()).unwrap();
+                (anchored_block.0, vec![])
+            });
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let burn_tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+            tips.push(StacksBlockHeader::make_index_block_hash(&burn_tip.burn_header_hash, &stacks_block.block_hash()));
+        }
+
+        let chainstate = peer.chainstate();
+        let mut tx = chainstate.headers_tx_begin().unwrap();
+
+        for (i, txid) in transfer_txids.iter().enumerate() {
+            let receipt = StacksChainState::get_transaction_receipt(&mut tx, &tips[num_blocks - 1], txid).unwrap().unwrap();
+            assert_eq!(receipt.txid, *txid);
+            assert_eq!(receipt.status, "success");
+            assert_eq!(receipt.result, Value::okay_true());
+            assert_eq!(receipt.index_block_hash(), tips[i + 1]);
+
+            assert_eq!(receipt.events.len(), 1);
+            assert_eq!(receipt.events[0]["type"], "stx_transfer_event");
+            assert_eq!(receipt.events[0]["committed"], true);
+            assert_eq!(receipt.events[0]["stx_transfer_event"]["amount"], format!("{}", i + 1));
+            assert_eq!(receipt.events[0]["stx_transfer_event"]["recipient"], recipient_addr_str);
+        }
+
+        // transactions mined after an earlier tip have no receipt as of that tip
+        assert!(StacksChainState::get_transaction_receipt(&mut tx, &tips[1], &transfer_txids[0]).unwrap().is_some());
+        assert!(StacksChainState::get_transaction_receipt(&mut tx, &tips[1], &transfer_txids[1]).unwrap().is_none());
+
+        // receipts and headers can be found by a prefix of their hashes
+        for (i, txid) in transfer_txids.iter().enumerate() {
+            let prefix = &txid.to_hex()[0..16];
+            let receipts = StacksChainState::find_transaction_receipts_by_prefix(&tx, prefix, 10).unwrap();
+            assert_eq!(receipts.len(), 1);
+            assert_eq!(receipts[0].txid, *txid);
+
+            let prefix = &tips[i + 1].to_hex()[0..16];
+            let headers = StacksChainState::find_headers_by_hash_prefix(&tx, prefix, 10).unwrap();
+            assert_eq!(headers.len(), 1);
+            assert_eq!(headers[0].index_block_hash(), tips[i + 1]);
+        }
+
+        // the empty prefix matches everything, up to the limit
+        assert_eq!(StacksChainState::find_transaction_receipts_by_prefix(&tx, "", 2).unwrap().len(), 2);
+        assert_eq!(StacksChainState::find_headers_by_hash_prefix(&tx, "", 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_stx_supply() {
+        let privk = StacksPrivateKey::from_hex("42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01").unwrap();
+        let addr = StacksAddress::from_public_keys(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, &AddressHashMode::SerializeP2PKH, 1, &vec![StacksPublicKey::from_private(&privk)]).unwrap();
+
+        let initial_balance = 1000000000;
+        let mut peer_config = TestPeerConfig::new("test_build_anchored_blocks_stx_supply", 2016, 2017);
+        peer_config.initial_balances = vec![
+            (addr.to_account_principal(), initial_balance)
+        ];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 10;
+        let fee = 200;
+
+        let recipient = StacksAddress::from_string("ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV").unwrap();
+        let mut sender_nonce = 0;
+
+        let mut tips = vec![];
+        for tenure_id in 0..num_blocks {
+            // send transactions to the mempool
+            let tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(|ref mut miner, ref mut burndb, ref mut chainstate, vrf_proof, ref parent_opt, ref parent_microblock_header_opt| {
+                let parent_tip = match parent_opt {
+                    None => {
+                        StacksChainState::get_genesis_header_info(&chainstate.headers_db).unwrap()
+                    }
+                    Some(block) => {
+                        let ic = burndb.index_conn();
+                        let snapshot = BurnDB::get_block_snapshot_for_winning_stacks_block(&ic, &tip.burn_header_hash, &block.block_hash()).unwrap().unwrap();      // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(&chainstate.headers_db, &snapshot.burn_header_hash, &snapshot.winning_stacks_block_hash).unwrap().unwrap()
+                    }
+                };
+
+                let parent_header_hash = parent_tip.anchored_header.block_hash();
+                let parent_tip_bhh = parent_tip.burn_header_hash.clone();
+
+                let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                // initial balances aren't materialized if the tip is the genesis header
+                if tenure_id > 0 {
+                    let stx_transfer = sign_standard_singlesig_tx(TransactionPayload::TokenTransfer(recipient.to_account_principal(), 1000, TokenTransferMemo([0u8; 34])),
+                                                                  &privk, sender_nonce, fee);
+                    sender_nonce += 1;
+                    mempool.submit(&parent_tip_bhh, &parent_header_hash, stx_transfer).unwrap();
+                } 
+                let anchored_block = StacksBlockBuilder::build_anchored_block(chainstate, &mempool, &parent_tip, tip.total_burn, vrf_proof, Hash160([tenure_id as u8; 20]), &coinbase_tx, ExecutionCost::max_value()).unwrap();
+                (anchored_block.0, vec![])
+            });
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let burn_tip = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+            tips.push(StacksBlockHeader::make_index_block_hash(&burn_tip.burn_header_hash, &stacks_block.block_hash()));
+        }
+
+        let chainstate = peer.chainstate();
+        let tx = chainstate.headers_tx_begin().unwrap();
+
+        let genesis_supply = StacksChainState::get_stx_supply(&tx, &StacksBlockHeader::make_index_block_hash(&FIRST_BURNCHAIN_BLOCK_HASH, &FIRST_STACKS_BLOCK_HASH)).unwrap().unwrap();
+        assert_eq!(genesis_supply.liquid_ustx, initial_balance as u128);
+        assert_eq!(genesis_supply.locked_ustx, 0);
+
+        for (i, tip) in tips.iter().enumerate() {
+            let supply = StacksChainState::get_stx_supply(&tx, tip).unwrap().unwrap();
+            let fees = (i as u128) * (fee as u128);
+
+            // fees leave the sender's balance, and wait for their block's reward to mature
+            assert_eq!(supply.block_height, (i + 1) as u64);
+            assert_eq!(supply.liquid_ustx, (initial_balance as u128) - fees);
+            assert!(supply.locked_ustx >= fees);
+            assert_eq!(supply.total_ustx(), supply.liquid_ustx + supply.locked_ustx);
+
+            // no coinbases have matured yet
+            if i < 5 {
+                assert_eq!(supply.locked_ustx, fees);
+            }
+        }
+
+        // by the last tip, some coinbases have matured
+        let supply = StacksChainState::get_stx_supply(&tx, &tips[num_blocks - 1]).unwrap().unwrap();
+        assert!(supply.locked_ustx > ((num_blocks - 1) as u128) * (fee as u128));
+    }
+
     #[test]
     fn test_build_anchored_blocks_stx_transfers_multi() {
         let mut privks = vec![];
@@ -26,6 +26,9 @@ pub mod index;
 pub mod miner;
 pub mod transaction;
 
+#[cfg(test)]
+mod golden;
+
 use std::fmt;
 use std::error;
 use std::ops::Deref;
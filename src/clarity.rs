@@ -29,6 +29,7 @@ use vm::{SymbolicExpression, SymbolicExpressionType, Value, execute as vm_execut
 use vm::analysis;
 use vm::analysis::{errors::CheckResult, AnalysisDatabase, ContractAnalysis};
 use vm::analysis::contract_interface_builder::build_contract_interface;
+use vm::analysis::contract_interface_builder::typescript::build_typescript_bindings;
 use vm::types::{QualifiedContractIdentifier, PrincipalData};
 use vm::costs::LimitedCostTracker;
 
@@ -60,6 +61,8 @@ where command is one of:
   eval_raw           to typecheck and evaluate an expression without a contract or database context.
   repl               to typecheck and evaluate expressions in a stdin/stdout loop.
   execute            to execute a public function of a defined contract.
+  bindings           to generate TypeScript bindings for a defined contract.
+  dependencies       to list the contracts a defined contract calls, and the contracts that call it.
   generate_address   to generate a random Stacks public address for testing purposes.
 ", invoked_by);
     panic_test!()
@@ -621,6 +624,73 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                 }
             }
         },
+        "bindings" => {
+            if args.len() < 3 {
+                eprintln!("Usage: {} {} [contract-identifier] [vm-state.db]", invoked_by, args[0]);
+                panic_test!();
+            }
+            let vm_filename = &args[2];
+
+            let contract_identifier = friendly_expect(QualifiedContractIdentifier::parse(&args[1]), "Failed to parse contract identifier.");
+
+            let marf_kv = friendly_expect(MarfedKV::open(vm_filename, None), "Failed to open VM database.");
+            let contract_analysis = at_chaintip(
+                vm_filename,
+                marf_kv,
+                |mut marf| {
+                    let contract_analysis = {
+                        let mut db = AnalysisDatabase::new(&mut marf);
+                        db.begin();
+                        let contract_analysis = db.load_contract(&contract_identifier);
+                        db.roll_back();
+                        contract_analysis
+                    };
+                    (marf, contract_analysis)
+                });
+
+            let contract_analysis = friendly_expect_opt(contract_analysis, &format!("No such contract: {}", &contract_identifier));
+            print!("{}", build_typescript_bindings(&contract_identifier, &build_contract_interface(&contract_analysis)));
+        },
+        "dependencies" => {
+            if args.len() < 3 {
+                eprintln!("Usage: {} {} [contract-identifier] [vm-state.db]", invoked_by, args[0]);
+                panic_test!();
+            }
+            let vm_filename = &args[2];
+
+            let contract_identifier = friendly_expect(QualifiedContractIdentifier::parse(&args[1]), "Failed to parse contract identifier.");
+
+            let marf_kv = friendly_expect(MarfedKV::open(vm_filename, None), "Failed to open VM database.");
+            let dependencies = at_chaintip(
+                vm_filename,
+                marf_kv,
+                |mut marf| {
+                    let dependencies = {
+                        let mut db = AnalysisDatabase::new(&mut marf);
+                        db.begin();
+                        let dependencies = if db.has_contract(&contract_identifier) {
+                            let calls = db.get_contract_dependencies(&contract_identifier);
+                            let called_by = db.get_contract_callers(&contract_identifier);
+                            Some((calls, called_by))
+                        } else {
+                            None
+                        };
+                        db.roll_back();
+                        dependencies
+                    };
+                    (marf, dependencies)
+                });
+
+            let (calls, called_by) = friendly_expect_opt(dependencies, &format!("No such contract: {}", &contract_identifier));
+            let to_strings = |contracts: Vec<QualifiedContractIdentifier>| -> Vec<String> {
+                contracts.iter().map(|contract| contract.to_string()).collect()
+            };
+            println!("{}", json!({
+                "contract": contract_identifier.to_string(),
+                "calls": calls.map(to_strings),
+                "called_by": to_strings(called_by)
+            }));
+        },
         "execute" => {
             if args.len() < 5 {
                 eprintln!("Usage: {} {} [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, args[0]);
@@ -750,6 +820,14 @@ mod test {
                                  db_name.clone()
                                  ]);
 
+        eprintln!("bindings tokens");
+        invoke_command("test", &["bindings".to_string(), "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(),
+                                 db_name.clone()]);
+
+        eprintln!("dependencies names");
+        invoke_command("test", &["dependencies".to_string(), "S1G2081040G2081040G2081040G208105NK8PE5.names".to_string(),
+                                 db_name.clone()]);
+
         eprintln!("eval_at_chaintip tokens");
         invoke_command("test", &["eval_at_chaintip".to_string(), "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(), 
                                  "sample-contracts/tokens-mint.clar".to_string(),
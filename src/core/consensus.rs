@@ -0,0 +1,158 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// Registry of the constants that two nodes must agree on to follow the same chain.  The
+// constants themselves stay next to the code that uses them; this module lists them in one
+// place, so operators can dump and compare them across builds.
+
+use core::{
+    SYSTEM_FORK_SET_VERSION, PEER_VERSION, NETWORK_ID_MAINNET, NETWORK_ID_TESTNET,
+    FIRST_BURNCHAIN_BLOCK_HASH, FIRST_BURNCHAIN_BLOCK_HASH_TESTNET, FIRST_BURNCHAIN_BLOCK_HASH_REGTEST,
+    FIRST_BURNCHAIN_BLOCK_TIMESTAMP, FIRST_STACKS_BLOCK_HASH, EMPTY_MICROBLOCK_PARENT_HASH,
+    BOOT_BLOCK_HASH, BURNCHAIN_BOOT_BLOCK_HASH, CHAINSTATE_VERSION
+};
+use burnchains::BLOCKSTACK_MAGIC_MAINNET;
+use burnchains::bitcoin::indexer::{FIRST_BLOCK_MAINNET, FIRST_BLOCK_TESTNET, FIRST_BLOCK_REGTEST};
+use chainstate::burn::CONSENSUS_HASH_LIFETIME;
+use chainstate::stacks::{
+    C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_MAINNET_MULTISIG,
+    C32_ADDRESS_VERSION_TESTNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_MULTISIG,
+    STACKS_BLOCK_VERSION, STACKS_MICROBLOCK_VERSION, MAX_TRANSACTION_LEN, MAX_BLOCK_LEN,
+    MAX_EPOCH_SIZE, MAX_MICROBLOCK_SIZE, TOKEN_TRANSFER_MEMO_LENGTH
+};
+use chainstate::stacks::db::{
+    STACKS_BOOT_CODE_CONTRACT_ADDRESS, MINER_REWARD_MATURITY, MINER_REWARD_WINDOW,
+    MINER_FEE_MINIMUM_BLOCK_USAGE, MINER_FEE_WINDOW
+};
+use vm::MAX_CALL_STACK_DEPTH;
+use vm::contexts::MAX_CONTEXT_DEPTH;
+use vm::costs::CLARITY_MEMORY_LIMIT;
+use vm::representations::MAX_STRING_LEN;
+use vm::types::{MAX_VALUE_SIZE, MAX_TYPE_DEPTH};
+
+use util::hash::{to_hex, Sha256Sum};
+
+/// One consensus-critical constant, with its value rendered as a string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusConstant {
+    pub name: String,
+    pub category: String,
+    pub value: String,
+}
+
+impl ConsensusConstant {
+    fn new(category: &str, name: &str, value: String) -> ConsensusConstant {
+        ConsensusConstant {
+            name: name.to_string(),
+            category: category.to_string(),
+            value: value
+        }
+    }
+}
+
+/// All consensus-critical constants compiled into this build, grouped by category.
+pub fn consensus_constants() -> Vec<ConsensusConstant> {
+    vec![
+        // network and chainstate versions
+        ConsensusConstant::new("version", "SYSTEM_FORK_SET_VERSION", to_hex(&SYSTEM_FORK_SET_VERSION)),
+        ConsensusConstant::new("version", "PEER_VERSION", format!("0x{:08x}", PEER_VERSION)),
+        ConsensusConstant::new("version", "NETWORK_ID_MAINNET", format!("0x{:08x}", NETWORK_ID_MAINNET)),
+        ConsensusConstant::new("version", "NETWORK_ID_TESTNET", format!("0x{:08x}", NETWORK_ID_TESTNET)),
+        ConsensusConstant::new("version", "CHAINSTATE_VERSION", CHAINSTATE_VERSION.to_string()),
+        ConsensusConstant::new("version", "STACKS_BLOCK_VERSION", format!("{}", STACKS_BLOCK_VERSION)),
+        ConsensusConstant::new("version", "STACKS_MICROBLOCK_VERSION", format!("{}", STACKS_MICROBLOCK_VERSION)),
+
+        // magic bytes and genesis hashes
+        ConsensusConstant::new("genesis", "BLOCKSTACK_MAGIC_MAINNET", to_hex(BLOCKSTACK_MAGIC_MAINNET.as_bytes())),
+        ConsensusConstant::new("genesis", "FIRST_BURNCHAIN_BLOCK_HASH", FIRST_BURNCHAIN_BLOCK_HASH.to_hex()),
+        ConsensusConstant::new("genesis", "FIRST_BURNCHAIN_BLOCK_HASH_TESTNET", FIRST_BURNCHAIN_BLOCK_HASH_TESTNET.to_hex()),
+        ConsensusConstant::new("genesis", "FIRST_BURNCHAIN_BLOCK_HASH_REGTEST", FIRST_BURNCHAIN_BLOCK_HASH_REGTEST.to_hex()),
+        ConsensusConstant::new("genesis", "FIRST_BURNCHAIN_BLOCK_TIMESTAMP", format!("{}", FIRST_BURNCHAIN_BLOCK_TIMESTAMP)),
+        ConsensusConstant::new("genesis", "FIRST_BLOCK_MAINNET", format!("{}", FIRST_BLOCK_MAINNET)),
+        ConsensusConstant::new("genesis", "FIRST_BLOCK_TESTNET", format!("{}", FIRST_BLOCK_TESTNET)),
+        ConsensusConstant::new("genesis", "FIRST_BLOCK_REGTEST", format!("{}", FIRST_BLOCK_REGTEST)),
+        ConsensusConstant::new("genesis", "FIRST_STACKS_BLOCK_HASH", FIRST_STACKS_BLOCK_HASH.to_hex()),
+        ConsensusConstant::new("genesis", "EMPTY_MICROBLOCK_PARENT_HASH", EMPTY_MICROBLOCK_PARENT_HASH.to_hex()),
+        ConsensusConstant::new("genesis", "BOOT_BLOCK_HASH", BOOT_BLOCK_HASH.to_hex()),
+        ConsensusConstant::new("genesis", "BURNCHAIN_BOOT_BLOCK_HASH", BURNCHAIN_BOOT_BLOCK_HASH.to_hex()),
+        ConsensusConstant::new("genesis", "STACKS_BOOT_CODE_CONTRACT_ADDRESS", STACKS_BOOT_CODE_CONTRACT_ADDRESS.to_string()),
+
+        // address versions
+        ConsensusConstant::new("address", "C32_ADDRESS_VERSION_MAINNET_SINGLESIG", format!("{}", C32_ADDRESS_VERSION_MAINNET_SINGLESIG)),
+        ConsensusConstant::new("address", "C32_ADDRESS_VERSION_MAINNET_MULTISIG", format!("{}", C32_ADDRESS_VERSION_MAINNET_MULTISIG)),
+        ConsensusConstant::new("address", "C32_ADDRESS_VERSION_TESTNET_SINGLESIG", format!("{}", C32_ADDRESS_VERSION_TESTNET_SINGLESIG)),
+        ConsensusConstant::new("address", "C32_ADDRESS_VERSION_TESTNET_MULTISIG", format!("{}", C32_ADDRESS_VERSION_TESTNET_MULTISIG)),
+
+        // block and transaction limits
+        ConsensusConstant::new("limits", "MAX_TRANSACTION_LEN", format!("{}", MAX_TRANSACTION_LEN)),
+        ConsensusConstant::new("limits", "MAX_BLOCK_LEN", format!("{}", MAX_BLOCK_LEN)),
+        ConsensusConstant::new("limits", "MAX_EPOCH_SIZE", format!("{}", MAX_EPOCH_SIZE)),
+        ConsensusConstant::new("limits", "MAX_MICROBLOCK_SIZE", format!("{}", MAX_MICROBLOCK_SIZE)),
+        ConsensusConstant::new("limits", "TOKEN_TRANSFER_MEMO_LENGTH", format!("{}", TOKEN_TRANSFER_MEMO_LENGTH)),
+        ConsensusConstant::new("limits", "CONSENSUS_HASH_LIFETIME", format!("{}", CONSENSUS_HASH_LIFETIME)),
+
+        // miner rewards
+        ConsensusConstant::new("rewards", "MINER_REWARD_MATURITY", format!("{}", MINER_REWARD_MATURITY)),
+        ConsensusConstant::new("rewards", "MINER_REWARD_WINDOW", format!("{}", MINER_REWARD_WINDOW)),
+        ConsensusConstant::new("rewards", "MINER_FEE_MINIMUM_BLOCK_USAGE", format!("{}", MINER_FEE_MINIMUM_BLOCK_USAGE)),
+        ConsensusConstant::new("rewards", "MINER_FEE_WINDOW", format!("{}", MINER_FEE_WINDOW)),
+
+        // Clarity limits
+        ConsensusConstant::new("clarity", "MAX_VALUE_SIZE", format!("{}", MAX_VALUE_SIZE)),
+        ConsensusConstant::new("clarity", "MAX_TYPE_DEPTH", format!("{}", MAX_TYPE_DEPTH)),
+        ConsensusConstant::new("clarity", "MAX_STRING_LEN", format!("{}", MAX_STRING_LEN)),
+        ConsensusConstant::new("clarity", "MAX_CALL_STACK_DEPTH", format!("{}", MAX_CALL_STACK_DEPTH)),
+        ConsensusConstant::new("clarity", "MAX_CONTEXT_DEPTH", format!("{}", MAX_CONTEXT_DEPTH)),
+        ConsensusConstant::new("clarity", "CLARITY_MEMORY_LIMIT", format!("{}", CLARITY_MEMORY_LIMIT)),
+    ]
+}
+
+/// Hash of all the consensus constants.  Two builds that report the same digest agree on every
+/// constant in the registry.
+pub fn consensus_constants_digest(constants: &[ConsensusConstant]) -> Sha256Sum {
+    let mut buf = vec![];
+    for constant in constants.iter() {
+        buf.extend_from_slice(format!("{}={}\n", &constant.name, &constant.value).as_bytes());
+    }
+    Sha256Sum::from_data(&buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn consensus_constants_are_unique() {
+        let constants = consensus_constants();
+        let names : HashSet<_> = constants.iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names.len(), constants.len());
+    }
+
+    #[test]
+    fn consensus_constants_digest_tracks_values() {
+        let constants = consensus_constants();
+        let digest = consensus_constants_digest(&constants);
+        assert_eq!(digest, consensus_constants_digest(&consensus_constants()));
+
+        let mut changed = constants.clone();
+        changed[0].value = "00000000".to_string();
+        assert!(digest != consensus_constants_digest(&changed));
+    }
+}
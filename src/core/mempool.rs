@@ -45,16 +45,23 @@ use chainstate::stacks::{
 use std::io::Read;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use util::db::u64_to_sql;
+use util::db::hex_prefix_bounds;
 use util::db::{DBConn, DBTx, FromRow};
 use util::db::FromColumn;
 use util::db::query_rows;
 use util::db::query_row;
+use util::db::query_row_columns;
+use util::db::query_count;
 use util::db::Error as db_error;
 use util::get_epoch_time_secs;
 use util::db::tx_begin_immediate;
-use util::db::tx_busy_handler;
+use util::db::{sqlite_open, DBReadPool, PooledDBConn, SqlitePragmas};
+use util::migrations::{SchemaMigration, migrate_schema};
+
+use vm::costs::ExecutionCost;
 
 use core::FIRST_STACKS_BLOCK_HASH;
 use core::FIRST_BURNCHAIN_BLOCK_HASH;
@@ -62,6 +69,9 @@ use core::FIRST_BURNCHAIN_BLOCK_HASH;
 // maximum number of confirmations a transaction can have before it's garbage-collected
 pub const MEMPOOL_MAX_TRANSACTION_AGE : u64 = 256;
 
+// how many idle read-only connections to keep open to the mempool
+pub const MEMPOOL_READ_POOL_SIZE : usize = 4;
+
 pub struct MemPoolAdmitter {
     // mempool admission should have its own chain state view.
     //   the mempool admitter interacts with the chain state
@@ -160,6 +170,93 @@ impl FromRow<MemPoolTxInfo> for MemPoolTxInfo {
     }
 }
 
+/// Fee rate percentiles reported in the mempool statistics
+pub const MEMPOOL_STATS_FEE_RATE_PERCENTILES : &'static [u64] = &[5, 25, 50, 75, 95];
+
+/// Upper bounds, in seconds, of the transaction age buckets reported in the mempool statistics.
+/// Transactions older than the last bound go in one more, open-ended bucket.
+pub const MEMPOOL_STATS_AGE_BUCKETS : &'static [u64] = &[60, 600, 3600, 21600, 86400];
+
+/// The fee rate paid by at least `percentile` percent of pending transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolFeeRatePercentile {
+    pub percentile: u64,
+    pub fee_rate: u64,
+}
+
+/// How many pending transactions have been in the mempool for less than `max_age` seconds (and
+/// at least as long as the previous bucket's `max_age`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolAgeBucket {
+    /// None for the last, open-ended bucket
+    pub max_age: Option<u64>,
+    pub tx_count: u64,
+}
+
+/// Summary of the transactions pending in the mempool, across all chain tips
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolStats {
+    pub tx_count: u64,
+    /// total length of the pending transactions
+    pub total_bytes: u64,
+    pub min_fee_rate: Option<u64>,
+    pub max_fee_rate: Option<u64>,
+    /// empty if the mempool is empty
+    pub fee_rate_percentiles: Vec<MemPoolFeeRatePercentile>,
+    pub age_distribution: Vec<MemPoolAgeBucket>,
+}
+
+impl MemPoolStats {
+    /// Summarize the given pending transactions as of `now`
+    pub fn from_metadata(txs: &[MemPoolTxMetadata], now: u64) -> MemPoolStats {
+        let mut fee_rates : Vec<u64> = txs.iter().map(|md| md.fee_rate).collect();
+        fee_rates.sort();
+
+        // nearest-rank percentiles
+        let fee_rate_percentiles =
+            if fee_rates.len() > 0 {
+                MEMPOOL_STATS_FEE_RATE_PERCENTILES
+                    .iter()
+                    .map(|percentile| {
+                        let rank = (percentile * (fee_rates.len() as u64) + 99) / 100;
+                        let index = if rank > 0 { (rank - 1) as usize } else { 0 };
+                        MemPoolFeeRatePercentile {
+                            percentile: *percentile,
+                            fee_rate: fee_rates[index]
+                        }
+                    })
+                    .collect()
+            }
+            else {
+                vec![]
+            };
+
+        let mut age_distribution : Vec<MemPoolAgeBucket> = MEMPOOL_STATS_AGE_BUCKETS
+            .iter()
+            .map(|max_age| MemPoolAgeBucket { max_age: Some(*max_age), tx_count: 0 })
+            .collect();
+        age_distribution.push(MemPoolAgeBucket { max_age: None, tx_count: 0 });
+
+        for md in txs.iter() {
+            let age = now.saturating_sub(md.accept_time);
+            let bucket = MEMPOOL_STATS_AGE_BUCKETS
+                .iter()
+                .position(|max_age| age < *max_age)
+                .unwrap_or(MEMPOOL_STATS_AGE_BUCKETS.len());
+            age_distribution[bucket].tx_count += 1;
+        }
+
+        MemPoolStats {
+            tx_count: txs.len() as u64,
+            total_bytes: txs.iter().map(|md| md.len).sum(),
+            min_fee_rate: fee_rates.first().cloned(),
+            max_fee_rate: fee_rates.last().cloned(),
+            fee_rate_percentiles,
+            age_distribution
+        }
+    }
+}
+
 
 const MEMPOOL_SQL : &'static [&'static str] = &[
     r#"
@@ -192,6 +289,7 @@ pub struct MemPoolDB {
     db: DBConn,
     path: String,
     admitter: MemPoolAdmitter,
+    read_pool: Arc<DBReadPool>,
 }
 
 pub struct MemPoolTx<'a> {
@@ -225,6 +323,10 @@ impl<'a> MemPoolTx<'a> {
     }
 }
 
+/// Changes to the mempool DB's schema, applied when it's opened (see util::migrations).  Add a
+/// migration here instead of changing MEMPOOL_SQL.
+pub const MEMPOOL_DB_MIGRATIONS : &'static [SchemaMigration] = &[];
+
 impl MemPoolDB {
     fn instantiate_mempool_db(conn: &mut DBConn) -> Result<(), db_error> {
         let tx = tx_begin_immediate(conn)?;
@@ -240,6 +342,12 @@ impl MemPoolDB {
     /// Open the mempool db within the chainstate directory.
     /// The chainstate must be instantiated already.
     pub fn open(mainnet: bool, chain_id: u32, chainstate_path: &str) -> Result<MemPoolDB, db_error> {
+        MemPoolDB::open_with_pragmas(mainnet, chain_id, chainstate_path, &SqlitePragmas::default())
+    }
+
+    /// Like open(), but the mempool db (and the chainstate it's opened with) is tuned with
+    /// `pragmas`
+    pub fn open_with_pragmas(mainnet: bool, chain_id: u32, chainstate_path: &str, pragmas: &SqlitePragmas) -> Result<MemPoolDB, db_error> {
         match fs::metadata(chainstate_path) {
             Ok(md) => {
                 if !md.is_dir() {
@@ -251,7 +359,7 @@ impl MemPoolDB {
             }
         }
 
-        let chainstate = StacksChainState::open(mainnet, chain_id, chainstate_path)
+        let chainstate = StacksChainState::open_with_pragmas(mainnet, chain_id, chainstate_path, ExecutionCost::max_value(), pragmas)
             .map_err(|e| db_error::Other(format!("Failed to open chainstate: {:?}", &e)))?;
         
         let mut path = PathBuf::from(chainstate.root_path.clone());
@@ -273,16 +381,17 @@ impl MemPoolDB {
                 OpenFlags::SQLITE_OPEN_READ_WRITE
             };
 
-        let mut conn = DBConn::open_with_flags(&db_path, open_flags).map_err(db_error::SqliteError)?;
-        conn.busy_handler(Some(tx_busy_handler)).map_err(db_error::SqliteError)?;
+        let mut conn = sqlite_open(&db_path, open_flags, Some(pragmas)).map_err(db_error::SqliteError)?;
 
         if create_flag {
             // instantiate!
             MemPoolDB::instantiate_mempool_db(&mut conn)?;
         }
-        
+        migrate_schema(&mut conn, MEMPOOL_DB_MIGRATIONS, None, false)?;
+
         Ok(MemPoolDB {
             db: conn,
+            read_pool: Arc::new(DBReadPool::new(&db_path, MEMPOOL_READ_POOL_SIZE, pragmas)),
             path: db_path.to_string(),
             admitter: admitter,
         })
@@ -418,6 +527,16 @@ impl MemPoolDB {
         &self.db
     }
 
+    /// Borrow a read-only connection to the mempool, separate from the writer connection.
+    pub fn read_conn(&self) -> Result<PooledDBConn, db_error> {
+        self.read_pool.get()
+    }
+
+    /// Shared handle to the mempool's read-only connection pool, for threads that only query.
+    pub fn read_pool(&self) -> Arc<DBReadPool> {
+        self.read_pool.clone()
+    }
+
     pub fn tx_begin<'a>(&'a mut self) -> Result<MemPoolTx<'a>, db_error> {
         let tx = tx_begin_immediate(&mut self.db)?;
         Ok(MemPoolTx::new(tx, &mut self.admitter))
@@ -436,6 +555,26 @@ impl MemPoolDB {
         query_row(conn, "SELECT estimated_fee FROM mempool WHERE txid = ?1", &[txid as &dyn ToSql])
     }
 
+    /// Get the number of transactions across all tips
+    pub fn get_tx_count(conn: &DBConn) -> Result<u64, db_error> {
+        let sql = "SELECT COUNT(*) FROM mempool".to_string();
+        query_count(conn, &sql, NO_PARAMS).map(|cnt| cnt as u64)
+    }
+
+    /// Get the lowest fee rate paid by any transaction across all tips
+    pub fn get_min_fee_rate(conn: &DBConn) -> Result<Option<u64>, db_error> {
+        query_row(conn, "SELECT fee_rate FROM mempool ORDER BY fee_rate ASC LIMIT 1", NO_PARAMS)
+    }
+
+    /// Summarize the transactions across all tips as of `now`
+    pub fn get_stats(conn: &DBConn, now: u64) -> Result<MemPoolStats, db_error> {
+        // everything but the transaction itself
+        let sql = "SELECT txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, estimated_fee, fee_rate, length, \
+                   burn_header_hash, block_header_hash, height, accept_time FROM mempool";
+        let txs = query_rows::<MemPoolTxMetadata, _>(conn, &sql, NO_PARAMS)?;
+        Ok(MemPoolStats::from_metadata(&txs, now))
+    }
+
     /// Get all transactions across all tips
     #[cfg(test)]
     pub fn get_all_txs(conn: &DBConn) -> Result<Vec<MemPoolTxInfo>, db_error> {
@@ -504,6 +643,33 @@ impl MemPoolDB {
         Ok(rows)
     }
 
+    /// Get up to `count` txids across all chain tips, in txid order, starting after `cursor` (if
+    /// given).  Txids are unique and immutable, so paging by txid stays stable as transactions
+    /// are added and removed.
+    pub fn get_txids_after(conn: &DBConn, cursor: Option<&Txid>, count: u64) -> Result<Vec<Txid>, db_error> {
+        match cursor {
+            Some(txid) => {
+                let sql = "SELECT txid FROM mempool WHERE txid > ?1 ORDER BY txid ASC LIMIT ?2".to_string();
+                let args : &[&dyn ToSql] = &[txid, &u64_to_sql(count)?];
+                query_row_columns(conn, &sql, args, "txid")
+            },
+            None => {
+                let sql = "SELECT txid FROM mempool ORDER BY txid ASC LIMIT ?1".to_string();
+                let args : &[&dyn ToSql] = &[&u64_to_sql(count)?];
+                query_row_columns(conn, &sql, args, "txid")
+            }
+        }
+    }
+
+    /// Get up to `count` txids across all chain tips, in txid order, that start with the given
+    /// lowercase hex prefix.
+    pub fn find_txids_by_prefix(conn: &DBConn, prefix: &str, count: u64) -> Result<Vec<Txid>, db_error> {
+        let (lower, upper) = hex_prefix_bounds(prefix);
+        let sql = "SELECT txid FROM mempool WHERE txid >= ?1 AND txid < ?2 ORDER BY txid ASC LIMIT ?3".to_string();
+        let args : &[&dyn ToSql] = &[&lower, &upper, &u64_to_sql(count)?];
+        query_row_columns(conn, &sql, args, "txid")
+    }
+
     /// Get a transaction's metadata in a chain tip, given its origin and sponsor metadata.
     /// Faster than getting the MemPoolTxInfo, since no deserialization will be needed.
     /// Used to see if there exists a transaction with this info, so as to implement replace-by-fee
@@ -725,7 +891,10 @@ mod tests {
         StacksTransaction, TransactionSmartContract, TransactionContractCall, StacksAddress };
 
     use util::db::{DBConn, FromRow};
+    use rusqlite::NO_PARAMS;
+    use rusqlite::types::ToSql;
     use super::MemPoolDB;
+    use super::{MemPoolStats, MemPoolAgeBucket, MemPoolFeeRatePercentile};
 
     use burnchains::BurnchainHeaderHash;
     use chainstate::stacks::test::codec_all_transactions;
@@ -745,6 +914,154 @@ mod tests {
         let mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
     }
 
+    #[test]
+    fn mempool_db_txid_pages() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_txid_pages");
+        let chainstate_path = chainstate_path("mempool_db_txid_pages");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(&TransactionVersion::Testnet, 0x80000000, &TransactionAnchorMode::Any, &TransactionPostConditionMode::Allow);
+        let mut expected_txids = vec![];
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, tx) in txs.drain(..).enumerate() {
+            let origin_address = StacksAddress { version: 22, bytes: Hash160::from_data(&i.to_be_bytes()) };
+            let sponsor_address = StacksAddress { version: 22, bytes: Hash160::from_data(&(i + 1).to_be_bytes()) };
+
+            let txid = tx.txid();
+            let mut tx_bytes = vec![];
+            tx.consensus_serialize(&mut tx_bytes).unwrap();
+            let len = tx_bytes.len() as u64;
+
+            MemPoolDB::try_add_tx(&mut mempool_tx, &BurnchainHeaderHash([0x1; 32]), &BlockHeaderHash([0x2; 32]), txid, tx_bytes, tx.get_fee_rate() * len, tx.get_fee_rate(), 100,
+                                  &origin_address, tx.get_origin_nonce(), &sponsor_address, tx.get_origin_nonce()).unwrap();
+            expected_txids.push(txid);
+        }
+        mempool_tx.commit().unwrap();
+
+        // pages are in the DB's (hex string) txid order, not Txid's little-endian Ord
+        expected_txids.sort_by_key(|txid| txid.to_hex());
+        expected_txids.dedup();
+
+        // walk every page, and confirm we see every txid exactly once, in order
+        let mut txids = vec![];
+        let mut cursor = None;
+        loop {
+            let page = MemPoolDB::get_txids_after(mempool.conn(), cursor.as_ref(), 3).unwrap();
+            assert!(page.len() <= 3);
+            if page.len() == 0 {
+                break;
+            }
+            cursor = page.last().cloned();
+            txids.extend(page);
+        }
+
+        assert_eq!(txids, expected_txids);
+
+        // pooled read-only connections see the committed txs, and go back to the pool
+        {
+            let read_conn_1 = mempool.read_conn().unwrap();
+            let read_conn_2 = mempool.read_conn().unwrap();
+            let page_1 = MemPoolDB::get_txids_after(&read_conn_1, None, expected_txids.len() as u64).unwrap();
+            let page_2 = MemPoolDB::get_txids_after(&read_conn_2, None, expected_txids.len() as u64).unwrap();
+            assert_eq!(page_1, expected_txids);
+            assert_eq!(page_2, expected_txids);
+            assert_eq!(mempool.read_pool().num_idle(), 0);
+        }
+        assert_eq!(mempool.read_pool().num_idle(), 2);
+
+        // the writer uses WAL
+        let journal_mode : String = mempool.conn().query_row("PRAGMA journal_mode", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+    }
+
+    #[test]
+    fn mempool_db_pragmas_per_open() {
+        let _chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_pragmas_per_open");
+        let chainstate_path = chainstate_path("mempool_db_pragmas_per_open");
+
+        let tuned_pragmas = SqlitePragmas {
+            cache_size: Some(-4096),
+            synchronous: "FULL".to_string(),
+            ..SqlitePragmas::default()
+        };
+        let tuned = MemPoolDB::open_with_pragmas(false, 0x80000000, &chainstate_path, &tuned_pragmas).unwrap();
+        let untuned = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        // each handle is tuned as it was opened, not as the last one was
+        let cache_size = |conn: &DBConn| -> i64 { conn.query_row("PRAGMA cache_size", NO_PARAMS, |row| row.get(0)).unwrap() };
+        let synchronous = |conn: &DBConn| -> i64 { conn.query_row("PRAGMA synchronous", NO_PARAMS, |row| row.get(0)).unwrap() };
+        assert_eq!(cache_size(tuned.conn()), -4096);
+        assert_ne!(cache_size(untuned.conn()), -4096);
+        assert_eq!(synchronous(tuned.conn()), 2);
+        assert_eq!(synchronous(untuned.conn()), 1);
+
+        // so are the pooled readers
+        let read_conn = tuned.read_conn().unwrap();
+        assert_eq!(cache_size(&*read_conn), -4096);
+    }
+
+    #[test]
+    fn mempool_db_stats() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_stats");
+        let chainstate_path = chainstate_path("mempool_db_stats");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let stats = MemPoolDB::get_stats(mempool.conn(), 1000000).unwrap();
+        assert_eq!(stats.tx_count, 0);
+        assert_eq!(stats.total_bytes, 0);
+        assert_eq!(stats.min_fee_rate, None);
+        assert_eq!(stats.fee_rate_percentiles, vec![]);
+        assert!(stats.age_distribution.iter().all(|bucket| bucket.tx_count == 0));
+
+        let mut txs = codec_all_transactions(&TransactionVersion::Testnet, 0x80000000, &TransactionAnchorMode::Any, &TransactionPostConditionMode::Allow);
+        let mut total_bytes = 0;
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, mut tx) in txs.drain(..).take(10).enumerate() {
+            let origin_address = StacksAddress { version: 22, bytes: Hash160::from_data(&i.to_be_bytes()) };
+            let sponsor_address = StacksAddress { version: 22, bytes: Hash160::from_data(&(i + 1).to_be_bytes()) };
+
+            // fee rates 1 through 10
+            tx.set_fee_rate((i + 1) as u64);
+            let txid = tx.txid();
+            let mut tx_bytes = vec![];
+            tx.consensus_serialize(&mut tx_bytes).unwrap();
+            let len = tx_bytes.len() as u64;
+            total_bytes += len;
+
+            MemPoolDB::try_add_tx(&mut mempool_tx, &BurnchainHeaderHash([0x1; 32]), &BlockHeaderHash([0x2; 32]), txid, tx_bytes, tx.get_fee_rate() * len, tx.get_fee_rate(), 100,
+                                  &origin_address, tx.get_origin_nonce(), &sponsor_address, tx.get_origin_nonce()).unwrap();
+
+            // accepted 10, 20, ... 100 minutes before now
+            let accept_time = 1000000 - 600 * (i as i64 + 1);
+            let args : &[&dyn ToSql] = &[&accept_time, &txid];
+            mempool_tx.execute("UPDATE mempool SET accept_time = ?1 WHERE txid = ?2", args).unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let stats = MemPoolDB::get_stats(mempool.conn(), 1000000).unwrap();
+        assert_eq!(stats.tx_count, 10);
+        assert_eq!(stats.total_bytes, total_bytes);
+        assert_eq!(stats.min_fee_rate, Some(1));
+        assert_eq!(stats.max_fee_rate, Some(10));
+        assert_eq!(stats.fee_rate_percentiles, vec![
+            MemPoolFeeRatePercentile { percentile: 5, fee_rate: 1 },
+            MemPoolFeeRatePercentile { percentile: 25, fee_rate: 3 },
+            MemPoolFeeRatePercentile { percentile: 50, fee_rate: 5 },
+            MemPoolFeeRatePercentile { percentile: 75, fee_rate: 8 },
+            MemPoolFeeRatePercentile { percentile: 95, fee_rate: 10 },
+        ]);
+        assert_eq!(stats.age_distribution, vec![
+            MemPoolAgeBucket { max_age: Some(60), tx_count: 0 },
+            MemPoolAgeBucket { max_age: Some(600), tx_count: 0 },
+            MemPoolAgeBucket { max_age: Some(3600), tx_count: 5 },
+            MemPoolAgeBucket { max_age: Some(21600), tx_count: 5 },
+            MemPoolAgeBucket { max_age: Some(86400), tx_count: 0 },
+            MemPoolAgeBucket { max_age: None, tx_count: 0 },
+        ]);
+    }
+
     #[test]
     fn mempool_db_load_store_replace_tx() {
         let chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_load_store_replace_tx");
@@ -24,6 +24,7 @@ use chainstate::burn::BlockHeaderHash;
 use util::log;
 
 pub mod mempool;
+pub mod consensus;
 pub use self::mempool::MemPoolDB;
 
 // fork set identifier -- to be mixed with the consensus hash (encodes the version)
@@ -40,6 +40,7 @@ extern crate byteorder;
 extern crate mio;
 extern crate url;
 extern crate percent_encoding;
+extern crate flate2;
 
 #[macro_use] extern crate serde_derive;
 #[macro_use] extern crate serde_json;
@@ -51,6 +52,18 @@ extern crate assert_json_diff;
 #[cfg(feature = "monitoring_prom")]
 #[macro_use] pub extern crate prometheus;
 
+#[cfg(feature = "tls")]
+extern crate rustls;
+
+#[cfg(feature = "fuzzing")]
+extern crate arbitrary;
+
+#[cfg(feature = "marf_rocksdb")]
+extern crate rocksdb;
+
+#[cfg(feature = "block_compression")]
+extern crate zstd;
+
 #[macro_use]
 pub mod util;
 
@@ -295,6 +295,16 @@ fn main() {
         return
     }
 
+    if argv[1] == "consensus-constants" {
+        // one constant per line, so two builds' output can be diffed
+        let constants = core::consensus::consensus_constants();
+        for constant in constants.iter() {
+            println!("{}.{} = {}", &constant.category, &constant.name, &constant.value);
+        }
+        println!("digest = {}", core::consensus::consensus_constants_digest(&constants).to_hex());
+        return
+    }
+
     if argv[1] == "local" {
         clarity::invoke_command(&format!("{} {}", argv[0], argv[1]), &argv[2..]);
         return
@@ -6,6 +6,43 @@ pub fn increment_rpc_calls_counter() {
     prometheus::RPC_CALL_COUNTER.inc();    
 }
 
+pub fn increment_rpc_requests_shed_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_REQUESTS_SHED_COUNTER.inc();
+}
+
+/// Record that an RPC request was rejected by a rate limit, labeled by its request class and
+/// which limit it ran into.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn increment_rpc_requests_throttled_counter(request_class: &str, limit: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_REQUESTS_THROTTLED_COUNTER
+        .with_label_values(&[request_class, limit])
+        .inc();
+}
+
+/// Record a call to a deprecated RPC route, labeled by its request class.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn increment_rpc_deprecated_requests_counter(request_class: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_DEPRECATED_REQUESTS_COUNTER
+        .with_label_values(&[request_class])
+        .inc();
+}
+
+/// Record that a heavy RPC request was left queued for a later pass, because no worker was free
+/// for it.
+pub fn increment_rpc_requests_deferred_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_REQUESTS_DEFERRED_COUNTER.inc();
+}
+
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_rpc_overload_level(level: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_OVERLOAD_LEVEL_GAUGE.set(level);
+}
+
 pub fn increment_p2p_msg_unauthenticated_handshake_received_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::P2P_MSG_UNAUTHENTICATED_HANDSHAKE_RECEIVED_COUNTER.inc();    
@@ -86,6 +123,21 @@ pub fn increment_stx_blocks_processed_counter() {
     prometheus::STX_BLOCKS_PROCESSED_COUNTER.inc();    
 }
 
+/// Record what a processed block spent running a contract, labeled by the contract.  There's
+/// one label per contract the node has seen run, so this grows with the chain.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn increment_contract_execution_cost_counters(contract_id: &str, transactions: u64, runtime: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::CONTRACT_TRANSACTIONS_COUNTER
+            .with_label_values(&[contract_id])
+            .inc_by(transactions as i64);
+        prometheus::CONTRACT_EXECUTION_RUNTIME_COUNTER
+            .with_label_values(&[contract_id])
+            .inc_by(runtime as i64);
+    }
+}
+
 pub fn increment_stx_blocks_mined_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::STX_BLOCKS_MINED_COUNTER.inc();    
@@ -100,3 +152,110 @@ pub fn increment_errors_emitted_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::ERRORS_EMITTED_COUNTER.inc();    
 }
+
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_last_block_commit_fee_rate(sats_per_byte: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::LAST_BLOCK_COMMIT_FEE_RATE_GAUGE.set(sats_per_byte as i64);
+}
+
+/// Record how many burnchain blocks it took for a block-commit to be mined, labeled by the
+/// feerate that was chosen for it.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn observe_block_commit_confirmation_latency(latency_blocks: u64, sats_per_byte: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BLOCK_COMMIT_CONFIRMATION_LATENCY_HISTOGRAM
+        .with_label_values(&[fee_rate_bucket(sats_per_byte)])
+        .observe(latency_blocks as f64);
+}
+
+pub fn increment_btc_block_commits_unconfirmed_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BLOCK_COMMITS_UNCONFIRMED_COUNTER.inc();
+}
+
+/// Record how long it took to handle an RPC request, labeled by its request class.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn observe_rpc_request_latency(request_class: &str, latency_secs: f64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_REQUEST_LATENCY_HISTOGRAM
+        .with_label_values(&[request_class])
+        .observe(latency_secs);
+}
+
+/// Record that an RPC request took longer than the slow request threshold, labeled by its
+/// request class.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn increment_rpc_slow_requests_counter(request_class: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_SLOW_REQUESTS_COUNTER
+        .with_label_values(&[request_class])
+        .inc();
+}
+
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_stacks_tip_height(height: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::STACKS_TIP_HEIGHT_GAUGE.set(height as i64);
+}
+
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_burnchain_height(height: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_HEIGHT_GAUGE.set(height as i64);
+}
+
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_neighbors_count(count: usize) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::NEIGHBORS_COUNT_GAUGE.set(count as i64);
+}
+
+/// Record the outcome of the last release manifest check.  `mandatory_burn_height` is the
+/// burnchain height by which this build must be upgraded, if an upgrade is mandatory.
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn set_update_status(update_available: bool, mandatory_burn_height: Option<u64>) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        let level = match (update_available, mandatory_burn_height) {
+            (_, Some(_)) => 2,
+            (true, None) => 1,
+            (false, None) => 0
+        };
+        prometheus::UPDATE_AVAILABLE_GAUGE.set(level);
+        prometheus::MANDATORY_UPDATE_BURN_HEIGHT_GAUGE.set(mandatory_burn_height.unwrap_or(0) as i64);
+    }
+}
+
+/// Render every registered metric in the Prometheus text format.  Returns None if the node was
+/// built without the `monitoring_prom` feature.
+pub fn render_metrics() -> Option<String> {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        use ::prometheus::{Encoder, TextEncoder, gather};
+
+        let encoder = TextEncoder::new();
+        let mut buffer = vec![];
+        if let Err(e) = encoder.encode(&gather(), &mut buffer) {
+            warn!("Failed to encode metrics: {:?}", &e);
+            return None;
+        }
+        return String::from_utf8(buffer).ok();
+    }
+
+    #[cfg(not(feature = "monitoring_prom"))]
+    None
+}
+
+/// Coarse feerate buckets (sats/vbyte), so that the latency metric has a bounded number of labels.
+fn fee_rate_bucket(sats_per_byte: u64) -> &'static str {
+    match sats_per_byte {
+        0..=1 => "1",
+        2..=5 => "5",
+        6..=10 => "10",
+        11..=25 => "25",
+        26..=50 => "50",
+        51..=100 => "100",
+        _ => "inf"
+    }
+}
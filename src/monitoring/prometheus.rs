@@ -1,4 +1,4 @@
-use prometheus::IntCounter;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, HistogramVec};
 
 lazy_static! {
     pub static ref RPC_CALL_COUNTER: IntCounter = register_int_counter!(opts!(
@@ -7,6 +7,34 @@ lazy_static! {
         labels! {"handler" => "all",}
     )).unwrap();
 
+    pub static ref RPC_REQUESTS_SHED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_rpc_requests_shed_total",
+        "Total number of RPC requests rejected because the node was overloaded.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref RPC_REQUESTS_THROTTLED_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_rpc_requests_throttled_total",
+        "Total number of RPC requests rejected by a rate limit, by request class and limit (ip or endpoint)."
+    ), &["request_class", "limit"]).unwrap();
+
+    pub static ref RPC_DEPRECATED_REQUESTS_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_rpc_deprecated_requests_total",
+        "Total number of calls to deprecated RPC routes, by request class."
+    ), &["request_class"]).unwrap();
+
+    pub static ref RPC_REQUESTS_DEFERRED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_rpc_requests_deferred_total",
+        "Total number of times a heavy RPC request was left queued because no worker was free for it.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref RPC_OVERLOAD_LEVEL_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_rpc_overload_level",
+        "Highest priority class of RPC requests currently being shed (0 = none, 1 = low, 2 = normal).",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
     pub static ref P2P_MSG_UNAUTHENTICATED_HANDSHAKE_RECEIVED_COUNTER: IntCounter = register_int_counter!(opts!(
         "stacks_node_p2p_msg_unauthenticated_handshake_received_total",
         "Total number of authenticated Handshake messages received.",
@@ -120,4 +148,73 @@ lazy_static! {
         "Total number of error logs emitted by node.",
         labels! {"handler" => "all",}
     )).unwrap();
+
+    pub static ref LAST_BLOCK_COMMIT_FEE_RATE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_btc_last_block_commit_fee_rate",
+        "Feerate (sats/vbyte) chosen for the most recently submitted block-commit.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref BLOCK_COMMIT_CONFIRMATION_LATENCY_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_btc_block_commit_confirmation_latency_blocks",
+        "Number of burnchain blocks between submitting a block-commit and seeing it mined, by chosen feerate.",
+        vec![1.0, 2.0, 3.0, 5.0, 10.0]
+    ), &["fee_rate"]).unwrap();
+
+    pub static ref BLOCK_COMMITS_UNCONFIRMED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_btc_block_commits_unconfirmed_total",
+        "Total number of block-commits that were never seen mined.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref RPC_REQUEST_LATENCY_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_rpc_request_latency_seconds",
+        "Time taken to handle an RPC request, by request class.",
+        vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    ), &["request_class"]).unwrap();
+
+    pub static ref RPC_SLOW_REQUESTS_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_rpc_slow_requests_total",
+        "Total number of RPC requests that took longer than the slow request threshold, by request class."
+    ), &["request_class"]).unwrap();
+
+    pub static ref CONTRACT_TRANSACTIONS_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_contract_transactions_total",
+        "Total number of processed transactions that called or deployed a contract, by contract."
+    ), &["contract"]).unwrap();
+
+    pub static ref CONTRACT_EXECUTION_RUNTIME_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_contract_execution_runtime_total",
+        "Total runtime cost of processed transactions that called or deployed a contract, by contract."
+    ), &["contract"]).unwrap();
+
+    pub static ref STACKS_TIP_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_stacks_tip_height",
+        "Height of the canonical Stacks chain tip.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref BURNCHAIN_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_burn_block_height",
+        "Height of the burnchain tip.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref NEIGHBORS_COUNT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_neighbors",
+        "Number of connected p2p peers, inbound and outbound.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref UPDATE_AVAILABLE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_update_available",
+        "1 if the release manifest lists a newer release than this build, 2 if upgrading is mandatory, 0 otherwise.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MANDATORY_UPDATE_BURN_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_mandatory_update_burn_height",
+        "Burnchain height by which this build must be upgraded, or 0 if no upgrade is mandatory.",
+        labels! {"handler" => "all",}
+    )).unwrap();
 }
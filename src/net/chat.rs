@@ -72,6 +72,7 @@ use std::convert::TryFrom;
 
 use util::log;
 use util::get_epoch_time_secs;
+use util::get_epoch_time_ms;
 use util::hash::to_hex;
 
 use rand;
@@ -143,6 +144,12 @@ pub struct NeighborStats {
     pub msgs_rx: u64,
     pub msgs_rx_unsolicited: u64,
     pub msgs_err: u64,
+    pub blocks_rx: u64,             // number of blocks pushed to us
+    pub microblocks_rx: u64,        // number of microblocks pushed to us
+    pub blocks_tx: u64,             // number of blocks we pushed
+    pub microblocks_tx: u64,        // number of microblocks we pushed
+    pub last_useful_data_time: u64, // last time the peer sent us chain data or answered a data request
+    pub rtt_ms: u64,                // smoothed round-trip time of our requests to this peer
     pub healthpoints: VecDeque<NeighborHealthPoint>,
     pub msg_rx_counts: HashMap<StacksMessageID, u64>,
     pub block_push_rx_counts: VecDeque<(u64, u64)>,         // (count, num bytes)
@@ -166,6 +173,12 @@ impl NeighborStats {
             msgs_rx: 0,
             msgs_rx_unsolicited: 0,
             msgs_err: 0,
+            blocks_rx: 0,
+            microblocks_rx: 0,
+            blocks_tx: 0,
+            microblocks_tx: 0,
+            last_useful_data_time: 0,
+            rtt_ms: 0,
             healthpoints: VecDeque::new(),
             msg_rx_counts: HashMap::new(),
             block_push_rx_counts: VecDeque::new(),
@@ -207,6 +220,25 @@ impl NeighborStats {
         }
     }
 
+    /// Fold a new round-trip time sample into the smoothed RTT, weighting it 1/8 (as TCP does)
+    pub fn add_rtt_sample(&mut self, rtt_ms: u64) -> () {
+        if self.rtt_ms == 0 {
+            self.rtt_ms = rtt_ms;
+        }
+        else {
+            self.rtt_ms = (7 * self.rtt_ms + rtt_ms) / 8;
+        }
+    }
+
+    /// Count the blocks and microblocks in a message we're sending to this peer
+    pub fn add_sent_data(&mut self, payload: &StacksMessageType) -> () {
+        match *payload {
+            StacksMessageType::Blocks(ref data) => self.blocks_tx += data.blocks.len() as u64,
+            StacksMessageType::Microblocks(ref data) => self.microblocks_tx += data.microblocks.len() as u64,
+            _ => {}
+        }
+    }
+
     pub fn add_relayer(&mut self, addr: NeighborAddress, num_bytes: u64) -> () {
         if let Some(stats) = self.relayed_messages.get_mut(&addr) {
             stats.num_messages += 1;
@@ -703,6 +735,7 @@ impl ConversationP2P {
         msg.consensus_serialize(&mut handle)?;
 
         self.stats.msgs_tx += 1;
+        self.stats.add_sent_data(&msg.payload);
         
         debug!("{:?}: relay-send({}) {} seq {}", &self, self.stats.msgs_tx, _name, _seq);
         Ok(handle)
@@ -719,6 +752,7 @@ impl ConversationP2P {
         msg.consensus_serialize(&mut handle)?;
 
         self.stats.msgs_tx += 1;
+        self.stats.add_sent_data(&msg.payload);
 
         debug!("{:?}: request-send({}) {} seq {}", &self, self.stats.msgs_tx, _name, _seq);
         Ok(handle)
@@ -1095,8 +1129,9 @@ impl ConversationP2P {
         let res = match msg.payload {
             StacksMessageType::GetNeighbors => self.handle_getneighbors(peerdb.conn(), local_peer, chain_view, &msg.preamble),
             StacksMessageType::GetBlocksInv(ref get_blocks_inv) => self.handle_getblocksinv(local_peer, burndb, chainstate, chain_view, &msg.preamble, get_blocks_inv),
-            StacksMessageType::Blocks(_) => {
+            StacksMessageType::Blocks(ref data) => {
                 monitoring::increment_stx_blocks_received_counter();
+                self.stats.blocks_rx += data.blocks.len() as u64;
 
                 // not handled here, but do some accounting -- we can't receive blocks too often,
                 // so close this conversation if we do.
@@ -1108,8 +1143,9 @@ impl ConversationP2P {
                     }
                 }
             },
-            StacksMessageType::Microblocks(_) => {
+            StacksMessageType::Microblocks(ref data) => {
                 monitoring::increment_stx_micro_blocks_received_counter();
+                self.stats.microblocks_rx += data.microblocks.len() as u64;
 
                 // not handled here, but do some accounting -- we can't receive too many
                 // unconfirmed microblocks per second
@@ -1486,6 +1522,24 @@ impl ConversationP2P {
                 self.stats.last_contact_time = get_epoch_time_secs();
                 self.stats.add_healthpoint(true);
 
+                match msg.payload {
+                    StacksMessageType::Neighbors(_) |
+                    StacksMessageType::BlocksInv(_) |
+                    StacksMessageType::BlocksAvailable(_) |
+                    StacksMessageType::MicroblocksAvailable(_) |
+                    StacksMessageType::Blocks(_) |
+                    StacksMessageType::Microblocks(_) |
+                    StacksMessageType::Transaction(_) => {
+                        self.stats.last_useful_data_time = now;
+                    },
+                    _ => {}
+                }
+
+                if let Some(sent_ms) = self.connection.get_request_sent_time_ms(&msg) {
+                    let rtt_ms = get_epoch_time_ms().saturating_sub(sent_ms);
+                    self.stats.add_rtt_sample(rtt_ms as u64);
+                }
+
                 // update chain view from preamble
                 if msg.preamble.burn_block_height > self.burnchain_tip_height {
                     self.burnchain_tip_height = msg.preamble.burn_block_height;
@@ -1571,6 +1625,8 @@ mod test {
     use chainstate::*;
     use chainstate::burn::*;
     use chainstate::burn::db::burndb::*;
+    use chainstate::stacks::StacksBlockId;
+    use chainstate::stacks::test::make_codec_test_block;
 
     use burnchains::bitcoin::address::BitcoinAddress;
     use burnchains::bitcoin::keys::BitcoinPublicKey;
@@ -2773,6 +2829,35 @@ mod test {
             assert_eq!(stats.num_bytes, (msg.preamble.payload_len - 1) as u64);
         }
     }
+
+    #[test]
+    fn neighbor_stats_rtt_and_sent_data() {
+        let mut stats = NeighborStats::new(true);
+
+        // first sample is taken as-is; later ones are smoothed
+        stats.add_rtt_sample(80);
+        assert_eq!(stats.rtt_ms, 80);
+        stats.add_rtt_sample(160);
+        assert_eq!(stats.rtt_ms, 90);
+
+        let blocks = StacksMessageType::Blocks(BlocksData {
+            blocks: vec![
+                (BurnchainHeaderHash([0x11; 32]), make_codec_test_block(1)),
+                (BurnchainHeaderHash([0x22; 32]), make_codec_test_block(1)),
+            ]
+        });
+        let microblocks = StacksMessageType::Microblocks(MicroblocksData {
+            index_anchor_block: StacksBlockId([0x33; 32]),
+            microblocks: vec![]
+        });
+
+        stats.add_sent_data(&blocks);
+        stats.add_sent_data(&microblocks);
+        stats.add_sent_data(&StacksMessageType::Ping(PingData::new()));
+
+        assert_eq!(stats.blocks_tx, 2);
+        assert_eq!(stats.microblocks_tx, 0);
+    }
 }
 
 // TODO: test bandwidth limits
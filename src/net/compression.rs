@@ -0,0 +1,233 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use net::Error as net_error;
+
+/// Content codings we can compress response bodies with.
+/// `deflate` is the zlib-wrapped stream, per RFC 7230.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl HttpContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            HttpContentEncoding::Gzip => "gzip",
+            HttpContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<HttpContentEncoding> {
+        match s.trim().to_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(HttpContentEncoding::Gzip),
+            "deflate" => Some(HttpContentEncoding::Deflate),
+            _ => None
+        }
+    }
+
+    /// Pick the encoding to answer with, given an `Accept-Encoding` header value.
+    /// Prefers gzip; codings (or `*`) with q=0 are refused.  None means send the body as-is.
+    pub fn negotiate(accept_encoding: &str) -> Option<HttpContentEncoding> {
+        let mut gzip_q = None;
+        let mut deflate_q = None;
+        let mut wildcard_q = None;
+
+        for coding in accept_encoding.split(',') {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or("").trim().to_lowercase();
+            let mut q = 1.0f64;
+            for param in parts {
+                let param = param.trim();
+                if param.len() > 2 && param[0..2].eq_ignore_ascii_case("q=") {
+                    q = param[2..].trim().parse::<f64>().unwrap_or(0.0);
+                }
+            }
+            match name.as_str() {
+                "gzip" | "x-gzip" => gzip_q = Some(q),
+                "deflate" => deflate_q = Some(q),
+                "*" => wildcard_q = Some(q),
+                _ => {}
+            }
+        }
+
+        let gzip_q = gzip_q.or(wildcard_q).unwrap_or(0.0);
+        let deflate_q = deflate_q.or(wildcard_q).unwrap_or(0.0);
+        if gzip_q > 0.0 && gzip_q >= deflate_q {
+            Some(HttpContentEncoding::Gzip)
+        }
+        else if deflate_q > 0.0 {
+            Some(HttpContentEncoding::Deflate)
+        }
+        else {
+            None
+        }
+    }
+}
+
+enum HttpEncoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+/// Incrementally compresses a response body as it is streamed out.
+/// Compressed bytes are handed back as soon as the encoder produces them.
+pub struct HttpCompressor {
+    encoder: HttpEncoder,
+}
+
+impl HttpCompressor {
+    pub fn new(encoding: HttpContentEncoding) -> HttpCompressor {
+        let encoder = match encoding {
+            HttpContentEncoding::Gzip => HttpEncoder::Gzip(GzEncoder::new(vec![], Compression::default())),
+            HttpContentEncoding::Deflate => HttpEncoder::Deflate(ZlibEncoder::new(vec![], Compression::default())),
+        };
+        HttpCompressor {
+            encoder
+        }
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        let out = match self.encoder {
+            HttpEncoder::Gzip(ref mut enc) => enc.get_mut(),
+            HttpEncoder::Deflate(ref mut enc) => enc.get_mut(),
+        };
+        let mut ret = vec![];
+        ret.append(out);
+        ret
+    }
+
+    /// Feed body bytes in, and get back whatever compressed bytes are ready (possibly none)
+    pub fn compress(&mut self, buf: &[u8]) -> Result<Vec<u8>, net_error> {
+        match self.encoder {
+            HttpEncoder::Gzip(ref mut enc) => enc.write_all(buf),
+            HttpEncoder::Deflate(ref mut enc) => enc.write_all(buf),
+        }.map_err(net_error::WriteError)?;
+        Ok(self.take_output())
+    }
+
+    /// End the compressed stream, and get back the remaining bytes (including any trailer)
+    pub fn finish(&mut self) -> Result<Vec<u8>, net_error> {
+        match self.encoder {
+            HttpEncoder::Gzip(ref mut enc) => enc.try_finish(),
+            HttpEncoder::Deflate(ref mut enc) => enc.try_finish(),
+        }.map_err(net_error::WriteError)?;
+        Ok(self.take_output())
+    }
+}
+
+/// Compress a whole body at once
+pub fn compress(encoding: HttpContentEncoding, data: &[u8]) -> Result<Vec<u8>, net_error> {
+    let mut compressor = HttpCompressor::new(encoding);
+    let mut ret = compressor.compress(data)?;
+    ret.append(&mut compressor.finish()?);
+    Ok(ret)
+}
+
+/// Decompress a whole body, refusing to inflate it past max_len bytes
+pub fn decompress(encoding: HttpContentEncoding, data: &[u8], max_len: u64) -> Result<Vec<u8>, net_error> {
+    let mut ret = vec![];
+    match encoding {
+        HttpContentEncoding::Gzip => GzDecoder::new(data).take(max_len + 1).read_to_end(&mut ret),
+        HttpContentEncoding::Deflate => ZlibDecoder::new(data).take(max_len + 1).read_to_end(&mut ret),
+    }.map_err(|e| net_error::DeserializeError(format!("Failed to decode {} body: {:?}", encoding.as_str(), &e)))?;
+
+    if (ret.len() as u64) > max_len {
+        return Err(net_error::OverflowError(format!("Decoded {} body exceeds {} bytes", encoding.as_str(), max_len)));
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_content_encoding_negotiate() {
+        let tests = vec![
+            ("gzip", Some(HttpContentEncoding::Gzip)),
+            ("deflate", Some(HttpContentEncoding::Deflate)),
+            ("gzip, deflate, br", Some(HttpContentEncoding::Gzip)),
+            ("deflate, gzip", Some(HttpContentEncoding::Gzip)),
+            ("GZIP", Some(HttpContentEncoding::Gzip)),
+            ("x-gzip", Some(HttpContentEncoding::Gzip)),
+            ("gzip;q=0.5, deflate", Some(HttpContentEncoding::Deflate)),
+            ("gzip; q=0, deflate; q=0.1", Some(HttpContentEncoding::Deflate)),
+            ("gzip;q=0", None),
+            ("*", Some(HttpContentEncoding::Gzip)),
+            ("*;q=0", None),
+            ("gzip;q=0, *", Some(HttpContentEncoding::Deflate)),
+            ("br", None),
+            ("identity", None),
+            ("", None),
+        ];
+
+        for (header, expected) in tests.into_iter() {
+            assert_eq!(HttpContentEncoding::negotiate(header), expected, "Accept-Encoding: {}", header);
+        }
+    }
+
+    #[test]
+    fn test_compress_decompress() {
+        let mut data = vec![];
+        for i in 0..100000 {
+            data.push((i % 17) as u8);
+        }
+
+        for encoding in [HttpContentEncoding::Gzip, HttpContentEncoding::Deflate].iter() {
+            // streamed in pieces, same as all at once
+            let mut compressor = HttpCompressor::new(*encoding);
+            let mut streamed = vec![];
+            for chunk in data.chunks(1000) {
+                streamed.append(&mut compressor.compress(chunk).unwrap());
+            }
+            streamed.append(&mut compressor.finish().unwrap());
+
+            assert!(streamed.len() < data.len() / 10);
+            assert_eq!(decompress(*encoding, &streamed, data.len() as u64).unwrap(), data);
+
+            let whole = compress(*encoding, &data).unwrap();
+            assert_eq!(decompress(*encoding, &whole, data.len() as u64).unwrap(), data);
+
+            // won't inflate past the limit
+            match decompress(*encoding, &whole, (data.len() - 1) as u64) {
+                Err(net_error::OverflowError(_)) => {},
+                x => panic!("Expected overflow, got {:?}", &x)
+            }
+
+            // garbage is rejected
+            match decompress(*encoding, &data[0..1000], data.len() as u64) {
+                Err(net_error::DeserializeError(_)) => {},
+                x => panic!("Expected deserialize error, got {:?}", &x)
+            }
+        }
+
+        // header values are matched leniently
+        assert_eq!(HttpContentEncoding::from_str(" Gzip "), Some(HttpContentEncoding::Gzip));
+        assert_eq!(HttpContentEncoding::from_str("deflate"), Some(HttpContentEncoding::Deflate));
+        assert_eq!(HttpContentEncoding::from_str("br"), None);
+    }
+}
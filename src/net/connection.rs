@@ -39,6 +39,7 @@ use mio::net as mio_net;
 
 use net::Error as net_error;
 use net::StacksMessageCodec;
+use net::overload::RequestPriority;
 use net::Preamble;
 use net::HttpRequestPreamble;
 use net::HttpResponsePreamble;
@@ -67,6 +68,7 @@ use chainstate::burn::ConsensusHash;
 use util::log;
 use util::secp256k1::Secp256k1PublicKey;
 use util::get_epoch_time_secs;
+use util::get_epoch_time_ms;
 use util::sleep_ms;
 use util::hash::to_hex;
 use util::pipe::*;
@@ -78,7 +80,8 @@ use util::pipe::*;
 struct ReceiverNotify<P: ProtocolFamily> {
     expected_seq: u32,
     receiver_input: SyncSender<P::Message>,
-    ttl: u64        // absolute deadline by which this message needs a reply (in seconds since the epoch)
+    ttl: u64,       // absolute deadline by which this message needs a reply (in seconds since the epoch)
+    sent_ms: u128   // when this request was queued (in milliseconds since the epoch)
 }
 
 impl<P: ProtocolFamily> ReceiverNotify<P> {
@@ -86,7 +89,8 @@ impl<P: ProtocolFamily> ReceiverNotify<P> {
         ReceiverNotify {
             expected_seq: seq,
             receiver_input: input,
-            ttl: ttl
+            ttl: ttl,
+            sent_ms: get_epoch_time_ms()
         }
     }
 
@@ -353,6 +357,28 @@ pub struct ConnectionOptions {
     pub public_ip_request_timeout: u64,
     pub public_ip_timeout: u64,
     pub public_ip_max_retries: u64,
+    pub rpc_overload_queue_depth: u64,
+    pub rpc_overload_latency_ms: u64,
+    pub rpc_priority_overrides: Vec<(String, RequestPriority)>,
+    pub rpc_rate_limit_per_ip: u64,
+    pub rpc_rate_limit_per_ip_burst: u64,
+    pub rpc_rate_limit_per_endpoint: Vec<(String, u64)>,
+    pub rpc_api_key: Option<String>,
+    pub rpc_metrics_enabled: bool,
+    pub mined_block_acceptance_budget: u64,
+    pub mempool_relay_capacity: u64,
+    pub mempool_relay_fee_rate_delta: u64,
+    pub rpc_tls_cert_path: Option<String>,
+    pub rpc_tls_key_path: Option<String>,
+    pub rpc_cors_allowed_origins: Vec<String>,
+    pub rpc_cors_allowed_methods: Vec<String>,
+    pub rpc_cors_allowed_headers: Vec<String>,
+    pub rpc_slow_request_ms: u64,
+    pub rpc_compression_enabled: bool,
+    pub rpc_batch_max_requests: u32,
+    pub rpc_serve_sunset_routes: bool,
+    pub rpc_heavy_request_workers: u64,
+    pub rpc_max_heavy_requests_per_connection: u64,
     
     // fault injection
     pub disable_neighbor_walk: bool,
@@ -409,6 +435,29 @@ impl std::default::Default for ConnectionOptions {
             public_ip_request_timeout: 60,  // how often we can attempt to look up our public IP address
             public_ip_timeout: 3600,        // re-learn the public IP ever hour, if it's not given
             public_ip_max_retries: 3,       // maximum number of retries before self-throttling for $public_ip_timeout
+            rpc_overload_queue_depth: 256,  // start shedding RPC requests once this many requests/replies are backed up
+            rpc_overload_latency_ms: 2000,  // start shedding RPC requests once a pass over all HTTP conversations takes this long
+            rpc_priority_overrides: vec![], // use each RPC request's default priority
+            rpc_rate_limit_per_ip: 0,       // no limit on how many RPC requests per second each client IP address can make
+            rpc_rate_limit_per_ip_burst: 0, // if rate-limited, each client IP address can burst up to its per-second limit
+            rpc_rate_limit_per_endpoint: vec![],    // no limit on how many RPC requests per second of any class all clients can make
+            rpc_api_key: None,              // privileged RPC endpoints are disabled
+            rpc_metrics_enabled: false,     // don't serve /metrics from the RPC server
+            mined_block_acceptance_budget: 120,     // neighbors should accept our mined blocks within 2 minutes
+            mempool_relay_capacity: 0,      // relay transactions no matter how full the mempool is
+            mempool_relay_fee_rate_delta: 1,    // once the mempool is near capacity, only relay transactions that pay more than the cheapest one in it
+            rpc_tls_cert_path: None,        // serve plain HTTP from the RPC server
+            rpc_tls_key_path: None,
+            rpc_cors_allowed_origins: vec!["http://localhost:*".to_string(), "http://127.0.0.1:*".to_string(),
+                                           "https://localhost:*".to_string(), "https://127.0.0.1:*".to_string()],   // only local dapps can call us from a browser
+            rpc_cors_allowed_methods: vec!["POST".to_string(), "GET".to_string(), "OPTIONS".to_string()],
+            rpc_cors_allowed_headers: vec!["origin".to_string(), "content-type".to_string()],
+            rpc_slow_request_ms: 1000,      // log RPC requests that take longer than this to handle
+            rpc_compression_enabled: true,  // gzip/deflate blocks and microblocks for clients that accept it
+            rpc_batch_max_requests: 100,    // most API calls one POST /v2/batch can make
+            rpc_serve_sunset_routes: false, // deprecated routes answer 410 Gone once their sunset date passes
+            rpc_heavy_request_workers: 16,  // start at most this many heavy RPC requests (block streams, proofs, read-only calls) per pass
+            rpc_max_heavy_requests_per_connection: 2,   // of which at most this many can come from any one connection
 
             // no faults on by default
             disable_neighbor_walk: false,
@@ -835,6 +884,11 @@ impl<P: ProtocolFamily> ConnectionInbox<P> {
         self.inbox.pop_front()
     }
 
+    /// Look at the oldest message received in the inbox, without taking it
+    pub fn peek_message(&self) -> Option<&P::Message> {
+        self.inbox.front()
+    }
+
     /// How many queued messsages do we have?
     pub fn num_messages(&self) -> usize {
         self.inbox.len()
@@ -1052,6 +1106,14 @@ impl<P: ProtocolFamily + Clone> NetworkConnection<P> {
         solicited
     }
 
+    /// If this message is a reply to one of our requests, get the time at which we queued the
+    /// request (in milliseconds since the epoch).
+    pub fn get_request_sent_time_ms(&self, msg: &P::Message) -> Option<u128> {
+        self.outbox.inflight.iter()
+            .find(|inflight| inflight.expected_seq == msg.request_id())
+            .map(|inflight| inflight.sent_ms)
+    }
+
     /// Fulfill an outstanding request with a message.
     /// Return the message itself if the message was unsolicited
     pub fn fulfill_request(&mut self, msg: P::Message) -> Option<P::Message> {
@@ -1185,6 +1247,11 @@ impl<P: ProtocolFamily + Clone> NetworkConnection<P> {
     pub fn next_inbox_message(&mut self) -> Option<P::Message> {
         self.inbox.next_message()
     }
+
+    /// look at the next inbox message, without taking it
+    pub fn peek_inbox_message(&self) -> Option<&P::Message> {
+        self.inbox.peek_message()
+    }
     
     /// set the public key 
     pub fn set_public_key(&mut self, pubk: Option<Secp256k1PublicKey>) -> () {
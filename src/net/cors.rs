@@ -0,0 +1,162 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::io::Write;
+
+use net::Error as net_error;
+use net::connection::ConnectionOptions;
+
+/// Which browser origins may call the RPC server, and with which methods and headers.
+/// An allowed origin is either `*` (any origin), an exact origin like `https://app.example.com`,
+/// or an origin with any port like `http://localhost:*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CORSPolicy {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl CORSPolicy {
+    /// Let any origin call with the methods and headers the RPC API uses
+    pub fn allow_all() -> CORSPolicy {
+        CORSPolicy {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: vec!["POST".to_string(), "GET".to_string(), "OPTIONS".to_string()],
+            allowed_headers: vec!["origin".to_string(), "content-type".to_string()],
+        }
+    }
+
+    pub fn from_options(opts: &ConnectionOptions) -> CORSPolicy {
+        CORSPolicy {
+            allowed_origins: opts.rpc_cors_allowed_origins.clone(),
+            allowed_methods: opts.rpc_cors_allowed_methods.clone(),
+            allowed_headers: opts.rpc_cors_allowed_headers.clone(),
+        }
+    }
+
+    /// Can any origin call us?
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == "*")
+    }
+
+    /// Can this origin call us?
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| CORSPolicy::origin_matches(allowed, origin))
+    }
+
+    fn origin_matches(allowed: &str, origin: &str) -> bool {
+        if allowed == "*" {
+            return true;
+        }
+        if allowed.ends_with(":*") {
+            // any port, or none
+            let prefix = &allowed[0..allowed.len() - 2];
+            if origin.len() < prefix.len() || !origin[0..prefix.len()].eq_ignore_ascii_case(prefix) {
+                return false;
+            }
+            let rest = &origin[prefix.len()..];
+            return rest.len() == 0 || (rest.len() > 1 && rest.starts_with(':') && rest[1..].chars().all(|c| c.is_ascii_digit()));
+        }
+        allowed.eq_ignore_ascii_case(origin)
+    }
+
+    /// Write the CORS headers for a reply to a request from the given origin (if it said)
+    pub fn write_headers<W: Write>(&self, fd: &mut W, origin: Option<&str>) -> Result<(), net_error> {
+        if self.allows_any_origin() {
+            fd.write_all("Access-Control-Allow-Origin: *\r\n".as_bytes()).map_err(net_error::WriteError)?;
+        }
+        else {
+            if let Some(origin) = origin {
+                if self.allows_origin(origin) {
+                    fd.write_all(format!("Access-Control-Allow-Origin: {}\r\n", origin).as_bytes()).map_err(net_error::WriteError)?;
+                }
+            }
+            // the reply depends on who asked
+            fd.write_all("Vary: Origin\r\n".as_bytes()).map_err(net_error::WriteError)?;
+        }
+        fd.write_all(format!("Access-Control-Allow-Headers: {}\r\n", self.allowed_headers.join(", ")).as_bytes()).map_err(net_error::WriteError)?;
+        fd.write_all(format!("Access-Control-Allow-Methods: {}\r\n", self.allowed_methods.join(", ")).as_bytes()).map_err(net_error::WriteError)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cors_headers(policy: &CORSPolicy, origin: Option<&str>) -> String {
+        let mut bytes = vec![];
+        policy.write_headers(&mut bytes, origin).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_cors_policy_origins() {
+        let policy = CORSPolicy::from_options(&ConnectionOptions::default());
+        assert!(!policy.allows_any_origin());
+
+        assert!(policy.allows_origin("http://localhost"));
+        assert!(policy.allows_origin("http://localhost:3000"));
+        assert!(policy.allows_origin("http://LOCALHOST:3000"));
+        assert!(policy.allows_origin("http://127.0.0.1:8080"));
+        assert!(policy.allows_origin("https://localhost:443"));
+
+        assert!(!policy.allows_origin("http://localhost:"));
+        assert!(!policy.allows_origin("http://localhost:3000x"));
+        assert!(!policy.allows_origin("http://localhost.evil.com"));
+        assert!(!policy.allows_origin("http://localhost.evil.com:3000"));
+        assert!(!policy.allows_origin("https://app.example.com"));
+        assert!(!policy.allows_origin("null"));
+
+        let policy = CORSPolicy {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            .. CORSPolicy::allow_all()
+        };
+        assert!(policy.allows_origin("https://app.example.com"));
+        assert!(!policy.allows_origin("https://app.example.com:8443"));
+        assert!(!policy.allows_origin("http://app.example.com"));
+
+        assert!(CORSPolicy::allow_all().allows_any_origin());
+        assert!(CORSPolicy::allow_all().allows_origin("https://app.example.com"));
+    }
+
+    #[test]
+    fn test_cors_policy_headers() {
+        let txt = cors_headers(&CORSPolicy::allow_all(), None);
+        assert_eq!(txt, "Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: origin, content-type\r\nAccess-Control-Allow-Methods: POST, GET, OPTIONS\r\n");
+
+        let policy = CORSPolicy {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            allowed_methods: vec!["GET".to_string()],
+            allowed_headers: vec!["content-type".to_string()],
+        };
+
+        // allowed origins are echoed back
+        let txt = cors_headers(&policy, Some("https://app.example.com"));
+        assert_eq!(txt, "Access-Control-Allow-Origin: https://app.example.com\r\nVary: Origin\r\nAccess-Control-Allow-Headers: content-type\r\nAccess-Control-Allow-Methods: GET\r\n");
+
+        // others get nothing to go on
+        let txt = cors_headers(&policy, Some("https://evil.com"));
+        assert!(txt.find("Access-Control-Allow-Origin").is_none());
+        assert!(txt.find("Vary: Origin\r\n").is_some());
+
+        let txt = cors_headers(&policy, None);
+        assert!(txt.find("Access-Control-Allow-Origin").is_none());
+    }
+}
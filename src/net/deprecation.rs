@@ -0,0 +1,135 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use net::Error as net_error;
+use net::http::rfc7231_date;
+
+/// An RPC route that is on its way out.  Between `deprecated_at` and `sunset_at`, the route is
+/// still served -- alongside its successor, which serves the new response shape -- but its
+/// replies carry `Deprecation`, `Sunset` and `Link` headers.  After `sunset_at`, the route
+/// answers 410 Gone, unless the node is configured to keep serving sunset routes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteDeprecation {
+    /// Request class of the deprecated route, as named in the RPC router
+    pub request_class: &'static str,
+    /// When the route was deprecated, in seconds since the epoch
+    pub deprecated_at: u64,
+    /// When the route stops being served, in seconds since the epoch
+    pub sunset_at: u64,
+    /// Path of the route that replaces it, if there is one
+    pub successor: Option<&'static str>,
+}
+
+/// Every deprecated route.  To retire a route, add its successor to the router, then list the old
+/// route here with a sunset date far enough out for wallets and other clients to move over.
+/// Remove the route and its entry once the sunset date has passed.
+pub const DEPRECATED_ROUTES: &'static [RouteDeprecation] = &[];
+
+impl RouteDeprecation {
+    /// Find the deprecation of the route for this request class, if it is deprecated
+    pub fn find(request_class: &str) -> Option<&'static RouteDeprecation> {
+        RouteDeprecation::find_in(DEPRECATED_ROUTES, request_class)
+    }
+
+    fn find_in(deprecations: &'static [RouteDeprecation], request_class: &str) -> Option<&'static RouteDeprecation> {
+        deprecations.iter().find(|deprecation| deprecation.request_class == request_class)
+    }
+
+    /// Has the route's sunset date passed?
+    pub fn is_sunset(&self, now: u64) -> bool {
+        now >= self.sunset_at
+    }
+
+    /// Message for a client that calls the route after its sunset date
+    pub fn gone_message(&self) -> String {
+        match self.successor {
+            Some(successor) => format!("{} has been removed; use {} instead", self.request_class, successor),
+            None => format!("{} has been removed", self.request_class)
+        }
+    }
+
+    /// Write the `Deprecation`, `Sunset` and (if there is a successor) `Link` headers for a reply
+    /// from the route.  The `Deprecation` header is a structured-field date, per RFC 9745, and
+    /// `Sunset` is an HTTP-date, per RFC 8594.
+    pub fn write_headers<W: Write>(&self, fd: &mut W) -> Result<(), net_error> {
+        let sunset = rfc7231_date(UNIX_EPOCH + Duration::from_secs(self.sunset_at));
+        fd.write_all(format!("Deprecation: @{}\r\n", self.deprecated_at).as_bytes()).map_err(net_error::WriteError)?;
+        fd.write_all(format!("Sunset: {}\r\n", &sunset).as_bytes()).map_err(net_error::WriteError)?;
+        if let Some(successor) = self.successor {
+            fd.write_all(format!("Link: <{}>; rel=\"successor-version\"\r\n", successor).as_bytes()).map_err(net_error::WriteError)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_DEPRECATED_ROUTES: &'static [RouteDeprecation] = &[
+        RouteDeprecation {
+            request_class: "GetTransferCost",
+            deprecated_at: 1577836800,
+            sunset_at: 1593561600,
+            successor: Some("/v2/fees/transaction"),
+        },
+        RouteDeprecation {
+            request_class: "GetMinedBlocks",
+            deprecated_at: 1577836800,
+            sunset_at: 1593561600,
+            successor: None,
+        },
+    ];
+
+    #[test]
+    fn test_find_deprecation() {
+        assert_eq!(RouteDeprecation::find_in(TEST_DEPRECATED_ROUTES, "GetTransferCost"), Some(&TEST_DEPRECATED_ROUTES[0]));
+        assert_eq!(RouteDeprecation::find_in(TEST_DEPRECATED_ROUTES, "GetInfo"), None);
+    }
+
+    #[test]
+    fn test_sunset() {
+        let deprecation = &TEST_DEPRECATED_ROUTES[0];
+        assert!(!deprecation.is_sunset(1577836800));
+        assert!(!deprecation.is_sunset(1593561599));
+        assert!(deprecation.is_sunset(1593561600));
+
+        assert_eq!(deprecation.gone_message(), "GetTransferCost has been removed; use /v2/fees/transaction instead");
+        assert_eq!(TEST_DEPRECATED_ROUTES[1].gone_message(), "GetMinedBlocks has been removed");
+    }
+
+    #[test]
+    fn test_deprecation_headers() {
+        let mut headers = vec![];
+        TEST_DEPRECATED_ROUTES[0].write_headers(&mut headers).unwrap();
+        let headers = String::from_utf8(headers).unwrap();
+        assert!(headers.contains("Deprecation: @1577836800\r\n"));
+        assert!(headers.contains("Sunset: "));
+        assert!(headers.contains("Link: </v2/fees/transaction>; rel=\"successor-version\"\r\n"));
+
+        let mut headers = vec![];
+        TEST_DEPRECATED_ROUTES[1].write_headers(&mut headers).unwrap();
+        let headers = String::from_utf8(headers).unwrap();
+        assert!(headers.contains("Deprecation: @1577836800\r\n"));
+        assert!(!headers.contains("Link: "));
+    }
+}
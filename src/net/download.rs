@@ -54,6 +54,8 @@ use net::db::*;
 
 use net::p2p::PeerNetwork;
 
+use net::compression::HttpContentEncoding;
+
 use util::db::Error as db_error;
 use util::db::DBConn;
 use util::secp256k1::Secp256k1PublicKey;
@@ -239,6 +241,13 @@ impl BlockDownloader {
         }
     }
 
+    /// Blocks and microblocks compress well, so ask peers to gzip them
+    fn request_metadata(peerhost: PeerHost) -> HttpRequestMetadata {
+        let mut md = HttpRequestMetadata::from_host(peerhost);
+        md.accept_encoding = Some(HttpContentEncoding::Gzip);
+        md
+    }
+
     pub fn reset(&mut self) -> () {
         self.state = BlockDownloaderState::DNSLookupBegin;
 
@@ -1177,7 +1186,7 @@ impl PeerNetwork {
             for sortition_height in priority.drain(..) {
                 match downloader.blocks_to_try.get_mut(&sortition_height) {
                     Some(ref mut keys) => {
-                        match PeerNetwork::begin_request(network, &downloader.dns_lookups, "anchored block", keys, chainstate, |peerhost, index_block_hash| HttpRequestType::GetBlock(HttpRequestMetadata::from_host(peerhost), index_block_hash)) {
+                        match PeerNetwork::begin_request(network, &downloader.dns_lookups, "anchored block", keys, chainstate, |peerhost, index_block_hash| HttpRequestType::GetBlock(BlockDownloader::request_metadata(peerhost), index_block_hash)) {
                             Some((key, handle)) => {
                                 requests.insert(key.clone(), handle);
                             },
@@ -1212,7 +1221,7 @@ impl PeerNetwork {
             for sortition_height in priority.drain(..) {
                 match downloader.microblocks_to_try.get_mut(&sortition_height) {
                     Some(ref mut keys) => {
-                        match PeerNetwork::begin_request(network, &downloader.dns_lookups, "microblock stream", keys, chainstate, |peerhost, index_block_hash| HttpRequestType::GetMicroblocksConfirmed(HttpRequestMetadata::from_host(peerhost), index_block_hash)) {
+                        match PeerNetwork::begin_request(network, &downloader.dns_lookups, "microblock stream", keys, chainstate, |peerhost, index_block_hash| HttpRequestType::GetMicroblocksConfirmed(BlockDownloader::request_metadata(peerhost), index_block_hash)) {
                             Some((key, handle)) => {
                                 requests.insert(key.clone(), handle);
                             },
@@ -40,6 +40,9 @@ use net::HttpResponsePreamble;
 use net::HttpContentType;
 use net::HttpRequestType;
 use net::HttpResponseType;
+use net::overload::RequestPriority;
+use net::cors::CORSPolicy;
+use net::compression::{self, HttpCompressor, HttpContentEncoding};
 use net::HttpVersion;
 use net::StacksHttpPreamble;
 use net::StacksHttpMessage;
@@ -49,20 +52,38 @@ use net::HttpRequestMetadata;
 use net::HttpResponseMetadata;
 use net::NeighborAddress;
 use net::CallReadOnlyRequestBody;
+use net::AnalyzeContractRequestBody;
+use net::FeeEstimateRequestBody;
+use net::SimulateTransactionRequestBody;
+use net::StateOverrideBody;
+use net::RPCBanPeersRequestBody;
+use net::RPCBatchRequestItem;
+use net::RPCBatchResponseItem;
 use net::HTTP_PREAMBLE_MAX_ENCODED_SIZE;
 use net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use net::MAX_MESSAGE_LEN;
 use net::MAX_MICROBLOCKS_UNCONFIRMED;
+use net::RPC_PAGE_LIMIT_DEFAULT;
+use net::RPC_PAGE_LIMIT_MAX;
+use net::RPC_EXPORT_BLOCKS_MAX;
+use net::{BlockExportFormat, ExportedBlockEntry};
+use net::TipRequest;
+use net::RPC_LOG_PARAM_MAX_LEN;
+use net::RPC_TX_WAIT_TIMEOUT_DEFAULT;
+use net::RPC_TX_WAIT_TIMEOUT_MAX;
+use net::BAN_PEERS_REQUEST_MAX_LEN;
 use net::HTTP_REQUEST_ID_RESERVED;
 
 use burnchains::{ Txid, Address };
+use chainstate::burn::BlockHeaderHash;
 use chainstate::stacks::{
     StacksAddress, StacksTransaction, StacksBlock, StacksMicroblock, StacksPublicKey,
-    StacksBlockId
+    StacksBlockId, TokenTransferMemo, TransactionPayload
 };
+use chainstate::stacks::db::transactions::StateOverride;
 
 use util::log;
-use util::hash::hex_bytes;
+use util::hash::{hex_bytes, to_hex};
 use util::retry::RetryReader;
 use util::retry::BoundReader;
 
@@ -70,7 +91,7 @@ use vm::{
     ast::parser::{
         STANDARD_PRINCIPAL_REGEX, PRINCIPAL_DATA_REGEX, CLARITY_NAME_REGEX, CONTRACT_NAME_REGEX
     },
-    types::{ PrincipalData, BOUND_VALUE_SERIALIZATION_HEX },
+    types::{ PrincipalData, QualifiedContractIdentifier, BOUND_VALUE_SERIALIZATION_HEX },
     ClarityName, ContractName, Value
 };
 
@@ -91,29 +112,83 @@ use std::time::SystemTime;
 lazy_static! {
     static ref PATH_GETINFO : Regex = Regex::new(r#"^/v2/info$"#).unwrap();
     static ref PATH_GETNEIGHBORS : Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
-    static ref PATH_GETBLOCK : Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
-    static ref PATH_GETMICROBLOCKS_INDEXED : Regex = Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
-    static ref PATH_GETMICROBLOCKS_CONFIRMED : Regex = Regex::new(r#"^/v2/microblocks/confirmed/([0-9a-f]{64})$"#).unwrap();
-    static ref PATH_GETMICROBLOCKS_UNCONFIRMED : Regex = Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
+    static ref PATH_GETSTATUS : Regex = Regex::new(r#"^/v2/status$"#).unwrap();
+    static ref PATH_GETNEIGHBOR_STATS : Regex = Regex::new(r#"^/v2/neighbors/stats$"#).unwrap();
+    static ref PATH_GETCONSENSUS_CONSTANTS : Regex = Regex::new(r#"^/v2/consensus_constants$"#).unwrap();
+    static ref PATH_GETMINED_BLOCKS : Regex = Regex::new(r#"^/v2/mined_blocks$"#).unwrap();
+    static ref PATH_POST_BAN_PEERS : Regex = Regex::new(r#"^/v2/neighbors/ban$"#).unwrap();
+    static ref PATH_POST_BATCH : Regex = Regex::new(r#"^/v2/batch$"#).unwrap();
+    static ref PATH_GETOPENAPI : Regex = Regex::new(r#"^/v2/openapi\.json$"#).unwrap();
+    static ref PATH_GETBLOCK : Regex = Regex::new(r#"^/v2/blocks/(?P<index_block_hash>[0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETBLOCK_BY_HEIGHT : Regex = Regex::new(r#"^/v2/blocks/by-height/(?P<height>[0-9]{1,20})$"#).unwrap();
+    static ref PATH_GETBLOCK_BY_HASH : Regex = Regex::new(r#"^/v2/blocks/by-hash/(?P<block_hash>[0-9a-f]{64})$"#).unwrap();
+    static ref PATH_EXPORT_BLOCKS : Regex = Regex::new(r#"^/v2/export/blocks$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_INDEXED : Regex = Regex::new(r#"^/v2/microblocks/(?P<index_microblock_hash>[0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_CONFIRMED : Regex = Regex::new(r#"^/v2/microblocks/confirmed/(?P<index_block_hash>[0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_UNCONFIRMED : Regex = Regex::new(r#"^/v2/microblocks/unconfirmed/(?P<index_block_hash>[0-9a-f]{64})/(?P<min_sequence>[0-9]{1,5})$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_UNCONFIRMED_TIP : Regex = Regex::new(r#"^/v2/microblocks/unconfirmed/tip/(?P<min_sequence>[0-9]{1,5})$"#).unwrap();
+    static ref PATH_GETMICROBLOCK_BY_HASH : Regex = Regex::new(r#"^/v2/microblocks/by-hash/(?P<microblock_hash>[0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETBLOCK_TRANSACTIONS : Regex = Regex::new(r#"^/v2/blocks/(?P<index_block_hash>[0-9a-f]{64})/transactions$"#).unwrap();
+    static ref PATH_GETBLOCK_CONTRACT_COSTS : Regex = Regex::new(r#"^/v2/blocks/(?P<index_block_hash>[0-9a-f]{64})/costs$"#).unwrap();
+    static ref PATH_GETBLOCK_RECEIPTS : Regex = Regex::new(r#"^/v2/blocks/(?P<index_block_hash>[0-9a-f]{64})/receipts$"#).unwrap();
+    static ref PATH_GETMEMPOOL_TXIDS : Regex = Regex::new(r#"^/v2/mempool/txids$"#).unwrap();
+    static ref PATH_GETMEMPOOL_STATS : Regex = Regex::new(r#"^/v2/mempool/stats$"#).unwrap();
+    static ref PATH_GETMETRICS : Regex = Regex::new(r#"^/metrics$"#).unwrap();
     static ref PATH_POSTTRANSACTION : Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
+    static ref PATH_GET_TRANSACTION_RECEIPT : Regex = Regex::new(r#"^/v2/transactions/(?P<txid>[0-9a-f]{64})/receipt$"#).unwrap();
+    static ref PATH_GET_STX_SUPPLY : Regex = Regex::new(r#"^/v2/supply$"#).unwrap();
+    static ref PATH_SEARCH_HASH_PREFIX : Regex = Regex::new(&format!(
+        "^/v2/search/(?P<prefix>[0-9a-f]{{4,64}}|{})$", *PRINCIPAL_DATA_REGEX)).unwrap();
     static ref PATH_GET_ACCOUNT: Regex = Regex::new(&format!(
         "^/v2/accounts/(?P<principal>{})$", *PRINCIPAL_DATA_REGEX)).unwrap();
+    static ref PATH_GET_TRANSFERS_BY_MEMO: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/transfers/(?P<memo>[0-9a-f]{{2,68}})$", *PRINCIPAL_DATA_REGEX)).unwrap();
+    static ref PATH_GET_BALANCE_HISTORY: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/balances$", *PRINCIPAL_DATA_REGEX)).unwrap();
+    static ref PATH_GET_TOP_BALANCES : Regex = Regex::new(r#"^/v2/balances/top$"#).unwrap();
+    static ref PATH_GET_ADDRESS_TRANSACTIONS: Regex = Regex::new(&format!(
+        "^/v2/addresses/(?P<principal>{})/transactions$", *PRINCIPAL_DATA_REGEX)).unwrap();
     static ref PATH_GET_MAP_ENTRY: Regex = Regex::new(&format!(
         "^/v2/map_entry/(?P<address>{})/(?P<contract>{})/(?P<map>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX)).unwrap();
+    static ref PATH_GET_DATA_VAR: Regex = Regex::new(&format!(
+        "^/v2/data_var/(?P<address>{})/(?P<contract>{})/(?P<var>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX)).unwrap();
+    static ref PATH_GET_FT_BALANCE: Regex = Regex::new(&format!(
+        "^/v2/ft_balance/(?P<address>{})/(?P<contract>{})/(?P<token>{})/(?P<principal>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX, *PRINCIPAL_DATA_REGEX)).unwrap();
+    static ref PATH_GET_NFT_OWNER: Regex = Regex::new(&format!(
+        "^/v2/nft_owner/(?P<address>{})/(?P<contract>{})/(?P<asset>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX)).unwrap();
     static ref PATH_POST_CALL_READ_ONLY: Regex = Regex::new(&format!(
         "^/v2/contracts/call-read/(?P<address>{})/(?P<contract>{})/(?P<function>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX)).unwrap();
+    static ref PATH_POST_ANALYZE_CONTRACT: Regex = Regex::new(r#"^/v2/contracts/analyze$"#).unwrap();
+    static ref PATH_POST_DRY_RUN_CONTRACT: Regex = Regex::new(r#"^/v2/contracts/dry-run$"#).unwrap();
     static ref PATH_GET_CONTRACT_SRC: Regex = Regex::new(&format!(
         "^/v2/contracts/source/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX)).unwrap();
+    static ref PATH_GET_CONTRACT_RECEIPTS: Regex = Regex::new(&format!(
+        "^/v2/contracts/receipts/(?P<address>{})/(?P<contract>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX)).unwrap();
     static ref PATH_GET_CONTRACT_ABI: Regex = Regex::new(&format!(
         "^/v2/contracts/interface/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX)).unwrap();
+    static ref PATH_GET_TRAIT_IMPLEMENTORS: Regex = Regex::new(&format!(
+        "^/v2/traits/(?P<address>{})/(?P<contract>{})/(?P<trait>{})/implementors$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX)).unwrap();
+    static ref PATH_GET_CONTRACT_DEPENDENCIES: Regex = Regex::new(&format!(
+        "^/v2/contracts/dependencies/(?P<address>{})/(?P<contract>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX)).unwrap();
     static ref PATH_GET_TRANSFER_COST: Regex = Regex::new("^/v2/fees/transfer$").unwrap();
+    static ref PATH_POST_ESTIMATE_FEE: Regex = Regex::new("^/v2/fees/estimate$").unwrap();
+    static ref PATH_POST_SIMULATE_TRANSACTION: Regex = Regex::new("^/v2/transactions/simulate$").unwrap();
     static ref PATH_OPTIONS_WILDCARD: Regex = Regex::new("^/v2/.{0,4096}$").unwrap();
 }
 
+/// Parses the rest of an HTTP request, once its verb and path have matched a route
+type RequestParser<R> = dyn Fn(&mut StacksHttp, &HttpRequestPreamble, &Captures, Option<&str>, &mut R) -> Result<HttpRequestType, net_error>;
+
 /// HTTP headers that we really care about
 #[derive(Debug, Clone, PartialEq)]
 enum HttpReservedHeader {
@@ -480,7 +555,8 @@ impl<'a, R: Read> Read for HttpChunkedTransferReader<'a, R> {
 pub struct HttpChunkedTransferWriterState {
     chunk_size: usize,
     chunk_buf: Vec<u8>,
-    corked: bool
+    corked: bool,
+    compressor: Option<HttpCompressor>
 }
 
 impl HttpChunkedTransferWriterState {
@@ -488,9 +564,17 @@ impl HttpChunkedTransferWriterState {
         HttpChunkedTransferWriterState {
             chunk_size: chunk_size,
             chunk_buf: vec![],
-            corked: false
+            corked: false,
+            compressor: None
         }
     }
+
+    /// Chunk the body after compressing it with the given encoding, if any
+    pub fn new_encoded(chunk_size: usize, encoding: Option<HttpContentEncoding>) -> HttpChunkedTransferWriterState {
+        let mut state = HttpChunkedTransferWriterState::new(chunk_size);
+        state.compressor = encoding.map(HttpCompressor::new);
+        state
+    }
 }
 
 pub struct HttpChunkedTransferWriter<'a, 'state, W: Write> {
@@ -548,10 +632,8 @@ impl<'a, 'state, W: Write> HttpChunkedTransferWriter<'a, 'state, W> {
     pub fn corked(&self) -> bool {
         self.state.corked
     }
-}
 
-impl<'a, 'state, W: Write> Write for HttpChunkedTransferWriter<'a, 'state, W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    fn write_chunks(&mut self, buf: &[u8]) -> io::Result<usize> {
         let mut written = 0;
         while written < buf.len() && !self.state.corked {
             if self.state.chunk_buf.len() > 0 {
@@ -576,10 +658,31 @@ impl<'a, 'state, W: Write> Write for HttpChunkedTransferWriter<'a, 'state, W> {
         }
         Ok(written)
     }
+}
+
+impl<'a, 'state, W: Write> Write for HttpChunkedTransferWriter<'a, 'state, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let compressed = match self.state.compressor {
+            Some(ref mut compressor) => compressor.compress(buf).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:?}", &e)))?,
+            None => {
+                return self.write_chunks(buf);
+            }
+        };
+        // the caller's bytes are all consumed, even if the compressor is holding onto them
+        self.write_chunks(&compressed)?;
+        Ok(buf.len())
+    }
 
     fn flush(&mut self) -> io::Result<()> {
         // send out any bufferred chunk data
         if !self.state.corked {
+            // this is the end of the body, so end the compressed stream too
+            let tail = match self.state.compressor {
+                Some(ref mut compressor) => compressor.finish().map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:?}", &e)))?,
+                None => vec![]
+            };
+            self.write_chunks(&tail)?;
+
             self.flush_chunk()
                 .and_then(|nw| {
                     if nw > 0 {
@@ -754,6 +857,24 @@ fn keep_alive_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result
     Ok(())
 }
 
+/// Headers that depend on the request we're answering
+fn response_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata, cors: &CORSPolicy) -> Result<(), net_error> {
+    keep_alive_headers(fd, md)?;
+    if let Some(deprecation) = md.deprecation {
+        deprecation.write_headers(fd)?;
+    }
+    cors.write_headers(fd, md.client_origin.as_ref().map(|origin| origin.as_str()))
+}
+
+/// Headers for a reply whose body will be compressed, if the client accepts it
+fn encoded_response_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata, cors: &CORSPolicy) -> Result<(), net_error> {
+    response_headers(fd, md, cors)?;
+    if let Some(ref encoding) = md.client_accept_encoding {
+        fd.write_all(format!("Content-Encoding: {}\r\n", encoding.as_str()).as_bytes()).map_err(net_error::WriteError)?;
+    }
+    Ok(())
+}
+
 fn write_headers<W: Write>(fd: &mut W, headers: &HashMap<String, String>) -> Result<(), net_error> {
     for (ref key, ref value) in headers.iter() {
         fd.write_all(key.as_str().as_bytes()).map_err(net_error::WriteError)?;
@@ -764,6 +885,16 @@ fn write_headers<W: Write>(fd: &mut W, headers: &HashMap<String, String>) -> Res
     Ok(())
 }
 
+fn request_headers<W: Write>(fd: &mut W, md: &HttpRequestMetadata) -> Result<(), net_error> {
+    if let Some(ref token) = md.auth_token {
+        fd.write_all(format!("Authorization: Bearer {}\r\n", token).as_bytes()).map_err(net_error::WriteError)?;
+    }
+    if let Some(ref encoding) = md.accept_encoding {
+        fd.write_all(format!("Accept-Encoding: {}\r\n", encoding.as_str()).as_bytes()).map_err(net_error::WriteError)?;
+    }
+    Ok(())
+}
+
 fn default_accept_header() -> String {
     format!("Accept: {}, {}, {}", HttpContentType::Bytes, HttpContentType::JSON, HttpContentType::Text)
 }
@@ -918,9 +1049,9 @@ impl HttpResponsePreamble {
         }
     }
 
-    pub fn ok_JSON_from_md<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result<(), net_error> {
+    pub fn ok_JSON_from_md<W: Write>(fd: &mut W, md: &HttpResponseMetadata, cors: &CORSPolicy) -> Result<(), net_error> {
         HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::JSON,
-                                             md.request_id, |ref mut fd| keep_alive_headers(fd, md))
+                                             md.request_id, |ref mut fd| response_headers(fd, md, cors))
     }
 
     pub fn new_serialized<W: Write, F>(fd: &mut W, status_code: u16, reason: &str, content_length: Option<u32>, content_type: &HttpContentType, request_id: u32, mut write_headers: F) -> Result<(), net_error>
@@ -931,9 +1062,6 @@ impl HttpResponsePreamble {
         fd.write_all(format!("{} {}\r\n", status_code, reason).as_bytes()).map_err(net_error::WriteError)?;
         fd.write_all("Server: stacks/2.0\r\nDate: ".as_bytes()).map_err(net_error::WriteError)?;
         fd.write_all(rfc7231_now().as_bytes()).map_err(net_error::WriteError)?;
-        fd.write_all("\r\nAccess-Control-Allow-Origin: *".as_bytes()).map_err(net_error::WriteError)?;
-        fd.write_all("\r\nAccess-Control-Allow-Headers: origin, content-type".as_bytes()).map_err(net_error::WriteError)?;
-        fd.write_all("\r\nAccess-Control-Allow-Methods: POST, GET, OPTIONS".as_bytes()).map_err(net_error::WriteError)?;
         fd.write_all("\r\nContent-Type: ".as_bytes()).map_err(net_error::WriteError)?;
         fd.write_all(content_type.as_str().as_bytes()).map_err(net_error::WriteError)?;
         fd.write_all("\r\n".as_bytes()).map_err(net_error::WriteError)?;
@@ -1014,7 +1142,10 @@ impl HttpResponsePreamble {
     }
 
     pub fn add_CORS_headers(&mut self) -> () {
+        let policy = CORSPolicy::allow_all();
         self.headers.insert("Access-Control-Allow-Origin".to_string(), "*".to_string());
+        self.headers.insert("Access-Control-Allow-Headers".to_string(), policy.allowed_headers.join(", "));
+        self.headers.insert("Access-Control-Allow-Methods".to_string(), policy.allowed_methods.join(", "));
     }
 
     // do we have Transfer-Encoding: chunked?
@@ -1023,10 +1154,15 @@ impl HttpResponsePreamble {
     }
 }
 
+/// Get an RFC 7231 date that represents the given time
+pub fn rfc7231_date(when: SystemTime) -> String {
+    let when = time::PrimitiveDateTime::from(when);
+    when.format("%a, %b %-d %-Y %-H:%M:%S GMT")
+}
+
 /// Get an RFC 7231 date that represents the current time
 fn rfc7231_now() -> String {
-    let now = time::PrimitiveDateTime::from(SystemTime::now());
-    now.format("%a, %b %-d %-Y %-H:%M:%S GMT")
+    rfc7231_date(SystemTime::now())
 }
 
 impl StacksMessageCodec for HttpResponsePreamble {
@@ -1140,6 +1276,78 @@ impl StacksMessageCodec for HttpResponsePreamble {
     }
 }
 
+/// Cut a value down to at most RPC_LOG_PARAM_MAX_LEN characters for logging
+fn truncate_log_param(value: &str) -> String {
+    match value.char_indices().nth(RPC_LOG_PARAM_MAX_LEN) {
+        Some((end, _)) => format!("{}...({} bytes)", &value[0..end], value.len()),
+        None => value.to_string()
+    }
+}
+
+/// Append query string parameters, if there are any, to a request path
+fn with_query_params(path: String, params: Vec<String>) -> String {
+    if params.len() == 0 {
+        path
+    }
+    else {
+        format!("{}?{}", path, params.join("&"))
+    }
+}
+
+/// The names request_class() gives, one per request type
+pub const REQUEST_CLASSES: &'static [&'static str] = &[
+    "GetInfo",
+    "GetNeighbors",
+    "GetStatus",
+    "GetNeighborStats",
+    "GetConsensusConstants",
+    "GetOpenAPI",
+    "GetMinedBlocks",
+    "GetBlock",
+    "GetBlockByHeight",
+    "GetBlockByHash",
+    "ExportBlocks",
+    "GetMicroblocksIndexed",
+    "GetMicroblocksConfirmed",
+    "GetMicroblocksUnconfirmed",
+    "GetMicroblocksUnconfirmedTip",
+    "GetMicroblockByHash",
+    "GetBlockTransactions",
+    "GetBlockContractCosts",
+    "GetMempoolTxids",
+    "GetMempoolStats",
+    "GetTransfersByMemo",
+    "GetTransactionReceipt",
+    "GetBlockReceipts",
+    "GetContractReceipts",
+    "SearchHashPrefix",
+    "GetStxSupply",
+    "GetBalanceHistory",
+    "GetTopBalances",
+    "GetAddressTransactions",
+    "GetMetrics",
+    "PostTransaction",
+    "GetAccount",
+    "GetMapEntry",
+    "GetDataVar",
+    "GetFungibleTokenBalance",
+    "GetNonFungibleTokenOwner",
+    "GetTransferCost",
+    "EstimateFee",
+    "SimulateTransaction",
+    "GetContractABI",
+    "GetContractSrc",
+    "GetTraitImplementors",
+    "GetContractDependencies",
+    "CallReadOnlyFunction",
+    "AnalyzeContract",
+    "DryRunContract",
+    "BanPeers",
+    "PostBatch",
+    "OptionsPreflight",
+    "Unmatched",
+];
+
 impl HttpRequestType {
     fn try_parse<R: Read, F>(protocol: &mut StacksHttp, verb: &str, regex: &Regex, preamble: &HttpRequestPreamble,
                              path: &str, query: Option<&str>, fd: &mut R, parser: F) -> Result<Option<HttpRequestType>, net_error>
@@ -1156,25 +1364,74 @@ impl HttpRequestType {
         Ok(None)
     }
 
-    pub fn parse<R: Read>(protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, fd: &mut R) -> Result<HttpRequestType, net_error> {
+    /// The RPC router.  Each route is a verb, a path regex, the class of request it makes, and the
+    /// parser for the rest of the request.  Routes are tried in order.
+    fn request_methods<R: Read>() -> Vec<(&'static str, &'static Regex, &'static str, &'static RequestParser<R>)> {
         // TODO: make this static somehow
-        let REQUEST_METHODS: &[(&str, &Regex, &dyn Fn(&mut StacksHttp, &HttpRequestPreamble, &Captures, Option<&str>, &mut R) -> Result<HttpRequestType, net_error>)] = &[
-            ("GET", &PATH_GETINFO, &HttpRequestType::parse_getinfo),
-            ("GET", &PATH_GETNEIGHBORS, &HttpRequestType::parse_getneighbors),
-            ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
-            ("GET", &PATH_GETMICROBLOCKS_INDEXED, &HttpRequestType::parse_getmicroblocks_indexed),
-            ("GET", &PATH_GETMICROBLOCKS_CONFIRMED, &HttpRequestType::parse_getmicroblocks_confirmed),
-            ("GET", &PATH_GETMICROBLOCKS_UNCONFIRMED, &HttpRequestType::parse_getmicroblocks_unconfirmed),
-            ("POST", &PATH_POSTTRANSACTION, &HttpRequestType::parse_posttransaction),
-            ("GET", &PATH_GET_ACCOUNT, &HttpRequestType::parse_get_account),
-            ("POST", &PATH_GET_MAP_ENTRY, &HttpRequestType::parse_get_map_entry),
-            ("GET", &PATH_GET_TRANSFER_COST, &HttpRequestType::parse_get_transfer_cost),
-            ("GET", &PATH_GET_CONTRACT_SRC, &HttpRequestType::parse_get_contract_source),
-            ("GET", &PATH_GET_CONTRACT_ABI, &HttpRequestType::parse_get_contract_abi),
-            ("POST", &PATH_POST_CALL_READ_ONLY, &HttpRequestType::parse_call_read_only),
-            ("OPTIONS", &PATH_OPTIONS_WILDCARD, &HttpRequestType::parse_options_preflight),
+        let request_methods: &[(&'static str, &'static Regex, &'static str, &'static RequestParser<R>)] = &[
+            ("GET", &PATH_GETINFO, "GetInfo", &HttpRequestType::parse_getinfo),
+            ("GET", &PATH_GETNEIGHBORS, "GetNeighbors", &HttpRequestType::parse_getneighbors),
+            ("GET", &PATH_GETSTATUS, "GetStatus", &HttpRequestType::parse_getstatus),
+            ("GET", &PATH_GETNEIGHBOR_STATS, "GetNeighborStats", &HttpRequestType::parse_getneighbor_stats),
+            ("GET", &PATH_GETCONSENSUS_CONSTANTS, "GetConsensusConstants", &HttpRequestType::parse_getconsensus_constants),
+            ("GET", &PATH_GETMINED_BLOCKS, "GetMinedBlocks", &HttpRequestType::parse_getmined_blocks),
+            ("POST", &PATH_POST_BAN_PEERS, "BanPeers", &HttpRequestType::parse_ban_peers),
+            ("POST", &PATH_POST_BATCH, "PostBatch", &HttpRequestType::parse_post_batch),
+            ("GET", &PATH_GETOPENAPI, "GetOpenAPI", &HttpRequestType::parse_getopenapi),
+            ("GET", &PATH_GETBLOCK, "GetBlock", &HttpRequestType::parse_getblock),
+            ("GET", &PATH_GETBLOCK_BY_HEIGHT, "GetBlockByHeight", &HttpRequestType::parse_getblock_by_height),
+            ("GET", &PATH_GETBLOCK_BY_HASH, "GetBlockByHash", &HttpRequestType::parse_getblock_by_hash),
+            ("GET", &PATH_EXPORT_BLOCKS, "ExportBlocks", &HttpRequestType::parse_export_blocks),
+            ("GET", &PATH_GETMICROBLOCKS_INDEXED, "GetMicroblocksIndexed", &HttpRequestType::parse_getmicroblocks_indexed),
+            ("GET", &PATH_GETMICROBLOCKS_CONFIRMED, "GetMicroblocksConfirmed", &HttpRequestType::parse_getmicroblocks_confirmed),
+            ("GET", &PATH_GETMICROBLOCKS_UNCONFIRMED, "GetMicroblocksUnconfirmed", &HttpRequestType::parse_getmicroblocks_unconfirmed),
+            ("GET", &PATH_GETMICROBLOCKS_UNCONFIRMED_TIP, "GetMicroblocksUnconfirmedTip", &HttpRequestType::parse_getmicroblocks_unconfirmed_tip),
+            ("GET", &PATH_GETMICROBLOCK_BY_HASH, "GetMicroblockByHash", &HttpRequestType::parse_getmicroblock_by_hash),
+            ("GET", &PATH_GETBLOCK_TRANSACTIONS, "GetBlockTransactions", &HttpRequestType::parse_getblock_transactions),
+            ("GET", &PATH_GETBLOCK_CONTRACT_COSTS, "GetBlockContractCosts", &HttpRequestType::parse_getblock_contract_costs),
+            ("GET", &PATH_GETMEMPOOL_TXIDS, "GetMempoolTxids", &HttpRequestType::parse_getmempool_txids),
+            ("GET", &PATH_GETMEMPOOL_STATS, "GetMempoolStats", &HttpRequestType::parse_getmempool_stats),
+            ("GET", &PATH_GETMETRICS, "GetMetrics", &HttpRequestType::parse_getmetrics),
+            ("POST", &PATH_POSTTRANSACTION, "PostTransaction", &HttpRequestType::parse_posttransaction),
+            ("GET", &PATH_GET_TRANSACTION_RECEIPT, "GetTransactionReceipt", &HttpRequestType::parse_get_transaction_receipt),
+            ("GET", &PATH_GETBLOCK_RECEIPTS, "GetBlockReceipts", &HttpRequestType::parse_getblock_receipts),
+            ("GET", &PATH_GET_CONTRACT_RECEIPTS, "GetContractReceipts", &HttpRequestType::parse_get_contract_receipts),
+            ("GET", &PATH_SEARCH_HASH_PREFIX, "SearchHashPrefix", &HttpRequestType::parse_search_hash_prefix),
+            ("GET", &PATH_GET_STX_SUPPLY, "GetStxSupply", &HttpRequestType::parse_get_stx_supply),
+            ("GET", &PATH_GET_ACCOUNT, "GetAccount", &HttpRequestType::parse_get_account),
+            ("GET", &PATH_GET_TRANSFERS_BY_MEMO, "GetTransfersByMemo", &HttpRequestType::parse_get_transfers_by_memo),
+            ("GET", &PATH_GET_BALANCE_HISTORY, "GetBalanceHistory", &HttpRequestType::parse_get_balance_history),
+            ("GET", &PATH_GET_TOP_BALANCES, "GetTopBalances", &HttpRequestType::parse_get_top_balances),
+            ("GET", &PATH_GET_ADDRESS_TRANSACTIONS, "GetAddressTransactions", &HttpRequestType::parse_get_address_transactions),
+            ("POST", &PATH_GET_MAP_ENTRY, "GetMapEntry", &HttpRequestType::parse_get_map_entry),
+            ("GET", &PATH_GET_DATA_VAR, "GetDataVar", &HttpRequestType::parse_get_data_var),
+            ("GET", &PATH_GET_FT_BALANCE, "GetFungibleTokenBalance", &HttpRequestType::parse_get_ft_balance),
+            ("POST", &PATH_GET_NFT_OWNER, "GetNonFungibleTokenOwner", &HttpRequestType::parse_get_nft_owner),
+            ("GET", &PATH_GET_TRANSFER_COST, "GetTransferCost", &HttpRequestType::parse_get_transfer_cost),
+            ("POST", &PATH_POST_ESTIMATE_FEE, "EstimateFee", &HttpRequestType::parse_estimate_fee),
+            ("POST", &PATH_POST_SIMULATE_TRANSACTION, "SimulateTransaction", &HttpRequestType::parse_simulate_transaction),
+            ("GET", &PATH_GET_CONTRACT_SRC, "GetContractSrc", &HttpRequestType::parse_get_contract_source),
+            ("GET", &PATH_GET_CONTRACT_ABI, "GetContractABI", &HttpRequestType::parse_get_contract_abi),
+            ("GET", &PATH_GET_TRAIT_IMPLEMENTORS, "GetTraitImplementors", &HttpRequestType::parse_get_trait_implementors),
+            ("GET", &PATH_GET_CONTRACT_DEPENDENCIES, "GetContractDependencies", &HttpRequestType::parse_get_contract_dependencies),
+            ("POST", &PATH_POST_CALL_READ_ONLY, "CallReadOnlyFunction", &HttpRequestType::parse_call_read_only),
+            ("POST", &PATH_POST_ANALYZE_CONTRACT, "AnalyzeContract", &HttpRequestType::parse_analyze_contract),
+            ("POST", &PATH_POST_DRY_RUN_CONTRACT, "DryRunContract", &HttpRequestType::parse_dry_run_contract),
+            ("OPTIONS", &PATH_OPTIONS_WILDCARD, "OptionsPreflight", &HttpRequestType::parse_options_preflight),
         ];
+        request_methods.to_vec()
+    }
+
+    /// The verb, path regex, and request class of each route the RPC server handles, in the
+    /// order they are matched.
+    pub fn routes() -> Vec<(&'static str, &'static Regex, &'static str)> {
+        HttpRequestType::request_methods::<&[u8]>()
+            .into_iter()
+            .map(|(verb, regex, request_class, _parser)| (verb, regex, request_class))
+            .collect()
+    }
 
+    pub fn parse<R: Read>(protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, fd: &mut R) -> Result<HttpRequestType, net_error> {
         // use url::Url to parse path and query string
         //   Url will refuse to parse just a path, so create a dummy URL
         let local_url = format!("http://local{}", &preamble.path);
@@ -1185,7 +1442,7 @@ impl HttpRequestType {
             .decode_utf8()
             .map_err(|_e| net_error::DeserializeError("Http request path could not be parsed as UTF-8".to_string()))?;
 
-        for (verb, regex, parser) in REQUEST_METHODS.iter() {
+        for (verb, regex, _request_class, parser) in HttpRequestType::request_methods::<R>().iter() {
             match HttpRequestType::try_parse(protocol, verb, regex, preamble, &decoded_path, url.query(), fd, parser)? {
                 Some(request) => {
                     return Ok(request);
@@ -1216,6 +1473,109 @@ impl HttpRequestType {
         Ok(HttpRequestType::GetNeighbors(HttpRequestMetadata::from_preamble(preamble)))
     }
 
+    /// Parse a GET /v2/status request.  If the query has `require_ready=1`, the caller wants an
+    /// error status while the node is still in its initial block download.
+    fn parse_getstatus<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetStatus".to_string()));
+        }
+
+        let require_ready = match query {
+            Some(query_string) => form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "require_ready")
+                .map(|(_k, value)| value == "1" || value == "true")
+                .unwrap_or(false),
+            None => false
+        };
+
+        Ok(HttpRequestType::GetStatus(HttpRequestMetadata::from_preamble(preamble), require_ready))
+    }
+
+    fn parse_getneighbor_stats<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetNeighborStats".to_string()));
+        }
+
+        Ok(HttpRequestType::GetNeighborStats(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    fn parse_getconsensus_constants<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetConsensusConstants".to_string()));
+        }
+
+        Ok(HttpRequestType::GetConsensusConstants(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    fn parse_getopenapi<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetOpenAPI".to_string()));
+        }
+
+        Ok(HttpRequestType::GetOpenAPI(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    fn parse_getmined_blocks<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMinedBlocks".to_string()));
+        }
+
+        Ok(HttpRequestType::GetMinedBlocks(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    fn parse_ban_peers<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < BAN_PEERS_REQUEST_MAX_LEN) {
+            return Err(net_error::DeserializeError("Invalid Http request: invalid body length for BanPeers".to_string()));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".to_string()));
+        }
+
+        let body : RPCBanPeersRequestBody = serde_json::from_reader(fd.take(content_len as u64))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        Ok(HttpRequestType::BanPeers(HttpRequestMetadata::from_preamble(preamble), body.ip, body.port))
+    }
+
+    fn parse_post_batch<R: Read>(protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError("Invalid Http request: invalid body length for PostBatch".to_string()));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".to_string()));
+        }
+
+        let items : Vec<RPCBatchRequestItem> = serde_json::from_reader(fd.take(content_len as u64))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        if items.len() > (protocol.maximum_batch_size as usize) {
+            return Err(net_error::DeserializeError(format!("Invalid Http request: batch has more than {} requests", protocol.maximum_batch_size)));
+        }
+
+        Ok(HttpRequestType::PostBatch(HttpRequestMetadata::from_preamble(preamble), items))
+    }
+
+    /// Parse one of the API calls in a batch, as if it had been made on its own by the same
+    /// client.  Its metadata is that of the batch, less any API key or accepted encoding.
+    pub fn parse_batch_item(protocol: &mut StacksHttp, md: &HttpRequestMetadata, item: &RPCBatchRequestItem) -> Result<HttpRequestType, net_error> {
+        let mut preamble = HttpRequestPreamble::new(md.version, item.method.to_uppercase(), item.path.clone(), md.peer.hostname(), md.peer.port(), md.keep_alive);
+        let body = match item.body {
+            Some(ref body) => {
+                let body_bytes = serde_json::to_vec(body)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+                preamble.set_content_type(HttpContentType::JSON);
+                preamble.set_content_length(body_bytes.len() as u32);
+                body_bytes
+            },
+            None => vec![]
+        };
+        HttpRequestType::parse(protocol, &preamble, &mut &body[..])
+    }
+
     fn parse_get_transfer_cost<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetTransferCost".to_string()));
@@ -1224,6 +1584,104 @@ impl HttpRequestType {
         Ok(HttpRequestType::GetTransferCost(HttpRequestMetadata::from_preamble(preamble)))
     }
 
+    fn parse_estimate_fee<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError("Invalid Http request: invalid body length for EstimateFee".to_string()));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".to_string()));
+        }
+
+        let body: FeeEstimateRequestBody = serde_json::from_reader(fd.take(content_len as u64))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let sender = StacksAddress::from_string(&body.sender)
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse sender address".into()))?;
+
+        let payload_hex = if body.payload.starts_with("0x") { &body.payload[2..] } else { &body.payload[..] };
+        let payload_bytes = hex_bytes(payload_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode payload hex".into()))?;
+        let payload = TransactionPayload::consensus_deserialize(&mut &payload_bytes[..])
+            .map_err(|_e| net_error::DeserializeError("Failed to deserialize transaction payload".into()))?;
+
+        Ok(HttpRequestType::EstimateFee(HttpRequestMetadata::from_preamble(preamble), sender, payload))
+    }
+
+    fn parse_simulate_transaction<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError("Invalid Http request: invalid body length for SimulateTransaction".to_string()));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".to_string()));
+        }
+
+        let body: SimulateTransactionRequestBody = serde_json::from_reader(fd.take(content_len as u64))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let sender = StacksAddress::from_string(&body.sender)
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse sender address".into()))?;
+
+        let payload_hex = if body.payload.starts_with("0x") { &body.payload[2..] } else { &body.payload[..] };
+        let payload_bytes = hex_bytes(payload_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode payload hex".into()))?;
+        let payload = TransactionPayload::consensus_deserialize(&mut &payload_bytes[..])
+            .map_err(|_e| net_error::DeserializeError("Failed to deserialize transaction payload".into()))?;
+
+        let overrides = body.overrides.iter()
+            .map(HttpRequestType::parse_state_override)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(HttpRequestType::SimulateTransaction(HttpRequestMetadata::from_preamble(preamble), sender, payload, overrides))
+    }
+
+    fn parse_state_override(body: &StateOverrideBody) -> Result<StateOverride, net_error> {
+        let parse_contract = |contract: &str| QualifiedContractIdentifier::parse(contract)
+            .map_err(|_e| net_error::DeserializeError(format!("Failed to parse contract identifier {}", contract)));
+        let parse_principal = |principal: &str| PrincipalData::parse(principal)
+            .map_err(|_e| net_error::DeserializeError(format!("Failed to parse principal {}", principal)));
+        let parse_name = |name: &str| ClarityName::try_from(name.to_string())
+            .map_err(|_e| net_error::DeserializeError(format!("Failed to parse Clarity name {}", name)));
+        let parse_amount = |amount: &str| amount.parse::<u128>()
+            .map_err(|_e| net_error::DeserializeError(format!("Failed to parse amount {}", amount)));
+        let parse_value = |value_hex: &str| Value::try_deserialize_hex_untyped(value_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to deserialize Clarity value".into()));
+
+        let state_override = match body {
+            StateOverrideBody::StxBalance { principal, amount } =>
+                StateOverride::StxBalance(parse_principal(principal)?, parse_amount(amount)?),
+            StateOverrideBody::FtBalance { contract, token, principal, amount } =>
+                StateOverride::FungibleTokenBalance(parse_contract(contract)?, parse_name(token)?, parse_principal(principal)?, parse_amount(amount)?),
+            StateOverrideBody::MapEntry { contract, map, key, value } => {
+                let value = match value {
+                    Some(value) => Some(parse_value(value)?),
+                    None => None
+                };
+                StateOverride::MapEntry(parse_contract(contract)?, parse_name(map)?, parse_value(key)?, value)
+            }
+        };
+        Ok(state_override)
+    }
+
+    fn state_override_body(state_override: &StateOverride) -> StateOverrideBody {
+        match state_override {
+            StateOverride::StxBalance(principal, amount) =>
+                StateOverrideBody::StxBalance { principal: principal.to_string(), amount: amount.to_string() },
+            StateOverride::FungibleTokenBalance(contract_id, token_name, principal, amount) =>
+                StateOverrideBody::FtBalance { contract: contract_id.to_string(), token: token_name.to_string(), principal: principal.to_string(), amount: amount.to_string() },
+            StateOverride::MapEntry(contract_id, map_name, key, value) =>
+                StateOverrideBody::MapEntry {
+                    contract: contract_id.to_string(),
+                    map: map_name.to_string(),
+                    key: format!("0x{}", key.serialize()),
+                    value: value.as_ref().map(|value| format!("0x{}", value.serialize()))
+                }
+        }
+    }
+
     /// check whether the given option query string
     ///   sets proof=0 (setting proof to false).
     /// Defaults to _true_
@@ -1240,6 +1698,19 @@ impl HttpRequestType {
         !no_proof
     }
 
+    /// Read the query string of a read-only call to see whether the caller asked for a cost
+    /// profile with profile=1.  Defaults to _false_
+    fn get_profile_query(query: Option<&str>) -> bool {
+        if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "profile")
+                .map(|(_k, value)| value == "1")
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
     fn parse_get_account<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetAccount".to_string()));
@@ -1249,8 +1720,28 @@ impl HttpRequestType {
             .map_err(|_e| net_error::DeserializeError("Failed to parse account principal".into()))?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
+        Ok(HttpRequestType::GetAccount(HttpRequestMetadata::from_preamble(preamble), principal, with_proof, tip_request))
+    }
+
+    fn parse_get_transfers_by_memo<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetTransfersByMemo".to_string()));
+        }
 
-        Ok(HttpRequestType::GetAccount(HttpRequestMetadata::from_preamble(preamble), principal, with_proof))
+        let principal = PrincipalData::parse(&captures["principal"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse account principal".into()))?;
+
+        // memos shorter than 34 bytes are zero-padded, just like they are in the transaction
+        let memo_bytes = hex_bytes(&captures["memo"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse transfer memo".into()))?;
+        let mut memo = TokenTransferMemo([0u8; 34]);
+        memo.0[0..memo_bytes.len()].copy_from_slice(&memo_bytes);
+
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetTransfersByMemo(HttpRequestMetadata::from_preamble(preamble), principal, memo, limit))
     }
 
     fn parse_get_map_entry<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
@@ -1271,18 +1762,95 @@ impl HttpRequestType {
         let map_name = ClarityName::try_from(captures["map"].to_string())
             .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
 
-        let value_hex: String = serde_json::from_reader(fd)
+        let key: serde_json::Value = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let value = HttpRequestType::parse_clarity_value(&key)
+            .ok_or_else(|| net_error::DeserializeError("Failed to deserialize key value".into()))?;
+
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
+        Ok(HttpRequestType::GetMapEntry(HttpRequestMetadata::from_preamble(preamble), contract_addr, contract_name, map_name, value, with_proof, tip_request))
+    }
+
+    fn parse_get_data_var<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetDataVar".to_string()));
+        }
+
+        let contract_addr =  StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse contract address".into()))?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let var_name = ClarityName::try_from(captures["var"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse variable name".into()))?;
+
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
+        Ok(HttpRequestType::GetDataVar(HttpRequestMetadata::from_preamble(preamble), contract_addr, contract_name, var_name, with_proof, tip_request))
+    }
+
+    fn parse_get_ft_balance<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetFungibleTokenBalance".to_string()));
+        }
+
+        let contract_addr =  StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse contract address".into()))?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let token_name = ClarityName::try_from(captures["token"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse token name".into()))?;
+        let principal = PrincipalData::parse(&captures["principal"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse account principal".into()))?;
+
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
+        Ok(HttpRequestType::GetFungibleTokenBalance(HttpRequestMetadata::from_preamble(preamble), contract_addr, contract_name, token_name, principal, with_proof, tip_request))
+    }
+
+    fn parse_get_nft_owner<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < (BOUND_VALUE_SERIALIZATION_HEX)) {
+            return Err(net_error::DeserializeError("Invalid Http request: invalid body length for GetNonFungibleTokenOwner".to_string()));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".into()));
+        }
+
+        let contract_addr =  StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse contract address".into()))?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let asset_name = ClarityName::try_from(captures["asset"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse asset name".into()))?;
+
+        let asset: serde_json::Value = serde_json::from_reader(fd)
             .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
 
-        let value = Value::try_deserialize_hex_untyped(&value_hex)
-            .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
+        let value = HttpRequestType::parse_clarity_value(&asset)
+            .ok_or_else(|| net_error::DeserializeError("Failed to deserialize asset value".into()))?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
+        Ok(HttpRequestType::GetNonFungibleTokenOwner(HttpRequestMetadata::from_preamble(preamble), contract_addr, contract_name, asset_name, value, with_proof, tip_request))
+    }
 
-        Ok(HttpRequestType::GetMapEntry(HttpRequestMetadata::from_preamble(preamble), contract_addr, contract_name, map_name, value, with_proof))
+    /// Parse a Clarity value given in a JSON body: either a string with its hex encoding, or its
+    /// JSON encoding
+    fn parse_clarity_value(json: &serde_json::Value) -> Option<Value> {
+        match json {
+            serde_json::Value::String(hex) => Value::try_deserialize_hex_untyped(hex).ok(),
+            _ => Value::json_deserialize(json).ok()
+        }
     }
 
-    fn parse_call_read_only<R: Read>(protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+    fn parse_call_read_only<R: Read>(protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
         let content_len = preamble.get_content_length();
         if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
             return Err(net_error::DeserializeError("Invalid Http request: invalid body length for GetMapEntry".to_string()));
@@ -1302,17 +1870,54 @@ impl HttpRequestType {
         let body: CallReadOnlyRequestBody = serde_json::from_reader(fd)
             .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
 
-        let sender = PrincipalData::parse(&body.sender)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse sender principal".into()))?;
+        let sender = match body.sender {
+            Some(sender) => PrincipalData::parse(&sender)
+                .map_err(|_e| net_error::DeserializeError("Failed to parse sender principal".into()))?,
+            None => PrincipalData::Contract(QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone()))
+        };
 
-        let arguments = body.arguments.into_iter()
-            .map(|hex| Value::try_deserialize_hex_untyped(&hex).ok())
+        let arguments = body.arguments.iter()
+            .map(HttpRequestType::parse_clarity_value)
             .collect::<Option<Vec<Value>>>()
             .ok_or_else(|| net_error::DeserializeError("Failed to deserialize argument value".into()))?;
 
+        let tip_request = HttpRequestType::get_tip_request_query(query)?;
+
         Ok(HttpRequestType::CallReadOnlyFunction(
             HttpRequestMetadata::from_preamble(preamble),
-            contract_addr, contract_name, sender, func_name, arguments))
+            contract_addr, contract_name, sender, func_name, arguments, HttpRequestType::get_profile_query(query), tip_request))
+    }
+
+    fn parse_analyze_contract<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let (deployer, contract_name, source) = HttpRequestType::parse_contract_deploy_body(preamble, fd, "AnalyzeContract")?;
+        Ok(HttpRequestType::AnalyzeContract(HttpRequestMetadata::from_preamble(preamble), deployer, contract_name, source))
+    }
+
+    fn parse_dry_run_contract<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let (deployer, contract_name, source) = HttpRequestType::parse_contract_deploy_body(preamble, fd, "DryRunContract")?;
+        Ok(HttpRequestType::DryRunContract(HttpRequestMetadata::from_preamble(preamble), deployer, contract_name, source))
+    }
+
+    /// Parse the deployer, name and source of a contract from a JSON body
+    fn parse_contract_deploy_body<R: Read>(preamble: &HttpRequestPreamble, fd: &mut R, request_class: &str) -> Result<(StacksAddress, ContractName, String), net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError(format!("Invalid Http request: invalid body length for {}", request_class)));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError("Invalid content-type: expected application/json".to_string()));
+        }
+
+        let body: AnalyzeContractRequestBody = serde_json::from_reader(fd.take(content_len as u64))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let deployer = StacksAddress::from_string(&body.deployer)
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse deployer address".into()))?;
+        let contract_name = ContractName::try_from(body.name)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+
+        Ok((deployer, contract_name, body.source))
     }
 
     fn parse_get_contract_arguments(preamble: &HttpRequestPreamble, captures: &Captures) -> Result<(HttpRequestMetadata, StacksAddress, ContractName), net_error> {
@@ -1339,50 +1944,276 @@ impl HttpRequestType {
             .map(|(preamble, addr, name)| HttpRequestType::GetContractSrc(preamble, addr, name, with_proof))
     }
 
-    fn parse_getblock<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+    fn parse_get_trait_implementors<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let (md, contract_addr, contract_name) = HttpRequestType::parse_get_contract_arguments(preamble, captures)?;
+        let trait_name = ClarityName::try_from(captures["trait"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse trait name".into()))?;
+        Ok(HttpRequestType::GetTraitImplementors(md, contract_addr, contract_name, trait_name))
+    }
+
+    fn parse_get_contract_dependencies<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        HttpRequestType::parse_get_contract_arguments(preamble, captures)
+            .map(|(preamble, addr, name)| HttpRequestType::GetContractDependencies(preamble, addr, name))
+    }
+
+    fn parse_get_transaction_receipt<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
-            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlock".to_string()));
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetTransactionReceipt".to_string()));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError("Failed to match path to block hash group".to_string()))?
-            .as_str();
+        let txid = Txid::from_hex(&captures["txid"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse txid".to_string()))?;
 
-        let block_hash = StacksBlockId::from_hex(block_hash_str)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
-
-        Ok(HttpRequestType::GetBlock(HttpRequestMetadata::from_preamble(preamble), block_hash))
+        Ok(HttpRequestType::GetTransactionReceipt(HttpRequestMetadata::from_preamble(preamble), txid))
     }
 
-    fn parse_getmicroblocks_indexed<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+    fn parse_getblock_receipts<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
-            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicroblocksIndexed".to_string()));
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlockReceipts".to_string()));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError("Failed to match path to microblock hash group".to_string()))?
-            .as_str();
+        let block_hash = StacksBlockId::from_hex(&captures["index_block_hash"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
 
-        let block_hash = StacksBlockId::from_hex(block_hash_str)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse microblock hash".to_string()))?;
+        Ok(HttpRequestType::GetBlockReceipts(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    }
 
-        Ok(HttpRequestType::GetMicroblocksIndexed(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    fn parse_get_contract_receipts<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        let tip = HttpRequestType::get_tip_query(query)?;
+        let limit = HttpRequestType::get_limit_query(query)?;
+        HttpRequestType::parse_get_contract_arguments(preamble, captures)
+            .map(|(preamble, addr, name)| HttpRequestType::GetContractReceipts(preamble, addr, name, tip, limit))
     }
-    
-    fn parse_getmicroblocks_confirmed<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+
+    fn parse_search_hash_prefix<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
-            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicrolocks".to_string()));
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for SearchHashPrefix".to_string()));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError("Failed to match path to microblock hash group".to_string()))?
-            .as_str();
+        // either a hex prefix or a principal
+        let prefix = captures["prefix"].to_string();
+        if !prefix.chars().all(|c| c.is_ascii_digit() || (c >= 'a' && c <= 'f')) {
+            PrincipalData::parse(&prefix)
+                .map_err(|_e| net_error::DeserializeError("Failed to parse principal".to_string()))?;
+        }
+        let limit = HttpRequestType::get_limit_query(query)?;
 
-        let block_hash = StacksBlockId::from_hex(block_hash_str)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse microblock hash".to_string()))?;
+        Ok(HttpRequestType::SearchHashPrefix(HttpRequestMetadata::from_preamble(preamble), prefix, limit))
+    }
+
+    fn parse_get_stx_supply<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetStxSupply".to_string()));
+        }
+
+        let tip = HttpRequestType::get_tip_query(query)?;
+
+        Ok(HttpRequestType::GetStxSupply(HttpRequestMetadata::from_preamble(preamble), tip))
+    }
+
+    fn parse_get_balance_history<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBalanceHistory".to_string()));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse account principal".into()))?;
+        let tip = HttpRequestType::get_tip_query(query)?;
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetBalanceHistory(HttpRequestMetadata::from_preamble(preamble), principal, tip, limit))
+    }
+
+    fn parse_get_top_balances<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetTopBalances".to_string()));
+        }
+
+        let tip = HttpRequestType::get_tip_query(query)?;
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetTopBalances(HttpRequestMetadata::from_preamble(preamble), tip, limit))
+    }
+
+    fn parse_get_address_transactions<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetAddressTransactions".to_string()));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse principal".into()))?;
+        let tip = HttpRequestType::get_tip_query(query)?;
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetAddressTransactions(HttpRequestMetadata::from_preamble(preamble), principal, tip, limit))
+    }
+
+    fn parse_getblock<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlock".to_string()));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to block hash group".to_string()))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlock(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    }
+
+    /// check whether the given option query string sets tip=<index block hash>, to resolve a
+    /// request against a specific Stacks fork instead of the canonical one.
+    fn get_tip_query(query: Option<&str>) -> Result<Option<StacksBlockId>, net_error> {
+        let tip_hex = match query {
+            Some(query_string) => form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "tip")
+                .map(|(_k, value)| value.to_string()),
+            None => None
+        };
+
+        match tip_hex {
+            Some(tip_hex) => StacksBlockId::from_hex(&tip_hex)
+                .map(|tip| Some(tip))
+                .map_err(|_e| net_error::DeserializeError("Failed to parse tip index block hash".to_string())),
+            None => Ok(None)
+        }
+    }
+
+    /// Read which block's state a read-only query should be evaluated against from its tip=<index
+    /// block hash> and height=<block height> query parameters.
+    fn get_tip_request_query(query: Option<&str>) -> Result<TipRequest, net_error> {
+        let tip = HttpRequestType::get_tip_query(query)?;
+        let height_str = match query {
+            Some(query_string) => form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "height")
+                .map(|(_k, value)| value.to_string()),
+            None => None
+        };
+
+        let height = match height_str {
+            Some(height_str) => Some(height_str.parse::<u64>()
+                .map_err(|_e| net_error::DeserializeError("Failed to parse block height".to_string()))?),
+            None => None
+        };
+
+        Ok(TipRequest { tip, height })
+    }
+
+    fn parse_getblock_by_height<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlockByHeight".to_string()));
+        }
+
+        let height_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to block height group".to_string()))?
+            .as_str();
+
+        let height = height_str.parse::<u64>()
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block height".to_string()))?;
+
+        let tip = HttpRequestType::get_tip_query(query)?;
+
+        Ok(HttpRequestType::GetBlockByHeight(HttpRequestMetadata::from_preamble(preamble), height, tip))
+    }
+
+    fn parse_getblock_by_hash<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlockByHash".to_string()));
+        }
+
+        let block_hash = BlockHeaderHash::from_hex(&captures["block_hash"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlockByHash(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    }
+
+    fn parse_getmicroblock_by_hash<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicroblockByHash".to_string()));
+        }
+
+        let microblock_hash = BlockHeaderHash::from_hex(&captures["microblock_hash"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse microblock hash".to_string()))?;
+
+        Ok(HttpRequestType::GetMicroblockByHash(HttpRequestMetadata::from_preamble(preamble), microblock_hash))
+    }
+
+    fn parse_export_blocks<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for ExportBlocks".to_string()));
+        }
+
+        let mut start_height = None;
+        let mut end_height = None;
+        let mut format = BlockExportFormat::Binary;
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                match &*key {
+                    "from" => {
+                        start_height = Some(value.parse::<u64>()
+                            .map_err(|_e| net_error::DeserializeError("Failed to parse 'from' block height".to_string()))?);
+                    },
+                    "to" => {
+                        end_height = Some(value.parse::<u64>()
+                            .map_err(|_e| net_error::DeserializeError("Failed to parse 'to' block height".to_string()))?);
+                    },
+                    "format" => {
+                        format = BlockExportFormat::from_str(&value)
+                            .ok_or(net_error::DeserializeError("Invalid export format: expected 'binary' or 'ndjson'".to_string()))?;
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        let start_height = start_height.ok_or(net_error::DeserializeError("Missing 'from' block height".to_string()))?;
+        let end_height = end_height.ok_or(net_error::DeserializeError("Missing 'to' block height".to_string()))?;
+
+        // the boot block at height 0 has no block data
+        if start_height == 0 || end_height < start_height || end_height >= u32::max_value() as u64 {
+            return Err(net_error::DeserializeError("Invalid block range".to_string()));
+        }
+        if end_height - start_height + 1 > RPC_EXPORT_BLOCKS_MAX {
+            return Err(net_error::DeserializeError(format!("Invalid block range: at most {} blocks can be exported at once", RPC_EXPORT_BLOCKS_MAX)));
+        }
+
+        let tip = HttpRequestType::get_tip_query(query)?;
+
+        Ok(HttpRequestType::ExportBlocks(HttpRequestMetadata::from_preamble(preamble), start_height, end_height, format, tip))
+    }
+
+    fn parse_getmicroblocks_indexed<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicroblocksIndexed".to_string()));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to microblock hash group".to_string()))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse microblock hash".to_string()))?;
+
+        Ok(HttpRequestType::GetMicroblocksIndexed(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    }
+    
+    fn parse_getmicroblocks_confirmed<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicrolocks".to_string()));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to microblock hash group".to_string()))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse microblock hash".to_string()))?;
 
         Ok(HttpRequestType::GetMicroblocksConfirmed(HttpRequestMetadata::from_preamble(preamble), block_hash))
     }
@@ -1410,7 +2241,157 @@ impl HttpRequestType {
         Ok(HttpRequestType::GetMicroblocksUnconfirmed(HttpRequestMetadata::from_preamble(preamble), block_hash, min_seq))
     }
 
-    fn parse_posttransaction<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
+    fn parse_getmicroblocks_unconfirmed_tip<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMicroblocksUnconfirmedTip".to_string()));
+        }
+
+        let min_seq_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to microblock minimum sequence group".to_string()))?
+            .as_str();
+
+        let min_seq = min_seq_str.parse::<u16>().map_err(|_e| net_error::DeserializeError("Failed to parse microblock minimum sequence".to_string()))?;
+
+        Ok(HttpRequestType::GetMicroblocksUnconfirmedTip(HttpRequestMetadata::from_preamble(preamble), min_seq))
+    }
+
+    /// Get the `limit` query argument for a paginated request.  Defaults to
+    /// RPC_PAGE_LIMIT_DEFAULT, and must be between 1 and RPC_PAGE_LIMIT_MAX.
+    fn get_limit_query(query: Option<&str>) -> Result<u64, net_error> {
+        let limit_str = match query {
+            Some(query_string) => form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "limit")
+                .map(|(_k, value)| value.to_string()),
+            None => None
+        };
+
+        match limit_str {
+            Some(limit_str) => {
+                let limit = limit_str.parse::<u64>()
+                    .map_err(|_e| net_error::DeserializeError("Failed to parse page limit".to_string()))?;
+                if limit == 0 || limit > RPC_PAGE_LIMIT_MAX {
+                    return Err(net_error::DeserializeError(format!("Invalid page limit: must be between 1 and {}", RPC_PAGE_LIMIT_MAX)));
+                }
+                Ok(limit)
+            },
+            None => Ok(RPC_PAGE_LIMIT_DEFAULT)
+        }
+    }
+
+    fn parse_getblock_transactions<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlockTransactions".to_string()));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to block hash group".to_string()))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        let offset = match query {
+            Some(query_string) => match form_urlencoded::parse(query_string.as_bytes()).find(|(key, _v)| key == "offset") {
+                Some((_k, value)) => value.parse::<u64>()
+                    .map_err(|_e| net_error::DeserializeError("Failed to parse page offset".to_string()))?,
+                None => 0
+            },
+            None => 0
+        };
+
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetBlockTransactions(HttpRequestMetadata::from_preamble(preamble), block_hash, offset, limit))
+    }
+
+    fn parse_getblock_contract_costs<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetBlockContractCosts".to_string()));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError("Failed to match path to block hash group".to_string()))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlockContractCosts(HttpRequestMetadata::from_preamble(preamble), block_hash))
+    }
+
+    fn parse_getmempool_txids<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMempoolTxids".to_string()));
+        }
+
+        let cursor = match query {
+            Some(query_string) => match form_urlencoded::parse(query_string.as_bytes()).find(|(key, _v)| key == "cursor") {
+                Some((_k, value)) => Some(Txid::from_hex(&value)
+                    .map_err(|_e| net_error::DeserializeError("Failed to parse page cursor".to_string()))?),
+                None => None
+            },
+            None => None
+        };
+
+        let limit = HttpRequestType::get_limit_query(query)?;
+
+        Ok(HttpRequestType::GetMempoolTxids(HttpRequestMetadata::from_preamble(preamble), cursor, limit))
+    }
+
+    fn parse_getmempool_stats<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _captures: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMempoolStats".to_string()));
+        }
+
+        Ok(HttpRequestType::GetMempoolStats(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    fn parse_getmetrics<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError("Invalid Http request: expected 0-length body for GetMetrics".to_string()));
+        }
+        Ok(HttpRequestType::GetMetrics(HttpRequestMetadata::from_preamble(preamble)))
+    }
+
+    /// Get the `wait` and `timeout` query arguments for a transaction submission.  Returns
+    /// Some(timeout) if the client wants to wait for the transaction to be anchored.  The timeout
+    /// defaults to RPC_TX_WAIT_TIMEOUT_DEFAULT, and must be between 1 and RPC_TX_WAIT_TIMEOUT_MAX.
+    fn get_tx_wait_query(query: Option<&str>) -> Result<Option<u64>, net_error> {
+        let (wait, timeout_str) = match query {
+            Some(query_string) => {
+                let wait = form_urlencoded::parse(query_string.as_bytes())
+                    .find(|(key, _v)| key == "wait")
+                    .map(|(_k, value)| value == "1" || value == "true")
+                    .unwrap_or(false);
+                let timeout_str = form_urlencoded::parse(query_string.as_bytes())
+                    .find(|(key, _v)| key == "timeout")
+                    .map(|(_k, value)| value.to_string());
+                (wait, timeout_str)
+            },
+            None => (false, None)
+        };
+
+        if !wait {
+            return Ok(None);
+        }
+
+        match timeout_str {
+            Some(timeout_str) => {
+                let timeout = timeout_str.parse::<u64>()
+                    .map_err(|_e| net_error::DeserializeError("Failed to parse wait timeout".to_string()))?;
+                if timeout == 0 || timeout > RPC_TX_WAIT_TIMEOUT_MAX {
+                    return Err(net_error::DeserializeError(format!("Invalid wait timeout: must be between 1 and {}", RPC_TX_WAIT_TIMEOUT_MAX)));
+                }
+                Ok(Some(timeout))
+            },
+            None => Ok(Some(RPC_TX_WAIT_TIMEOUT_DEFAULT))
+        }
+    }
+
+    fn parse_posttransaction<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, query: Option<&str>, fd: &mut R) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() == 0 {
             return Err(net_error::DeserializeError("Invalid Http request: expected non-zero-length body for PostTransaction".to_string()));
         }
@@ -1427,8 +2408,9 @@ impl HttpRequestType {
             }
         };
 
+        let wait_timeout = HttpRequestType::get_tx_wait_query(query)?;
         let tx = StacksTransaction::consensus_deserialize(fd)?;
-        Ok(HttpRequestType::PostTransaction(HttpRequestMetadata::from_preamble(preamble), tx))
+        Ok(HttpRequestType::PostTransaction(HttpRequestMetadata::from_preamble(preamble), tx, wait_timeout))
     }
 
     fn parse_options_preflight<R: Read>(_protocol: &mut StacksHttp, preamble: &HttpRequestPreamble, _regex: &Captures, _query: Option<&str>, _fd: &mut R) -> Result<HttpRequestType, net_error> {
@@ -1439,17 +2421,52 @@ impl HttpRequestType {
         match *self {
             HttpRequestType::GetInfo(ref md) => md,
             HttpRequestType::GetNeighbors(ref md) => md,
+            HttpRequestType::GetStatus(ref md, _) => md,
+            HttpRequestType::GetNeighborStats(ref md) => md,
+            HttpRequestType::GetConsensusConstants(ref md) => md,
+            HttpRequestType::GetOpenAPI(ref md) => md,
+            HttpRequestType::GetMinedBlocks(ref md) => md,
             HttpRequestType::GetBlock(ref md, _) => md,
+            HttpRequestType::GetBlockByHeight(ref md, ..) => md,
+            HttpRequestType::GetBlockByHash(ref md, _) => md,
+            HttpRequestType::ExportBlocks(ref md, ..) => md,
             HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
-            HttpRequestType::PostTransaction(ref md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmedTip(ref md, _) => md,
+            HttpRequestType::GetMicroblockByHash(ref md, _) => md,
+            HttpRequestType::GetBlockTransactions(ref md, ..) => md,
+            HttpRequestType::GetBlockContractCosts(ref md, ..) => md,
+            HttpRequestType::GetMempoolTxids(ref md, ..) => md,
+            HttpRequestType::GetMempoolStats(ref md) => md,
+            HttpRequestType::GetTransfersByMemo(ref md, ..) => md,
+            HttpRequestType::GetTransactionReceipt(ref md, ..) => md,
+            HttpRequestType::GetBlockReceipts(ref md, ..) => md,
+            HttpRequestType::GetContractReceipts(ref md, ..) => md,
+            HttpRequestType::SearchHashPrefix(ref md, ..) => md,
+            HttpRequestType::GetStxSupply(ref md, ..) => md,
+            HttpRequestType::GetBalanceHistory(ref md, ..) => md,
+            HttpRequestType::GetTopBalances(ref md, ..) => md,
+            HttpRequestType::GetAddressTransactions(ref md, ..) => md,
+            HttpRequestType::GetMetrics(ref md) => md,
+            HttpRequestType::PostTransaction(ref md, _, _) => md,
             HttpRequestType::GetAccount(ref md, ..) => md,
             HttpRequestType::GetMapEntry(ref md, ..) => md,
+            HttpRequestType::GetDataVar(ref md, ..) => md,
+            HttpRequestType::GetFungibleTokenBalance(ref md, ..) => md,
+            HttpRequestType::GetNonFungibleTokenOwner(ref md, ..) => md,
             HttpRequestType::GetTransferCost(ref md) => md,
+            HttpRequestType::EstimateFee(ref md, ..) => md,
+            HttpRequestType::SimulateTransaction(ref md, ..) => md,
             HttpRequestType::GetContractABI(ref md, ..) => md,
             HttpRequestType::GetContractSrc(ref md, ..) => md,
+            HttpRequestType::GetTraitImplementors(ref md, ..) => md,
+            HttpRequestType::GetContractDependencies(ref md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
+            HttpRequestType::AnalyzeContract(ref md, ..) => md,
+            HttpRequestType::DryRunContract(ref md, ..) => md,
+            HttpRequestType::BanPeers(ref md, ..) => md,
+            HttpRequestType::PostBatch(ref md, ..) => md,
             HttpRequestType::OptionsPreflight(ref md, ..) => md,
             HttpRequestType::Unmatched(ref md, ..) => md,
         }
@@ -1459,17 +2476,52 @@ impl HttpRequestType {
         match *self {
             HttpRequestType::GetInfo(ref mut md) => md,
             HttpRequestType::GetNeighbors(ref mut md) => md,
+            HttpRequestType::GetStatus(ref mut md, _) => md,
+            HttpRequestType::GetNeighborStats(ref mut md) => md,
+            HttpRequestType::GetConsensusConstants(ref mut md) => md,
+            HttpRequestType::GetOpenAPI(ref mut md) => md,
+            HttpRequestType::GetMinedBlocks(ref mut md) => md,
             HttpRequestType::GetBlock(ref mut md, _) => md,
+            HttpRequestType::GetBlockByHeight(ref mut md, ..) => md,
+            HttpRequestType::GetBlockByHash(ref mut md, _) => md,
+            HttpRequestType::ExportBlocks(ref mut md, ..) => md,
             HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
-            HttpRequestType::PostTransaction(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmedTip(ref mut md, _) => md,
+            HttpRequestType::GetMicroblockByHash(ref mut md, _) => md,
+            HttpRequestType::GetBlockTransactions(ref mut md, ..) => md,
+            HttpRequestType::GetBlockContractCosts(ref mut md, ..) => md,
+            HttpRequestType::GetMempoolTxids(ref mut md, ..) => md,
+            HttpRequestType::GetMempoolStats(ref mut md) => md,
+            HttpRequestType::GetTransfersByMemo(ref mut md, ..) => md,
+            HttpRequestType::GetTransactionReceipt(ref mut md, ..) => md,
+            HttpRequestType::GetBlockReceipts(ref mut md, ..) => md,
+            HttpRequestType::GetContractReceipts(ref mut md, ..) => md,
+            HttpRequestType::SearchHashPrefix(ref mut md, ..) => md,
+            HttpRequestType::GetStxSupply(ref mut md, ..) => md,
+            HttpRequestType::GetBalanceHistory(ref mut md, ..) => md,
+            HttpRequestType::GetTopBalances(ref mut md, ..) => md,
+            HttpRequestType::GetAddressTransactions(ref mut md, ..) => md,
+            HttpRequestType::GetMetrics(ref mut md) => md,
+            HttpRequestType::PostTransaction(ref mut md, _, _) => md,
             HttpRequestType::GetAccount(ref mut md, ..) => md,
             HttpRequestType::GetMapEntry(ref mut md, ..) => md,
+            HttpRequestType::GetDataVar(ref mut md, ..) => md,
+            HttpRequestType::GetFungibleTokenBalance(ref mut md, ..) => md,
+            HttpRequestType::GetNonFungibleTokenOwner(ref mut md, ..) => md,
             HttpRequestType::GetTransferCost(ref mut md) => md,
+            HttpRequestType::EstimateFee(ref mut md, ..) => md,
+            HttpRequestType::SimulateTransaction(ref mut md, ..) => md,
             HttpRequestType::GetContractABI(ref mut md, ..) => md,
             HttpRequestType::GetContractSrc(ref mut md, ..) => md,
+            HttpRequestType::GetTraitImplementors(ref mut md, ..) => md,
+            HttpRequestType::GetContractDependencies(ref mut md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
+            HttpRequestType::AnalyzeContract(ref mut md, ..) => md,
+            HttpRequestType::DryRunContract(ref mut md, ..) => md,
+            HttpRequestType::BanPeers(ref mut md, ..) => md,
+            HttpRequestType::PostBatch(ref mut md, ..) => md,
             HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
             HttpRequestType::Unmatched(ref mut md, ..) => md,
         }
@@ -1479,81 +2531,425 @@ impl HttpRequestType {
         match self {
             HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
             HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
+            HttpRequestType::GetStatus(_md, require_ready) => format!("/v2/status{}", if *require_ready { "?require_ready=1" } else { "" }),
+            HttpRequestType::GetNeighborStats(_md) => "/v2/neighbors/stats".to_string(),
+            HttpRequestType::GetConsensusConstants(_md) => "/v2/consensus_constants".to_string(),
+            HttpRequestType::GetOpenAPI(_md) => "/v2/openapi.json".to_string(),
+            HttpRequestType::GetMinedBlocks(_md) => "/v2/mined_blocks".to_string(),
             HttpRequestType::GetBlock(_md, block_hash) => format!("/v2/blocks/{}", block_hash.to_hex()),
+            HttpRequestType::GetBlockByHeight(_md, height, tip_opt) => match tip_opt {
+                Some(tip) => format!("/v2/blocks/by-height/{}?tip={}", height, tip.to_hex()),
+                None => format!("/v2/blocks/by-height/{}", height)
+            },
+            HttpRequestType::GetBlockByHash(_md, block_hash) => format!("/v2/blocks/by-hash/{}", block_hash.to_hex()),
+            HttpRequestType::ExportBlocks(_md, start_height, end_height, format, tip_opt) => match tip_opt {
+                Some(tip) => format!("/v2/export/blocks?from={}&to={}&format={}&tip={}", start_height, end_height, format.as_str(), tip.to_hex()),
+                None => format!("/v2/export/blocks?from={}&to={}&format={}", start_height, end_height, format.as_str())
+            },
             HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => format!("/v2/microblocks/{}", block_hash.to_hex()),
             HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => format!("/v2/microblocks/confirmed/{}", block_hash.to_hex()),
             HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!("/v2/microblocks/unconfirmed/{}/{}", block_hash.to_hex(), min_seq),
-            HttpRequestType::PostTransaction(_md, _tx) => "/v2/transactions".to_string(),
-            HttpRequestType::GetAccount(_md, principal, _with_proof) => 
-                format!("/v2/accounts/{}", &principal.to_string()[1..]),
-            HttpRequestType::GetMapEntry(_md, contract_addr, contract_name, map_name, _key, _with_proof) =>
-                format!("/v2/map_entry/{}/{}/{}",
-                        contract_addr, contract_name.as_str(), map_name.as_str()),
+            HttpRequestType::GetMicroblocksUnconfirmedTip(_md, min_seq) => format!("/v2/microblocks/unconfirmed/tip/{}", min_seq),
+            HttpRequestType::GetMicroblockByHash(_md, microblock_hash) => format!("/v2/microblocks/by-hash/{}", microblock_hash.to_hex()),
+            HttpRequestType::GetBlockTransactions(_md, block_hash, offset, limit) => format!("/v2/blocks/{}/transactions?offset={}&limit={}", block_hash.to_hex(), offset, limit),
+            HttpRequestType::GetBlockContractCosts(_md, block_hash) => format!("/v2/blocks/{}/costs", block_hash.to_hex()),
+            HttpRequestType::GetMempoolTxids(_md, cursor_opt, limit) => match cursor_opt {
+                Some(cursor) => format!("/v2/mempool/txids?cursor={}&limit={}", cursor.to_hex(), limit),
+                None => format!("/v2/mempool/txids?limit={}", limit)
+            },
+            HttpRequestType::GetMempoolStats(_md) => "/v2/mempool/stats".to_string(),
+            HttpRequestType::GetMetrics(_md) => "/metrics".to_string(),
+            HttpRequestType::PostTransaction(_md, _tx, wait_timeout) => match wait_timeout {
+                Some(timeout) => format!("/v2/transactions?wait=true&timeout={}", timeout),
+                None => "/v2/transactions".to_string()
+            },
+            HttpRequestType::GetAccount(_md, principal, _with_proof, tip_request) =>
+                with_query_params(format!("/v2/accounts/{}", &principal.to_string()[1..]), tip_request.query_params()),
+            HttpRequestType::GetTransfersByMemo(_md, principal, memo, limit) =>
+                format!("/v2/accounts/{}/transfers/{}?limit={}", principal, to_hex(memo.as_bytes()), limit),
+            HttpRequestType::GetTransactionReceipt(_md, txid) => format!("/v2/transactions/{}/receipt", txid.to_hex()),
+            HttpRequestType::GetBlockReceipts(_md, block_hash) => format!("/v2/blocks/{}/receipts", block_hash.to_hex()),
+            HttpRequestType::GetContractReceipts(_md, contract_addr, contract_name, tip_opt, limit) => match tip_opt {
+                Some(tip) => format!("/v2/contracts/receipts/{}/{}?tip={}&limit={}", contract_addr, contract_name.as_str(), tip.to_hex(), limit),
+                None => format!("/v2/contracts/receipts/{}/{}?limit={}", contract_addr, contract_name.as_str(), limit)
+            },
+            HttpRequestType::SearchHashPrefix(_md, prefix, limit) => format!("/v2/search/{}?limit={}", prefix, limit),
+            HttpRequestType::GetStxSupply(_md, tip_opt) => match tip_opt {
+                Some(tip) => format!("/v2/supply?tip={}", tip.to_hex()),
+                None => "/v2/supply".to_string()
+            },
+            HttpRequestType::GetBalanceHistory(_md, principal, tip_opt, limit) => match tip_opt {
+                Some(tip) => format!("/v2/accounts/{}/balances?tip={}&limit={}", principal, tip.to_hex(), limit),
+                None => format!("/v2/accounts/{}/balances?limit={}", principal, limit)
+            },
+            HttpRequestType::GetTopBalances(_md, tip_opt, limit) => match tip_opt {
+                Some(tip) => format!("/v2/balances/top?tip={}&limit={}", tip.to_hex(), limit),
+                None => format!("/v2/balances/top?limit={}", limit)
+            },
+            HttpRequestType::GetAddressTransactions(_md, principal, tip_opt, limit) => match tip_opt {
+                Some(tip) => format!("/v2/addresses/{}/transactions?tip={}&limit={}", principal, tip.to_hex(), limit),
+                None => format!("/v2/addresses/{}/transactions?limit={}", principal, limit)
+            },
+            HttpRequestType::GetMapEntry(_md, contract_addr, contract_name, map_name, _key, _with_proof, tip_request) =>
+                with_query_params(format!("/v2/map_entry/{}/{}/{}", contract_addr, contract_name.as_str(), map_name.as_str()),
+                                  tip_request.query_params()),
+            HttpRequestType::GetDataVar(_md, contract_addr, contract_name, var_name, _with_proof, tip_request) =>
+                with_query_params(format!("/v2/data_var/{}/{}/{}", contract_addr, contract_name.as_str(), var_name.as_str()),
+                                  tip_request.query_params()),
+            HttpRequestType::GetFungibleTokenBalance(_md, contract_addr, contract_name, token_name, principal, _with_proof, tip_request) =>
+                with_query_params(format!("/v2/ft_balance/{}/{}/{}/{}", contract_addr, contract_name.as_str(), token_name.as_str(), principal),
+                                  tip_request.query_params()),
+            HttpRequestType::GetNonFungibleTokenOwner(_md, contract_addr, contract_name, asset_name, _asset, _with_proof, tip_request) =>
+                with_query_params(format!("/v2/nft_owner/{}/{}/{}", contract_addr, contract_name.as_str(), asset_name.as_str()),
+                                  tip_request.query_params()),
             HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
+            HttpRequestType::EstimateFee(..) => "/v2/fees/estimate".to_string(),
+            HttpRequestType::SimulateTransaction(..) => "/v2/transactions/simulate".to_string(),
             HttpRequestType::GetContractABI(_, contract_addr, contract_name) =>
                 format!("/v2/contracts/interface/{}/{}", contract_addr, contract_name.as_str()),
             HttpRequestType::GetContractSrc(_, contract_addr, contract_name, _with_proof) => 
                 format!("/v2/contracts/source/{}/{}", contract_addr, contract_name.as_str()),
-            HttpRequestType::CallReadOnlyFunction(_, contract_addr, contract_name, _, func_name, ..) => {
-                format!("/v2/contracts/call-read/{}/{}/{}", contract_addr, contract_name.as_str(), func_name.as_str())
+            HttpRequestType::GetTraitImplementors(_, contract_addr, contract_name, trait_name) =>
+                format!("/v2/traits/{}/{}/{}/implementors", contract_addr, contract_name.as_str(), trait_name.as_str()),
+            HttpRequestType::GetContractDependencies(_, contract_addr, contract_name) =>
+                format!("/v2/contracts/dependencies/{}/{}", contract_addr, contract_name.as_str()),
+            HttpRequestType::CallReadOnlyFunction(_, contract_addr, contract_name, _, func_name, _, with_profile, tip_request) => {
+                let mut params = tip_request.query_params();
+                if *with_profile {
+                    params.insert(0, "profile=1".to_string());
+                }
+                with_query_params(format!("/v2/contracts/call-read/{}/{}/{}", contract_addr, contract_name.as_str(), func_name.as_str()), params)
             },
+            HttpRequestType::AnalyzeContract(..) => "/v2/contracts/analyze".to_string(),
+            HttpRequestType::DryRunContract(..) => "/v2/contracts/dry-run".to_string(),
+            HttpRequestType::BanPeers(_md, ..) => "/v2/neighbors/ban".to_string(),
+            HttpRequestType::PostBatch(_md, ..) => "/v2/batch".to_string(),
             HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
             HttpRequestType::Unmatched(_md, path) => path.to_string(),
         }
     }
 
-    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
+    /// The parameters of this request that aren't in its path, for logging.  Long values are
+    /// truncated, and transactions are reduced to their txids.
+    pub fn log_params(&self) -> String {
         match self {
-            HttpRequestType::PostTransaction(md, tx) => {
-                let mut tx_bytes = vec![];
-                write_next(&mut tx_bytes, tx)?;
-
-                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(tx_bytes.len() as u32), Some(&HttpContentType::Bytes), empty_headers)?;
-                fd.write_all(&tx_bytes).map_err(net_error::WriteError)?;
+            HttpRequestType::PostTransaction(_md, tx, _wait_timeout) => format!("txid={}", tx.txid().to_hex()),
+            HttpRequestType::GetMapEntry(_md, _contract_addr, _contract_name, _map_name, key, _with_proof, _tip_request) =>
+                format!("key={}", truncate_log_param(&key.to_string())),
+            HttpRequestType::GetNonFungibleTokenOwner(_md, _contract_addr, _contract_name, _asset_name, asset, _with_proof, _tip_request) =>
+                format!("asset={}", truncate_log_param(&asset.to_string())),
+            HttpRequestType::CallReadOnlyFunction(_md, _contract_addr, _contract_name, sender, _func_name, args, _with_profile, _tip_request) => {
+                let args_str : Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                format!("sender={} args=[{}]", sender, truncate_log_param(&args_str.join(", ")))
             },
-            other_type => {
-                let md = other_type.metadata();
-                let request_path = other_type.request_path();
-                HttpRequestPreamble::new_serialized(fd, &md.version, "GET", &request_path, &md.peer, md.keep_alive, None, None, empty_headers)?;
-            }
+            HttpRequestType::AnalyzeContract(_md, deployer, contract_name, source) |
+            HttpRequestType::DryRunContract(_md, deployer, contract_name, source) =>
+                format!("contract={}.{} source_len={}", deployer, contract_name.as_str(), source.len()),
+            HttpRequestType::EstimateFee(_md, sender, payload) =>
+                format!("sender={} payload_len={}", sender, payload.serialize_to_vec().len()),
+            HttpRequestType::SimulateTransaction(_md, sender, payload, overrides) =>
+                format!("sender={} payload_len={} overrides={}", sender, payload.serialize_to_vec().len(), overrides.len()),
+            HttpRequestType::BanPeers(_md, addr, port_opt) => match port_opt {
+                Some(port) => format!("addr={} port={}", addr.to_socketaddr(*port).ip(), port),
+                None => format!("addr={}", addr.to_socketaddr(0).ip())
+            },
+            HttpRequestType::PostBatch(_md, items) => format!("requests={}", items.len()),
+            _ => "".to_string()
         }
-        Ok(())
     }
-}
 
-impl HttpResponseType {
-    fn try_parse<R: Read, F>(protocol: &mut StacksHttp, regex: &Regex, request_version: HttpVersion, preamble: &HttpResponsePreamble, request_path: &String, fd: &mut R, len_hint: Option<usize>, parser: F) -> Result<Option<HttpResponseType>, net_error>
-    where
-        F: Fn(&mut StacksHttp, HttpVersion, &HttpResponsePreamble, &mut R, Option<usize>) -> Result<HttpResponseType, net_error>
-    {
-        if regex.is_match(request_path) {
-            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
-            Ok(Some(payload))
-        }
-        else {
-            Ok(None)
+    /// Name of this request's class, as used to configure its priority
+    pub fn request_class(&self) -> &'static str {
+        match self {
+            HttpRequestType::GetInfo(..) => "GetInfo",
+            HttpRequestType::GetNeighbors(..) => "GetNeighbors",
+            HttpRequestType::GetStatus(..) => "GetStatus",
+            HttpRequestType::GetNeighborStats(..) => "GetNeighborStats",
+            HttpRequestType::GetConsensusConstants(..) => "GetConsensusConstants",
+            HttpRequestType::GetOpenAPI(..) => "GetOpenAPI",
+            HttpRequestType::GetMinedBlocks(..) => "GetMinedBlocks",
+            HttpRequestType::GetBlock(..) => "GetBlock",
+            HttpRequestType::GetBlockByHeight(..) => "GetBlockByHeight",
+            HttpRequestType::GetBlockByHash(..) => "GetBlockByHash",
+            HttpRequestType::ExportBlocks(..) => "ExportBlocks",
+            HttpRequestType::GetMicroblocksIndexed(..) => "GetMicroblocksIndexed",
+            HttpRequestType::GetMicroblocksConfirmed(..) => "GetMicroblocksConfirmed",
+            HttpRequestType::GetMicroblocksUnconfirmed(..) => "GetMicroblocksUnconfirmed",
+            HttpRequestType::GetMicroblocksUnconfirmedTip(..) => "GetMicroblocksUnconfirmedTip",
+            HttpRequestType::GetMicroblockByHash(..) => "GetMicroblockByHash",
+            HttpRequestType::GetBlockTransactions(..) => "GetBlockTransactions",
+            HttpRequestType::GetBlockContractCosts(..) => "GetBlockContractCosts",
+            HttpRequestType::GetMempoolTxids(..) => "GetMempoolTxids",
+            HttpRequestType::GetMempoolStats(..) => "GetMempoolStats",
+            HttpRequestType::GetTransfersByMemo(..) => "GetTransfersByMemo",
+            HttpRequestType::GetTransactionReceipt(..) => "GetTransactionReceipt",
+            HttpRequestType::GetBlockReceipts(..) => "GetBlockReceipts",
+            HttpRequestType::GetContractReceipts(..) => "GetContractReceipts",
+            HttpRequestType::SearchHashPrefix(..) => "SearchHashPrefix",
+            HttpRequestType::GetStxSupply(..) => "GetStxSupply",
+            HttpRequestType::GetBalanceHistory(..) => "GetBalanceHistory",
+            HttpRequestType::GetTopBalances(..) => "GetTopBalances",
+            HttpRequestType::GetAddressTransactions(..) => "GetAddressTransactions",
+            HttpRequestType::GetMetrics(..) => "GetMetrics",
+            HttpRequestType::PostTransaction(..) => "PostTransaction",
+            HttpRequestType::GetAccount(..) => "GetAccount",
+            HttpRequestType::GetMapEntry(..) => "GetMapEntry",
+            HttpRequestType::GetDataVar(..) => "GetDataVar",
+            HttpRequestType::GetFungibleTokenBalance(..) => "GetFungibleTokenBalance",
+            HttpRequestType::GetNonFungibleTokenOwner(..) => "GetNonFungibleTokenOwner",
+            HttpRequestType::GetTransferCost(..) => "GetTransferCost",
+            HttpRequestType::EstimateFee(..) => "EstimateFee",
+            HttpRequestType::SimulateTransaction(..) => "SimulateTransaction",
+            HttpRequestType::GetContractABI(..) => "GetContractABI",
+            HttpRequestType::GetContractSrc(..) => "GetContractSrc",
+            HttpRequestType::GetTraitImplementors(..) => "GetTraitImplementors",
+            HttpRequestType::GetContractDependencies(..) => "GetContractDependencies",
+            HttpRequestType::CallReadOnlyFunction(..) => "CallReadOnlyFunction",
+            HttpRequestType::AnalyzeContract(..) => "AnalyzeContract",
+            HttpRequestType::DryRunContract(..) => "DryRunContract",
+            HttpRequestType::BanPeers(..) => "BanPeers",
+            HttpRequestType::PostBatch(..) => "PostBatch",
+            HttpRequestType::OptionsPreflight(..) => "OptionsPreflight",
+            HttpRequestType::Unmatched(..) => "Unmatched",
         }
     }
 
-    fn parse_error<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R) -> Result<HttpResponseType, net_error> {
-        if preamble.status_code < 400 || preamble.status_code > 599 {
-            return Err(net_error::DeserializeError("Inavlid response: not an error".to_string()));
+    /// Is this the name of a request class, as used to configure its priority?
+    pub fn is_request_class(name: &str) -> bool {
+        REQUEST_CLASSES.contains(&name)
+    }
+
+    /// How important is this request to serve under load?  Block and microblock fetches keep
+    /// other nodes syncing, and transaction posts feed the mempool, so they're never shed.
+    /// Contract state reads are analytics traffic, and are shed first.
+    pub fn default_priority(&self) -> RequestPriority {
+        match self {
+            HttpRequestType::GetBlock(..) |
+            HttpRequestType::GetBlockByHeight(..) |
+            HttpRequestType::GetMicroblocksIndexed(..) |
+            HttpRequestType::GetMicroblocksConfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmedTip(..) |
+            HttpRequestType::PostTransaction(..) |
+            HttpRequestType::BanPeers(..) => RequestPriority::Critical,
+            HttpRequestType::GetMapEntry(..) |
+            HttpRequestType::GetDataVar(..) |
+            HttpRequestType::GetFungibleTokenBalance(..) |
+            HttpRequestType::GetNonFungibleTokenOwner(..) |
+            HttpRequestType::GetContractABI(..) |
+            HttpRequestType::GetContractSrc(..) |
+            HttpRequestType::GetTraitImplementors(..) |
+            HttpRequestType::GetContractDependencies(..) |
+            HttpRequestType::SearchHashPrefix(..) |
+            HttpRequestType::GetBlockContractCosts(..) |
+            HttpRequestType::GetContractReceipts(..) |
+            HttpRequestType::ExportBlocks(..) |
+            HttpRequestType::CallReadOnlyFunction(..) |
+            HttpRequestType::AnalyzeContract(..) |
+            HttpRequestType::DryRunContract(..) |
+            HttpRequestType::EstimateFee(..) |
+            HttpRequestType::SimulateTransaction(..) => RequestPriority::Low,
+            _ => RequestPriority::Normal
         }
-        
-        if preamble.content_type != HttpContentType::Text {
-            return Err(net_error::DeserializeError("Invalid error response: expected text/plain".to_string()));
+    }
+
+    /// Does this request change the node's state in a way only its operator should be able to?
+    /// If so, it needs the node's API key.  Posting transactions is open to everyone.
+    pub fn is_privileged(&self) -> bool {
+        match self {
+            HttpRequestType::BanPeers(..) => true,
+            _ => false
         }
+    }
 
-        let mut error_text = String::new();
-        fd.read_to_string(&mut error_text).map_err(net_error::ReadError)?;
+    /// Does this request write to the chainstate or mempool?  These are refused while the node
+    /// is repairing its chainstate.
+    pub fn writes_chainstate(&self) -> bool {
+        match self {
+            HttpRequestType::PostTransaction(..) => true,
+            _ => false
+        }
+    }
 
-        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
-        let resp = match preamble.status_code {
+    /// Does this request read the Clarity state (or, to post a transaction, check it against the
+    /// state)?  A light-synced node has none, so these are refused.
+    pub fn reads_clarity_state(&self) -> bool {
+        match self {
+            HttpRequestType::SearchHashPrefix(..) |
+            HttpRequestType::PostTransaction(..) |
+            HttpRequestType::GetAccount(..) |
+            HttpRequestType::GetMapEntry(..) |
+            HttpRequestType::GetDataVar(..) |
+            HttpRequestType::GetFungibleTokenBalance(..) |
+            HttpRequestType::GetNonFungibleTokenOwner(..) |
+            HttpRequestType::EstimateFee(..) |
+            HttpRequestType::SimulateTransaction(..) |
+            HttpRequestType::GetContractABI(..) |
+            HttpRequestType::GetContractSrc(..) |
+            HttpRequestType::GetTraitImplementors(..) |
+            HttpRequestType::GetContractDependencies(..) |
+            HttpRequestType::CallReadOnlyFunction(..) |
+            HttpRequestType::AnalyzeContract(..) |
+            HttpRequestType::DryRunContract(..) => true,
+            _ => false
+        }
+    }
+
+    /// Can this request be made as part of a batch?  Only reads answered in one reply can be --
+    /// not streams, writes, privileged requests, or other batches.
+    pub fn is_batchable(&self) -> bool {
+        match self {
+            HttpRequestType::GetBlock(..) |
+            HttpRequestType::GetBlockByHeight(..) |
+            HttpRequestType::GetBlockByHash(..) |
+            HttpRequestType::ExportBlocks(..) |
+            HttpRequestType::GetMicroblocksIndexed(..) |
+            HttpRequestType::GetMicroblocksConfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmedTip(..) |
+            HttpRequestType::PostBatch(..) |
+            HttpRequestType::OptionsPreflight(..) => false,
+            other => !other.writes_chainstate() && !other.is_privileged()
+        }
+    }
+
+    /// Is this request expensive to serve?  Block and microblock streams, block exports, MARF
+    /// proofs, read-only calls and batches are.  The HTTP server only starts a few of these per
+    /// pass, so they can't crowd out cheap requests like transaction posts.
+    pub fn is_heavy(&self) -> bool {
+        match self {
+            HttpRequestType::GetBlock(..) |
+            HttpRequestType::GetBlockByHeight(..) |
+            HttpRequestType::GetBlockByHash(..) |
+            HttpRequestType::ExportBlocks(..) |
+            HttpRequestType::GetMicroblocksIndexed(..) |
+            HttpRequestType::GetMicroblocksConfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmed(..) |
+            HttpRequestType::GetMicroblocksUnconfirmedTip(..) |
+            HttpRequestType::CallReadOnlyFunction(..) |
+            HttpRequestType::AnalyzeContract(..) |
+            HttpRequestType::DryRunContract(..) |
+            HttpRequestType::EstimateFee(..) |
+            HttpRequestType::SimulateTransaction(..) |
+            HttpRequestType::PostBatch(..) => true,
+            HttpRequestType::GetAccount(_, _, with_proof, _) |
+            HttpRequestType::GetMapEntry(_, _, _, _, _, with_proof, _) |
+            HttpRequestType::GetDataVar(_, _, _, _, with_proof, _) |
+            HttpRequestType::GetFungibleTokenBalance(_, _, _, _, _, with_proof, _) |
+            HttpRequestType::GetNonFungibleTokenOwner(_, _, _, _, _, with_proof, _) |
+            HttpRequestType::GetContractSrc(_, _, _, with_proof) => *with_proof,
+            _ => false
+        }
+    }
+
+    /// This request's priority, given the node's configured overrides
+    pub fn priority(&self, overrides: &[(String, RequestPriority)]) -> RequestPriority {
+        let class = self.request_class();
+        for (override_class, priority) in overrides.iter() {
+            if override_class == class {
+                return *priority;
+            }
+        }
+        self.default_priority()
+    }
+
+    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
+        match self {
+            HttpRequestType::PostTransaction(md, tx, _) => {
+                let mut tx_bytes = vec![];
+                write_next(&mut tx_bytes, tx)?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(tx_bytes.len() as u32), Some(&HttpContentType::Bytes), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&tx_bytes).map_err(net_error::WriteError)?;
+            },
+            HttpRequestType::BanPeers(md, ip, port) => {
+                let body = RPCBanPeersRequestBody { ip: ip.clone(), port: port.clone() };
+                let body_bytes = serde_json::to_vec(&body)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(body_bytes.len() as u32), Some(&HttpContentType::JSON), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            },
+            HttpRequestType::AnalyzeContract(md, deployer, contract_name, source) |
+            HttpRequestType::DryRunContract(md, deployer, contract_name, source) => {
+                let body = AnalyzeContractRequestBody { deployer: deployer.to_string(), name: contract_name.to_string(), source: source.clone() };
+                let body_bytes = serde_json::to_vec(&body)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(body_bytes.len() as u32), Some(&HttpContentType::JSON), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            },
+            HttpRequestType::EstimateFee(md, sender, payload) => {
+                let body = FeeEstimateRequestBody { sender: sender.to_string(), payload: format!("0x{}", to_hex(&payload.serialize_to_vec())) };
+                let body_bytes = serde_json::to_vec(&body)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(body_bytes.len() as u32), Some(&HttpContentType::JSON), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            },
+            HttpRequestType::SimulateTransaction(md, sender, payload, overrides) => {
+                let body = SimulateTransactionRequestBody {
+                    sender: sender.to_string(),
+                    payload: format!("0x{}", to_hex(&payload.serialize_to_vec())),
+                    overrides: overrides.iter().map(HttpRequestType::state_override_body).collect()
+                };
+                let body_bytes = serde_json::to_vec(&body)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(body_bytes.len() as u32), Some(&HttpContentType::JSON), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            },
+            HttpRequestType::PostBatch(md, items) => {
+                let body_bytes = serde_json::to_vec(items)
+                    .map_err(|e| net_error::SerializeError(format!("Failed to serialize JSON body: {:?}", &e)))?;
+
+                HttpRequestPreamble::new_serialized(fd, &md.version, "POST", &self.request_path(), &md.peer, md.keep_alive, Some(body_bytes.len() as u32), Some(&HttpContentType::JSON), |ref mut fd| request_headers(fd, md))?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            },
+            other_type => {
+                let md = other_type.metadata();
+                let request_path = other_type.request_path();
+                HttpRequestPreamble::new_serialized(fd, &md.version, "GET", &request_path, &md.peer, md.keep_alive, None, None, |ref mut fd| request_headers(fd, md))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HttpResponseType {
+    fn try_parse<R: Read, F>(protocol: &mut StacksHttp, regex: &Regex, request_version: HttpVersion, preamble: &HttpResponsePreamble, request_path: &String, fd: &mut R, len_hint: Option<usize>, parser: F) -> Result<Option<HttpResponseType>, net_error>
+    where
+        F: Fn(&mut StacksHttp, HttpVersion, &HttpResponsePreamble, &mut R, Option<usize>) -> Result<HttpResponseType, net_error>
+    {
+        if regex.is_match(request_path) {
+            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
+            Ok(Some(payload))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    fn parse_error<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R) -> Result<HttpResponseType, net_error> {
+        if preamble.status_code < 400 || preamble.status_code > 599 {
+            return Err(net_error::DeserializeError("Inavlid response: not an error".to_string()));
+        }
+        
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError("Invalid error response: expected text/plain".to_string()));
+        }
+
+        let mut error_text = String::new();
+        fd.read_to_string(&mut error_text).map_err(net_error::ReadError)?;
+
+        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
+        let resp = match preamble.status_code {
             400 => HttpResponseType::BadRequest(md, error_text),
             401 => HttpResponseType::Unauthorized(md, error_text),
             402 => HttpResponseType::PaymentRequired(md, error_text),
             403 => HttpResponseType::Forbidden(md, error_text),
             404 => HttpResponseType::NotFound(md, error_text),
+            429 => HttpResponseType::TooManyRequests(md, error_text),
             500 => HttpResponseType::ServerError(md, error_text),
             503 => HttpResponseType::ServiceUnavailable(md, error_text),
             _ => HttpResponseType::Error(md, preamble.status_code, error_text)
@@ -1567,6 +2963,16 @@ impl HttpResponseType {
             return Err(net_error::DeserializeError("Invalid content-type: expected application/octet-stream".to_string()));
         }
 
+        if let Some(encoding) = preamble.headers.get("content-encoding") {
+            // compressed, so the whole body has to be read before it can be decoded
+            let encoding = HttpContentEncoding::from_str(encoding)
+                .ok_or(net_error::DeserializeError(format!("Unsupported Content-Encoding: {}", encoding)))?;
+
+            let body = HttpResponseType::read_body(preamble, fd, len_hint, max_len)?;
+            let decoded = compression::decompress(encoding, &body, max_len)?;
+            return read_next(&mut &decoded[..]);
+        }
+
         let item : T =
             if preamble.is_chunked() && len_hint.is_none() {
                 let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
@@ -1642,6 +3048,11 @@ impl HttpResponseType {
         if preamble.content_type != HttpContentType::Text {
             return Err(net_error::DeserializeError("Invalid content-type: expected text/plain".to_string()));
         }
+        HttpResponseType::read_body(preamble, fd, len_hint, max_len)
+    }
+
+    /// Read the raw body, whatever it encodes
+    fn read_body<R: Read>(preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>, max_len: u64) -> Result<Vec<u8>, net_error> {
         let buf =
             if preamble.is_chunked() && len_hint.is_none() {
                 let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
@@ -1656,6 +3067,9 @@ impl HttpResponseType {
                     (None, None) => None
                 };
                 if let Some(len) = content_length_opt {
+                    if (len as u64) > max_len {
+                        return Err(net_error::DeserializeError("Invalid Content-Length header: too long".to_string()));
+                    }
                     let mut buf = vec![0u8; len as usize];
                     fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
                     buf
@@ -1677,18 +3091,55 @@ impl HttpResponseType {
         }
 
         // TODO: make this static somehow
-        let RESPONSE_METHODS : [(&Regex, &dyn Fn(&mut StacksHttp, HttpVersion, &HttpResponsePreamble, &mut R, Option<usize>) -> Result<HttpResponseType, net_error>); 7] = [
+        let RESPONSE_METHODS : [(&Regex, &dyn Fn(&mut StacksHttp, HttpVersion, &HttpResponsePreamble, &mut R, Option<usize>) -> Result<HttpResponseType, net_error>); 32] = [
             (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
             (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
+            (&PATH_GETSTATUS, &HttpResponseType::parse_status),
+            (&PATH_GETNEIGHBOR_STATS, &HttpResponseType::parse_neighbor_stats),
+            (&PATH_GETCONSENSUS_CONSTANTS, &HttpResponseType::parse_consensus_constants),
+            (&PATH_GETMINED_BLOCKS, &HttpResponseType::parse_mined_blocks),
+            (&PATH_POST_BAN_PEERS, &HttpResponseType::parse_peers_banned),
+            (&PATH_POST_BATCH, &HttpResponseType::parse_batch_results),
+            (&PATH_GETOPENAPI, &HttpResponseType::parse_openapi),
             (&PATH_GETBLOCK, &HttpResponseType::parse_block),
+            (&PATH_GETBLOCK_BY_HEIGHT, &HttpResponseType::parse_block),
+            (&PATH_GETBLOCK_BY_HASH, &HttpResponseType::parse_block),
+            (&PATH_EXPORT_BLOCKS, &HttpResponseType::parse_exported_blocks),
             (&PATH_GETMICROBLOCKS_INDEXED, &HttpResponseType::parse_microblocks),
             (&PATH_GETMICROBLOCKS_CONFIRMED, &HttpResponseType::parse_microblocks),
             (&PATH_GETMICROBLOCKS_UNCONFIRMED, &HttpResponseType::parse_microblocks_unconfirmed),
+            (&PATH_GETMICROBLOCKS_UNCONFIRMED_TIP, &HttpResponseType::parse_microblocks_unconfirmed),
+            (&PATH_GETMICROBLOCK_BY_HASH, &HttpResponseType::parse_microblock),
+            (&PATH_GETBLOCK_TRANSACTIONS, &HttpResponseType::parse_block_transactions),
+            (&PATH_GETBLOCK_CONTRACT_COSTS, &HttpResponseType::parse_block_contract_costs),
+            (&PATH_GETMEMPOOL_TXIDS, &HttpResponseType::parse_mempool_txids),
+            (&PATH_GETMEMPOOL_STATS, &HttpResponseType::parse_mempool_stats),
+            (&PATH_GET_TRANSFERS_BY_MEMO, &HttpResponseType::parse_transfers_by_memo),
+            (&PATH_GET_TRANSACTION_RECEIPT, &HttpResponseType::parse_mined_transaction_receipt),
+            (&PATH_GETBLOCK_RECEIPTS, &HttpResponseType::parse_mined_transaction_receipts),
+            (&PATH_GET_CONTRACT_RECEIPTS, &HttpResponseType::parse_mined_transaction_receipts),
+            (&PATH_SEARCH_HASH_PREFIX, &HttpResponseType::parse_hash_prefix_matches),
+            (&PATH_GET_STX_SUPPLY, &HttpResponseType::parse_stx_supply),
+            (&PATH_GET_BALANCE_HISTORY, &HttpResponseType::parse_balance_history),
+            (&PATH_GET_TOP_BALANCES, &HttpResponseType::parse_top_balances),
+            (&PATH_GET_ADDRESS_TRANSACTIONS, &HttpResponseType::parse_address_transactions),
+            (&PATH_GETMETRICS, &HttpResponseType::parse_metrics),
+            (&PATH_GET_CONTRACT_ABI, &HttpResponseType::parse_contract_abi),
+            (&PATH_GET_CONTRACT_SRC, &HttpResponseType::parse_contract_src),
+            (&PATH_GET_TRAIT_IMPLEMENTORS, &HttpResponseType::parse_trait_implementors),
+            (&PATH_GET_CONTRACT_DEPENDENCIES, &HttpResponseType::parse_contract_dependencies),
+            (&PATH_POST_ANALYZE_CONTRACT, &HttpResponseType::parse_contract_analysis),
+            (&PATH_POST_DRY_RUN_CONTRACT, &HttpResponseType::parse_contract_dry_run),
+            (&PATH_POST_ESTIMATE_FEE, &HttpResponseType::parse_fee_estimate),
+            (&PATH_POST_SIMULATE_TRANSACTION, &HttpResponseType::parse_transaction_simulation),
             (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid)
         ];
 
+        // match on the path alone -- the query string (if any) doesn't determine the response type
+        let request_path_no_query = request_path.split('?').next().unwrap_or("").to_string();
+
         for (regex, parser) in RESPONSE_METHODS.iter() {
-            match HttpResponseType::try_parse(protocol, regex, request_version, preamble, &request_path, fd, len_hint, parser) {
+            match HttpResponseType::try_parse(protocol, regex, request_version, preamble, &request_path_no_query, fd, len_hint, parser) {
                 Ok(Some(request)) => {
                     return Ok(request);
                 },
@@ -1716,16 +3167,205 @@ impl HttpResponseType {
         Ok(HttpResponseType::Neighbors(HttpResponseMetadata::from_preamble(request_version, preamble), neighbors_data))
     }
 
+    fn parse_status<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let status_data = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Status(HttpResponseMetadata::from_preamble(request_version, preamble), status_data))
+    }
+
+    fn parse_neighbor_stats<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let stats_data = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NeighborStats(HttpResponseMetadata::from_preamble(request_version, preamble), stats_data))
+    }
+
+    fn parse_contract_abi<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let contract_interface = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetContractABI(HttpResponseMetadata::from_preamble(request_version, preamble), contract_interface))
+    }
+
+    fn parse_contract_src<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let contract_src = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetContractSrc(HttpResponseMetadata::from_preamble(request_version, preamble), contract_src))
+    }
+
+    fn parse_mined_transaction_receipt<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let receipt = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MinedTransactionReceipt(HttpResponseMetadata::from_preamble(request_version, preamble), receipt))
+    }
+
+    fn parse_mined_transaction_receipts<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let receipts = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MinedTransactionReceipts(HttpResponseMetadata::from_preamble(request_version, preamble), receipts))
+    }
+
+    fn parse_hash_prefix_matches<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let matches = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::HashPrefixMatches(HttpResponseMetadata::from_preamble(request_version, preamble), matches))
+    }
+
+    fn parse_stx_supply<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let supply = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StxSupply(HttpResponseMetadata::from_preamble(request_version, preamble), supply))
+    }
+
+    fn parse_balance_history<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let history = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BalanceHistory(HttpResponseMetadata::from_preamble(request_version, preamble), history))
+    }
+
+    fn parse_top_balances<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let accounts = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TopBalances(HttpResponseMetadata::from_preamble(request_version, preamble), accounts))
+    }
+
+    fn parse_address_transactions<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let transactions = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AddressTransactions(HttpResponseMetadata::from_preamble(request_version, preamble), transactions))
+    }
+
+    fn parse_contract_analysis<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let analysis = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AnalyzeContract(HttpResponseMetadata::from_preamble(request_version, preamble), analysis))
+    }
+
+    fn parse_contract_dry_run<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let dry_run = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::DryRunContract(HttpResponseMetadata::from_preamble(request_version, preamble), dry_run))
+    }
+
+    fn parse_fee_estimate<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let estimate = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::FeeEstimate(HttpResponseMetadata::from_preamble(request_version, preamble), estimate))
+    }
+
+    fn parse_transaction_simulation<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let simulation = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::SimulateTransaction(HttpResponseMetadata::from_preamble(request_version, preamble), simulation))
+    }
+
+    fn parse_trait_implementors<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let implementors = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetTraitImplementors(HttpResponseMetadata::from_preamble(request_version, preamble), implementors))
+    }
+
+    fn parse_contract_dependencies<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let dependencies = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetContractDependencies(HttpResponseMetadata::from_preamble(request_version, preamble), dependencies))
+    }
+
+    fn parse_consensus_constants<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let constants_data = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ConsensusConstants(HttpResponseMetadata::from_preamble(request_version, preamble), constants_data))
+    }
+
+    fn parse_openapi<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let document = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::OpenAPI(HttpResponseMetadata::from_preamble(request_version, preamble), document))
+    }
+
+    fn parse_mined_blocks<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let mined_blocks = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MinedBlocks(HttpResponseMetadata::from_preamble(request_version, preamble), mined_blocks))
+    }
+
+    fn parse_peers_banned<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let banned = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeersBanned(HttpResponseMetadata::from_preamble(request_version, preamble), banned))
+    }
+
+    fn parse_batch_results<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let results = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BatchResults(HttpResponseMetadata::from_preamble(request_version, preamble), results))
+    }
+
+    fn parse_block_contract_costs<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let costs = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockContractCosts(HttpResponseMetadata::from_preamble(request_version, preamble), costs))
+    }
+
+    fn parse_block_transactions<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockTransactions(HttpResponseMetadata::from_preamble(request_version, preamble), page))
+    }
+
+    fn parse_mempool_txids<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MempoolTxids(HttpResponseMetadata::from_preamble(request_version, preamble), page))
+    }
+
+    fn parse_mempool_stats<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let stats = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MempoolStats(HttpResponseMetadata::from_preamble(request_version, preamble), stats))
+    }
+
+    fn parse_transfers_by_memo<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let transfers = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TransfersByMemo(HttpResponseMetadata::from_preamble(request_version, preamble), transfers))
+    }
+
+    fn parse_metrics<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let text_bytes = HttpResponseType::parse_text(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        let text = String::from_utf8(text_bytes)
+            .map_err(|_e| net_error::DeserializeError("Metrics are not valid UTF-8".to_string()))?;
+        Ok(HttpResponseType::Metrics(HttpResponseMetadata::from_preamble(request_version, preamble), text))
+    }
+
     fn parse_block<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
         let block : StacksBlock = HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
         Ok(HttpResponseType::Block(HttpResponseMetadata::from_preamble(request_version, preamble), block))
     }
 
+    /// Parse a block export in either format.  Only exports that fit in MAX_MESSAGE_LEN bytes can
+    /// be parsed this way; bulk exports need to be read as a stream.
+    fn parse_exported_blocks<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let mut body = HttpResponseType::read_body(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        if let Some(encoding) = preamble.headers.get("content-encoding") {
+            let encoding = HttpContentEncoding::from_str(encoding)
+                .ok_or(net_error::DeserializeError(format!("Unsupported Content-Encoding: {}", encoding)))?;
+            body = compression::decompress(encoding, &body, MAX_MESSAGE_LEN as u64)?;
+        }
+
+        let mut blocks = vec![];
+        match preamble.content_type {
+            HttpContentType::Bytes => {
+                let mut cursor = &body[..];
+                while cursor.len() > 0 {
+                    let block_len : u32 = read_next(&mut cursor)?;
+                    if (block_len as usize) > cursor.len() {
+                        return Err(net_error::DeserializeError("Truncated block in block export".to_string()));
+                    }
+                    let block : StacksBlock = read_next(&mut &cursor[0..(block_len as usize)])?;
+                    blocks.push(block);
+                    cursor = &cursor[(block_len as usize)..];
+                }
+            },
+            HttpContentType::NDJSON => {
+                for line in body.split(|b| *b == b'\n').filter(|line| line.len() > 0) {
+                    let entry : ExportedBlockEntry = serde_json::from_slice(line)
+                        .map_err(|e| net_error::DeserializeError(format!("Failed to parse exported block: {:?}", &e)))?;
+                    let block_bytes = hex_bytes(&entry.block)
+                        .map_err(|_e| net_error::DeserializeError("Failed to decode exported block".to_string()))?;
+                    let block : StacksBlock = read_next(&mut &block_bytes[..])?;
+                    blocks.push(block);
+                }
+            },
+            _ => {
+                return Err(net_error::DeserializeError("Invalid content-type: expected application/octet-stream or application/x-ndjson".to_string()));
+            }
+        }
+
+        Ok(HttpResponseType::ExportedBlocks(HttpResponseMetadata::from_preamble(request_version, preamble), blocks))
+    }
+
     fn parse_microblocks<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
         let microblocks : Vec<StacksMicroblock> = HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
         Ok(HttpResponseType::Microblocks(HttpResponseMetadata::from_preamble(request_version, preamble), microblocks))
     }
 
+    fn parse_microblock<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
+        let microblock : StacksMicroblock = HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Microblock(HttpResponseMetadata::from_preamble(request_version, preamble), microblock))
+    }
+
     fn parse_microblocks_unconfirmed<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
         // NOTE: there will be no length prefix on this, but we won't ever get more than
         // MAX_MICROBLOCKS_UNCONFIRMED microblocks
@@ -1755,8 +3395,18 @@ impl HttpResponseType {
         Ok(HttpResponseType::Microblocks(HttpResponseMetadata::from_preamble(request_version, preamble), microblocks))
     }
 
+    /// A transaction submission gets back either the txid, or (if we asked to wait) a receipt
     fn parse_txid<R: Read>(_protocol: &mut StacksHttp, request_version: HttpVersion, preamble: &HttpResponsePreamble, fd: &mut R, len_hint: Option<usize>) -> Result<HttpResponseType, net_error> {
-        let txid_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
+        let json_data : serde_json::Value = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        let txid_hex = match json_data {
+            serde_json::Value::String(txid_hex) => txid_hex,
+            receipt_json => {
+                let receipt = serde_json::from_value(receipt_json)
+                    .map_err(|_e| net_error::DeserializeError("Failed to decode transaction receipt".to_string()))?;
+                return Ok(HttpResponseType::TransactionReceipt(HttpResponseMetadata::from_preamble(request_version, preamble), receipt));
+            }
+        };
+
         if txid_hex.len() != 64 {
             return Err(net_error::DeserializeError("Invalid txid: expected 64 bytes".to_string()));
         }
@@ -1773,15 +3423,17 @@ impl HttpResponseType {
             402 => "Payment Required",
             403 => "Forbidden",
             404 => "Not Found",
+            410 => "Gone",
+            429 => "Too Many Requests",
             500 => "Internal Server Error",
             503 => "Service Temporarily Unavailable",
             _ => "Error"
         }
     }
 
-    fn error_response<W: Write>(&self, fd: &mut W, code: u16, message: &str) -> Result<(), net_error> {
+    fn error_response<W: Write>(&self, fd: &mut W, code: u16, message: &str, cors: &CORSPolicy) -> Result<(), net_error> {
         let md = self.metadata();
-        HttpResponsePreamble::new_serialized(fd, code, HttpResponseType::error_reason(code), Some(message.len() as u32), &HttpContentType::Text, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+        HttpResponsePreamble::new_serialized(fd, code, HttpResponseType::error_reason(code), Some(message.len() as u32), &HttpContentType::Text, md.request_id, |ref mut fd| response_headers(fd, md, cors))?;
         fd.write_all(message.as_bytes()).map_err(net_error::WriteError)?;
         Ok(())
     }
@@ -1790,17 +3442,50 @@ impl HttpResponseType {
         match *self {
             HttpResponseType::PeerInfo(ref md, _) => md,
             HttpResponseType::Neighbors(ref md, _) => md,
+            HttpResponseType::Status(ref md, _) => md,
+            HttpResponseType::NeighborStats(ref md, _) => md,
+            HttpResponseType::ConsensusConstants(ref md, _) => md,
+            HttpResponseType::OpenAPI(ref md, _) => md,
+            HttpResponseType::MinedBlocks(ref md, _) => md,
+            HttpResponseType::PeersBanned(ref md, _) => md,
+            HttpResponseType::BatchResults(ref md, _) => md,
             HttpResponseType::Block(ref md, _) => md,
             HttpResponseType::BlockStream(ref md) => md,
+            HttpResponseType::BlockExportStream(ref md, _) => md,
+            HttpResponseType::ExportedBlocks(ref md, _) => md,
             HttpResponseType::Microblocks(ref md, _) => md,
+            HttpResponseType::Microblock(ref md, _) => md,
             HttpResponseType::MicroblockStream(ref md) => md,
             HttpResponseType::TransactionID(ref md, _) => md,
+            HttpResponseType::TransactionReceipt(ref md, _) => md,
             HttpResponseType::TokenTransferCost(ref md, _) => md,
+            HttpResponseType::FeeEstimate(ref md, _) => md,
+            HttpResponseType::SimulateTransaction(ref md, _) => md,
             HttpResponseType::GetMapEntry(ref md, _) => md,
+            HttpResponseType::GetDataVar(ref md, _) => md,
+            HttpResponseType::GetFungibleTokenBalance(ref md, _) => md,
+            HttpResponseType::GetNonFungibleTokenOwner(ref md, _) => md,
             HttpResponseType::GetAccount(ref md, _) => md,
             HttpResponseType::GetContractABI(ref md, _) => md,
             HttpResponseType::GetContractSrc(ref md, _) => md,
+            HttpResponseType::GetTraitImplementors(ref md, _) => md,
+            HttpResponseType::GetContractDependencies(ref md, _) => md,
+            HttpResponseType::BlockTransactions(ref md, _) => md,
+            HttpResponseType::BlockContractCosts(ref md, _) => md,
+            HttpResponseType::MempoolTxids(ref md, _) => md,
+            HttpResponseType::MempoolStats(ref md, _) => md,
+            HttpResponseType::TransfersByMemo(ref md, _) => md,
+            HttpResponseType::MinedTransactionReceipt(ref md, _) => md,
+            HttpResponseType::MinedTransactionReceipts(ref md, _) => md,
+            HttpResponseType::HashPrefixMatches(ref md, _) => md,
+            HttpResponseType::StxSupply(ref md, _) => md,
+            HttpResponseType::BalanceHistory(ref md, _) => md,
+            HttpResponseType::TopBalances(ref md, _) => md,
+            HttpResponseType::AddressTransactions(ref md, _) => md,
+            HttpResponseType::Metrics(ref md, _) => md,
             HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
+            HttpResponseType::AnalyzeContract(ref md, _) => md,
+            HttpResponseType::DryRunContract(ref md, _) => md,
             HttpResponseType::OptionsPreflight(ref md) => md,
             // errors
             HttpResponseType::BadRequestJSON(ref md, _) => md,
@@ -1809,6 +3494,7 @@ impl HttpResponseType {
             HttpResponseType::PaymentRequired(ref md, _) => md,
             HttpResponseType::Forbidden(ref md, _) => md,
             HttpResponseType::NotFound(ref md, _) => md,
+            HttpResponseType::TooManyRequests(ref md, _) => md,
             HttpResponseType::ServerError(ref md, _) => md,
             HttpResponseType::ServiceUnavailable(ref md, _) => md,
             HttpResponseType::Error(ref md, _, _) => md,
@@ -1830,6 +3516,25 @@ impl HttpResponseType {
         }
     }
 
+    /// Send a block or microblocks, compressed if the client accepts it.
+    /// The compressed length isn't known up front, so compressed replies are always chunk-encoded.
+    fn send_block_data<W: Write, T: StacksMessageCodec>(protocol: &mut StacksHttp, md: &HttpResponseMetadata, fd: &mut W, message: &T) -> Result<(), net_error> {
+        match md.client_accept_encoding {
+            Some(encoding) => {
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Bytes, md.request_id, |ref mut fd| encoded_response_headers(fd, md, &protocol.cors_policy))?;
+                let mut write_state = HttpChunkedTransferWriterState::new_encoded(protocol.chunk_size as usize, Some(encoding));
+                let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
+                write_next(&mut encoder, message)?;
+                encoder.flush().map_err(net_error::WriteError)?;
+                Ok(())
+            },
+            None => {
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::Bytes, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
+                HttpResponseType::send_bytestream(protocol, md, fd, message)
+            }
+        }
+    }
+
     fn send_text<W: Write>(protocol: &mut StacksHttp, md: &HttpResponseMetadata, fd: &mut W, text: &[u8]) -> Result<(), net_error> {
         if md.content_length.is_some() {
             // have explicit content-length, so we can send as-is
@@ -1863,76 +3568,213 @@ impl HttpResponseType {
     pub fn send<W: Write>(&self, protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
         match *self {
             HttpResponseType::GetAccount(ref md, ref account_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, account_data)?;
             },
             HttpResponseType::GetContractABI(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             },
             HttpResponseType::GetContractSrc(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            },
+            HttpResponseType::GetTraitImplementors(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             },
+            HttpResponseType::GetContractDependencies(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            },
+            HttpResponseType::BlockTransactions(ref md, ref page) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            },
+            HttpResponseType::BlockContractCosts(ref md, ref costs) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, costs)?;
+            },
+            HttpResponseType::MempoolTxids(ref md, ref page) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            },
+            HttpResponseType::MempoolStats(ref md, ref stats) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, stats)?;
+            },
+            HttpResponseType::TransfersByMemo(ref md, ref transfers) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, transfers)?;
+            },
+            HttpResponseType::MinedTransactionReceipt(ref md, ref receipt) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, receipt)?;
+            },
+            HttpResponseType::MinedTransactionReceipts(ref md, ref receipts) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, receipts)?;
+            },
+            HttpResponseType::HashPrefixMatches(ref md, ref matches) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, matches)?;
+            },
+            HttpResponseType::StxSupply(ref md, ref supply) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, supply)?;
+            },
+            HttpResponseType::BalanceHistory(ref md, ref history) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, history)?;
+            },
+            HttpResponseType::TopBalances(ref md, ref accounts) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, accounts)?;
+            },
+            HttpResponseType::AddressTransactions(ref md, ref transactions) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, transactions)?;
+            },
+            HttpResponseType::Metrics(ref md, ref text) => {
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::Text, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
+                HttpResponseType::send_text(protocol, md, fd, text.as_bytes())?;
+            },
             HttpResponseType::TokenTransferCost(ref md, ref cost) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, cost)?;
             },
+            HttpResponseType::FeeEstimate(ref md, ref estimate) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, estimate)?;
+            },
+            HttpResponseType::SimulateTransaction(ref md, ref simulation) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, simulation)?;
+            },
             HttpResponseType::CallReadOnlyFunction(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             },
+            HttpResponseType::AnalyzeContract(ref md, ref analysis) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, analysis)?;
+            },
+            HttpResponseType::DryRunContract(ref md, ref dry_run) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, dry_run)?;
+            },
             HttpResponseType::GetMapEntry(ref md, ref map_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, map_data)?;
             },
+            HttpResponseType::GetDataVar(ref md, ref var_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, var_data)?;
+            },
+            HttpResponseType::GetFungibleTokenBalance(ref md, ref balance) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, balance)?;
+            },
+            HttpResponseType::GetNonFungibleTokenOwner(ref md, ref owner) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, owner)?;
+            },
             HttpResponseType::PeerInfo(ref md, ref peer_info) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, peer_info)?;
             },
             HttpResponseType::Neighbors(ref md, ref neighbor_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
                 HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
             },
+            HttpResponseType::Status(ref md, ref status_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, status_data)?;
+            },
+            HttpResponseType::NeighborStats(ref md, ref stats_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, stats_data)?;
+            },
+            HttpResponseType::ConsensusConstants(ref md, ref constants_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, constants_data)?;
+            },
+            HttpResponseType::OpenAPI(ref md, ref document) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, document)?;
+            },
+            HttpResponseType::MinedBlocks(ref md, ref mined_blocks) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, mined_blocks)?;
+            },
+            HttpResponseType::PeersBanned(ref md, ref banned) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, banned)?;
+            },
+            HttpResponseType::BatchResults(ref md, ref results) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, results)?;
+            },
             HttpResponseType::Block(ref md, ref block) => {
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::Bytes, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
-                HttpResponseType::send_bytestream(protocol, md, fd, block)?;
+                HttpResponseType::send_block_data(protocol, md, fd, block)?;
             },
             HttpResponseType::BlockStream(ref md) => {
                 // only send the preamble.  The caller will need to figure out how to send along
-                // the block data itself.
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Bytes, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+                // the block data itself (compressed, if we say so here).
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Bytes, md.request_id, |ref mut fd| encoded_response_headers(fd, md, &protocol.cors_policy))?;
+            },
+            HttpResponseType::BlockExportStream(ref md, ref format) => {
+                // only send the preamble.  The caller will stream the blocks.
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &format.content_type(), md.request_id, |ref mut fd| encoded_response_headers(fd, md, &protocol.cors_policy))?;
+            },
+            HttpResponseType::ExportedBlocks(ref md, ref blocks) => {
+                let mut body = vec![];
+                for block in blocks.iter() {
+                    let mut block_bytes = vec![];
+                    write_next(&mut block_bytes, block)?;
+                    body.extend_from_slice(&(block_bytes.len() as u32).to_be_bytes());
+                    body.extend_from_slice(&block_bytes);
+                }
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::Bytes, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
+                HttpResponseType::send_text(protocol, md, fd, &body)?;
             },
             HttpResponseType::Microblocks(ref md, ref microblocks) => {
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::Bytes, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
-                HttpResponseType::send_bytestream(protocol, md, fd, microblocks)?;
+                HttpResponseType::send_block_data(protocol, md, fd, microblocks)?;
+            },
+            HttpResponseType::Microblock(ref md, ref microblock) => {
+                HttpResponseType::send_block_data(protocol, md, fd, microblock)?;
             },
             HttpResponseType::MicroblockStream(ref md) => {
                 // only send the preamble.  The caller will need to figure out how to send along
-                // the microblock data itself.
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Bytes, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+                // the microblock data itself (compressed, if we say so here).
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Bytes, md.request_id, |ref mut fd| encoded_response_headers(fd, md, &protocol.cors_policy))?;
             },
             HttpResponseType::TransactionID(ref md, ref txid) => {
                 let txid_bytes = txid.to_hex();
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::JSON, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", md.content_length.clone(), &HttpContentType::JSON, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
                 HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
             },
+            HttpResponseType::TransactionReceipt(ref md, ref receipt) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md, &protocol.cors_policy)?;
+                HttpResponseType::send_json(protocol, md, fd, receipt)?;
+            },
             HttpResponseType::OptionsPreflight(ref md) => {
-                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Text, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+                HttpResponsePreamble::new_serialized(fd, 200, "OK", None, &HttpContentType::Text, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
                 HttpResponseType::send_text(protocol, md, fd, "".as_bytes())?;
             },
             HttpResponseType::BadRequestJSON(ref md, ref data) => {
-                HttpResponsePreamble::new_serialized(fd, 400, HttpResponseType::error_reason(400), md.content_length.clone(), &HttpContentType::JSON, md.request_id, |ref mut fd| keep_alive_headers(fd, md))?;
+                HttpResponsePreamble::new_serialized(fd, 400, HttpResponseType::error_reason(400), md.content_length.clone(), &HttpContentType::JSON, md.request_id, |ref mut fd| response_headers(fd, md, &protocol.cors_policy))?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             },
-            HttpResponseType::BadRequest(_, ref msg) => self.error_response(fd, 400, msg)?,
-            HttpResponseType::Unauthorized(_, ref msg) => self.error_response(fd, 401, msg)?,
-            HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg)?,
-            HttpResponseType::Forbidden(_, ref msg) => self.error_response(fd, 403, msg)?,
-            HttpResponseType::NotFound(_, ref msg) => self.error_response(fd, 404, msg)?,
-            HttpResponseType::ServerError(_, ref msg) => self.error_response(fd, 500, msg)?,
-            HttpResponseType::ServiceUnavailable(_, ref msg) => self.error_response(fd, 503, msg)?,
-            HttpResponseType::Error(_, ref error_code, ref msg) => self.error_response(fd, *error_code, msg)?
+            HttpResponseType::BadRequest(_, ref msg) => self.error_response(fd, 400, msg, &protocol.cors_policy)?,
+            HttpResponseType::Unauthorized(_, ref msg) => self.error_response(fd, 401, msg, &protocol.cors_policy)?,
+            HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg, &protocol.cors_policy)?,
+            HttpResponseType::Forbidden(_, ref msg) => self.error_response(fd, 403, msg, &protocol.cors_policy)?,
+            HttpResponseType::NotFound(_, ref msg) => self.error_response(fd, 404, msg, &protocol.cors_policy)?,
+            HttpResponseType::TooManyRequests(_, ref msg) => self.error_response(fd, 429, msg, &protocol.cors_policy)?,
+            HttpResponseType::ServerError(_, ref msg) => self.error_response(fd, 500, msg, &protocol.cors_policy)?,
+            HttpResponseType::ServiceUnavailable(_, ref msg) => self.error_response(fd, 503, msg, &protocol.cors_policy)?,
+            HttpResponseType::Error(_, ref error_code, ref msg) => self.error_response(fd, *error_code, msg, &protocol.cors_policy)?
         };
         Ok(())
     }
@@ -1989,40 +3831,109 @@ impl MessageSequence for StacksHttpMessage {
             StacksHttpMessage::Request(ref req) => match req {
                 HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
                 HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
+                HttpRequestType::GetStatus(..) => "HTTP(GetStatus)",
+                HttpRequestType::GetNeighborStats(_) => "HTTP(GetNeighborStats)",
+                HttpRequestType::GetConsensusConstants(_) => "HTTP(GetConsensusConstants)",
+                HttpRequestType::GetOpenAPI(_) => "HTTP(GetOpenAPI)",
+                HttpRequestType::GetMinedBlocks(_) => "HTTP(GetMinedBlocks)",
                 HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
+                HttpRequestType::GetBlockByHeight(..) => "HTTP(GetBlockByHeight)",
+                HttpRequestType::GetBlockByHash(..) => "HTTP(GetBlockByHash)",
+                HttpRequestType::ExportBlocks(..) => "HTTP(ExportBlocks)",
                 HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
                 HttpRequestType::GetMicroblocksConfirmed(_, _) => "HTTP(GetMicroblocksConfirmed)",
                 HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => "HTTP(GetMicroblocksUnconfirmed)",
-                HttpRequestType::PostTransaction(_, _) => "HTTP(PostTransaction)",
+                HttpRequestType::GetMicroblocksUnconfirmedTip(_, _) => "HTTP(GetMicroblocksUnconfirmedTip)",
+                HttpRequestType::GetMicroblockByHash(..) => "HTTP(GetMicroblockByHash)",
+                HttpRequestType::GetBlockTransactions(..) => "HTTP(GetBlockTransactions)",
+                HttpRequestType::GetBlockContractCosts(..) => "HTTP(GetBlockContractCosts)",
+                HttpRequestType::GetMempoolTxids(..) => "HTTP(GetMempoolTxids)",
+                HttpRequestType::GetMempoolStats(..) => "HTTP(GetMempoolStats)",
+                HttpRequestType::GetTransfersByMemo(..) => "HTTP(GetTransfersByMemo)",
+                HttpRequestType::GetTransactionReceipt(..) => "HTTP(GetTransactionReceipt)",
+                HttpRequestType::GetBlockReceipts(..) => "HTTP(GetBlockReceipts)",
+                HttpRequestType::GetContractReceipts(..) => "HTTP(GetContractReceipts)",
+                HttpRequestType::SearchHashPrefix(..) => "HTTP(SearchHashPrefix)",
+                HttpRequestType::GetStxSupply(..) => "HTTP(GetStxSupply)",
+                HttpRequestType::GetBalanceHistory(..) => "HTTP(GetBalanceHistory)",
+                HttpRequestType::GetTopBalances(..) => "HTTP(GetTopBalances)",
+                HttpRequestType::GetAddressTransactions(..) => "HTTP(GetAddressTransactions)",
+                HttpRequestType::GetMetrics(_) => "HTTP(GetMetrics)",
+                HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
                 HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
                 HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
+                HttpRequestType::GetDataVar(..) => "HTTP(GetDataVar)",
+                HttpRequestType::GetFungibleTokenBalance(..) => "HTTP(GetFungibleTokenBalance)",
+                HttpRequestType::GetNonFungibleTokenOwner(..) => "HTTP(GetNonFungibleTokenOwner)",
                 HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
+                HttpRequestType::EstimateFee(..) => "HTTP(EstimateFee)",
+                HttpRequestType::SimulateTransaction(..) => "HTTP(SimulateTransaction)",
                 HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpRequestType::GetTraitImplementors(..) => "HTTP(GetTraitImplementors)",
+                HttpRequestType::GetContractDependencies(..) => "HTTP(GetContractDependencies)",
                 HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpRequestType::AnalyzeContract(..) => "HTTP(AnalyzeContract)",
+                HttpRequestType::DryRunContract(..) => "HTTP(DryRunContract)",
+                HttpRequestType::BanPeers(..) => "HTTP(BanPeers)",
+                HttpRequestType::PostBatch(..) => "HTTP(PostBatch)",
                 HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
                 HttpRequestType::Unmatched(..) => "HTTP(Unmatched)",
             },
             StacksHttpMessage::Response(ref res) => match res {
                 HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
+                HttpResponseType::FeeEstimate(..) => "HTTP(FeeEstimate)",
+                HttpResponseType::SimulateTransaction(..) => "HTTP(SimulateTransaction)",
                 HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
+                HttpResponseType::GetDataVar(_, _) => "HTTP(GetDataVar)",
+                HttpResponseType::GetFungibleTokenBalance(_, _) => "HTTP(GetFungibleTokenBalance)",
+                HttpResponseType::GetNonFungibleTokenOwner(_, _) => "HTTP(GetNonFungibleTokenOwner)",
                 HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
                 HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpResponseType::GetTraitImplementors(..) => "HTTP(GetTraitImplementors)",
+                HttpResponseType::GetContractDependencies(..) => "HTTP(GetContractDependencies)",
+                HttpResponseType::BlockTransactions(..) => "HTTP(BlockTransactions)",
+                HttpResponseType::BlockContractCosts(..) => "HTTP(BlockContractCosts)",
+                HttpResponseType::MempoolTxids(..) => "HTTP(MempoolTxids)",
+                HttpResponseType::MempoolStats(..) => "HTTP(MempoolStats)",
+                HttpResponseType::TransfersByMemo(..) => "HTTP(TransfersByMemo)",
+                HttpResponseType::MinedTransactionReceipt(..) => "HTTP(MinedTransactionReceipt)",
+                HttpResponseType::MinedTransactionReceipts(..) => "HTTP(MinedTransactionReceipts)",
+                HttpResponseType::HashPrefixMatches(..) => "HTTP(HashPrefixMatches)",
+                HttpResponseType::StxSupply(..) => "HTTP(StxSupply)",
+                HttpResponseType::BalanceHistory(..) => "HTTP(BalanceHistory)",
+                HttpResponseType::TopBalances(..) => "HTTP(TopBalances)",
+                HttpResponseType::AddressTransactions(..) => "HTTP(AddressTransactions)",
+                HttpResponseType::Metrics(..) => "HTTP(Metrics)",
                 HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpResponseType::AnalyzeContract(..) => "HTTP(AnalyzeContract)",
+                HttpResponseType::DryRunContract(..) => "HTTP(DryRunContract)",
                 HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
+                HttpResponseType::Status(_, _) => "HTTP(Status)",
+                HttpResponseType::NeighborStats(_, _) => "HTTP(NeighborStats)",
+                HttpResponseType::ConsensusConstants(_, _) => "HTTP(ConsensusConstants)",
+                HttpResponseType::OpenAPI(_, _) => "HTTP(OpenAPI)",
+                HttpResponseType::MinedBlocks(_, _) => "HTTP(MinedBlocks)",
+                HttpResponseType::PeersBanned(_, _) => "HTTP(PeersBanned)",
+                HttpResponseType::BatchResults(_, _) => "HTTP(BatchResults)",
                 HttpResponseType::Block(_, _) => "HTTP(Block)",
                 HttpResponseType::BlockStream(_) => "HTTP(BlockStream)",
+                HttpResponseType::BlockExportStream(..) => "HTTP(BlockExportStream)",
+                HttpResponseType::ExportedBlocks(..) => "HTTP(ExportedBlocks)",
                 HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
+                HttpResponseType::Microblock(_, _) => "HTTP(Microblock)",
                 HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
                 HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
+                HttpResponseType::TransactionReceipt(_, _) => "HTTP(TransactionReceipt)",
                 HttpResponseType::OptionsPreflight(_) => "HTTP(OptionsPreflight)",
                 HttpResponseType::BadRequestJSON(..) | HttpResponseType::BadRequest(..) => "HTTP(400)",
                 HttpResponseType::Unauthorized(_, _) => "HTTP(401)",
                 HttpResponseType::PaymentRequired(_, _) => "HTTP(402)",
                 HttpResponseType::Forbidden(_, _) => "HTTP(403)",
                 HttpResponseType::NotFound(_, _) => "HTTP(404)",
+                HttpResponseType::TooManyRequests(_, _) => "HTTP(429)",
                 HttpResponseType::ServerError(_, _) => "HTTP(500)",
                 HttpResponseType::ServiceUnavailable(_, _) => "HTTP(503)",
                 HttpResponseType::Error(_, _, _) => "HTTP(other)"
@@ -2104,6 +4015,36 @@ impl HttpRecvStream {
     }
 }
 
+impl RPCBatchResponseItem {
+    /// Decode a reply to one of the API calls in a batch, as written by its handler
+    pub fn from_http_response(bytes: &[u8]) -> Result<RPCBatchResponseItem, net_error> {
+        let mut fd = io::Cursor::new(bytes);
+        let preamble = HttpResponsePreamble::consensus_deserialize(&mut fd)?;
+
+        let mut body = vec![];
+        if preamble.is_chunked() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(&mut fd, MAX_MESSAGE_LEN as u64);
+            chunked_fd.read_to_end(&mut body).map_err(net_error::ReadError)?;
+        }
+        else {
+            fd.read_to_end(&mut body).map_err(net_error::ReadError)?;
+        }
+
+        let body = match preamble.content_type {
+            HttpContentType::JSON => serde_json::from_slice(&body)
+                .map_err(|e| net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e)))?,
+            HttpContentType::Text |
+            HttpContentType::NDJSON => serde_json::Value::String(String::from_utf8_lossy(&body).to_string()),
+            HttpContentType::Bytes => serde_json::Value::String(to_hex(&body)),
+        };
+
+        Ok(RPCBatchResponseItem {
+            status: preamble.status_code,
+            body: body
+        })
+    }
+}
+
 /// Information about an in-flight request
 #[derive(Debug, Clone, PartialEq)]
 struct HttpReplyData {
@@ -2126,6 +4067,10 @@ pub struct StacksHttp {
     chunk_size: usize,
     /// Maximum size of call arguments
     pub maximum_call_argument_size: u32,
+    /// Which browser origins may call us
+    pub cors_policy: CORSPolicy,
+    /// Maximum number of API calls in a batch
+    pub maximum_batch_size: u32,
 }
 
 impl StacksHttp {
@@ -2136,6 +4081,8 @@ impl StacksHttp {
             request_path: None,
             chunk_size: 8192,
             maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
+            cors_policy: CORSPolicy::allow_all(),
+            maximum_batch_size: 100,
         }
     }
 
@@ -2439,10 +4386,30 @@ mod test {
     use std::error::Error;
     use net::test::*;
     use net::codec::test::check_codec_and_corruption;
+    use net::connection::ConnectionOptions;
     use net::RPCNeighbor;
     use net::RPCNeighborsInfo;
+    use net::RPCTransactionReceipt;
+    use net::MinedTransactionReceiptData;
+    use net::{HashPrefixMatch, HashPrefixSearchData, StxSupplyData, BlockContractCostsData, ContractCostData};
+    use net::{BalanceHistoryEntry, AccountBalanceData, AddressTransactionData};
+    use core::mempool::{MemPoolStats, MemPoolFeeRatePercentile, MemPoolAgeBucket};
+    use net::RPCBanPeersData;
+    use net::ContractSrcResponse;
+    use net::TraitImplementorsResponse;
+    use net::ContractDependenciesResponse;
+    use net::AnalyzeContractResponse;
+    use net::DryRunContractResponse;
+    use net::FeeEstimateResponse;
+    use net::SimulateTransactionResponse;
+
+    use vm::costs::ExecutionCost;
+    use vm::types::TupleData;
+    use vm::analysis::contract_interface_builder::*;
 
     use burnchains::Txid;
+    use burnchains::BurnchainHeaderHash;
+    use chainstate::burn::BlockHeaderHash;
     use chainstate::stacks::test::make_codec_test_block;
     use chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
     use chainstate::stacks::StacksTransaction;
@@ -3052,131 +5019,1073 @@ mod test {
     }
 
     #[test]
-    fn test_http_parse_host_header_value() {
-        let hosts = vec![
-            "1.2.3.4",
-            "1.2.3.4:5678",
-            "[1:203:405:607:809:a0b:c0d:e0f]",
-            "[1:203:405:607:809:a0b:c0d:e0f]:12345",
-            "www.foo.com",
-            "www.foo.com:12345",
-            // invalid IP addresses will be parsed to DNS names
-            "1.2.3.4.5",
-            "[1:203:405:607:809:a0b:c0d:e0f:1011]",
-            // these won't parse at all, since the port is invalid
-            "1.2.3.4:1234567",
-            "1.2.3.4.5:1234567",
-            "[1:203:405:607:809:a0b:c0d:e0f]:1234567",
-            "[1:203:405:607:809:a0b:c0d:e0f:1011]:1234567",
-            "www.foo.com:1234567",
-            ":",
-            ":123",
+    fn test_http_parse_host_header_value() {
+        let hosts = vec![
+            "1.2.3.4",
+            "1.2.3.4:5678",
+            "[1:203:405:607:809:a0b:c0d:e0f]",
+            "[1:203:405:607:809:a0b:c0d:e0f]:12345",
+            "www.foo.com",
+            "www.foo.com:12345",
+            // invalid IP addresses will be parsed to DNS names
+            "1.2.3.4.5",
+            "[1:203:405:607:809:a0b:c0d:e0f:1011]",
+            // these won't parse at all, since the port is invalid
+            "1.2.3.4:1234567",
+            "1.2.3.4.5:1234567",
+            "[1:203:405:607:809:a0b:c0d:e0f]:1234567",
+            "[1:203:405:607:809:a0b:c0d:e0f:1011]:1234567",
+            "www.foo.com:1234567",
+            ":",
+            ":123",
+        ];
+
+        let peerhosts = vec![
+            Some(PeerHost::IP(PeerAddress([0,0,0,0,0,0,0,0,0,0,0xff,0xff,1,2,3,4]), 80)),
+            Some(PeerHost::IP(PeerAddress([0,0,0,0,0,0,0,0,0,0,0xff,0xff,1,2,3,4]), 5678)),
+            Some(PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 80)),
+            Some(PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 12345)),
+            Some(PeerHost::DNS("www.foo.com".to_string(), 80)),
+            Some(PeerHost::DNS("www.foo.com".to_string(), 12345)),
+            Some(PeerHost::DNS("1.2.3.4.5".to_string(), 80)),
+            Some(PeerHost::DNS("[1:203:405:607:809:a0b:c0d:e0f:1011]".to_string(), 80)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None
+        ];
+
+        for (host, expected_host) in hosts.iter().zip(peerhosts.iter()) {
+            let peerhost = match host.parse::<PeerHost>() {
+                Ok(ph) => Some(ph),
+                Err(_) => None
+            };
+
+            match (peerhost, expected_host) {
+                (Some(ref ph), Some(ref expected_ph)) => assert_eq!(*ph, *expected_ph),
+                (None, None) => {},
+                (Some(ph), None) => {
+                    eprintln!("Parsed {} successfully to {:?}, but expected error", host, ph);
+                    assert!(false);
+                }
+                (None, Some(expected_ph)) => {
+                    eprintln!("Failed to parse {} successfully", host);
+                    assert!(false);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_request_type_codec() {
+        let http_request_metadata_ip = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 12345),
+            keep_alive: true,
+            auth_token: None,
+            origin: None,
+            accept_encoding: None,
+        };
+        let http_request_metadata_dns = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+            auth_token: None,
+            origin: None,
+            accept_encoding: None,
+        };
+
+        let tests = vec![
+            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
+            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
+            HttpRequestType::GetMicroblocksIndexed(http_request_metadata_ip.clone(), StacksBlockId([3u8; 32])),
+            HttpRequestType::GetMicroblocksUnconfirmedTip(http_request_metadata_dns.clone(), 7),
+            HttpRequestType::PostTransaction(http_request_metadata_dns.clone(), make_test_transaction(), None),
+            HttpRequestType::PostTransaction(http_request_metadata_ip.clone(), make_test_transaction(), Some(30)),
+            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
+        ];
+
+        let mut tx_body = vec![];
+        make_test_transaction().consensus_serialize(&mut tx_body).unwrap();
+
+        let mut post_transaction_preamble = HttpRequestPreamble::new(HttpVersion::Http11, "POST".to_string(), "/v2/transactions".to_string(), http_request_metadata_dns.peer.hostname(), http_request_metadata_dns.peer.port(), http_request_metadata_dns.keep_alive);
+        post_transaction_preamble.set_content_type(HttpContentType::Bytes);
+        post_transaction_preamble.set_content_length(tx_body.len() as u32);
+
+        // all of these should parse
+        let expected_http_preambles = vec![
+            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), "/v2/neighbors".to_string(), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
+            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), format!("/v2/blocks/{}", StacksBlockId([2u8; 32]).to_hex()), http_request_metadata_dns.peer.hostname(), http_request_metadata_dns.peer.port(), http_request_metadata_dns.keep_alive),
+            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), format!("/v2/microblocks/{}", StacksBlockId([3u8; 32]).to_hex()), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
+            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), "/v2/microblocks/unconfirmed/tip/7".to_string(), http_request_metadata_dns.peer.hostname(), http_request_metadata_dns.peer.port(), http_request_metadata_dns.keep_alive),
+            post_transaction_preamble,
+            HttpRequestPreamble::new(HttpVersion::Http11, "OPTIONS".to_string(), format!("/"), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
+        ];
+
+        let expected_http_bodies = vec![
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            tx_body,
+        ];
+
+        for (test, (expected_http_preamble, expected_http_body)) in tests.iter().zip(expected_http_preambles.iter().zip(expected_http_bodies.iter())) {
+            let mut expected_bytes = vec![];
+            expected_http_preamble.consensus_serialize(&mut expected_bytes).unwrap();
+
+            test_debug!("Expected preamble:\n{}", str::from_utf8(&expected_bytes).unwrap());
+
+            if expected_http_preamble.content_type.is_none() || expected_http_preamble.content_type != Some(HttpContentType::Bytes) {
+                test_debug!("Expected http body:\n{}", str::from_utf8(&expected_http_body).unwrap());
+            }
+            else {
+                test_debug!("Expected http body (hex):\n{}", to_hex(&expected_http_body));
+            }
+
+            expected_bytes.append(&mut expected_http_body.clone());
+            
+            let mut bytes = vec![];
+            let mut http = StacksHttp::new();
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(test.clone())).unwrap();
+
+            assert_eq!(bytes, expected_bytes);
+        }
+    }
+
+    #[test]
+    fn test_http_cors_preflight() {
+        let request = "OPTIONS /v2/info HTTP/1.1\r\nHost: localhost:20443\r\nOrigin: http://localhost:3000\r\nAccess-Control-Request-Method: GET\r\n\r\n";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        let req = match msg {
+            StacksHttpMessage::Request(req) => req,
+            _ => panic!("Did not parse a request: {:?}", &msg)
+        };
+        assert_eq!(req.metadata().origin, Some("http://localhost:3000".to_string()));
+
+        let response = HttpResponseType::OptionsPreflight(HttpResponseMetadata::from(&req));
+        let write_reply = |policy: CORSPolicy, response: &HttpResponseType| {
+            let mut http = StacksHttp::new();
+            http.cors_policy = policy;
+            let mut bytes = vec![];
+            response.send(&mut http, &mut bytes).unwrap();
+            String::from_utf8(bytes).unwrap()
+        };
+
+        // allowed by default
+        let txt = write_reply(CORSPolicy::from_options(&ConnectionOptions::default()), &response);
+        assert!(txt.find("Access-Control-Allow-Origin: http://localhost:3000\r\n").is_some(), "CORS origin is missing");
+        assert!(txt.find("Access-Control-Allow-Methods: POST, GET, OPTIONS\r\n").is_some(), "CORS methods are missing");
+
+        // not allowed
+        let policy = CORSPolicy {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            .. CORSPolicy::allow_all()
+        };
+        let txt = write_reply(policy, &response);
+        assert!(txt.find("Access-Control-Allow-Origin").is_none());
+
+        // anyone's allowed
+        let txt = write_reply(CORSPolicy::allow_all(), &response);
+        assert!(txt.find("Access-Control-Allow-Origin: *\r\n").is_some(), "CORS origin is missing");
+    }
+
+    #[test]
+    fn test_http_parse_ban_peers() {
+        let body = "{\"ip\":\"1.2.3.4\",\"port\":20444}";
+        let requests = vec![
+            (format!("POST /v2/neighbors/ban HTTP/1.1\r\nHost: localhost:6270\r\nAuthorization: Bearer secret\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body), Some("secret".to_string())),
+            (format!("POST /v2/neighbors/ban HTTP/1.1\r\nHost: localhost:6270\r\nX-API-Key: secret\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body), Some("secret".to_string())),
+            (format!("POST /v2/neighbors/ban HTTP/1.1\r\nHost: localhost:6270\r\nAuthorization: Basic c2VjcmV0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body), None),
+            (format!("POST /v2/neighbors/ban HTTP/1.1\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body), None),
+        ];
+
+        for (request, expected_token) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::BanPeers(md, ip, port)) => {
+                    assert_eq!(ip, PeerAddress::from_ipv4(1, 2, 3, 4));
+                    assert_eq!(port, Some(20444));
+                    assert_eq!(md.auth_token, expected_token);
+                    assert_eq!(md.is_authorized("secret"), expected_token.is_some());
+                    assert!(!md.is_authorized("secreT"));
+                    assert!(!md.is_authorized("secret2"));
+                },
+                _ => panic!("Did not parse a BanPeers request: {:?}", &msg)
+            }
+        }
+
+        // round-trips, API key and all
+        let mut md = HttpRequestMetadata::from_host(PeerHost::from_host_port("127.0.0.1".to_string(), 20443));
+        md.auth_token = Some("secret".to_string());
+        let request = HttpRequestType::BanPeers(md, PeerAddress::from_ipv4(5, 6, 7, 8), None);
+        assert!(request.is_privileged());
+
+        let request_bytes = StacksHttp::serialize_request(&request).unwrap();
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(&request_bytes).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request_bytes[offset..]).unwrap();
+        assert_eq!(msg, StacksHttpMessage::Request(request));
+
+        // needs a JSON body
+        let bad_request = "POST /v2/neighbors/ban HTTP/1.1\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &bad_request.as_bytes()[offset..]).is_err());
+    }
+
+    #[test]
+    fn test_http_parse_getblock_by_height() {
+        let tip = StacksBlockId([0x11; 32]);
+        let requests = vec![
+            (format!("GET /v2/blocks/by-height/123 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n"), 123, None),
+            (format!("GET /v2/blocks/by-height/0?tip={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", tip.to_hex()), 0, Some(tip.clone())),
+        ];
+
+        for (request, expected_height, expected_tip) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetBlockByHeight(_, height, tip_opt)) => {
+                    assert_eq!(height, expected_height);
+                    assert_eq!(tip_opt, expected_tip);
+                },
+                _ => panic!("Did not parse a GetBlockByHeight request: {:?}", &msg)
+            }
+        }
+
+        // malformed tip
+        let bad_request = "GET /v2/blocks/by-height/1?tip=zz HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &bad_request.as_bytes()[offset..]).is_err());
+    }
+
+    #[test]
+    fn test_http_parse_get_by_hash() {
+        let block_hash = BlockHeaderHash([0x11; 32]);
+        let request = format!("GET /v2/blocks/by-hash/{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", block_hash.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetBlockByHash(_, hash)) => assert_eq!(hash, block_hash),
+            _ => panic!("Did not parse a GetBlockByHash request: {:?}", &msg)
+        }
+
+        let microblock_hash = BlockHeaderHash([0x22; 32]);
+        let request = format!("GET /v2/microblocks/by-hash/{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", microblock_hash.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetMicroblockByHash(_, hash)) => assert_eq!(hash, microblock_hash),
+            _ => panic!("Did not parse a GetMicroblockByHash request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_export_blocks() {
+        let tip = StacksBlockId([0x33; 32]);
+        let requests = vec![
+            ("GET /v2/export/blocks?from=1&to=100 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), 1, 100, BlockExportFormat::Binary, None),
+            ("GET /v2/export/blocks?from=5&to=5&format=ndjson HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), 5, 5, BlockExportFormat::NDJSON, None),
+            (format!("GET /v2/export/blocks?format=binary&to=20&from=10&tip={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", tip.to_hex()), 10, 20, BlockExportFormat::Binary, Some(tip.clone())),
+        ];
+
+        for (request, expected_start, expected_end, expected_format, expected_tip) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::ExportBlocks(_, start_height, end_height, format, tip_opt)) => {
+                    assert_eq!(start_height, expected_start);
+                    assert_eq!(end_height, expected_end);
+                    assert_eq!(format, expected_format);
+                    assert_eq!(tip_opt, expected_tip);
+                },
+                _ => panic!("Did not parse an ExportBlocks request: {:?}", &msg)
+            }
+        }
+
+        // missing, empty, and oversized ranges, and unknown formats
+        let bad_requests = vec![
+            "GET /v2/export/blocks?from=1 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            "GET /v2/export/blocks?from=0&to=10 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            "GET /v2/export/blocks?from=10&to=9 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            format!("GET /v2/export/blocks?from=1&to={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", RPC_EXPORT_BLOCKS_MAX + 1),
+            "GET /v2/export/blocks?from=1&to=10&format=csv HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+        ];
+
+        for bad_request in bad_requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+            assert!(http.read_payload(&preamble, &bad_request.as_bytes()[offset..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_parse_paginated_requests() {
+        let block_hash = StacksBlockId([0x22; 32]);
+        let cursor = Txid([0x33; 32]);
+
+        let request = format!("GET /v2/blocks/{}/transactions?offset=5&limit=10 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", block_hash.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetBlockTransactions(_, index_block_hash, page_offset, limit)) => {
+                assert_eq!(index_block_hash, block_hash);
+                assert_eq!(page_offset, 5);
+                assert_eq!(limit, 10);
+            },
+            _ => panic!("Did not parse a GetBlockTransactions request: {:?}", &msg)
+        }
+
+        let requests = vec![
+            ("GET /v2/mempool/txids HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), None, RPC_PAGE_LIMIT_DEFAULT),
+            (format!("GET /v2/mempool/txids?cursor={}&limit={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", cursor.to_hex(), RPC_PAGE_LIMIT_MAX), Some(cursor.clone()), RPC_PAGE_LIMIT_MAX),
+        ];
+
+        for (request, expected_cursor, expected_limit) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetMempoolTxids(_, cursor_opt, limit)) => {
+                    assert_eq!(cursor_opt, expected_cursor);
+                    assert_eq!(limit, expected_limit);
+                },
+                _ => panic!("Did not parse a GetMempoolTxids request: {:?}", &msg)
+            }
+        }
+
+        // out-of-range limits and malformed cursors
+        let bad_requests = vec![
+            "GET /v2/mempool/txids?limit=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            format!("GET /v2/mempool/txids?limit={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", RPC_PAGE_LIMIT_MAX + 1),
+            "GET /v2/mempool/txids?cursor=zz HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            format!("GET /v2/blocks/{}/transactions?offset=-1 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", block_hash.to_hex()),
+        ];
+
+        for bad_request in bad_requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+            assert!(http.read_payload(&preamble, &bad_request.as_bytes()[offset..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_request_log_params() {
+        let addr = StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let md = HttpRequestMetadata::new("127.0.0.1".to_string(), 20443);
+
+        let req = HttpRequestType::GetMapEntry(md.clone(), addr.clone(), "hello-world".into(), "units".into(), Value::Int(123), true, TipRequest::canonical());
+        assert_eq!(req.log_params(), "key=123");
+
+        // long arguments get cut short
+        let args = (0..100).map(|i| Value::UInt(i)).collect();
+        let req = HttpRequestType::CallReadOnlyFunction(md.clone(), addr.clone(), "hello-world".into(), addr.to_account_principal(), "get-units".into(), args, false, TipRequest::canonical());
+        let params = req.log_params();
+        assert!(params.starts_with(&format!("sender={} args=[u0, u1, u2, ", &addr)));
+        assert!(params.ends_with("...(488 bytes)]"));
+        assert_eq!(params.len(), format!("sender={} args=[", &addr).len() + RPC_LOG_PARAM_MAX_LEN + "...(488 bytes)]".len());
+
+        let req = HttpRequestType::BanPeers(md.clone(), PeerAddress::from_ipv4(10, 0, 0, 1), Some(20444));
+        assert_eq!(req.log_params(), "addr=10.0.0.1 port=20444");
+
+        let req = HttpRequestType::GetInfo(md.clone());
+        assert_eq!(req.log_params(), "");
+    }
+
+    #[test]
+    fn test_http_parse_get_transfers_by_memo() {
+        let principal = PrincipalData::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap();
+
+        let mut memo_short = TokenTransferMemo([0u8; 34]);
+        memo_short.0[0..4].copy_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+        let memo_full = TokenTransferMemo([0xab; 34]);
+
+        let requests = vec![
+            (format!("GET /v2/accounts/{}/transfers/01020304 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal), memo_short.clone(), RPC_PAGE_LIMIT_DEFAULT),
+            (format!("GET /v2/accounts/{}/transfers/{}?limit=7 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal, to_hex(memo_full.as_bytes())), memo_full.clone(), 7),
+        ];
+
+        for (request, expected_memo, expected_limit) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetTransfersByMemo(_, recipient, memo, limit)) => {
+                    assert_eq!(recipient, principal);
+                    assert_eq!(memo, expected_memo);
+                    assert_eq!(limit, expected_limit);
+                },
+                _ => panic!("Did not parse a GetTransfersByMemo request: {:?}", &msg)
+            }
+        }
+
+        // memos that are too long don't match
+        let request = format!("GET /v2/accounts/{}/transfers/{}00 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal, to_hex(memo_full.as_bytes()));
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::Unmatched(..)) => {},
+            _ => panic!("Parsed an over-long memo: {:?}", &msg)
+        }
+
+        // memos that aren't whole bytes, and bad limits
+        let bad_requests = vec![
+            format!("GET /v2/accounts/{}/transfers/abc HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal),
+            format!("GET /v2/accounts/{}/transfers/01?limit=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal),
+        ];
+
+        for bad_request in bad_requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+            assert!(http.read_payload(&preamble, &bad_request.as_bytes()[offset..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_transaction_receipt() {
+        let txid = Txid([0xab; 32]);
+        let request = format!("GET /v2/transactions/{}/receipt HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", txid.to_hex());
+
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetTransactionReceipt(_, parsed_txid)) => {
+                assert_eq!(parsed_txid, txid);
+            },
+            _ => panic!("Did not parse a GetTransactionReceipt request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_getmempool_stats() {
+        let request = "GET /v2/mempool/stats HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetMempoolStats(..)) => {},
+            _ => panic!("Did not parse a GetMempoolStats request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_getblock_contract_costs() {
+        let index_block_hash = StacksBlockId([0x2; 32]);
+        let request = format!("GET /v2/blocks/{}/costs HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", index_block_hash.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetBlockContractCosts(_, parsed_block_hash)) => {
+                assert_eq!(parsed_block_hash, index_block_hash);
+            },
+            _ => panic!("Did not parse a GetBlockContractCosts request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_receipts() {
+        let index_block_hash = StacksBlockId([0x2; 32]);
+        let request = format!("GET /v2/blocks/{}/receipts HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", index_block_hash.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetBlockReceipts(_, parsed_block_hash)) => {
+                assert_eq!(parsed_block_hash, index_block_hash);
+            },
+            _ => panic!("Did not parse a GetBlockReceipts request: {:?}", &msg)
+        }
+
+        let requests = vec![
+            ("GET /v2/contracts/receipts/ST000000000000000000002AMW42H/pox HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), None, RPC_PAGE_LIMIT_DEFAULT),
+            (format!("GET /v2/contracts/receipts/ST000000000000000000002AMW42H/pox?tip={}&limit=7 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", index_block_hash.to_hex()), Some(index_block_hash.clone()), 7),
+        ];
+        for (request, expected_tip, expected_limit) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetContractReceipts(_, contract_addr, contract_name, parsed_tip, limit)) => {
+                    assert_eq!(contract_addr.to_string(), "ST000000000000000000002AMW42H");
+                    assert_eq!(contract_name.as_str(), "pox");
+                    assert_eq!(parsed_tip, expected_tip);
+                    assert_eq!(limit, expected_limit);
+                },
+                _ => panic!("Did not parse a GetContractReceipts request: {:?}", &msg)
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_stx_supply() {
+        let request = "GET /v2/supply HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetStxSupply(_, None)) => {},
+            _ => panic!("Did not parse a GetStxSupply request: {:?}", &msg)
+        }
+
+        let tip = StacksBlockId([0x22; 32]);
+        let request = format!("GET /v2/supply?tip={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", tip.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetStxSupply(_, Some(parsed_tip))) => {
+                assert_eq!(parsed_tip, tip);
+            },
+            _ => panic!("Did not parse a GetStxSupply request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_balances() {
+        let principal = PrincipalData::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap();
+        let tip = StacksBlockId([0x22; 32]);
+
+        let requests = vec![
+            (format!("GET /v2/accounts/{}/balances HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal), None, RPC_PAGE_LIMIT_DEFAULT),
+            (format!("GET /v2/accounts/{}/balances?tip={}&limit=7 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", &principal, tip.to_hex()), Some(tip.clone()), 7),
+        ];
+        for (request, expected_tip, expected_limit) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetBalanceHistory(_, parsed_principal, parsed_tip, limit)) => {
+                    assert_eq!(parsed_principal, principal);
+                    assert_eq!(parsed_tip, expected_tip);
+                    assert_eq!(limit, expected_limit);
+                },
+                _ => panic!("Did not parse a GetBalanceHistory request: {:?}", &msg)
+            }
+        }
+
+        let request = format!("GET /v2/balances/top?tip={}&limit=3 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", tip.to_hex());
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetTopBalances(_, Some(parsed_tip), 3)) => {
+                assert_eq!(parsed_tip, tip);
+            },
+            _ => panic!("Did not parse a GetTopBalances request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_address_transactions() {
+        let principals = vec![
+            PrincipalData::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap(),
+            PrincipalData::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world").unwrap(),
+        ];
+        let tip = StacksBlockId([0x22; 32]);
+        for principal in principals.iter() {
+            let request = format!("GET /v2/addresses/{}/transactions?tip={}&limit=7 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", principal, tip.to_hex());
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetAddressTransactions(_, parsed_principal, Some(parsed_tip), 7)) => {
+                    assert_eq!(parsed_principal, *principal);
+                    assert_eq!(parsed_tip, tip);
+                },
+                _ => panic!("Did not parse a GetAddressTransactions request: {:?}", &msg)
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_call_read_only_profile() {
+        let body = r#"{"sender":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","arguments":["0x0000000000000000000000000000000001"]}"#;
+        for (query, expected_profile) in [("", false), ("?profile=1", true), ("?profile=0", false)].iter() {
+            let request = format!("POST /v2/contracts/call-read/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/get-units{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                                  query, body.len(), body);
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            let req = match msg {
+                StacksHttpMessage::Request(req) => req,
+                _ => panic!("Did not parse a request: {:?}", &msg)
+            };
+            match req {
+                HttpRequestType::CallReadOnlyFunction(_, _, _, _, ref func_name, ref args, with_profile, _) => {
+                    assert_eq!(func_name.as_str(), "get-units");
+                    assert_eq!(args, &vec![Value::Int(1)]);
+                    assert_eq!(with_profile, *expected_profile);
+                },
+                _ => panic!("Did not parse a CallReadOnlyFunction request: {:?}", &req)
+            }
+            assert_eq!(req.request_path(), format!("/v2/contracts/call-read/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/get-units{}",
+                                                   if *expected_profile { "?profile=1" } else { "" }));
+        }
+    }
+
+    #[test]
+    fn test_http_parse_tip_request() {
+        let tip = StacksBlockId([0x22; 32]);
+        let call_body = r#"{"arguments":[]}"#;
+        let key_body = r#""0x0000000000000000000000000000000001""#;
+        let tests = vec![
+            ("".to_string(), Some(TipRequest::canonical())),
+            (format!("?tip={}", tip.to_hex()), Some(TipRequest { tip: Some(tip.clone()), height: None })),
+            ("?height=12".to_string(), Some(TipRequest { tip: None, height: Some(12) })),
+            (format!("?height=12&tip={}", tip.to_hex()), Some(TipRequest { tip: Some(tip.clone()), height: Some(12) })),
+            ("?height=-1".to_string(), None),
+            ("?tip=zz".to_string(), None),
+        ];
+
+        for (query, expected_tip_request) in tests.iter() {
+            let requests = vec![
+                format!("GET /v2/accounts/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", query),
+                format!("POST /v2/map_entry/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/owners{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        query, key_body.len(), key_body),
+                format!("POST /v2/contracts/call-read/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/get-units{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        query, call_body.len(), call_body),
+                format!("GET /v2/data_var/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/total{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", query),
+                format!("GET /v2/ft_balance/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/stackaroos/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", query),
+                format!("POST /v2/nft_owner/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/names{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        query, key_body.len(), key_body),
+            ];
+            for request in requests.iter() {
+                let mut http = StacksHttp::new();
+                let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+                let res = http.read_payload(&preamble, &request.as_bytes()[offset..]);
+                let req = match (res, expected_tip_request) {
+                    (Ok((StacksHttpMessage::Request(req), _)), Some(_)) => req,
+                    (Err(_), None) => continue,
+                    (res, _) => panic!("Unexpected result for {}: {:?}", request, &res)
+                };
+                let tip_request = match req {
+                    HttpRequestType::GetAccount(_, _, _, ref tip_request) |
+                    HttpRequestType::GetMapEntry(_, _, _, _, _, _, ref tip_request) |
+                    HttpRequestType::GetDataVar(_, _, _, _, _, ref tip_request) |
+                    HttpRequestType::GetFungibleTokenBalance(_, _, _, _, _, _, ref tip_request) |
+                    HttpRequestType::GetNonFungibleTokenOwner(_, _, _, _, _, _, ref tip_request) |
+                    HttpRequestType::CallReadOnlyFunction(_, _, _, _, _, _, _, ref tip_request) => tip_request.clone(),
+                    _ => panic!("Unexpected request for {}: {:?}", request, &req)
+                };
+                assert_eq!(Some(&tip_request), expected_tip_request.as_ref());
+
+                // the request's path asks for the same block
+                let path = req.request_path();
+                let path_query = path.find('?').map(|i| &path[(i + 1)..]);
+                assert_eq!(HttpRequestType::get_tip_request_query(path_query).unwrap(), tip_request);
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_call_read_only_sender() {
+        let contract_addr = StacksAddress::from_string("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap();
+        let contract_principal = PrincipalData::Contract(QualifiedContractIdentifier::new(contract_addr.into(), "hello-world".into()));
+        let tests = vec![
+            (r#"{"sender":"SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B","arguments":[]}"#,
+             Some(PrincipalData::parse("SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B").unwrap())),
+            (r#"{"sender":"SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B.get-info","arguments":[]}"#,
+             Some(PrincipalData::parse("SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B.get-info").unwrap())),
+            // no sender, so the contract calls itself
+            (r#"{"arguments":[]}"#, Some(contract_principal.clone())),
+            (r#"{"sender":null,"arguments":[]}"#, Some(contract_principal.clone())),
+            (r#"{"sender":"not-a-principal","arguments":[]}"#, None),
+        ];
+
+        for (body, expected_sender) in tests.iter() {
+            let request = format!("POST /v2/contracts/call-read/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/get-units HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                                  body.len(), body);
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let res = http.read_payload(&preamble, &request.as_bytes()[offset..]);
+            match (res, expected_sender) {
+                (Ok((StacksHttpMessage::Request(HttpRequestType::CallReadOnlyFunction(_, _, _, sender, _, _, _, _)), _)), Some(expected_sender)) => {
+                    assert_eq!(&sender, expected_sender);
+                },
+                (Err(_), None) => {},
+                (res, _) => panic!("Unexpected result for {}: {:?}", body, &res)
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_json_clarity_values() {
+        let int_json = r#"{"type":"int","value":"1"}"#;
+        let tuple_json = r#"{"type":"tuple","value":{"id":{"type":"uint","value":"7"},"owner":{"type":"some","value":{"type":"principal","value":"SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B"}}}}"#;
+        let tuple = Value::from(TupleData::from_data(vec![
+            ("id".into(), Value::UInt(7)),
+            ("owner".into(), Value::some(Value::from(PrincipalData::parse("SP2JXKMSH007NPYAQHKJPQMAQYAD90NQGTVJVQ02B").unwrap())).unwrap())]).unwrap());
+
+        // hex and JSON arguments can be mixed
+        let tests = vec![
+            (format!(r#"{{"arguments":[{},"0x0000000000000000000000000000000001",{}]}}"#, int_json, tuple_json),
+             Some(vec![Value::Int(1), Value::Int(1), tuple.clone()])),
+            (r#"{"arguments":[{"type":"int","value":1}]}"#.to_string(), None),
+            (r#"{"arguments":[1]}"#.to_string(), None),
+        ];
+        for (body, expected_args) in tests.iter() {
+            let request = format!("POST /v2/contracts/call-read/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/get-units HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                                  body.len(), body);
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let res = http.read_payload(&preamble, &request.as_bytes()[offset..]);
+            match (res, expected_args) {
+                (Ok((StacksHttpMessage::Request(HttpRequestType::CallReadOnlyFunction(_, _, _, _, _, args, _, _)), _)), Some(expected_args)) => {
+                    assert_eq!(&args, expected_args);
+                },
+                (Err(_), None) => {},
+                (res, _) => panic!("Unexpected result for {}: {:?}", body, &res)
+            }
+        }
+
+        let request = format!("POST /v2/map_entry/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/owners HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              tuple_json.len(), tuple_json);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        match msg {
+            StacksHttpMessage::Request(HttpRequestType::GetMapEntry(_, _, _, ref map_name, ref key, _, _)) => {
+                assert_eq!(map_name.as_str(), "owners");
+                assert_eq!(key, &tuple);
+            },
+            _ => panic!("Did not parse a GetMapEntry request: {:?}", &msg)
+        }
+    }
+
+    #[test]
+    fn test_http_parse_contract_state_requests() {
+        let asset_json = r#"{"type":"uint","value":"12"}"#;
+        let requests = vec![
+            "GET /v2/data_var/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/total HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            "GET /v2/ft_balance/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/stackaroos/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R?proof=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            format!("POST /v2/nft_owner/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/names HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    asset_json.len(), asset_json),
         ];
 
-        let peerhosts = vec![
-            Some(PeerHost::IP(PeerAddress([0,0,0,0,0,0,0,0,0,0,0xff,0xff,1,2,3,4]), 80)),
-            Some(PeerHost::IP(PeerAddress([0,0,0,0,0,0,0,0,0,0,0xff,0xff,1,2,3,4]), 5678)),
-            Some(PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 80)),
-            Some(PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 12345)),
-            Some(PeerHost::DNS("www.foo.com".to_string(), 80)),
-            Some(PeerHost::DNS("www.foo.com".to_string(), 12345)),
-            Some(PeerHost::DNS("1.2.3.4.5".to_string(), 80)),
-            Some(PeerHost::DNS("[1:203:405:607:809:a0b:c0d:e0f:1011]".to_string(), 80)),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None
-        ];
+        let mut parsed = vec![];
+        for request in requests.iter() {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(req) => parsed.push(req),
+                _ => panic!("Did not parse a request: {:?}", &msg)
+            }
+        }
 
-        for (host, expected_host) in hosts.iter().zip(peerhosts.iter()) {
-            let peerhost = match host.parse::<PeerHost>() {
-                Ok(ph) => Some(ph),
-                Err(_) => None
-            };
+        match parsed[0] {
+            HttpRequestType::GetDataVar(_, _, ref contract_name, ref var_name, with_proof, _) => {
+                assert_eq!(contract_name.as_str(), "hello-world");
+                assert_eq!(var_name.as_str(), "total");
+                assert!(with_proof);
+            },
+            _ => panic!("Did not parse a GetDataVar request: {:?}", &parsed[0])
+        }
+        assert_eq!(parsed[0].request_path(), "/v2/data_var/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/total");
 
-            match (peerhost, expected_host) {
-                (Some(ref ph), Some(ref expected_ph)) => assert_eq!(*ph, *expected_ph),
-                (None, None) => {},
-                (Some(ph), None) => {
-                    eprintln!("Parsed {} successfully to {:?}, but expected error", host, ph);
-                    assert!(false);
-                }
-                (None, Some(expected_ph)) => {
-                    eprintln!("Failed to parse {} successfully", host);
-                    assert!(false);
-                }
-            }
+        match parsed[1] {
+            HttpRequestType::GetFungibleTokenBalance(_, _, _, ref token_name, ref principal, with_proof, _) => {
+                assert_eq!(token_name.as_str(), "stackaroos");
+                assert_eq!(principal, &PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap());
+                assert!(!with_proof);
+            },
+            _ => panic!("Did not parse a GetFungibleTokenBalance request: {:?}", &parsed[1])
+        }
+
+        match parsed[2] {
+            HttpRequestType::GetNonFungibleTokenOwner(_, _, _, ref asset_name, ref asset, with_proof, _) => {
+                assert_eq!(asset_name.as_str(), "names");
+                assert_eq!(asset, &Value::UInt(12));
+                assert!(with_proof);
+            },
+            _ => panic!("Did not parse a GetNonFungibleTokenOwner request: {:?}", &parsed[2])
         }
+        assert_eq!(parsed[2].log_params(), "asset=u12");
+
+        // the asset must be given
+        let request = "POST /v2/nft_owner/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/hello-world/names HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: 0\r\n\r\n";
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &request.as_bytes()[offset..]).is_err());
     }
 
     #[test]
-    fn test_http_request_type_codec() {
-        let http_request_metadata_ip = HttpRequestMetadata {
-            version: HttpVersion::Http11,
-            peer: PeerHost::IP(PeerAddress([0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]), 12345),
-            keep_alive: true,
+    fn test_http_parse_analyze_contract() {
+        let body = r#"{"deployer":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","name":"hello-world","source":"(define-read-only (get-units) (ok u1))"}"#;
+        let request = format!("POST /v2/contracts/analyze HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        let req = match msg {
+            StacksHttpMessage::Request(req) => req,
+            _ => panic!("Did not parse a request: {:?}", &msg)
         };
-        let http_request_metadata_dns = HttpRequestMetadata {
-            version: HttpVersion::Http11,
-            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
-            keep_alive: true,
+        match req {
+            HttpRequestType::AnalyzeContract(_, ref deployer, ref contract_name, ref source) => {
+                assert_eq!(deployer.to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY");
+                assert_eq!(contract_name.as_str(), "hello-world");
+                assert_eq!(source, "(define-read-only (get-units) (ok u1))");
+            },
+            _ => panic!("Did not parse an AnalyzeContract request: {:?}", &req)
+        }
+        assert_eq!(req.request_path(), "/v2/contracts/analyze");
+
+        // bad contract name
+        let body = r#"{"deployer":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","name":"hello world","source":"(ok u1)"}"#;
+        let request = format!("POST /v2/contracts/analyze HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &request.as_bytes()[offset..]).is_err());
+    }
+
+    #[test]
+    fn test_http_parse_dry_run_contract() {
+        let body = r#"{"deployer":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","name":"hello-world","source":"(define-read-only (get-units) (ok u1))"}"#;
+        let request = format!("POST /v2/contracts/dry-run HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        let req = match msg {
+            StacksHttpMessage::Request(req) => req,
+            _ => panic!("Did not parse a request: {:?}", &msg)
         };
+        match req {
+            HttpRequestType::DryRunContract(_, ref deployer, ref contract_name, ref source) => {
+                assert_eq!(deployer.to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY");
+                assert_eq!(contract_name.as_str(), "hello-world");
+                assert_eq!(source, "(define-read-only (get-units) (ok u1))");
+            },
+            _ => panic!("Did not parse a DryRunContract request: {:?}", &req)
+        }
+        assert_eq!(req.request_path(), "/v2/contracts/dry-run");
 
-        let tests = vec![
-            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
-            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
-            HttpRequestType::GetMicroblocksIndexed(http_request_metadata_ip.clone(), StacksBlockId([3u8; 32])),
-            HttpRequestType::PostTransaction(http_request_metadata_dns.clone(), make_test_transaction()),
-            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
-        ];
+        // not JSON
+        let request = format!("POST /v2/contracts/dry-run HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &request.as_bytes()[offset..]).is_err());
+    }
 
-        let mut tx_body = vec![];
-        make_test_transaction().consensus_serialize(&mut tx_body).unwrap();
+    #[test]
+    fn test_http_parse_estimate_fee() {
+        let recipient = StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let payload = TransactionPayload::TokenTransfer(recipient.into(), 123, TokenTransferMemo([0u8; 34]));
+        let body = format!(r#"{{"sender":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","payload":"0x{}"}}"#, to_hex(&payload.serialize_to_vec()));
+        let request = format!("POST /v2/fees/estimate HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        let req = match msg {
+            StacksHttpMessage::Request(req) => req,
+            _ => panic!("Did not parse a request: {:?}", &msg)
+        };
+        match req {
+            HttpRequestType::EstimateFee(_, ref sender, ref parsed_payload) => {
+                assert_eq!(sender.to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY");
+                assert_eq!(*parsed_payload, payload);
+            },
+            _ => panic!("Did not parse an EstimateFee request: {:?}", &req)
+        }
+        assert_eq!(req.request_path(), "/v2/fees/estimate");
 
-        let mut post_transaction_preamble = HttpRequestPreamble::new(HttpVersion::Http11, "POST".to_string(), "/v2/transactions".to_string(), http_request_metadata_dns.peer.hostname(), http_request_metadata_dns.peer.port(), http_request_metadata_dns.keep_alive);
-        post_transaction_preamble.set_content_type(HttpContentType::Bytes);
-        post_transaction_preamble.set_content_length(tx_body.len() as u32);
+        // not a payload
+        let body = r#"{"sender":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","payload":"0x0102"}"#;
+        let request = format!("POST /v2/fees/estimate HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &request.as_bytes()[offset..]).is_err());
+    }
 
-        // all of these should parse
-        let expected_http_preambles = vec![
-            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), "/v2/neighbors".to_string(), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
-            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), format!("/v2/blocks/{}", StacksBlockId([2u8; 32]).to_hex()), http_request_metadata_dns.peer.hostname(), http_request_metadata_dns.peer.port(), http_request_metadata_dns.keep_alive),
-            HttpRequestPreamble::new(HttpVersion::Http11, "GET".to_string(), format!("/v2/microblocks/{}", StacksBlockId([3u8; 32]).to_hex()), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
-            post_transaction_preamble,
-            HttpRequestPreamble::new(HttpVersion::Http11, "OPTIONS".to_string(), format!("/"), http_request_metadata_ip.peer.hostname(), http_request_metadata_ip.peer.port(), http_request_metadata_ip.keep_alive),
+    #[test]
+    fn test_http_parse_simulate_transaction() {
+        let contract_addr = StacksAddress::from_string("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap();
+        let payload = TransactionPayload::new_contract_call(contract_addr, "hello-world", "spend", vec![Value::UInt(100)]).unwrap();
+        let body = format!(r#"{{"sender":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","payload":"0x{}","overrides":[
+            {{"type":"stx_balance","principal":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","amount":"1000000"}},
+            {{"type":"ft_balance","contract":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world","token":"coin","principal":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world","amount":"5"}},
+            {{"type":"map_entry","contract":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world","map":"limits","key":"0x0100000000000000000000000000000001","value":"0x0100000000000000000000000000000002"}},
+            {{"type":"map_entry","contract":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world","map":"limits","key":"0x0100000000000000000000000000000003"}}]}}"#,
+            to_hex(&payload.serialize_to_vec()));
+        let request = format!("POST /v2/transactions/simulate HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+        let req = match msg {
+            StacksHttpMessage::Request(req) => req,
+            _ => panic!("Did not parse a request: {:?}", &msg)
+        };
+
+        let principal = PrincipalData::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY").unwrap();
+        let contract_id = QualifiedContractIdentifier::parse("SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world").unwrap();
+        let expected_overrides = vec![
+            StateOverride::StxBalance(principal, 1000000),
+            StateOverride::FungibleTokenBalance(contract_id.clone(), "coin".into(), PrincipalData::Contract(contract_id.clone()), 5),
+            StateOverride::MapEntry(contract_id.clone(), "limits".into(), Value::UInt(1), Some(Value::UInt(2))),
+            StateOverride::MapEntry(contract_id.clone(), "limits".into(), Value::UInt(3), None),
         ];
+        match req {
+            HttpRequestType::SimulateTransaction(_, ref sender, ref parsed_payload, ref overrides) => {
+                assert_eq!(sender.to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY");
+                assert_eq!(*parsed_payload, payload);
+                assert_eq!(*overrides, expected_overrides);
+                for state_override in overrides.iter() {
+                    let body = HttpRequestType::state_override_body(state_override);
+                    assert_eq!(HttpRequestType::parse_state_override(&body).unwrap(), *state_override);
+                }
+            },
+            _ => panic!("Did not parse a SimulateTransaction request: {:?}", &req)
+        }
+        assert_eq!(req.request_path(), "/v2/transactions/simulate");
+
+        // amounts have to be decimal numbers
+        let body = format!(r#"{{"sender":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","payload":"0x{}","overrides":[
+            {{"type":"stx_balance","principal":"SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY","amount":"0x10"}}]}}"#,
+            to_hex(&payload.serialize_to_vec()));
+        let request = format!("POST /v2/transactions/simulate HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                              body.len(), body);
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http.read_payload(&preamble, &request.as_bytes()[offset..]).is_err());
+    }
 
-        let expected_http_bodies = vec![
-            vec![],
-            vec![],
-            vec![],
-            tx_body,
+    #[test]
+    fn test_http_parse_search_hash_prefix() {
+        let requests = vec![
+            ("GET /v2/search/abcd HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), "abcd", RPC_PAGE_LIMIT_DEFAULT),
+            (format!("GET /v2/search/{}?limit=5 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", Txid([0xab; 32]).to_hex()), "abababababababababababababababababababababababababababababababab", 5),
+            ("GET /v2/search/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY", RPC_PAGE_LIMIT_DEFAULT),
+            ("GET /v2/search/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(), "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY.hello-world", RPC_PAGE_LIMIT_DEFAULT),
         ];
 
-        for (test, (expected_http_preamble, expected_http_body)) in tests.iter().zip(expected_http_preambles.iter().zip(expected_http_bodies.iter())) {
-            let mut expected_bytes = vec![];
-            expected_http_preamble.consensus_serialize(&mut expected_bytes).unwrap();
+        for (request, expected_prefix, expected_limit) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::SearchHashPrefix(_, prefix, limit)) => {
+                    assert_eq!(prefix, expected_prefix);
+                    assert_eq!(limit, expected_limit);
+                },
+                _ => panic!("Did not parse a SearchHashPrefix request: {:?}", &msg)
+            }
+        }
 
-            test_debug!("Expected preamble:\n{}", str::from_utf8(&expected_bytes).unwrap());
+        // prefixes that are too short, too long, or neither lowercase hex nor a principal don't match
+        let unmatched = vec![
+            "GET /v2/search/abc HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+            format!("GET /v2/search/{}00 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", Txid([0xab; 32]).to_hex()),
+            "GET /v2/search/ABCD HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n".to_string(),
+        ];
 
-            if expected_http_preamble.content_type.is_none() || expected_http_preamble.content_type != Some(HttpContentType::Bytes) {
-                test_debug!("Expected http body:\n{}", str::from_utf8(&expected_http_body).unwrap());
+        for request in unmatched {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::Unmatched(..)) => {},
+                _ => panic!("Parsed a bad hash prefix: {:?}", &msg)
             }
-            else {
-                test_debug!("Expected http body (hex):\n{}", to_hex(&expected_http_body));
+        }
+    }
+
+    #[test]
+    fn test_http_parse_getstatus() {
+        let requests = vec![
+            ("GET /v2/status HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", false),
+            ("GET /v2/status?require_ready=1 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", true),
+            ("GET /v2/status?require_ready=true HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", true),
+            ("GET /v2/status?require_ready=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\n\r\n", false),
+        ];
+
+        for (request, expected_require_ready) in requests {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (msg, _) = http.read_payload(&preamble, &request.as_bytes()[offset..]).unwrap();
+            match msg {
+                StacksHttpMessage::Request(HttpRequestType::GetStatus(_, require_ready)) => {
+                    assert_eq!(require_ready, expected_require_ready);
+                },
+                _ => panic!("Did not parse a GetStatus request: {:?}", &msg)
             }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_posttransaction_wait() {
+        let mut tx_bytes = vec![];
+        make_test_transaction().consensus_serialize(&mut tx_bytes).unwrap();
+        let too_long = format!("/v2/transactions?wait=true&timeout={}", RPC_TX_WAIT_TIMEOUT_MAX + 1);
+
+        let requests = vec![
+            ("/v2/transactions", Some(None)),
+            ("/v2/transactions?wait=false&timeout=30", Some(None)),
+            ("/v2/transactions?wait=true", Some(Some(RPC_TX_WAIT_TIMEOUT_DEFAULT))),
+            ("/v2/transactions?wait=1&timeout=30", Some(Some(30))),
+            ("/v2/transactions?wait=true&timeout=0", None),
+            ("/v2/transactions?wait=true&timeout=abc", None),
+            (too_long.as_str(), None),
+        ];
+
+        for (path, expected_wait) in requests {
+            let mut request = format!("POST {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:6270\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n", path, tx_bytes.len()).into_bytes();
+            request.extend_from_slice(&tx_bytes);
 
-            expected_bytes.append(&mut expected_http_body.clone());
-            
-            let mut bytes = vec![];
             let mut http = StacksHttp::new();
-            http.write_message(&mut bytes, &StacksHttpMessage::Request(test.clone())).unwrap();
+            let (preamble, offset) = http.read_preamble(&request).unwrap();
+            match (http.read_payload(&preamble, &request[offset..]), expected_wait) {
+                (Ok((StacksHttpMessage::Request(HttpRequestType::PostTransaction(_, _, wait_timeout)), _)), Some(expected_wait_timeout)) => {
+                    assert_eq!(wait_timeout, expected_wait_timeout);
+                },
+                (Err(_), None) => {},
+                (res, _) => panic!("Unexpected parse of {}: {:?}", path, &res)
+            }
+        }
+    }
 
-            assert_eq!(bytes, expected_bytes);
+    #[test]
+    fn test_http_request_priority() {
+        let md = HttpRequestMetadata::new("127.0.0.1".to_string(), 20443);
+        let getblock = HttpRequestType::GetBlock(md.clone(), StacksBlockId([0x11; 32]));
+        let getinfo = HttpRequestType::GetInfo(md.clone());
+        let getsrc = HttpRequestType::GetContractSrc(md.clone(), StacksAddress { version: 1, bytes: Hash160([0x22; 20]) }, "hello-world".into(), false);
+
+        assert_eq!(getblock.priority(&[]), RequestPriority::Critical);
+        assert_eq!(getinfo.priority(&[]), RequestPriority::Normal);
+        assert_eq!(getsrc.priority(&[]), RequestPriority::Low);
+
+        let overrides = vec![("GetContractSrc".to_string(), RequestPriority::Critical), ("GetInfo".to_string(), RequestPriority::Low)];
+        assert_eq!(getblock.priority(&overrides), RequestPriority::Critical);
+        assert_eq!(getinfo.priority(&overrides), RequestPriority::Low);
+        assert_eq!(getsrc.priority(&overrides), RequestPriority::Critical);
+
+        for req in [getblock, getinfo, getsrc].iter() {
+            assert!(HttpRequestType::is_request_class(req.request_class()));
         }
+        assert!(!HttpRequestType::is_request_class("GetContractSource"));
     }
 
     #[test]
@@ -3235,6 +6144,203 @@ mod test {
             outbound: vec![]
         };
 
+        let mut test_contract_interface = ContractInterface::new();
+        test_contract_interface.maps.push(ContractInterfaceMap {
+            name: "balances".to_string(),
+            key: vec![ContractInterfaceTupleEntryType { name: "owner".to_string(), type_f: ContractInterfaceAtomType::principal }],
+            value: vec![ContractInterfaceTupleEntryType { name: "balance".to_string(), type_f: ContractInterfaceAtomType::uint128 }],
+        });
+        test_contract_interface.fungible_tokens.push(ContractInterfaceFungibleTokens { name: "stackaroos".to_string() });
+
+        let test_contract_src = ContractSrcResponse {
+            source: "(define-read-only (hello) \"world\")".to_string(),
+            publish_height: 7,
+            marf_proof: Some("0123".to_string())
+        };
+
+        let test_trait_implementors = TraitImplementorsResponse {
+            trait_identifier: "ST000000000000000000002AMW42H.nft-trait.nft-trait".to_string(),
+            contracts: vec!["ST000000000000000000002AMW42H.stackaroos".to_string()]
+        };
+
+        let test_contract_dependencies = ContractDependenciesResponse {
+            contract: "ST000000000000000000002AMW42H.names".to_string(),
+            calls: Some(vec!["ST000000000000000000002AMW42H.tokens".to_string()]),
+            called_by: vec![]
+        };
+
+        let test_contract_analysis = AnalyzeContractResponse {
+            okay: true,
+            contract_id: "ST000000000000000000002AMW42H.pox".to_string(),
+            interface: Some(test_contract_interface.clone()),
+            defined_traits: vec![],
+            implemented_traits: vec!["ST000000000000000000002AMW42H.nft-trait.nft-trait".to_string()],
+            cost: Some(ExecutionCost { runtime: 100, write_length: 0, write_count: 0, read_length: 0, read_count: 0 }),
+            cause: None,
+            diagnostic: None,
+            errors: vec![],
+            warnings: vec![]
+        };
+
+        let test_contract_dry_run = DryRunContractResponse {
+            okay: false,
+            contract_id: "ST000000000000000000002AMW42H.pox".to_string(),
+            name_collision: true,
+            interface: Some(test_contract_interface.clone()),
+            defined_traits: vec![],
+            implemented_traits: vec![],
+            analysis_cost: Some(ExecutionCost { runtime: 100, write_length: 0, write_count: 0, read_length: 0, read_count: 0 }),
+            deploy_cost: None,
+            cause: Some("Duplicate contract 'ST000000000000000000002AMW42H.pox'".to_string()),
+            diagnostic: None,
+            errors: vec![],
+            warnings: vec![]
+        };
+
+        let test_fee_estimate = FeeEstimateResponse {
+            okay: true,
+            cost: Some(ExecutionCost { runtime: 100, write_length: 10, write_count: 1, read_length: 20, read_count: 2 }),
+            result: Some("0x0703".to_string()),
+            result_json: Some(Value::okay_true().json_serialize()),
+            estimated_len: 180,
+            suggested_fee: 180,
+            cause: None,
+        };
+
+        let test_simulation = SimulateTransactionResponse {
+            okay: true,
+            cost: Some(ExecutionCost { runtime: 100, write_length: 10, write_count: 1, read_length: 20, read_count: 2 }),
+            result: Some("0x0703".to_string()),
+            result_json: Some(Value::okay_true().json_serialize()),
+            events: vec![json!({
+                "type": "stx_transfer_event",
+                "stx_transfer_event": {
+                    "sender": "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY",
+                    "recipient": "ST000000000000000000002AMW42H",
+                    "amount": "100"
+                }
+            })],
+            cause: None,
+        };
+
+        let test_receipt = RPCTransactionReceipt {
+            txid: Txid([0x1; 32]).to_hex(),
+            status: "anchored".to_string(),
+            index_block_hash: Some(StacksBlockId([0x2; 32]).to_hex()),
+            burn_header_hash: Some(BurnchainHeaderHash([0x3; 32]).to_hex()),
+            block_hash: Some(BlockHeaderHash([0x4; 32])),
+            block_height: Some(5),
+            tx_index: Some(1)
+        };
+
+        let test_mined_receipt = MinedTransactionReceiptData {
+            txid: Txid([0x1; 32]).to_hex(),
+            status: "success".to_string(),
+            result: "0x0703".to_string(),
+            result_json: Value::okay_true().json_serialize(),
+            events: vec![json!({ "type": "contract_event", "committed": true })],
+            abort_code: None,
+            block_hash: BlockHeaderHash([0x4; 32]).to_hex(),
+            burn_header_hash: BurnchainHeaderHash([0x3; 32]).to_hex(),
+            index_block_hash: StacksBlockId([0x2; 32]).to_hex(),
+            block_height: 5
+        };
+
+        let test_stx_supply = StxSupplyData {
+            total_stx: "1000500".to_string(),
+            unlocked_stx: "1000000".to_string(),
+            locked_stx: "500".to_string(),
+            index_block_hash: StacksBlockId([0x2; 32]).to_hex(),
+            block_height: 5
+        };
+
+        let test_balance_history = vec![BalanceHistoryEntry {
+            balance: "1000000".to_string(),
+            nonce: 3,
+            index_block_hash: StacksBlockId([0x2; 32]).to_hex(),
+            block_height: 5
+        }];
+
+        let test_top_balances = vec![AccountBalanceData {
+            principal: "SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY".to_string(),
+            balance: "1000000".to_string(),
+            nonce: 3
+        }];
+
+        let test_address_transactions = vec![AddressTransactionData {
+            txid: Txid([0x1; 32]).to_hex(),
+            index_block_hash: StacksBlockId([0x2; 32]).to_hex(),
+            block_height: 5,
+            tx_index: 1
+        }];
+
+        let test_contract_costs = BlockContractCostsData {
+            index_block_hash: StacksBlockId([0x2; 32]).to_hex(),
+            block_height: 5,
+            block_limit: ExecutionCost::max_value(),
+            metered: true,
+            contracts: vec![
+                ContractCostData {
+                    contract_id: "ST000000000000000000002AMW42H.pox".to_string(),
+                    transactions: 2,
+                    cost: ExecutionCost { runtime: 100, read_count: 2, read_length: 20, write_count: 1, write_length: 10 }
+                }
+            ]
+        };
+
+        let test_prefix_search = HashPrefixSearchData {
+            prefix: "0101".to_string(),
+            matches: vec![
+                HashPrefixMatch {
+                    match_type: "transaction".to_string(),
+                    field: "txid".to_string(),
+                    hash: Txid([0x1; 32]).to_hex(),
+                    index_block_hash: Some(StacksBlockId([0x2; 32]).to_hex()),
+                    block_height: Some(5),
+                    canonical: Some(true),
+                    balance: None,
+                    nonce: None
+                },
+                HashPrefixMatch {
+                    match_type: "mempool_transaction".to_string(),
+                    field: "txid".to_string(),
+                    hash: Txid([0x1; 32]).to_hex(),
+                    index_block_hash: None,
+                    block_height: None,
+                    canonical: None,
+                    balance: None,
+                    nonce: None
+                },
+                HashPrefixMatch {
+                    match_type: "burn_block".to_string(),
+                    field: "burn_header_hash".to_string(),
+                    hash: BurnchainHeaderHash([0x1; 32]).to_hex(),
+                    index_block_hash: Some(StacksBlockId([0x2; 32]).to_hex()),
+                    block_height: Some(105),
+                    canonical: Some(false),
+                    balance: None,
+                    nonce: None
+                }
+            ],
+            ambiguous: false,
+            truncated: false
+        };
+
+        let test_mempool_stats = MemPoolStats {
+            tx_count: 2,
+            total_bytes: 360,
+            min_fee_rate: Some(1),
+            max_fee_rate: Some(3),
+            fee_rate_percentiles: vec![
+                MemPoolFeeRatePercentile { percentile: 50, fee_rate: 1 },
+                MemPoolFeeRatePercentile { percentile: 95, fee_rate: 3 },
+            ],
+            age_distribution: vec![
+                MemPoolAgeBucket { max_age: Some(60), tx_count: 2 },
+                MemPoolAgeBucket { max_age: None, tx_count: 0 },
+            ]
+        };
+
         let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
         let test_block_info = make_codec_test_block(5);
         let test_microblock_info = make_sample_microblock_stream(&privk, &test_block_info.block_hash());
@@ -3245,6 +6351,13 @@ mod test {
         let mut test_microblock_info_bytes = vec![];
         test_microblock_info.consensus_serialize(&mut test_microblock_info_bytes).unwrap();
 
+        let mut test_single_microblock_bytes = vec![];
+        test_microblock_info[0].consensus_serialize(&mut test_single_microblock_bytes).unwrap();
+
+        let mut test_exported_blocks_bytes = vec![];
+        test_exported_blocks_bytes.extend_from_slice(&(test_block_info_bytes.len() as u32).to_be_bytes());
+        test_exported_blocks_bytes.extend_from_slice(&test_block_info_bytes);
+
         let tests = vec![
             // length is known
             (HttpResponseType::Neighbors(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(serde_json::to_string(&test_neighbors_info).unwrap().len() as u32), true), test_neighbors_info.clone()), "/v2/neighbors".to_string()),
@@ -3257,6 +6370,30 @@ mod test {
             (HttpResponseType::Block(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_block_info.clone()), format!("/v2/blocks/{}", test_block_info.block_hash().to_hex())),
             (HttpResponseType::Microblocks(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_microblock_info.clone()), format!("/v2/microblocks/{}", test_microblock_info[0].block_hash().to_hex())),
             (HttpResponseType::TransactionID(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), Txid([0x1; 32])), "/v2/transactions".to_string()),
+            (HttpResponseType::TransactionReceipt(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_receipt.clone()), "/v2/transactions?wait=true&timeout=30".to_string()),
+            (HttpResponseType::PeersBanned(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), RPCBanPeersData { banned: 2 }), "/v2/neighbors/ban".to_string()),
+            (HttpResponseType::GetContractABI(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_interface.clone()), "/v2/contracts/interface/ST000000000000000000002AMW42H/pox".to_string()),
+            (HttpResponseType::GetContractSrc(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_src.clone()), "/v2/contracts/source/ST000000000000000000002AMW42H/pox?proof=1".to_string()),
+            (HttpResponseType::GetTraitImplementors(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_trait_implementors.clone()), "/v2/traits/ST000000000000000000002AMW42H/nft-trait/nft-trait/implementors".to_string()),
+            (HttpResponseType::GetContractDependencies(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_dependencies.clone()), "/v2/contracts/dependencies/ST000000000000000000002AMW42H/names".to_string()),
+            (HttpResponseType::AnalyzeContract(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_analysis.clone()), "/v2/contracts/analyze".to_string()),
+            (HttpResponseType::DryRunContract(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_dry_run.clone()), "/v2/contracts/dry-run".to_string()),
+            (HttpResponseType::FeeEstimate(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_fee_estimate.clone()), "/v2/fees/estimate".to_string()),
+            (HttpResponseType::SimulateTransaction(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_simulation.clone()), "/v2/transactions/simulate".to_string()),
+            (HttpResponseType::OpenAPI(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), json!({ "openapi": "3.0.3", "paths": { "/v2/info": {} } })), "/v2/openapi.json".to_string()),
+            (HttpResponseType::MinedTransactionReceipt(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_mined_receipt.clone()), format!("/v2/transactions/{}/receipt", Txid([0x1; 32]).to_hex())),
+            (HttpResponseType::HashPrefixMatches(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_prefix_search.clone()), "/v2/search/0101?limit=10".to_string()),
+            (HttpResponseType::MempoolStats(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_mempool_stats.clone()), "/v2/mempool/stats".to_string()),
+            (HttpResponseType::StxSupply(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_stx_supply.clone()), format!("/v2/supply?tip={}", StacksBlockId([0x2; 32]).to_hex())),
+            (HttpResponseType::BalanceHistory(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_balance_history.clone()), "/v2/accounts/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/balances?limit=10".to_string()),
+            (HttpResponseType::TopBalances(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_top_balances.clone()), "/v2/balances/top?limit=10".to_string()),
+            (HttpResponseType::AddressTransactions(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_address_transactions.clone()), "/v2/addresses/SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY/transactions?limit=10".to_string()),
+            (HttpResponseType::MinedTransactionReceipts(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), vec![test_mined_receipt.clone()]), format!("/v2/blocks/{}/receipts", StacksBlockId([0x2; 32]).to_hex())),
+            (HttpResponseType::MinedTransactionReceipts(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), vec![test_mined_receipt.clone()]), "/v2/contracts/receipts/ST000000000000000000002AMW42H/pox?limit=10".to_string()),
+            (HttpResponseType::BlockContractCosts(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_contract_costs.clone()), format!("/v2/blocks/{}/costs", StacksBlockId([0x2; 32]).to_hex())),
+            (HttpResponseType::Block(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_block_info.clone()), format!("/v2/blocks/by-hash/{}", test_block_info.block_hash().to_hex())),
+            (HttpResponseType::Microblock(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), test_microblock_info[0].clone()), format!("/v2/microblocks/by-hash/{}", test_microblock_info[0].block_hash().to_hex())),
+            (HttpResponseType::ExportedBlocks(HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true), vec![test_block_info.clone()]), "/v2/export/blocks?from=1&to=1&format=binary".to_string()),
 
             // errors without error messages
             (HttpResponseType::BadRequest(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
@@ -3264,6 +6401,7 @@ mod test {
             (HttpResponseType::PaymentRequired(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::Forbidden(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::NotFound(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
+            (HttpResponseType::TooManyRequests(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::ServerError(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::ServiceUnavailable(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), "".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::Error(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true), 502, "".to_string()), "/v2/neighbors".to_string()),
@@ -3274,6 +6412,7 @@ mod test {
             (HttpResponseType::PaymentRequired(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::Forbidden(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::NotFound(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
+            (HttpResponseType::TooManyRequests(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::ServerError(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::ServiceUnavailable(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), "foo".to_string()), "/v2/neighbors".to_string()),
             (HttpResponseType::Error(HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true), 502, "foo".to_string()), "/v2/neighbors".to_string()),
@@ -3291,6 +6430,21 @@ mod test {
             HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::Bytes, true, 123),
             HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::Bytes, true, 123),
             HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::JSON, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::Bytes, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::Bytes, true, 123),
+            HttpResponsePreamble::new(200, "OK".to_string(), None, HttpContentType::Bytes, true, 123),
 
             // errors
             HttpResponsePreamble::new_error(400, 123, None),
@@ -3298,6 +6452,7 @@ mod test {
             HttpResponsePreamble::new_error(402, 123, None),
             HttpResponsePreamble::new_error(403, 123, None),
             HttpResponsePreamble::new_error(404, 123, None),
+            HttpResponsePreamble::new_error(429, 123, None),
             HttpResponsePreamble::new_error(500, 123, None),
             HttpResponsePreamble::new_error(503, 123, None),
 
@@ -3310,6 +6465,7 @@ mod test {
             HttpResponsePreamble::new_error(402, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(403, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(404, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(429, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(500, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(503, 123, Some("foo".to_string())),
             
@@ -3325,9 +6481,24 @@ mod test {
             
             // with transfer-encoding: chunked
             serde_json::to_string(&test_neighbors_info).unwrap().as_bytes().to_vec(),
-            test_block_info_bytes,
+            test_block_info_bytes.clone(),
             test_microblock_info_bytes,
             Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
+            serde_json::to_string(&test_receipt).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&RPCBanPeersData { banned: 2 }).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_contract_interface).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_contract_src).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_trait_implementors).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_contract_analysis).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&json!({ "openapi": "3.0.3", "paths": { "/v2/info": {} } })).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_mined_receipt).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_prefix_search).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_mempool_stats).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_stx_supply).unwrap().as_bytes().to_vec(),
+            serde_json::to_string(&test_contract_costs).unwrap().as_bytes().to_vec(),
+            test_block_info_bytes,
+            test_single_microblock_bytes,
+            test_exported_blocks_bytes,
 
             // errors
             vec![],
@@ -3338,6 +6509,7 @@ mod test {
             vec![],
             vec![],
             vec![],
+            vec![],
 
             // errors with messages
             "foo".as_bytes().to_vec(),
@@ -3348,6 +6520,7 @@ mod test {
             "foo".as_bytes().to_vec(),
             "foo".as_bytes().to_vec(),
             "foo".as_bytes().to_vec(),
+            "foo".as_bytes().to_vec(),
         ];
 
         for ((test, request_path), (expected_http_preamble, _expected_http_body)) in tests.iter().zip(expected_http_preambles.iter().zip(expected_http_bodies.iter())) {
@@ -3442,6 +6615,86 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_compressed_blocks() {
+        let privk = StacksPrivateKey::from_hex("6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001").unwrap();
+        let test_block_info = make_codec_test_block(5);
+        let test_microblock_info = make_sample_microblock_stream(&privk, &test_block_info.block_hash());
+
+        // clients ask for a compressed reply
+        let mut md = HttpRequestMetadata::new("127.0.0.1".to_string(), 20443);
+        md.accept_encoding = Some(HttpContentEncoding::Gzip);
+        let request = HttpRequestType::GetBlock(md, StacksBlockId([2u8; 32]));
+
+        let mut http = StacksHttp::new();
+        let mut bytes = vec![];
+        request.send(&mut http, &mut bytes).unwrap();
+        assert!(str::from_utf8(&bytes).unwrap().find("\r\nAccept-Encoding: gzip\r\n").is_some());
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(req) => {
+                assert_eq!(req.metadata().accept_encoding, Some(HttpContentEncoding::Gzip));
+                assert_eq!(HttpResponseMetadata::from(&req).client_accept_encoding, Some(HttpContentEncoding::Gzip));
+            },
+            _ => panic!("parsed a response")
+        }
+
+        for encoding in [HttpContentEncoding::Gzip, HttpContentEncoding::Deflate].iter() {
+            let mut md = HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true);
+            md.client_accept_encoding = Some(*encoding);
+
+            let tests = vec![
+                (HttpResponseType::Block(md.clone(), test_block_info.clone()), format!("/v2/blocks/{}", test_block_info.block_hash().to_hex())),
+                (HttpResponseType::Microblocks(md.clone(), test_microblock_info.clone()), format!("/v2/microblocks/{}", test_microblock_info[0].block_hash().to_hex())),
+            ];
+
+            for (response, request_path) in tests.into_iter() {
+                let mut http = StacksHttp::new();
+                let mut bytes = vec![];
+
+                http.begin_request(HttpVersion::Http11, request_path.clone());
+                http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone())).unwrap();
+
+                let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+                match preamble {
+                    StacksHttpPreamble::Response(ref resp) => {
+                        assert!(resp.is_chunked());
+                        assert_eq!(resp.headers.get("content-encoding"), Some(&encoding.as_str().to_string()));
+                    },
+                    _ => panic!("parsed a request")
+                }
+
+                let (message_opt, _) = http.stream_payload(&preamble, &mut &bytes[offset..]).unwrap();
+                match (message_opt.unwrap().0, &response) {
+                    (StacksHttpMessage::Response(HttpResponseType::Block(_, block)), HttpResponseType::Block(_, ref expected)) => assert_eq!(block, *expected),
+                    (StacksHttpMessage::Response(HttpResponseType::Microblocks(_, mblocks)), HttpResponseType::Microblocks(_, ref expected)) => assert_eq!(mblocks, *expected),
+                    (msg, _) => panic!("Unexpected message {:?}", &msg)
+                }
+                assert_eq!(http.num_pending(), 0);
+            }
+        }
+
+        // bodies in codings we don't speak, or that don't decode, are rejected
+        let bad_payloads = vec![
+            "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/octet-stream\r\nContent-Encoding: br\r\nContent-length: 2\r\n\r\nab",
+            "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/octet-stream\r\nContent-Encoding: gzip\r\nContent-length: 2\r\n\r\nab",
+        ];
+        let expected_errors = vec![
+            "Unsupported Content-Encoding",
+            "Failed to decode gzip body",
+        ];
+        for (payload, expected_error) in bad_payloads.iter().zip(expected_errors.iter()) {
+            let mut http = StacksHttp::new();
+            http.begin_request(HttpVersion::Http11, format!("/v2/blocks/{}", test_block_info.block_hash().to_hex()));
+
+            let (preamble, offset) = http.read_preamble(payload.as_bytes()).unwrap();
+            let e = http.read_payload(&preamble, &payload.as_bytes()[offset..]);
+            assert!(e.unwrap_err().to_string().find(expected_error).is_some());
+        }
+    }
+
     #[test]
     fn test_http_headers_too_big() {
         let bad_header_value = std::iter::repeat("A").take(HTTP_PREAMBLE_MAX_ENCODED_SIZE as usize).collect::<String>();
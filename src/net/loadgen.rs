@@ -0,0 +1,711 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::thread_rng;
+
+use serde_json;
+
+use net::Error as net_error;
+use net::{
+    HttpRequestMetadata,
+    HttpRequestType,
+    HttpResponseType,
+    PeerHost,
+    StacksHttpMessage,
+    StacksMessageCodec,
+    TipRequest,
+    RPC_TX_WAIT_TIMEOUT_DEFAULT,
+    RPC_TX_WAIT_TIMEOUT_MAX,
+};
+use net::http::StacksHttp;
+
+use chainstate::stacks::{
+    C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+    C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+    StacksAddress,
+    StacksPrivateKey,
+    StacksPublicKey,
+    StacksTransaction,
+    StacksTransactionSigner,
+    TokenTransferMemo,
+    TransactionAuth,
+    TransactionContractCall,
+    TransactionPayload,
+    TransactionSmartContract,
+    TransactionSpendingCondition,
+    TransactionVersion,
+};
+
+use address::AddressHashMode;
+use burnchains::Address;
+
+use vm;
+use vm::{ClarityName, ContractName, Value};
+use vm::types::PrincipalData;
+
+use util::hash::{Sha256Sum, to_hex};
+use util::sleep_ms;
+use util::strings::StacksString;
+
+fn default_seed() -> String {
+    "load-test".to_string()
+}
+
+fn default_fee_rate() -> u64 {
+    1000
+}
+
+fn default_transfer_amount() -> u64 {
+    1
+}
+
+fn default_wait_timeout() -> u64 {
+    RPC_TX_WAIT_TIMEOUT_DEFAULT
+}
+
+/// Percentage of each kind of transaction in a workload.  Must add up to 100.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionMix {
+    #[serde(default)]
+    pub token_transfer: u64,
+    #[serde(default)]
+    pub contract_call: u64,
+    #[serde(default)]
+    pub publish: u64,
+}
+
+/// A contract to publish.  Each publish gets a fresh contract name of the form `{name}-{n}`,
+/// and every `{n}` in the source is replaced with the same `n`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractTemplate {
+    pub name: String,
+    pub source: String,
+}
+
+/// A contract function to call.  `contract` is a fully-qualified contract identifier
+/// (`address.name`), and each argument is a Clarity expression that gets evaluated once, when
+/// the workload is loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractCallTemplate {
+    pub contract: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// A load test workload.  The sending accounts' keys are derived from `seed`, so a workload
+/// always uses the same accounts, and they need to be funded before the test runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workload {
+    pub accounts: u64,
+    #[serde(default = "default_seed")]
+    pub seed: String,
+    pub mix: TransactionMix,
+    #[serde(default)]
+    pub contracts: Vec<ContractTemplate>,
+    #[serde(default)]
+    pub calls: Vec<ContractCallTemplate>,
+    /// transactions per second to offer the node
+    pub target_tps: u64,
+    /// seconds to offer transactions for
+    pub duration: u64,
+    #[serde(default = "default_fee_rate")]
+    pub fee_rate: u64,
+    #[serde(default = "default_transfer_amount")]
+    pub transfer_amount: u64,
+    /// seconds to wait for each transaction to be anchored
+    #[serde(default = "default_wait_timeout")]
+    pub wait_timeout: u64,
+}
+
+impl Workload {
+    pub fn from_json(json_str: &str) -> Result<Workload, net_error> {
+        let workload : Workload = serde_json::from_str(json_str)
+            .map_err(|e| net_error::DeserializeError(format!("Failed to parse workload: {}", e)))?;
+        workload.validate()?;
+        Ok(workload)
+    }
+
+    pub fn validate(&self) -> Result<(), net_error> {
+        if self.accounts == 0 {
+            return Err(net_error::DeserializeError("Workload needs at least one account".to_string()));
+        }
+        if self.target_tps == 0 || self.duration == 0 {
+            return Err(net_error::DeserializeError("Workload target_tps and duration must be positive".to_string()));
+        }
+        if self.wait_timeout == 0 || self.wait_timeout > RPC_TX_WAIT_TIMEOUT_MAX {
+            return Err(net_error::DeserializeError(format!("Workload wait_timeout must be between 1 and {}", RPC_TX_WAIT_TIMEOUT_MAX)));
+        }
+        let total = self.mix.token_transfer.saturating_add(self.mix.contract_call).saturating_add(self.mix.publish);
+        if total != 100 {
+            return Err(net_error::DeserializeError(format!("Workload mix must add up to 100, not {}", total)));
+        }
+        if self.mix.contract_call > 0 && self.calls.len() == 0 {
+            return Err(net_error::DeserializeError("Workload has contract calls in its mix, but no calls".to_string()));
+        }
+        if self.mix.publish > 0 && self.contracts.len() == 0 {
+            return Err(net_error::DeserializeError("Workload has publishes in its mix, but no contracts".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The private key of the ith sending account
+    pub fn account_key(&self, i: u64) -> StacksPrivateKey {
+        let mut seed_bytes = self.seed.as_bytes().to_vec();
+        seed_bytes.extend_from_slice(&i.to_be_bytes());
+        let mut privk = StacksPrivateKey::from_slice(Sha256Sum::from_data(&seed_bytes).as_bytes())
+            .expect("FATAL: could not derive private key from seed");
+        privk.set_compress_public(true);
+        privk
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransactionKind {
+    TokenTransfer,
+    ContractCall,
+    Publish,
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransactionKind::TokenTransfer => write!(f, "token_transfer"),
+            TransactionKind::ContractCall => write!(f, "contract_call"),
+            TransactionKind::Publish => write!(f, "publish"),
+        }
+    }
+}
+
+impl TransactionMix {
+    /// Map a roll in [0, 100) to a transaction kind, in proportion to the mix
+    pub fn pick(&self, roll: u64) -> TransactionKind {
+        if roll < self.token_transfer {
+            TransactionKind::TokenTransfer
+        }
+        else if roll < self.token_transfer + self.contract_call {
+            TransactionKind::ContractCall
+        }
+        else {
+            TransactionKind::Publish
+        }
+    }
+}
+
+/// Summary of a set of latency samples, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+    pub mean: u64,
+}
+
+impl LatencySummary {
+    pub fn from_samples(samples: &mut Vec<u64>) -> Option<LatencySummary> {
+        if samples.len() == 0 {
+            return None;
+        }
+        samples.sort();
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+        let sum : u128 = samples.iter().map(|s| *s as u128).sum();
+        Some(LatencySummary {
+            min: samples[0],
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples[samples.len() - 1],
+            mean: (sum / (samples.len() as u128)) as u64,
+        })
+    }
+}
+
+/// Counts for one kind of transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct TransactionKindReport {
+    pub sent: u64,
+    pub anchored: u64,
+}
+
+/// The outcome of a load test run.  `offered` counts the transactions the schedule called for;
+/// `skipped` counts the ones that weren't sent because every account still had a transaction
+/// in flight.  Latencies are from sending a transaction to seeing it anchored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadTestReport {
+    pub target_tps: u64,
+    pub duration_ms: u64,
+    pub offered: u64,
+    pub skipped: u64,
+    pub sent: u64,
+    pub anchored: u64,
+    pub pending: u64,
+    pub rejected: u64,
+    pub errors: u64,
+    pub anchored_tps: f64,
+    pub latency_ms: Option<LatencySummary>,
+    pub by_kind: BTreeMap<String, TransactionKindReport>,
+    pub rejections: BTreeMap<String, u64>,
+}
+
+/// What happened to a sent transaction
+#[derive(Debug, Clone, PartialEq)]
+enum TxOutcome {
+    Anchored,
+    /// accepted, but not anchored before the wait timed out
+    Pending,
+    /// rejected by the node, for the given reason
+    Rejected(String),
+    /// couldn't talk to the node
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct TxResult {
+    account: usize,
+    kind: TransactionKind,
+    outcome: TxOutcome,
+    latency_ms: u64,
+    /// the account's next nonce, if we know it
+    next_nonce: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct LoadTestAccount {
+    privk: StacksPrivateKey,
+    address: StacksAddress,
+    nonce: u64,
+}
+
+/// Send one request to the node and read back its response
+fn http_request(peer: &PeerHost, mut request: HttpRequestType, read_timeout: u64) -> Result<HttpResponseType, net_error> {
+    request.metadata_mut().keep_alive = false;
+    let request_path = request.request_path();
+    let request_bytes = StacksHttp::serialize_request(&request)?;
+
+    let mut sock = TcpStream::connect((peer.hostname().as_str(), peer.port()))
+        .map_err(|_e| net_error::ConnectionError)?;
+    sock.set_read_timeout(Some(Duration::from_secs(read_timeout)))
+        .map_err(net_error::ReadError)?;
+
+    sock.write_all(&request_bytes).map_err(net_error::WriteError)?;
+
+    let mut response_bytes = vec![];
+    sock.read_to_end(&mut response_bytes).map_err(net_error::ReadError)?;
+
+    match StacksHttp::parse_response(&request_path, &response_bytes)? {
+        StacksHttpMessage::Response(response) => Ok(response),
+        StacksHttpMessage::Request(_) => Err(net_error::InvalidMessage)
+    }
+}
+
+fn get_account_nonce(peer: &PeerHost, address: &StacksAddress) -> Result<u64, net_error> {
+    let request = HttpRequestType::GetAccount(HttpRequestMetadata::from_host(peer.clone()), address.to_account_principal(), false, TipRequest::canonical());
+    match http_request(peer, request, 30)? {
+        HttpResponseType::GetAccount(_, account) => Ok(account.nonce),
+        _ => Err(net_error::InvalidMessage)
+    }
+}
+
+/// Send a transaction and wait for it to be anchored
+fn post_transaction(peer: &PeerHost, tx: StacksTransaction, wait_timeout: u64) -> TxOutcome {
+    let request = HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(peer.clone()), tx, Some(wait_timeout));
+    match http_request(peer, request, wait_timeout + 30) {
+        Ok(HttpResponseType::TransactionReceipt(_, receipt)) => {
+            if receipt.status == "anchored" {
+                TxOutcome::Anchored
+            }
+            else {
+                TxOutcome::Pending
+            }
+        },
+        Ok(HttpResponseType::TransactionID(..)) => TxOutcome::Pending,
+        Ok(HttpResponseType::BadRequestJSON(_, reason_json)) => {
+            let reason = reason_json.get("reason").and_then(|r| r.as_str()).unwrap_or("BadRequest").to_string();
+            TxOutcome::Rejected(reason)
+        },
+        Ok(HttpResponseType::TooManyRequests(..)) => TxOutcome::Rejected("TooManyRequests".to_string()),
+        Ok(HttpResponseType::ServiceUnavailable(..)) => TxOutcome::Rejected("ServiceUnavailable".to_string()),
+        Ok(other) => TxOutcome::Failed(format!("Unexpected response {:?}", &other)),
+        Err(e) => TxOutcome::Failed(format!("{}", &e))
+    }
+}
+
+/// Interprets a workload against a node
+pub struct LoadGenerator {
+    workload: Workload,
+    peer: PeerHost,
+    version: TransactionVersion,
+    chain_id: u32,
+    accounts: Vec<LoadTestAccount>,
+    calls: Vec<TransactionContractCall>,
+    num_published: u64,
+}
+
+impl LoadGenerator {
+    pub fn new(workload: Workload, peer: PeerHost, version: TransactionVersion, chain_id: u32) -> Result<LoadGenerator, net_error> {
+        workload.validate()?;
+
+        let mut calls = vec![];
+        for call in workload.calls.iter() {
+            let mut parts = call.contract.splitn(2, '.');
+            let (address_str, name_str) = match (parts.next(), parts.next()) {
+                (Some(address_str), Some(name_str)) => (address_str, name_str),
+                _ => {
+                    return Err(net_error::DeserializeError(format!("Invalid contract identifier '{}'", &call.contract)));
+                }
+            };
+            let address = StacksAddress::from_string(address_str)
+                .ok_or(net_error::DeserializeError(format!("Invalid contract address '{}'", address_str)))?;
+            let contract_name = ContractName::try_from(name_str.to_string())
+                .map_err(|_e| net_error::DeserializeError(format!("Invalid contract name '{}'", name_str)))?;
+            let function_name = ClarityName::try_from(call.function.clone())
+                .map_err(|_e| net_error::DeserializeError(format!("Invalid function name '{}'", &call.function)))?;
+
+            let mut function_args = vec![];
+            for arg in call.args.iter() {
+                let value = vm::execute(arg)
+                    .map_err(|e| net_error::DeserializeError(format!("Failed to evaluate argument '{}': {}", arg, &e)))?
+                    .ok_or(net_error::DeserializeError(format!("Argument '{}' did not evaluate to a value", arg)))?;
+                function_args.push(value);
+            }
+
+            calls.push(TransactionContractCall {
+                address, contract_name, function_name, function_args
+            });
+        }
+
+        let address_version = match version {
+            TransactionVersion::Mainnet => C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            TransactionVersion::Testnet => C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        };
+
+        let accounts = (0..workload.accounts).map(|i| {
+            let privk = workload.account_key(i);
+            let address = StacksAddress::from_public_keys(address_version, &AddressHashMode::SerializeP2PKH, 1, &vec![StacksPublicKey::from_private(&privk)])
+                .expect("FATAL: could not make address from public key");
+            LoadTestAccount { privk, address, nonce: 0 }
+        }).collect();
+
+        Ok(LoadGenerator {
+            workload,
+            peer,
+            version,
+            chain_id,
+            accounts,
+            calls,
+            num_published: 0,
+        })
+    }
+
+    /// The sending accounts' private keys and addresses, so they can be funded
+    pub fn account_list(&self) -> Vec<(String, String)> {
+        self.accounts.iter().map(|a| (a.privk.to_hex(), a.address.to_string())).collect()
+    }
+
+    /// Make and sign the next transaction of the given kind from the ith account
+    fn make_transaction(&mut self, i: usize, kind: TransactionKind) -> Result<StacksTransaction, net_error> {
+        let payload = match kind {
+            TransactionKind::TokenTransfer => {
+                // pass tokens around the ring of accounts, so they stay funded
+                let recipient = self.accounts[(i + 1) % self.accounts.len()].address.to_account_principal();
+                TransactionPayload::TokenTransfer(recipient, self.workload.transfer_amount, TokenTransferMemo([0u8; 34]))
+            },
+            TransactionKind::ContractCall => {
+                let call = &self.calls[thread_rng().gen_range(0, self.calls.len())];
+                TransactionPayload::ContractCall(call.clone())
+            },
+            TransactionKind::Publish => {
+                let template = &self.workload.contracts[thread_rng().gen_range(0, self.workload.contracts.len())];
+                let n = format!("{}", self.num_published);
+                let name = ContractName::try_from(format!("{}-{}", &template.name, &n))
+                    .map_err(|_e| net_error::SerializeError(format!("Invalid contract name '{}-{}'", &template.name, &n)))?;
+                let code_body = StacksString::from_string(&template.source.replace("{n}", &n))
+                    .ok_or(net_error::SerializeError(format!("Contract '{}' has illegal characters", &template.name)))?;
+                self.num_published += 1;
+                TransactionPayload::SmartContract(TransactionSmartContract { name, code_body })
+            }
+        };
+
+        let account = &self.accounts[i];
+        let mut spending_condition = TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(&account.privk))
+            .expect("FATAL: could not make p2pkh spending condition");
+        spending_condition.set_nonce(account.nonce);
+        spending_condition.set_fee_rate(self.workload.fee_rate);
+
+        let mut unsigned_tx = StacksTransaction::new(self.version, TransactionAuth::Standard(spending_condition), payload);
+        unsigned_tx.chain_id = self.chain_id;
+
+        let mut signer = StacksTransactionSigner::new(&unsigned_tx);
+        signer.sign_origin(&account.privk)?;
+        signer.get_tx().ok_or(net_error::SigningError("Failed to sign transaction".to_string()))
+    }
+
+    /// Run the workload.  Each account has at most one transaction in flight, since the mempool
+    /// only takes transactions whose nonce is next in line.  Once it's anchored, the account is
+    /// free to send again.
+    pub fn run(&mut self) -> Result<LoadTestReport, net_error> {
+        for account in self.accounts.iter_mut() {
+            account.nonce = get_account_nonce(&self.peer, &account.address)?;
+        }
+
+        let mut free : VecDeque<usize> = (0..self.accounts.len()).collect();
+        let (result_tx, result_rx) : (Sender<TxResult>, Receiver<TxResult>) = channel();
+
+        let mut by_kind : BTreeMap<String, TransactionKindReport> = BTreeMap::new();
+        let mut rejections : BTreeMap<String, u64> = BTreeMap::new();
+        let mut latencies = vec![];
+        let (mut offered, mut skipped, mut sent, mut anchored, mut pending, mut rejected, mut errors) = (0, 0, 0, 0, 0, 0, 0);
+        let mut in_flight = 0;
+
+        let interval_us = 1_000_000 / self.workload.target_tps;
+        let duration_us = self.workload.duration * 1_000_000;
+        let start = Instant::now();
+        let mut next_send_us = 0;
+
+        loop {
+            let elapsed_us = start.elapsed().as_micros() as u64;
+            let sending = elapsed_us < duration_us;
+            if !sending && in_flight == 0 {
+                break;
+            }
+
+            loop {
+                let result = match result_rx.try_recv() {
+                    Ok(result) => result,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => unreachable!("result channel closed while we hold a sender")
+                };
+                in_flight -= 1;
+
+                match result.outcome {
+                    TxOutcome::Anchored => {
+                        anchored += 1;
+                        latencies.push(result.latency_ms);
+                        by_kind.entry(result.kind.to_string()).or_insert_with(TransactionKindReport::default).anchored += 1;
+                    },
+                    TxOutcome::Pending => {
+                        pending += 1;
+                    },
+                    TxOutcome::Rejected(reason) => {
+                        rejected += 1;
+                        *rejections.entry(reason).or_insert(0) += 1;
+                    },
+                    TxOutcome::Failed(msg) => {
+                        debug!("Load test transaction failed: {}", &msg);
+                        errors += 1;
+                    }
+                }
+
+                match result.next_nonce {
+                    Some(nonce) => {
+                        self.accounts[result.account].nonce = nonce;
+                        free.push_back(result.account);
+                    },
+                    None => {
+                        warn!("Load test account {} is out of sync with the node; no longer using it", &self.accounts[result.account].address);
+                    }
+                }
+            }
+
+            if sending && elapsed_us >= next_send_us {
+                next_send_us += interval_us;
+                offered += 1;
+
+                let i = match free.pop_front() {
+                    Some(i) => i,
+                    None => {
+                        skipped += 1;
+                        continue;
+                    }
+                };
+
+                let kind = self.workload.mix.pick(thread_rng().gen_range(0, 100));
+                let tx = self.make_transaction(i, kind)?;
+                let nonce = self.accounts[i].nonce;
+                let address = self.accounts[i].address.clone();
+                let peer = self.peer.clone();
+                let wait_timeout = self.workload.wait_timeout;
+                let result_tx = result_tx.clone();
+
+                sent += 1;
+                in_flight += 1;
+                by_kind.entry(kind.to_string()).or_insert_with(TransactionKindReport::default).sent += 1;
+
+                thread::spawn(move || {
+                    let sent_at = Instant::now();
+                    let outcome = post_transaction(&peer, tx, wait_timeout);
+                    let latency_ms = sent_at.elapsed().as_millis() as u64;
+                    let next_nonce = match outcome {
+                        TxOutcome::Anchored => Some(nonce + 1),
+                        _ => get_account_nonce(&peer, &address).ok()
+                    };
+                    let _ = result_tx.send(TxResult { account: i, kind, outcome, latency_ms, next_nonce });
+                });
+                continue;
+            }
+
+            sleep_ms(1);
+        }
+
+        let duration_ms = start.elapsed().as_millis() as u64;
+        Ok(LoadTestReport {
+            target_tps: self.workload.target_tps,
+            duration_ms,
+            offered,
+            skipped,
+            sent,
+            anchored,
+            pending,
+            rejected,
+            errors,
+            anchored_tps: (anchored as f64) * 1000.0 / (std::cmp::max(duration_ms, 1) as f64),
+            latency_ms: LatencySummary::from_samples(&mut latencies),
+            by_kind,
+            rejections,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_workload_json() -> &'static str {
+        r#"{
+            "accounts": 4,
+            "mix": { "token_transfer": 70, "contract_call": 20, "publish": 10 },
+            "contracts": [ { "name": "counter", "source": "(define-data-var count int {n})" } ],
+            "calls": [ { "contract": "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter-0", "function": "add", "args": ["(+ 1 2)", "u7"] } ],
+            "target_tps": 10,
+            "duration": 30
+        }"#
+    }
+
+    #[test]
+    fn test_workload_parse() {
+        let workload = Workload::from_json(make_workload_json()).unwrap();
+        assert_eq!(workload.accounts, 4);
+        assert_eq!(workload.seed, "load-test");
+        assert_eq!(workload.fee_rate, 1000);
+        assert_eq!(workload.wait_timeout, RPC_TX_WAIT_TIMEOUT_DEFAULT);
+
+        // keys are derived from the seed
+        assert_eq!(workload.account_key(0), workload.account_key(0));
+        assert!(workload.account_key(0) != workload.account_key(1));
+
+        let mut other_workload = workload.clone();
+        other_workload.seed = "other".to_string();
+        assert!(workload.account_key(0) != other_workload.account_key(0));
+
+        // arguments are evaluated once
+        let generator = LoadGenerator::new(workload, PeerHost::from_host_port("127.0.0.1".to_string(), 20443), TransactionVersion::Testnet, 0x80000000).unwrap();
+        assert_eq!(generator.calls[0].function_args, vec![Value::Int(3), Value::UInt(7)]);
+        assert_eq!(generator.account_list().len(), 4);
+
+        let bad_workloads = [
+            // mix doesn't add up
+            r#"{ "accounts": 1, "mix": { "token_transfer": 50 }, "target_tps": 1, "duration": 1 }"#,
+            // no accounts
+            r#"{ "accounts": 0, "mix": { "token_transfer": 100 }, "target_tps": 1, "duration": 1 }"#,
+            // calls in the mix, but none to make
+            r#"{ "accounts": 1, "mix": { "token_transfer": 50, "contract_call": 50 }, "target_tps": 1, "duration": 1 }"#,
+            // wait timeout too long
+            r#"{ "accounts": 1, "mix": { "token_transfer": 100 }, "target_tps": 1, "duration": 1, "wait_timeout": 100000 }"#,
+            // missing fields
+            r#"{ "accounts": 1, "mix": { "token_transfer": 100 } }"#,
+        ];
+        for bad_workload in bad_workloads.iter() {
+            assert!(Workload::from_json(bad_workload).is_err());
+        }
+    }
+
+    #[test]
+    fn test_workload_mix_and_transactions() {
+        let workload = Workload::from_json(make_workload_json()).unwrap();
+        let mut counts = BTreeMap::new();
+        for roll in 0..100 {
+            *counts.entry(workload.mix.pick(roll)).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(&TransactionKind::TokenTransfer), Some(&70));
+        assert_eq!(counts.get(&TransactionKind::ContractCall), Some(&20));
+        assert_eq!(counts.get(&TransactionKind::Publish), Some(&10));
+
+        let mut generator = LoadGenerator::new(workload, PeerHost::from_host_port("127.0.0.1".to_string(), 20443), TransactionVersion::Testnet, 0x80000000).unwrap();
+        generator.accounts[1].nonce = 5;
+
+        let tx = generator.make_transaction(1, TransactionKind::TokenTransfer).unwrap();
+        assert_eq!(tx.get_origin_nonce(), 5);
+        assert_eq!(tx.chain_id, 0x80000000);
+        assert!(tx.verify().is_ok());
+        match tx.payload {
+            TransactionPayload::TokenTransfer(ref recipient, amount, _) => {
+                assert_eq!(*recipient, generator.accounts[2].address.to_account_principal());
+                assert_eq!(amount, 1);
+            },
+            _ => panic!("not a token transfer")
+        }
+
+        // each publish gets its own contract name
+        for n in 0..2 {
+            let tx = generator.make_transaction(0, TransactionKind::Publish).unwrap();
+            match tx.payload {
+                TransactionPayload::SmartContract(ref contract) => {
+                    assert_eq!(contract.name.as_str(), format!("counter-{}", n));
+                    assert_eq!(contract.code_body.to_string(), format!("(define-data-var count int {})", n));
+                },
+                _ => panic!("not a publish")
+            }
+        }
+
+        let tx = generator.make_transaction(3, TransactionKind::ContractCall).unwrap();
+        match tx.payload {
+            TransactionPayload::ContractCall(ref call) => {
+                assert_eq!(call.function_name.as_str(), "add");
+            },
+            _ => panic!("not a contract call")
+        }
+    }
+
+    #[test]
+    fn test_latency_summary() {
+        assert_eq!(LatencySummary::from_samples(&mut vec![]), None);
+
+        let mut samples : Vec<u64> = (1..101).rev().collect();
+        let summary = LatencySummary::from_samples(&mut samples).unwrap();
+        assert_eq!(summary.min, 1);
+        assert_eq!(summary.p50, 50);
+        assert_eq!(summary.p90, 90);
+        assert_eq!(summary.p99, 99);
+        assert_eq!(summary.max, 100);
+        assert_eq!(summary.mean, 50);
+    }
+}
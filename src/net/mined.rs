@@ -0,0 +1,180 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use net::NeighborKey;
+use net::inv::NeighborBlockStats;
+
+use chainstate::burn::BlockHeaderHash;
+use burnchains::BurnchainHeaderHash;
+
+/// How many of our most recently-mined blocks to keep track of
+pub const MAX_TRACKED_MINED_BLOCKS : usize = 32;
+
+/// What we know about whether or not a neighbor has accepted one of our blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinedBlockAcceptance {
+    /// The neighbor's inventory shows the block, as of this time
+    Accepted(u64),
+    /// The neighbor's inventory covers the block's sortition, but does not have the block.  It
+    /// either never got the block, or it got it and rejected it.
+    Missing,
+    /// We haven't synced the neighbor's inventory as far as the block's sortition yet
+    Unknown
+}
+
+/// A block this node mined, and which neighbors have accepted it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinedBlockRecord {
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub block_hash: BlockHeaderHash,
+    pub burn_block_height: u64,
+    pub mined_at: u64,
+    pub neighbors: HashMap<NeighborKey, MinedBlockAcceptance>,
+}
+
+/// Watches our neighbors' block inventories (which BlocksAvailable messages also update) to see
+/// how quickly they accept the blocks we mine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinedBlockTracker {
+    blocks: VecDeque<MinedBlockRecord>,
+}
+
+impl MinedBlockTracker {
+    pub fn new() -> MinedBlockTracker {
+        MinedBlockTracker {
+            blocks: VecDeque::new()
+        }
+    }
+
+    /// Start tracking a block we mined.  Forgets the oldest tracked block if we're tracking too
+    /// many.
+    pub fn track(&mut self, burn_header_hash: BurnchainHeaderHash, block_hash: BlockHeaderHash, burn_block_height: u64, now: u64) -> () {
+        if self.blocks.iter().any(|record| record.burn_header_hash == burn_header_hash && record.block_hash == block_hash) {
+            return;
+        }
+
+        self.blocks.push_back(MinedBlockRecord {
+            burn_header_hash: burn_header_hash,
+            block_hash: block_hash,
+            burn_block_height: burn_block_height,
+            mined_at: now,
+            neighbors: HashMap::new()
+        });
+        while self.blocks.len() > MAX_TRACKED_MINED_BLOCKS {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Update each tracked block's acceptance from our neighbors' latest block inventories.
+    /// Once a neighbor has accepted a block, its acceptance time doesn't change.
+    pub fn update(&mut self, block_stats: &HashMap<NeighborKey, NeighborBlockStats>, now: u64) -> () {
+        for record in self.blocks.iter_mut() {
+            for (nk, stats) in block_stats.iter() {
+                if let Some(MinedBlockAcceptance::Accepted(_)) = record.neighbors.get(nk) {
+                    continue;
+                }
+
+                let acceptance =
+                    if stats.inv.has_ith_block(record.burn_block_height) {
+                        MinedBlockAcceptance::Accepted(now)
+                    }
+                    else if record.burn_block_height < stats.inv.first_block_height + stats.inv.num_sortitions {
+                        MinedBlockAcceptance::Missing
+                    }
+                    else {
+                        MinedBlockAcceptance::Unknown
+                    };
+
+                record.neighbors.insert(nk.clone(), acceptance);
+            }
+        }
+    }
+
+    /// Tracked blocks, newest first
+    pub fn blocks(&self) -> impl Iterator<Item = &MinedBlockRecord> {
+        self.blocks.iter().rev()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use net::PeerAddress;
+    use net::inv::PeerBlocksInv;
+
+    fn make_neighbor_key(port: u16) -> NeighborKey {
+        NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 127, 0, 0, 1]),
+            port: port
+        }
+    }
+
+    #[test]
+    fn test_mined_block_tracker_acceptance() {
+        let mut tracker = MinedBlockTracker::new();
+        tracker.track(BurnchainHeaderHash([0x11; 32]), BlockHeaderHash([0x22; 32]), 12, 1000);
+
+        let accepting = make_neighbor_key(20001);
+        let rejecting = make_neighbor_key(20002);
+        let lagging = make_neighbor_key(20003);
+
+        // sortitions start at height 10, so our block is in the 3rd one (bit 2)
+        let mut block_stats = HashMap::new();
+        block_stats.insert(accepting.clone(), NeighborBlockStats { inv: PeerBlocksInv::new(vec![0x07], vec![0x00], 3, 10) });
+        block_stats.insert(rejecting.clone(), NeighborBlockStats { inv: PeerBlocksInv::new(vec![0x03], vec![0x00], 3, 10) });
+        block_stats.insert(lagging.clone(), NeighborBlockStats { inv: PeerBlocksInv::new(vec![0x03], vec![0x00], 2, 10) });
+
+        tracker.update(&block_stats, 1005);
+
+        let record = tracker.blocks().next().unwrap();
+        assert_eq!(record.neighbors.get(&accepting), Some(&MinedBlockAcceptance::Accepted(1005)));
+        assert_eq!(record.neighbors.get(&rejecting), Some(&MinedBlockAcceptance::Missing));
+        assert_eq!(record.neighbors.get(&lagging), Some(&MinedBlockAcceptance::Unknown));
+
+        // acceptance time sticks; others get updated
+        block_stats.insert(lagging.clone(), NeighborBlockStats { inv: PeerBlocksInv::new(vec![0x07], vec![0x00], 3, 10) });
+        tracker.update(&block_stats, 1010);
+
+        let record = tracker.blocks().next().unwrap();
+        assert_eq!(record.neighbors.get(&accepting), Some(&MinedBlockAcceptance::Accepted(1005)));
+        assert_eq!(record.neighbors.get(&lagging), Some(&MinedBlockAcceptance::Accepted(1010)));
+    }
+
+    #[test]
+    fn test_mined_block_tracker_bounded() {
+        let mut tracker = MinedBlockTracker::new();
+        for i in 0..(MAX_TRACKED_MINED_BLOCKS + 5) {
+            tracker.track(BurnchainHeaderHash([i as u8; 32]), BlockHeaderHash([i as u8; 32]), i as u64, 1000 + i as u64);
+        }
+
+        // tracking the same block twice is a no-op
+        let last = (MAX_TRACKED_MINED_BLOCKS + 4) as u8;
+        tracker.track(BurnchainHeaderHash([last; 32]), BlockHeaderHash([last; 32]), last as u64, 2000);
+
+        let heights : Vec<u64> = tracker.blocks().map(|record| record.burn_block_height).collect();
+        assert_eq!(heights.len(), MAX_TRACKED_MINED_BLOCKS);
+        assert_eq!(heights[0], last as u64);
+        assert_eq!(*heights.last().unwrap(), 5);
+    }
+}
@@ -20,19 +20,29 @@
 pub mod asn;
 pub mod chat;
 pub mod codec;
+pub mod compression;
 pub mod connection;
+pub mod cors;
 pub mod db;
+pub mod deprecation;
 pub mod dns;
 pub mod download;
 pub mod http;
 pub mod inv;
+pub mod loadgen;
+pub mod mined;
+pub mod openapi;
 pub mod neighbors;
+pub mod overload;
 pub mod p2p;
 pub mod poll;
 pub mod prune;
+pub mod ratelimit;
 pub mod rpc;
 pub mod relay;
 pub mod server;
+pub mod tls;
+pub mod workers;
 
 use std::fmt;
 use std::hash::Hash;
@@ -43,6 +53,7 @@ use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::io::prelude::*;
 use std::io;
 use std::io::{Read, Write};
@@ -65,6 +76,7 @@ use serde::{Serialize, Deserialize};
 use regex::Regex;
 
 use core::mempool::*;
+use core::consensus::ConsensusConstant;
 
 use burnchains::BurnchainHeaderHash;
 use burnchains::Txid;
@@ -80,9 +92,12 @@ use chainstate::stacks::{
     StacksTransaction,
     StacksPublicKey,
     StacksBlockId,
+    TokenTransferMemo,
+    TransactionPayload,
     Error as chain_error
 };
 use chainstate::stacks::db::blocks::MemPoolRejection;
+use chainstate::stacks::db::transactions::StateOverride;
 
 use chainstate::stacks::Error as chainstate_error;
 
@@ -92,6 +107,8 @@ use vm::{
     Value,
     types::PrincipalData,
     analysis::contract_interface_builder::ContractInterface,
+    costs::{ CostProfileReport, ExecutionCost },
+    diagnostic::Diagnostic,
 };
 
 use util::hash::Hash160;
@@ -117,6 +134,8 @@ use vm::clarity::Error as clarity_error;
 use chainstate::stacks::index::Error as marf_error;
 
 use self::dns::*;
+use self::compression::HttpContentEncoding;
+use self::deprecation::RouteDeprecation;
 
 #[derive(Debug)]
 pub enum Error {
@@ -210,6 +229,8 @@ pub enum Error {
     ClarityError(clarity_error),
     /// Catch-all for chainstate errors that don't map cleanly into network errors
     ChainstateError(String),
+    /// Failed to set up TLS for the RPC server
+    TlsError(String),
 }
 
 impl fmt::Display for Error {
@@ -258,6 +279,7 @@ impl fmt::Display for Error {
             Error::PeerThrottled => write!(f, "Peer is transmitting too fast"),
             Error::LookupError(ref s) => fmt::Display::fmt(s, f),
             Error::ChainstateError(ref s) => fmt::Display::fmt(s, f),
+            Error::TlsError(ref s) => fmt::Display::fmt(s, f),
             Error::ClarityError(ref e) => fmt::Display::fmt(e, f),
             Error::MARFError(ref e) => fmt::Display::fmt(e, f),
         }
@@ -310,6 +332,7 @@ impl error::Error for Error {
             Error::PeerThrottled => None,
             Error::LookupError(ref _s) => None,
             Error::ChainstateError(ref _s) => None,
+            Error::TlsError(ref _s) => None,
             Error::ClarityError(ref e) => Some(e),
             Error::MARFError(ref e) => Some(e),
         }
@@ -507,7 +530,8 @@ pub const STACKS_PUBLIC_KEY_ENCODED_SIZE : u32 = 33;
 pub enum HttpContentType {
     Bytes,
     Text,
-    JSON
+    JSON,
+    NDJSON
 }
 
 impl fmt::Display for HttpContentType {
@@ -521,7 +545,8 @@ impl HttpContentType {
         match *self {
             HttpContentType::Bytes => "application/octet-stream",
             HttpContentType::Text => "text/plain",
-            HttpContentType::JSON => "application/json"
+            HttpContentType::JSON => "application/json",
+            HttpContentType::NDJSON => "application/x-ndjson"
         }
     }
 }
@@ -540,6 +565,9 @@ impl FromStr for HttpContentType {
         else if s == "application/json" {
             Ok(HttpContentType::JSON)
         }
+        else if s == "application/x-ndjson" {
+            Ok(HttpContentType::NDJSON)
+        }
         else {
             Err(Error::DeserializeError("Unsupported HTTP content type".to_string()))
         }
@@ -705,6 +733,14 @@ pub struct HandshakeData {
 pub enum ServiceFlags {
     RELAY = 0x01,
     RPC = 0x02,
+    /// this node discards old block and microblock data, so it can't serve the whole chain
+    PRUNED = 0x04,
+    /// this node keeps every block and the state at every block, so it can answer queries pinned
+    /// to any of them
+    ARCHIVE = 0x08,
+    /// this node attaches blocks to its headers without executing them, so it has no state to
+    /// answer queries about
+    LIGHT = 0x10,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -878,6 +914,54 @@ pub struct RPCPeerInfoData {
     pub stacks_tip: BlockHeaderHash,
     pub stacks_tip_burn_block: String,
     pub exit_at_block_height: Option<u64>,
+    /// true if this node discards old block and microblock data
+    #[serde(default)]
+    pub pruned: bool,
+    /// true if this node keeps every block and the state at every block
+    #[serde(default)]
+    pub archive: bool,
+    /// true if this node syncs block headers without executing the blocks
+    #[serde(default)]
+    pub light: bool,
+}
+
+/// The data we return on GET /v2/status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCStatusData {
+    pub burn_block_height: u64,
+    /// highest burnchain block height reported by us or any of our peers
+    pub burn_block_target_height: u64,
+    pub stacks_tip_height: u64,
+    pub stacks_tip: BlockHeaderHash,
+    pub stacks_tip_burn_block: String,
+    /// true while we are still catching up to the burnchain or still downloading blocks
+    pub initial_block_download: bool,
+    pub is_miner: bool,
+    /// true while the node is repairing its chainstate after an unclean shutdown.  Reads are
+    /// served from the last fully-processed Stacks tip, and transactions are refused.
+    pub safe_mode: bool,
+    /// result of the last release manifest check, if the node is configured to check for updates
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<RPCUpdateStatus>,
+}
+
+/// How the running build compares to the latest release, per the release manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCUpdateStatus {
+    pub current_version: String,
+    pub latest_version: String,
+    /// true if the latest release is newer than this build
+    pub update_available: bool,
+    /// oldest release that nodes must run by `mandatory_burn_height`, if any
+    pub mandatory_version: Option<String>,
+    pub mandatory_burn_height: Option<u64>,
+    /// true if this build is older than `mandatory_version`
+    pub mandatory: bool,
+    /// release artifact for this node's platform, if the manifest lists one
+    pub download_url: Option<String>,
+    /// when the manifest was last fetched, in seconds since the epoch
+    pub last_checked: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy, Hash)]
@@ -891,12 +975,52 @@ pub enum HttpVersion {
 pub struct HttpRequestMetadata {
     pub version: HttpVersion,
     pub peer: PeerHost,
-    pub keep_alive: bool
+    pub keep_alive: bool,
+    /// API key from an `Authorization: Bearer` or `X-API-Key` header, if given
+    pub auth_token: Option<String>,
+    /// `Origin` header of a browser request, if given
+    pub origin: Option<String>,
+    /// Encoding the response body may be compressed with, per the `Accept-Encoding` header
+    pub accept_encoding: Option<HttpContentEncoding>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MapEntryResponse {
     pub data: String,
+    /// `data` in the JSON encoding of Clarity values
+    #[serde(default)]
+    pub data_json: serde_json::Value,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub marf_proof: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataVarResponse {
+    pub data: String,
+    /// `data` in the JSON encoding of Clarity values
+    #[serde(default)]
+    pub data_json: serde_json::Value,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub marf_proof: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FungibleTokenBalanceResponse {
+    pub balance: String,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub marf_proof: Option<String>
+}
+
+/// The owner of a non-fungible token, if it has been minted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NonFungibleTokenOwnerResponse {
+    pub owner: Option<String>,
     #[serde(rename = "proof")]
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")] 
@@ -913,15 +1037,160 @@ pub struct ContractSrcResponse {
     pub marf_proof: Option<String>
 }
 
+/// Contracts that implement a trait, as reported by the trait-implementation index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraitImplementorsResponse {
+    #[serde(rename = "trait")]
+    pub trait_identifier: String,
+    pub contracts: Vec<String>,
+}
+
+/// The contracts a contract calls, and the contracts that call it, as reported by the
+/// contract-call index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractDependenciesResponse {
+    pub contract: String,
+    /// None if the contract was analyzed before its calls were indexed
+    pub calls: Option<Vec<String>>,
+    pub called_by: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CallReadOnlyResponse {
     pub okay: bool,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")] 
     pub result: Option<String>,
+    /// `result` in the JSON encoding of Clarity values
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")] 
-    pub cause: Option<String>
+    pub result_json: Option<serde_json::Value>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cause: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub profile: Option<CostProfileReport>
+}
+
+/// What deploying a contract would find: its interface and traits if it passes analysis, or
+/// why it doesn't
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeContractResponse {
+    pub okay: bool,
+    pub contract_id: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub interface: Option<ContractInterface>,
+    /// names of the traits the contract defines
+    #[serde(default)]
+    pub defined_traits: Vec<String>,
+    /// identifiers of the traits the contract declares it implements
+    #[serde(default)]
+    pub implemented_traits: Vec<String>,
+    /// what parsing and analyzing the contract cost
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cost: Option<ExecutionCost>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cause: Option<String>,
+    /// where in the source the contract failed to parse or analyze, if it did
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub diagnostic: Option<Diagnostic>,
+    /// every syntax error in the source, if it has any; otherwise, the `diagnostic`, if there is one
+    #[serde(default)]
+    pub errors: Vec<Diagnostic>,
+    /// what the linter found in a contract that passes; none of it stops it from being deployed
+    #[serde(default)]
+    pub warnings: Vec<Diagnostic>
+}
+
+/// What deploying a contract would produce: everything the analyze endpoint reports, plus what
+/// the deploy would cost in all, and whether its name is taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DryRunContractResponse {
+    pub okay: bool,
+    pub contract_id: String,
+    /// whether the deployer already has a contract with this name
+    pub name_collision: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub interface: Option<ContractInterface>,
+    #[serde(default)]
+    pub defined_traits: Vec<String>,
+    #[serde(default)]
+    pub implemented_traits: Vec<String>,
+    /// what parsing and analyzing the contract cost
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub analysis_cost: Option<ExecutionCost>,
+    /// what the whole deploy would cost, including running the contract's top-level expressions
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub deploy_cost: Option<ExecutionCost>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cause: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub diagnostic: Option<Diagnostic>,
+    #[serde(default)]
+    pub errors: Vec<Diagnostic>,
+    #[serde(default)]
+    pub warnings: Vec<Diagnostic>
+}
+
+/// What a contract call did when simulated against a set of changes to chain state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulateTransactionResponse {
+    /// whether the call ran to completion
+    pub okay: bool,
+    /// what running the call cost, up to where it stopped.  Unknown if it isn't valid.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cost: Option<ExecutionCost>,
+    /// the call's return value, hex-encoded
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub result: Option<String>,
+    /// `result` in the JSON encoding of Clarity values
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub result_json: Option<serde_json::Value>,
+    /// the events the call emitted, in the event observer's encoding
+    #[serde(default)]
+    pub events: Vec<serde_json::Value>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cause: Option<String>,
+}
+
+/// What a transaction with a payload would cost to run, and the fee to offer for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeEstimateResponse {
+    /// whether the payload ran to completion
+    pub okay: bool,
+    /// what running the payload cost, up to where it stopped.  Unknown if it isn't valid.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cost: Option<ExecutionCost>,
+    /// a contract call's return value, hex-encoded
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub result: Option<String>,
+    /// `result` in the JSON encoding of Clarity values
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub result_json: Option<serde_json::Value>,
+    /// the length of a single-signature transaction with this payload
+    pub estimated_len: u64,
+    /// the fee to offer for that transaction, in microSTX
+    pub suggested_fee: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] 
+    pub cause: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -936,6 +1205,245 @@ pub struct AccountEntryResponse {
     pub nonce_proof: Option<String>
 }
 
+/// One page of txids from the mempool, in txid order.  Pass `next_cursor` back as `cursor` to
+/// get the next page; it's None once the last page has been returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MempoolTxidsPage {
+    pub txids: Vec<String>,
+    pub next_cursor: Option<String>,
+}
+
+/// A mined STX transfer that carried the memo a client searched for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferByMemoEntry {
+    pub txid: String,
+    pub sender: String,
+    pub recipient: String,
+    pub amount: String,
+    pub memo: String,
+    pub block_hash: String,
+    pub burn_header_hash: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+}
+
+/// The receipt of a mined transaction: its outcome, return value, and the events it emitted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinedTransactionReceiptData {
+    pub txid: String,
+    /// one of "success", "abort_by_response" or "abort_by_post_condition"
+    pub status: String,
+    /// hex-encoded, consensus-serialized Clarity value
+    pub result: String,
+    /// `result` in the JSON encoding of Clarity values
+    #[serde(default)]
+    pub result_json: serde_json::Value,
+    /// STX, fungible and non-fungible token events, and contract (print) events, in the format
+    /// the event observer gets
+    pub events: Vec<serde_json::Value>,
+    /// why the transaction was aborted, if it was aborted by something other than returning an
+    /// `err`: "arithmetic", "unwrap_failure", "runtime_limit_exceeded", "asset_operation",
+    /// "runtime", "post_condition" or "analysis_failure"
+    #[serde(default)]
+    pub abort_code: Option<String>,
+    pub block_hash: String,
+    pub burn_header_hash: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+}
+
+/// A block, burn block, transaction or principal that matches a search query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashPrefixMatch {
+    /// one of "block", "burn_block", "transaction" (mined), "mempool_transaction", "account" or
+    /// "contract"
+    #[serde(rename = "type")]
+    pub match_type: String,
+    /// what matched: "block_hash", "index_block_hash", "burn_header_hash", "txid", "address" or
+    /// "contract_id"
+    pub field: String,
+    /// the full hash or principal that matched
+    pub hash: String,
+    /// the block, the block the transaction was mined in, or the Stacks block the burn block
+    /// selected (absent for mempool transactions and principals)
+    pub index_block_hash: Option<String>,
+    /// height of the block, or of the burn block for burn blocks, or the height at which a
+    /// contract was published
+    pub block_height: Option<u64>,
+    /// is that block in the canonical (burn or Stacks) fork?  (absent for mempool transactions
+    /// and principals)
+    pub canonical: Option<bool>,
+    /// an account's balance, as a hex-encoded u128, at the canonical chain tip
+    pub balance: Option<String>,
+    /// an account's nonce at the canonical chain tip
+    pub nonce: Option<u64>,
+}
+
+/// The data we return on GET /v2/search/{hash-prefix-or-principal}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashPrefixSearchData {
+    /// the hash prefix or principal searched for
+    pub prefix: String,
+    pub matches: Vec<HashPrefixMatch>,
+    /// true if more than one distinct hash starts with the prefix
+    pub ambiguous: bool,
+    /// true if there were more matches than the limit, so not all of them are listed
+    pub truncated: bool,
+}
+
+/// The data we return on GET /v2/supply.  Amounts are in microSTX, as strings since they're u128s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StxSupplyData {
+    /// all STX in existence as of the tip
+    pub total_stx: String,
+    /// STX in account balances
+    pub unlocked_stx: String,
+    /// STX earned by miners that they can't spend yet
+    pub locked_stx: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+}
+
+/// An account's balance as of a block that changed it, in the data we return on
+/// GET /v2/accounts/{principal}/balances.  The balance is in microSTX, as a string since it's a u128.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceHistoryEntry {
+    pub balance: String,
+    pub nonce: u64,
+    pub index_block_hash: String,
+    pub block_height: u64,
+}
+
+/// An account, in the data we return on GET /v2/balances/top.  The balance is in microSTX, as a
+/// string since it's a u128.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountBalanceData {
+    pub principal: String,
+    pub balance: String,
+    pub nonce: u64,
+}
+
+/// A transaction an account or contract took part in, in the data we return on
+/// GET /v2/addresses/{principal}/transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressTransactionData {
+    pub txid: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+    /// position among the transactions the block mined
+    pub tx_index: u64,
+}
+
+/// What a block's transactions spent running one contract, in the data we return on
+/// GET /v2/blocks/{index_block_hash}/costs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractCostData {
+    pub contract_id: String,
+    /// transactions that called or deployed the contract
+    pub transactions: u64,
+    pub cost: ExecutionCost,
+}
+
+/// The data we return on GET /v2/blocks/{index_block_hash}/costs.  Contracts are listed most
+/// expensive (by runtime) first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockContractCostsData {
+    pub index_block_hash: String,
+    pub block_height: u64,
+    pub block_limit: ExecutionCost,
+    /// false if the block's costs weren't counted when it was processed
+    pub metered: bool,
+    pub contracts: Vec<ContractCostData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTransactionEntry {
+    pub txid: String,
+    pub tx: String,
+}
+
+/// One page of a block's transactions, in the order they appear in the block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTransactionsPage {
+    pub index_block_hash: String,
+    pub total: u64,
+    pub offset: u64,
+    pub transactions: Vec<BlockTransactionEntry>,
+}
+
+/// The block whose state a read-only query is evaluated against, given by its `tip` and
+/// `height` query parameters.  With neither, it's the canonical chain tip; with a `height`, it's
+/// the block at that height in the fork ending at `tip` (or the canonical chain tip).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TipRequest {
+    pub tip: Option<StacksBlockId>,
+    pub height: Option<u64>,
+}
+
+impl TipRequest {
+    pub fn canonical() -> TipRequest {
+        TipRequest::default()
+    }
+
+    /// The query string parameters that select this block
+    pub fn query_params(&self) -> Vec<String> {
+        let mut params = vec![];
+        if let Some(ref tip) = self.tip {
+            params.push(format!("tip={}", tip.to_hex()));
+        }
+        if let Some(height) = self.height {
+            params.push(format!("height={}", height));
+        }
+        params
+    }
+}
+
+/// How GET /v2/export/blocks frames the blocks it streams
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockExportFormat {
+    /// each block's consensus serialization, prefixed with its length as a 4-byte big-endian integer
+    Binary,
+    /// one ExportedBlockEntry JSON object per line
+    NDJSON,
+}
+
+impl BlockExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            BlockExportFormat::Binary => "binary",
+            BlockExportFormat::NDJSON => "ndjson",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<BlockExportFormat> {
+        match s {
+            "binary" => Some(BlockExportFormat::Binary),
+            "ndjson" => Some(BlockExportFormat::NDJSON),
+            _ => None
+        }
+    }
+
+    pub fn content_type(&self) -> HttpContentType {
+        match *self {
+            BlockExportFormat::Binary => HttpContentType::Bytes,
+            BlockExportFormat::NDJSON => HttpContentType::NDJSON,
+        }
+    }
+}
+
+/// One line of an ndjson block export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedBlockEntry {
+    pub block_height: u64,
+    pub index_block_hash: String,
+    pub block_hash: String,
+    pub parent_block_hash: String,
+    pub burn_header_hash: String,
+    pub burn_header_timestamp: u64,
+    /// hex-encoded, consensus-serialized block
+    pub block: String,
+}
+
 /// Request ID to use or expect from non-Stacks HTTP clients.
 /// In particular, if a HTTP response does not contain the x-request-id header, then it's assumed
 /// to be this value.  This is needed to support fetching immutables like block and microblock data
@@ -948,6 +1456,9 @@ impl HttpRequestMetadata {
             version: HttpVersion::Http11,
             peer: PeerHost::from_host_port(host, port),
             keep_alive: true,
+            auth_token: None,
+            origin: None,
+            accept_encoding: None,
         }
     }
 
@@ -956,6 +1467,9 @@ impl HttpRequestMetadata {
             version: HttpVersion::Http11,
             peer: peer_host,
             keep_alive: true,
+            auth_token: None,
+            origin: None,
+            accept_encoding: None,
         }
     }
 
@@ -964,14 +1478,88 @@ impl HttpRequestMetadata {
             version: preamble.version,
             peer: preamble.host.clone(),
             keep_alive: preamble.keep_alive,
+            auth_token: HttpRequestMetadata::get_auth_token(preamble),
+            origin: preamble.headers.get("origin").map(|origin| origin.trim().to_string()),
+            accept_encoding: preamble.headers.get("accept-encoding").and_then(|accept| HttpContentEncoding::negotiate(accept)),
+        }
+    }
+
+    fn get_auth_token(preamble: &HttpRequestPreamble) -> Option<String> {
+        if let Some(authorization) = preamble.headers.get("authorization") {
+            let mut parts = authorization.trim().splitn(2, ' ');
+            if let (Some(scheme), Some(token)) = (parts.next(), parts.next()) {
+                if scheme.eq_ignore_ascii_case("bearer") {
+                    return Some(token.trim().to_string());
+                }
+            }
+        }
+        preamble.headers.get("x-api-key").map(|token| token.trim().to_string())
+    }
+
+    /// Does this request carry the given API key?  Compares in constant time.
+    pub fn is_authorized(&self, api_key: &str) -> bool {
+        match self.auth_token {
+            Some(ref token) => {
+                if token.len() != api_key.len() {
+                    return false;
+                }
+                token.as_bytes().iter().zip(api_key.as_bytes().iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+            },
+            None => false
         }
     }
 }
 
+/// A read-only call's arguments.  `sender` is the `tx-sender` the function sees; if it's left
+/// out, the function sees the contract itself.  Each argument is either a hex-encoded Clarity
+/// value, or a value in the JSON encoding of Clarity values.
 #[derive(Serialize, Deserialize)]
 pub struct CallReadOnlyRequestBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    pub arguments: Vec<serde_json::Value>,
+}
+
+/// A contract to analyze, or dry-run, as if `deployer` deployed it as `name`
+#[derive(Serialize, Deserialize)]
+pub struct AnalyzeContractRequestBody {
+    pub deployer: String,
+    pub name: String,
+    pub source: String,
+}
+
+/// A transaction payload to estimate the fee of, hex-encoded, as if `sender` sent it
+#[derive(Serialize, Deserialize)]
+pub struct FeeEstimateRequestBody {
+    pub sender: String,
+    pub payload: String,
+}
+
+/// A contract call to simulate, hex-encoded, as if `sender` sent it, and the changes to chain
+/// state to simulate it against
+#[derive(Serialize, Deserialize)]
+pub struct SimulateTransactionRequestBody {
     pub sender: String,
-    pub arguments: Vec<String>,
+    pub payload: String,
+    #[serde(default)]
+    pub overrides: Vec<StateOverrideBody>,
+}
+
+/// A change to chain state to simulate a transaction against.  Amounts are decimal strings, and
+/// map keys and values hex-encoded Clarity values.  A map entry without a value is deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateOverrideBody {
+    StxBalance { principal: String, amount: String },
+    FtBalance { contract: String, token: String, principal: String, amount: String },
+    MapEntry {
+        contract: String,
+        map: String,
+        key: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<String>
+    },
 }
 
 /// Items in the NeighborsInfo -- combines NeighborKey and NeighborAddress
@@ -1007,23 +1595,181 @@ pub struct RPCNeighborsInfo {
     pub outbound: Vec<RPCNeighbor>,
 }
 
+/// Protocol statistics for one connected peer, as given back from `/v2/neighbors/stats`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerStats {
+    pub neighbor: RPCNeighbor,
+    pub outbound: bool,
+    pub first_contact_time: u64,
+    pub last_contact_time: u64,
+    pub last_recv_time: u64,
+    pub last_useful_data_time: u64,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+    pub msgs_tx: u64,
+    pub msgs_rx: u64,
+    pub msgs_rx_unsolicited: u64,
+    pub msgs_err: u64,
+    /// number of messages received, by message type
+    pub msgs_rx_by_type: BTreeMap<String, u64>,
+    pub blocks_rx: u64,
+    pub microblocks_rx: u64,
+    pub blocks_tx: u64,
+    pub microblocks_tx: u64,
+    pub rtt_ms: u64,
+    pub health_score: f64,
+}
+
+/// Struct given back from a call to `/v2/consensus_constants`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCConsensusConstantsData {
+    /// hash over all constants' names and values, for comparing builds at a glance
+    pub digest: String,
+    pub constants: Vec<ConsensusConstant>,
+}
+
+/// Struct given back from a call to `/v2/neighbors/stats`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerStatsInfo {
+    pub peers: Vec<RPCPeerStats>,
+}
+
+/// Whether or not one neighbor has accepted one of our mined blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinedBlockNeighbor {
+    #[serde(rename = "ip")]
+    pub addrbytes: PeerAddress,
+    pub port: u16,
+    /// one of "accepted", "missing", or "unknown"
+    pub status: String,
+    pub accepted_at: Option<u64>,
+    pub latency_secs: Option<u64>,
+    pub within_budget: bool,
+}
+
+/// One of our mined blocks, and how our neighbors have received it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinedBlockInfo {
+    pub burn_header_hash: String,
+    pub block_hash: BlockHeaderHash,
+    pub burn_block_height: u64,
+    pub mined_at: u64,
+    pub num_accepted: u64,
+    pub num_accepted_within_budget: u64,
+    pub num_missing: u64,
+    pub num_unknown: u64,
+    pub neighbors: Vec<RPCMinedBlockNeighbor>,
+}
+
+/// Struct given back from a call to `/v2/mined_blocks`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinedBlocksInfo {
+    pub acceptance_budget_secs: u64,
+    pub blocks: Vec<RPCMinedBlockInfo>,
+}
+
+/// Request body of `POST /v2/neighbors/ban`.  Without a port, every connection from the IP
+/// address is banned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBanPeersRequestBody {
+    pub ip: PeerAddress,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+/// Struct given back from a call to `POST /v2/neighbors/ban`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBanPeersData {
+    /// number of connected peers that will be banned
+    pub banned: u64,
+}
+
+/// One API call in the body of `POST /v2/batch`.  The body is only given for calls that take a
+/// JSON body on their own, like `POST /v2/contracts/call-read/...`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBatchRequestItem {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+/// Result of one API call in a `POST /v2/batch`.  JSON replies are embedded as-is, text replies
+/// (including error messages) are strings, and binary replies are hex strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBatchResponseItem {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+/// Struct given back from `POST /v2/transactions?wait=true` once the transaction is anchored or
+/// the wait times out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCTransactionReceipt {
+    pub txid: String,
+    /// one of "anchored" or "pending"
+    pub status: String,
+    pub index_block_hash: Option<String>,
+    pub burn_header_hash: Option<String>,
+    pub block_hash: Option<BlockHeaderHash>,
+    pub block_height: Option<u64>,
+    pub tx_index: Option<u64>,
+}
+
 /// All HTTP request paths we support, and the arguments they carry in their paths
 #[derive(Debug, Clone, PartialEq)]
 pub enum HttpRequestType {
     GetInfo(HttpRequestMetadata),
     GetNeighbors(HttpRequestMetadata),
+    GetStatus(HttpRequestMetadata, bool),
+    GetNeighborStats(HttpRequestMetadata),
+    GetConsensusConstants(HttpRequestMetadata),
+    GetOpenAPI(HttpRequestMetadata),
+    GetMinedBlocks(HttpRequestMetadata),
     GetBlock(HttpRequestMetadata, StacksBlockId),
+    GetBlockByHeight(HttpRequestMetadata, u64, Option<StacksBlockId>),
+    GetBlockByHash(HttpRequestMetadata, BlockHeaderHash),
+    ExportBlocks(HttpRequestMetadata, u64, u64, BlockExportFormat, Option<StacksBlockId>),     // first and last height, inclusive
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
-    PostTransaction(HttpRequestMetadata, StacksTransaction),
-    GetAccount(HttpRequestMetadata, PrincipalData, bool),
-    GetMapEntry(HttpRequestMetadata, StacksAddress, ContractName, ClarityName, Value, bool),
+    GetMicroblocksUnconfirmedTip(HttpRequestMetadata, u16),
+    GetMicroblockByHash(HttpRequestMetadata, BlockHeaderHash),
+    GetBlockTransactions(HttpRequestMetadata, StacksBlockId, u64, u64),
+    GetMempoolTxids(HttpRequestMetadata, Option<Txid>, u64),
+    GetMempoolStats(HttpRequestMetadata),
+    GetTransfersByMemo(HttpRequestMetadata, PrincipalData, TokenTransferMemo, u64),
+    GetTransactionReceipt(HttpRequestMetadata, Txid),
+    GetBlockReceipts(HttpRequestMetadata, StacksBlockId),
+    GetContractReceipts(HttpRequestMetadata, StacksAddress, ContractName, Option<StacksBlockId>, u64),
+    SearchHashPrefix(HttpRequestMetadata, String, u64),
+    GetStxSupply(HttpRequestMetadata, Option<StacksBlockId>),
+    GetBalanceHistory(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>, u64),
+    GetTopBalances(HttpRequestMetadata, Option<StacksBlockId>, u64),
+    GetAddressTransactions(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>, u64),
+    GetBlockContractCosts(HttpRequestMetadata, StacksBlockId),
+    GetMetrics(HttpRequestMetadata),
+    PostTransaction(HttpRequestMetadata, StacksTransaction, Option<u64>),     // Some(timeout) to wait for the tx to be anchored
+    GetAccount(HttpRequestMetadata, PrincipalData, bool, TipRequest),
+    GetMapEntry(HttpRequestMetadata, StacksAddress, ContractName, ClarityName, Value, bool, TipRequest),
+    GetDataVar(HttpRequestMetadata, StacksAddress, ContractName, ClarityName, bool, TipRequest),
+    GetFungibleTokenBalance(HttpRequestMetadata, StacksAddress, ContractName, ClarityName, PrincipalData, bool, TipRequest),
+    GetNonFungibleTokenOwner(HttpRequestMetadata, StacksAddress, ContractName, ClarityName, Value, bool, TipRequest),
     CallReadOnlyFunction(HttpRequestMetadata, StacksAddress, ContractName,
-                         PrincipalData, ClarityName, Vec<Value>),
+                         PrincipalData, ClarityName, Vec<Value>, bool, TipRequest),     // true to profile the call's costs
+    AnalyzeContract(HttpRequestMetadata, StacksAddress, ContractName, String),
+    DryRunContract(HttpRequestMetadata, StacksAddress, ContractName, String),
+    BanPeers(HttpRequestMetadata, PeerAddress, Option<u16>),
+    PostBatch(HttpRequestMetadata, Vec<RPCBatchRequestItem>),
     GetTransferCost(HttpRequestMetadata),
+    EstimateFee(HttpRequestMetadata, StacksAddress, TransactionPayload),
+    SimulateTransaction(HttpRequestMetadata, StacksAddress, TransactionPayload, Vec<StateOverride>),
     GetContractSrc(HttpRequestMetadata, StacksAddress, ContractName, bool),
     GetContractABI(HttpRequestMetadata, StacksAddress, ContractName),
+    GetTraitImplementors(HttpRequestMetadata, StacksAddress, ContractName, ClarityName),
+    GetContractDependencies(HttpRequestMetadata, StacksAddress, ContractName),
     OptionsPreflight(HttpRequestMetadata, String),
     Unmatched(HttpRequestMetadata, String),     // catch-all if we can't parse the request
 }
@@ -1035,6 +1781,12 @@ pub struct HttpResponseMetadata {
     pub client_keep_alive: bool,
     pub request_id: u32,
     pub content_length: Option<u32>,
+    /// `Origin` of the request being answered, if given
+    pub client_origin: Option<String>,
+    /// Encoding the client will accept a compressed body in, if any
+    pub client_accept_encoding: Option<HttpContentEncoding>,
+    /// Deprecation of the route being answered, if it is deprecated
+    pub deprecation: Option<&'static RouteDeprecation>,
 }
 
 impl HttpResponseMetadata {
@@ -1053,6 +1805,9 @@ impl HttpResponseMetadata {
             client_keep_alive: client_keep_alive,
             request_id: request_id,
             content_length: content_length,
+            client_origin: None,
+            client_accept_encoding: None,
+            deprecation: None,
         }
     }
 
@@ -1062,6 +1817,9 @@ impl HttpResponseMetadata {
             client_keep_alive: preamble.keep_alive,
             request_id: preamble.request_id,
             content_length: preamble.content_length.clone(),
+            client_origin: None,
+            client_accept_encoding: None,
+            deprecation: None,
         }
     }
 
@@ -1070,7 +1828,10 @@ impl HttpResponseMetadata {
             client_version: HttpVersion::Http11,
             client_keep_alive: false,
             request_id: HttpResponseMetadata::make_request_id(),
-            content_length: Some(0)
+            content_length: Some(0),
+            client_origin: None,
+            client_accept_encoding: None,
+            deprecation: None,
         }
     }
 }
@@ -1078,7 +1839,11 @@ impl HttpResponseMetadata {
 impl From<&HttpRequestType> for HttpResponseMetadata {
     fn from(req: &HttpRequestType) -> HttpResponseMetadata {
         let metadata = req.metadata();
-        HttpResponseMetadata::new(metadata.version, HttpResponseMetadata::make_request_id(), None, metadata.keep_alive)
+        let mut response_metadata = HttpResponseMetadata::new(metadata.version, HttpResponseMetadata::make_request_id(), None, metadata.keep_alive);
+        response_metadata.client_origin = metadata.origin.clone();
+        response_metadata.client_accept_encoding = metadata.accept_encoding.clone();
+        response_metadata.deprecation = RouteDeprecation::find(req.request_class());
+        response_metadata
     }
 }
 
@@ -1087,17 +1852,50 @@ impl From<&HttpRequestType> for HttpResponseMetadata {
 pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
+    Status(HttpResponseMetadata, RPCStatusData),
+    NeighborStats(HttpResponseMetadata, RPCPeerStatsInfo),
+    ConsensusConstants(HttpResponseMetadata, RPCConsensusConstantsData),
+    OpenAPI(HttpResponseMetadata, serde_json::Value),
+    MinedBlocks(HttpResponseMetadata, RPCMinedBlocksInfo),
     Block(HttpResponseMetadata, StacksBlock),
     BlockStream(HttpResponseMetadata),
+    BlockExportStream(HttpResponseMetadata, BlockExportFormat),
+    ExportedBlocks(HttpResponseMetadata, Vec<StacksBlock>),
     Microblocks(HttpResponseMetadata, Vec<StacksMicroblock>),
+    Microblock(HttpResponseMetadata, StacksMicroblock),
     MicroblockStream(HttpResponseMetadata),
     TransactionID(HttpResponseMetadata, Txid),
+    TransactionReceipt(HttpResponseMetadata, RPCTransactionReceipt),
     TokenTransferCost(HttpResponseMetadata, u64),
+    FeeEstimate(HttpResponseMetadata, FeeEstimateResponse),
+    SimulateTransaction(HttpResponseMetadata, SimulateTransactionResponse),
     GetMapEntry(HttpResponseMetadata, MapEntryResponse),
+    GetDataVar(HttpResponseMetadata, DataVarResponse),
+    GetFungibleTokenBalance(HttpResponseMetadata, FungibleTokenBalanceResponse),
+    GetNonFungibleTokenOwner(HttpResponseMetadata, NonFungibleTokenOwnerResponse),
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
+    AnalyzeContract(HttpResponseMetadata, AnalyzeContractResponse),
+    DryRunContract(HttpResponseMetadata, DryRunContractResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
     GetContractABI(HttpResponseMetadata, ContractInterface),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
+    GetTraitImplementors(HttpResponseMetadata, TraitImplementorsResponse),
+    GetContractDependencies(HttpResponseMetadata, ContractDependenciesResponse),
+    BlockTransactions(HttpResponseMetadata, BlockTransactionsPage),
+    MempoolTxids(HttpResponseMetadata, MempoolTxidsPage),
+    MempoolStats(HttpResponseMetadata, MemPoolStats),
+    TransfersByMemo(HttpResponseMetadata, Vec<TransferByMemoEntry>),
+    MinedTransactionReceipt(HttpResponseMetadata, MinedTransactionReceiptData),
+    MinedTransactionReceipts(HttpResponseMetadata, Vec<MinedTransactionReceiptData>),
+    HashPrefixMatches(HttpResponseMetadata, HashPrefixSearchData),
+    StxSupply(HttpResponseMetadata, StxSupplyData),
+    BalanceHistory(HttpResponseMetadata, Vec<BalanceHistoryEntry>),
+    TopBalances(HttpResponseMetadata, Vec<AccountBalanceData>),
+    AddressTransactions(HttpResponseMetadata, Vec<AddressTransactionData>),
+    BlockContractCosts(HttpResponseMetadata, BlockContractCostsData),
+    PeersBanned(HttpResponseMetadata, RPCBanPeersData),
+    BatchResults(HttpResponseMetadata, Vec<RPCBatchResponseItem>),
+    Metrics(HttpResponseMetadata, String),
     OptionsPreflight(HttpResponseMetadata),
     // peer-given error responses
     BadRequest(HttpResponseMetadata, String),
@@ -1106,6 +1904,7 @@ pub enum HttpResponseType {
     PaymentRequired(HttpResponseMetadata, String),
     Forbidden(HttpResponseMetadata, String),
     NotFound(HttpResponseMetadata, String),
+    TooManyRequests(HttpResponseMetadata, String),
     ServerError(HttpResponseMetadata, String),
     ServiceUnavailable(HttpResponseMetadata, String),
     Error(HttpResponseMetadata, u16, String)
@@ -1362,6 +2161,24 @@ pub const NUM_NEIGHBORS : usize = 32;
 // maximum number of unconfirmed microblocks can get streamed to us
 pub const MAX_MICROBLOCKS_UNCONFIRMED : usize = 1024;
 
+// default and maximum number of items in a page of a paginated RPC response
+pub const RPC_PAGE_LIMIT_DEFAULT : u64 = 100;
+pub const RPC_PAGE_LIMIT_MAX : u64 = 1000;
+
+// maximum number of blocks a single block export can stream
+pub const RPC_EXPORT_BLOCKS_MAX : u64 = 10000;
+
+// longest a parameter of a slow RPC request can be when it gets logged
+pub const RPC_LOG_PARAM_MAX_LEN : usize = 128;
+
+// default and maximum number of seconds a transaction submission can wait for the transaction to
+// be anchored
+pub const RPC_TX_WAIT_TIMEOUT_DEFAULT : u64 = 60;
+pub const RPC_TX_WAIT_TIMEOUT_MAX : u64 = 600;
+
+// maximum size of a POST /v2/neighbors/ban request body
+pub const BAN_PEERS_REQUEST_MAX_LEN : u32 = 1024;
+
 // how long a peer will be denied for if it misbehaves
 #[cfg(test)] pub const DENY_BAN_DURATION : u64 = 30;           // seconds
 #[cfg(not(test))] pub const DENY_BAN_DURATION : u64 = 86400;   // seconds (1 day)
@@ -0,0 +1,923 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// OpenAPI description of the RPC server, served at /v2/openapi.json.  Paths, verbs, and path
+// parameters come straight from the RPC router, so a route can't be added or changed without
+// the document following along.  Summaries, query parameters, and schemas are kept per request
+// class below.
+
+use serde_json;
+
+use net::HttpRequestType;
+use net::deprecation::RouteDeprecation;
+
+use crate::{version_string};
+
+const OPENAPI_VERSION : &str = "3.0.3";
+
+/// What the document says about one request class, beyond what its route already gives
+struct OperationDoc {
+    summary: &'static str,
+    /// query string parameters: name, JSON schema type, and description
+    query: Vec<(&'static str, &'static str, &'static str)>,
+    /// content type and schema of the request body, if it takes one
+    request_body: Option<(&'static str, serde_json::Value)>,
+    /// content type and schema of a successful reply
+    response: (&'static str, serde_json::Value),
+}
+
+fn hex_string() -> serde_json::Value {
+    json!({ "type": "string", "pattern": "^(0x)?[0-9a-f]*$" })
+}
+
+/// A Clarity value in its JSON encoding.  `value` is left out for `none`.
+fn clarity_value() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "type": { "type": "string", "enum": ["int", "uint", "bool", "buffer", "principal", "list", "tuple", "none", "some", "ok", "err"] },
+            "value": {}
+        },
+        "required": ["type"]
+    })
+}
+
+/// A Clarity value given in a request: hex-encoded, or in its JSON encoding
+fn clarity_argument() -> serde_json::Value {
+    json!({ "oneOf": [hex_string(), clarity_value()] })
+}
+
+fn integer() -> serde_json::Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn nullable(mut schema: serde_json::Value) -> serde_json::Value {
+    schema["nullable"] = json!(true);
+    schema
+}
+
+fn object(properties: serde_json::Value) -> serde_json::Value {
+    let required : Vec<String> = match properties.as_object() {
+        Some(props) => props.iter()
+            .filter(|(_name, schema)| schema.get("nullable").is_none())
+            .map(|(name, _schema)| name.clone())
+            .collect(),
+        None => vec![]
+    };
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn array(items: serde_json::Value) -> serde_json::Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn binary() -> (&'static str, serde_json::Value) {
+    ("application/octet-stream", json!({ "type": "string", "format": "binary" }))
+}
+
+fn execution_cost() -> serde_json::Value {
+    object(json!({
+        "runtime": integer(),
+        "write_length": integer(),
+        "write_count": integer(),
+        "read_length": integer(),
+        "read_count": integer()
+    }))
+}
+
+fn diagnostic() -> serde_json::Value {
+    object(json!({
+        "level": { "type": "string" },
+        "message": { "type": "string" },
+        "spans": array(object(json!({
+            "start_line": integer(),
+            "start_column": integer(),
+            "end_line": integer(),
+            "end_column": integer()
+        }))),
+        "suggestion": nullable(json!({ "type": "string" }))
+    }))
+}
+
+fn mined_receipt() -> serde_json::Value {
+    object(json!({
+        "txid": hex_string(),
+        "status": { "type": "string", "enum": ["success", "abort_by_response", "abort_by_post_condition"] },
+        "result": hex_string(),
+        "result_json": clarity_value(),
+        "events": array(json!({ "type": "object" })),
+        "abort_code": nullable(json!({ "type": "string", "enum": ["arithmetic", "unwrap_failure", "runtime_limit_exceeded", "asset_operation", "runtime", "post_condition", "analysis_failure"] })),
+        "block_hash": hex_string(),
+        "burn_header_hash": hex_string(),
+        "index_block_hash": hex_string(),
+        "block_height": integer()
+    }))
+}
+
+fn neighbor() -> serde_json::Value {
+    object(json!({
+        "network_id": integer(),
+        "peer_version": integer(),
+        "ip": { "type": "string" },
+        "port": integer(),
+        "public_key_hash": hex_string(),
+        "authenticated": { "type": "boolean" }
+    }))
+}
+
+const PROOF_QUERY : (&str, &str, &str) = ("proof", "integer", "Set to 0 to leave out the MARF proof");
+const LIMIT_QUERY : (&str, &str, &str) = ("limit", "integer", "Page size (default 100, at most 1000)");
+const STATE_TIP_QUERY : (&str, &str, &str) = ("tip", "string", "Index block hash of the block to read the state of; defaults to the canonical chain tip");
+const STATE_HEIGHT_QUERY : (&str, &str, &str) = ("height", "integer", "Height of the block to read the state of, in the fork ending at tip");
+
+/// Documentation for a request class, or None if it has none yet
+fn operation_doc(request_class: &str) -> Option<OperationDoc> {
+    let doc = match request_class {
+        "GetInfo" => OperationDoc {
+            summary: "Get the node's view of the burnchain and Stacks chain tips",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "peer_version": integer(),
+                "burn_consensus": hex_string(),
+                "burn_block_height": integer(),
+                "stable_burn_consensus": hex_string(),
+                "stable_burn_block_height": integer(),
+                "server_version": { "type": "string" },
+                "network_id": integer(),
+                "parent_network_id": integer(),
+                "stacks_tip_height": integer(),
+                "stacks_tip": hex_string(),
+                "stacks_tip_burn_block": hex_string(),
+                "exit_at_block_height": nullable(integer()),
+                "pruned": { "type": "boolean" },
+                "archive": { "type": "boolean" },
+                "light": { "type": "boolean" }
+            }))),
+        },
+        "GetNeighbors" => OperationDoc {
+            summary: "Get a sample of the node's neighbors, and its inbound and outbound peers",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "sample": array(neighbor()),
+                "inbound": array(neighbor()),
+                "outbound": array(neighbor())
+            }))),
+        },
+        "GetStatus" => OperationDoc {
+            summary: "Get the node's sync status, for health checks",
+            query: vec![("require_ready", "integer", "Set to 1 to get a 503 error while the node is syncing or repairing")],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "burn_block_height": integer(),
+                "burn_block_target_height": integer(),
+                "stacks_tip_height": integer(),
+                "stacks_tip": hex_string(),
+                "stacks_tip_burn_block": hex_string(),
+                "initial_block_download": { "type": "boolean" },
+                "is_miner": { "type": "boolean" },
+                "safe_mode": { "type": "boolean" },
+                "update": object(json!({
+                    "current_version": { "type": "string" },
+                    "latest_version": { "type": "string" },
+                    "update_available": { "type": "boolean" },
+                    "mandatory_version": { "type": "string" },
+                    "mandatory_burn_height": integer(),
+                    "mandatory": { "type": "boolean" },
+                    "download_url": { "type": "string" },
+                    "last_checked": integer()
+                }))
+            }))),
+        },
+        "GetNeighborStats" => OperationDoc {
+            summary: "Get protocol statistics for each connected peer",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "peers": array(json!({ "type": "object" }))
+            }))),
+        },
+        "GetConsensusConstants" => OperationDoc {
+            summary: "Get the consensus-critical constants compiled into this build",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "digest": hex_string(),
+                "constants": array(object(json!({
+                    "name": { "type": "string" },
+                    "category": { "type": "string" },
+                    "value": { "type": "string" }
+                })))
+            }))),
+        },
+        "GetOpenAPI" => OperationDoc {
+            summary: "Get this OpenAPI document",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", json!({ "type": "object" })),
+        },
+        "GetMinedBlocks" => OperationDoc {
+            summary: "Get the node's recently mined blocks, and which neighbors have accepted them",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "acceptance_budget_secs": integer(),
+                "blocks": array(json!({ "type": "object" }))
+            }))),
+        },
+        "BanPeers" => OperationDoc {
+            summary: "Ban every connected peer at an IP address, and optionally a port.  Needs the node's API key",
+            query: vec![],
+            request_body: Some(("application/json", object(json!({
+                "ip": { "type": "string" },
+                "port": nullable(integer())
+            })))),
+            response: ("application/json", object(json!({
+                "banned": integer()
+            }))),
+        },
+        "PostBatch" => OperationDoc {
+            summary: "Make several API calls in one request",
+            query: vec![],
+            request_body: Some(("application/json", array(object(json!({
+                "method": { "type": "string" },
+                "path": { "type": "string" },
+                "body": nullable(json!({}))
+            }))))),
+            response: ("application/json", array(object(json!({
+                "status": integer(),
+                "body": {}
+            })))),
+        },
+        "GetBlock" => OperationDoc {
+            summary: "Get a consensus-serialized anchored block",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetBlockByHeight" => OperationDoc {
+            summary: "Get the consensus-serialized anchored block at a height",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain")],
+            request_body: None,
+            response: binary(),
+        },
+        "GetBlockByHash" => OperationDoc {
+            summary: "Get a consensus-serialized anchored block by its block hash",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "ExportBlocks" => OperationDoc {
+            summary: "Stream a range of anchored blocks, as length-prefixed consensus-serialized blocks or as ndjson",
+            query: vec![("from", "integer", "Height of the first block to export"),
+                        ("to", "integer", "Height of the last block to export (at most 10000 blocks at once)"),
+                        ("format", "string", "'binary' (the default) or 'ndjson'"),
+                        ("tip", "string", "Index block hash of the fork to export; defaults to the canonical chain")],
+            request_body: None,
+            response: binary(),
+        },
+        "GetMicroblocksIndexed" => OperationDoc {
+            summary: "Get a consensus-serialized microblock stream, ending at the given microblock",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetMicroblocksConfirmed" => OperationDoc {
+            summary: "Get the confirmed microblocks that descend from an anchored block",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetMicroblocksUnconfirmed" => OperationDoc {
+            summary: "Get the unconfirmed microblocks that descend from an anchored block",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetMicroblocksUnconfirmedTip" => OperationDoc {
+            summary: "Get the unconfirmed microblocks built on the canonical chain tip",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetMicroblockByHash" => OperationDoc {
+            summary: "Get a single consensus-serialized microblock by its hash",
+            query: vec![],
+            request_body: None,
+            response: binary(),
+        },
+        "GetBlockTransactions" => OperationDoc {
+            summary: "Get a page of the transactions in an anchored block",
+            query: vec![("offset", "integer", "Index of the first transaction to return (default 0)"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "index_block_hash": hex_string(),
+                "total": integer(),
+                "offset": integer(),
+                "transactions": array(object(json!({
+                    "txid": hex_string(),
+                    "tx": hex_string()
+                })))
+            }))),
+        },
+        "GetBlockContractCosts" => OperationDoc {
+            summary: "Get what each contract cost to run in a processed anchored block, most expensive (by runtime) first",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "index_block_hash": hex_string(),
+                "block_height": integer(),
+                "block_limit": execution_cost(),
+                "metered": { "type": "boolean" },
+                "contracts": array(object(json!({
+                    "contract_id": { "type": "string" },
+                    "transactions": integer(),
+                    "cost": execution_cost()
+                })))
+            }))),
+        },
+        "GetMempoolTxids" => OperationDoc {
+            summary: "Get a page of the txids in the mempool, in txid order",
+            query: vec![("cursor", "string", "next_cursor from the previous page"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "txids": array(hex_string()),
+                "next_cursor": nullable(hex_string())
+            }))),
+        },
+        "GetMempoolStats" => OperationDoc {
+            summary: "Get the number, total size, fee rates and ages of the transactions in the mempool",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "tx_count": integer(),
+                "total_bytes": integer(),
+                "min_fee_rate": nullable(integer()),
+                "max_fee_rate": nullable(integer()),
+                "fee_rate_percentiles": array(object(json!({
+                    "percentile": integer(),
+                    "fee_rate": integer()
+                }))),
+                "age_distribution": array(object(json!({
+                    "max_age": nullable(integer()),
+                    "tx_count": integer()
+                })))
+            }))),
+        },
+        "GetTransfersByMemo" => OperationDoc {
+            summary: "Get the mined STX transfers to a principal that carry a memo",
+            query: vec![LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", array(object(json!({
+                "txid": hex_string(),
+                "sender": { "type": "string" },
+                "recipient": { "type": "string" },
+                "amount": hex_string(),
+                "memo": hex_string(),
+                "block_hash": hex_string(),
+                "burn_header_hash": hex_string(),
+                "index_block_hash": hex_string(),
+                "block_height": integer()
+            })))),
+        },
+        "GetMetrics" => OperationDoc {
+            summary: "Get the node's Prometheus metrics",
+            query: vec![],
+            request_body: None,
+            response: ("text/plain", json!({ "type": "string" })),
+        },
+        "PostTransaction" => OperationDoc {
+            summary: "Submit a consensus-serialized transaction to the mempool",
+            query: vec![("wait", "boolean", "Wait for the transaction to be mined and return a receipt"),
+                        ("timeout", "integer", "Seconds to wait (default 60, at most 600)")],
+            request_body: Some(binary()),
+            response: ("application/json", json!({
+                "oneOf": [
+                    hex_string(),
+                    object(json!({
+                        "txid": hex_string(),
+                        "status": { "type": "string", "enum": ["anchored", "pending"] },
+                        "index_block_hash": nullable(hex_string()),
+                        "burn_header_hash": nullable(hex_string()),
+                        "block_hash": nullable(hex_string()),
+                        "block_height": nullable(integer()),
+                        "tx_index": nullable(integer())
+                    }))
+                ]
+            })),
+        },
+        "GetTransactionReceipt" => OperationDoc {
+            summary: "Get the outcome, return value and events of a transaction mined in the canonical fork",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", mined_receipt()),
+        },
+        "GetBlockReceipts" => OperationDoc {
+            summary: "Get the outcomes, return values and events of the transactions mined in a block, in the order they were mined",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", array(mined_receipt())),
+        },
+        "GetContractReceipts" => OperationDoc {
+            summary: "Get the outcomes, return values and events of the transactions that called or deployed a contract, emitted its events or moved its tokens, newest first",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", array(mined_receipt())),
+        },
+        "SearchHashPrefix" => OperationDoc {
+            summary: "Find the blocks, burn blocks and transactions whose hashes start with a prefix of at least 4 hex digits, or the account or contract a principal names",
+            query: vec![LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "prefix": { "type": "string" },
+                "matches": array(object(json!({
+                    "type": { "type": "string", "enum": ["block", "burn_block", "transaction", "mempool_transaction", "account", "contract"] },
+                    "field": { "type": "string", "enum": ["block_hash", "index_block_hash", "burn_header_hash", "txid", "address", "contract_id"] },
+                    "hash": { "type": "string" },
+                    "index_block_hash": nullable(hex_string()),
+                    "block_height": nullable(integer()),
+                    "canonical": nullable(json!({ "type": "boolean" })),
+                    "balance": nullable(hex_string()),
+                    "nonce": nullable(integer())
+                }))),
+                "ambiguous": { "type": "boolean" },
+                "truncated": { "type": "boolean" }
+            }))),
+        },
+        "GetStxSupply" => OperationDoc {
+            summary: "Get the total, unlocked and locked STX supply in microSTX, as of a tip",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain")],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "total_stx": { "type": "string" },
+                "unlocked_stx": { "type": "string" },
+                "locked_stx": { "type": "string" },
+                "index_block_hash": hex_string(),
+                "block_height": integer()
+            }))),
+        },
+        "GetBalanceHistory" => OperationDoc {
+            summary: "Get an account's STX balances in the blocks that changed them, newest first, from the balance index",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", array(object(json!({
+                "balance": { "type": "string" },
+                "nonce": integer(),
+                "index_block_hash": hex_string(),
+                "block_height": integer()
+            })))),
+        },
+        "GetTopBalances" => OperationDoc {
+            summary: "Get the accounts with the most STX, from the balance index",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", array(object(json!({
+                "principal": { "type": "string" },
+                "balance": { "type": "string" },
+                "nonce": integer()
+            })))),
+        },
+        "GetAddressTransactions" => OperationDoc {
+            summary: "Get the transactions an account or contract sent, received assets in, or was called in, newest first, from the address index",
+            query: vec![("tip", "string", "Index block hash of the fork to look in; defaults to the canonical chain"), LIMIT_QUERY],
+            request_body: None,
+            response: ("application/json", array(object(json!({
+                "txid": hex_string(),
+                "index_block_hash": hex_string(),
+                "block_height": integer(),
+                "tx_index": integer()
+            })))),
+        },
+        "GetAccount" => OperationDoc {
+            summary: "Get an account's balance and nonce",
+            query: vec![PROOF_QUERY, STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "balance": hex_string(),
+                "nonce": integer(),
+                "balance_proof": nullable(hex_string()),
+                "nonce_proof": nullable(hex_string())
+            }))),
+        },
+        "GetMapEntry" => OperationDoc {
+            summary: "Get an entry from a contract's data map.  The body is the key, hex-serialized or JSON-encoded",
+            query: vec![PROOF_QUERY, STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: Some(("application/json", clarity_argument())),
+            response: ("application/json", object(json!({
+                "data": hex_string(),
+                "data_json": clarity_value(),
+                "proof": nullable(hex_string())
+            }))),
+        },
+        "GetDataVar" => OperationDoc {
+            summary: "Get the value of a contract's data variable",
+            query: vec![PROOF_QUERY, STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "data": hex_string(),
+                "data_json": clarity_value(),
+                "proof": nullable(hex_string())
+            }))),
+        },
+        "GetFungibleTokenBalance" => OperationDoc {
+            summary: "Get a principal's balance of a contract's fungible token",
+            query: vec![PROOF_QUERY, STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "balance": hex_string(),
+                "proof": nullable(hex_string())
+            }))),
+        },
+        "GetNonFungibleTokenOwner" => OperationDoc {
+            summary: "Get the owner of one of a contract's non-fungible tokens.  The body is the token's value, hex-serialized or JSON-encoded",
+            query: vec![PROOF_QUERY, STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: Some(("application/json", clarity_argument())),
+            response: ("application/json", object(json!({
+                "owner": nullable(json!({ "type": "string" })),
+                "proof": nullable(hex_string())
+            }))),
+        },
+        "GetTransferCost" => OperationDoc {
+            summary: "Get the estimated fee rate for STX transfers",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", integer()),
+        },
+        "GetContractSrc" => OperationDoc {
+            summary: "Get a contract's source code",
+            query: vec![PROOF_QUERY],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "source": { "type": "string" },
+                "publish_height": integer(),
+                "proof": nullable(hex_string())
+            }))),
+        },
+        "GetContractABI" => OperationDoc {
+            summary: "Get a contract's interface: its functions, variables, maps, and tokens",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "functions": array(json!({ "type": "object" })),
+                "variables": array(json!({ "type": "object" })),
+                "maps": array(json!({ "type": "object" })),
+                "fungible_tokens": array(json!({ "type": "object" })),
+                "non_fungible_tokens": array(json!({ "type": "object" }))
+            }))),
+        },
+        "GetTraitImplementors" => OperationDoc {
+            summary: "Get the contracts that declare they implement a trait",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "trait": { "type": "string" },
+                "contracts": array(json!({ "type": "string" }))
+            }))),
+        },
+        "GetContractDependencies" => OperationDoc {
+            summary: "Get the contracts a contract calls with contract-call?, and the contracts that call it",
+            query: vec![],
+            request_body: None,
+            response: ("application/json", object(json!({
+                "contract": { "type": "string" },
+                "calls": nullable(array(json!({ "type": "string" }))),
+                "called_by": array(json!({ "type": "string" }))
+            }))),
+        },
+        "CallReadOnlyFunction" => OperationDoc {
+            summary: "Call a read-only contract function",
+            query: vec![("profile", "integer", "Set to 1 to get the call's costs broken down by expression"), STATE_TIP_QUERY, STATE_HEIGHT_QUERY],
+            request_body: Some(("application/json", object(json!({
+                "sender": nullable(json!({ "type": "string" })),
+                "arguments": array(clarity_argument())
+            })))),
+            response: ("application/json", object(json!({
+                "okay": { "type": "boolean" },
+                "result": nullable(hex_string()),
+                "result_json": nullable(clarity_value()),
+                "cause": nullable(json!({ "type": "string" })),
+                "profile": nullable(object(json!({
+                    "total": execution_cost(),
+                    "unattributed": execution_cost(),
+                    "expressions": array(object(json!({
+                        "contract": { "type": "string" },
+                        "expression_id": integer(),
+                        "expression": { "type": "string" },
+                        "line": nullable(integer()),
+                        "count": integer(),
+                        "self_cost": execution_cost(),
+                        "total_cost": execution_cost()
+                    })))
+                })))
+            }))),
+        },
+        "AnalyzeContract" => OperationDoc {
+            summary: "Run the checks a contract deploy would run on contract source, without deploying it",
+            query: vec![],
+            request_body: Some(("application/json", object(json!({
+                "deployer": { "type": "string" },
+                "name": { "type": "string" },
+                "source": { "type": "string" }
+            })))),
+            response: ("application/json", object(json!({
+                "okay": { "type": "boolean" },
+                "contract_id": { "type": "string" },
+                "interface": nullable(json!({ "type": "object" })),
+                "defined_traits": array(json!({ "type": "string" })),
+                "implemented_traits": array(json!({ "type": "string" })),
+                "cost": nullable(execution_cost()),
+                "cause": nullable(json!({ "type": "string" })),
+                "diagnostic": nullable(diagnostic()),
+                "errors": array(diagnostic()),
+                "warnings": array(diagnostic())
+            }))),
+        },
+        "DryRunContract" => OperationDoc {
+            summary: "Report what deploying contract source would produce, without deploying it",
+            query: vec![],
+            request_body: Some(("application/json", object(json!({
+                "deployer": { "type": "string" },
+                "name": { "type": "string" },
+                "source": { "type": "string" }
+            })))),
+            response: ("application/json", object(json!({
+                "okay": { "type": "boolean" },
+                "contract_id": { "type": "string" },
+                "name_collision": { "type": "boolean" },
+                "interface": nullable(json!({ "type": "object" })),
+                "defined_traits": array(json!({ "type": "string" })),
+                "implemented_traits": array(json!({ "type": "string" })),
+                "analysis_cost": nullable(execution_cost()),
+                "deploy_cost": nullable(execution_cost()),
+                "cause": nullable(json!({ "type": "string" })),
+                "diagnostic": nullable(diagnostic()),
+                "errors": array(diagnostic()),
+                "warnings": array(diagnostic())
+            }))),
+        },
+        "EstimateFee" => OperationDoc {
+            summary: "Measure the cost of running a transaction payload, and suggest a fee for it",
+            query: vec![],
+            request_body: Some(("application/json", object(json!({
+                "sender": { "type": "string" },
+                "payload": { "type": "string" }
+            })))),
+            response: ("application/json", object(json!({
+                "okay": { "type": "boolean" },
+                "cost": nullable(execution_cost()),
+                "result": nullable(json!({ "type": "string" })),
+                "result_json": nullable(clarity_value()),
+                "estimated_len": { "type": "integer" },
+                "suggested_fee": { "type": "integer" },
+                "cause": nullable(json!({ "type": "string" }))
+            }))),
+        },
+        "SimulateTransaction" => OperationDoc {
+            summary: "Simulate a contract call against caller-supplied changes to chain state",
+            query: vec![],
+            request_body: Some(("application/json", object(json!({
+                "sender": { "type": "string" },
+                "payload": { "type": "string" },
+                "overrides": nullable(array(json!({
+                    "type": "object",
+                    "required": ["type"],
+                    "properties": {
+                        "type": { "type": "string", "enum": ["stx_balance", "ft_balance", "map_entry"] },
+                        "principal": { "type": "string" },
+                        "amount": { "type": "string" },
+                        "contract": { "type": "string" },
+                        "token": { "type": "string" },
+                        "map": { "type": "string" },
+                        "key": { "type": "string" },
+                        "value": { "type": "string" }
+                    }
+                })))
+            })))),
+            response: ("application/json", object(json!({
+                "okay": { "type": "boolean" },
+                "cost": nullable(execution_cost()),
+                "result": nullable(json!({ "type": "string" })),
+                "result_json": nullable(clarity_value()),
+                "events": array(json!({ "type": "object" })),
+                "cause": nullable(json!({ "type": "string" }))
+            }))),
+        },
+        _ => {
+            return None;
+        }
+    };
+    Some(doc)
+}
+
+/// Turn a route's path regex into an OpenAPI path template.  Each top-level capture group
+/// becomes a `{name}` parameter; returns the template and each parameter's name and pattern.
+pub fn path_template(path_regex: &str) -> (String, Vec<(String, String)>) {
+    let path_regex = path_regex.trim_start_matches('^').trim_end_matches('$');
+    let chars : Vec<char> = path_regex.chars().collect();
+
+    let mut template = String::new();
+    let mut params = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                // escaped literal
+                template.push(chars[i + 1]);
+                i += 2;
+            },
+            '(' => {
+                // find the matching close paren, skipping escapes and character classes
+                let mut depth = 0;
+                let mut in_class = false;
+                let mut j = i;
+                while j < chars.len() {
+                    match chars[j] {
+                        '\\' => {
+                            j += 1;
+                        },
+                        '[' => {
+                            in_class = true;
+                        },
+                        ']' => {
+                            in_class = false;
+                        },
+                        '(' if !in_class => {
+                            depth += 1;
+                        },
+                        ')' if !in_class => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        },
+                        _ => {}
+                    }
+                    j += 1;
+                }
+
+                let group : String = chars[(i + 1)..j.min(chars.len())].iter().collect();
+                let (name, pattern) = if group.starts_with("?P<") {
+                    let name_end = group.find('>').unwrap_or(group.len() - 1);
+                    (group[3..name_end].to_string(), group[(name_end + 1)..].to_string())
+                }
+                else {
+                    (format!("param{}", params.len() + 1), group)
+                };
+
+                template.push_str(&format!("{{{}}}", &name));
+                params.push((name, pattern));
+                i = j + 1;
+            },
+            c => {
+                template.push(c);
+                i += 1;
+            }
+        }
+    }
+    (template, params)
+}
+
+/// Build the OpenAPI document for every route the RPC server handles
+pub fn openapi_document() -> serde_json::Value {
+    let mut paths = serde_json::Map::new();
+    for (verb, regex, request_class) in HttpRequestType::routes() {
+        if verb == "OPTIONS" {
+            // CORS preflight, which matches every path
+            continue;
+        }
+
+        let (template, path_params) = path_template(regex.as_str());
+        let doc = operation_doc(request_class).unwrap_or_else(|| OperationDoc {
+            summary: request_class,
+            query: vec![],
+            request_body: None,
+            response: ("application/json", json!({})),
+        });
+
+        let mut parameters = vec![];
+        for (name, pattern) in path_params.into_iter() {
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string", "pattern": format!("^{}$", &pattern) }
+            }));
+        }
+        for (name, schema_type, description) in doc.query.into_iter() {
+            parameters.push(json!({
+                "name": name,
+                "in": "query",
+                "required": false,
+                "description": description,
+                "schema": { "type": schema_type }
+            }));
+        }
+
+        let (response_type, response_schema) = doc.response;
+        let mut operation = json!({
+            "operationId": request_class,
+            "summary": doc.summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "Success",
+                    "content": { response_type: { "schema": response_schema } }
+                },
+                "default": {
+                    "description": "Error, with a text message",
+                    "content": { "text/plain": { "schema": { "type": "string" } } }
+                }
+            }
+        });
+        if let Some((body_type, body_schema)) = doc.request_body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { body_type: { "schema": body_schema } }
+            });
+        }
+
+        if RouteDeprecation::find(request_class).is_some() {
+            operation["deprecated"] = json!(true);
+        }
+
+        let path_item = paths.entry(template).or_insert_with(|| json!({}));
+        path_item[verb.to_lowercase().as_str()] = operation;
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Stacks node RPC API",
+            "version": option_env!("CARGO_PKG_VERSION").unwrap_or("0.0.0.0"),
+            "description": version_string(
+                option_env!("CARGO_PKG_NAME").unwrap_or("stacks-node"),
+                option_env!("CARGO_PKG_VERSION").unwrap_or("0.0.0.0"))
+        },
+        "paths": paths
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(path_template(r#"^/v2/openapi\.json$"#), ("/v2/openapi.json".to_string(), vec![]));
+        assert_eq!(path_template(r#"^/v2/blocks/(?P<index_block_hash>[0-9a-f]{64})/transactions$"#),
+                   ("/v2/blocks/{index_block_hash}/transactions".to_string(),
+                    vec![("index_block_hash".to_string(), "[0-9a-f]{64}".to_string())]));
+
+        // nested groups and parens in character classes stay inside their parameter
+        let (template, params) = path_template(r#"^/v2/x/(?P<a>(b|c)[()]{1,2})/(d)$"#);
+        assert_eq!(template, "/v2/x/{a}/{param2}");
+        assert_eq!(params, vec![("a".to_string(), "(b|c)[()]{1,2}".to_string()), ("param2".to_string(), "d".to_string())]);
+    }
+
+    #[test]
+    fn test_openapi_document_covers_router() {
+        let document = openapi_document();
+        let paths = document["paths"].as_object().unwrap();
+
+        let mut num_operations = 0;
+        for (verb, regex, request_class) in HttpRequestType::routes() {
+            if verb == "OPTIONS" {
+                continue;
+            }
+            assert!(operation_doc(request_class).is_some(), "no OpenAPI documentation for {}", request_class);
+
+            let (template, params) = path_template(regex.as_str());
+            let operation = &paths[&template][verb.to_lowercase().as_str()];
+            assert_eq!(operation["operationId"], json!(request_class));
+            for (name, _pattern) in params.iter() {
+                assert!(operation["parameters"].as_array().unwrap().iter().any(|p| p["name"] == json!(name) && p["in"] == json!("path")));
+                assert!(template.contains(&format!("{{{}}}", name)));
+            }
+            num_operations += 1;
+        }
+
+        // no two routes were merged into one operation
+        let total : usize = paths.values().map(|item| item.as_object().unwrap().len()).sum();
+        assert_eq!(total, num_operations);
+
+        assert_eq!(paths["/v2/accounts/{principal}"]["get"]["parameters"][1]["name"], json!("proof"));
+        assert_eq!(paths["/v2/transactions"]["post"]["requestBody"]["content"]["application/octet-stream"]["schema"]["format"], json!("binary"));
+    }
+}
@@ -0,0 +1,165 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+
+use std::fmt;
+
+/// Priority class of an RPC request.  When the RPC server is overloaded, it sheds the
+/// lowest-priority requests first, and never sheds critical ones (i.e. the ones that keep
+/// block sync and transaction admission going).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    Low,
+    Normal,
+    Critical
+}
+
+impl RequestPriority {
+    pub fn from_str(s: &str) -> Option<RequestPriority> {
+        match s {
+            "low" => Some(RequestPriority::Low),
+            "normal" => Some(RequestPriority::Normal),
+            "critical" => Some(RequestPriority::Critical),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for RequestPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RequestPriority::Low => write!(f, "low"),
+            RequestPriority::Normal => write!(f, "normal"),
+            RequestPriority::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Decides when the RPC server is overloaded, based on how many requests and replies are
+/// backed up in its conversations and on how long it takes to get through them.
+/// A threshold of 0 disables the corresponding signal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverloadDetector {
+    max_queue_depth: u64,
+    max_latency_ms: u64,
+
+    queue_depth: u64,
+    latency_ms: u64,        // exponentially-weighted moving average
+}
+
+impl OverloadDetector {
+    pub fn new(max_queue_depth: u64, max_latency_ms: u64) -> OverloadDetector {
+        OverloadDetector {
+            max_queue_depth: max_queue_depth,
+            max_latency_ms: max_latency_ms,
+            queue_depth: 0,
+            latency_ms: 0,
+        }
+    }
+
+    /// Record the state of the RPC server after one pass over its conversations.
+    pub fn record_pass(&mut self, queue_depth: u64, latency_ms: u64) -> () {
+        self.queue_depth = queue_depth;
+        self.latency_ms = (3 * self.latency_ms + latency_ms) / 4;
+    }
+
+    /// How far over its thresholds the server is: 0 if under both, 1 if over either, 2 if
+    /// over either by at least a factor of 2.
+    fn overload_level(&self) -> u64 {
+        let level = |value: u64, max: u64| {
+            if max == 0 || value < max {
+                0
+            }
+            else if value < 2 * max {
+                1
+            }
+            else {
+                2
+            }
+        };
+        std::cmp::max(level(self.queue_depth, self.max_queue_depth), level(self.latency_ms, self.max_latency_ms))
+    }
+
+    /// Requests at or below this priority should be shed, if any.
+    pub fn shed_priority(&self) -> Option<RequestPriority> {
+        match self.overload_level() {
+            0 => None,
+            1 => Some(RequestPriority::Low),
+            _ => Some(RequestPriority::Normal)
+        }
+    }
+
+    /// Should a request of this priority be shed, given the current load?
+    pub fn should_shed(shed_priority: Option<RequestPriority>, priority: RequestPriority) -> bool {
+        match shed_priority {
+            Some(shed) => priority != RequestPriority::Critical && priority <= shed,
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overload_detector_levels() {
+        let mut detector = OverloadDetector::new(10, 1000);
+        assert_eq!(detector.shed_priority(), None);
+
+        detector.record_pass(10, 0);
+        assert_eq!(detector.shed_priority(), Some(RequestPriority::Low));
+
+        detector.record_pass(20, 0);
+        assert_eq!(detector.shed_priority(), Some(RequestPriority::Normal));
+
+        detector.record_pass(0, 0);
+        assert_eq!(detector.shed_priority(), None);
+
+        // latency is smoothed
+        detector.record_pass(0, 4000);
+        assert_eq!(detector.shed_priority(), Some(RequestPriority::Low));
+        detector.record_pass(0, 4000);
+        detector.record_pass(0, 4000);
+        assert_eq!(detector.shed_priority(), Some(RequestPriority::Normal));
+
+        // disabled signals never trigger
+        let mut detector = OverloadDetector::new(0, 0);
+        detector.record_pass(1000000, 1000000);
+        assert_eq!(detector.shed_priority(), None);
+    }
+
+    #[test]
+    fn test_should_shed() {
+        assert!(!OverloadDetector::should_shed(None, RequestPriority::Low));
+        assert!(OverloadDetector::should_shed(Some(RequestPriority::Low), RequestPriority::Low));
+        assert!(!OverloadDetector::should_shed(Some(RequestPriority::Low), RequestPriority::Normal));
+        assert!(OverloadDetector::should_shed(Some(RequestPriority::Normal), RequestPriority::Normal));
+        assert!(!OverloadDetector::should_shed(Some(RequestPriority::Normal), RequestPriority::Critical));
+        assert!(!OverloadDetector::should_shed(Some(RequestPriority::Critical), RequestPriority::Critical));
+    }
+
+    #[test]
+    fn test_request_priority_from_str() {
+        for p in [RequestPriority::Low, RequestPriority::Normal, RequestPriority::Critical].iter() {
+            assert_eq!(RequestPriority::from_str(&format!("{}", p)), Some(*p));
+        }
+        assert_eq!(RequestPriority::from_str("urgent"), None);
+    }
+}
@@ -86,6 +86,8 @@ use chainstate::stacks::{MAX_BLOCK_LEN, MAX_TRANSACTION_LEN};
 use util::log;
 use util::get_epoch_time_secs;
 
+use monitoring;
+
 use rand::prelude::*;
 use rand::thread_rng;
 
@@ -93,8 +95,10 @@ use mio;
 use mio::net as mio_net;
 
 use net::inv::*;
+use net::mined::MinedBlockTracker;
 use net::relay::*;
 use net::rpc::RPCHandlerArgs;
+use net::tls::TlsServerConfig;
 
 /// inter-thread request to send a p2p message from another thread in this program.
 pub enum NetworkRequest {
@@ -105,7 +109,8 @@ pub enum NetworkRequest {
     AdvertizeMicroblocks(BlocksAvailableMap),       // announce to all wanting neighbors that we have these confirmed microblock streams
     Request(NeighborKey, StacksMessage, u64),       // target neighbor, message to send, ttl
     Relay(NeighborKey, StacksMessage),
-    Broadcast(Vec<RelayData>, StacksMessageType)
+    Broadcast(Vec<RelayData>, StacksMessageType),
+    TrackMinedBlock(BurnchainHeaderHash, BlockHeaderHash, u64),  // burn header hash, block hash, and burn block height of a block we mined
 }
 
 /// Handle for other threads to use to issue p2p network requests.
@@ -205,6 +210,12 @@ impl NetworkHandle {
         self.send_request(req)
     }
 
+    /// Track how quickly our neighbors accept a block we mined
+    pub fn track_mined_block(&mut self, burn_header_hash: BurnchainHeaderHash, block_hash: BlockHeaderHash, burn_block_height: u64) -> Result<(), net_error> {
+        let req = NetworkRequest::TrackMinedBlock(burn_header_hash, block_hash, burn_block_height);
+        self.send_request(req)
+    }
+
     /// Sends the message to the p2p network thread and gets back a reply handle the calling thread
     /// can wait on.
     pub fn send_signed_request(&mut self, neighbor_key: NeighborKey, msg: StacksMessage, ttl: u64) -> Result<ReplyHandleP2P, net_error> {
@@ -316,6 +327,9 @@ pub struct PeerNetwork {
     // peer block download state
     pub block_downloader: Option<BlockDownloader>,
 
+    // which neighbors have accepted the blocks we mined
+    pub mined_blocks: MinedBlockTracker,
+
     // do we need to do a prune at the end of the work state cycle?
     pub do_prune: bool,
 
@@ -387,6 +401,7 @@ impl PeerNetwork {
             
             inv_state: None,
             block_downloader: None,
+            mined_blocks: MinedBlockTracker::new(),
 
             do_prune: false,
 
@@ -416,6 +431,9 @@ impl PeerNetwork {
 
     /// start serving.
     pub fn bind(&mut self, my_addr: &SocketAddr, http_addr: &SocketAddr) -> Result<(), net_error> {
+        // fail before binding anything if we can't serve RPC over TLS as configured
+        let tls_config = TlsServerConfig::from_options(&self.connection_opts)?;
+
         let mut net = NetworkState::new(self.connection_opts.max_sockets)?;
 
         let p2p_handle = net.bind(my_addr)?;
@@ -428,6 +446,7 @@ impl PeerNetwork {
         self.http_network_handle = http_handle;
 
         self.http.set_server_handle(http_handle);
+        self.http.set_tls_config(tls_config);
 
         self.bind_nk = NeighborKey {
             network_id: self.local_peer.network_id,
@@ -767,6 +786,10 @@ impl PeerNetwork {
                 }
                 Ok(None)
             }
+            NetworkRequest::TrackMinedBlock(burn_header_hash, block_hash, burn_block_height) => {
+                self.mined_blocks.track(burn_header_hash, block_hash, burn_block_height, get_epoch_time_secs());
+                Ok(None)
+            },
             NetworkRequest::Request(neighbor_key, msg, ttl) => {
                 self.send_message(&neighbor_key, msg, ttl)
                     .and_then(|rh| Ok(Some(rh)))
@@ -2660,6 +2683,10 @@ impl PeerNetwork {
 
         // update burnchain snapshot if we need to (careful -- it's expensive)
         let sn = BurnDB::get_canonical_burn_chain_tip(burndb.conn())?;
+        monitoring::set_burnchain_height(sn.block_height);
+        monitoring::set_stacks_tip_height(sn.canonical_stacks_tip_height);
+        monitoring::set_neighbors_count(self.peers.len());
+
         if sn.block_height > self.chain_view.burn_block_height {
             debug!("{:?}: load chain view for burn block {}", &self.local_peer, sn.block_height);
             let new_chain_view = {
@@ -2713,6 +2740,11 @@ impl PeerNetwork {
             self.prune_connections();
         }
         
+        // see which neighbors have accepted our mined blocks
+        if let Some(ref inv_state) = self.inv_state {
+            self.mined_blocks.update(&inv_state.block_stats, get_epoch_time_secs());
+        }
+
         // In parallel, do a neighbor walk
         self.do_network_neighbor_walk()?;
         
@@ -2782,11 +2814,22 @@ impl PeerNetwork {
   
         let mut result = NetworkResult::new();
 
+        // let RPC clients see whether or not we're still downloading blocks
+        let downloading_blocks = self.has_more_downloads();
+
         PeerNetwork::with_network_state(self, |ref mut network, ref mut network_state| {
+            let handler_args = RPCHandlerArgs { downloading_blocks: downloading_blocks,
+                                                mined_blocks: Some(&network.mined_blocks),
+                                                .. handler_args.clone() };
             let http_stacks_msgs = network.http.run(
                 network_state, network.chain_view.clone(), &network.peers, burndb,
-                &network.peerdb, chainstate, mempool, http_poll_state, handler_args)?;
+                &network.peerdb, chainstate, mempool, http_poll_state, &handler_args)?;
             result.consume_http_uploads(http_stacks_msgs);
+
+            // ban the peers that our operator asked us to ban over RPC
+            for event_id in network.http.take_bans() {
+                network.bans.insert(event_id);
+            }
             Ok(())
         })?;
         
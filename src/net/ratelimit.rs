@@ -0,0 +1,234 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+use net::connection::ConnectionOptions;
+
+/// Which rate limit a throttled RPC request ran into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimit {
+    /// the client's IP address sent too many requests
+    PerIP,
+    /// all clients together sent too many requests of this request class
+    PerEndpoint
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RateLimit::PerIP => write!(f, "ip"),
+            RateLimit::PerEndpoint => write!(f, "endpoint"),
+        }
+    }
+}
+
+/// A token bucket that holds up to `capacity` tokens and gains `rate` tokens a second.  Each
+/// request takes one token.  Tokens are tracked in thousandths so refills don't round away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBucket {
+    rate: u64,
+    capacity: u64,
+    millitokens: u64,
+    last_refill_ms: u64,
+}
+
+impl TokenBucket {
+    /// Make a full bucket
+    pub fn new(rate: u64, capacity: u64, now_ms: u64) -> TokenBucket {
+        TokenBucket {
+            rate: rate,
+            capacity: capacity,
+            millitokens: capacity.saturating_mul(1000),
+            last_refill_ms: now_ms,
+        }
+    }
+
+    fn refill(&mut self, now_ms: u64) -> () {
+        let elapsed_ms = now_ms.saturating_sub(self.last_refill_ms);
+        self.millitokens = std::cmp::min(self.capacity.saturating_mul(1000), self.millitokens.saturating_add(elapsed_ms.saturating_mul(self.rate)));
+        self.last_refill_ms = now_ms;
+    }
+
+    /// Is there a token to take?
+    pub fn ready(&mut self, now_ms: u64) -> bool {
+        self.refill(now_ms);
+        self.millitokens >= 1000
+    }
+
+    /// Take a token.  Only call after ready() returns true.
+    pub fn take(&mut self) -> () {
+        self.millitokens = self.millitokens.saturating_sub(1000);
+    }
+
+    /// Has the bucket refilled all the way?  If so, it's no different from a new bucket.
+    pub fn is_full(&mut self, now_ms: u64) -> bool {
+        self.refill(now_ms);
+        self.millitokens >= self.capacity.saturating_mul(1000)
+    }
+}
+
+/// Per-IP and per-endpoint rate limits on RPC requests.  Each client IP address gets its own
+/// token bucket, and each rate-limited request class gets one bucket shared by all clients.
+/// A rate of 0 disables the corresponding limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCRateLimiter {
+    per_ip_rate: u64,
+    per_ip_burst: u64,
+    endpoint_rates: HashMap<String, u64>,
+
+    ip_buckets: HashMap<IpAddr, TokenBucket>,
+    endpoint_buckets: HashMap<String, TokenBucket>,
+}
+
+impl RPCRateLimiter {
+    pub fn new(per_ip_rate: u64, per_ip_burst: u64, endpoint_rates: &[(String, u64)]) -> RPCRateLimiter {
+        RPCRateLimiter {
+            per_ip_rate: per_ip_rate,
+            per_ip_burst: std::cmp::max(per_ip_rate, per_ip_burst),
+            endpoint_rates: endpoint_rates.iter().filter(|(_, rate)| *rate > 0).cloned().collect(),
+            ip_buckets: HashMap::new(),
+            endpoint_buckets: HashMap::new(),
+        }
+    }
+
+    pub fn from_options(opts: &ConnectionOptions) -> RPCRateLimiter {
+        RPCRateLimiter::new(opts.rpc_rate_limit_per_ip, opts.rpc_rate_limit_per_ip_burst, &opts.rpc_rate_limit_per_endpoint)
+    }
+
+    /// Charge a request from `ip` of the given request class against the rate limits.  Returns
+    /// the limit it would exceed, if any, in which case it isn't charged against any of them.
+    pub fn check(&mut self, ip: &IpAddr, request_class: &str, now_ms: u64) -> Option<RateLimit> {
+        if self.per_ip_rate > 0 {
+            let (rate, burst) = (self.per_ip_rate, self.per_ip_burst);
+            let bucket = self.ip_buckets.entry(ip.clone()).or_insert_with(|| TokenBucket::new(rate, burst, now_ms));
+            if !bucket.ready(now_ms) {
+                return Some(RateLimit::PerIP);
+            }
+        }
+
+        if let Some(rate) = self.endpoint_rates.get(request_class) {
+            let rate = *rate;
+            let bucket = self.endpoint_buckets.entry(request_class.to_string()).or_insert_with(|| TokenBucket::new(rate, rate, now_ms));
+            if !bucket.ready(now_ms) {
+                return Some(RateLimit::PerEndpoint);
+            }
+            bucket.take();
+        }
+
+        if let Some(bucket) = self.ip_buckets.get_mut(ip) {
+            bucket.take();
+        }
+        None
+    }
+
+    /// Forget the buckets of IP addresses that haven't sent a request in long enough for their
+    /// buckets to refill.
+    pub fn prune(&mut self, now_ms: u64) -> () {
+        self.ip_buckets.retain(|_, bucket| !bucket.is_full(now_ms));
+    }
+
+    /// How many IP addresses are we tracking?
+    pub fn num_tracked_ips(&self) -> usize {
+        self.ip_buckets.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(2, 4, 1000);
+
+        // starts full
+        for _ in 0..4 {
+            assert!(bucket.ready(1000));
+            bucket.take();
+        }
+        assert!(!bucket.ready(1000));
+
+        // 2 tokens a second means 1 every 500ms
+        assert!(!bucket.ready(1499));
+        assert!(bucket.ready(1500));
+        bucket.take();
+        assert!(!bucket.ready(1500));
+
+        // never holds more than its capacity
+        assert!(bucket.is_full(100000));
+        for _ in 0..4 {
+            assert!(bucket.ready(100000));
+            bucket.take();
+        }
+        assert!(!bucket.ready(100000));
+    }
+
+    #[test]
+    fn test_rate_limiter_per_ip() {
+        let mut limiter = RPCRateLimiter::new(1, 2, &[]);
+        let ip_1 = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let ip_2 = IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8));
+
+        assert_eq!(limiter.check(&ip_1, "GetInfo", 1000), None);
+        assert_eq!(limiter.check(&ip_1, "GetInfo", 1000), None);
+        assert_eq!(limiter.check(&ip_1, "GetInfo", 1000), Some(RateLimit::PerIP));
+
+        // other clients aren't affected
+        assert_eq!(limiter.check(&ip_2, "GetInfo", 1000), None);
+
+        assert_eq!(limiter.check(&ip_1, "GetInfo", 2000), None);
+        assert_eq!(limiter.check(&ip_1, "GetInfo", 2000), Some(RateLimit::PerIP));
+
+        // idle clients get forgotten
+        limiter.prune(2500);
+        assert_eq!(limiter.num_tracked_ips(), 1);
+        limiter.prune(4000);
+        assert_eq!(limiter.num_tracked_ips(), 0);
+    }
+
+    #[test]
+    fn test_rate_limiter_per_endpoint() {
+        let mut limiter = RPCRateLimiter::new(0, 0, &[("CallReadOnlyFunction".to_string(), 2), ("GetInfo".to_string(), 0)]);
+        let ip_1 = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let ip_2 = IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8));
+
+        // shared by all clients
+        assert_eq!(limiter.check(&ip_1, "CallReadOnlyFunction", 1000), None);
+        assert_eq!(limiter.check(&ip_2, "CallReadOnlyFunction", 1000), None);
+        assert_eq!(limiter.check(&ip_1, "CallReadOnlyFunction", 1000), Some(RateLimit::PerEndpoint));
+        assert_eq!(limiter.check(&ip_2, "CallReadOnlyFunction", 1000), Some(RateLimit::PerEndpoint));
+
+        // other request classes, and request classes with a rate of 0, aren't limited
+        for _ in 0..100 {
+            assert_eq!(limiter.check(&ip_1, "GetInfo", 1000), None);
+            assert_eq!(limiter.check(&ip_1, "GetBlock", 1000), None);
+        }
+        assert_eq!(limiter.num_tracked_ips(), 0);
+
+        // a request throttled by its endpoint doesn't use up its client's tokens
+        let mut limiter = RPCRateLimiter::new(1, 1, &[("CallReadOnlyFunction".to_string(), 1)]);
+        assert_eq!(limiter.check(&ip_1, "CallReadOnlyFunction", 1000), None);
+        assert_eq!(limiter.check(&ip_2, "CallReadOnlyFunction", 1000), Some(RateLimit::PerEndpoint));
+        assert_eq!(limiter.check(&ip_2, "GetInfo", 1000), None);
+    }
+}
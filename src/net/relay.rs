@@ -61,10 +61,23 @@ pub const MAX_RELAYER_STATS : usize = 4096;
 pub const MAX_RECENT_MESSAGES : usize = 256;
 pub const MAX_RECENT_MESSAGE_AGE : usize = 600;     // seconds; equal to the expected epoch length
 pub const RELAY_DUPLICATE_INFERENCE_WARMUP : usize = 128;
+pub const MEMPOOL_NEAR_CAPACITY_PCT : u64 = 90;     // the mempool is near capacity once it's this full
 
 pub struct Relayer {
     /// Connection to the p2p thread
     p2p: NetworkHandle,
+    /// Which new transactions are worth forwarding
+    tx_relay_policy: TxRelayPolicy,
+}
+
+/// Once the mempool is near capacity, only relay transactions that pay a high enough fee rate to
+/// have a chance of getting mined, so we don't spend bandwidth gossiping ones that never will.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TxRelayPolicy {
+    /// how many transactions the mempool holds at capacity (0 means no limit)
+    pub mempool_capacity: u64,
+    /// how much more than the lowest fee rate in the mempool a transaction must pay to be relayed
+    pub fee_rate_delta: u64,
 }
 
 #[derive(Debug)]
@@ -425,16 +438,42 @@ impl RelayerStats {
     }
 }
 
+impl TxRelayPolicy {
+    pub fn from_options(opts: &ConnectionOptions) -> TxRelayPolicy {
+        TxRelayPolicy {
+            mempool_capacity: opts.mempool_relay_capacity,
+            fee_rate_delta: opts.mempool_relay_fee_rate_delta,
+        }
+    }
+
+    /// Is a mempool with this many transactions near capacity?
+    pub fn is_near_capacity(&self, num_txs: u64) -> bool {
+        self.mempool_capacity > 0 && num_txs.saturating_mul(100) >= self.mempool_capacity.saturating_mul(MEMPOOL_NEAR_CAPACITY_PCT)
+    }
+
+    /// What's the lowest fee rate a transaction must pay for us to relay it?  None if we relay
+    /// everything.
+    pub fn min_relay_fee_rate(&self, num_txs: u64, lowest_fee_rate: Option<u64>) -> Option<u64> {
+        if !self.is_near_capacity(num_txs) {
+            return None;
+        }
+        lowest_fee_rate.map(|fee_rate| fee_rate.saturating_add(self.fee_rate_delta))
+    }
+}
+
 impl Relayer {
     pub fn new(handle: NetworkHandle) -> Relayer {
         Relayer {
            p2p: handle,
+           tx_relay_policy: TxRelayPolicy::default(),
         }
     }
 
     pub fn from_p2p(network: &mut PeerNetwork) -> Relayer {
         let handle = network.new_handle(1024, 1024);
-        Relayer::new(handle)
+        let mut relayer = Relayer::new(handle);
+        relayer.tx_relay_policy = TxRelayPolicy::from_options(&network.connection_opts);
+        relayer
     }
 
     /// Given blocks pushed to us, verify that they correspond to expected block data.
@@ -802,6 +841,13 @@ impl Relayer {
     /// Store all new transactions we received, and return the list of transactions that we need to
     /// forward (as well as their relay hints).  Also, garbage-collect the mempool.
     fn process_transactions(network_result: &mut NetworkResult, burndb: &BurnDB, chainstate: &StacksChainState, mempool: &mut MemPoolDB) -> Result<Vec<(Vec<RelayData>, StacksTransaction)>, net_error> {
+        if chainstate.light_sync_enabled() {
+            // a light-synced node has no state to check transactions against, so it neither
+            // admits nor relays them
+            debug!("Light sync is on; dropping {} pushed transaction(s)", network_result.pushed_transactions.len());
+            return Ok(vec![]);
+        }
+
         let (burn_header_hash, block_hash, chain_height) = match chainstate.get_stacks_chain_tip(burndb)? {
             Some(tip) => (tip.burn_header_hash, tip.anchored_block_hash, tip.height),
             None => {
@@ -844,6 +890,10 @@ impl Relayer {
         self.p2p.advertize_blocks(available)
     }
 
+    pub fn track_mined_block(&mut self, burn_header_hash: BurnchainHeaderHash, block_hash: BlockHeaderHash, burn_block_height: u64) -> Result<(), net_error> {
+        self.p2p.track_mined_block(burn_header_hash, block_hash, burn_block_height)
+    }
+
     pub fn broadcast_block(&mut self, burn_header_hash: BurnchainHeaderHash, block: StacksBlock) -> Result<(), net_error> {
         let blocks_data = BlocksData {
             blocks: vec![(burn_header_hash, block)]
@@ -929,10 +979,28 @@ impl Relayer {
             debug!("{:?}: Send {} transactions to neighbors", &_local_peer, new_txs.len());
         }
 
+        // don't forward transactions that are too cheap to get mined out of a nearly-full mempool
+        let min_relay_fee_rate = 
+            if new_txs.len() > 0 {
+                let num_txs = MemPoolDB::get_tx_count(mempool.conn())?;
+                let lowest_fee_rate = MemPoolDB::get_min_fee_rate(mempool.conn())?;
+                self.tx_relay_policy.min_relay_fee_rate(num_txs, lowest_fee_rate)
+            }
+            else {
+                None
+            };
+
         let mut mempool_txs_added = vec![];
         for (relayers, tx) in new_txs.into_iter() {
-            debug!("{:?}: Broadcast tx {}", &_local_peer, &tx.txid());
             mempool_txs_added.push(tx.clone());
+            if let Some(min_fee_rate) = min_relay_fee_rate {
+                if tx.get_fee_rate() < min_fee_rate {
+                    debug!("{:?}: Will not relay tx {}: fee rate {} < {} while the mempool is near capacity", &_local_peer, &tx.txid(), tx.get_fee_rate(), min_fee_rate);
+                    continue;
+                }
+            }
+
+            debug!("{:?}: Broadcast tx {}", &_local_peer, &tx.txid());
             let msg = StacksMessageType::Transaction(tx);
             if let Err(e) = self.p2p.broadcast_message(relayers, msg) {
                 warn!("Failed to broadcast transaction: {:?}", &e);
@@ -1190,6 +1258,32 @@ mod test {
 
     use util::sleep_ms;
 
+    #[test]
+    fn test_tx_relay_policy() {
+        // disabled by default
+        let policy = TxRelayPolicy::default();
+        assert!(!policy.is_near_capacity(u64::max_value()));
+        assert_eq!(policy.min_relay_fee_rate(u64::max_value(), Some(10)), None);
+
+        let policy = TxRelayPolicy {
+            mempool_capacity: 1000,
+            fee_rate_delta: 5,
+        };
+
+        // not yet near capacity
+        assert!(!policy.is_near_capacity(899));
+        assert_eq!(policy.min_relay_fee_rate(899, Some(10)), None);
+
+        // near capacity
+        assert!(policy.is_near_capacity(900));
+        assert!(policy.is_near_capacity(2000));
+        assert_eq!(policy.min_relay_fee_rate(900, Some(10)), Some(15));
+        assert_eq!(policy.min_relay_fee_rate(900, Some(u64::max_value())), Some(u64::max_value()));
+
+        // nothing to outbid
+        assert_eq!(policy.min_relay_fee_rate(900, None), None);
+    }
+
     #[test]
     fn test_relayer_stats_add_relyed_messages() {
         let mut relay_stats = RelayerStats::new();
@@ -1833,6 +1927,38 @@ mod test {
         tx_contract
     }
 
+    #[test]
+    fn test_light_sync_drops_pushed_transactions() {
+        let mut peer_config = TestPeerConfig::new("test_light_sync_drops_pushed_transactions", 4230, 4231);
+        let spending_address = peer_config.spending_account.origin_address().unwrap();
+        peer_config.initial_balances = vec![(PrincipalData::from(spending_address), 1000000)];
+        let mut peer = TestPeer::new(peer_config);
+
+        let (burn_ops, stacks_block, microblocks) = peer.make_default_tenure();
+        peer.next_burnchain_block(burn_ops);
+        peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+        let sn = BurnDB::get_canonical_burn_chain_tip(&peer.burndb.as_ref().unwrap().conn()).unwrap();
+        let neighbor_key = peer.to_neighbor().addr;
+
+        let push_transactions = |peer: &mut TestPeer, tx: StacksTransaction| {
+            let mut network_result = NetworkResult::new();
+            network_result.pushed_transactions.insert(neighbor_key.clone(), vec![(vec![], tx)]);
+            Relayer::process_transactions(&mut network_result, peer.burndb.as_ref().unwrap(), &peer.stacks_node.as_ref().unwrap().chainstate, peer.mempool.as_mut().unwrap()).unwrap()
+        };
+
+        // a full node admits and relays a pushed transaction
+        let tx = make_test_smart_contract_transaction(&mut peer, "light-sync-full", &sn.burn_header_hash, &stacks_block.block_hash());
+        assert_eq!(push_transactions(&mut peer, tx.clone()).len(), 1);
+        assert!(peer.mempool.as_ref().unwrap().has_tx(&tx.txid()));
+
+        // a light-synced node does neither
+        peer.stacks_node.as_mut().unwrap().chainstate.enable_light_sync().unwrap();
+        let tx = make_test_smart_contract_transaction(&mut peer, "light-sync-light", &sn.burn_header_hash, &stacks_block.block_hash());
+        assert_eq!(push_transactions(&mut peer, tx.clone()).len(), 0);
+        assert!(!peer.mempool.as_ref().unwrap().has_tx(&tx.txid()));
+    }
+
     #[test]
     #[ignore]
     fn test_get_blocks_and_microblocks_2_peers_push_transactions() {
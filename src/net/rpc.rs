@@ -21,9 +21,11 @@ use std::io::prelude::*;
 use std::io;
 use std::io::{Read, Write, Seek, SeekFrom};
 use std::fmt;
+use std::cmp;
 use std::net::SocketAddr;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use net::Error as net_error;
 use net::http::*;
@@ -36,7 +38,10 @@ use net::HttpResponseType;
 use net::HttpRequestMetadata;
 use net::HttpResponseMetadata;
 use net::PeerAddress;
+use net::ServiceFlags;
 use net::RPCPeerInfoData;
+use net::RPCStatusData;
+use net::RPCUpdateStatus;
 use net::NeighborAddress;
 use net::NeighborsData;
 use net::StacksHttp;
@@ -47,12 +52,36 @@ use net::StacksMessageType;
 use net::connection::ConnectionHttp;
 use net::connection::ReplyHandleHttp;
 use net::connection::ConnectionOptions;
+use net::cors::CORSPolicy;
+use net::overload::{OverloadDetector, RequestPriority};
+use net::ratelimit::RPCRateLimiter;
+use net::workers::RPCWorkerPool;
 use net::db::PeerDB;
 use net::p2p::PeerNetwork;
 use net::{ RPCNeighbor, RPCNeighborsInfo };
-use net::{ MapEntryResponse, AccountEntryResponse, CallReadOnlyResponse, ContractSrcResponse };
+use net::{ RPCPeerStats, RPCPeerStatsInfo };
+use net::{ RPCMinedBlockNeighbor, RPCMinedBlockInfo, RPCMinedBlocksInfo };
+use net::mined::{ MinedBlockTracker, MinedBlockAcceptance };
+use net::{ MapEntryResponse, DataVarResponse, FungibleTokenBalanceResponse, NonFungibleTokenOwnerResponse, AccountEntryResponse, CallReadOnlyResponse, AnalyzeContractResponse, DryRunContractResponse, ContractSrcResponse, TraitImplementorsResponse, ContractDependenciesResponse };
+use net::FeeEstimateResponse;
+use net::SimulateTransactionResponse;
+use net::{ BlockTransactionEntry, BlockTransactionsPage, MempoolTxidsPage, TransferByMemoEntry, MinedTransactionReceiptData };
+use net::RPCTransactionReceipt;
+use net::{ HashPrefixMatch, HashPrefixSearchData };
+use net::StxSupplyData;
+use net::{ BalanceHistoryEntry, AccountBalanceData, AddressTransactionData };
+use net::{ BlockContractCostsData, ContractCostData };
+use net::BlockExportFormat;
+use net::TipRequest;
+use net::deprecation::RouteDeprecation;
+use net::RPCBanPeersData;
+use net::RPCBatchRequestItem;
+use net::RPCBatchResponseItem;
+use net::RPCConsensusConstantsData;
 use net::p2p::PeerMap;
 use core::mempool::*;
+use core::consensus::{consensus_constants, consensus_constants_digest};
+use net::openapi::openapi_document;
 
 use burnchains::Burnchain;
 use burnchains::BurnchainView;
@@ -62,8 +91,12 @@ use chainstate::burn::db::burndb::BurnDB;
 use chainstate::burn::BlockHeaderHash;
 use chainstate::stacks::db::{
     StacksChainState,
+    StacksDBTx,
     BlockStreamData,
-    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE};
+    blocks::MINIMUM_TX_FEE,
+    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE,
+    receipts::TransactionReceiptEntry,
+    transactions::StateOverride};
 use chainstate::stacks::Error as chain_error;
 use chainstate::stacks::*;
 use burnchains::*;
@@ -74,13 +107,19 @@ use rusqlite::{DatabaseName, NO_PARAMS};
 use util::db::Error as db_error;
 use util::db::DBConn;
 use util::get_epoch_time_secs;
+use util::get_epoch_time_ms;
 use util::hash::to_hex;
 use util::hash::Hash160;
+use util::secp256k1::MessageSignature;
 
 use crate::{version_string};
 
 use vm::{
-    clarity::ClarityConnection,
+    analysis::contract_interface_builder::build_contract_interface,
+    analysis::lint::Linter,
+    ast::{ errors::ParseError, parser },
+    diagnostic::Diagnostic,
+    clarity::{ ClarityConnection, Error as clarity_error },
     ClarityName,
     ContractName,
     Value,
@@ -88,7 +127,8 @@ use vm::{
     costs::{ LimitedCostTracker,
              ExecutionCost },
     types::{ PrincipalData,
-             QualifiedContractIdentifier },
+             QualifiedContractIdentifier,
+             TraitIdentifier },
     database::{ ClarityDatabase,
                 MarfedKV,
                 ClaritySerializable,
@@ -100,9 +140,28 @@ use rand::thread_rng;
 
 pub const STREAM_CHUNK_SIZE : u64 = 4096;
 
-#[derive(Default)]
+/// Why a light-synced node refuses requests that read the Clarity state
+const LIGHT_SYNC_REFUSAL : &'static str = "Node is light-synced and has no Clarity state; ask a full node";
+
+#[derive(Default, Clone)]
 pub struct RPCHandlerArgs <'a> {
     pub exit_at_block_height: Option<&'a u64>,
+    /// shed requests at or below this priority (set by the HTTP server when it's overloaded)
+    pub shed_priority: Option<RequestPriority>,
+    /// is this node configured to mine?
+    pub is_miner: bool,
+    /// is the block downloader still fetching blocks (set by the peer network)
+    pub downloading_blocks: bool,
+    /// which neighbors have accepted the blocks we mined (set by the peer network)
+    pub mined_blocks: Option<&'a MinedBlockTracker>,
+    /// is the node repairing its chainstate?  If so, only requests that don't write to the
+    /// chainstate or mempool are served.
+    pub safe_mode: bool,
+    /// is the node light-synced?  If so, it has no Clarity state, so requests that read it are
+    /// refused.
+    pub light_sync: bool,
+    /// result of the last release manifest check (set by the version checker, if enabled)
+    pub update_status: Option<&'a RPCUpdateStatus>,
 }
 
 pub struct ConversationHttp {
@@ -128,6 +187,24 @@ pub struct ConversationHttp {
     pending_request: Option<ReplyHandleHttp>,
     pending_response: Option<HttpResponseType>,
     pending_error_response: Option<HttpResponseType>,
+
+    // transaction submission waiting for its transaction to be anchored, if any
+    pending_tx_wait: Option<PendingTxWait>,
+
+    // p2p event IDs of peers that a client asked us to ban
+    pending_bans: Vec<usize>,
+}
+
+/// A transaction submission whose reply is held back until the transaction gets anchored, or
+/// until the client's timeout passes
+struct PendingTxWait {
+    reply: ReplyHandleHttp,
+    response_metadata: HttpResponseMetadata,
+    keep_alive: bool,
+    txid: Txid,
+    deadline: u64,
+    /// last Stacks chain tip we looked up the transaction at
+    last_tip: (BurnchainHeaderHash, BlockHeaderHash),
 }
 
 impl fmt::Display for ConversationHttp {
@@ -179,7 +256,35 @@ impl RPCPeerInfoData {
             stacks_tip_height,
             stacks_tip,
             stacks_tip_burn_block: stacks_tip_burn_block.to_hex(),
-            exit_at_block_height: exit_at_block_height.cloned()
+            exit_at_block_height: exit_at_block_height.cloned(),
+            pruned: (local_peer.services & (ServiceFlags::PRUNED as u16)) != 0,
+            archive: (local_peer.services & (ServiceFlags::ARCHIVE as u16)) != 0,
+            light: (local_peer.services & (ServiceFlags::LIGHT as u16)) != 0,
+        })
+    }
+}
+
+impl RPCStatusData {
+    /// Summarize how far along this node is in syncing.  We consider ourselves caught up to the
+    /// burnchain once we've processed the highest burnchain block any of our peers claims to have.
+    pub fn from_p2p(burndb: &BurnDB, chain_view: &BurnchainView, peers: &PeerMap, handler_args: &RPCHandlerArgs) -> Result<RPCStatusData, net_error> {
+        let burnchain_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn())?;
+        let burn_block_target_height = peers.values()
+            .map(|convo| convo.get_burnchain_tip_height())
+            .fold(chain_view.burn_block_height, |max_height, height| cmp::max(max_height, height));
+
+        let initial_block_download = burnchain_tip.block_height < burn_block_target_height || handler_args.downloading_blocks;
+
+        Ok(RPCStatusData {
+            burn_block_height: burnchain_tip.block_height,
+            burn_block_target_height,
+            stacks_tip_height: burnchain_tip.canonical_stacks_tip_height,
+            stacks_tip: burnchain_tip.canonical_stacks_tip_hash,
+            stacks_tip_burn_block: burnchain_tip.canonical_stacks_tip_burn_hash.to_hex(),
+            initial_block_download,
+            is_miner: handler_args.is_miner,
+            safe_mode: handler_args.safe_mode,
+            update: handler_args.update_status.cloned(),
         })
     }
 }
@@ -216,10 +321,107 @@ impl RPCNeighborsInfo {
     }
 }
 
+impl RPCPeerStatsInfo {
+    /// Load the protocol statistics of each peer we're connected to, in connection order
+    pub fn from_p2p(peers: &PeerMap) -> RPCPeerStatsInfo {
+        let mut event_ids : Vec<&usize> = peers.keys().collect();
+        event_ids.sort();
+
+        let peer_stats = event_ids
+            .into_iter()
+            .filter_map(|event_id| peers.get(event_id))
+            .map(|convo| {
+                let nk = convo.to_neighbor_key();
+                let naddr = convo.to_neighbor_address();
+                let stats = &convo.stats;
+                RPCPeerStats {
+                    neighbor: RPCNeighbor::from_neighbor_key_and_pubkh(nk, naddr.public_key_hash, convo.is_authenticated()),
+                    outbound: convo.is_outbound(),
+                    first_contact_time: stats.first_contact_time,
+                    last_contact_time: stats.last_contact_time,
+                    last_recv_time: stats.last_recv_time,
+                    last_useful_data_time: stats.last_useful_data_time,
+                    bytes_tx: stats.bytes_tx,
+                    bytes_rx: stats.bytes_rx,
+                    msgs_tx: stats.msgs_tx,
+                    msgs_rx: stats.msgs_rx,
+                    msgs_rx_unsolicited: stats.msgs_rx_unsolicited,
+                    msgs_err: stats.msgs_err,
+                    msgs_rx_by_type: stats.msg_rx_counts.iter()
+                        .map(|(msg_id, count)| (format!("{:?}", msg_id), *count))
+                        .collect(),
+                    blocks_rx: stats.blocks_rx,
+                    microblocks_rx: stats.microblocks_rx,
+                    blocks_tx: stats.blocks_tx,
+                    microblocks_tx: stats.microblocks_tx,
+                    rtt_ms: stats.rtt_ms,
+                    health_score: stats.get_health_score(),
+                }
+            })
+            .collect();
+
+        RPCPeerStatsInfo {
+            peers: peer_stats
+        }
+    }
+}
+
+impl RPCMinedBlocksInfo {
+    /// Summarize which neighbors have accepted our mined blocks, and how quickly
+    pub fn from_tracker(tracker: &MinedBlockTracker, acceptance_budget_secs: u64) -> RPCMinedBlocksInfo {
+        let blocks = tracker.blocks()
+            .map(|record| {
+                let mut neighbor_keys : Vec<_> = record.neighbors.keys().collect();
+                neighbor_keys.sort_by_key(|nk| (nk.addrbytes.clone(), nk.port));
+
+                let neighbors : Vec<RPCMinedBlockNeighbor> = neighbor_keys
+                    .into_iter()
+                    .map(|nk| {
+                        let (status, accepted_at) = match record.neighbors.get(nk) {
+                            Some(MinedBlockAcceptance::Accepted(accepted_at)) => ("accepted", Some(*accepted_at)),
+                            Some(MinedBlockAcceptance::Missing) => ("missing", None),
+                            _ => ("unknown", None)
+                        };
+                        let latency_secs = accepted_at.map(|accepted_at| accepted_at.saturating_sub(record.mined_at));
+                        RPCMinedBlockNeighbor {
+                            addrbytes: nk.addrbytes.clone(),
+                            port: nk.port,
+                            status: status.to_string(),
+                            accepted_at,
+                            latency_secs,
+                            within_budget: latency_secs.map(|latency| latency <= acceptance_budget_secs).unwrap_or(false),
+                        }
+                    })
+                    .collect();
+
+                let count = |status: &str| neighbors.iter().filter(|n| n.status == status).count() as u64;
+                RPCMinedBlockInfo {
+                    burn_header_hash: record.burn_header_hash.to_hex(),
+                    block_hash: record.block_hash.clone(),
+                    burn_block_height: record.burn_block_height,
+                    mined_at: record.mined_at,
+                    num_accepted: count("accepted"),
+                    num_accepted_within_budget: neighbors.iter().filter(|n| n.within_budget).count() as u64,
+                    num_missing: count("missing"),
+                    num_unknown: count("unknown"),
+                    neighbors,
+                }
+            })
+            .collect();
+
+        RPCMinedBlocksInfo {
+            acceptance_budget_secs,
+            blocks
+        }
+    }
+}
+
 impl ConversationHttp {
     pub fn new(network_id: u32, burnchain: &Burnchain, peer_addr: SocketAddr, outbound_url: Option<UrlString>, peer_host: PeerHost, conn_opts: &ConnectionOptions, conn_id: usize) -> ConversationHttp {
         let mut stacks_http = StacksHttp::new();
         stacks_http.maximum_call_argument_size = conn_opts.maximum_call_argument_size;
+        stacks_http.maximum_batch_size = conn_opts.rpc_batch_max_requests;
+        stacks_http.cors_policy = CORSPolicy::from_options(conn_opts);
         ConversationHttp {
             network_id: network_id,
             connection: ConnectionHttp::new(stacks_http, conn_opts, None),
@@ -233,6 +435,8 @@ impl ConversationHttp {
             pending_request: None,
             pending_response: None,
             pending_error_response: None,
+            pending_tx_wait: None,
+            pending_bans: vec![],
             keep_alive: true,
             total_request_count: 0,
             total_reply_count: 0,
@@ -242,6 +446,11 @@ impl ConversationHttp {
         }
     }
 
+    /// How many requests are waiting to be handled on this conversation?
+    pub fn num_pending_inbound(&self) -> usize {
+        self.connection.inbox_len()
+    }
+
     /// How many ongoing requests do we have on this conversation?
     pub fn num_pending_outbound(&self) -> usize {
         self.reply_streams.len()
@@ -340,6 +549,95 @@ impl ConversationHttp {
         response.send(http, fd)
     }
 
+    /// Handle a GET neighbor stats
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getneighbor_stats<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, peers: &PeerMap) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = HttpResponseType::NeighborStats(response_metadata, RPCPeerStatsInfo::from_p2p(peers));
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for this build's consensus-critical constants.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getconsensus_constants<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let constants = consensus_constants();
+        let constants_data = RPCConsensusConstantsData {
+            digest: consensus_constants_digest(&constants).to_hex(),
+            constants: constants
+        };
+        let response = HttpResponseType::ConsensusConstants(response_metadata, constants_data);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the OpenAPI description of this RPC server.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getopenapi<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = HttpResponseType::OpenAPI(response_metadata, openapi_document());
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to ban peers.  Every connected peer at the given IP address (and port, if
+    /// given) gets banned once the HTTP server is done with this pass.  Returns their p2p event
+    /// IDs.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_ban_peers<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, ip: &PeerAddress, port: Option<u16>, peers: &PeerMap) -> Result<Vec<usize>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let mut event_ids : Vec<usize> = peers.iter()
+            .filter(|(_, convo)| {
+                (convo.peer_addrbytes == *ip && port.map(|p| p == convo.peer_port).unwrap_or(true)) ||
+                (convo.handshake_addrbytes == *ip && port.map(|p| p == convo.handshake_port).unwrap_or(true))
+            })
+            .map(|(event_id, _)| *event_id)
+            .collect();
+        event_ids.sort();
+
+        let response = HttpResponseType::PeersBanned(response_metadata, RPCBanPeersData { banned: event_ids.len() as u64 });
+        response.send(http, fd)?;
+        Ok(event_ids)
+    }
+
+    /// Handle a GET mined blocks
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getmined_blocks<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, handler_args: &RPCHandlerArgs, options: &ConnectionOptions) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let mined_blocks = match handler_args.mined_blocks {
+            Some(tracker) => RPCMinedBlocksInfo::from_tracker(tracker, options.mined_block_acceptance_budget),
+            None => RPCMinedBlocksInfo::from_tracker(&MinedBlockTracker::new(), options.mined_block_acceptance_budget)
+        };
+        let response = HttpResponseType::MinedBlocks(response_metadata, mined_blocks);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET status.  If the caller requires the node to be ready, reply with a 503 while
+    /// it's still in its initial block download.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getstatus<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, require_ready: bool, burndb: &BurnDB, chain_view: &BurnchainView,
+                                  peers: &PeerMap, handler_args: &RPCHandlerArgs) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        match RPCStatusData::from_p2p(burndb, chain_view, peers, handler_args) {
+            Ok(status) => {
+                let response = 
+                    if require_ready && status.initial_block_download {
+                        HttpResponseType::ServiceUnavailable(response_metadata, "Node is still in initial block download".to_string())
+                    }
+                    else if require_ready && status.safe_mode {
+                        HttpResponseType::ServiceUnavailable(response_metadata, "Node is repairing its chainstate".to_string())
+                    }
+                    else {
+                        HttpResponseType::Status(response_metadata, status)
+                    };
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to get node status {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, "Failed to query node status".to_string());
+                response.send(http, fd)
+            }
+        }
+    }
+
     /// Handle a GET block.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
@@ -350,6 +648,20 @@ impl ConversationHttp {
 
         let response_metadata = HttpResponseMetadata::from(req);
 
+        // did we have it, once?
+        match StacksChainState::is_block_pruned(&chainstate.blocks_db, index_block_hash) {
+            Ok(true) => {
+                let response = HttpResponseType::NotFound(response_metadata, format!("Block {} was pruned", index_block_hash.to_hex()));
+                return response.send(http, fd).and_then(|_| Ok(None));
+            },
+            Ok(false) => {},
+            Err(e) => {
+                warn!("Failed to serve block {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query block {}", index_block_hash.to_hex()));
+                return response.send(http, fd).and_then(|_| Ok(None));
+            }
+        }
+
         // do we have this block?
         match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
             Ok(false) => {
@@ -372,144 +684,1015 @@ impl ConversationHttp {
         }
     }
     
-    /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getmicroblocks_confirmed<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_anchor_block_hash: &StacksBlockId, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
-        monitoring::increment_stx_confirmed_micro_blocks_served_counter();
-
+    /// Handle a GET block by height.  The height is resolved against the fork ending at the given
+    /// tip's index block hash, or the canonical Stacks chain tip if no tip is given.  Once the
+    /// block is found, start streaming the reply just like handle_getblock().
+    fn handle_getblock_by_height<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, height: u64, tip_opt: &Option<StacksBlockId>,
+                                           burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
 
-        match chainstate.get_confirmed_microblock_index_hash(index_anchor_block_hash) {
+        let tip = match tip_opt {
+            Some(tip) => tip.clone(),
+            None => match chainstate.get_stacks_chain_tip(burndb)? {
+                Some(tip) => StacksBlockHeader::make_index_block_hash(&tip.burn_header_hash, &tip.anchored_block_hash),
+                None => {
+                    warn!("Failed to load Stacks chain tip");
+                    let response = HttpResponseType::ServerError(response_metadata, format!("Failed to load Stacks chain tip"));
+                    return response.send(http, fd).and_then(|_| Ok(None));
+                }
+            }
+        };
+
+        let ancestor_res = chainstate.headers_tx_begin()
+            .and_then(|mut headers_tx| StacksChainState::get_index_tip_ancestor(&mut headers_tx, &tip, height));
+
+        match ancestor_res {
+            Ok(Some(header_info)) => {
+                let index_block_hash = header_info.index_block_hash();
+                ConversationHttp::handle_getblock(http, fd, req, &index_block_hash, chainstate)
+            },
+            Ok(None) => {
+                let response = HttpResponseType::NotFound(response_metadata, format!("No block at height {} in fork {}", height, tip.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
             Err(e) => {
-                // oops
-                warn!("Failed to serve confirmed microblock stream {:?}: {:?}", req, &e);
-                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query confirmed microblock stream from anchor block {}", index_anchor_block_hash.to_hex()));
+                warn!("Failed to serve block {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query block at height {} in fork {}", height, tip.to_hex()));
                 response.send(http, fd).and_then(|_| Ok(None))
+            }
+        }
+    }
+    
+    /// Handle a GET block by its block hash, without the burnchain header hash that would make it
+    /// an index block hash.  Once the block is found, start streaming the reply just like
+    /// handle_getblock().
+    fn handle_getblock_by_hash<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, block_hash: &BlockHeaderHash, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        match chainstate.get_stored_block_index_hash(block_hash) {
+            Ok(Some(index_block_hash)) => {
+                ConversationHttp::handle_getblock(http, fd, req, &index_block_hash, chainstate)
             },
             Ok(None) => {
-                // we don't have it
-                let response = HttpResponseType::NotFound(response_metadata, format!("No such confirmed microblock stream from anchor block {}", index_anchor_block_hash.to_hex()));
+                let response = HttpResponseType::NotFound(response_metadata, format!("No such block {}", block_hash.to_hex()));
                 response.send(http, fd).and_then(|_| Ok(None))
             },
-            Ok(Some(index_microblock_hash)) => {
-                // Have it!
-                let stream = BlockStreamData::new_microblock_confirmed(index_microblock_hash.clone());
-                let response = HttpResponseType::MicroblockStream(response_metadata);
-                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+            Err(e) => {
+                warn!("Failed to serve block {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query block {}", block_hash.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
             }
         }
     }
-
-    /// Handle a GET confirmed microblock stream, by _index microblock hash_.  Start streaming the reply.
+    
+    /// Handle a GET for a range of blocks, to be streamed back one after the other.  The heights
+    /// are resolved against the fork ending at the given tip's index block hash, or the canonical
+    /// Stacks chain tip if no tip is given, and the range stops early at that tip.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
-    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getmicroblocks_indexed<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_microblock_hash: &StacksBlockId, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
-        monitoring::increment_stx_micro_blocks_served_counter();
-
+    /// Return a BlockStreamData struct for the blocks that we're sending, so we can continue to
+    /// make progress sending them.
+    fn handle_export_blocks<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, start_height: u64, end_height: u64, format: BlockExportFormat,
+                                      tip_opt: &Option<StacksBlockId>, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
 
-        // do we have this confirmed microblock stream?
-        match chainstate.has_confirmed_microblocks_indexed(index_microblock_hash) {
-            Ok(false) => {
-                // nope
-                let response = HttpResponseType::NotFound(response_metadata, format!("No such confirmed microblock stream {}", index_microblock_hash.to_hex()));
-                response.send(http, fd).and_then(|_| Ok(None))
+        let tip = match tip_opt {
+            Some(tip) => tip.clone(),
+            None => match chainstate.get_stacks_chain_tip(burndb)? {
+                Some(tip) => StacksBlockHeader::make_index_block_hash(&tip.burn_header_hash, &tip.anchored_block_hash),
+                None => {
+                    warn!("Failed to load Stacks chain tip");
+                    let response = HttpResponseType::ServerError(response_metadata, format!("Failed to load Stacks chain tip"));
+                    return response.send(http, fd).and_then(|_| Ok(None));
+                }
+            }
+        };
+
+        let headers_res = chainstate.headers_tx_begin()
+            .and_then(|mut headers_tx| StacksChainState::get_index_tip_ancestors(&mut headers_tx, &tip, start_height, end_height));
+
+        match headers_res {
+            Ok(headers) => {
+                if headers.len() == 0 {
+                    let response = HttpResponseType::NotFound(response_metadata, format!("No blocks at heights {}-{} in fork {}", start_height, end_height, tip.to_hex()));
+                    return response.send(http, fd).and_then(|_| Ok(None));
+                }
+
+                let stream = BlockStreamData::new_block_export(headers, format);
+                let response = HttpResponseType::BlockExportStream(response_metadata, format);
+                response.send(http, fd).and_then(|_| Ok(Some(stream)))
             },
             Err(e) => {
-                // nope
-                warn!("Failed to serve confirmed microblock stream {:?}: {:?}", req, &e);
-                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query confirmed microblock stream {}", index_microblock_hash.to_hex()));
+                warn!("Failed to export blocks {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query blocks at heights {}-{} in fork {}", start_height, end_height, tip.to_hex()));
                 response.send(http, fd).and_then(|_| Ok(None))
-            },
-            Ok(true) => {
-                // yup! start streaming it back
-                let stream = BlockStreamData::new_microblock_confirmed(index_microblock_hash.clone());
-                let response = HttpResponseType::MicroblockStream(response_metadata);
-                response.send(http, fd).and_then(|_| Ok(Some(stream)))
             }
         }
     }
 
-    /// Handle a GET token transfer cost.  Reply the entire response.
-    /// TODO: accurately estimate the cost/length fee for token transfers, based on mempool
-    /// pressure.
-    fn handle_token_transfer_cost<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType) -> Result<(), net_error> {
+    /// Handle a GET for a page of a block's transactions, by index block hash.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getblock_transactions<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_block_hash: &StacksBlockId, offset: u64, limit: u64,
+                                              chainstate: &mut StacksChainState) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
 
-        // todo -- need to actually estimate the cost / length for token transfers
-        //   right now, it just uses the minimum.
-        let fee = MINIMUM_TX_FEE_RATE_PER_BYTE;
-        let response = HttpResponseType::TokenTransferCost(response_metadata, fee);
-        response.send(http, fd).map(|_| ())
+        let block_res = match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
+            Ok(true) => match chainstate.get_block_header_hashes(index_block_hash) {
+                Ok(Some((burn_header_hash, block_hash))) => StacksChainState::load_block(&chainstate.blocks_path, &burn_header_hash, &block_hash),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e)
+            },
+            Ok(false) => Ok(None),
+            Err(e) => Err(e)
+        };
+
+        let response = match block_res {
+            Ok(Some(block)) => {
+                let transactions = block.txs
+                    .iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .map(|tx| BlockTransactionEntry {
+                        txid: tx.txid().to_hex(),
+                        tx: to_hex(&tx.serialize_to_vec())
+                    })
+                    .collect();
+
+                let page = BlockTransactionsPage {
+                    index_block_hash: index_block_hash.to_hex(),
+                    total: block.txs.len() as u64,
+                    offset: offset,
+                    transactions: transactions
+                };
+                HttpResponseType::BlockTransactions(response_metadata, page)
+            },
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No such block {}", index_block_hash.to_hex())),
+            Err(e) => {
+                warn!("Failed to load block transactions {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, format!("Failed to load block {}", index_block_hash.to_hex()))
+            }
+        };
+        response.send(http, fd)
     }
 
-    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
-    /// MARF proof for each account detail loaded from the chain tip.
-    fn handle_get_account_entry<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
-                                          chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
-                                          account: &PrincipalData, with_proof: bool) -> Result<(), net_error> {
+    /// Handle a GET for a page of mempool txids.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getmempool_txids<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, cursor: &Option<Txid>, limit: u64, mempool: &MemPoolDB) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
 
-        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
-            clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                let key = ClarityDatabase::make_key_for_account_balance(&account);
-                let (balance, balance_proof) = clarity_db.get_with_proof::<u128>(&key)
-                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                    .unwrap_or_else(|| (0, "".into()));
-                let balance_proof = if with_proof {
-                    Some(balance_proof)
-                } else {
-                    None
-                };
-                let key = ClarityDatabase::make_key_for_account_nonce(&account);
-                let (nonce, nonce_proof) = clarity_db.get_with_proof(&key)
-                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                    .unwrap_or_else(|| (0, "".into()));
-                let nonce_proof = if with_proof {
-                    Some(nonce_proof)
-                } else {
+        let response = match mempool.read_conn().and_then(|conn| MemPoolDB::get_txids_after(&conn, cursor.as_ref(), limit)) {
+            Ok(txids) => {
+                // a short page means there's nothing left to fetch
+                let next_cursor = if (txids.len() as u64) < limit {
                     None
+                }
+                else {
+                    txids.last().map(|txid| txid.to_hex())
                 };
 
-                let balance = format!("0x{}", to_hex(&balance.to_be_bytes()));
-                AccountEntryResponse { balance, nonce, balance_proof, nonce_proof }
-            })
-        });
+                let page = MempoolTxidsPage {
+                    txids: txids.iter().map(|txid| txid.to_hex()).collect(),
+                    next_cursor: next_cursor
+                };
+                HttpResponseType::MempoolTxids(response_metadata, page)
+            },
+            Err(e) => {
+                warn!("Failed to query mempool txids {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query mempool".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
 
-        let response = HttpResponseType::GetAccount(
-            response_metadata, data);
+    /// Handle a GET for a summary of the mempool: how many transactions are pending, how large
+    /// they are, what fee rates they pay, and how long they've been waiting.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getmempool_stats<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, mempool: &MemPoolDB) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match mempool.read_conn().and_then(|conn| MemPoolDB::get_stats(&conn, get_epoch_time_secs())) {
+            Ok(stats) => HttpResponseType::MempoolStats(response_metadata, stats),
+            Err(e) => {
+                warn!("Failed to query mempool stats {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query mempool".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
 
-        response.send(http, fd).map(|_| ())
+    /// Handle a GET for the STX transfers to a principal with a given memo, in the canonical fork.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_transfers_by_memo<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash,
+                                              recipient: &PrincipalData, memo: &TokenTransferMemo, limit: u64, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = StacksBlockHeader::make_index_block_hash(burn_header_hash, block_hash);
+
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_transfer_memos(&mut tx, &tip, recipient, memo)) {
+            Ok(entries) => {
+                let transfers = entries
+                    .iter()
+                    .take(limit as usize)
+                    .map(|entry| TransferByMemoEntry {
+                        txid: entry.txid.to_hex(),
+                        sender: entry.sender.to_string(),
+                        recipient: entry.recipient.to_string(),
+                        amount: format!("{}", entry.amount),
+                        memo: to_hex(entry.memo.as_bytes()),
+                        block_hash: entry.block_hash.to_hex(),
+                        burn_header_hash: entry.burn_header_hash.to_hex(),
+                        index_block_hash: entry.index_block_hash().to_hex(),
+                        block_height: entry.block_height
+                    })
+                    .collect();
+                HttpResponseType::TransfersByMemo(response_metadata, transfers)
+            },
+            Err(e) => {
+                warn!("Failed to query transfers by memo {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query transfers".to_string())
+            }
+        };
+        response.send(http, fd)
     }
 
-    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
-    /// supplies a MARF proof for the value.
-    fn handle_get_map_entry<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
-                                      chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
-                                      contract_addr: &StacksAddress, contract_name: &ContractName,
-                                      map_name: &ClarityName, key: &Value, with_proof: bool) -> Result<(), net_error> {
+    /// Handle a GET for the receipt of a transaction mined in the canonical fork.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_transaction_receipt<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash,
+                                                txid: &Txid, chainstate: &mut StacksChainState) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let tip = StacksBlockHeader::make_index_block_hash(burn_header_hash, block_hash);
 
-        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
-            clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                let key = ClarityDatabase::make_key_for_data_map_entry(&contract_identifier, map_name, key);
-                let (value, marf_proof) = clarity_db.get_with_proof::<Value>(&key)
-                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                    .unwrap_or_else(|| (Value::none(), "".into()));
-                let marf_proof = if with_proof {
-                    Some(marf_proof)
-                } else {
-                    None
-                };
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_transaction_receipt(&mut tx, &tip, txid)) {
+            Ok(Some(entry)) => {
+                HttpResponseType::MinedTransactionReceipt(response_metadata, ConversationHttp::mined_receipt_data(&entry))
+            },
+            Ok(None) => {
+                HttpResponseType::NotFound(response_metadata, format!("No receipt for transaction {} in the canonical fork", txid.to_hex()))
+            },
+            Err(e) => {
+                warn!("Failed to query transaction receipt {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query transaction receipt".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
 
-                let data = format!("0x{}", value.serialize());
-                MapEntryResponse { data, marf_proof }
+    /// Handle a GET for the receipts of the transactions mined in a block, in the order they
+    /// were mined.  Responds with a 404 if the block hasn't been processed.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_block_receipts<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_block_hash: &StacksBlockId,
+                                           chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        let receipts_res = StacksChainState::get_stacks_block_header_info_by_index_block_hash(&chainstate.headers_db, index_block_hash)
+            .and_then(|header_info_opt| match header_info_opt {
+                Some(_) => StacksChainState::get_block_transaction_receipts(&chainstate.headers_db, index_block_hash).map(Some),
+                None => Ok(None)
+            });
+
+        let response = match receipts_res {
+            Ok(Some(entries)) => {
+                let receipts = entries.iter().map(ConversationHttp::mined_receipt_data).collect();
+                HttpResponseType::MinedTransactionReceipts(response_metadata, receipts)
+            },
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No such processed block {}", index_block_hash.to_hex())),
+            Err(e) => {
+                warn!("Failed to query block receipts {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query block receipts".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the receipts of the transactions involving a contract in the fork ending
+    /// at `tip_opt` (or the canonical chain tip), newest first.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_contract_receipts<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, contract_addr: &StacksAddress, contract_name: &ContractName,
+                                              tip_opt: &Option<StacksBlockId>, limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = match ConversationHttp::handle_load_tip_index_hash(http, fd, req, tip_opt, burndb, chainstate)? {
+            Some(tip) => tip,
+            None => return Ok(())
+        };
+        let contract_id = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_contract_transaction_receipts(&mut tx, &tip, &contract_id, limit)) {
+            Ok(entries) => {
+                let receipts = entries.iter().map(ConversationHttp::mined_receipt_data).collect();
+                HttpResponseType::MinedTransactionReceipts(response_metadata, receipts)
+            },
+            Err(e) => {
+                warn!("Failed to query contract receipts {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query contract receipts".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// The data we return for a stored transaction receipt
+    fn mined_receipt_data(entry: &TransactionReceiptEntry) -> MinedTransactionReceiptData {
+        MinedTransactionReceiptData {
+            txid: entry.txid.to_hex(),
+            status: entry.status.clone(),
+            result: format!("0x{}", entry.result.serialize()),
+            result_json: entry.result.json_serialize(),
+            events: entry.events.clone(),
+            abort_code: entry.abort_code.clone(),
+            block_hash: entry.block_hash.to_hex(),
+            burn_header_hash: entry.burn_header_hash.to_hex(),
+            index_block_hash: entry.index_block_hash().to_hex(),
+            block_height: entry.block_height
+        }
+    }
+
+    /// Is the block with this index hash and height an ancestor of (or the same as) the tip?
+    fn is_in_fork<'a>(tx: &mut StacksDBTx<'a>, tip: &StacksBlockId, index_block_hash: &StacksBlockId, block_height: u64) -> Result<bool, chain_error> {
+        match StacksChainState::get_index_tip_ancestor(tx, tip, block_height)? {
+            Some(ancestor) => Ok(ancestor.index_block_hash() == *index_block_hash),
+            None => Ok(false)
+        }
+    }
+
+    /// Find up to `limit` blocks, mined transactions and mempool transactions whose hashes start
+    /// with `prefix`.  Blocks and mined transactions are searched across all forks, and each is
+    /// marked as being in the fork ending at `tip` or not.
+    fn search_hash_prefix(tip: &StacksBlockId, prefix: &str, limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState, mempool: &MemPoolDB) -> Result<HashPrefixSearchData, chain_error> {
+        let mut matches = vec![];
+        let mut truncated = false;
+
+        // fetch one more than the limit of each, so we can tell if the list is truncated
+        let mut tx = chainstate.headers_tx_begin()?;
+        let headers = StacksChainState::find_headers_by_hash_prefix(&tx, prefix, limit + 1)?;
+        truncated |= (headers.len() as u64) > limit;
+        for header in headers.into_iter() {
+            let block_hash = header.anchored_header.block_hash().to_hex();
+            let index_block_hash = header.index_block_hash();
+            let (field, hash) =
+                if block_hash.starts_with(prefix) {
+                    ("block_hash", block_hash)
+                }
+                else {
+                    ("index_block_hash", index_block_hash.to_hex())
+                };
+            let canonical = ConversationHttp::is_in_fork(&mut tx, tip, &index_block_hash, header.block_height)?;
+            matches.push(HashPrefixMatch {
+                match_type: "block".to_string(),
+                field: field.to_string(),
+                hash: hash,
+                index_block_hash: Some(index_block_hash.to_hex()),
+                block_height: Some(header.block_height),
+                canonical: Some(canonical),
+                balance: None,
+                nonce: None
+            });
+        }
+
+        let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn()).map_err(chain_error::DBError)?;
+        let snapshots = BurnDB::find_snapshots_by_hash_prefix(burndb.conn(), prefix, limit + 1).map_err(chain_error::DBError)?;
+        truncated |= (snapshots.len() as u64) > limit;
+        for snapshot in snapshots.into_iter() {
+            let canonical = BurnDB::get_block_snapshot_in_fork(&burndb.index_conn(), snapshot.block_height, &burn_tip.burn_header_hash)
+                .map_err(chain_error::DBError)?
+                .map(|sn| sn.burn_header_hash == snapshot.burn_header_hash)
+                .unwrap_or(false);
+
+            // the Stacks block this burn block selected, if we have processed it
+            let index_block_hash =
+                if snapshot.sortition && snapshot.stacks_block_accepted {
+                    Some(StacksBlockHeader::make_index_block_hash(&snapshot.burn_header_hash, &snapshot.winning_stacks_block_hash).to_hex())
+                }
+                else {
+                    None
+                };
+
+            matches.push(HashPrefixMatch {
+                match_type: "burn_block".to_string(),
+                field: "burn_header_hash".to_string(),
+                hash: snapshot.burn_header_hash.to_hex(),
+                index_block_hash: index_block_hash,
+                block_height: Some(snapshot.block_height),
+                canonical: Some(canonical),
+                balance: None,
+                nonce: None
+            });
+        }
+
+        let receipts = StacksChainState::find_transaction_receipts_by_prefix(&tx, prefix, limit + 1)?;
+        truncated |= (receipts.len() as u64) > limit;
+        for receipt in receipts.into_iter() {
+            let index_block_hash = receipt.index_block_hash();
+            let canonical = ConversationHttp::is_in_fork(&mut tx, tip, &index_block_hash, receipt.block_height)?;
+            matches.push(HashPrefixMatch {
+                match_type: "transaction".to_string(),
+                field: "txid".to_string(),
+                hash: receipt.txid.to_hex(),
+                index_block_hash: Some(index_block_hash.to_hex()),
+                block_height: Some(receipt.block_height),
+                canonical: Some(canonical),
+                balance: None,
+                nonce: None
+            });
+        }
+
+        let txids = mempool.read_conn()
+            .and_then(|conn| MemPoolDB::find_txids_by_prefix(&conn, prefix, limit + 1))
+            .map_err(chain_error::DBError)?;
+        truncated |= (txids.len() as u64) > limit;
+        for txid in txids.into_iter() {
+            matches.push(HashPrefixMatch {
+                match_type: "mempool_transaction".to_string(),
+                field: "txid".to_string(),
+                hash: txid.to_hex(),
+                index_block_hash: None,
+                block_height: None,
+                canonical: None,
+                balance: None,
+                nonce: None
+            });
+        }
+
+        // the same transaction or block can show up more than once (e.g. mined in two forks), so
+        // the prefix is only ambiguous if it matches different hashes
+        let distinct_hashes : HashSet<&String> = matches.iter().map(|m| &m.hash).collect();
+        let ambiguous = distinct_hashes.len() > 1;
+
+        truncated |= (matches.len() as u64) > limit;
+        matches.truncate(limit as usize);
+
+        Ok(HashPrefixSearchData {
+            prefix: prefix.to_string(),
+            matches,
+            ambiguous,
+            truncated
+        })
+    }
+
+    /// Look up a principal at the given chain tip.  A standard principal always matches, as an
+    /// account (possibly empty); a contract principal only matches if the contract exists.
+    fn search_principal(burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash, principal: &PrincipalData, chainstate: &mut StacksChainState) -> HashPrefixSearchData {
+        let found = chainstate.with_read_only_clarity_tx(burn_header_hash, block_hash, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                match *principal {
+                    PrincipalData::Standard(_) => {
+                        let balance = clarity_db.get_account_stx_balance(principal);
+                        let nonce = clarity_db.get_account_nonce(principal);
+                        Some(HashPrefixMatch {
+                            match_type: "account".to_string(),
+                            field: "address".to_string(),
+                            hash: principal.to_string(),
+                            index_block_hash: None,
+                            block_height: None,
+                            canonical: None,
+                            balance: Some(format!("0x{}", to_hex(&balance.to_be_bytes()))),
+                            nonce: Some(nonce)
+                        })
+                    },
+                    PrincipalData::Contract(ref contract_identifier) => {
+                        let contract_commit_key = MarfedKV::make_contract_hash_key(contract_identifier);
+                        clarity_db.get_with_proof::<ContractCommitment>(&contract_commit_key)
+                            .map(|(contract_commit, _proof)| HashPrefixMatch {
+                                match_type: "contract".to_string(),
+                                field: "contract_id".to_string(),
+                                hash: principal.to_string(),
+                                index_block_hash: None,
+                                block_height: Some(contract_commit.block_height as u64),
+                                canonical: None,
+                                balance: None,
+                                nonce: None
+                            })
+                    }
+                }
+            })
+        });
+
+        HashPrefixSearchData {
+            prefix: principal.to_string(),
+            matches: found.into_iter().collect(),
+            ambiguous: false,
+            truncated: false
+        }
+    }
+
+    /// Handle a GET for whatever a search query names: the blocks, burn blocks and transactions
+    /// whose hashes start with a hex prefix, or the account or contract a principal names.  This
+    /// way truncated hashes can be resolved, and a search box can tell what it was given.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_search_hash_prefix<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, burn_header_hash: &BurnchainHeaderHash, block_hash: &BlockHeaderHash,
+                                           query: &str, limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState, mempool: &MemPoolDB) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = StacksBlockHeader::make_index_block_hash(burn_header_hash, block_hash);
+
+        let search_result = match PrincipalData::parse(query) {
+            Ok(principal) => Ok(ConversationHttp::search_principal(burn_header_hash, block_hash, &principal, chainstate)),
+            Err(_) => ConversationHttp::search_hash_prefix(&tip, query, limit, burndb, chainstate, mempool)
+        };
+
+        let response = match search_result {
+            Ok(search) => HttpResponseType::HashPrefixMatches(response_metadata, search),
+            Err(e) => {
+                warn!("Failed to search for hash prefix {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to search for hash prefix".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the total, unlocked and locked STX supply as of the given tip, or the
+    /// canonical chain tip if none is given.  Tips processed before the node tracked the supply
+    /// don't have one.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_stx_supply<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, tip_opt: &Option<StacksBlockId>,
+                                       burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        let tip = match tip_opt {
+            Some(tip) => tip.clone(),
+            None => match chainstate.get_stacks_chain_tip(burndb)? {
+                Some(tip) => StacksBlockHeader::make_index_block_hash(&tip.burn_header_hash, &tip.anchored_block_hash),
+                None => {
+                    warn!("Failed to load Stacks chain tip");
+                    let response = HttpResponseType::ServerError(response_metadata, format!("Failed to load Stacks chain tip"));
+                    return response.send(http, fd);
+                }
+            }
+        };
+
+        let response = match chainstate.headers_tx_begin().and_then(|tx| StacksChainState::get_stx_supply(&tx, &tip)) {
+            Ok(Some(supply)) => HttpResponseType::StxSupply(response_metadata, StxSupplyData {
+                total_stx: supply.total_ustx().to_string(),
+                unlocked_stx: supply.liquid_ustx.to_string(),
+                locked_stx: supply.locked_ustx.to_string(),
+                index_block_hash: tip.to_hex(),
+                block_height: supply.block_height
+            }),
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No STX supply known for {}", tip.to_hex())),
+            Err(e) => {
+                warn!("Failed to query STX supply {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query STX supply".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Get the index hash of `tip_opt`, or of the canonical Stacks chain tip if it's not given.
+    /// Responds with a server error and returns None if there's no canonical tip.
+    fn handle_load_tip_index_hash<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, tip_opt: &Option<StacksBlockId>,
+                                            burndb: &BurnDB, chainstate: &StacksChainState) -> Result<Option<StacksBlockId>, net_error> {
+        match tip_opt {
+            Some(tip) => Ok(Some(tip.clone())),
+            None => match ConversationHttp::handle_load_stacks_chain_tip(http, fd, req, burndb, chainstate)? {
+                Some((burn_block, block)) => Ok(Some(StacksBlockHeader::make_index_block_hash(&burn_block, &block))),
+                None => Ok(None)
+            }
+        }
+    }
+
+    /// Handle a GET for an account's balances in the fork ending at `tip_opt` (or the canonical
+    /// chain tip), newest first, from the balance index.  Responds with a 404 if the tip's
+    /// balances aren't indexed.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_balance_history<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, principal: &PrincipalData, tip_opt: &Option<StacksBlockId>,
+                                            limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = match ConversationHttp::handle_load_tip_index_hash(http, fd, req, tip_opt, burndb, chainstate)? {
+            Some(tip) => tip,
+            None => return Ok(())
+        };
+
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_account_balance_history(&mut tx, &tip, principal, limit)) {
+            Ok(Some(entries)) => {
+                let history = entries
+                    .iter()
+                    .map(|entry| BalanceHistoryEntry {
+                        balance: entry.stx_balance.to_string(),
+                        nonce: entry.nonce,
+                        index_block_hash: entry.index_block_hash.to_hex(),
+                        block_height: entry.block_height
+                    })
+                    .collect();
+                HttpResponseType::BalanceHistory(response_metadata, history)
+            },
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No balances indexed for {}", tip.to_hex())),
+            Err(e) => {
+                warn!("Failed to query balance history {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query balance history".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the accounts with the most STX as of `tip_opt` (or the canonical chain
+    /// tip), from the balance index.  Responds with a 404 if the tip's balances aren't indexed.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_top_balances<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, tip_opt: &Option<StacksBlockId>,
+                                         limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = match ConversationHttp::handle_load_tip_index_hash(http, fd, req, tip_opt, burndb, chainstate)? {
+            Some(tip) => tip,
+            None => return Ok(())
+        };
+
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_top_balances(&mut tx, &tip, limit)) {
+            Ok(Some(accounts)) => {
+                let accounts = accounts
+                    .iter()
+                    .map(|account| AccountBalanceData {
+                        principal: account.principal.to_string(),
+                        balance: account.stx_balance.to_string(),
+                        nonce: account.nonce
+                    })
+                    .collect();
+                HttpResponseType::TopBalances(response_metadata, accounts)
+            },
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No balances indexed for {}", tip.to_hex())),
+            Err(e) => {
+                warn!("Failed to query top balances {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query top balances".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the transactions an account or contract took part in, in the fork ending
+    /// at `tip_opt` (or the canonical chain tip), newest first, from the address index.
+    /// Responds with a 404 if the tip's transactions aren't indexed.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_address_transactions<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, principal: &PrincipalData, tip_opt: &Option<StacksBlockId>,
+                                                 limit: u64, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let tip = match ConversationHttp::handle_load_tip_index_hash(http, fd, req, tip_opt, burndb, chainstate)? {
+            Some(tip) => tip,
+            None => return Ok(())
+        };
+
+        let response = match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_address_transactions(&mut tx, &tip, principal, limit)) {
+            Ok(Some(entries)) => {
+                let transactions = entries
+                    .iter()
+                    .map(|entry| AddressTransactionData {
+                        txid: entry.txid.to_hex(),
+                        index_block_hash: entry.index_block_hash.to_hex(),
+                        block_height: entry.block_height,
+                        tx_index: entry.tx_index
+                    })
+                    .collect();
+                HttpResponseType::AddressTransactions(response_metadata, transactions)
+            },
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No transactions indexed by address for {}", tip.to_hex())),
+            Err(e) => {
+                warn!("Failed to query address transactions {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query address transactions".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for what each contract cost to run in a block.  Blocks processed before the
+    /// node tracked contract costs list none, and so do unmetered ones.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_block_contract_costs<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_block_hash: &StacksBlockId,
+                                                 chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let block_limit = chainstate.block_limit();
+
+        let costs_res = chainstate.headers_tx_begin().and_then(|tx| {
+            match StacksChainState::get_stacks_block_header_info_by_index_block_hash(&tx, index_block_hash)? {
+                Some(header_info) => {
+                    let unmetered = StacksChainState::is_block_unmetered(&tx, index_block_hash)?;
+                    let costs = StacksChainState::get_contract_costs(&tx, index_block_hash)?;
+                    Ok(Some((header_info, unmetered, costs)))
+                },
+                None => Ok(None)
+            }
+        });
+
+        let response = match costs_res {
+            Ok(Some((header_info, unmetered, costs))) => HttpResponseType::BlockContractCosts(response_metadata, BlockContractCostsData {
+                index_block_hash: index_block_hash.to_hex(),
+                block_height: header_info.block_height,
+                block_limit,
+                metered: !unmetered,
+                contracts: costs.into_iter()
+                    .map(|contract_cost| ContractCostData {
+                        contract_id: contract_cost.contract_id.to_string(),
+                        transactions: contract_cost.transactions,
+                        cost: contract_cost.cost
+                    })
+                    .collect()
+            }),
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No such processed block {}", index_block_hash.to_hex())),
+            Err(e) => {
+                warn!("Failed to query contract costs {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to query contract costs".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the node's Prometheus metrics, if they're enabled.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getmetrics<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, options: &ConnectionOptions) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.rpc_metrics_enabled {
+            HttpResponseType::NotFound(response_metadata, "Metrics are not enabled on this node".to_string())
+        }
+        else {
+            match monitoring::render_metrics() {
+                Some(text) => HttpResponseType::Metrics(response_metadata, text),
+                None => HttpResponseType::NotFound(response_metadata, "This node was built without metrics support".to_string())
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getmicroblocks_confirmed<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_anchor_block_hash: &StacksBlockId, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
+        monitoring::increment_stx_confirmed_micro_blocks_served_counter();
+
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // pruning an anchored block discards the stream it produced, too
+        match StacksChainState::is_block_pruned(&chainstate.blocks_db, index_anchor_block_hash) {
+            Ok(true) => {
+                let response = HttpResponseType::NotFound(response_metadata, format!("Confirmed microblock stream from anchor block {} was pruned", index_anchor_block_hash.to_hex()));
+                return response.send(http, fd).and_then(|_| Ok(None));
+            },
+            Ok(false) => {},
+            Err(e) => {
+                warn!("Failed to serve confirmed microblock stream {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query confirmed microblock stream from anchor block {}", index_anchor_block_hash.to_hex()));
+                return response.send(http, fd).and_then(|_| Ok(None));
+            }
+        }
+
+        match chainstate.get_confirmed_microblock_index_hash(index_anchor_block_hash) {
+            Err(e) => {
+                // oops
+                warn!("Failed to serve confirmed microblock stream {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query confirmed microblock stream from anchor block {}", index_anchor_block_hash.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
+            Ok(None) => {
+                // we don't have it
+                let response = HttpResponseType::NotFound(response_metadata, format!("No such confirmed microblock stream from anchor block {}", index_anchor_block_hash.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
+            Ok(Some(index_microblock_hash)) => {
+                // Have it!
+                let stream = BlockStreamData::new_microblock_confirmed(index_microblock_hash.clone());
+                let response = HttpResponseType::MicroblockStream(response_metadata);
+                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+            }
+        }
+    }
+
+    /// Handle a GET confirmed microblock stream, by _index microblock hash_.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getmicroblocks_indexed<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, index_microblock_hash: &StacksBlockId, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
+        monitoring::increment_stx_micro_blocks_served_counter();
+
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // do we have this confirmed microblock stream?
+        match chainstate.has_confirmed_microblocks_indexed(index_microblock_hash) {
+            Ok(false) => {
+                // nope
+                let response = HttpResponseType::NotFound(response_metadata, format!("No such confirmed microblock stream {}", index_microblock_hash.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
+            Err(e) => {
+                // nope
+                warn!("Failed to serve confirmed microblock stream {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to query confirmed microblock stream {}", index_microblock_hash.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
+            Ok(true) => {
+                // yup! start streaming it back
+                let stream = BlockStreamData::new_microblock_confirmed(index_microblock_hash.clone());
+                let response = HttpResponseType::MicroblockStream(response_metadata);
+                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+            }
+        }
+    }
+
+    /// Handle a GET microblock by its hash, whether it's confirmed or still in staging.  Reply the
+    /// entire microblock.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getmicroblock_by_hash<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, microblock_hash: &BlockHeaderHash, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        monitoring::increment_stx_micro_blocks_served_counter();
+
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match chainstate.load_microblock_by_hash(microblock_hash) {
+            Ok(Some(microblock)) => HttpResponseType::Microblock(response_metadata, microblock),
+            Ok(None) => HttpResponseType::NotFound(response_metadata, format!("No such microblock {}", microblock_hash.to_hex())),
+            Err(e) => {
+                warn!("Failed to serve microblock {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(response_metadata, format!("Failed to query microblock {}", microblock_hash.to_hex()))
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET token transfer cost.  Reply the entire response.
+    /// TODO: accurately estimate the cost/length fee for token transfers, based on mempool
+    /// pressure.
+    fn handle_token_transfer_cost<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // todo -- need to actually estimate the cost / length for token transfers
+        //   right now, it just uses the minimum.
+        let fee = MINIMUM_TX_FEE_RATE_PER_BYTE;
+        let response = HttpResponseType::TokenTransferCost(response_metadata, fee);
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// The length of a single-signature transaction from `sender` carrying `payload`.  Signing
+    /// doesn't change a transaction's length, so an unsigned one will do.
+    fn estimate_tx_len(mainnet: bool, sender: &StacksAddress, payload: &TransactionPayload) -> u64 {
+        let auth = TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+            hash_mode: SinglesigHashMode::P2PKH,
+            signer: sender.bytes.clone(),
+            nonce: 0,
+            fee_rate: 0,
+            key_encoding: TransactionPublicKeyEncoding::Compressed,
+            signature: MessageSignature::empty()
+        }));
+        let version = if mainnet { TransactionVersion::Mainnet } else { TransactionVersion::Testnet };
+        let tx = StacksTransaction::new(version, auth, payload.clone());
+        tx.serialize_to_vec().len() as u64
+    }
+
+    /// Handle a POST to estimate the fee of a transaction with the given payload, as of the given
+    /// chain tip: run the payload within a block's cost limit, and price a single-signature
+    /// transaction carrying it.  Fees only depend on length for now, so the suggested fee is the
+    /// least the mempool takes for that length.
+    fn handle_estimate_fee<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                     chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                     sender: &StacksAddress, payload: &TransactionPayload) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let cost_limit = chainstate.block_limit();
+
+        let estimated_len = ConversationHttp::estimate_tx_len(chainstate.mainnet, sender, payload);
+        let suggested_fee = cmp::max(MINIMUM_TX_FEE, estimated_len * MINIMUM_TX_FEE_RATE_PER_BYTE);
+
+        let result = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            StacksChainState::estimate_payload_cost(clarity_tx, sender, payload, cost_limit)
+        });
+
+        let response = match result {
+            Ok(estimate) => HttpResponseType::FeeEstimate(response_metadata, FeeEstimateResponse {
+                okay: estimate.runtime_error.is_none(),
+                cost: Some(estimate.cost),
+                result: estimate.result.as_ref().map(|value| format!("0x{}", value.serialize())),
+                result_json: estimate.result.as_ref().map(|value| value.json_serialize()),
+                estimated_len,
+                suggested_fee,
+                cause: estimate.runtime_error
+            }),
+            Err(e @ chain_error::ClarityError(_)) | Err(e @ chain_error::InvalidStacksTransaction(_)) =>
+                HttpResponseType::FeeEstimate(response_metadata, FeeEstimateResponse {
+                    okay: false,
+                    cost: None,
+                    result: None,
+                    result_json: None,
+                    estimated_len,
+                    suggested_fee,
+                    cause: Some(e.to_string())
+                }),
+            Err(e) => {
+                warn!("Failed to estimate the fee of a payload from {}: {:?}", sender, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to estimate fee".to_string())
+            }
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to simulate a contract call as of the given chain tip, after making the given
+    /// changes to chain state, within a block's cost limit.  Nothing is stored, and the changes
+    /// only last as long as the simulation.  Calls and overrides that aren't valid get a 200
+    /// response saying why, like failed fee estimates.
+    fn handle_simulate_transaction<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                             chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                             sender: &StacksAddress, payload: &TransactionPayload, overrides: &[StateOverride]) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let cost_limit = chainstate.block_limit();
+
+        let result = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            StacksChainState::simulate_payload(clarity_tx, sender, payload, overrides, cost_limit)
+        });
+
+        let response = match result {
+            Ok(simulation) => HttpResponseType::SimulateTransaction(response_metadata, SimulateTransactionResponse {
+                okay: simulation.runtime_error.is_none(),
+                cost: Some(simulation.cost),
+                result: simulation.result.as_ref().map(|value| format!("0x{}", value.serialize())),
+                result_json: simulation.result.as_ref().map(|value| value.json_serialize()),
+                events: simulation.events.iter().map(|event| event.json_serialize_event()).collect(),
+                cause: simulation.runtime_error
+            }),
+            Err(e @ chain_error::ClarityError(_)) | Err(e @ chain_error::InvalidStacksTransaction(_)) =>
+                HttpResponseType::SimulateTransaction(response_metadata, SimulateTransactionResponse {
+                    okay: false,
+                    cost: None,
+                    result: None,
+                    result_json: None,
+                    events: vec![],
+                    cause: Some(e.to_string())
+                }),
+            Err(e) => {
+                warn!("Failed to simulate a payload from {}: {:?}", sender, &e);
+                HttpResponseType::ServerError(response_metadata, "Failed to simulate transaction".to_string())
+            }
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
+    /// MARF proof for each account detail loaded from the chain tip.
+    fn handle_get_account_entry<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                          chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                          account: &PrincipalData, with_proof: bool) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // without proofs, the balance index can answer without a MARF lookup, if it's indexed
+        if !with_proof {
+            let tip = StacksBlockHeader::make_index_block_hash(cur_burn, cur_block);
+            match chainstate.headers_tx_begin().and_then(|mut tx| StacksChainState::get_indexed_account(&mut tx, &tip, account)) {
+                Ok(Some(indexed)) => {
+                    let balance = format!("0x{}", to_hex(&indexed.stx_balance.to_be_bytes()));
+                    let data = AccountEntryResponse { balance, nonce: indexed.nonce, balance_proof: None, nonce_proof: None };
+                    let response = HttpResponseType::GetAccount(response_metadata, data);
+                    return response.send(http, fd).map(|_| ());
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    warn!("Failed to look up {} in the balance index: {:?}", account, &e);
+                }
+            }
+        }
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_account_balance(&account);
+                let (balance, balance_proof) = clarity_db.get_with_proof::<u128>(&key)
+                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
+                    .unwrap_or_else(|| (0, "".into()));
+                let balance_proof = if with_proof {
+                    Some(balance_proof)
+                } else {
+                    None
+                };
+                let key = ClarityDatabase::make_key_for_account_nonce(&account);
+                let (nonce, nonce_proof) = clarity_db.get_with_proof(&key)
+                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
+                    .unwrap_or_else(|| (0, "".into()));
+                let nonce_proof = if with_proof {
+                    Some(nonce_proof)
+                } else {
+                    None
+                };
+
+                let balance = format!("0x{}", to_hex(&balance.to_be_bytes()));
+                AccountEntryResponse { balance, nonce, balance_proof, nonce_proof }
+            })
+        });
+
+        let response = HttpResponseType::GetAccount(
+            response_metadata, data);
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
+    /// supplies a MARF proof for the value.
+    fn handle_get_map_entry<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                      chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                      contract_addr: &StacksAddress, contract_name: &ContractName,
+                                      map_name: &ClarityName, key: &Value, with_proof: bool) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_data_map_entry(&contract_identifier, map_name, key);
+                let (value, marf_proof) = clarity_db.get_with_proof::<Value>(&key)
+                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
+                    .unwrap_or_else(|| (Value::none(), "".into()));
+                let marf_proof = if with_proof {
+                    Some(marf_proof)
+                } else {
+                    None
+                };
+
+                let data = format!("0x{}", value.serialize());
+                MapEntryResponse { data, data_json: value.json_serialize(), marf_proof }
             })
         });
 
@@ -519,34 +1702,266 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
+    /// Handle a GET for a contract's data variable, with a MARF proof of its value unless
+    /// with_proof is false.  Responds with a 404 if there's no such variable.
+    fn handle_get_data_var<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                     chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                     contract_addr: &StacksAddress, contract_name: &ContractName,
+                                     var_name: &ClarityName, with_proof: bool) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_data_var(&contract_identifier, var_name);
+                // every data variable is set when its contract is launched
+                clarity_db.get_with_proof::<Value>(&key)
+                    .map(|(value, marf_proof)| {
+                        let marf_proof = if with_proof {
+                            Some(format!("0x{}", marf_proof.to_hex()))
+                        } else {
+                            None
+                        };
+                        let data = format!("0x{}", value.serialize());
+                        DataVarResponse { data, data_json: value.json_serialize(), marf_proof }
+                    })
+            })
+        });
+
+        let response = match data {
+            Some(data) => HttpResponseType::GetDataVar(response_metadata, data),
+            None => HttpResponseType::NotFound(response_metadata, format!("No such data variable {} in {}", var_name.as_str(), &contract_identifier))
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for a principal's balance of a contract's fungible token, with a MARF proof
+    /// of it unless with_proof is false.  Principals that have never held the token have a 0
+    /// balance, and an empty proof.
+    fn handle_get_ft_balance<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                       chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                       contract_addr: &StacksAddress, contract_name: &ContractName,
+                                       token_name: &ClarityName, principal: &PrincipalData, with_proof: bool) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_ft_balance(&contract_identifier, token_name, principal);
+                let (balance, marf_proof) = clarity_db.get_with_proof::<u128>(&key)
+                    .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
+                    .unwrap_or_else(|| (0, "".into()));
+                let marf_proof = if with_proof {
+                    Some(marf_proof)
+                } else {
+                    None
+                };
+
+                let balance = format!("0x{}", to_hex(&balance.to_be_bytes()));
+                FungibleTokenBalanceResponse { balance, marf_proof }
+            })
+        });
+
+        let response = HttpResponseType::GetFungibleTokenBalance(response_metadata, data);
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST for the owner of one of a contract's non-fungible tokens, with a MARF proof
+    /// of it unless with_proof is false.  Tokens that haven't been minted have no owner, and an
+    /// empty proof.
+    fn handle_get_nft_owner<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                      chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                      contract_addr: &StacksAddress, contract_name: &ContractName,
+                                      asset_name: &ClarityName, asset: &Value, with_proof: bool) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_nft_owner(&contract_identifier, asset_name, asset);
+                let (owner, marf_proof) = clarity_db.get_with_proof::<PrincipalData>(&key)
+                    .map(|(a, b)| (Some(a.to_string()), format!("0x{}", b.to_hex())))
+                    .unwrap_or_else(|| (None, "".into()));
+                let marf_proof = if with_proof {
+                    Some(marf_proof)
+                } else {
+                    None
+                };
+
+                NonFungibleTokenOwnerResponse { owner, marf_proof }
+            })
+        });
+
+        let response = HttpResponseType::GetNonFungibleTokenOwner(response_metadata, data);
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Handle a POST to run a read-only function call with the given parameters on the given chain
     /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
     fn handle_readonly_function_call<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
                                                chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash,
                                                cur_block: &BlockHeaderHash, contract_addr: &StacksAddress, contract_name: &ContractName,
-                                               function: &ClarityName, sender: &PrincipalData, args: &[Value], options: &ConnectionOptions) -> Result<(), net_error> {
+                                               function: &ClarityName, sender: &PrincipalData, args: &[Value], with_profile: bool, options: &ConnectionOptions) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
         let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        let cost_track = LimitedCostTracker::new(options.read_only_call_limit.clone());
+        let mut cost_track = LimitedCostTracker::new(options.read_only_call_limit.clone());
+        if with_profile {
+            cost_track.enable_profiling();
+        }
 
         let args: Vec<_> = args.iter().map(|x| SymbolicExpression::atom_value(x.clone())).collect();
 
-        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
-            clarity_tx.with_readonly_clarity_env(sender.clone(), cost_track, |env| {
-                env.execute_contract(&contract_identifier, function.as_str(), &args, true)
-            })
+        // profiles are reported even if the call fails, since running out of budget is when
+        // they're most useful
+        let mut profile = None;
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_readonly_clarity_env(sender.clone(), cost_track, |env| {
+                let result = env.execute_contract(&contract_identifier, function.as_str(), &args, true);
+                profile = env.global_context.cost_track.take_profile();
+                result
+            })
+        });
+
+        let response = match data {
+            Ok(data) => 
+                CallReadOnlyResponse { okay: true, result: Some(format!("0x{}", data.serialize())), result_json: Some(data.json_serialize()), cause: None, profile },
+            Err(e) =>
+                CallReadOnlyResponse { okay: false, result: None, result_json: None, cause: Some(e.to_string()), profile },
+        };
+
+        let response = HttpResponseType::CallReadOnlyFunction(response_metadata, response);
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Every syntax error in a contract's source, if the parse error it failed with is one of
+    /// them.  Otherwise, just the parse error.
+    fn parse_error_diagnostics(source: &str, error: &ParseError) -> Vec<Diagnostic> {
+        match parser::parse_reporting_all_errors(source) {
+            Err(errors) => errors.into_iter().map(|e| e.diagnostic).collect(),
+            Ok(_) => vec![error.diagnostic.clone()]
+        }
+    }
+
+    /// Handle a POST to analyze a contract's source as of the given chain tip, without deploying
+    /// it.  Runs the same checks a deploy would, within a block's cost limit.  Contracts that
+    /// fail them get a 200 response saying why, like failed read-only calls.
+    fn handle_analyze_contract<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                         chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                         deployer: &StacksAddress, contract_name: &ContractName, source: &str) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(deployer.clone().into(), contract_name.clone());
+        let cost_limit = chainstate.block_limit();
+
+        let result = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            StacksChainState::analyze_contract(clarity_tx, &contract_identifier, source, cost_limit)
+        });
+
+        let failed = |cause: String, diagnostic: Option<Diagnostic>, errors| AnalyzeContractResponse {
+            okay: false,
+            contract_id: contract_identifier.to_string(),
+            interface: None,
+            defined_traits: vec![],
+            implemented_traits: vec![],
+            cost: None,
+            cause: Some(cause),
+            diagnostic,
+            errors,
+            warnings: vec![]
+        };
+
+        let response = match result {
+            Ok((contract_analysis, cost)) => HttpResponseType::AnalyzeContract(response_metadata, AnalyzeContractResponse {
+                okay: true,
+                contract_id: contract_identifier.to_string(),
+                interface: Some(build_contract_interface(&contract_analysis)),
+                defined_traits: contract_analysis.defined_traits.keys().map(|name| name.to_string()).collect(),
+                implemented_traits: contract_analysis.implemented_traits.iter().map(|trait_id| trait_id.to_string()).collect(),
+                cost: Some(cost),
+                cause: None,
+                diagnostic: None,
+                errors: vec![],
+                warnings: Linter::run(&contract_analysis)
+            }),
+            Err(chain_error::ClarityError(clarity_error::Parse(e))) => {
+                let errors = ConversationHttp::parse_error_diagnostics(source, &e);
+                HttpResponseType::AnalyzeContract(response_metadata, failed(e.to_string(), Some(e.diagnostic), errors))
+            },
+            Err(chain_error::ClarityError(clarity_error::Analysis(e))) =>
+                HttpResponseType::AnalyzeContract(response_metadata, failed(e.to_string(), Some(e.diagnostic.clone()), vec![e.diagnostic])),
+            Err(e @ chain_error::ClarityError(_)) | Err(e @ chain_error::InvalidStacksTransaction(_)) =>
+                HttpResponseType::AnalyzeContract(response_metadata, failed(e.to_string(), None, vec![])),
+            Err(e) => {
+                warn!("Failed to analyze contract {}: {:?}", &contract_identifier, &e);
+                HttpResponseType::ServerError(response_metadata, format!("Failed to analyze contract {}", &contract_identifier))
+            }
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to dry-run a contract deploy as of the given chain tip: everything the
+    /// analyze endpoint does, and then running the contract's top-level expressions, within a
+    /// block's cost limit.  Nothing is stored.
+    fn handle_dry_run_contract<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                         chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                         deployer: &StacksAddress, contract_name: &ContractName, source: &str) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(deployer.clone().into(), contract_name.clone());
+        let cost_limit = chainstate.block_limit();
+
+        let result = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            StacksChainState::dry_run_contract_deploy(clarity_tx, &contract_identifier, source, cost_limit)
         });
 
+        let failed = |cause: String, diagnostic: Option<Diagnostic>, errors| DryRunContractResponse {
+            okay: false,
+            contract_id: contract_identifier.to_string(),
+            name_collision: false,
+            interface: None,
+            defined_traits: vec![],
+            implemented_traits: vec![],
+            analysis_cost: None,
+            deploy_cost: None,
+            cause: Some(cause),
+            diagnostic,
+            errors,
+            warnings: vec![]
+        };
 
-        let response = match data {
-            Ok(data) => 
-                CallReadOnlyResponse { okay: true, result: Some(format!("0x{}", data.serialize())), cause: None },
-            Err(e) =>
-                CallReadOnlyResponse { okay: false, result: None, cause: Some(e.to_string()) },
+        let response = match result {
+            Ok(dry_run) => {
+                let contract_analysis = &dry_run.contract_analysis;
+                HttpResponseType::DryRunContract(response_metadata, DryRunContractResponse {
+                    okay: !dry_run.name_collision,
+                    contract_id: contract_identifier.to_string(),
+                    name_collision: dry_run.name_collision,
+                    interface: Some(build_contract_interface(contract_analysis)),
+                    defined_traits: contract_analysis.defined_traits.keys().map(|name| name.to_string()).collect(),
+                    implemented_traits: contract_analysis.implemented_traits.iter().map(|trait_id| trait_id.to_string()).collect(),
+                    analysis_cost: Some(dry_run.analysis_cost.clone()),
+                    deploy_cost: dry_run.deploy_cost.clone(),
+                    cause: if dry_run.name_collision { Some(format!("Duplicate contract '{}'", &contract_identifier)) } else { None },
+                    diagnostic: None,
+                    errors: vec![],
+                    warnings: Linter::run(contract_analysis)
+                })
+            },
+            Err(chain_error::ClarityError(clarity_error::Parse(e))) => {
+                let errors = ConversationHttp::parse_error_diagnostics(source, &e);
+                HttpResponseType::DryRunContract(response_metadata, failed(e.to_string(), Some(e.diagnostic), errors))
+            },
+            Err(chain_error::ClarityError(clarity_error::Analysis(e))) =>
+                HttpResponseType::DryRunContract(response_metadata, failed(e.to_string(), Some(e.diagnostic.clone()), vec![e.diagnostic])),
+            Err(e @ chain_error::ClarityError(_)) =>
+                HttpResponseType::DryRunContract(response_metadata, failed(e.to_string(), None, vec![])),
+            Err(e) => {
+                warn!("Failed to dry-run contract {}: {:?}", &contract_identifier, &e);
+                HttpResponseType::ServerError(response_metadata, format!("Failed to dry-run contract {}", &contract_identifier))
+            }
         };
 
-        let response = HttpResponseType::CallReadOnlyFunction(response_metadata, response);
         response.send(http, fd).map(|_| ())
     }
 
@@ -608,6 +2023,70 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
     
+    /// Handle a GET for the contracts that implement a trait, given the chain tip.  Like the
+    /// contract interface, this comes from the node's analysis data rather than from the chain
+    /// itself, so it only covers contracts this node has analyzed.
+    fn handle_get_trait_implementors<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                               chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                               contract_addr: &StacksAddress, contract_name: &ContractName, trait_name: &ClarityName) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let trait_identifier = TraitIdentifier { name: trait_name.clone(), contract_identifier: contract_identifier.clone() };
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_analysis_db_readonly(|db| {
+                let contract = db.load_contract(&contract_identifier)?;
+                if contract.get_defined_trait(trait_name.as_str()).is_none() {
+                    return None;
+                }
+                let contracts = db.get_trait_implementors(&trait_identifier);
+                Some(TraitImplementorsResponse {
+                    trait_identifier: trait_identifier.to_string(),
+                    contracts: contracts.iter().map(|contract| contract.to_string()).collect()
+                })
+            })
+        });
+
+        let response = match data {
+            Some(data) => HttpResponseType::GetTraitImplementors(response_metadata, data),
+            None => HttpResponseType::NotFound(response_metadata, "No such trait".into())
+        };
+        
+        response.send(http, fd).map(|_| ())
+    }
+    
+    /// Handle a GET for the contracts a contract calls, and the contracts that call it, given the
+    /// chain tip.  Like the trait implementors, this comes from the node's analysis data, so it
+    /// only covers contracts this node has analyzed.
+    fn handle_get_contract_dependencies<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType,
+                                                  chainstate: &mut StacksChainState, cur_burn: &BurnchainHeaderHash, cur_block: &BlockHeaderHash,
+                                                  contract_addr: &StacksAddress, contract_name: &ContractName) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier = QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let data = chainstate.with_read_only_clarity_tx(cur_burn, cur_block, |clarity_tx| {
+            clarity_tx.with_analysis_db_readonly(|db| {
+                if !db.has_contract(&contract_identifier) {
+                    return None;
+                }
+                let calls = db.get_contract_dependencies(&contract_identifier);
+                let called_by = db.get_contract_callers(&contract_identifier);
+                Some(ContractDependenciesResponse {
+                    contract: contract_identifier.to_string(),
+                    calls: calls.map(|calls| calls.iter().map(|contract| contract.to_string()).collect()),
+                    called_by: called_by.iter().map(|contract| contract.to_string()).collect()
+                })
+            })
+        });
+
+        let response = match data {
+            Some(data) => HttpResponseType::GetContractDependencies(response_metadata, data),
+            None => HttpResponseType::NotFound(response_metadata, "No contract analysis found for this contract".into())
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
@@ -638,6 +2117,18 @@ impl ConversationHttp {
         }
     }
 
+    /// Handle a GET unconfirmed microblock stream off of the canonical Stacks chain tip.  Start
+    /// streaming the reply, as with handle_getmicroblocks_unconfirmed.
+    fn handle_getmicroblocks_unconfirmed_tip<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, min_seq: u16, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<Option<BlockStreamData>, net_error> {
+        match ConversationHttp::handle_load_stacks_chain_tip(http, fd, req, burndb, chainstate)? {
+            Some((burn_block, block)) => {
+                let index_anchor_block_hash = StacksBlockHeader::make_index_block_hash(&burn_block, &block);
+                ConversationHttp::handle_getmicroblocks_unconfirmed(http, fd, req, &index_anchor_block_hash, min_seq, chainstate)
+            },
+            None => Ok(None)
+        }
+    }
+
     /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block 
     /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
     /// will return different values here.
@@ -653,11 +2144,58 @@ impl ConversationHttp {
         }
     }
 
+    /// Load the block whose state a read-only query asks for: the canonical chain tip, a given
+    /// block, or the block at a given height in the fork ending at either.  The node keeps the
+    /// state of every block it has processed.  If the block isn't known, reply with a 404.
+    fn handle_load_stacks_tip_request<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, tip_request: &TipRequest,
+                                                burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<Option<(BurnchainHeaderHash, BlockHeaderHash)>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        let tip = match tip_request.tip {
+            Some(ref tip) => tip.clone(),
+            None => match ConversationHttp::handle_load_stacks_chain_tip(http, fd, req, burndb, chainstate)? {
+                Some((burn_block, block)) => {
+                    if tip_request.height.is_none() {
+                        return Ok(Some((burn_block, block)));
+                    }
+                    StacksBlockHeader::make_index_block_hash(&burn_block, &block)
+                },
+                None => {
+                    return Ok(None);
+                }
+            }
+        };
+
+        let header_res = chainstate.headers_tx_begin()
+            .and_then(|mut headers_tx| match tip_request.height {
+                Some(height) => StacksChainState::get_index_tip_ancestor(&mut headers_tx, &tip, height),
+                None => StacksChainState::get_stacks_block_header_info_by_index_block_hash(&headers_tx, &tip)
+            });
+
+        match header_res {
+            Ok(Some(header_info)) => Ok(Some((header_info.burn_header_hash.clone(), header_info.anchored_header.block_hash()))),
+            Ok(None) => {
+                let msg = match tip_request.height {
+                    Some(height) => format!("No block at height {} in fork {}", height, tip.to_hex()),
+                    None => format!("No such block {}", tip.to_hex())
+                };
+                let response = HttpResponseType::NotFound(response_metadata, msg);
+                response.send(http, fd).and_then(|_| Ok(None))
+            },
+            Err(e) => {
+                warn!("Failed to load block for {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(response_metadata, format!("Failed to load block for {}", tip.to_hex()));
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+        }
+    }
+
     /// Handle a transaction.  Directly submit it to the mempool so the client can see any
     /// rejection reasons up-front (different from how the peer network handles it).  Indicate
     /// whether or not the transaction was accepted (and thus needs to be forwarded) in the return
-    /// value.
-    fn handle_post_transaction<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, burn_header_hash: BurnchainHeaderHash, block_hash: BlockHeaderHash, mempool: &mut MemPoolDB, tx: StacksTransaction) -> Result<bool, net_error> {
+    /// value, as well as whether or not the caller needs to wait for it to be anchored before
+    /// replying.  If `wait` is set, then no reply is sent for a transaction the mempool has.
+    fn handle_post_transaction<W: Write>(http: &mut StacksHttp, fd: &mut W, req: &HttpRequestType, burn_header_hash: BurnchainHeaderHash, block_hash: BlockHeaderHash, mempool: &mut MemPoolDB, tx: StacksTransaction, wait: bool) -> Result<(bool, bool), net_error> {
         let txid = tx.txid();
         let response_metadata = HttpResponseMetadata::from(req);
         let (response, accepted) = 
@@ -675,7 +2213,326 @@ impl ConversationHttp {
                 }
             };
 
-        response.send(http, fd).and_then(|_| Ok(accepted))
+        if let HttpResponseType::TransactionID(..) = response {
+            if wait {
+                return Ok((accepted, true));
+            }
+        }
+
+        response.send(http, fd).and_then(|_| Ok((accepted, false)))
+    }
+
+    /// Look up a transaction we're waiting on in the receipt index, as of the canonical Stacks
+    /// chain tip.  This finds it whether it was mined in an anchored block or in a microblock
+    /// that an anchored block confirmed.  The lookup is skipped if the tip hasn't moved since the
+    /// last call.
+    fn find_anchored_tx(tx_wait: &mut PendingTxWait, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<Option<RPCTransactionReceipt>, net_error> {
+        let tip = match chainstate.get_stacks_chain_tip(burndb)? {
+            Some(tip) => tip,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let tip_hashes = (tip.burn_header_hash, tip.anchored_block_hash);
+        if tip_hashes == tx_wait.last_tip {
+            return Ok(None);
+        }
+
+        let tip_index = StacksBlockHeader::make_index_block_hash(&tip_hashes.0, &tip_hashes.1);
+        let entry_opt = {
+            let mut headers_tx = chainstate.headers_tx_begin()?;
+            StacksChainState::get_transaction_receipt(&mut headers_tx, &tip_index, &tx_wait.txid)?
+        };
+
+        tx_wait.last_tip = tip_hashes;
+        Ok(entry_opt.map(|entry| RPCTransactionReceipt {
+            txid: tx_wait.txid.to_hex(),
+            status: "anchored".to_string(),
+            index_block_hash: Some(entry.index_block_hash().to_hex()),
+            burn_header_hash: Some(entry.burn_header_hash.to_hex()),
+            block_hash: Some(entry.block_hash),
+            block_height: Some(entry.block_height),
+            tx_index: entry.tx_index
+        }))
+    }
+
+    /// Take the p2p event IDs of the peers that clients asked us to ban
+    pub fn take_bans(&mut self) -> Vec<usize> {
+        std::mem::replace(&mut self.pending_bans, vec![])
+    }
+
+    /// Is a transaction submission waiting for its transaction to be anchored?
+    pub fn has_pending_tx_wait(&self) -> bool {
+        self.pending_tx_wait.is_some()
+    }
+
+    /// Check on a transaction submission that's waiting for its transaction to be anchored.  If
+    /// the transaction got anchored, or if the wait timed out, then send the receipt.
+    /// Returns true if the wait is over.
+    pub fn try_finish_tx_wait(&mut self, burndb: &BurnDB, chainstate: &mut StacksChainState) -> Result<bool, net_error> {
+        let mut tx_wait = match self.pending_tx_wait.take() {
+            Some(tx_wait) => tx_wait,
+            None => {
+                return Ok(false);
+            }
+        };
+
+        let receipt = match ConversationHttp::find_anchored_tx(&mut tx_wait, burndb, chainstate) {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) | Err(_) if get_epoch_time_secs() < tx_wait.deadline => {
+                self.pending_tx_wait = Some(tx_wait);
+                return Ok(false);
+            },
+            res => {
+                if let Err(e) = res {
+                    warn!("{:?}: failed to search for transaction {}: {:?}", &self, &tx_wait.txid, &e);
+                }
+                debug!("{:?}: timed out waiting for transaction {} to be anchored", &self, &tx_wait.txid);
+                RPCTransactionReceipt {
+                    txid: tx_wait.txid.to_hex(),
+                    status: "pending".to_string(),
+                    index_block_hash: None,
+                    burn_header_hash: None,
+                    block_hash: None,
+                    block_height: None,
+                    tx_index: None
+                }
+            }
+        };
+
+        let PendingTxWait { mut reply, response_metadata, keep_alive, .. } = tx_wait;
+        let response = HttpResponseType::TransactionReceipt(response_metadata, receipt);
+        response.send(&mut self.connection.protocol, &mut reply)?;
+        self.reply_streams.push_back((reply, None, keep_alive));
+        Ok(true)
+    }
+
+    /// Count a call to a deprecated route, and refuse it with 410 Gone if the route's sunset date
+    /// has passed (unless we're configured to keep serving sunset routes).
+    fn check_deprecated(&self, req: &HttpRequestType) -> Option<HttpResponseType> {
+        let deprecation = RouteDeprecation::find(req.request_class())?;
+        monitoring::increment_rpc_deprecated_requests_counter(req.request_class());
+        if deprecation.is_sunset(get_epoch_time_secs()) && !self.connection.options.rpc_serve_sunset_routes {
+            debug!("{:?}: refuse request {} from {} (route is past its sunset date)", &self, req.request_path(), &self.peer_addr);
+            return Some(HttpResponseType::Error(HttpResponseMetadata::from(req), 410, deprecation.gone_message()));
+        }
+        None
+    }
+
+    /// Handle one of the API calls in a batch, writing its reply to fd.  The call is throttled
+    /// and shed on its own, as if it had been made separately.
+    fn handle_batch_item<W: Write>(&mut self, fd: &mut W, req: &HttpRequestType, chain_view: &BurnchainView, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
+                                   chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, rate_limiter: &mut RPCRateLimiter, handler_opts: &RPCHandlerArgs) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !req.is_batchable() {
+            let response = HttpResponseType::BadRequest(response_metadata, format!("{} cannot be batched", req.request_path()));
+            return response.send(&mut self.connection.protocol, fd).map(|_| ());
+        }
+
+        if handler_opts.light_sync && req.reads_clarity_state() {
+            let response = HttpResponseType::ServiceUnavailable(response_metadata, LIGHT_SYNC_REFUSAL.to_string());
+            return response.send(&mut self.connection.protocol, fd).map(|_| ());
+        }
+
+        if let Some(limit) = rate_limiter.check(&self.peer_addr.ip(), req.request_class(), get_epoch_time_ms() as u64) {
+            monitoring::increment_rpc_requests_throttled_counter(req.request_class(), &limit.to_string());
+            let response = HttpResponseType::TooManyRequests(response_metadata, "Rate limit exceeded; try again later".to_string());
+            return response.send(&mut self.connection.protocol, fd).map(|_| ());
+        }
+
+        if let Some(response) = self.check_deprecated(req) {
+            return response.send(&mut self.connection.protocol, fd).map(|_| ());
+        }
+
+        if OverloadDetector::should_shed(handler_opts.shed_priority, req.priority(&self.connection.options.rpc_priority_overrides)) {
+            monitoring::increment_rpc_requests_shed_counter();
+            let response = HttpResponseType::ServiceUnavailable(response_metadata, "Node is overloaded; try again later".to_string());
+            return response.send(&mut self.connection.protocol, fd).map(|_| ());
+        }
+
+        match *req {
+            HttpRequestType::GetInfo(ref _md) => {
+                ConversationHttp::handle_getinfo(&mut self.connection.protocol, fd, req, &self.burnchain, burndb, peerdb, handler_opts)?;
+            },
+            HttpRequestType::GetNeighbors(ref _md) => {
+                ConversationHttp::handle_getneighbors(&mut self.connection.protocol, fd, req, self.network_id, chain_view, peers, peerdb)?;
+            },
+            HttpRequestType::GetMinedBlocks(ref _md) => {
+                ConversationHttp::handle_getmined_blocks(&mut self.connection.protocol, fd, req, handler_opts, &self.connection.options)?;
+            },
+            HttpRequestType::GetNeighborStats(ref _md) => {
+                ConversationHttp::handle_getneighbor_stats(&mut self.connection.protocol, fd, req, peers)?;
+            },
+            HttpRequestType::GetConsensusConstants(ref _md) => {
+                ConversationHttp::handle_getconsensus_constants(&mut self.connection.protocol, fd, req)?;
+            },
+            HttpRequestType::GetOpenAPI(ref _md) => {
+                ConversationHttp::handle_getopenapi(&mut self.connection.protocol, fd, req)?;
+            },
+            HttpRequestType::GetStatus(ref _md, require_ready) => {
+                ConversationHttp::handle_getstatus(&mut self.connection.protocol, fd, req, require_ready, burndb, chain_view, peers, handler_opts)?;
+            },
+            HttpRequestType::GetBlockTransactions(ref _md, ref index_block_hash, ref offset, ref limit) => {
+                ConversationHttp::handle_getblock_transactions(&mut self.connection.protocol, fd, req, index_block_hash, *offset, *limit, chainstate)?;
+            },
+            HttpRequestType::GetMempoolTxids(ref _md, ref cursor, ref limit) => {
+                ConversationHttp::handle_getmempool_txids(&mut self.connection.protocol, fd, req, cursor, *limit, mempool)?;
+            },
+            HttpRequestType::GetMempoolStats(ref _md) => {
+                ConversationHttp::handle_getmempool_stats(&mut self.connection.protocol, fd, req, mempool)?;
+            },
+            HttpRequestType::GetMicroblockByHash(ref _md, ref microblock_hash) => {
+                ConversationHttp::handle_getmicroblock_by_hash(&mut self.connection.protocol, fd, req, microblock_hash, chainstate)?;
+            },
+            HttpRequestType::GetTransfersByMemo(ref _md, ref principal, ref memo, ref limit) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_transfers_by_memo(&mut self.connection.protocol, fd, req, &burn_block, &block, principal, memo, *limit, chainstate)?;
+                }
+            },
+            HttpRequestType::GetTransactionReceipt(ref _md, ref txid) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_transaction_receipt(&mut self.connection.protocol, fd, req, &burn_block, &block, txid, chainstate)?;
+                }
+            },
+            HttpRequestType::GetBlockReceipts(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_receipts(&mut self.connection.protocol, fd, req, index_block_hash, chainstate)?;
+            },
+            HttpRequestType::GetContractReceipts(ref _md, ref contract_addr, ref contract_name, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_contract_receipts(&mut self.connection.protocol, fd, req, contract_addr, contract_name, tip_opt, *limit, burndb, chainstate)?;
+            },
+            HttpRequestType::SearchHashPrefix(ref _md, ref prefix, ref limit) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_search_hash_prefix(&mut self.connection.protocol, fd, req, &burn_block, &block, prefix, *limit, burndb, chainstate, mempool)?;
+                }
+            },
+            HttpRequestType::GetStxSupply(ref _md, ref tip_opt) => {
+                ConversationHttp::handle_get_stx_supply(&mut self.connection.protocol, fd, req, tip_opt, burndb, chainstate)?;
+            },
+            HttpRequestType::GetBalanceHistory(ref _md, ref principal, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_balance_history(&mut self.connection.protocol, fd, req, principal, tip_opt, *limit, burndb, chainstate)?;
+            },
+            HttpRequestType::GetTopBalances(ref _md, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_top_balances(&mut self.connection.protocol, fd, req, tip_opt, *limit, burndb, chainstate)?;
+            },
+            HttpRequestType::GetAddressTransactions(ref _md, ref principal, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_address_transactions(&mut self.connection.protocol, fd, req, principal, tip_opt, *limit, burndb, chainstate)?;
+            },
+            HttpRequestType::GetBlockContractCosts(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_contract_costs(&mut self.connection.protocol, fd, req, index_block_hash, chainstate)?;
+            },
+            HttpRequestType::GetMetrics(ref _md) => {
+                ConversationHttp::handle_getmetrics(&mut self.connection.protocol, fd, req, &self.connection.options)?;
+            },
+            HttpRequestType::GetAccount(ref _md, ref principal, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_account_entry(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, principal, *with_proof)?;
+                }
+            },
+            HttpRequestType::GetMapEntry(ref _md, ref contract_addr, ref contract_name, ref map_name, ref key, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_map_entry(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block,
+                                                           contract_addr, contract_name, map_name, key, *with_proof)?;
+                }
+            },
+            HttpRequestType::GetDataVar(ref _md, ref contract_addr, ref contract_name, ref var_name, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_data_var(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block,
+                                                          contract_addr, contract_name, var_name, *with_proof)?;
+                }
+            },
+            HttpRequestType::GetFungibleTokenBalance(ref _md, ref contract_addr, ref contract_name, ref token_name, ref principal, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_ft_balance(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block,
+                                                            contract_addr, contract_name, token_name, principal, *with_proof)?;
+                }
+            },
+            HttpRequestType::GetNonFungibleTokenOwner(ref _md, ref contract_addr, ref contract_name, ref asset_name, ref asset, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_nft_owner(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block,
+                                                           contract_addr, contract_name, asset_name, asset, *with_proof)?;
+                }
+            },
+            HttpRequestType::GetTransferCost(ref _md) => {
+                ConversationHttp::handle_token_transfer_cost(&mut self.connection.protocol, fd, req)?;
+            },
+            HttpRequestType::GetContractABI(ref _md, ref contract_addr, ref contract_name) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_contract_abi(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, contract_addr, contract_name)?;
+                }
+            },
+            HttpRequestType::GetTraitImplementors(ref _md, ref contract_addr, ref contract_name, ref trait_name) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_trait_implementors(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, contract_addr, contract_name, trait_name)?;
+                }
+            },
+            HttpRequestType::GetContractDependencies(ref _md, ref contract_addr, ref contract_name) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_contract_dependencies(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, contract_addr, contract_name)?;
+                }
+            },
+            HttpRequestType::CallReadOnlyFunction(ref _md, ref ctrct_addr, ref ctrct_name, ref as_sender, ref func_name, ref args, ref with_profile, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, fd, req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_readonly_function_call(
+                        &mut self.connection.protocol, fd, req, chainstate, &burn_block, &block,
+                        ctrct_addr, ctrct_name, func_name, as_sender, args, *with_profile, &self.connection.options)?;
+                }
+            },
+            HttpRequestType::GetContractSrc(ref _md, ref contract_addr, ref contract_name, ref with_proof) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_contract_src(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, contract_addr, contract_name, *with_proof)?;
+                }
+            },
+            HttpRequestType::AnalyzeContract(ref _md, ref deployer, ref contract_name, ref source) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_analyze_contract(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, deployer, contract_name, source)?;
+                }
+            },
+            HttpRequestType::DryRunContract(ref _md, ref deployer, ref contract_name, ref source) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_dry_run_contract(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, deployer, contract_name, source)?;
+                }
+            },
+            HttpRequestType::EstimateFee(ref _md, ref sender, ref payload) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_estimate_fee(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, sender, payload)?;
+                }
+            },
+            HttpRequestType::SimulateTransaction(ref _md, ref sender, ref payload, ref overrides) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, fd, req, burndb, chainstate)? {
+                    ConversationHttp::handle_simulate_transaction(&mut self.connection.protocol, fd, req, chainstate, &burn_block, &block, sender, payload, overrides)?;
+                }
+            },
+            _ => {
+                // unmatched paths (everything else is not batchable)
+                let response = HttpResponseType::NotFound(response_metadata, req.request_path());
+                response.send(&mut self.connection.protocol, fd).map(|_| ())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a batch of API calls, in order.  Each call's reply is decoded into its place in the
+    /// result list; a call that can't be parsed gets a 400.
+    fn handle_batch<W: Write>(&mut self, fd: &mut W, req: &HttpRequestType, items: &Vec<RPCBatchRequestItem>, chain_view: &BurnchainView, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
+                              chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, rate_limiter: &mut RPCRateLimiter, handler_opts: &RPCHandlerArgs) -> Result<(), net_error> {
+        let mut results = Vec::with_capacity(items.len());
+        for item in items.iter() {
+            let mut reply_bytes = vec![];
+            match HttpRequestType::parse_batch_item(&mut self.connection.protocol, req.metadata(), item) {
+                Ok(item_req) => {
+                    self.handle_batch_item(&mut reply_bytes, &item_req, chain_view, peers, burndb, peerdb, chainstate, mempool, rate_limiter, handler_opts)?;
+                },
+                Err(e) => {
+                    let response = HttpResponseType::BadRequest(HttpResponseMetadata::from(req), format!("Failed to parse {} {}: {}", &item.method, &item.path, &e));
+                    response.send(&mut self.connection.protocol, &mut reply_bytes).map(|_| ())?;
+                }
+            }
+            results.push(RPCBatchResponseItem::from_http_response(&reply_bytes)?);
+        }
+
+        let response = HttpResponseType::BatchResults(HttpResponseMetadata::from(req), results);
+        response.send(&mut self.connection.protocol, fd).map(|_| ())
     }
 
     /// Handle an external HTTP request.
@@ -683,8 +2540,8 @@ impl ConversationHttp {
     /// those new streams into the `reply_streams` set.
     /// Returns a StacksMessageType option -- it's Some(...) if we need to forward a message to the
     /// peer network (like a transaction or a block or microblock)
-    pub fn handle_request(&mut self, req: HttpRequestType, chain_view: &BurnchainView, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
-                          chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, handler_opts: &RPCHandlerArgs) -> Result<Option<StacksMessageType>, net_error> {
+    pub fn handle_request(&mut self, mut req: HttpRequestType, chain_view: &BurnchainView, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
+                          chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, rate_limiter: &mut RPCRateLimiter, handler_opts: &RPCHandlerArgs) -> Result<Option<StacksMessageType>, net_error> {
 
         monitoring::increment_rpc_calls_counter();
 
@@ -692,6 +2549,77 @@ impl ConversationHttp {
         let keep_alive = req.metadata().keep_alive;
         let mut ret = None;
 
+        if !self.connection.options.rpc_compression_enabled {
+            req.metadata_mut().accept_encoding = None;
+        }
+
+        if let Some(limit) = rate_limiter.check(&self.peer_addr.ip(), req.request_class(), get_epoch_time_ms() as u64) {
+            debug!("{:?}: throttle request {} from {} (over {} rate limit)", &self, req.request_path(), &self.peer_addr, limit);
+            monitoring::increment_rpc_requests_throttled_counter(req.request_class(), &limit.to_string());
+            let response_metadata = HttpResponseMetadata::from(&req);
+            let response = HttpResponseType::TooManyRequests(response_metadata, "Rate limit exceeded; try again later".to_string());
+            response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        if let Some(response) = self.check_deprecated(&req) {
+            response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        if req.is_privileged() {
+            let response_metadata = HttpResponseMetadata::from(&req);
+            let response_opt = match self.connection.options.rpc_api_key {
+                Some(ref api_key) => {
+                    if req.metadata().is_authorized(api_key) {
+                        None
+                    }
+                    else {
+                        Some(HttpResponseType::Unauthorized(response_metadata, "Missing or invalid API key".to_string()))
+                    }
+                },
+                None => Some(HttpResponseType::Forbidden(response_metadata, "Privileged RPC endpoints are disabled; configure an API key to enable them".to_string()))
+            };
+            if let Some(response) = response_opt {
+                warn!("{:?}: refuse privileged request {} from {}", &self, req.request_path(), &self.peer_addr);
+                response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+                self.reply_streams.push_back((reply, None, keep_alive));
+                return Ok(None);
+            }
+        }
+
+        if handler_opts.safe_mode && req.writes_chainstate() {
+            debug!("{:?}: refuse request {} while repairing chainstate", &self, req.request_path());
+            let response_metadata = HttpResponseMetadata::from(&req);
+            let response = HttpResponseType::ServiceUnavailable(response_metadata, "Node is repairing its chainstate; only reads are served".to_string());
+            response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        if handler_opts.light_sync && req.reads_clarity_state() {
+            debug!("{:?}: refuse request {} on a light-synced node", &self, req.request_path());
+            let response_metadata = HttpResponseMetadata::from(&req);
+            let response = HttpResponseType::ServiceUnavailable(response_metadata, LIGHT_SYNC_REFUSAL.to_string());
+            response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        let priority = req.priority(&self.connection.options.rpc_priority_overrides);
+        if OverloadDetector::should_shed(handler_opts.shed_priority, priority) {
+            debug!("{:?}: shed {}-priority request {} under load", &self, priority, req.request_path());
+            monitoring::increment_rpc_requests_shed_counter();
+            let response_metadata = HttpResponseMetadata::from(&req);
+            let response = HttpResponseType::ServiceUnavailable(response_metadata, "Node is overloaded; try again later".to_string());
+            response.send(&mut self.connection.protocol, &mut reply).map(|_| ())?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        let request_start_ms = get_epoch_time_ms();
         let stream_opt = match req {
             HttpRequestType::GetInfo(ref _md) => {
                 ConversationHttp::handle_getinfo(&mut self.connection.protocol, &mut reply, &req, &self.burnchain,
@@ -702,32 +2630,151 @@ impl ConversationHttp {
                 ConversationHttp::handle_getneighbors(&mut self.connection.protocol, &mut reply, &req, self.network_id, chain_view, peers, peerdb)?;
                 None
             },
+            HttpRequestType::GetMinedBlocks(ref _md) => {
+                ConversationHttp::handle_getmined_blocks(&mut self.connection.protocol, &mut reply, &req, handler_opts, &self.connection.options)?;
+                None
+            },
+            HttpRequestType::GetNeighborStats(ref _md) => {
+                ConversationHttp::handle_getneighbor_stats(&mut self.connection.protocol, &mut reply, &req, peers)?;
+                None
+            },
+            HttpRequestType::GetConsensusConstants(ref _md) => {
+                ConversationHttp::handle_getconsensus_constants(&mut self.connection.protocol, &mut reply, &req)?;
+                None
+            },
+            HttpRequestType::GetOpenAPI(ref _md) => {
+                ConversationHttp::handle_getopenapi(&mut self.connection.protocol, &mut reply, &req)?;
+                None
+            },
+            HttpRequestType::GetStatus(ref _md, require_ready) => {
+                ConversationHttp::handle_getstatus(&mut self.connection.protocol, &mut reply, &req, require_ready, burndb, chain_view, peers, handler_opts)?;
+                None
+            },
             HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
                 ConversationHttp::handle_getblock(&mut self.connection.protocol, &mut reply, &req, index_block_hash, chainstate)?
             },
+            HttpRequestType::GetBlockByHeight(ref _md, ref height, ref tip_opt) => {
+                ConversationHttp::handle_getblock_by_height(&mut self.connection.protocol, &mut reply, &req, *height, tip_opt, burndb, chainstate)?
+            },
+            HttpRequestType::GetBlockByHash(ref _md, ref block_hash) => {
+                ConversationHttp::handle_getblock_by_hash(&mut self.connection.protocol, &mut reply, &req, block_hash, chainstate)?
+            },
+            HttpRequestType::ExportBlocks(ref _md, ref start_height, ref end_height, ref format, ref tip_opt) => {
+                ConversationHttp::handle_export_blocks(&mut self.connection.protocol, &mut reply, &req, *start_height, *end_height, *format, tip_opt, burndb, chainstate)?
+            },
             HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
                 ConversationHttp::handle_getmicroblocks_indexed(&mut self.connection.protocol, &mut reply, &req, index_head_hash, chainstate)?
             },
             HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
                 ConversationHttp::handle_getmicroblocks_confirmed(&mut self.connection.protocol, &mut reply, &req, anchor_index_block_hash, chainstate)?
             },
-            HttpRequestType::GetMicroblocksUnconfirmed(ref _md, ref index_anchor_block_hash, ref min_seq) => {
-                ConversationHttp::handle_getmicroblocks_unconfirmed(&mut self.connection.protocol, &mut reply, &req, index_anchor_block_hash, *min_seq, chainstate)?
+            HttpRequestType::GetMicroblocksUnconfirmed(ref _md, ref index_anchor_block_hash, ref min_seq) => {
+                ConversationHttp::handle_getmicroblocks_unconfirmed(&mut self.connection.protocol, &mut reply, &req, index_anchor_block_hash, *min_seq, chainstate)?
+            },
+            HttpRequestType::GetMicroblocksUnconfirmedTip(ref _md, ref min_seq) => {
+                ConversationHttp::handle_getmicroblocks_unconfirmed_tip(&mut self.connection.protocol, &mut reply, &req, *min_seq, burndb, chainstate)?
+            },
+            HttpRequestType::GetMicroblockByHash(ref _md, ref microblock_hash) => {
+                ConversationHttp::handle_getmicroblock_by_hash(&mut self.connection.protocol, &mut reply, &req, microblock_hash, chainstate)?;
+                None
+            },
+            HttpRequestType::GetBlockTransactions(ref _md, ref index_block_hash, ref offset, ref limit) => {
+                ConversationHttp::handle_getblock_transactions(&mut self.connection.protocol, &mut reply, &req, index_block_hash, *offset, *limit, chainstate)?;
+                None
+            },
+            HttpRequestType::GetMempoolTxids(ref _md, ref cursor, ref limit) => {
+                ConversationHttp::handle_getmempool_txids(&mut self.connection.protocol, &mut reply, &req, cursor, *limit, mempool)?;
+                None
+            },
+            HttpRequestType::GetMempoolStats(ref _md) => {
+                ConversationHttp::handle_getmempool_stats(&mut self.connection.protocol, &mut reply, &req, mempool)?;
+                None
+            },
+            HttpRequestType::GetTransfersByMemo(ref _md, ref principal, ref memo, ref limit) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_transfers_by_memo(&mut self.connection.protocol, &mut reply, &req, &burn_block, &block, principal, memo, *limit, chainstate)?;
+                }
+                None
+            },
+            HttpRequestType::GetTransactionReceipt(ref _md, ref txid) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_transaction_receipt(&mut self.connection.protocol, &mut reply, &req, &burn_block, &block, txid, chainstate)?;
+                }
+                None
+            },
+            HttpRequestType::GetBlockReceipts(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_receipts(&mut self.connection.protocol, &mut reply, &req, index_block_hash, chainstate)?;
+                None
+            },
+            HttpRequestType::GetContractReceipts(ref _md, ref contract_addr, ref contract_name, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_contract_receipts(&mut self.connection.protocol, &mut reply, &req, contract_addr, contract_name, tip_opt, *limit, burndb, chainstate)?;
+                None
+            },
+            HttpRequestType::SearchHashPrefix(ref _md, ref prefix, ref limit) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_search_hash_prefix(&mut self.connection.protocol, &mut reply, &req, &burn_block, &block, prefix, *limit, burndb, chainstate, mempool)?;
+                }
+                None
+            },
+            HttpRequestType::GetStxSupply(ref _md, ref tip_opt) => {
+                ConversationHttp::handle_get_stx_supply(&mut self.connection.protocol, &mut reply, &req, tip_opt, burndb, chainstate)?;
+                None
+            },
+            HttpRequestType::GetBalanceHistory(ref _md, ref principal, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_balance_history(&mut self.connection.protocol, &mut reply, &req, principal, tip_opt, *limit, burndb, chainstate)?;
+                None
+            },
+            HttpRequestType::GetTopBalances(ref _md, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_top_balances(&mut self.connection.protocol, &mut reply, &req, tip_opt, *limit, burndb, chainstate)?;
+                None
+            },
+            HttpRequestType::GetAddressTransactions(ref _md, ref principal, ref tip_opt, ref limit) => {
+                ConversationHttp::handle_get_address_transactions(&mut self.connection.protocol, &mut reply, &req, principal, tip_opt, *limit, burndb, chainstate)?;
+                None
+            },
+            HttpRequestType::GetBlockContractCosts(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_contract_costs(&mut self.connection.protocol, &mut reply, &req, index_block_hash, chainstate)?;
+                None
+            },
+            HttpRequestType::GetMetrics(ref _md) => {
+                ConversationHttp::handle_getmetrics(&mut self.connection.protocol, &mut reply, &req, &self.connection.options)?;
+                None
             },
-            HttpRequestType::GetAccount(ref _md, ref principal, ref with_proof) => {
-                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+            HttpRequestType::GetAccount(ref _md, ref principal, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
                     ConversationHttp::handle_get_account_entry(&mut self.connection.protocol, &mut reply, &req, chainstate,
                                                                &burn_block, &block, principal, *with_proof)?;
                 }
                 None
             },
-            HttpRequestType::GetMapEntry(ref _md, ref contract_addr, ref contract_name, ref map_name, ref key, ref with_proof) => {
-                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+            HttpRequestType::GetMapEntry(ref _md, ref contract_addr, ref contract_name, ref map_name, ref key, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
                     ConversationHttp::handle_get_map_entry(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
                                                            contract_addr, contract_name, map_name, key, *with_proof)?;
                 }
                 None
             },
+            HttpRequestType::GetDataVar(ref _md, ref contract_addr, ref contract_name, ref var_name, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_data_var(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                          contract_addr, contract_name, var_name, *with_proof)?;
+                }
+                None
+            },
+            HttpRequestType::GetFungibleTokenBalance(ref _md, ref contract_addr, ref contract_name, ref token_name, ref principal, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_ft_balance(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                            contract_addr, contract_name, token_name, principal, *with_proof)?;
+                }
+                None
+            },
+            HttpRequestType::GetNonFungibleTokenOwner(ref _md, ref contract_addr, ref contract_name, ref asset_name, ref asset, ref with_proof, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
+                    ConversationHttp::handle_get_nft_owner(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                           contract_addr, contract_name, asset_name, asset, *with_proof)?;
+                }
+                None
+            },
             HttpRequestType::GetTransferCost(ref _md) => {
                 ConversationHttp::handle_token_transfer_cost(&mut self.connection.protocol, &mut reply, &req)?;
                 None
@@ -739,11 +2786,25 @@ impl ConversationHttp {
                 }
                 None
             },
-            HttpRequestType::CallReadOnlyFunction(ref _md, ref ctrct_addr, ref ctrct_name, ref as_sender, ref func_name, ref args) => {
+            HttpRequestType::GetTraitImplementors(ref _md, ref contract_addr, ref contract_name, ref trait_name) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_trait_implementors(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                                    contract_addr, contract_name, trait_name)?;
+                }
+                None
+            },
+            HttpRequestType::GetContractDependencies(ref _md, ref contract_addr, ref contract_name) => {
                 if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_get_contract_dependencies(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                                       contract_addr, contract_name)?;
+                }
+                None
+            },
+            HttpRequestType::CallReadOnlyFunction(ref _md, ref ctrct_addr, ref ctrct_name, ref as_sender, ref func_name, ref args, ref with_profile, ref tip_request) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_tip_request(&mut self.connection.protocol, &mut reply, &req, tip_request, burndb, chainstate)? {
                     ConversationHttp::handle_readonly_function_call(
                         &mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
-                        ctrct_addr, ctrct_name, func_name, as_sender, args, &self.connection.options)?;
+                        ctrct_addr, ctrct_name, func_name, as_sender, args, *with_profile, &self.connection.options)?;
                 }
                 None
             },
@@ -754,16 +2815,65 @@ impl ConversationHttp {
                 }
                 None
             },
-            HttpRequestType::PostTransaction(ref _md, ref tx) => {
+            HttpRequestType::AnalyzeContract(ref _md, ref deployer, ref contract_name, ref source) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_analyze_contract(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                              deployer, contract_name, source)?;
+                }
+                None
+            },
+            HttpRequestType::DryRunContract(ref _md, ref deployer, ref contract_name, ref source) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_dry_run_contract(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block,
+                                                              deployer, contract_name, source)?;
+                }
+                None
+            },
+            HttpRequestType::EstimateFee(ref _md, ref sender, ref payload) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_estimate_fee(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block, sender, payload)?;
+                }
+                None
+            },
+            HttpRequestType::SimulateTransaction(ref _md, ref sender, ref payload, ref overrides) => {
+                if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
+                    ConversationHttp::handle_simulate_transaction(&mut self.connection.protocol, &mut reply, &req, chainstate, &burn_block, &block, sender, payload, overrides)?;
+                }
+                None
+            },
+            HttpRequestType::PostTransaction(ref _md, ref tx, ref wait_timeout) => {
                 if let Some((burn_block, block)) = ConversationHttp::handle_load_stacks_chain_tip(&mut self.connection.protocol, &mut reply, &req, burndb, chainstate)? {
-                    let accepted = ConversationHttp::handle_post_transaction(&mut self.connection.protocol, &mut reply, &req, burn_block, block, mempool, tx.clone())?;
+                    let (accepted, wait) = ConversationHttp::handle_post_transaction(&mut self.connection.protocol, &mut reply, &req, burn_block.clone(), block.clone(), mempool, tx.clone(), wait_timeout.is_some())?;
                     if accepted {
                         // forward to peer network
                         ret = Some(StacksMessageType::Transaction(tx.clone()));
                     }
+                    if wait {
+                        // the receipt gets sent once the transaction is anchored, or the wait
+                        // times out (see try_finish_tx_wait())
+                        self.pending_tx_wait = Some(PendingTxWait {
+                            reply: reply,
+                            response_metadata: HttpResponseMetadata::from(&req),
+                            keep_alive: keep_alive,
+                            txid: tx.txid(),
+                            deadline: get_epoch_time_secs() + wait_timeout.unwrap_or(0),
+                            last_tip: (burn_block, block)
+                        });
+                        return Ok(ret);
+                    }
                 }
                 None
             },
+            HttpRequestType::BanPeers(ref _md, ref ip, ref port) => {
+                let mut event_ids = ConversationHttp::handle_ban_peers(&mut self.connection.protocol, &mut reply, &req, ip, *port, peers)?;
+                info!("{:?}: ban {} peer(s) at {:?} port {:?} on request from {}", &self, event_ids.len(), ip, port, &self.peer_addr);
+                self.pending_bans.append(&mut event_ids);
+                None
+            },
+            HttpRequestType::PostBatch(ref _md, ref items) => {
+                self.handle_batch(&mut reply, &req, items, chain_view, peers, burndb, peerdb, chainstate, mempool, rate_limiter, handler_opts)?;
+                None
+            },
             HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
                 let response_metadata = HttpResponseMetadata::from(&req);
                 let response = HttpResponseType::OptionsPreflight(response_metadata);
@@ -778,12 +2888,21 @@ impl ConversationHttp {
             }
         };
 
+        // for streamed replies, this only covers setting up the stream
+        let request_ms = get_epoch_time_ms().saturating_sub(request_start_ms);
+        monitoring::observe_rpc_request_latency(req.request_class(), (request_ms as f64) / 1000.0);
+
+        if self.connection.options.rpc_slow_request_ms > 0 && request_ms > self.connection.options.rpc_slow_request_ms as u128 {
+            warn!("{:?}: slow request {} {} {} from {} took {} ms", &self, req.request_class(), req.request_path(), req.log_params(), &self.peer_addr, request_ms);
+            monitoring::increment_rpc_slow_requests_counter(req.request_class());
+        }
+
         match stream_opt {
             None => {
                 self.reply_streams.push_back((reply, None, keep_alive));
             },
             Some(stream) => {
-                self.reply_streams.push_back((reply, Some((HttpChunkedTransferWriterState::new(STREAM_CHUNK_SIZE as usize), stream)), keep_alive));
+                self.reply_streams.push_back((reply, Some((HttpChunkedTransferWriterState::new_encoded(STREAM_CHUNK_SIZE as usize, req.metadata().accept_encoding.clone()), stream)), keep_alive));
             }
         }
         Ok(ret)
@@ -953,7 +3072,7 @@ impl ConversationHttp {
 
     /// Is the connection idle?
     pub fn is_idle(&self) -> bool {
-        self.pending_response.is_none() && self.connection.inbox_len() == 0 && self.connection.outbox_len() == 0 && self.reply_streams.len() == 0
+        self.pending_response.is_none() && self.connection.inbox_len() == 0 && self.connection.outbox_len() == 0 && self.reply_streams.len() == 0 && self.pending_tx_wait.is_none()
     }
 
     /// Is the conversation out of pending data?
@@ -985,7 +3104,8 @@ impl ConversationHttp {
     /// Make progress on in-flight requests and replies.
     /// Returns the list of transactions we'll need to forward to the peer network
     pub fn chat(&mut self, chain_view: &BurnchainView, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
-                chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, handler_args: &RPCHandlerArgs) -> Result<Vec<StacksMessageType>, net_error> {
+                chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, rate_limiter: &mut RPCRateLimiter, workers: &mut RPCWorkerPool,
+                handler_args: &RPCHandlerArgs) -> Result<Vec<StacksMessageType>, net_error> {
 
         // if we have an in-flight error, then don't take any more requests.
         if self.pending_error_response.is_some() {
@@ -998,6 +3118,23 @@ impl ConversationHttp {
         test_debug!("{:?}: {} HTTP requests pending", &self, num_inbound);
 
         for _i in 0..num_inbound {
+            if self.pending_tx_wait.is_some() {
+                // replies go out in request order, so requests that arrive after a transaction
+                // submission that's waiting must wait too
+                break;
+            }
+
+            // heavy requests wait for a free worker, and so do the requests behind them
+            let has_worker = match self.connection.peek_inbox_message() {
+                Some(StacksHttpMessage::Request(ref req)) => workers.try_start(self.conn_id, req.is_heavy()),
+                _ => true
+            };
+            if !has_worker {
+                debug!("{:?}: no worker free for a heavy request; leaving {} request(s) queued", &self, self.connection.inbox_len());
+                monitoring::increment_rpc_requests_deferred_counter();
+                break;
+            }
+
             let msg = match self.connection.next_inbox_message() {
                 None => {
                     continue;
@@ -1011,7 +3148,7 @@ impl ConversationHttp {
                     self.total_request_count += 1;
                     self.last_request_timestamp = get_epoch_time_secs();
                     let msg_opt = self.handle_request(req, chain_view, peers, burndb,
-                                                      peerdb, chainstate, mempool, handler_args)?;
+                                                      peerdb, chainstate, mempool, rate_limiter, handler_args)?;
                     if let Some(msg) = msg_opt {
                         ret.push(msg);
                     }
@@ -1099,11 +3236,84 @@ impl ConversationHttp {
         HttpRequestType::GetNeighbors(HttpRequestMetadata::from_host(self.peer_host.clone()))
     }
 
+    /// Make a new getneighbor_stats request to this endpoint
+    pub fn new_getneighbor_stats(&self) -> HttpRequestType {
+        HttpRequestType::GetNeighborStats(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new getconsensus_constants request to this endpoint
+    pub fn new_getconsensus_constants(&self) -> HttpRequestType {
+        HttpRequestType::GetConsensusConstants(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request for this endpoint's OpenAPI document
+    pub fn new_getopenapi(&self) -> HttpRequestType {
+        HttpRequestType::GetOpenAPI(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request for a contract's interface to this endpoint
+    pub fn new_get_contract_abi(&self, contract_addr: StacksAddress, contract_name: ContractName) -> HttpRequestType {
+        HttpRequestType::GetContractABI(HttpRequestMetadata::from_host(self.peer_host.clone()), contract_addr, contract_name)
+    }
+
+    /// Make a new request for a contract's source code to this endpoint
+    pub fn new_get_contract_src(&self, contract_addr: StacksAddress, contract_name: ContractName, with_proof: bool) -> HttpRequestType {
+        HttpRequestType::GetContractSrc(HttpRequestMetadata::from_host(self.peer_host.clone()), contract_addr, contract_name, with_proof)
+    }
+
+    /// Make a new request for the contracts that implement a trait to this endpoint
+    pub fn new_get_trait_implementors(&self, contract_addr: StacksAddress, contract_name: ContractName, trait_name: ClarityName) -> HttpRequestType {
+        HttpRequestType::GetTraitImplementors(HttpRequestMetadata::from_host(self.peer_host.clone()), contract_addr, contract_name, trait_name)
+    }
+
+    /// Make a new request for the contracts a contract calls and is called by to this endpoint
+    pub fn new_get_contract_dependencies(&self, contract_addr: StacksAddress, contract_name: ContractName) -> HttpRequestType {
+        HttpRequestType::GetContractDependencies(HttpRequestMetadata::from_host(self.peer_host.clone()), contract_addr, contract_name)
+    }
+
+    /// Make a new getmined_blocks request to this endpoint
+    pub fn new_getmined_blocks(&self) -> HttpRequestType {
+        HttpRequestType::GetMinedBlocks(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request to ban the peers at an IP address (and port, if given), authorized with
+    /// the given API key
+    pub fn new_ban_peers(&self, ip: PeerAddress, port: Option<u16>, api_key: Option<String>) -> HttpRequestType {
+        let mut md = HttpRequestMetadata::from_host(self.peer_host.clone());
+        md.auth_token = api_key;
+        HttpRequestType::BanPeers(md, ip, port)
+    }
+
+    /// Make a new batch of API calls to this endpoint
+    pub fn new_batch(&self, items: Vec<RPCBatchRequestItem>) -> HttpRequestType {
+        HttpRequestType::PostBatch(HttpRequestMetadata::from_host(self.peer_host.clone()), items)
+    }
+
+    /// Make a new getstatus request to this endpoint
+    pub fn new_getstatus(&self, require_ready: bool) -> HttpRequestType {
+        HttpRequestType::GetStatus(HttpRequestMetadata::from_host(self.peer_host.clone()), require_ready)
+    }
+
     /// Make a new getblock request to this endpoint
     pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
         HttpRequestType::GetBlock(HttpRequestMetadata::from_host(self.peer_host.clone()), index_block_hash)
     }
     
+    /// Make a new getblock-by-height request to this endpoint
+    pub fn new_getblock_by_height(&self, height: u64, tip: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::GetBlockByHeight(HttpRequestMetadata::from_host(self.peer_host.clone()), height, tip)
+    }
+    
+    /// Make a new getblock-by-hash request to this endpoint
+    pub fn new_getblock_by_hash(&self, block_hash: BlockHeaderHash) -> HttpRequestType {
+        HttpRequestType::GetBlockByHash(HttpRequestMetadata::from_host(self.peer_host.clone()), block_hash)
+    }
+    
+    /// Make a new block export request to this endpoint
+    pub fn new_export_blocks(&self, start_height: u64, end_height: u64, format: BlockExportFormat, tip: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::ExportBlocks(HttpRequestMetadata::from_host(self.peer_host.clone()), start_height, end_height, format, tip)
+    }
+    
     /// Make a new get-microblocks request to this endpoint
     pub fn new_getmicroblocks_indexed(&self, index_microblock_hash: StacksBlockId) -> HttpRequestType {
         HttpRequestType::GetMicroblocksIndexed(HttpRequestMetadata::from_host(self.peer_host.clone()), index_microblock_hash)
@@ -1119,9 +3329,121 @@ impl ConversationHttp {
         HttpRequestType::GetMicroblocksUnconfirmed(HttpRequestMetadata::from_host(self.peer_host.clone()), anchored_index_block_hash, min_seq)
     }
 
+    /// Make a new get-microblocks request for unconfirmed microblocks off of the canonical chain tip
+    pub fn new_getmicroblocks_unconfirmed_tip(&self, min_seq: u16) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksUnconfirmedTip(HttpRequestMetadata::from_host(self.peer_host.clone()), min_seq)
+    }
+
+    /// Make a new request for a single microblock, by its hash
+    pub fn new_getmicroblock_by_hash(&self, microblock_hash: BlockHeaderHash) -> HttpRequestType {
+        HttpRequestType::GetMicroblockByHash(HttpRequestMetadata::from_host(self.peer_host.clone()), microblock_hash)
+    }
+
+    /// Make a new request for a page of a block's transactions
+    pub fn new_getblock_transactions(&self, index_block_hash: StacksBlockId, offset: u64, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetBlockTransactions(HttpRequestMetadata::from_host(self.peer_host.clone()), index_block_hash, offset, limit)
+    }
+
+    /// Make a new request for a page of mempool txids, starting after `cursor` (if given)
+    pub fn new_get_transfers_by_memo(&self, principal: PrincipalData, memo: TokenTransferMemo, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetTransfersByMemo(HttpRequestMetadata::from_host(self.peer_host.clone()), principal, memo, limit)
+    }
+
+    /// Make a new request for the receipt of a mined transaction
+    pub fn new_get_transaction_receipt(&self, txid: Txid) -> HttpRequestType {
+        HttpRequestType::GetTransactionReceipt(HttpRequestMetadata::from_host(self.peer_host.clone()), txid)
+    }
+
+    /// Make a new request for the receipts of the transactions mined in a block
+    pub fn new_get_block_receipts(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlockReceipts(HttpRequestMetadata::from_host(self.peer_host.clone()), index_block_hash)
+    }
+
+    /// Make a new request for the receipts of the transactions involving a contract as of `tip`,
+    /// or the canonical chain tip if not given
+    pub fn new_get_contract_receipts(&self, contract_addr: StacksAddress, contract_name: ContractName, tip: Option<StacksBlockId>, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetContractReceipts(HttpRequestMetadata::from_host(self.peer_host.clone()), contract_addr, contract_name, tip, limit)
+    }
+
+    /// Make a new request for the blocks, burn blocks and transactions whose hashes start with
+    /// `query`, or for the account or contract it names if it is a principal
+    pub fn new_search_hash_prefix(&self, query: String, limit: u64) -> HttpRequestType {
+        HttpRequestType::SearchHashPrefix(HttpRequestMetadata::from_host(self.peer_host.clone()), query, limit)
+    }
+
+    /// Make a new request for the STX supply as of `tip`, or the canonical chain tip if not given
+    pub fn new_get_stx_supply(&self, tip: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::GetStxSupply(HttpRequestMetadata::from_host(self.peer_host.clone()), tip)
+    }
+
+    /// Make a new request for an account's balances as of `tip`, or the canonical chain tip if
+    /// not given
+    pub fn new_get_balance_history(&self, principal: PrincipalData, tip: Option<StacksBlockId>, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetBalanceHistory(HttpRequestMetadata::from_host(self.peer_host.clone()), principal, tip, limit)
+    }
+
+    /// Make a new request for the accounts with the most STX as of `tip`, or the canonical chain
+    /// tip if not given
+    pub fn new_get_top_balances(&self, tip: Option<StacksBlockId>, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetTopBalances(HttpRequestMetadata::from_host(self.peer_host.clone()), tip, limit)
+    }
+
+    /// Make a new request for the transactions an account or contract took part in as of `tip`,
+    /// or the canonical chain tip if not given
+    pub fn new_get_address_transactions(&self, principal: PrincipalData, tip: Option<StacksBlockId>, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetAddressTransactions(HttpRequestMetadata::from_host(self.peer_host.clone()), principal, tip, limit)
+    }
+
+    /// Make a new request for what each contract cost to run in a block
+    pub fn new_get_block_contract_costs(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlockContractCosts(HttpRequestMetadata::from_host(self.peer_host.clone()), index_block_hash)
+    }
+
+    /// Make a new request to analyze a contract's source as if `deployer` deployed it as
+    /// `contract_name`
+    pub fn new_analyze_contract(&self, deployer: StacksAddress, contract_name: ContractName, source: String) -> HttpRequestType {
+        HttpRequestType::AnalyzeContract(HttpRequestMetadata::from_host(self.peer_host.clone()), deployer, contract_name, source)
+    }
+
+    /// Make a new request to dry-run deploying a contract's source as `deployer`'s
+    /// `contract_name`
+    pub fn new_dry_run_contract(&self, deployer: StacksAddress, contract_name: ContractName, source: String) -> HttpRequestType {
+        HttpRequestType::DryRunContract(HttpRequestMetadata::from_host(self.peer_host.clone()), deployer, contract_name, source)
+    }
+
+    /// Make a new request to estimate the fee of a transaction from `sender` with this payload
+    pub fn new_estimate_fee(&self, sender: StacksAddress, payload: TransactionPayload) -> HttpRequestType {
+        HttpRequestType::EstimateFee(HttpRequestMetadata::from_host(self.peer_host.clone()), sender, payload)
+    }
+
+    /// Make a new request to simulate a contract call from `sender` against these changes to chain state
+    pub fn new_simulate_transaction(&self, sender: StacksAddress, payload: TransactionPayload, overrides: Vec<StateOverride>) -> HttpRequestType {
+        HttpRequestType::SimulateTransaction(HttpRequestMetadata::from_host(self.peer_host.clone()), sender, payload, overrides)
+    }
+
+    pub fn new_getmempool_txids(&self, cursor: Option<Txid>, limit: u64) -> HttpRequestType {
+        HttpRequestType::GetMempoolTxids(HttpRequestMetadata::from_host(self.peer_host.clone()), cursor, limit)
+    }
+
+    /// Make a new request for a summary of the mempool
+    pub fn new_getmempool_stats(&self) -> HttpRequestType {
+        HttpRequestType::GetMempoolStats(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request for the node's Prometheus metrics
+    pub fn new_getmetrics(&self) -> HttpRequestType {
+        HttpRequestType::GetMetrics(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
     /// Make a new post-transaction request
     pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
-        HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(self.peer_host.clone()), tx)
+        HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(self.peer_host.clone()), tx, None)
+    }
+
+    /// Make a new post-transaction request that waits up to `timeout` seconds for the
+    /// transaction to be anchored
+    pub fn new_post_transaction_wait(&self, tx: StacksTransaction, timeout: u64) -> HttpRequestType {
+        HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(self.peer_host.clone()), tx, Some(timeout))
     }
 }
 
@@ -1148,6 +3470,8 @@ mod test {
     use chainstate::stacks::*;
     use burnchains::*;
    
+    use serde_json;
+
     use util::pipe::*;
     use util::get_epoch_time_secs;
 
@@ -1221,7 +3545,7 @@ mod test {
         let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
         let mut peer_1_mempool = peer_1.mempool.take().unwrap();
 
-        convo_1.chat(&view_1, &PeerMap::new(), &mut peer_1_burndb, &peer_1.network.peerdb, &mut peer_1_stacks_node.chainstate, &mut peer_1_mempool, &RPCHandlerArgs::default()).unwrap();
+        convo_1.chat(&view_1, &PeerMap::new(), &mut peer_1_burndb, &peer_1.network.peerdb, &mut peer_1_stacks_node.chainstate, &mut peer_1_mempool, &mut RPCRateLimiter::from_options(&peer_1.config.connection_opts), &mut RPCWorkerPool::from_options(&peer_1.config.connection_opts), &RPCHandlerArgs::default()).unwrap();
 
         peer_1.burndb = Some(peer_1_burndb);
         peer_1.stacks_node = Some(peer_1_stacks_node);
@@ -1234,7 +3558,7 @@ mod test {
         let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
         let mut peer_2_mempool = peer_2.mempool.take().unwrap();
 
-        convo_2.chat(&view_2, &PeerMap::new(), &mut peer_2_burndb, &peer_2.network.peerdb, &mut peer_2_stacks_node.chainstate, &mut peer_2_mempool, &RPCHandlerArgs::default()).unwrap();
+        convo_2.chat(&view_2, &PeerMap::new(), &mut peer_2_burndb, &peer_2.network.peerdb, &mut peer_2_stacks_node.chainstate, &mut peer_2_mempool, &mut RPCRateLimiter::from_options(&peer_2.config.connection_opts), &mut RPCWorkerPool::from_options(&peer_2.config.connection_opts), &RPCHandlerArgs::default()).unwrap();
         
         peer_2.burndb = Some(peer_2_burndb);
         peer_2.stacks_node = Some(peer_2_stacks_node);
@@ -1251,7 +3575,7 @@ mod test {
         let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
         let mut peer_1_mempool = peer_1.mempool.take().unwrap();
 
-        convo_1.chat(&view_1, &PeerMap::new(), &mut peer_1_burndb, &peer_1.network.peerdb, &mut peer_1_stacks_node.chainstate, &mut peer_1_mempool, &RPCHandlerArgs::default()).unwrap();
+        convo_1.chat(&view_1, &PeerMap::new(), &mut peer_1_burndb, &peer_1.network.peerdb, &mut peer_1_stacks_node.chainstate, &mut peer_1_mempool, &mut RPCRateLimiter::from_options(&peer_1.config.connection_opts), &mut RPCWorkerPool::from_options(&peer_1.config.connection_opts), &RPCHandlerArgs::default()).unwrap();
         
         peer_1.burndb = Some(peer_1_burndb);
         peer_1.stacks_node = Some(peer_1_stacks_node);
@@ -1513,6 +3837,218 @@ mod test {
                 });
     }
 
+    #[test]
+    fn test_rpc_getmetrics_disabled() {
+        test_rpc("test_rpc_getmetrics_disabled", 40090, 40091, 50090, 50091,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     // metrics are off by default
+                     convo_client.new_getmetrics()
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::NotFound(_, _) => true,
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_getmined_blocks() {
+        test_rpc("test_rpc_getmined_blocks", 40120, 40121, 50120, 50121,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_client.new_getmined_blocks()
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::MinedBlocks(_, mined_blocks) => {
+                            // the server hasn't mined anything
+                            assert_eq!(mined_blocks.acceptance_budget_secs, peer_server.config.connection_opts.mined_block_acceptance_budget);
+                            assert_eq!(mined_blocks.blocks.len(), 0);
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_ban_peers_disabled() {
+        test_rpc("test_rpc_ban_peers_disabled", 40130, 40131, 50130, 50131,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     // no API key is configured by default
+                     convo_client.new_ban_peers(PeerAddress::from_ipv4(1, 2, 3, 4), None, Some("secret".to_string()))
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::Forbidden(_, _) => true,
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_ban_peers_unauthorized() {
+        test_rpc("test_rpc_ban_peers_unauthorized", 40140, 40141, 50140, 50141,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_server.connection.options.rpc_api_key = Some("secret".to_string());
+                     convo_client.new_ban_peers(PeerAddress::from_ipv4(1, 2, 3, 4), None, Some("wrong".to_string()))
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::Unauthorized(_, _) => true,
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_ban_peers() {
+        test_rpc("test_rpc_ban_peers", 40150, 40151, 50150, 50151,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_server.connection.options.rpc_api_key = Some("secret".to_string());
+                     convo_client.new_ban_peers(PeerAddress::from_ipv4(1, 2, 3, 4), Some(20444), Some("secret".to_string()))
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::PeersBanned(_, data) => {
+                            // the test harness gives the server no p2p peers
+                            assert_eq!(data.banned, 0);
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_batch() {
+        test_rpc("test_rpc_batch", 40160, 40161, 50160, 50161,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     let batch_item = |method: &str, path: &str, body: Option<serde_json::Value>| RPCBatchRequestItem {
+                         method: method.to_string(),
+                         path: path.to_string(),
+                         body: body
+                     };
+                     convo_client.new_batch(vec![
+                        batch_item("GET", "/v2/status", None),
+                        batch_item("get", "/v2/neighbors/stats", None),
+                        batch_item("GET", &format!("/v2/blocks/{}", StacksBlockId([0x11; 32]).to_hex()), None),
+                        batch_item("POST", "/v2/neighbors/ban", Some(json!({ "ip": "1.2.3.4" }))),
+                        batch_item("POST", "/v2/contracts/call-read/SP000000000000000000002Q6VF78/pox/get-pox-info", Some(json!({ "sender": "nope" }))),
+                        batch_item("GET", "/v2/nope", None),
+                     ])
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::BatchResults(_, results) => {
+                            let statuses : Vec<u16> = results.iter().map(|result| result.status).collect();
+                            assert_eq!(statuses, vec![200, 200, 400, 400, 400, 404]);
+
+                            // replies are embedded in order
+                            let burnchain_tip = BurnDB::get_canonical_burn_chain_tip(peer_server.burndb.as_ref().unwrap().conn()).unwrap();
+                            assert_eq!(results[0].body["burn_block_height"], json!(burnchain_tip.block_height));
+                            assert_eq!(results[1].body["peers"], json!([]));
+
+                            // streams and writes aren't run
+                            assert!(results[2].body.as_str().unwrap().find("cannot be batched").is_some());
+                            assert!(results[3].body.as_str().unwrap().find("cannot be batched").is_some());
+
+                            assert!(results[4].body.as_str().unwrap().find("Failed to parse POST").is_some());
+                            assert_eq!(results[5].body, json!("/v2/nope"));
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_getneighbor_stats() {
+        test_rpc("test_rpc_getneighbor_stats", 40110, 40111, 50110, 50111,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_client.new_getneighbor_stats()
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::NeighborStats(_, stats_info) => {
+                            // the test harness gives the server no p2p peers
+                            assert_eq!(stats_info.peers.len(), 0);
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_getconsensus_constants() {
+        test_rpc("test_rpc_getconsensus_constants", 40170, 40171, 50170, 50171,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_client.new_getconsensus_constants()
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::ConsensusConstants(_, constants_data) => {
+                            assert_eq!(constants_data.constants, consensus_constants());
+                            assert_eq!(constants_data.digest, consensus_constants_digest(&constants_data.constants).to_hex());
+                            assert!(constants_data.constants.iter().find(|c| c.name == "PEER_VERSION").is_some());
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
+    #[test]
+    fn test_rpc_getstatus() {
+        test_rpc("test_rpc_getstatus", 40100, 40101, 50100, 50101,
+                 |ref mut peer_client, ref mut convo_client, ref mut peer_server, ref mut convo_server| {
+                     convo_client.new_getstatus(false)
+                 },
+                 |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                     match http_response {
+                        HttpResponseType::Status(_, status) => {
+                            let burnchain_tip = BurnDB::get_canonical_burn_chain_tip(peer_server.burndb.as_ref().unwrap().conn()).unwrap();
+                            assert_eq!(status.burn_block_height, burnchain_tip.block_height);
+                            assert!(status.burn_block_target_height >= status.burn_block_height);
+                            assert_eq!(status.stacks_tip, burnchain_tip.canonical_stacks_tip_hash);
+                            assert_eq!(status.stacks_tip_height, burnchain_tip.canonical_stacks_tip_height);
+                            assert!(!status.is_miner);
+                            assert!(!status.safe_mode);
+                            true
+                        },
+                        _ => {
+                           error!("Invalid response: {:?}", &http_response);
+                           false
+                        }
+                    }
+                });
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_missing_getblock() {
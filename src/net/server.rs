@@ -39,6 +39,10 @@ use net::poll::*;
 use net::rpc::*;
 use net::http::*;
 use net::p2p::PeerMap;
+use net::overload::{OverloadDetector, RequestPriority};
+use net::ratelimit::RPCRateLimiter;
+use net::workers::RPCWorkerPool;
+use net::tls::{HttpStream, TlsServerConfig};
 
 use chainstate::burn::db::burndb::BurnDB;
 use chainstate::stacks::db::StacksChainState;
@@ -49,6 +53,9 @@ use burnchains::BurnchainView;
 use mio::net as mio_net;
 
 use util::get_epoch_time_secs;
+use util::get_epoch_time_ms;
+
+use monitoring;
 
 use core::mempool::*;
 
@@ -58,7 +65,7 @@ pub struct HttpPeer {
 
     // ongoing http conversations (either they reached out to us, or we to them)
     pub peers: HashMap<usize, ConversationHttp>,
-    pub sockets: HashMap<usize, mio_net::TcpStream>,
+    pub sockets: HashMap<usize, HttpStream>,
 
     // outbound connections that are pending connection 
     pub connecting: HashMap<usize, (mio_net::TcpStream, Option<UrlString>, Option<HttpRequestType>, u64)>,
@@ -71,6 +78,21 @@ pub struct HttpPeer {
 
     // connection options
     pub connection_opts: ConnectionOptions,
+
+    // how backed-up we are
+    pub overload: OverloadDetector,
+
+    // how many requests clients have been making
+    pub rate_limiter: RPCRateLimiter,
+
+    // how many heavy requests we've started in this pass
+    pub workers: RPCWorkerPool,
+
+    // p2p event IDs of peers that clients asked us to ban
+    pub bans: Vec<usize>,
+
+    // certificate and key to terminate TLS with on inbound connections, if any
+    pub tls_config: Option<TlsServerConfig>,
 }
 
 impl HttpPeer {
@@ -85,14 +107,29 @@ impl HttpPeer {
             http_server_handle: server_handle,
 
             burnchain: burnchain,
+            overload: OverloadDetector::new(conn_opts.rpc_overload_queue_depth, conn_opts.rpc_overload_latency_ms),
+            rate_limiter: RPCRateLimiter::from_options(&conn_opts),
+            workers: RPCWorkerPool::from_options(&conn_opts),
+            bans: vec![],
+            tls_config: None,
             connection_opts: conn_opts
         }
     }
 
+    /// Take the p2p event IDs of the peers that clients asked us to ban
+    pub fn take_bans(&mut self) -> Vec<usize> {
+        std::mem::replace(&mut self.bans, vec![])
+    }
+
     pub fn set_server_handle(&mut self, h: usize) -> () {
         self.http_server_handle = h;
     }
 
+    /// Terminate TLS on inbound connections with this certificate and key (or don't, if None)
+    pub fn set_tls_config(&mut self, tls_config: Option<TlsServerConfig>) -> () {
+        self.tls_config = tls_config;
+    }
+
     /// Is there a HTTP conversation open to this data_url that is not in progress?
     pub fn find_free_conversation(&self, data_url: &UrlString) -> Option<usize> {
         for (event_id, convo) in self.peers.iter() {
@@ -111,7 +148,7 @@ impl HttpPeer {
     }
     
     /// Get a mut ref to a conversation and its socket
-    pub fn get_conversation_and_socket(&mut self, event_id: usize) -> (Option<&mut ConversationHttp>, Option<&mut mio::net::TcpStream>) {
+    pub fn get_conversation_and_socket(&mut self, event_id: usize) -> (Option<&mut ConversationHttp>, Option<&mut HttpStream>) {
         (self.peers.get_mut(&event_id), self.sockets.get_mut(&event_id))
     }
 
@@ -166,7 +203,7 @@ impl HttpPeer {
     /// Low-level method to register a socket/event pair on the p2p network interface.
     /// Call only once the socket is connected (called once the socket triggers ready).
     /// Will destroy the socket if we can't register for whatever reason.
-    fn register_http(&mut self, network_state: &mut NetworkState, chainstate: &mut StacksChainState, event_id: usize, mut socket: mio_net::TcpStream, outbound_url: Option<UrlString>, initial_request: Option<HttpRequestType>) -> Result<(), net_error> {
+    fn register_http(&mut self, network_state: &mut NetworkState, chainstate: &mut StacksChainState, event_id: usize, mut socket: HttpStream, outbound_url: Option<UrlString>, initial_request: Option<HttpRequestType>) -> Result<(), net_error> {
         let client_addr = match socket.peer_addr() {
             Ok(addr) => addr,
             Err(e) => {
@@ -178,7 +215,7 @@ impl HttpPeer {
         match self.can_register_http(&client_addr, outbound_url.as_ref()) {
             Ok(_) => {},
             Err(e) => {
-                let _ = network_state.deregister(event_id, &socket);
+                let _ = network_state.deregister(event_id, socket.get_ref());
                 return Err(e);
             }
         }
@@ -197,7 +234,7 @@ impl HttpPeer {
             match new_convo.send_request(request) {
                 Ok(_) => {},
                 Err(e) => {
-                    let _ = network_state.deregister(event_id, &socket);
+                    let _ = network_state.deregister(event_id, socket.get_ref());
                     return Err(e);
                 }
             }
@@ -206,7 +243,7 @@ impl HttpPeer {
             match HttpPeer::saturate_http_socket(&mut socket, &mut new_convo, chainstate) {
                 Ok(_) => {},
                 Err(e) => {
-                    let _ = network_state.deregister(event_id, &socket);
+                    let _ = network_state.deregister(event_id, socket.get_ref());
                     return Err(e);
                 }
             }
@@ -228,7 +265,7 @@ impl HttpPeer {
         match self.sockets.get_mut(&event_id) {
             None => {},
             Some(ref sock) => {
-                let _ = network_state.deregister(event_id, sock);
+                let _ = network_state.deregister(event_id, sock.get_ref());
                 to_remove.push(event_id);   // force it to close anyway
             }
         }
@@ -252,6 +289,11 @@ impl HttpPeer {
         }
         
         for (event_id, convo) in self.peers.iter() {
+            if convo.has_pending_tx_wait() {
+                // waiting on the chain, not the client; the wait has its own timeout
+                continue;
+            }
+
             let mut last_request_time = convo.get_last_request_time();
             if last_request_time == 0 {
                 // never got a request
@@ -278,7 +320,7 @@ impl HttpPeer {
 
     /// Saturate a conversation's socket -- either sends the whole request, or fills the socket
     /// buffer.
-    pub fn saturate_http_socket(client_sock: &mut mio::net::TcpStream, convo: &mut ConversationHttp, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+    pub fn saturate_http_socket(client_sock: &mut HttpStream, convo: &mut ConversationHttp, chainstate: &mut StacksChainState) -> Result<(), net_error> {
         // saturate the socket
         loop {
             let send_res = convo.send(client_sock, chainstate);
@@ -319,6 +361,7 @@ impl HttpPeer {
                 continue;
             }
 
+            let client_sock = HttpStream::accept(client_sock, self.tls_config.as_ref());
             if let Err(_e) = self.register_http(network_state, chainstate, event_id, client_sock, None, None) {
                 // NOTE: register_http will deregister the socket for us
                 continue;
@@ -335,9 +378,9 @@ impl HttpPeer {
     fn process_http_conversation(chain_view: &BurnchainView, peers: &PeerMap,
                                  burndb: &BurnDB, peerdb: &PeerDB,
                                  chainstate: &mut StacksChainState, mempool: &mut MemPoolDB,
-                                 event_id: usize, client_sock: &mut mio_net::TcpStream,
-                                 convo: &mut ConversationHttp,
-                                 handler_args: &RPCHandlerArgs) -> Result<(bool, Vec<StacksMessageType>), net_error> {
+                                 event_id: usize, client_sock: &mut HttpStream,
+                                 convo: &mut ConversationHttp, rate_limiter: &mut RPCRateLimiter,
+                                 workers: &mut RPCWorkerPool, handler_args: &RPCHandlerArgs) -> Result<(bool, Vec<StacksMessageType>), net_error> {
         // get incoming bytes and update the state of this conversation.
         let mut convo_dead = false;
         let recv_res = convo.recv(client_sock);
@@ -381,7 +424,7 @@ impl HttpPeer {
         // react to inbound messages -- do we need to send something out, or fulfill requests
         // to other threads?  Try to chat even if the recv() failed, since we'll want to at
         // least drain the conversation inbox.
-        let msgs = match convo.chat(chain_view, peers, burndb, peerdb, chainstate, mempool, handler_args) {
+        let msgs = match convo.chat(chain_view, peers, burndb, peerdb, chainstate, mempool, rate_limiter, workers, handler_args) {
             Ok(msgs) => msgs,
             Err(e) => {
                 debug!("Failed to converse HTTP on event {} (socket {:?}): {:?}", event_id, &client_sock, &e);
@@ -400,6 +443,12 @@ impl HttpPeer {
                     convo_dead = true;
                 }
             }
+
+            // push out anything the socket buffered (i.e. TLS records)
+            if let Err(e) = client_sock.flush() {
+                debug!("Failed to flush HTTP data to event {} (socket {:?}): {:?}", event_id, &client_sock, &e);
+                convo_dead = true;
+            }
         }
 
         Ok((!convo_dead, msgs))
@@ -417,7 +466,7 @@ impl HttpPeer {
                 let (socket, data_url, initial_request_opt, _) = self.connecting.remove(event_id).unwrap();
                 debug!("HTTP event {} connected ({:?})", event_id, &data_url);
 
-                if let Err(_e) = self.register_http(network_state, chainstate, *event_id, socket, data_url.clone(), initial_request_opt) {
+                if let Err(_e) = self.register_http(network_state, chainstate, *event_id, HttpStream::Plain(socket), data_url.clone(), initial_request_opt) {
                     debug!("Failed to register HTTP connection ({}, {:?})", event_id, data_url);
                 }
             }
@@ -452,7 +501,7 @@ impl HttpPeer {
                     // activity on a http socket
                     test_debug!("Process HTTP data from {:?}", convo);
                     match HttpPeer::process_http_conversation(&self.chain_view, peers, burndb, peerdb, chainstate, mempool,
-                                                              *event_id, client_sock, convo, handler_args) {
+                                                              *event_id, client_sock, convo, &mut self.rate_limiter, &mut self.workers, handler_args) {
                         Ok((alive, mut new_msgs)) => {
                             if !alive {
                                 to_remove.push(*event_id);
@@ -475,6 +524,58 @@ impl HttpPeer {
         (msgs, to_remove)
     }
 
+    /// Finish transaction submissions that are waiting for their transactions to be anchored.
+    /// Once a wait is over, advance its conversation so it sends the receipt and handles any
+    /// requests that queued up behind it (its socket may not become ready on its own).
+    /// Return the list of events that correspond to failed conversations, as well as the list of
+    /// peer network messages we'll need to forward
+    fn process_tx_waits(&mut self, peers: &PeerMap, burndb: &BurnDB, peerdb: &PeerDB,
+                        chainstate: &mut StacksChainState, mempool: &mut MemPoolDB, handler_args: &RPCHandlerArgs) -> (Vec<StacksMessageType>, Vec<usize>) {
+        let mut to_remove = vec![];
+        let mut msgs = vec![];
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.has_pending_tx_wait() {
+                continue;
+            }
+
+            match convo.try_finish_tx_wait(burndb, chainstate) {
+                Ok(true) => {},
+                Ok(false) => {
+                    continue;
+                },
+                Err(_e) => {
+                    debug!("Failed to finish transaction wait on event {}: {:?}", event_id, &_e);
+                    to_remove.push(*event_id);
+                    continue;
+                }
+            }
+
+            let client_sock = match self.sockets.get_mut(event_id) {
+                Some(client_sock) => client_sock,
+                None => {
+                    test_debug!("No such socket event {}", event_id);
+                    to_remove.push(*event_id);
+                    continue;
+                }
+            };
+
+            match HttpPeer::process_http_conversation(&self.chain_view, peers, burndb, peerdb, chainstate, mempool,
+                                                      *event_id, client_sock, convo, &mut self.rate_limiter, &mut self.workers, handler_args) {
+                Ok((alive, mut new_msgs)) => {
+                    if !alive {
+                        to_remove.push(*event_id);
+                    }
+                    msgs.append(&mut new_msgs);
+                },
+                Err(_e) => {
+                    to_remove.push(*event_id);
+                }
+            }
+        }
+
+        (msgs, to_remove)
+    }
+
     /// Flush outgoing replies, but don't block.
     /// Drop broken handles.
     /// Return the list of conversation event IDs to close (i.e. they're broken, or the request is done)
@@ -490,7 +591,15 @@ impl HttpPeer {
                     close.push(*event_id);
                 }
             }
-            if convo.is_drained() && !convo.is_keep_alive() {
+
+            // don't cut off a reply the socket is still sending (i.e. buffered TLS records)
+            let mut socket_drained = true;
+            if let Some(socket) = self.sockets.get_mut(event_id) {
+                let _ = socket.flush();
+                socket_drained = !socket.has_pending_output();
+            }
+
+            if convo.is_drained() && socket_drained && !convo.is_keep_alive() {
                 // did some work, but nothing more to do and we're not keep-alive
                 debug!("Close drained HTTP connection {:?}", convo);
                 close.push(*event_id);
@@ -500,6 +609,42 @@ impl HttpPeer {
         close
     }
     
+    /// Event IDs of the conversations that still have requests queued from a previous pass, and
+    /// aren't already in `ready`.  Conversations waiting on a transaction submission are left to
+    /// `process_tx_waits()`.
+    fn queued_request_events(&self, ready: &Vec<usize>) -> Vec<usize> {
+        let mut queued : Vec<usize> = self.peers.iter()
+            .filter(|(event_id, convo)| convo.num_pending_inbound() > 0 && !convo.has_pending_tx_wait() && !ready.contains(*event_id))
+            .map(|(event_id, _)| *event_id)
+            .collect();
+        queued.sort();
+        queued
+    }
+
+    /// Update the overload detector with how long this pass took and how much work is still
+    /// backed up in our conversations.
+    fn update_overload(&mut self, pass_ms: u64) -> () {
+        let queue_depth : u64 = self.peers.values()
+            .map(|convo| (convo.num_pending_inbound() + convo.num_pending_outbound()) as u64)
+            .sum();
+
+        let old_shed_priority = self.overload.shed_priority();
+        self.overload.record_pass(queue_depth, pass_ms);
+        let new_shed_priority = self.overload.shed_priority();
+
+        if old_shed_priority != new_shed_priority {
+            match new_shed_priority {
+                Some(priority) => warn!("HTTP server overloaded ({} queued, {} ms/pass): shedding {}-priority requests", queue_depth, pass_ms, priority),
+                None => info!("HTTP server no longer overloaded; serving all requests")
+            }
+            monitoring::set_rpc_overload_level(match new_shed_priority {
+                None => 0,
+                Some(RequestPriority::Low) => 1,
+                Some(_) => 2
+            });
+        }
+    }
+
     /// Update HTTP server state
     /// -- accept new connections
     /// -- send data on ready sockets
@@ -520,14 +665,38 @@ impl HttpPeer {
         // set up connected sockets
         self.process_connecting_sockets(network_state, chainstate, &mut poll_state);
 
-        // run existing conversations, clear out broken ones, and get back messages forwarded to us
-        let (stacks_msgs, error_events) = self.process_ready_sockets(
-            &mut poll_state, p2p_peers, burndb, peerdb, chainstate, mempool, handler_args);
+        // conversations with requests left queued in the last pass (i.e. heavy requests that
+        // didn't get a worker) go first, even if their sockets aren't ready.
+        self.workers.begin_pass();
+        let mut ready = self.queued_request_events(&poll_state.ready);
+        ready.append(&mut poll_state.ready);
+        poll_state.ready = ready;
+
+        // run existing conversations, clear out broken ones, and get back messages forwarded to us.
+        // If we're overloaded, shed low-priority requests.
+        let handler_args = RPCHandlerArgs { shed_priority: self.overload.shed_priority(), .. handler_args.clone() };
+        let pass_start_ms = get_epoch_time_ms();
+        let (mut stacks_msgs, error_events) = self.process_ready_sockets(
+            &mut poll_state, p2p_peers, burndb, peerdb, chainstate, mempool, &handler_args);
         for error_event in error_events {
             debug!("Failed HTTP connection on event {}", error_event);
             self.deregister_http(network_state, error_event);
         }
 
+        // reply to transaction submissions whose transactions got anchored (or timed out)
+        let (mut tx_wait_msgs, tx_wait_error_events) = self.process_tx_waits(
+            p2p_peers, burndb, peerdb, chainstate, mempool, &handler_args);
+        stacks_msgs.append(&mut tx_wait_msgs);
+        for error_event in tx_wait_error_events {
+            debug!("Failed HTTP connection on event {}", error_event);
+            self.deregister_http(network_state, error_event);
+        }
+
+        // pass along the peers that clients asked us to ban
+        for (_, convo) in self.peers.iter_mut() {
+            self.bans.append(&mut convo.take_bans());
+        }
+
         // move conversations along
         let close_events = self.flush_conversations(chainstate);
         for close_event in close_events {
@@ -535,6 +704,9 @@ impl HttpPeer {
             self.deregister_http(network_state, close_event);
         }
 
+        let pass_ms = get_epoch_time_ms().saturating_sub(pass_start_ms) as u64;
+        self.update_overload(pass_ms);
+
         // remove timed-out requests 
         for (_, convo) in self.peers.iter_mut() {
             convo.clear_timeouts();
@@ -543,6 +715,9 @@ impl HttpPeer {
         // clear out slow or non-responsive peers
         self.disconnect_unresponsive(network_state);
 
+        // forget clients that have stopped making requests
+        self.rate_limiter.prune(get_epoch_time_ms() as u64);
+
         Ok(stacks_msgs)
     }
 }
@@ -790,6 +965,45 @@ mod test {
                         });
     }
     
+    #[test]
+    #[ignore]
+    fn test_http_10_threads_getblock_one_worker() {
+        // block fetches are heavy, so with one worker, they're served one pass at a time
+        let mut conn_opts = ConnectionOptions::default();
+        conn_opts.rpc_heavy_request_workers = 1;
+
+        test_http_server("test_http_getblock_one_worker", 51090, 51091, conn_opts, 10, 0,
+                        |client_id, ref mut peer_server| {
+                            let peer_server_block = make_codec_test_block(25);
+                            let peer_server_burn_block_hash = BurnchainHeaderHash([(client_id+1) as u8; 32]);
+                            let index_block_hash = StacksBlockHeader::make_index_block_hash(&peer_server_burn_block_hash, &peer_server_block.block_hash());
+
+                            test_debug!("Store peer server index block {:?}", &index_block_hash);
+                            store_staging_block(peer_server.chainstate(), &peer_server_burn_block_hash, get_epoch_time_secs(), &peer_server_block, &BurnchainHeaderHash([client_id as u8; 32]), 456, 123);
+
+                            let mut request = HttpRequestType::GetBlock(HttpRequestMetadata::from_host(PeerHost::from_host_port("127.0.0.1".to_string(), 51091)), index_block_hash);
+                            request.metadata_mut().keep_alive = false;
+                            
+                            let request_bytes = StacksHttp::serialize_request(&request).unwrap();
+                            request_bytes
+                        },
+                        |client_id, http_response_bytes_res| {
+                            // should be a Block
+                            let http_response_bytes = http_response_bytes_res.unwrap();
+
+                            let peer_server_block = make_codec_test_block(25);
+                            let peer_server_burn_block_hash = BurnchainHeaderHash([(client_id+1) as u8; 32]);
+                            let index_block_hash = StacksBlockHeader::make_index_block_hash(&peer_server_burn_block_hash, &peer_server_block.block_hash());
+
+                            let request_path = format!("/v2/blocks/{}", &index_block_hash);
+                            let response = StacksHttp::parse_response(&request_path, &http_response_bytes).unwrap();
+                            match response {
+                                StacksHttpMessage::Response(HttpResponseType::Block(md, block_data)) => block_data == peer_server_block,
+                                _ => false
+                            }
+                        });
+    }
+    
     #[test]
     #[ignore]
     fn test_http_too_many_clients() {
@@ -892,7 +1106,7 @@ mod test {
                             
                             let signed_contract_tx = signer.get_tx().unwrap();
 
-                            let mut request = HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(PeerHost::from_host_port("127.0.0.1".to_string(), 51061)), signed_contract_tx);
+                            let mut request = HttpRequestType::PostTransaction(HttpRequestMetadata::from_host(PeerHost::from_host_port("127.0.0.1".to_string(), 51061)), signed_contract_tx, None);
                             request.metadata_mut().keep_alive = false;
                             
                             let request_bytes = StacksHttp::serialize_request(&request).unwrap();
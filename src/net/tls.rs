@@ -0,0 +1,298 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+#[cfg(feature = "tls")]
+use std::fs::File;
+#[cfg(feature = "tls")]
+use std::io::BufReader;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+#[cfg(feature = "tls")]
+use rustls;
+#[cfg(feature = "tls")]
+use rustls::Session;
+
+use mio::net as mio_net;
+
+use net::Error as net_error;
+use net::connection::ConnectionOptions;
+
+/// Certificate chain and private key the RPC server presents to its clients.
+/// Only usable if the node was built with the `tls` feature.
+#[derive(Clone)]
+pub struct TlsServerConfig {
+    #[cfg(feature = "tls")]
+    inner: Arc<rustls::ServerConfig>,
+}
+
+impl fmt::Debug for TlsServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TlsServerConfig")
+    }
+}
+
+impl TlsServerConfig {
+    /// Load the RPC server's TLS certificate and key, if the node is configured to terminate TLS.
+    /// Returns None if it isn't.
+    pub fn from_options(opts: &ConnectionOptions) -> Result<Option<TlsServerConfig>, net_error> {
+        match (opts.rpc_tls_cert_path.as_ref(), opts.rpc_tls_key_path.as_ref()) {
+            (None, None) => Ok(None),
+            (Some(cert_path), Some(key_path)) => TlsServerConfig::load(cert_path, key_path).map(|config| Some(config)),
+            (_, _) => Err(net_error::TlsError("Both a TLS certificate and a TLS private key are required".to_string()))
+        }
+    }
+
+    /// Load a PEM-encoded certificate chain and PEM-encoded (PKCS#8 or RSA) private key.
+    #[cfg(feature = "tls")]
+    pub fn load(cert_path: &str, key_path: &str) -> Result<TlsServerConfig, net_error> {
+        let cert_file = File::open(cert_path)
+            .map_err(|e| net_error::TlsError(format!("Failed to open TLS certificate {}: {:?}", cert_path, &e)))?;
+        let certs = rustls::internal::pemfile::certs(&mut BufReader::new(cert_file))
+            .map_err(|_| net_error::TlsError(format!("Failed to parse TLS certificate {}", cert_path)))?;
+        if certs.len() == 0 {
+            return Err(net_error::TlsError(format!("No certificates in {}", cert_path)));
+        }
+
+        let mut keys = TlsServerConfig::load_keys(key_path, rustls::internal::pemfile::pkcs8_private_keys)?;
+        if keys.len() == 0 {
+            keys = TlsServerConfig::load_keys(key_path, rustls::internal::pemfile::rsa_private_keys)?;
+        }
+        if keys.len() == 0 {
+            return Err(net_error::TlsError(format!("No private keys in {}", key_path)));
+        }
+
+        let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+        config.set_single_cert(certs, keys.remove(0))
+            .map_err(|e| net_error::TlsError(format!("Invalid TLS certificate or private key: {:?}", &e)))?;
+
+        Ok(TlsServerConfig {
+            inner: Arc::new(config)
+        })
+    }
+
+    #[cfg(feature = "tls")]
+    fn load_keys<F>(key_path: &str, parse: F) -> Result<Vec<rustls::PrivateKey>, net_error>
+    where
+        F: FnOnce(&mut dyn io::BufRead) -> Result<Vec<rustls::PrivateKey>, ()>
+    {
+        let key_file = File::open(key_path)
+            .map_err(|e| net_error::TlsError(format!("Failed to open TLS private key {}: {:?}", key_path, &e)))?;
+        parse(&mut BufReader::new(key_file))
+            .map_err(|_| net_error::TlsError(format!("Failed to parse TLS private key {}", key_path)))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub fn load(_cert_path: &str, _key_path: &str) -> Result<TlsServerConfig, net_error> {
+        Err(net_error::TlsError("This node was built without TLS support (enable the `tls` feature)".to_string()))
+    }
+}
+
+/// A TLS session with an RPC client, driven over a non-blocking socket.
+#[cfg(feature = "tls")]
+pub struct TlsStream {
+    socket: mio_net::TcpStream,
+    session: rustls::ServerSession,
+}
+
+#[cfg(feature = "tls")]
+impl TlsStream {
+    /// Send pending TLS records until there are none left.
+    /// Fails with WouldBlock if the socket can't take all of them yet.
+    fn write_tls(&mut self) -> io::Result<()> {
+        while self.session.wants_write() {
+            if self.session.write_tls(&mut self.socket)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero));
+            }
+        }
+        Ok(())
+    }
+
+    /// Send as many pending TLS records as the socket will take.  The rest go out on a later
+    /// read, write, or flush.
+    fn try_write_tls(&mut self) -> io::Result<()> {
+        match self.write_tls() {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            res => res
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // hand back any plaintext we already decrypted
+            let nr = self.session.read(buf)?;
+            if nr > 0 {
+                return Ok(nr);
+            }
+
+            // handshake messages (and any records left over from a blocked write) go out first
+            self.try_write_tls()?;
+
+            // fails with WouldBlock if there's nothing more to read
+            if self.session.read_tls(&mut self.socket)? == 0 {
+                return Ok(0);
+            }
+
+            if let Err(e) = self.session.process_new_packets() {
+                // tell the client what went wrong, if we can
+                let _ = self.try_write_tls();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("TLS error: {:?}", &e)));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // don't buffer any more plaintext until the socket has taken what we already encrypted
+        self.write_tls()?;
+        let nw = self.session.write(buf)?;
+        self.try_write_tls()?;
+        Ok(nw)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.session.flush()?;
+        self.try_write_tls()
+    }
+}
+
+/// A socket to an HTTP peer.  Inbound connections are TLS-encrypted if the RPC server is
+/// configured to terminate TLS; outbound connections never are.
+pub enum HttpStream {
+    Plain(mio_net::TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+impl HttpStream {
+    /// Wrap a socket accepted by the RPC server
+    pub fn accept(socket: mio_net::TcpStream, tls_config: Option<&TlsServerConfig>) -> HttpStream {
+        match tls_config {
+            None => HttpStream::Plain(socket),
+            #[cfg(feature = "tls")]
+            Some(config) => HttpStream::Tls(TlsStream {
+                socket: socket,
+                session: rustls::ServerSession::new(&config.inner)
+            }),
+            #[cfg(not(feature = "tls"))]
+            Some(_) => unreachable!("TLS config loaded without TLS support"),
+        }
+    }
+
+    /// The underlying socket, for registering with the poller
+    pub fn get_ref(&self) -> &mio_net::TcpStream {
+        match *self {
+            HttpStream::Plain(ref socket) => socket,
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref stream) => &stream.socket,
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<::std::net::SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    /// Is there data buffered in the stream that the socket hasn't taken yet?
+    pub fn has_pending_output(&self) -> bool {
+        match *self {
+            HttpStream::Plain(_) => false,
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref stream) => stream.session.wants_write(),
+        }
+    }
+}
+
+impl fmt::Debug for HttpStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HttpStream::Plain(ref socket) => write!(f, "{:?}", socket),
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref stream) => write!(f, "TLS({:?})", &stream.socket),
+        }
+    }
+}
+
+impl Read for HttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            HttpStream::Plain(ref mut socket) => socket.read(buf),
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for HttpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            HttpStream::Plain(ref mut socket) => socket.write(buf),
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            HttpStream::Plain(ref mut socket) => socket.flush(),
+            #[cfg(feature = "tls")]
+            HttpStream::Tls(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tls_server_config_from_options() {
+        let mut opts = ConnectionOptions::default();
+        assert!(TlsServerConfig::from_options(&opts).unwrap().is_none());
+
+        // need both a cert and a key
+        opts.rpc_tls_cert_path = Some("/does/not/exist/cert.pem".to_string());
+        match TlsServerConfig::from_options(&opts) {
+            Err(net_error::TlsError(_)) => {},
+            res => panic!("Expected TlsError, got {:?}", &res)
+        }
+
+        opts.rpc_tls_cert_path = None;
+        opts.rpc_tls_key_path = Some("/does/not/exist/key.pem".to_string());
+        match TlsServerConfig::from_options(&opts) {
+            Err(net_error::TlsError(_)) => {},
+            res => panic!("Expected TlsError, got {:?}", &res)
+        }
+
+        // missing files (or no TLS support)
+        opts.rpc_tls_cert_path = Some("/does/not/exist/cert.pem".to_string());
+        match TlsServerConfig::from_options(&opts) {
+            Err(net_error::TlsError(_)) => {},
+            res => panic!("Expected TlsError, got {:?}", &res)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+
+use net::connection::ConnectionOptions;
+
+/// The HTTP server's workers for heavy requests (see `HttpRequestType::is_heavy()`).  The server
+/// handles requests on the network thread, one pass over its conversations at a time, so a
+/// worker is a slot in a pass: at most `heavy_workers` heavy requests start per pass, and at
+/// most `max_heavy_per_conn` of them come from any one connection.  A heavy request that
+/// doesn't get a worker stays queued in its connection, along with the requests behind it,
+/// until a later pass.  Other requests never wait for a worker.
+/// A limit of 0 disables it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCWorkerPool {
+    heavy_workers: u64,
+    max_heavy_per_conn: u64,
+
+    heavy_started: u64,
+    heavy_started_per_conn: HashMap<usize, u64>,
+}
+
+impl RPCWorkerPool {
+    pub fn new(heavy_workers: u64, max_heavy_per_conn: u64) -> RPCWorkerPool {
+        RPCWorkerPool {
+            heavy_workers: heavy_workers,
+            max_heavy_per_conn: max_heavy_per_conn,
+            heavy_started: 0,
+            heavy_started_per_conn: HashMap::new(),
+        }
+    }
+
+    pub fn from_options(opts: &ConnectionOptions) -> RPCWorkerPool {
+        RPCWorkerPool::new(opts.rpc_heavy_request_workers, opts.rpc_max_heavy_requests_per_connection)
+    }
+
+    /// Free up all workers, at the start of a pass over the server's conversations.
+    pub fn begin_pass(&mut self) -> () {
+        self.heavy_started = 0;
+        self.heavy_started_per_conn.clear();
+    }
+
+    /// Try to get a worker for a request on the given connection.  Returns false if it's heavy
+    /// and there's no worker free for it, in which case it should wait for a later pass.
+    pub fn try_start(&mut self, conn_id: usize, heavy: bool) -> bool {
+        if !heavy {
+            return true;
+        }
+
+        if self.heavy_workers > 0 && self.heavy_started >= self.heavy_workers {
+            return false;
+        }
+
+        let started_on_conn = self.heavy_started_per_conn.entry(conn_id).or_insert(0);
+        if self.max_heavy_per_conn > 0 && *started_on_conn >= self.max_heavy_per_conn {
+            return false;
+        }
+
+        *started_on_conn += 1;
+        self.heavy_started += 1;
+        true
+    }
+
+    /// How many heavy requests have started in this pass?
+    pub fn num_heavy_started(&self) -> u64 {
+        self.heavy_started
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_worker_pool_limits() {
+        let mut pool = RPCWorkerPool::new(3, 2);
+
+        // light requests never wait
+        for _ in 0..10 {
+            assert!(pool.try_start(1, false));
+        }
+        assert_eq!(pool.num_heavy_started(), 0);
+
+        // per-connection limit
+        assert!(pool.try_start(1, true));
+        assert!(pool.try_start(1, true));
+        assert!(!pool.try_start(1, true));
+        assert!(pool.try_start(1, false));
+
+        // pool-wide limit
+        assert!(pool.try_start(2, true));
+        assert!(!pool.try_start(2, true));
+        assert!(!pool.try_start(3, true));
+        assert_eq!(pool.num_heavy_started(), 3);
+
+        // all free again on the next pass
+        pool.begin_pass();
+        assert_eq!(pool.num_heavy_started(), 0);
+        assert!(pool.try_start(1, true));
+        assert!(pool.try_start(3, true));
+    }
+
+    #[test]
+    fn test_worker_pool_unlimited() {
+        let mut pool = RPCWorkerPool::new(0, 0);
+        for _ in 0..100 {
+            assert!(pool.try_start(1, true));
+        }
+        assert_eq!(pool.num_heavy_started(), 100);
+
+        // only the per-connection limit
+        let mut pool = RPCWorkerPool::new(0, 1);
+        assert!(pool.try_start(1, true));
+        assert!(!pool.try_start(1, true));
+        assert!(pool.try_start(2, true));
+    }
+}
@@ -25,6 +25,7 @@ use std::io::Error as IOError;
 use std::path::PathBuf;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::sync::Mutex;
 
 use util::hash::to_hex;
 use util::sleep_ms;
@@ -32,10 +33,13 @@ use util::sleep_ms;
 use chainstate::burn::BlockHeaderHash;
 
 use rusqlite::NO_PARAMS;
+use rusqlite::ffi;
 use rusqlite::Error as sqlite_error;
 use rusqlite::Connection;
+use rusqlite::OpenFlags;
 use rusqlite::Row;
 use rusqlite::TransactionBehavior;
+use rusqlite::backup::{Backup, StepResult};
 use rusqlite::Transaction;
 use rusqlite::types::{ToSql, ToSqlOutput, FromSql, FromSqlResult, FromSqlError, Value as RusqliteValue, ValueRef as RusqliteValueRef};
 
@@ -184,6 +188,14 @@ pub fn u64_to_sql(x: u64) -> Result<i64, Error> {
     Ok(x as i64)
 }
 
+/// Bounds of the lowercase hex strings that start with `prefix`, so a prefix search can be an
+/// indexed range scan (`col >= lower AND col < upper`) instead of a `LIKE`, which can't use the
+/// index.
+pub fn hex_prefix_bounds(prefix: &str) -> (String, String) {
+    // 'g' sorts after every hex digit
+    (prefix.to_string(), format!("{}g", prefix))
+}
+
 macro_rules! impl_byte_array_from_column {
     ($thing:ident) => {
         impl rusqlite::types::FromSql for $thing {
@@ -449,6 +461,174 @@ pub fn tx_busy_handler(run_count: i32) -> bool {
     true
 }
 
+/// Settings of `PRAGMA synchronous`
+const SQLITE_SYNCHRONOUS_MODES: &[&str] = &["OFF", "NORMAL", "FULL", "EXTRA"];
+
+/// How the databases opened with sqlite_open() are tuned.  The defaults leave sqlite's own page,
+/// cache and mmap sizes alone, and set `synchronous = NORMAL`.  Each database the node writes is
+/// always in WAL mode, since it's also read through read-only connections, which mustn't block
+/// its writer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlitePragmas {
+    /// Bytes per page of databases created from now on.  A database in WAL mode keeps the page
+    /// size it was created with.
+    pub page_size: Option<u32>,
+    /// Pages of cache each connection keeps, or, if negative, KiB of cache
+    pub cache_size: Option<i64>,
+    /// Bytes of each database to memory-map; 0 turns memory-mapping off
+    pub mmap_size: Option<u64>,
+    /// `PRAGMA synchronous` of writable connections
+    pub synchronous: String,
+}
+
+impl Default for SqlitePragmas {
+    fn default() -> SqlitePragmas {
+        SqlitePragmas {
+            page_size: None,
+            cache_size: None,
+            mmap_size: None,
+            synchronous: "NORMAL".to_string(),
+        }
+    }
+}
+
+impl SqlitePragmas {
+    /// Check the settings, and put the synchronous mode in upper case
+    pub fn validate(mut self) -> Result<SqlitePragmas, String> {
+        if let Some(page_size) = self.page_size {
+            if page_size < 512 || page_size > 65536 || !page_size.is_power_of_two() {
+                return Err(format!("page size must be a power of two from 512 to 65536, not {}", page_size));
+            }
+        }
+        self.synchronous = self.synchronous.to_uppercase();
+        if !SQLITE_SYNCHRONOUS_MODES.contains(&self.synchronous.as_str()) {
+            return Err(format!("synchronous must be one of {}, not \"{}\"", SQLITE_SYNCHRONOUS_MODES.join(", "), &self.synchronous));
+        }
+        Ok(self)
+    }
+}
+
+/// Open a sqlite database, handling busy errors with exponential backoff.  With `pragmas`, the
+/// connection's cache and mmap sizes are tuned, and a read-write connection also sets up the
+/// database itself:  its page size (if it's still empty), its `synchronous` setting, and
+/// write-ahead logging (which persists in the file), so that readers on other connections and
+/// the database's writer don't block one another.  A database that can't be put in WAL mode
+/// isn't opened.  Read-only connections leave the database alone.
+pub fn sqlite_open(path: &str, open_flags: OpenFlags, pragmas: Option<&SqlitePragmas>) -> Result<Connection, sqlite_error> {
+    let conn = Connection::open_with_flags(path, open_flags)?;
+    conn.busy_handler(Some(tx_busy_handler))?;
+    let pragmas = match pragmas {
+        Some(pragmas) => pragmas,
+        None => return Ok(conn)
+    };
+    if let Some(cache_size) = pragmas.cache_size {
+        conn.execute_batch(&format!("PRAGMA cache_size = {};", cache_size))?;
+    }
+    if let Some(mmap_size) = pragmas.mmap_size {
+        conn.execute_batch(&format!("PRAGMA mmap_size = {};", mmap_size))?;
+    }
+    if open_flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE) {
+        // only takes effect if the database is still empty
+        if let Some(page_size) = pragmas.page_size {
+            conn.execute_batch(&format!("PRAGMA page_size = {};", page_size))?;
+        }
+        // sqlite answers with the mode the database ended up in.  An in-memory database has no
+        // other connections, and stays in MEMORY.
+        let mode : String = conn.query_row("PRAGMA journal_mode = WAL", NO_PARAMS, |row| row.get(0))?;
+        if mode.to_lowercase() != "wal" && !(path == ":memory:" && mode.to_lowercase() == "memory") {
+            return Err(sqlite_error::SqliteFailure(ffi::Error::new(ffi::SQLITE_CANTOPEN),
+                                                   Some(format!("{} is in journal mode {}, and can't be put in WAL mode", path, mode))));
+        }
+        conn.execute_batch(&format!("PRAGMA synchronous = {};", &pragmas.synchronous))?;
+    }
+    Ok(conn)
+}
+
+/// Copy a live sqlite database to `dest_path` with sqlite's online backup API.  The pages are
+/// copied in one step, under one read transaction, so the copy is of the database as of a single
+/// point in time even while the node writes to it.
+pub fn sqlite_backup(src_path: &str, dest_path: &str) -> Result<(), sqlite_error> {
+    let src = sqlite_open(src_path, OpenFlags::SQLITE_OPEN_READ_ONLY, None)?;
+    let mut dest = Connection::open(dest_path)?;
+    let backup = Backup::new(&src, &mut dest)?;
+    loop {
+        match backup.step(-1)? {
+            StepResult::Done => return Ok(()),
+            StepResult::More => {},
+            StepResult::Busy | StepResult::Locked => sleep_ms(100),
+        }
+    }
+}
+
+/// A small pool of read-only connections to one sqlite database.  Reads made through the pool
+/// don't have to take turns on the database's (single) writer connection, and with the database
+/// in WAL mode they don't wait on its writes either.  The pool can be shared across threads.
+pub struct DBReadPool {
+    path: String,
+    pragmas: SqlitePragmas,
+    max_idle: usize,
+    idle: Mutex<Vec<DBConn>>,
+}
+
+/// A connection borrowed from a `DBReadPool`.  It goes back to the pool when dropped.
+pub struct PooledDBConn<'a> {
+    pool: &'a DBReadPool,
+    conn: Option<DBConn>,
+}
+
+impl DBReadPool {
+    /// Make a pool for the database at `path`.  Connections are opened as needed, tuned with
+    /// `pragmas`; at most `max_idle` of them are kept open between uses.
+    pub fn new(path: &str, max_idle: usize, pragmas: &SqlitePragmas) -> DBReadPool {
+        DBReadPool {
+            path: path.to_string(),
+            pragmas: pragmas.clone(),
+            max_idle: max_idle,
+            idle: Mutex::new(vec![]),
+        }
+    }
+
+    /// Borrow a read-only connection, opening a new one if none are idle.
+    pub fn get(&self) -> Result<PooledDBConn, Error> {
+        let idle_conn = match self.idle.lock() {
+            Ok(mut idle) => idle.pop(),
+            Err(_) => None
+        };
+        let conn = match idle_conn {
+            Some(conn) => conn,
+            None => sqlite_open(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY, Some(&self.pragmas)).map_err(Error::SqliteError)?
+        };
+        Ok(PooledDBConn {
+            pool: self,
+            conn: Some(conn),
+        })
+    }
+
+    /// Number of connections waiting in the pool
+    pub fn num_idle(&self) -> usize {
+        self.idle.lock().map(|idle| idle.len()).unwrap_or(0)
+    }
+}
+
+impl<'a> Deref for PooledDBConn<'a> {
+    type Target = DBConn;
+    fn deref(&self) -> &DBConn {
+        self.conn.as_ref().expect("BUG: pooled connection already returned")
+    }
+}
+
+impl<'a> Drop for PooledDBConn<'a> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if let Ok(mut idle) = self.pool.idle.lock() {
+                if idle.len() < self.pool.max_idle {
+                    idle.push(conn);
+                }
+            }
+        }
+    }
+}
+
 /// Begin an immediate-mode transaction, and handle busy errors with exponential backoff.
 /// Handling busy errors when the tx begins is preferable to doing it when the tx commits, since
 /// then we don't have to worry about any extra rollback logic.
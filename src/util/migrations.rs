@@ -0,0 +1,302 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt;
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use rusqlite::types::ToSql;
+
+use util::db::Error;
+use util::db::tx_begin_immediate;
+use util::get_epoch_time_secs;
+
+/// Keeps which migrations were applied to a database, and when
+const SCHEMA_MIGRATIONS_SQL: &'static str = r#"
+    CREATE TABLE IF NOT EXISTS schema_migrations(
+        version INTEGER PRIMARY KEY NOT NULL,
+        description TEXT NOT NULL,
+        applied_at INTEGER NOT NULL
+    );
+"#;
+
+/// A change to a database's schema, and how to undo it.  A database's migrations are numbered
+/// from 1, one after another.  The tables a database is created with are version 0, and a new
+/// database is migrated from there like any other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaMigration {
+    /// The schema version the database is at once this is applied
+    pub version: u32,
+    pub description: &'static str,
+    /// Statements that make the change
+    pub up: &'static [&'static str],
+    /// Statements that undo it, leaving the tables as they were before.  None if it can't be
+    /// undone.
+    pub down: Option<&'static [&'static str]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationDirection {
+    Apply,
+    Rollback,
+}
+
+/// A migration applied or rolled back, or that would be, in a dry run
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStep {
+    pub version: u32,
+    pub description: String,
+    pub direction: MigrationDirection,
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.direction {
+            MigrationDirection::Apply => write!(f, "apply {}: {}", self.version, &self.description),
+            MigrationDirection::Rollback => write!(f, "roll back {}: {}", self.version, &self.description),
+        }
+    }
+}
+
+/// The newest schema version these migrations bring a database to
+pub fn latest_schema_version(migrations: &[SchemaMigration]) -> u32 {
+    migrations.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+fn check_migrations(migrations: &[SchemaMigration]) -> Result<(), Error> {
+    for (i, migration) in migrations.iter().enumerate() {
+        if migration.version != (i as u32) + 1 {
+            return Err(Error::Other(format!("Schema migration {} is out of order: expected version {}", migration.version, i + 1)));
+        }
+    }
+    Ok(())
+}
+
+pub fn instantiate_schema_migrations(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch(SCHEMA_MIGRATIONS_SQL).map_err(Error::SqliteError)
+}
+
+/// The version a database's schema is at:  the newest migration applied to it.  Databases from
+/// before migrations were kept track of are at version 0.  Doesn't write to the database.
+pub fn get_schema_version(conn: &Connection) -> Result<u32, Error> {
+    let num_tables : i64 = conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'", NO_PARAMS, |row| row.get(0))
+        .map_err(Error::SqliteError)?;
+    if num_tables == 0 {
+        return Ok(0);
+    }
+    let version : Option<i64> = conn.query_row("SELECT MAX(version) FROM schema_migrations", NO_PARAMS, |row| row.get(0))
+        .map_err(Error::SqliteError)?;
+    Ok(version.unwrap_or(0) as u32)
+}
+
+fn record_migration(conn: &Connection, migration: &SchemaMigration) -> Result<(), Error> {
+    let description = migration.description.to_string();
+    let applied_at = get_epoch_time_secs() as i64;
+    let args : &[&dyn ToSql] = &[&migration.version, &description, &applied_at];
+    conn.execute("INSERT INTO schema_migrations (version, description, applied_at) VALUES (?1, ?2, ?3)", args)
+        .map_err(Error::SqliteError)?;
+    Ok(())
+}
+
+/// Plan the migrations that bring a database at schema version `current` to version `target`:
+/// the ones after `current` up to `target`, in order, or the ones after `target` up to
+/// `current`, rolled back newest first.
+pub fn plan_migrations(migrations: &[SchemaMigration], current: u32, target: u32) -> Result<Vec<MigrationStep>, Error> {
+    check_migrations(migrations)?;
+    let latest = latest_schema_version(migrations);
+    if current > latest {
+        return Err(Error::Other(format!("The database's schema is at version {}, newer than the newest this node knows of ({}); run a newer release, or roll it back with the release that migrated it", current, latest)));
+    }
+    if target > latest {
+        return Err(Error::Other(format!("There is no schema version {}; the newest is {}", target, latest)));
+    }
+
+    let mut steps = vec![];
+    if target >= current {
+        for migration in migrations[current as usize..target as usize].iter() {
+            steps.push(MigrationStep { version: migration.version, description: migration.description.to_string(), direction: MigrationDirection::Apply });
+        }
+    }
+    else {
+        for migration in migrations[target as usize..current as usize].iter().rev() {
+            if migration.down.is_none() {
+                return Err(Error::Other(format!("Schema migration {} ({}) can't be rolled back", migration.version, migration.description)));
+            }
+            steps.push(MigrationStep { version: migration.version, description: migration.description.to_string(), direction: MigrationDirection::Rollback });
+        }
+    }
+    Ok(steps)
+}
+
+/// Check, without writing to it, that a database's schema isn't newer than these migrations
+/// know of
+pub fn check_schema_version(conn: &Connection, migrations: &[SchemaMigration]) -> Result<(), Error> {
+    let current = get_schema_version(conn)?;
+    plan_migrations(migrations, current, current).map(|_| ())
+}
+
+/// Bring a database's schema to version `target`, or the latest if None, by applying or rolling
+/// back migrations.  They all happen in one transaction, so a migration that fails leaves the
+/// database as it was.  With `dry_run`, the migrations are run and then the transaction is
+/// rolled back, so that one that would fail is found without changing the database.  Returns
+/// the migrations that were (or would have been) applied or rolled back, in order.
+pub fn migrate_schema(conn: &mut Connection, migrations: &[SchemaMigration], target: Option<u32>, dry_run: bool) -> Result<Vec<MigrationStep>, Error> {
+    let target = target.unwrap_or(latest_schema_version(migrations));
+
+    // most opens have nothing to do, so check with a read before taking the write lock
+    if plan_migrations(migrations, get_schema_version(conn)?, target)?.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let tx = tx_begin_immediate(conn)?;
+    instantiate_schema_migrations(&tx)?;
+    let current = get_schema_version(&tx)?;
+    let steps = plan_migrations(migrations, current, target)?;
+
+    for step in steps.iter() {
+        let migration = &migrations[step.version as usize - 1];
+        match step.direction {
+            MigrationDirection::Apply => {
+                for cmd in migration.up.iter() {
+                    tx.execute_batch(cmd).map_err(Error::SqliteError)?;
+                }
+                record_migration(&tx, migration)?;
+            },
+            MigrationDirection::Rollback => {
+                for cmd in migration.down.unwrap_or(&[]).iter() {
+                    tx.execute_batch(cmd).map_err(Error::SqliteError)?;
+                }
+                tx.execute("DELETE FROM schema_migrations WHERE version = ?1", &[&migration.version as &dyn ToSql])
+                    .map_err(Error::SqliteError)?;
+            }
+        }
+    }
+
+    if dry_run || steps.is_empty() {
+        tx.rollback().map_err(Error::SqliteError)?;
+    }
+    else {
+        tx.commit().map_err(Error::SqliteError)?;
+        for step in steps.iter() {
+            info!("Schema migration: {}", step);
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_MIGRATIONS : &'static [SchemaMigration] = &[
+        SchemaMigration {
+            version: 1,
+            description: "add t.y",
+            up: &["ALTER TABLE t ADD COLUMN y INTEGER NOT NULL DEFAULT 0;"],
+            down: Some(&["CREATE TABLE t_old AS SELECT x FROM t; DROP TABLE t; ALTER TABLE t_old RENAME TO t;"]),
+        },
+        SchemaMigration {
+            version: 2,
+            description: "index t.y",
+            up: &["CREATE INDEX t_y ON t(y);"],
+            down: Some(&["DROP INDEX t_y;"]),
+        },
+    ];
+
+    fn has_column(conn: &Connection, column: &str) -> bool {
+        conn.prepare(&format!("SELECT {} FROM t", column)).is_ok()
+    }
+
+    #[test]
+    fn test_migrate_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t(x INTEGER NOT NULL); INSERT INTO t VALUES (1);").unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+
+        // a dry run changes nothing
+        let steps = migrate_schema(&mut conn, TEST_MIGRATIONS, None, true).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0], MigrationStep { version: 1, description: "add t.y".to_string(), direction: MigrationDirection::Apply });
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        assert!(!has_column(&conn, "y"));
+
+        let steps = migrate_schema(&mut conn, TEST_MIGRATIONS, None, false).unwrap();
+        assert_eq!(steps.iter().map(|step| step.version).collect::<Vec<u32>>(), vec![1, 2]);
+        assert_eq!(get_schema_version(&conn).unwrap(), 2);
+        assert!(has_column(&conn, "y"));
+        assert!(migrate_schema(&mut conn, TEST_MIGRATIONS, None, false).unwrap().is_empty());
+
+        // roll back to before the column was added; the rows stay
+        let steps = migrate_schema(&mut conn, TEST_MIGRATIONS, Some(0), false).unwrap();
+        assert_eq!(steps.iter().map(|step| (step.version, step.direction)).collect::<Vec<_>>(),
+                   vec![(2, MigrationDirection::Rollback), (1, MigrationDirection::Rollback)]);
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        assert!(!has_column(&conn, "y"));
+        let x : i64 = conn.query_row("SELECT x FROM t", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(x, 1);
+
+        // a failing migration leaves the database as it was
+        conn.execute_batch("CREATE INDEX t_y ON t(x);").unwrap();
+        assert!(migrate_schema(&mut conn, TEST_MIGRATIONS, None, false).is_err());
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        assert!(!has_column(&conn, "y"));
+    }
+
+    #[test]
+    fn test_migrate_schema_up_to_date() {
+        let path = std::env::temp_dir().join("test_migrate_schema_up_to_date.db");
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE t(x INTEGER NOT NULL);").unwrap();
+        migrate_schema(&mut conn, TEST_MIGRATIONS, None, false).unwrap();
+
+        // a database that's up to date is opened without waiting on whoever is writing to it
+        let mut writer = Connection::open(&path).unwrap();
+        let tx = tx_begin_immediate(&mut writer).unwrap();
+        tx.execute("INSERT INTO t VALUES (1, 0)", NO_PARAMS).unwrap();
+
+        let mut conn = Connection::open(&path).unwrap();
+        conn.busy_timeout(std::time::Duration::from_millis(0)).unwrap();
+        assert!(migrate_schema(&mut conn, TEST_MIGRATIONS, None, false).unwrap().is_empty());
+        assert_eq!(get_schema_version(&conn).unwrap(), 2);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_plan_migrations() {
+        assert_eq!(plan_migrations(TEST_MIGRATIONS, 0, 1).unwrap().len(), 1);
+        assert!(plan_migrations(TEST_MIGRATIONS, 2, 2).unwrap().is_empty());
+
+        // newer than this node knows of
+        assert!(plan_migrations(TEST_MIGRATIONS, 3, 2).is_err());
+        assert!(plan_migrations(TEST_MIGRATIONS, 0, 3).is_err());
+
+        let irreversible : &[SchemaMigration] = &[
+            SchemaMigration { version: 1, description: "backfill", up: &["UPDATE t SET x = 0;"], down: None },
+        ];
+        assert!(plan_migrations(irreversible, 1, 0).is_err());
+
+        let out_of_order : &[SchemaMigration] = &[
+            SchemaMigration { version: 2, description: "skips 1", up: &[], down: None },
+        ];
+        assert!(plan_migrations(out_of_order, 0, 2).is_err());
+    }
+}
@@ -28,6 +28,7 @@ pub mod secp256k1;
 pub mod uint;
 pub mod strings;
 pub mod vrf;
+pub mod migrations;
 
 use std::time;
 use std::thread;
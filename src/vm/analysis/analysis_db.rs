@@ -63,6 +63,18 @@ impl <'a> AnalysisDatabase <'a> {
         "analysis"
     }
 
+    fn trait_implementation_key(trait_identifier: &TraitIdentifier) -> String {
+        format!("implements::{}", trait_identifier)
+    }
+
+    fn contract_call_key(callee: &QualifiedContractIdentifier) -> String {
+        format!("calls::{}", callee)
+    }
+
+    fn dependencies_key() -> &'static str {
+        "dependencies"
+    }
+
     // used by tests to ensure that
     //   the contract -> contract hash key exists in the marf
     //    even if the contract isn't published.
@@ -91,9 +103,51 @@ impl <'a> AnalysisDatabase <'a> {
         }
 
         self.store.insert_metadata(contract_identifier, key, &contract.serialize());
+
+        // index the contract by the traits it implements, so it can be found from the trait
+        for trait_identifier in contract.implemented_traits.iter() {
+            self.store.insert_metadata(contract_identifier, &AnalysisDatabase::trait_implementation_key(trait_identifier), &trait_identifier.to_string());
+        }
+
+        // and by the contracts it calls, so its callers can be found from each of them
+        let called_contracts = contract.get_called_contracts();
+        for callee in called_contracts.iter() {
+            self.store.insert_metadata(contract_identifier, &AnalysisDatabase::contract_call_key(callee), &callee.to_string());
+        }
+        let dependencies: Vec<String> = called_contracts.iter().map(|callee| callee.to_string()).collect();
+        self.store.insert_metadata(contract_identifier, AnalysisDatabase::dependencies_key(),
+                                   &serde_json::to_string(&dependencies).expect("Failed to serialize contract dependencies"));
         Ok(())
     }
 
+    /// All contracts (in this fork) that declared `impl-trait` for the given trait.
+    pub fn get_trait_implementors(&mut self, trait_identifier: &TraitIdentifier) -> Vec<QualifiedContractIdentifier> {
+        let key = AnalysisDatabase::trait_implementation_key(trait_identifier);
+        let mut contracts = self.store.get_contracts_with_metadata_entry(&key);
+        contracts.sort_by_key(|contract| contract.to_string());
+        contracts
+    }
+
+    /// The contracts (in this fork) that the given contract names in a `contract-call?`, or None
+    /// if it was analyzed before its calls were indexed.
+    pub fn get_contract_dependencies(&mut self, contract_identifier: &QualifiedContractIdentifier) -> Option<Vec<QualifiedContractIdentifier>> {
+        let dependencies = self.store.get_metadata(contract_identifier, AnalysisDatabase::dependencies_key())
+            .ok()??;
+        let dependencies: Vec<String> = serde_json::from_str(&dependencies)
+            .expect("Failed to deserialize contract dependencies");
+        Some(dependencies.iter()
+             .filter_map(|callee| QualifiedContractIdentifier::parse(callee).ok())
+             .collect())
+    }
+
+    /// All contracts (in this fork) that name the given contract in a `contract-call?`.
+    pub fn get_contract_callers(&mut self, contract_identifier: &QualifiedContractIdentifier) -> Vec<QualifiedContractIdentifier> {
+        let key = AnalysisDatabase::contract_call_key(contract_identifier);
+        let mut contracts = self.store.get_contracts_with_metadata_entry(&key);
+        contracts.sort_by_key(|contract| contract.to_string());
+        contracts
+    }
+
     pub fn get_public_function_type(&mut self, contract_identifier: &QualifiedContractIdentifier, function_name: &str) -> CheckResult<Option<FunctionType>> {
         // TODO: this function loads the whole contract to obtain the function type.
         //         but it doesn't need to -- rather this information can just be 
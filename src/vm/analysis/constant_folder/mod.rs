@@ -0,0 +1,310 @@
+use std::mem;
+
+use vm::representations::{SymbolicExpression, SymbolicExpressionType, FoldedExpression, FoldedStep};
+use vm::representations::SymbolicExpressionType::{Atom, AtomValue, LiteralValue, List};
+use vm::callables::CallableType;
+use vm::costs::{cost_functions, CostTracker, LimitedCostTracker, MemoryConsumer};
+use vm::functions::{NativeFunctions, lookup_reserved_functions};
+use vm::functions::define::DefineFunctions;
+use vm::types::Value;
+use vm::analysis::errors::CheckResult;
+
+#[cfg(test)]
+mod tests;
+
+/*
+
+Contracts are interpreted from the expressions they were deployed with, so an expression
+whose value can't change, like `(* u10 (pow u10 u6))`, is worked out again every time it's
+evaluated, and an `if` whose condition can't change checks it every time. The constant folder
+works these out once, when a contract is analyzed, and stores the contract with what they
+came to, so that nodes following the chain don't have to.
+
+Folding can't change what a contract does, or what it costs to run it. So a folded expression
+keeps the steps that evaluating the original took (charging its costs, holding on to memory,
+and calling built-in functions), and takes them again each time it's evaluated, in the same
+order. Only calls to built-in functions that don't read or write anything, and `if`, are
+folded, and only when they don't fail: an expression like `(/ 1 0)` is left to fail when
+it's evaluated.
+
+Working out a call to a built-in function at analysis time is charged to the analysis, as
+evaluating it would be, on top of what evaluating the folded expression charges later.
+
+A folded expression keeps the id and span of the original, and the original itself.  When the
+debugger, an evaluation hook or a coverage collector is attached, the VM evaluates the original
+instead, so they see every expression the contract was written with.
+
+Contracts stored with folded expressions can't be read by binaries from before folding, so
+contracts are only folded when the caller asks for it (see
+`vm::analysis::run_analysis_with_constant_folding`).
+
+*/
+
+pub struct ConstantFolder<'a> {
+    cost_track: &'a mut LimitedCostTracker,
+}
+
+// what evaluating a constant expression does, and what it evaluates to
+struct Constant {
+    steps: Vec<FoldedStep>,
+    value: Value,
+}
+
+impl <'a> ConstantFolder<'a> {
+    /// Fold the constant expressions of a contract that passed analysis, charging the built-in
+    /// functions it evaluates to the analysis' cost tracker
+    pub fn run(expressions: &mut [SymbolicExpression], cost_track: &'a mut LimitedCostTracker) -> CheckResult<()> {
+        let mut folder = ConstantFolder { cost_track };
+        for expr in expressions.iter_mut() {
+            folder.fold_top_level(expr)?;
+        }
+        Ok(())
+    }
+
+    fn fold_top_level(&mut self, expr: &mut SymbolicExpression) -> CheckResult<()> {
+        let define_type = DefineFunctions::try_parse(expr).map(|(define_type, args)| (define_type, args.len()));
+        // the position of the expression a definition evaluates, if any
+        let evaluated = match define_type {
+            Some((DefineFunctions::Constant, _)) => Some(2),
+            Some((DefineFunctions::PersistedVariable, _)) => Some(3),
+            Some((DefineFunctions::FungibleToken, 2)) => Some(2),
+            Some((DefineFunctions::PrivateFunction, _)) | Some((DefineFunctions::PublicFunction, _)) |
+            Some((DefineFunctions::ReadOnlyFunction, _)) => Some(2),
+            Some(_) => None,
+            None => return self.fold_expression(expr)
+        };
+        if let (Some(position), &mut List(ref mut list)) = (evaluated, &mut expr.expr) {
+            if let Some(evaluated) = list.get_mut(position) {
+                self.fold_expression(evaluated)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fold an expression, or the constant expressions within it
+    fn fold_expression(&mut self, expr: &mut SymbolicExpression) -> CheckResult<()> {
+        let original = expr.clone();
+        if let Some(constant) = self.fold(expr)? {
+            ConstantFolder::replace_with_constant(expr, original, constant);
+        }
+        Ok(())
+    }
+
+    // lists are the only expressions that are any cheaper to evaluate folded
+    fn replace_with_constant(expr: &mut SymbolicExpression, original: SymbolicExpression, constant: Constant) {
+        if let List(_) = expr.expr {
+            expr.expr = AtomValue(constant.value);
+            ConstantFolder::replace_with_folded(expr, original, constant.steps, None);
+        }
+    }
+
+    // replace an expression with what it folds to: the given expression, or the expression
+    //  itself, keeping its id and span, and the original
+    fn replace_with_folded(expr: &mut SymbolicExpression, original: SymbolicExpression, steps: Vec<FoldedStep>, folded_to: Option<SymbolicExpression>) {
+        let folded_to = folded_to.unwrap_or_else(|| expr.clone());
+        expr.expr = SymbolicExpressionType::Folded(Box::new(FoldedExpression { steps, expr: folded_to, original }));
+    }
+
+    /// Work out what an expression evaluates to, if it's constant.  If it isn't, fold the
+    /// constant expressions within it instead.
+    fn fold(&mut self, expr: &mut SymbolicExpression) -> CheckResult<Option<Constant>> {
+        if ConstantFolder::is_if(expr) {
+            return self.fold_if(expr)
+        }
+        let list = match expr.expr {
+            AtomValue(ref value) | LiteralValue(ref value) => return Ok(Some(Constant { steps: vec![], value: value.clone() })),
+            // these can't be rebound, and looking them up is free
+            Atom(ref name) => return Ok(match name.as_str() {
+                "true" => Some(Constant { steps: vec![], value: Value::Bool(true) }),
+                "false" => Some(Constant { steps: vec![], value: Value::Bool(false) }),
+                "none" => Some(Constant { steps: vec![], value: Value::none() }),
+                _ => None
+            }),
+            List(ref mut list) => list,
+            _ => return Ok(None)
+        };
+
+        let native_function = list.first()
+            .and_then(|function| function.match_atom())
+            .and_then(|name| NativeFunctions::lookup_by_name(name));
+        let args = &mut list[1..];
+
+        use vm::functions::NativeFunctions::*;
+        match native_function {
+            Some(Let) if !args.is_empty() => {
+                // only the values of the bindings, and the body, are evaluated
+                if let List(ref mut bindings) = args[0].expr {
+                    for binding in bindings.iter_mut() {
+                        if let List(ref mut pair) = binding.expr {
+                            if pair.len() == 2 {
+                                self.fold_expression(&mut pair[1])?;
+                            }
+                        }
+                    }
+                }
+                self.fold_all(&mut args[1..])?;
+                Ok(None)
+            },
+            Some(Match) if args.len() == 4 || args.len() == 5 => {
+                // the input, and the branches; the last argument is always a branch
+                let last = args.len() - 1;
+                self.fold_expression(&mut args[0])?;
+                self.fold_expression(&mut args[2])?;
+                self.fold_expression(&mut args[last])?;
+                Ok(None)
+            },
+            Some(TupleCons) => {
+                // only the values of a tuple are evaluated
+                for pair in args.iter_mut() {
+                    if let List(ref mut pair) = pair.expr {
+                        if let Some(value) = pair.get_mut(1) {
+                            self.fold_expression(value)?;
+                        }
+                    }
+                }
+                Ok(None)
+            },
+            Some(TupleGet) if args.len() == 2 => {
+                self.fold_expression(&mut args[1])?;
+                Ok(None)
+            },
+            Some(native_function) => self.fold_application(native_function, args),
+            None => {
+                self.fold_all(args)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_if(expr: &SymbolicExpression) -> bool {
+        match expr.match_list() {
+            Some(list) => list.len() == 4 && list[0].match_atom().map(|name| name.as_str()) == Some("if"),
+            None => false
+        }
+    }
+
+    fn fold_all(&mut self, exprs: &mut [SymbolicExpression]) -> CheckResult<()> {
+        for expr in exprs.iter_mut() {
+            self.fold_expression(expr)?;
+        }
+        Ok(())
+    }
+
+    // work out what each of a list of expressions evaluates to, keeping their originals
+    fn fold_each(&mut self, exprs: &mut [SymbolicExpression]) -> CheckResult<Vec<(SymbolicExpression, Option<Constant>)>> {
+        let mut constants = Vec::with_capacity(exprs.len());
+        for expr in exprs.iter_mut() {
+            let original = expr.clone();
+            constants.push((original, self.fold(expr)?));
+        }
+        Ok(constants)
+    }
+
+    /// Evaluate a call to a built-in function with constant arguments, taking the same
+    /// steps as `vm::apply`, and charging the analysis for the call.  Only calls to functions
+    /// that work on their arguments alone are folded, and only if they succeed.
+    fn fold_application(&mut self, native_function: NativeFunctions, args: &mut [SymbolicExpression]) -> CheckResult<Option<Constant>> {
+        let constants = self.fold_each(args)?;
+
+        let callable = lookup_reserved_functions(&native_function.get_name())
+            .expect("built-in function should be reserved");
+        let folded = match callable {
+            CallableType::NativeFunction(_, ref function, ref cost_function) if constants.iter().all(|(_, c)| c.is_some()) => {
+                let mut steps = vec![];
+                let mut values = vec![];
+                let mut used_memory = 0;
+                let lookup_cost = cost_functions::LOOKUP_FUNCTION.compute_cost(0).ok();
+                let call_cost = cost_function.compute_cost(constants.len() as u64).ok();
+                match (lookup_cost, call_cost) {
+                    (Some(lookup_cost), Some(call_cost)) => {
+                        steps.push(FoldedStep::Cost(lookup_cost));
+                        steps.push(FoldedStep::Call(callable.get_identifier()));
+                        for (_, constant) in constants.iter() {
+                            let constant = constant.as_ref().expect("argument should be constant");
+                            steps.extend(constant.steps.iter().cloned());
+                            let memory = constant.value.get_memory_use();
+                            steps.push(FoldedStep::AddMemory(memory));
+                            used_memory += memory;
+                            values.push(constant.value.clone());
+                        }
+                        steps.push(FoldedStep::Cost(call_cost.clone()));
+                        // working it out now costs what evaluating it would
+                        self.cost_track.add_cost(call_cost)?;
+                        function.apply(&mut values).ok()
+                            .map(|value| {
+                                steps.push(FoldedStep::DropMemory(used_memory));
+                                steps.push(FoldedStep::Return);
+                                Constant { steps, value }
+                            })
+                    },
+                    _ => None
+                }
+            },
+            _ => None
+        };
+
+        if folded.is_none() {
+            for (arg, (original, constant)) in args.iter_mut().zip(constants) {
+                if let Some(constant) = constant {
+                    ConstantFolder::replace_with_constant(arg, original, constant);
+                }
+            }
+        }
+        Ok(folded)
+    }
+
+    /// Fold an `if`, if its condition is constant: to what its branch evaluates to if that's
+    /// constant too, or else to the branch
+    fn fold_if(&mut self, expr: &mut SymbolicExpression) -> CheckResult<Option<Constant>> {
+        let original = expr.clone();
+        let args = match expr.expr {
+            List(ref mut list) => &mut list[1..],
+            _ => return Ok(None)
+        };
+        let mut folded = self.fold_each(args)?;
+        let mut branches = folded.split_off(1);
+        let (condition_original, condition) = folded.pop().expect("if should have a condition");
+
+        let (condition, taken) = match condition {
+            Some(Constant { steps, value: Value::Bool(condition) }) => (steps, if condition { 0 } else { 1 }),
+            condition => {
+                if let Some(condition) = condition {
+                    ConstantFolder::replace_with_constant(&mut args[0], condition_original, condition);
+                }
+                for (branch, (branch_original, constant)) in args[1..].iter_mut().zip(branches) {
+                    if let Some(constant) = constant {
+                        ConstantFolder::replace_with_constant(branch, branch_original, constant);
+                    }
+                }
+                return Ok(None)
+            }
+        };
+
+        let callable = lookup_reserved_functions(&NativeFunctions::If.get_name())
+            .expect("built-in function should be reserved");
+        let (lookup_cost, if_cost) = match (cost_functions::LOOKUP_FUNCTION.compute_cost(0), cost_functions::IF.compute_cost(0)) {
+            (Ok(lookup_cost), Ok(if_cost)) => (lookup_cost, if_cost),
+            _ => return Ok(None)
+        };
+        let mut steps = vec![
+            FoldedStep::Cost(lookup_cost),
+            FoldedStep::Call(callable.get_identifier()),
+            FoldedStep::Cost(if_cost),
+        ];
+        steps.extend(condition);
+
+        match branches.swap_remove(taken) {
+            (_, Some(branch)) => {
+                steps.extend(branch.steps);
+                steps.push(FoldedStep::Return);
+                Ok(Some(Constant { steps, value: branch.value }))
+            },
+            (_, None) => {
+                // the branch is evaluated within the call to `if`, which returns once it's done
+                let branch = mem::replace(&mut args[1 + taken], SymbolicExpression::atom_value(Value::Bool(false)));
+                ConstantFolder::replace_with_folded(expr, original, steps, Some(branch));
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use vm::analysis::{type_check, run_analysis, run_analysis_with_constant_folding};
+use vm::ast::build_ast;
+use vm::ast::types::ContractAST;
+use vm::contexts::{Environment, LocalContext, OwnedEnvironment};
+use vm::contracts::Contract;
+use vm::costs::{ExecutionCost, LimitedCostTracker};
+use vm::database::{ClarityDeserializable, ClaritySerializable, MemoryBackingStore};
+use vm::hooks::{self, EvalHook};
+use vm::representations::{depth_traverse, SymbolicExpression, SymbolicExpressionType};
+use vm::tests::symbols_from_values;
+use vm::types::{QualifiedContractIdentifier, Value};
+
+const CONTRACT: &str = "(define-constant scale (* u10 (pow u10 u6)))
+     (define-data-var total uint (+ u1 u2))
+     (define-read-only (scaled (x uint)) (* x (+ scale (- u4 u3))))
+     (define-read-only (checked (x uint)) (if (> u2 u1) (+ x (* u2 u3)) (/ x u0)))
+     (define-read-only (constant) (if (is-eq u1 u2) u0 (+ u1 u1)))
+     (define-read-only (bound (x uint)) (let ((a (+ u1 u2))) (+ a x (var-get total))))
+     (define-read-only (failing) (/ u1 u0))";
+
+fn contract_identifier() -> QualifiedContractIdentifier {
+    QualifiedContractIdentifier::local("folded").unwrap()
+}
+
+fn parse() -> ContractAST {
+    build_ast(&contract_identifier(), CONTRACT, &mut ()).unwrap()
+}
+
+fn fold() -> ContractAST {
+    let mut contract_ast = parse();
+    let mut marf = MemoryBackingStore::new();
+    run_analysis_with_constant_folding(&contract_identifier(), &mut contract_ast.expressions, &mut marf.as_analysis_db(),
+                                       false, LimitedCostTracker::new_max_limit()).unwrap();
+    contract_ast
+}
+
+// the expression a top-level definition evaluates
+fn defined<'a>(contract_ast: &'a ContractAST, index: usize) -> &'a SymbolicExpression {
+    &contract_ast.expressions[index].match_list().unwrap()[2]
+}
+
+fn folded_to(expr: &SymbolicExpression) -> &SymbolicExpression {
+    match expr.expr {
+        SymbolicExpressionType::Folded(ref folded) => &folded.expr,
+        _ => panic!("Expected {} to be folded", expr)
+    }
+}
+
+// deploy the contract and call one of its functions, with the given cost limit, returning
+//  what it evaluated to and what the whole thing cost
+fn run(contract_ast: &ContractAST, function: &str, args: Vec<Value>, limit: ExecutionCost) -> (String, ExecutionCost) {
+    let mut marf = MemoryBackingStore::new();
+    let mut owned_env = OwnedEnvironment::new_cost_limited(marf.as_clarity_db(), LimitedCostTracker::new(limit));
+    let contract_identifier = contract_identifier();
+    let sender = Value::from(contract_identifier.issuer.clone());
+    let result = owned_env.initialize_contract_from_ast(contract_identifier.clone(), contract_ast, CONTRACT)
+        .and_then(|_| owned_env.execute_in_env(sender, |env| {
+            env.execute_contract(&contract_identifier, function, &symbols_from_values(args), true)
+        }));
+    let cost = owned_env.get_exec_environment(None).global_context.cost_track.get_total();
+    (format!("{:?}", result.map(|(value, _, _)| value)), cost)
+}
+
+#[test]
+fn test_constant_folding() {
+    let contract_ast = fold();
+
+    assert_eq!(folded_to(defined(&contract_ast, 0)).match_atom_value(), Some(&Value::UInt(10_000_000)));
+    let initial = &contract_ast.expressions[1].match_list().unwrap()[3];
+    assert_eq!(folded_to(initial).match_atom_value(), Some(&Value::UInt(3)));
+
+    // only the constant parts of an expression are folded
+    let scaled = defined(&contract_ast, 2).match_list().unwrap();
+    let sum = scaled[2].match_list().unwrap();
+    assert_eq!(sum[1].match_atom().unwrap().as_str(), "scale");
+    assert_eq!(folded_to(&sum[2]).match_atom_value(), Some(&Value::UInt(1)));
+
+    // an `if` with a constant condition is folded to the branch it takes
+    let checked = folded_to(defined(&contract_ast, 3)).match_list().unwrap();
+    assert_eq!(checked[0].match_atom().unwrap().as_str(), "+");
+    assert_eq!(folded_to(&checked[2]).match_atom_value(), Some(&Value::UInt(6)));
+    assert_eq!(folded_to(defined(&contract_ast, 4)).match_atom_value(), Some(&Value::UInt(2)));
+
+    let bound = defined(&contract_ast, 5).match_list().unwrap();
+    let binding = bound[1].match_list().unwrap()[0].match_list().unwrap();
+    assert_eq!(folded_to(&binding[1]).match_atom_value(), Some(&Value::UInt(3)));
+
+    // expressions that fail are left to fail when they're evaluated
+    assert!(defined(&contract_ast, 6).match_list().is_some());
+}
+
+#[test]
+fn test_folding_keeps_costs() {
+    let folded = fold();
+    let unfolded = parse();
+
+    let calls = [("scaled", vec![Value::UInt(2)]), ("checked", vec![Value::UInt(1)]), ("constant", vec![]),
+                 ("bound", vec![Value::UInt(1)]), ("failing", vec![])];
+    for (function, args) in calls.iter() {
+        let expected = run(&unfolded, function, args.clone(), ExecutionCost::max_value());
+        assert_eq!(run(&folded, function, args.clone(), ExecutionCost::max_value()), expected);
+
+        // running out partway through fails the same way, at the same point
+        for runtime in 0..(expected.1.runtime + 1) {
+            let mut limit = ExecutionCost::max_value();
+            limit.runtime = runtime;
+            assert_eq!(run(&folded, function, args.clone(), limit.clone()),
+                       run(&unfolded, function, args.clone(), limit));
+        }
+    }
+}
+
+#[test]
+fn test_folding_is_opt_in() {
+    let mut contract_ast = parse();
+    let mut marf = MemoryBackingStore::new();
+    type_check(&contract_identifier(), &mut contract_ast.expressions, &mut marf.as_analysis_db(), false).unwrap();
+    assert_eq!(contract_ast.expressions, parse().expressions);
+    for expr in contract_ast.expressions.iter() {
+        depth_traverse(expr, |expr| match expr.expr {
+            SymbolicExpressionType::Folded(_) => Err(format!("{} was folded", expr)),
+            _ => Ok(())
+        }).unwrap();
+    }
+}
+
+#[test]
+fn test_folding_charges_analysis() {
+    let analysis_cost = |fold_constants: bool| {
+        let mut contract_ast = parse();
+        let mut marf = MemoryBackingStore::new();
+        let mut analysis_db = marf.as_analysis_db();
+        let analysis = if fold_constants {
+            run_analysis_with_constant_folding(&contract_identifier(), &mut contract_ast.expressions, &mut analysis_db,
+                                               false, LimitedCostTracker::new_max_limit())
+        } else {
+            run_analysis(&contract_identifier(), &mut contract_ast.expressions, &mut analysis_db,
+                         false, LimitedCostTracker::new_max_limit())
+        };
+        analysis.unwrap().take_contract_cost_tracker().get_total()
+    };
+    assert!(analysis_cost(true).runtime > analysis_cost(false).runtime);
+
+    // running out of budget while folding fails the analysis
+    let mut contract_ast = parse();
+    let mut marf = MemoryBackingStore::new();
+    let mut limit = ExecutionCost::max_value();
+    limit.runtime = analysis_cost(false).runtime;
+    assert!(run_analysis_with_constant_folding(&contract_identifier(), &mut contract_ast.expressions, &mut marf.as_analysis_db(),
+                                               false, LimitedCostTracker::new(limit)).is_err());
+}
+
+// records the expressions the VM evaluates
+struct Recorder(Rc<RefCell<Vec<u64>>>);
+
+impl EvalHook for Recorder {
+    fn will_eval(&mut self, _env: &mut Environment, _context: &LocalContext, expr: &SymbolicExpression) {
+        self.0.borrow_mut().push(expr.id);
+    }
+}
+
+#[test]
+fn test_hooks_see_folded_expressions() {
+    let folded = fold();
+    let unfolded = parse();
+
+    let record = |contract_ast: &ContractAST| {
+        let evaluated = Rc::new(RefCell::new(vec![]));
+        hooks::attach(Box::new(Recorder(evaluated.clone())));
+        let outcome = run(contract_ast, "checked", vec![Value::UInt(1)], ExecutionCost::max_value());
+        hooks::detach();
+        let evaluated = evaluated.borrow().clone();
+        (outcome, evaluated)
+    };
+    assert_eq!(record(&folded), record(&unfolded));
+}
+
+// `(define-read-only (flip) (not false))`, as stored by binaries from before folding
+const STORED_CONTRACT: &str = concat!(
+    r#"{"contract_context":{"contract_identifier":{"issuer":[26,[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]],"name":"stored"},"#,
+    r#""variables":{},"functions":{"flip":{"identifier":{"identifier":"ST000000000000000000002AMW42H.stored:flip"},"#,
+    r#""name":"flip","arg_types":[],"define_type":"ReadOnly","arguments":[],"#,
+    r#""body":{"expr":{"List":["#,
+    r#"{"expr":{"Atom":"not"},"id":6,"span":{"start_line":1,"start_column":27,"end_line":1,"end_column":29}},"#,
+    r#"{"expr":{"Atom":"false"},"id":7,"span":{"start_line":1,"start_column":31,"end_line":1,"end_column":35}}"#,
+    r#"]},"id":5,"span":{"start_line":1,"start_column":26,"end_line":1,"end_column":36}}}},"#,
+    r#""defined_traits":{},"implemented_traits":[],"persisted_names":[],"data_size":0}}"#);
+
+#[test]
+#[cfg(feature = "developer-mode")]
+fn test_reads_contracts_stored_before_folding() {
+    let contract = Contract::deserialize(STORED_CONTRACT);
+    // and writes them the same way
+    assert_eq!(contract.serialize(), STORED_CONTRACT);
+
+    let flip = contract.contract_context.lookup_function("flip").unwrap();
+    assert!(flip.is_read_only());
+    let mut marf = MemoryBackingStore::new();
+    let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+    let sender = Value::from(contract.contract_context.contract_identifier.issuer.clone());
+    let (result, _, _) = owned_env.execute_in_env(sender, |env| flip.apply(&[], env)).unwrap();
+    assert_eq!(result, Value::Bool(true));
+}
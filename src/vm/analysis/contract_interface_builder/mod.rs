@@ -3,6 +3,8 @@ use std::collections::{BTreeMap, BTreeSet};
 use vm::{ClarityName};
 use vm::types::{TypeSignature, FunctionArg, TupleTypeSignature, FunctionType, FixedFunction};
 
+pub mod typescript;
+
 pub fn build_contract_interface(contract_analysis: &ContractAnalysis) -> ContractInterface {
     let mut contract_interface = ContractInterface::new();
 
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use vm::analysis::contract_interface_builder::{ContractInterface, ContractInterfaceAtomType, ContractInterfaceFunctionAccess,
+                                               ContractInterfaceFunction, ContractInterfaceTupleEntryType};
+use vm::types::QualifiedContractIdentifier;
+
+// Types for the JSON encoding of Clarity values, which every binding is written in terms of
+const PRELUDE: &str = r#"export type ClarityInt = { type: "int"; value: string };
+export type ClarityUInt = { type: "uint"; value: string };
+export type ClarityBool = { type: "bool"; value: boolean };
+export type ClarityBuffer = { type: "buffer"; value: string };
+export type ClarityPrincipal = { type: "principal"; value: string };
+export type ClarityList<T> = { type: "list"; value: T[] };
+export type ClarityTuple<T> = { type: "tuple"; value: T };
+export type ClarityOptional<T> = { type: "none" } | { type: "some"; value: T };
+export type ClarityResponse<T, E> = { type: "ok"; value: T } | { type: "err"; value: E };
+export type ClarityValue =
+  | ClarityInt | ClarityUInt | ClarityBool | ClarityBuffer | ClarityPrincipal
+  | ClarityList<ClarityValue> | ClarityTuple<{ [name: string]: ClarityValue }>
+  | ClarityOptional<ClarityValue> | ClarityResponse<ClarityValue, ClarityValue>;
+
+/** A call of one of the contract's functions, which returns a `R` */
+export interface ContractCall<R> {
+  contractAddress: string;
+  contractName: string;
+  functionName: string;
+  functionArgs: ClarityValue[];
+  /** never set: only here to carry the result type */
+  result?: R;
+}
+"#;
+
+// words TypeScript doesn't allow as parameter names, and the names the bindings use themselves
+const RESERVED_WORDS: &[&str] = &[
+    "contractAddress", "contractName",
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do",
+    "else", "enum", "export", "extends", "false", "finally", "for", "function", "if", "import",
+    "in", "instanceof", "new", "null", "return", "super", "switch", "this", "throw", "true", "try",
+    "typeof", "var", "void", "while", "with", "implements", "interface", "let", "package",
+    "private", "protected", "public", "static", "yield", "arguments", "eval",
+];
+
+/// The TypeScript type of a Clarity type's values, in the JSON encoding of Clarity values
+pub fn typescript_type(atom_type: &ContractInterfaceAtomType) -> String {
+    match atom_type {
+        // only the unused side of a response or optional has no type
+        ContractInterfaceAtomType::none => "never".to_string(),
+        ContractInterfaceAtomType::int128 => "ClarityInt".to_string(),
+        ContractInterfaceAtomType::uint128 => "ClarityUInt".to_string(),
+        ContractInterfaceAtomType::bool => "ClarityBool".to_string(),
+        ContractInterfaceAtomType::principal | ContractInterfaceAtomType::trait_reference => "ClarityPrincipal".to_string(),
+        ContractInterfaceAtomType::buffer { .. } => "ClarityBuffer".to_string(),
+        ContractInterfaceAtomType::tuple(entries) => format!("ClarityTuple<{}>", typescript_fields(entries)),
+        ContractInterfaceAtomType::optional(some_type) => format!("ClarityOptional<{}>", typescript_type(some_type)),
+        ContractInterfaceAtomType::response { ok, error } => format!("ClarityResponse<{}, {}>", typescript_type(ok), typescript_type(error)),
+        ContractInterfaceAtomType::list { type_f, .. } => format!("ClarityList<{}>", typescript_type(type_f)),
+    }
+}
+
+fn typescript_fields(entries: &[ContractInterfaceTupleEntryType]) -> String {
+    let fields: Vec<String> = entries.iter()
+        .map(|entry| format!("{}: {}", quote(&entry.name), typescript_type(&entry.type_f)))
+        .collect();
+    format!("{{ {} }}", fields.join("; "))
+}
+
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).expect("Failed to serialize string")
+}
+
+/// Turn each of a function's argument names into a distinct TypeScript identifier
+fn parameter_names(function: &ContractInterfaceFunction) -> Vec<String> {
+    let mut used = HashSet::new();
+    function.args.iter().map(|arg| {
+        let mut name: String = arg.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if RESERVED_WORDS.contains(&name.as_str()) {
+            name.push('_');
+        }
+        while !used.insert(name.clone()) {
+            name.push('_');
+        }
+        name
+    }).collect()
+}
+
+fn write_functions(out: &mut String, interface: &ContractInterface, access: ContractInterfaceFunctionAccess, object_name: &str) {
+    writeln!(out, "export const {} = {{", object_name).unwrap();
+    for function in interface.functions.iter().filter(|f| f.access == access) {
+        let names = parameter_names(function);
+        let params: Vec<String> = names.iter().zip(function.args.iter())
+            .map(|(name, arg)| format!("{}: {}", name, typescript_type(&arg.type_f)))
+            .collect();
+        writeln!(out, "  {}: ({}): ContractCall<{}> => ({{", quote(&function.name), params.join(", "),
+                 typescript_type(&function.outputs.type_f)).unwrap();
+        writeln!(out, "    contractAddress, contractName, functionName: {}, functionArgs: [{}],", quote(&function.name), names.join(", ")).unwrap();
+        writeln!(out, "  }}),").unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+}
+
+/// Generate TypeScript bindings for a contract: a function building each public and read-only
+/// function call, with its arguments and result typed, and the types of its maps and variables.
+/// Values are in the JSON encoding of Clarity values the RPC interface takes and returns.
+pub fn build_typescript_bindings(contract_identifier: &QualifiedContractIdentifier, interface: &ContractInterface) -> String {
+    let mut out = String::new();
+    writeln!(out, "// TypeScript bindings for {}, generated by `clarity-cli bindings`.", contract_identifier).unwrap();
+    writeln!(out, "// Values are in the JSON encoding of Clarity values that the node's RPC interface uses.").unwrap();
+    writeln!(out).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+
+    writeln!(out, "export const contractAddress = {};", quote(&contract_identifier.issuer.to_address())).unwrap();
+    writeln!(out, "export const contractName = {};", quote(contract_identifier.name.as_str())).unwrap();
+    writeln!(out).unwrap();
+
+    write_functions(&mut out, interface, ContractInterfaceFunctionAccess::public, "publicFunctions");
+    writeln!(out).unwrap();
+    write_functions(&mut out, interface, ContractInterfaceFunctionAccess::read_only, "readOnlyFunctions");
+    writeln!(out).unwrap();
+
+    writeln!(out, "export interface Maps {{").unwrap();
+    for map in interface.maps.iter() {
+        writeln!(out, "  {}: {{ key: ClarityTuple<{}>; value: ClarityTuple<{}> }};",
+                 quote(&map.name), typescript_fields(&map.key), typescript_fields(&map.value)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "export interface Variables {{").unwrap();
+    for variable in interface.variables.iter() {
+        writeln!(out, "  {}: {};", quote(&variable.name), typescript_type(&variable.type_f)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vm::analysis::mem_type_check;
+    use vm::analysis::contract_interface_builder::build_contract_interface;
+
+    #[test]
+    fn test_typescript_bindings() {
+        let contract = "(define-map balances ((owner principal)) ((amount uint)))
+             (define-data-var last-sender (optional principal) none)
+             (define-constant max-supply u100)
+             (define-private (helper) 1)
+             (define-read-only (get-balance (owner principal))
+               (default-to u0 (get amount (map-get? balances { owner: owner }))))
+             (define-public (transfer! (amount uint) (to principal) (default (buff 2)) (default_ (list 2 int)))
+               (begin (var-set last-sender (some tx-sender)) (ok amount)))";
+        let (_, contract_analysis) = mem_type_check(contract).unwrap();
+        let interface = build_contract_interface(&contract_analysis);
+        let contract_id = QualifiedContractIdentifier::parse("SP2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKNRV9EJ7.tokens").unwrap();
+        let bindings = build_typescript_bindings(&contract_id, &interface);
+
+        assert!(bindings.contains("export const contractAddress = \"SP2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKNRV9EJ7\";"));
+        assert!(bindings.contains("export const contractName = \"tokens\";"));
+
+        // reserved and clashing argument names are renamed
+        assert!(bindings.contains("  \"transfer!\": (amount: ClarityUInt, to: ClarityPrincipal, default_: ClarityBuffer, default__: ClarityList<ClarityInt>): ContractCall<ClarityResponse<ClarityUInt, never>> => ({\n    contractAddress, contractName, functionName: \"transfer!\", functionArgs: [amount, to, default_, default__],\n"));
+        assert!(bindings.contains("export const readOnlyFunctions = {\n  \"get-balance\": (owner: ClarityPrincipal): ContractCall<ClarityUInt> => ({"));
+        // private functions can't be called
+        assert!(!bindings.contains("helper"));
+
+        assert!(bindings.contains("  \"balances\": { key: ClarityTuple<{ \"owner\": ClarityPrincipal }>; value: ClarityTuple<{ \"amount\": ClarityUInt }> };"));
+        assert!(bindings.contains("  \"last-sender\": ClarityOptional<ClarityPrincipal>;"));
+        assert!(bindings.contains("  \"max-supply\": ClarityUInt;"));
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, HashSet};
+
+use vm::representations::{SymbolicExpression, SymbolicExpressionType, ClarityName};
+use vm::representations::SymbolicExpressionType::{Atom, AtomValue, List};
+use vm::functions::NativeFunctions;
+use vm::functions::define::DefineFunctionsParsed;
+use vm::analysis::types::ContractAnalysis;
+use vm::diagnostic::{Diagnostic, DiagnosableError};
+use vm::types::{TypeSignature, Value};
+use vm::is_reserved;
+
+#[cfg(test)]
+mod tests;
+
+/// Things the linter warns about.  None of them stop a contract from being deployed.
+#[derive(Debug, PartialEq)]
+pub enum Lint {
+    UnusedPrivateFunction(String),
+    ShadowedBinding(String),
+    UnreachableBranch,
+    UncheckedResponse,
+}
+
+impl DiagnosableError for Lint {
+    fn message(&self) -> String {
+        match self {
+            Lint::UnusedPrivateFunction(name) => format!("private function '{}' is never called", name),
+            Lint::ShadowedBinding(name) => format!("binding '{}' has the same name as a definition of the contract or a built-in", name),
+            Lint::UnreachableBranch => "this expression is never evaluated".into(),
+            Lint::UncheckedResponse => "the response of this expression is discarded without being checked".into(),
+        }
+    }
+
+    fn suggestion(&self) -> Option<String> {
+        match self {
+            Lint::UnusedPrivateFunction(_) => Some("remove the function, or call it".into()),
+            Lint::ShadowedBinding(_) => Some("rename the binding".into()),
+            Lint::UnreachableBranch => None,
+            Lint::UncheckedResponse => Some("use unwrap!, try! or asserts! to handle an err".into()),
+        }
+    }
+}
+
+/// A non-consensus pass over a contract that has passed analysis, looking for code that is
+/// probably a mistake.  Unlike the analysis passes, it never fails, and it is never run by
+/// deploys: it is only run on request, by the analyze endpoint and the REPL.
+pub struct Linter <'a> {
+    contract_analysis: &'a ContractAnalysis,
+    /// names of the contract's definitions
+    defined_names: HashSet<&'a ClarityName>,
+    /// private functions, with where they are defined
+    private_functions: BTreeMap<&'a ClarityName, &'a SymbolicExpression>,
+    /// every name referred to outside of a binding
+    referenced_names: HashSet<&'a ClarityName>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl <'a> Linter <'a> {
+    /// Lint a contract, returning its warnings in source order
+    pub fn run(contract_analysis: &'a ContractAnalysis) -> Vec<Diagnostic> {
+        let mut linter = Linter::new(contract_analysis);
+        for expr in contract_analysis.expressions.iter() {
+            linter.lint_top_level(expr);
+        }
+
+        let unused: Vec<_> = linter.private_functions.iter()
+            .filter(|(name, _)| !linter.referenced_names.contains(*name))
+            .map(|(name, expr)| (name.to_string(), *expr))
+            .collect();
+        for (name, expr) in unused {
+            linter.warn(Lint::UnusedPrivateFunction(name), expr);
+        }
+
+        let mut diagnostics = linter.diagnostics;
+        diagnostics.sort_by_key(|d| d.spans.first().map(|s| (s.start_line, s.start_column)));
+        diagnostics
+    }
+
+    fn new(contract_analysis: &'a ContractAnalysis) -> Linter<'a> {
+        let defined_names = contract_analysis.private_function_types.keys()
+            .chain(contract_analysis.public_function_types.keys())
+            .chain(contract_analysis.read_only_function_types.keys())
+            .chain(contract_analysis.variable_types.keys())
+            .chain(contract_analysis.persisted_variable_types.keys())
+            .chain(contract_analysis.map_types.keys())
+            .chain(contract_analysis.fungible_tokens.iter())
+            .chain(contract_analysis.non_fungible_tokens.keys())
+            .chain(contract_analysis.defined_traits.keys())
+            .collect();
+        Linter {
+            contract_analysis,
+            defined_names,
+            private_functions: BTreeMap::new(),
+            referenced_names: HashSet::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, expr: &SymbolicExpression) {
+        let mut diagnostic = Diagnostic::warning(&lint);
+        diagnostic.spans = vec![expr.span.clone()];
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn lint_top_level(&mut self, expr: &'a SymbolicExpression) {
+        use vm::functions::define::DefineFunctionsParsed::*;
+        // the contract has passed analysis, so its definitions parse
+        match DefineFunctionsParsed::try_parse(expr) {
+            Ok(Some(define_type)) => match define_type {
+                Constant { value, .. } => self.lint_expr(value, false),
+                PersistedVariable { initial, .. } => self.lint_expr(initial, false),
+                BoundedFungibleToken { max_supply, .. } => self.lint_expr(max_supply, false),
+                PrivateFunction { signature, body } => {
+                    if let Some(name) = signature.first().and_then(|name| name.match_atom()) {
+                        self.private_functions.insert(name, expr);
+                    }
+                    self.lint_function(signature, body);
+                },
+                PublicFunction { signature, body } | ReadOnlyFunction { signature, body } => {
+                    self.lint_function(signature, body);
+                },
+                Map { .. } | NonFungibleToken { .. } | UnboundedFungibleToken { .. } |
+                Trait { .. } | UseTrait { .. } | ImplTrait { .. } => {},
+            },
+            // the result of a top-level expression is thrown away
+            Ok(None) => self.lint_expr(expr, true),
+            Err(_) => {}
+        }
+    }
+
+    fn lint_function(&mut self, signature: &'a [SymbolicExpression], body: &'a SymbolicExpression) {
+        for arg in signature.iter().skip(1) {
+            if let Some(name) = arg.match_list().and_then(|pair| pair.first()) {
+                self.check_binding(name);
+            }
+        }
+        self.lint_expr(body, false);
+    }
+
+    /// Warn if a binding shares its name with a definition of the contract or a built-in.
+    /// Analysis rejects most of these, but not bindings named like read-only functions or
+    /// reserved names, which only fail once they're evaluated.
+    fn check_binding(&mut self, name_expr: &SymbolicExpression) {
+        if let Some(name) = name_expr.match_atom() {
+            if self.defined_names.contains(name) || is_reserved(name) {
+                self.warn(Lint::ShadowedBinding(name.to_string()), name_expr);
+            }
+        }
+    }
+
+    fn is_response(&self, expr: &SymbolicExpression) -> bool {
+        match self.contract_analysis.type_map.as_ref().and_then(|type_map| type_map.get_type(expr)) {
+            Some(TypeSignature::ResponseType(_)) => true,
+            _ => false
+        }
+    }
+
+    /// Lint an expression.  `discarded` is whether its value is thrown away.
+    fn lint_expr(&mut self, expr: &'a SymbolicExpression, discarded: bool) {
+        match expr.expr {
+            Atom(ref name) => {
+                self.referenced_names.insert(name);
+            },
+            List(ref list) => {
+                if discarded && self.is_response(expr) {
+                    self.warn(Lint::UncheckedResponse, expr);
+                }
+                self.lint_application(list);
+            },
+            _ => {}
+        }
+    }
+
+    fn lint_all(&mut self, exprs: &'a [SymbolicExpression]) {
+        for expr in exprs.iter() {
+            self.lint_expr(expr, false);
+        }
+    }
+
+    /// Lint a block of expressions, where the values of all but the last are thrown away
+    fn lint_block(&mut self, exprs: &'a [SymbolicExpression]) {
+        if let Some((last, rest)) = exprs.split_last() {
+            for expr in rest.iter() {
+                self.lint_expr(expr, true);
+            }
+            self.lint_expr(last, false);
+        }
+    }
+
+    fn lint_application(&mut self, list: &'a [SymbolicExpression]) {
+        use vm::functions::NativeFunctions::*;
+        let (function_name, args) = match list.split_first() {
+            Some((function, args)) => match function.match_atom() {
+                Some(name) => (name, args),
+                None => return self.lint_all(list)
+            },
+            None => return
+        };
+
+        match NativeFunctions::lookup_by_name(function_name) {
+            Some(Begin) => self.lint_block(args),
+            Some(Let) if !args.is_empty() => {
+                for binding in args[0].match_list().unwrap_or(&[]).iter() {
+                    if let Some(pair) = binding.match_list() {
+                        if pair.len() == 2 {
+                            self.check_binding(&pair[0]);
+                            self.lint_expr(&pair[1], false);
+                        }
+                    }
+                }
+                self.lint_block(&args[1..]);
+            },
+            Some(Match) if args.len() == 4 || args.len() == 5 => {
+                self.check_binding(&args[1]);
+                if args.len() == 5 {
+                    self.check_binding(&args[3]);
+                }
+                // the input, and the branches; the last argument is always a branch
+                self.lint_expr(&args[0], false);
+                self.lint_expr(&args[2], false);
+                self.lint_expr(&args[args.len() - 1], false);
+            },
+            Some(If) if args.len() == 3 => {
+                self.lint_all(args);
+                match literal_bool(&args[0]) {
+                    Some(true) => self.warn(Lint::UnreachableBranch, &args[2]),
+                    Some(false) => self.warn(Lint::UnreachableBranch, &args[1]),
+                    None => {}
+                }
+            },
+            Some(And) | Some(Or) => {
+                self.lint_all(args);
+                // `and` stops at the first false, `or` at the first true
+                let stops_at = function_name.as_str() == "or";
+                let stop = args.iter().position(|arg| literal_bool(arg) == Some(stops_at));
+                if let Some(stop) = stop {
+                    if let Some(first_skipped) = args.get(stop + 1) {
+                        self.warn(Lint::UnreachableBranch, first_skipped);
+                    }
+                }
+            },
+            Some(Asserts) if args.len() == 2 => {
+                self.lint_all(args);
+                if literal_bool(&args[0]) == Some(true) {
+                    self.warn(Lint::UnreachableBranch, &args[1]);
+                }
+            },
+            Some(TupleCons) => {
+                // only the values of a tuple are evaluated
+                for pair in args.iter() {
+                    if let Some(value) = pair.match_list().and_then(|pair| pair.get(1)) {
+                        self.lint_expr(value, false);
+                    }
+                }
+            },
+            Some(TupleGet) if args.len() == 2 => self.lint_expr(&args[1], false),
+            _ => {
+                self.referenced_names.insert(function_name);
+                self.lint_all(args);
+            }
+        }
+    }
+}
+
+/// The value of `true`, `false`, or a boolean literal, if the expression is one
+fn literal_bool(expr: &SymbolicExpression) -> Option<bool> {
+    match expr.expr {
+        Atom(ref name) => match name.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None
+        },
+        AtomValue(Value::Bool(value)) => Some(value),
+        SymbolicExpressionType::LiteralValue(Value::Bool(value)) => Some(value),
+        _ => None
+    }
+}
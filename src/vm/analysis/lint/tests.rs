@@ -0,0 +1,86 @@
+use vm::analysis::mem_type_check;
+use vm::analysis::lint::{Linter, Lint};
+use vm::diagnostic::{Diagnostic, DiagnosableError, Level};
+
+fn lint(contract: &str) -> Vec<Diagnostic> {
+    let (_, contract_analysis) = mem_type_check(contract).unwrap();
+    Linter::run(&contract_analysis)
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics.iter().map(|d| d.message.clone()).collect()
+}
+
+#[test]
+fn test_clean_contract() {
+    let contract = "(define-data-var counter int 0)
+         (define-private (add (x int)) (+ x (var-get counter)))
+         (define-public (bump (x int))
+           (begin
+             (var-set counter (add x))
+             (ok (var-get counter))))";
+    assert_eq!(lint(contract), vec![]);
+}
+
+#[test]
+fn test_unused_private_function() {
+    let contract = "(define-private (used) 1)
+         (define-private (unused) 2)
+         (define-private (inc (x int)) (+ x 1))
+         (define-read-only (get) (+ (used) (fold + (list 1 2) 0)))
+         (define-read-only (incs) (map inc (list 1 2)))";
+    let diagnostics = lint(contract);
+    assert_eq!(messages(&diagnostics), vec![Lint::UnusedPrivateFunction("unused".into()).message()]);
+    assert_eq!(diagnostics[0].level, Level::Warning);
+    assert_eq!(diagnostics[0].spans[0].start_line, 2);
+}
+
+#[test]
+fn test_shadowed_binding() {
+    let contract = "(define-read-only (supply) 10)
+         (define-read-only (get (supply int))
+           (let ((block-height 1)) supply))
+         (define-read-only (matched (x (optional int)))
+           (match x supply supply 0))";
+    let diagnostics = lint(contract);
+    assert_eq!(messages(&diagnostics), vec![
+        Lint::ShadowedBinding("supply".into()).message(),
+        Lint::ShadowedBinding("block-height".into()).message(),
+        Lint::ShadowedBinding("supply".into()).message()]);
+    let lines: Vec<u32> = diagnostics.iter().map(|d| d.spans[0].start_line).collect();
+    assert_eq!(lines, vec![2, 3, 5]);
+}
+
+#[test]
+fn test_unreachable_branch() {
+    let examples = [
+        "(define-read-only (f) (if true 1 2))",
+        "(define-read-only (f) (if false 1 2))",
+        "(define-read-only (f (x bool)) (and x false x))",
+        "(define-read-only (f (x bool)) (or true x))",
+        "(define-public (f) (begin (asserts! true (err u1)) (ok u1)))",
+    ];
+    for contract in examples.iter() {
+        assert_eq!(messages(&lint(contract)), vec![Lint::UnreachableBranch.message()], "{}", contract);
+    }
+
+    assert_eq!(lint("(define-read-only (f (x bool)) (and x true (or false x)))"), vec![]);
+}
+
+#[test]
+fn test_unchecked_response() {
+    let contract = "(define-public (f)
+           (begin
+             (ok u1)
+             (let ((x 1))
+               (err u2)
+               (ok x))
+             (unwrap-panic (ok u3))
+             (ok u4)))
+         (f)";
+    let diagnostics = lint(contract);
+    assert_eq!(messages(&diagnostics), vec![Lint::UncheckedResponse.message(); 4]);
+    let lines: Vec<u32> = diagnostics.iter().map(|d| d.spans[0].start_line).collect();
+    // the discarded `let` returns a response too
+    assert_eq!(lines, vec![3, 4, 5, 9]);
+}
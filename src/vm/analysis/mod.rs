@@ -5,6 +5,8 @@ pub mod type_checker;
 pub mod read_only_checker;
 pub mod analysis_db;
 pub mod contract_interface_builder;
+pub mod lint;
+pub mod constant_folder;
 
 pub use self::types::{ContractAnalysis, AnalysisPass};
 use vm::representations::{SymbolicExpression};
@@ -19,6 +21,7 @@ use self::read_only_checker::ReadOnlyChecker;
 use self::trait_checker::TraitChecker;
 use self::type_checker::TypeChecker;
 use self::contract_interface_builder::build_contract_interface;
+use self::constant_folder::ConstantFolder;
 
 pub fn mem_type_check(snippet: &str) -> CheckResult<(Option<TypeSignature>, ContractAnalysis)> {
     use vm::database::MemoryBackingStore;
@@ -50,6 +53,26 @@ pub fn run_analysis(contract_identifier: &QualifiedContractIdentifier,
                     analysis_db: &mut AnalysisDatabase, 
                     save_contract: bool,
                     cost_tracker: LimitedCostTracker) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
+    inner_run_analysis(contract_identifier, expressions, analysis_db, save_contract, cost_tracker, false)
+}
+
+/// Analyze a contract, and then fold its constant expressions (see `constant_folder`).
+/// Contracts stored with folded expressions can't be read by binaries from before folding,
+/// so this is only for callers that know every reader of the contract can.
+pub fn run_analysis_with_constant_folding(contract_identifier: &QualifiedContractIdentifier, 
+                                          expressions: &mut [SymbolicExpression],
+                                          analysis_db: &mut AnalysisDatabase, 
+                                          save_contract: bool,
+                                          cost_tracker: LimitedCostTracker) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
+    inner_run_analysis(contract_identifier, expressions, analysis_db, save_contract, cost_tracker, true)
+}
+
+fn inner_run_analysis(contract_identifier: &QualifiedContractIdentifier, 
+                      expressions: &mut [SymbolicExpression],
+                      analysis_db: &mut AnalysisDatabase, 
+                      save_contract: bool,
+                      cost_tracker: LimitedCostTracker,
+                      fold_constants: bool) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
     let mut contract_analysis = ContractAnalysis::new(contract_identifier.clone(), expressions.to_vec(), cost_tracker);
     let result = analysis_db.execute(|db| {
         ReadOnlyChecker::run_pass(&mut contract_analysis, db)?;
@@ -59,6 +82,12 @@ pub fn run_analysis(contract_identifier: &QualifiedContractIdentifier,
             let interface = build_contract_interface(&contract_analysis);
             contract_analysis.contract_interface = Some(interface);
         }
+        if fold_constants {
+            let mut cost_track = contract_analysis.take_contract_cost_tracker();
+            let folded = ConstantFolder::run(expressions, &mut cost_track);
+            contract_analysis.replace_contract_cost_tracker(cost_track);
+            folded?;
+        }
         if save_contract {
             db.insert_contract(&contract_identifier, &contract_analysis)?;
         }
@@ -267,3 +267,45 @@ fn test_write_attempt_in_readonly() {
     let err = mem_type_check(snippet).unwrap_err();
     assert!(format!("{}", err.diagnostic).contains("expecting read-only statements, detected a writing operation"));
 }
+
+#[test]
+fn test_contract_call_index() {
+    use vm::database::MemoryBackingStore;
+    use vm::types::QualifiedContractIdentifier;
+
+    let tokens_contract = "(define-public (transfer (amount uint)) (ok amount))";
+    let names_contract =
+        "(define-public (register (amount uint))
+           (contract-call? .tokens transfer amount))";
+    let market_contract =
+        "(define-trait transferable ((transfer (uint) (response uint uint))))
+         (define-public (buy (amount uint))
+           (begin (try! (contract-call? .tokens transfer amount))
+                  (contract-call? .names register amount)))
+         (define-public (sell (token <transferable>) (amount uint))
+           (contract-call? token transfer amount))";
+
+    let tokens_id = QualifiedContractIdentifier::local("tokens").unwrap();
+    let names_id = QualifiedContractIdentifier::local("names").unwrap();
+    let market_id = QualifiedContractIdentifier::local("market").unwrap();
+    let mut tokens = parse(&tokens_id, tokens_contract).unwrap();
+    let mut names = parse(&names_id, names_contract).unwrap();
+    let mut market = parse(&market_id, market_contract).unwrap();
+
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    db.execute(|db| {
+        type_check(&tokens_id, &mut tokens, db, true)?;
+        type_check(&names_id, &mut names, db, true)?;
+        type_check(&market_id, &mut market, db, true)
+    }).unwrap();
+
+    assert_eq!(db.get_contract_dependencies(&tokens_id), Some(vec![]));
+    assert_eq!(db.get_contract_dependencies(&names_id), Some(vec![tokens_id.clone()]));
+    // calls through trait references aren't known until they're made
+    assert_eq!(db.get_contract_dependencies(&market_id), Some(vec![names_id.clone(), tokens_id.clone()]));
+
+    assert_eq!(db.get_contract_callers(&tokens_id), vec![market_id.clone(), names_id.clone()]);
+    assert_eq!(db.get_contract_callers(&names_id), vec![market_id.clone()]);
+    assert_eq!(db.get_contract_callers(&market_id), vec![]);
+}
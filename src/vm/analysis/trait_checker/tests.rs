@@ -1049,3 +1049,60 @@ fn test_contract_of_wrong_type() {
         }
     }
 }
+
+#[test]
+fn test_trait_implementors_index() {
+    use std::convert::TryFrom;
+    use vm::types::TraitIdentifier;
+    use vm::representations::ClarityName;
+
+    let contract_defining_trait =
+        "(define-trait trait-1 (
+            (get-1 (uint) (response uint uint))))
+        (define-trait trait-2 (
+            (get-2 (uint) (response uint uint))))";
+    let impl_contract =
+        "(impl-trait .defun.trait-1)
+        (define-public (get-1 (x uint)) (ok u1))";
+    let both_contract =
+        "(impl-trait .defun.trait-1)
+        (impl-trait .defun.trait-2)
+        (define-public (get-1 (x uint)) (ok u1))
+        (define-public (get-2 (x uint)) (ok u2))";
+    let no_impl_contract =
+        "(define-public (get-1 (x uint)) (ok u1))";
+
+    let def_contract_id = QualifiedContractIdentifier::local("defun").unwrap();
+    let impl_contract_id = QualifiedContractIdentifier::local("implem").unwrap();
+    let both_contract_id = QualifiedContractIdentifier::local("both").unwrap();
+    let no_impl_contract_id = QualifiedContractIdentifier::local("no-impl").unwrap();
+    let mut c1 = parse(&def_contract_id, contract_defining_trait).unwrap();
+    let mut c2 = parse(&impl_contract_id, impl_contract).unwrap();
+    let mut c3 = parse(&both_contract_id, both_contract).unwrap();
+    let mut c4 = parse(&no_impl_contract_id, no_impl_contract).unwrap();
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    db.execute(|db| {
+        type_check(&def_contract_id, &mut c1, db, true)?;
+        type_check(&impl_contract_id, &mut c2, db, true)?;
+        type_check(&both_contract_id, &mut c3, db, true)?;
+        type_check(&no_impl_contract_id, &mut c4, db, true)
+    }).unwrap();
+
+    let trait_1 = TraitIdentifier {
+        name: ClarityName::try_from("trait-1".to_string()).unwrap(),
+        contract_identifier: def_contract_id.clone()
+    };
+    let trait_2 = TraitIdentifier {
+        name: ClarityName::try_from("trait-2".to_string()).unwrap(),
+        contract_identifier: def_contract_id.clone()
+    };
+    let trait_3 = TraitIdentifier {
+        name: ClarityName::try_from("trait-3".to_string()).unwrap(),
+        contract_identifier: def_contract_id.clone()
+    };
+
+    assert_eq!(db.get_trait_implementors(&trait_1), vec![both_contract_id.clone(), impl_contract_id.clone()]);
+    assert_eq!(db.get_trait_implementors(&trait_2), vec![both_contract_id.clone()]);
+    assert_eq!(db.get_trait_implementors(&trait_3), vec![]);
+}
@@ -5,7 +5,7 @@ pub mod natives;
 use std::convert::TryInto;
 use std::collections::{HashMap, BTreeMap};
 use vm::representations::{SymbolicExpression, ClarityName, depth_traverse};
-use vm::representations::SymbolicExpressionType::{AtomValue, Atom, List, LiteralValue, TraitReference, Field};
+use vm::representations::SymbolicExpressionType::{AtomValue, Atom, List, LiteralValue, TraitReference, Field, Folded};
 use vm::types::{TypeSignature, TupleTypeSignature, FunctionArg,
                 FunctionType, FixedFunction, parse_name_type_pairs, Value, PrincipalData};
 use vm::types::signatures::{FunctionSignature};
@@ -66,6 +66,9 @@ impl CostTracker for TypeChecker<'_, '_> {
     fn reset_memory(&mut self) {
         self.cost_track.reset_memory()
     }
+    fn counts_costs(&self) -> bool {
+        self.cost_track.counts_costs()
+    }
 }
 
 impl AnalysisPass for TypeChecker <'_, '_> {
@@ -481,6 +484,9 @@ impl <'a, 'b> TypeChecker <'a, 'b> {
             List(ref expression) => {
                 self.type_check_function_application(expression, context)?
             },
+            Folded(ref folded) => {
+                self.type_check(&folded.expr, context)?
+            },
             TraitReference(_, _) | Field(_) => {
                 return Err(CheckErrors::UnexpectedTraitOrFieldReference.into());
             }
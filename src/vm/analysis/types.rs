@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
-use vm::{SymbolicExpression, ClarityName};
-use vm::types::{TypeSignature, FunctionType, QualifiedContractIdentifier, TraitIdentifier};
+use vm::{SymbolicExpression, ClarityName, Value};
+use vm::representations::depth_traverse;
+use vm::types::{TypeSignature, FunctionType, PrincipalData, QualifiedContractIdentifier, TraitIdentifier};
 use vm::types::signatures::FunctionSignature;
 use vm::analysis::analysis_db::{AnalysisDatabase};
 use vm::analysis::errors::{CheckResult, CheckErrors};
@@ -136,6 +137,27 @@ impl ContractAnalysis {
         self.defined_traits.get(name)
     }
 
+    /// The contracts this contract's `contract-call?`s name.  Calls made through a trait
+    /// reference go to whichever contract is passed in, so they aren't included.
+    pub fn get_called_contracts(&self) -> BTreeSet<QualifiedContractIdentifier> {
+        let mut called = BTreeSet::new();
+        for expression in self.expressions.iter() {
+            let _ = depth_traverse::<_, _, ()>(expression, |expr| {
+                if let Some(list) = expr.match_list() {
+                    if let (Some(function), Some(callee)) = (list.get(0).and_then(|f| f.match_atom()), list.get(1)) {
+                        if function.as_str() == "contract-call?" {
+                            if let Some(Value::Principal(PrincipalData::Contract(contract_identifier))) = callee.match_literal_value() {
+                                called.insert(contract_identifier.clone());
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            });
+        }
+        called
+    }
+
     pub fn check_trait_compliance(&self, trait_identifier: &TraitIdentifier, trait_definition: &BTreeMap<ClarityName, FunctionSignature>) -> CheckResult<()> {
 
         let trait_name = trait_identifier.name.to_string(); 
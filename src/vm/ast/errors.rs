@@ -1,4 +1,4 @@
-use vm::representations::PreSymbolicExpression;
+use vm::representations::{PreSymbolicExpression, Span};
 use vm::diagnostic::{Diagnostic, DiagnosableError};
 use vm::types::{TypeSignature, TupleTypeSignature};
 use vm::MAX_CALL_STACK_DEPTH;
@@ -72,6 +72,10 @@ impl ParseError {
         self.pre_expressions.replace(vec![expr.clone()]);
     }
 
+    pub fn set_span(&mut self, start_line: u32, start_column: u32, end_line: u32, end_column: u32) {
+        self.diagnostic.spans = vec![Span { start_line, start_column, end_line, end_column }];
+    }
+
     pub fn set_pre_expressions(&mut self, exprs: Vec<PreSymbolicExpression>) {
         self.diagnostic.spans = exprs.iter().map(|e| e.span.clone()).collect();
         self.pre_expressions.replace(exprs.clone().to_vec());
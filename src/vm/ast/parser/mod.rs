@@ -90,7 +90,191 @@ lazy_static! {
     pub static ref CLARITY_NAME_REGEX: String = format!(r#"([[:word:]]|[-!?+<>=/*]){{1,{}}}"#, MAX_STRING_LEN); 
 }
 
+// lex a single token, setting the context the token after it is lexed in
+fn lex_token(handler: &TokenType, current_slice: &str, captures: Captures, context: &mut LexContext) -> ParseResult<LexItem> {
+    match handler {
+        TokenType::LParens => {
+            *context = LexContext::ExpectNothing;
+            Ok(LexItem::LeftParen)
+        },
+        TokenType::RParens => {
+            Ok(LexItem::RightParen)
+        },
+        TokenType::Whitespace => {
+            *context = LexContext::ExpectNothing;
+            Ok(LexItem::Whitespace)
+        },
+        TokenType::Comma => {
+            *context = LexContext::ExpectNothing;
+            Ok(LexItem::CommaSeparator)
+        },
+        TokenType::Colon => {
+            // colon should not be followed directly by an item,
+            //  e.g., {a:b} should not be legal
+            *context = LexContext::ExpectClosingColon;
+            Ok(LexItem::ColonSeparator)
+        },
+        TokenType::LCurly => {
+            *context = LexContext::ExpectNothing;
+            Ok(LexItem::LeftCurly)
+        },
+        TokenType::RCurly => {
+            Ok(LexItem::RightCurly)
+        },
+        TokenType::Variable => {
+            let value = get_value_or_err(current_slice, captures)?;
+            if value.contains("#") {
+                Err(ParseError::new(ParseErrors::IllegalVariableName(value)))
+            } else {
+                Ok(LexItem::Variable(value))
+            }
+        },
+        TokenType::UIntLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match u128::from_str_radix(&str_value, 10) {
+                Ok(parsed) => Ok(Value::UInt(parsed)),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingIntValue(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+        TokenType::IntLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match i128::from_str_radix(&str_value, 10) {
+                Ok(parsed) => Ok(Value::Int(parsed)),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingIntValue(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+        TokenType::FullyQualifiedContractIdentifierLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match PrincipalData::parse_qualified_contract_principal(&str_value) {
+                Ok(parsed) => Ok(Value::Principal(parsed)),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+        TokenType::SugaredContractIdentifierLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match str_value[1..].to_string().try_into() {
+                Ok(parsed) => Ok(parsed),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
+            }?;
+            Ok(LexItem::SugaredContractIdentifier(str_value.len(), value))
+        },
+        TokenType::FullyQualifiedFieldIdentifierLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match TraitIdentifier::parse_fully_qualified(&str_value) {
+                Ok(parsed) => Ok(parsed),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingField(str_value.clone())))
+            }?;
+            Ok(LexItem::FieldIdentifier(str_value.len(), value))
+        },
+        TokenType::SugaredFieldIdentifierLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let (contract_name, field_name) = match TraitIdentifier::parse_sugared_syntax(&str_value) {
+                Ok((contract_name, field_name)) => Ok((contract_name, field_name)),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingField(str_value.clone())))
+            }?;
+            Ok(LexItem::SugaredFieldIdentifier(str_value.len(), contract_name, field_name))
+        },
+        TokenType::PrincipalLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let value = match PrincipalData::parse_standard_principal(&str_value) {
+                Ok(parsed) => Ok(Value::Principal(PrincipalData::Standard(parsed))),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+        TokenType::TraitReferenceLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let data = str_value.clone().try_into()
+                .map_err(|_| { ParseError::new(ParseErrors::IllegalVariableName(str_value.to_string())) })?;
+            Ok(LexItem::TraitReference(str_value.len(), data))
+        },
+        TokenType::HexStringLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let byte_vec = hex_bytes(&str_value)
+                .map_err(|x| { ParseError::new(ParseErrors::FailedParsingHexValue(str_value.clone(), x.to_string())) })?;
+            let value = match Value::buff_from(byte_vec) {
+                Ok(parsed) => Ok(parsed),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingBuffer(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+        TokenType::StringLiteral => {
+            let str_value = get_value_or_err(current_slice, captures)?;
+            let quote_unescaped = str_value.replace("\\\"","\"");
+            let slash_unescaped = quote_unescaped.replace("\\\\","\\");
+            let byte_vec = slash_unescaped.as_bytes().to_vec();
+            let value = match Value::buff_from(byte_vec) {
+                Ok(parsed) => Ok(parsed),
+                Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingBuffer(str_value.clone())))
+            }?;
+            Ok(LexItem::LiteralValue(str_value.len(), value))
+        },
+    }
+}
+
+// check that a token is separated from the one before it, if it needs to be
+fn check_separator(context: &LexContext, handler: &TokenType, token: &str) -> ParseResult<()> {
+    match context {
+        LexContext::ExpectNothing => Ok(()),
+        LexContext::ExpectClosing => {
+            // expect the next lexed item to be something that typically
+            // "closes" an atom -- i.e., whitespace or a right-parens.
+            // this prevents an atom like 1234abc from getting split into "1234" and "abc"
+            match handler {
+                TokenType::RParens => Ok(()),
+                TokenType::RCurly => Ok(()),
+                TokenType::Whitespace => Ok(()),
+                TokenType::Comma => Ok(()),
+                TokenType::Colon => Ok(()),
+                _ => Err(ParseError::new(ParseErrors::SeparatorExpected(token.to_string())))
+            }
+        },
+        LexContext::ExpectClosingColon => {
+            // handle the expected whitespace after a `:`
+            match handler {
+                TokenType::RParens => Ok(()),
+                TokenType::RCurly => Ok(()),
+                TokenType::Whitespace => Ok(()),
+                TokenType::Comma => Ok(()),
+                TokenType::Colon => Ok(()),
+                _ => Err(ParseError::new(ParseErrors::SeparatorExpectedAfterColon(token.to_string())))
+            }
+        }
+    }
+}
+
+// Where the errors found while lexing and parsing go. Normally, the first one fails the parse.
+//  When they're being collected instead, the parse skips past each one and carries on, so that
+//  it can report every error in the source at once.
+struct ErrorSink {
+    collect: bool,
+    errors: Vec<ParseError>
+}
+
+impl ErrorSink {
+    fn new(collect: bool) -> ErrorSink {
+        ErrorSink { collect, errors: vec![] }
+    }
+
+    fn report(&mut self, mut error: ParseError, start_line: u32, start_column: u32, end_line: u32, end_column: u32) -> ParseResult<()> {
+        error.set_span(start_line, start_column, end_line, end_column);
+        if self.collect {
+            self.errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
 pub fn lex(input: &str) -> ParseResult<Vec<(LexItem, u32, u32)>> {
+    lex_reporting(input, &mut ErrorSink::new(false))
+}
+
+fn lex_reporting(input: &str, errors: &mut ErrorSink) -> ParseResult<Vec<(LexItem, u32, u32)>> {
     // Aaron: I'd like these to be static, but that'd require using
     //    lazy_static (or just hand implementing that), and I'm not convinced
     //    it's worth either (1) an extern macro, or (2) the complexity of hand implementing.
@@ -129,8 +313,7 @@ pub fn lex(input: &str) -> ParseResult<Vec<(LexItem, u32, u32)>> {
     let mut result = Vec::new();
     let mut munch_index = 0;
     let mut column_pos: u32 = 1;
-    let mut did_match = true;
-    while did_match && munch_index < input.len() {
+    while munch_index < input.len() {
         if let Some(next_line_ix) = next_line_break {
             if munch_index > next_line_ix {
                 next_line_break = line_indices.pop();
@@ -140,182 +323,49 @@ pub fn lex(input: &str) -> ParseResult<Vec<(LexItem, u32, u32)>> {
             }
         }
 
-        did_match = false;
+        let mut did_match = false;
         let current_slice = &input[munch_index..];
         for matcher in lex_matchers.iter() {
             if let Some(captures) = matcher.matcher.captures(current_slice) {
                 let whole_match = captures.get(0).unwrap();
                 assert_eq!(whole_match.start(), 0);
                 munch_index += whole_match.end();
+                let end_column = column_pos + (whole_match.end() as u32) - 1;
 
-                match context {
-                    LexContext::ExpectNothing => Ok(()),
-                    LexContext::ExpectClosing => {
-                        // expect the next lexed item to be something that typically
-                        // "closes" an atom -- i.e., whitespace or a right-parens.
-                        // this prevents an atom like 1234abc from getting split into "1234" and "abc"
-                        match matcher.handler {
-                            TokenType::RParens => Ok(()),
-                            TokenType::RCurly => Ok(()),
-                            TokenType::Whitespace => Ok(()),
-                            TokenType::Comma => Ok(()),
-                            TokenType::Colon => Ok(()),
-                            _ => Err(ParseError::new(ParseErrors::SeparatorExpected(current_slice[..whole_match.end()].to_string())))
-                        }
-                    },
-                    LexContext::ExpectClosingColon => {
-                        // handle the expected whitespace after a `:`
-                        match matcher.handler {
-                            TokenType::RParens => Ok(()),
-                            TokenType::RCurly => Ok(()),
-                            TokenType::Whitespace => Ok(()),
-                            TokenType::Comma => Ok(()),
-                            TokenType::Colon => Ok(()),
-                            _ => Err(ParseError::new(ParseErrors::SeparatorExpectedAfterColon(
-                                current_slice[..whole_match.end()].to_string())))
-                        }
-                    }
-
-                }?;
+                let separated = check_separator(&context, &matcher.handler, &current_slice[..whole_match.end()]);
 
                 // default to expect a closing
                 context = LexContext::ExpectClosing;
+                let token = lex_token(&matcher.handler, current_slice, captures, &mut context);
 
-                let token = match matcher.handler {
-                    TokenType::LParens => {
-                        context = LexContext::ExpectNothing;
-                        Ok(LexItem::LeftParen)
-                    },
-                    TokenType::RParens => {
-                        Ok(LexItem::RightParen)
-                    },
-                    TokenType::Whitespace => {
-                        context = LexContext::ExpectNothing;
-                        Ok(LexItem::Whitespace)
-                    },
-                    TokenType::Comma => {
-                        context = LexContext::ExpectNothing;
-                        Ok(LexItem::CommaSeparator)
-                    },
-                    TokenType::Colon => {
-                        // colon should not be followed directly by an item,
-                        //  e.g., {a:b} should not be legal
-                        context = LexContext::ExpectClosingColon;
-                        Ok(LexItem::ColonSeparator)
-                    },
-                    TokenType::LCurly => {
-                        context = LexContext::ExpectNothing;
-                        Ok(LexItem::LeftCurly)
-                    },
-                    TokenType::RCurly => {
-                        Ok(LexItem::RightCurly)
-                    },
-                    TokenType::Variable => {
-                        let value = get_value_or_err(current_slice, captures)?;
-                        if value.contains("#") {
-                            Err(ParseError::new(ParseErrors::IllegalVariableName(value)))
-                        } else {
-                            Ok(LexItem::Variable(value))
-                        }
-                    },
-                    TokenType::UIntLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match u128::from_str_radix(&str_value, 10) {
-                            Ok(parsed) => Ok(Value::UInt(parsed)),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingIntValue(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                    TokenType::IntLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match i128::from_str_radix(&str_value, 10) {
-                            Ok(parsed) => Ok(Value::Int(parsed)),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingIntValue(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                    TokenType::FullyQualifiedContractIdentifierLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match PrincipalData::parse_qualified_contract_principal(&str_value) {
-                            Ok(parsed) => Ok(Value::Principal(parsed)),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                    TokenType::SugaredContractIdentifierLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match str_value[1..].to_string().try_into() {
-                            Ok(parsed) => Ok(parsed),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
-                        }?;
-                        Ok(LexItem::SugaredContractIdentifier(str_value.len(), value))
-                    },
-                    TokenType::FullyQualifiedFieldIdentifierLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match TraitIdentifier::parse_fully_qualified(&str_value) {
-                            Ok(parsed) => Ok(parsed),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingField(str_value.clone())))
-                        }?;
-                        Ok(LexItem::FieldIdentifier(str_value.len(), value))
-                    },
-                    TokenType::SugaredFieldIdentifierLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let (contract_name, field_name) = match TraitIdentifier::parse_sugared_syntax(&str_value) {
-                            Ok((contract_name, field_name)) => Ok((contract_name, field_name)),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingField(str_value.clone())))
-                        }?;
-                        Ok(LexItem::SugaredFieldIdentifier(str_value.len(), contract_name, field_name))
-                    },
-                    TokenType::PrincipalLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let value = match PrincipalData::parse_standard_principal(&str_value) {
-                            Ok(parsed) => Ok(Value::Principal(PrincipalData::Standard(parsed))),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingPrincipal(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                    TokenType::TraitReferenceLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let data = str_value.clone().try_into()
-                            .map_err(|_| { ParseError::new(ParseErrors::IllegalVariableName(str_value.to_string())) })?;
-                        Ok(LexItem::TraitReference(str_value.len(), data))
-                    },
-                    TokenType::HexStringLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let byte_vec = hex_bytes(&str_value)
-                            .map_err(|x| { ParseError::new(ParseErrors::FailedParsingHexValue(str_value.clone(), x.to_string())) })?;
-                        let value = match Value::buff_from(byte_vec) {
-                            Ok(parsed) => Ok(parsed),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingBuffer(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                    TokenType::StringLiteral => {
-                        let str_value = get_value_or_err(current_slice, captures)?;
-                        let quote_unescaped = str_value.replace("\\\"","\"");
-                        let slash_unescaped = quote_unescaped.replace("\\\\","\\");
-                        let byte_vec = slash_unescaped.as_bytes().to_vec();
-                        let value = match Value::buff_from(byte_vec) {
-                            Ok(parsed) => Ok(parsed),
-                            Err(_e) => Err(ParseError::new(ParseErrors::FailedParsingBuffer(str_value.clone())))
-                        }?;
-                        Ok(LexItem::LiteralValue(str_value.len(), value))
-                    },
-                }?;
+                // tokens that fail to lex are left out
+                match separated.and(token) {
+                    Ok(token) => result.push((token, current_line, column_pos)),
+                    Err(e) => errors.report(e, current_line, column_pos, current_line, end_column)?
+                }
 
-                result.push((token, current_line, column_pos));
                 column_pos += whole_match.end() as u32;
                 did_match = true;
                 break;
             }
         }
-    }
 
-    if munch_index == input.len() {
-        Ok(result)
-    } else {
-        Err(ParseError::new(ParseErrors::FailedParsingRemainder(input[munch_index..].to_string())))
+        if !did_match {
+            // skip to the next whitespace or bracket
+            let remainder = &input[munch_index..];
+            let skipped = remainder.char_indices().skip(1)
+                .find(|(_, c)| c.is_whitespace() || "(){}".contains(*c))
+                .map(|(ix, _)| ix)
+                .unwrap_or(remainder.len());
+            let unlexed = if errors.collect { &remainder[..skipped] } else { remainder };
+            errors.report(ParseError::new(ParseErrors::FailedParsingRemainder(unlexed.to_string())),
+                          current_line, column_pos, current_line, column_pos + (skipped as u32) - 1)?;
+            munch_index += skipped;
+            column_pos += skipped as u32;
+        }
     }
+
+    Ok(result)
 }
 
 enum ParseStackItem {
@@ -335,7 +385,80 @@ fn handle_expression(parse_stack: &mut Vec<(Vec<ParseStackItem>, u32, u32, Parse
     }
 }
 
-pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSymbolicExpression>> {
+// build a list from the items collected between its parentheses, which mustn't be separators.
+//  separators are left out of the list if errors are being collected.
+fn close_list(list: Vec<ParseStackItem>, errors: &mut ErrorSink,
+              start_line: u32, start_column: u32, end_line: u32, end_column: u32) -> ParseResult<PreSymbolicExpression> {
+    let mut checked_list = Vec::new();
+    let mut reported = false;
+    for item in list.into_iter() {
+        let error = match item {
+            ParseStackItem::Expression(e) => {
+                checked_list.push(e);
+                continue
+            },
+            ParseStackItem::Colon => ParseErrors::ColonSeparatorUnexpected,
+            ParseStackItem::Comma => ParseErrors::CommaSeparatorUnexpected,
+        };
+        if !reported {
+            errors.report(ParseError::new(error), start_line, start_column, end_line, end_column)?;
+            reported = true;
+        }
+    }
+    let mut pre_expr = PreSymbolicExpression::list(checked_list.into_boxed_slice());
+    pre_expr.set_span(start_line, start_column, end_line, end_column);
+    Ok(pre_expr)
+}
+
+// build a tuple from the items collected between its braces, which must be (expr, colon, expr, comma)
+fn close_tuple(tuple_list: Vec<ParseStackItem>, errors: &mut ErrorSink,
+               start_line: u32, start_column: u32, end_line: u32, end_column: u32) -> ParseResult<PreSymbolicExpression> {
+    let mut checked_list = Vec::new();
+    let mut reported = false;
+    for (index, item) in tuple_list.into_iter().enumerate() {
+        // check that tuple items are (expr, colon, expr, comma)
+        let checked = match index % 4 {
+            0 | 2 => {
+                if let ParseStackItem::Expression(e) = item {
+                    checked_list.push(e);
+                    Ok(())
+                } else {
+                    Err(ParseErrors::TupleItemExpected(index))
+                }
+            },
+            1 => {
+                if let ParseStackItem::Colon = item {
+                    Ok(())
+                } else {
+                    Err(ParseErrors::TupleColonExpected(index))
+                }
+            },
+            3 => {
+                if let ParseStackItem::Comma = item {
+                    Ok(())
+                } else {
+                    Err(ParseErrors::TupleCommaExpected(index))
+                }
+            },
+            _ => unreachable!("More than four modulos of four."),
+        };
+        if let Err(error) = checked {
+            if !reported {
+                errors.report(ParseError::new(error), start_line, start_column, end_line, end_column)?;
+                reported = true;
+            }
+        }
+    }
+    let mut pre_expr = PreSymbolicExpression::tuple(checked_list.into_boxed_slice());
+    pre_expr.set_span(start_line, start_column, end_line, end_column);
+    Ok(pre_expr)
+}
+
+pub fn parse_lexed(input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSymbolicExpression>> {
+    parse_lexed_reporting(input, &mut ErrorSink::new(false))
+}
+
+fn parse_lexed_reporting(mut input: Vec<(LexItem, u32, u32)>, errors: &mut ErrorSink) -> ParseResult<Vec<PreSymbolicExpression>> {
     let mut parse_stack = Vec::new();
 
     let mut output_list = Vec::new();
@@ -350,28 +473,19 @@ pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSy
             LexItem::RightParen => {
                 // end current list.
                 if let Some((list, start_line, start_column, parse_context)) = parse_stack.pop() {
-                    match parse_context {
-                        ParseContext::CollectList => {
-                            let checked_list: ParseResult<Box<[PreSymbolicExpression]>> = list.into_iter()
-                                .map(|i| {
-                                    match i {
-                                        ParseStackItem::Expression(e) => Ok(e),
-                                        ParseStackItem::Colon => Err(ParseError::new(ParseErrors::ColonSeparatorUnexpected)),
-                                        ParseStackItem::Comma => Err(ParseError::new(ParseErrors::CommaSeparatorUnexpected)),
-                                    } 
-                                })
-                                .collect();
-                            let checked_list = checked_list?;
-                            let mut pre_expr = PreSymbolicExpression::list(checked_list);
-                            pre_expr.set_span(start_line, start_column, line_pos, column_pos);
-                            handle_expression(&mut parse_stack, &mut output_list, pre_expr);
-                        },
-                        ParseContext::CollectTuple => {
-                            return Err(ParseError::new(ParseErrors::ClosingTupleLiteralExpected))
-                        }
+                    if let ParseContext::CollectTuple = parse_context {
+                        // if errors are being collected, close the tuple anyway
+                        errors.report(ParseError::new(ParseErrors::ClosingTupleLiteralExpected),
+                                      line_pos, column_pos, line_pos, column_pos)?;
                     }
+                    let pre_expr = match parse_context {
+                        ParseContext::CollectList => close_list(list, errors, start_line, start_column, line_pos, column_pos)?,
+                        ParseContext::CollectTuple => close_tuple(list, errors, start_line, start_column, line_pos, column_pos)?,
+                    };
+                    handle_expression(&mut parse_stack, &mut output_list, pre_expr);
                 } else {
-                    return Err(ParseError::new(ParseErrors::ClosingParenthesisUnexpected))
+                    errors.report(ParseError::new(ParseErrors::ClosingParenthesisUnexpected),
+                                  line_pos, column_pos, line_pos, column_pos)?;
                 }
             },
             LexItem::LeftCurly => {
@@ -380,56 +494,32 @@ pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSy
             },
             LexItem::RightCurly => {
                 if let Some((tuple_list, start_line, start_column, parse_context)) = parse_stack.pop() {
-                    match parse_context {
-                        ParseContext::CollectTuple => {
-                            let mut checked_list = Vec::new();
-                            for (index, item) in tuple_list.into_iter().enumerate() {
-                                // check that tuple items are (expr, colon, expr, comma)
-                                match index % 4 {
-                                    0 | 2 => {
-                                        if let ParseStackItem::Expression(e) = item {
-                                            checked_list.push(e);
-                                            Ok(())
-                                        } else {
-                                            Err(ParseErrors::TupleItemExpected(index))
-                                        }
-                                    },
-                                    1 => {
-                                        if let ParseStackItem::Colon = item {
-                                            Ok(())
-                                        } else {
-                                            Err(ParseErrors::TupleColonExpected(index))
-                                        }
-                                    },
-                                    3 => {
-                                        if let ParseStackItem::Comma = item {
-                                            Ok(())
-                                        } else {
-                                            Err(ParseErrors::TupleCommaExpected(index))
-                                        }
-                                    },
-                                    _ => unreachable!("More than four modulos of four."),
-                                }?;
-                            }
-                            let mut pre_expr = PreSymbolicExpression::tuple(checked_list.into_boxed_slice());
-                            pre_expr.set_span(start_line, start_column, line_pos, column_pos);
-                            handle_expression(&mut parse_stack, &mut output_list, pre_expr);
-                        },
-                        ParseContext::CollectList => {
-                            return Err(ParseError::new(ParseErrors::ClosingParenthesisExpected))
-                        }
+                    if let ParseContext::CollectList = parse_context {
+                        // if errors are being collected, close the list anyway
+                        errors.report(ParseError::new(ParseErrors::ClosingParenthesisExpected),
+                                      line_pos, column_pos, line_pos, column_pos)?;
                     }
+                    let pre_expr = match parse_context {
+                        ParseContext::CollectTuple => close_tuple(tuple_list, errors, start_line, start_column, line_pos, column_pos)?,
+                        ParseContext::CollectList => close_list(tuple_list, errors, start_line, start_column, line_pos, column_pos)?,
+                    };
+                    handle_expression(&mut parse_stack, &mut output_list, pre_expr);
                 } else {
-                    return Err(ParseError::new(ParseErrors::ClosingTupleLiteralUnexpected))
+                    errors.report(ParseError::new(ParseErrors::ClosingTupleLiteralUnexpected),
+                                  line_pos, column_pos, line_pos, column_pos)?;
                 }
             },
             LexItem::Variable(value) => {
                 let end_column = column_pos + (value.len() as u32) - 1;
-                let value = value.clone().try_into()
-                    .map_err(|_| { ParseError::new(ParseErrors::IllegalVariableName(value.to_string())) })?;
-                let mut pre_expr = PreSymbolicExpression::atom(value);
-                pre_expr.set_span(line_pos, column_pos, line_pos, end_column);
-                handle_expression(&mut parse_stack, &mut output_list, pre_expr);
+                match value.clone().try_into() {
+                    Ok(value) => {
+                        let mut pre_expr = PreSymbolicExpression::atom(value);
+                        pre_expr.set_span(line_pos, column_pos, line_pos, end_column);
+                        handle_expression(&mut parse_stack, &mut output_list, pre_expr);
+                    },
+                    Err(_) => errors.report(ParseError::new(ParseErrors::IllegalVariableName(value.to_string())),
+                                            line_pos, column_pos, line_pos, end_column)?
+                }
             },
             LexItem::LiteralValue(length, value) => {
                 let mut end_column = column_pos + (length as u32);
@@ -473,15 +563,20 @@ pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSy
             },
             LexItem::TraitReference(_length, value) => {
                 let end_column = column_pos + (value.len() as u32) - 1;
-                let value = value.clone().try_into()
-                    .map_err(|_| { ParseError::new(ParseErrors::IllegalVariableName(value.to_string())) })?;
-                let mut pre_expr = PreSymbolicExpression::trait_reference(value);
-                pre_expr.set_span(line_pos, column_pos, line_pos, end_column);
-                handle_expression(&mut parse_stack, &mut output_list, pre_expr);
+                match value.clone().try_into() {
+                    Ok(value) => {
+                        let mut pre_expr = PreSymbolicExpression::trait_reference(value);
+                        pre_expr.set_span(line_pos, column_pos, line_pos, end_column);
+                        handle_expression(&mut parse_stack, &mut output_list, pre_expr);
+                    },
+                    Err(_) => errors.report(ParseError::new(ParseErrors::IllegalVariableName(value.to_string())),
+                                            line_pos, column_pos, line_pos, end_column)?
+                }
             }
             LexItem::ColonSeparator => {
                 match parse_stack.last_mut() {
-                    None => return Err(ParseError::new(ParseErrors::ColonSeparatorUnexpected)),
+                    None => errors.report(ParseError::new(ParseErrors::ColonSeparatorUnexpected),
+                                          line_pos, column_pos, line_pos, column_pos)?,
                     Some((ref mut list, ..)) => {
                         list.push(ParseStackItem::Colon);
                     }
@@ -489,7 +584,8 @@ pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSy
             }
             LexItem::CommaSeparator => {
                 match parse_stack.last_mut() {
-                    None => return Err(ParseError::new(ParseErrors::CommaSeparatorUnexpected)),
+                    None => errors.report(ParseError::new(ParseErrors::CommaSeparatorUnexpected),
+                                          line_pos, column_pos, line_pos, column_pos)?,
                     Some((ref mut list, ..)) => {
                         list.push(ParseStackItem::Comma);
                     }
@@ -500,11 +596,12 @@ pub fn parse_lexed(mut input: Vec<(LexItem, u32, u32)>) -> ParseResult<Vec<PreSy
     }
 
     // check unfinished stack:
-    if parse_stack.len() > 0 {
-        Err(ParseError::new(ParseErrors::ClosingParenthesisExpected))
-    } else {
-        Ok(output_list)
+    for (_, start_line, start_column, _) in parse_stack.iter() {
+        errors.report(ParseError::new(ParseErrors::ClosingParenthesisExpected),
+                      *start_line, *start_column, *start_line, *start_column)?;
     }
+
+    Ok(output_list)
 }
 
 pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
@@ -512,10 +609,28 @@ pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
     parse_lexed(lexed)
 }
 
+/// Parse a contract's source, carrying on past each error found so as to report all of them,
+///  in the order they appear in the source, with where in the source each one is.
+pub fn parse_reporting_all_errors(input: &str) -> std::result::Result<Vec<PreSymbolicExpression>, Vec<ParseError>> {
+    let mut errors = ErrorSink::new(true);
+    let parsed = lex_reporting(input, &mut errors)
+        .and_then(|lexed| parse_lexed_reporting(lexed, &mut errors));
+    let mut errors = errors.errors;
+    match parsed {
+        Ok(pre_expressions) if errors.is_empty() => return Ok(pre_expressions),
+        Ok(_) => {},
+        // only errors that can't be skipped past, like running out of lines, stop the parse early
+        Err(e) => errors.push(e)
+    }
+    errors.sort_by_key(|e| e.diagnostic.spans.first()
+                       .map(|span| (span.start_line, span.start_column)));
+    Err(errors)
+}
+
 
 #[cfg(test)]
 mod test {
-    use vm::representations::{PreSymbolicExpression, PreSymbolicExpressionType};
+    use vm::representations::{PreSymbolicExpression, PreSymbolicExpressionType, Span};
     use vm::{Value, ast};
     use vm::types::{QualifiedContractIdentifier, PrincipalData};
     use vm::ast::errors::{ParseErrors, ParseError};
@@ -775,4 +890,31 @@ r#"z (let ((x 1) (y 2))
         ast::parser::parse(&function_with_LF).unwrap();
     }
 
+    #[test]
+    fn test_parse_reporting_all_errors() {
+        let input = "(define-private (foo) (+ 1 2)))
+(define-private (bar) (let ((x#y 1)) x))
+(define-read-only (baz) { a 1 })";
+
+        let errors = ast::parser::parse_reporting_all_errors(&input).unwrap_err();
+        let found: Vec<_> = errors.into_iter()
+            .map(|e| (e.err, e.diagnostic.spans))
+            .collect();
+        assert_eq!(found, vec![
+            (ParseErrors::ClosingParenthesisUnexpected,
+             vec![Span { start_line: 1, start_column: 31, end_line: 1, end_column: 31 }]),
+            (ParseErrors::FailedParsingRemainder("#y".to_string()),
+             vec![Span { start_line: 2, start_column: 31, end_line: 2, end_column: 32 }]),
+            (ParseErrors::TupleColonExpected(1),
+             vec![Span { start_line: 3, start_column: 25, end_line: 3, end_column: 31 }]),
+        ]);
+
+        // a plain parse stops at the first error it finds
+        assert!(match ast::parser::parse(&input).unwrap_err().err {
+            ParseErrors::FailedParsingRemainder(remainder) => remainder.starts_with("#y 1)) x))"), _ => false });
+
+        let input = "(define-private (foo) (+ 1 2))";
+        assert_eq!(ast::parser::parse_reporting_all_errors(&input), Ok(ast::parser::parse(&input).unwrap()));
+    }
+
 }
@@ -11,7 +11,7 @@ use vm::errors::{InterpreterResult as Result, Error, check_argument_count};
 use vm::analysis::errors::CheckErrors;
 use vm::representations::{SymbolicExpression, ClarityName};
 use vm::types::{TypeSignature, QualifiedContractIdentifier, TraitIdentifier, PrincipalData, FunctionType};
-use vm::{eval, Value, LocalContext, Environment};
+use vm::{eval, hooks, Value, LocalContext, Environment};
 use vm::contexts::ContractContext;
 
 pub enum CallableType {
@@ -40,18 +40,19 @@ pub struct DefinedFunction {
 pub enum NativeHandle {
     SingleArg(&'static dyn Fn(Value) -> Result<Value>),
     DoubleArg(&'static dyn Fn(Value, Value) -> Result<Value>),
-    MoreArg(&'static dyn Fn(Vec<Value>) -> Result<Value>)
+    MoreArg(&'static dyn Fn(&mut Vec<Value>) -> Result<Value>)
 }
 
 impl NativeHandle {
-    pub fn apply(&self, mut args: Vec<Value>) -> Result<Value> {
+    /// Apply the function to its arguments, taking them out of `args`
+    pub fn apply(&self, args: &mut Vec<Value>) -> Result<Value> {
         match self {
             NativeHandle::SingleArg(function) => {
-                check_argument_count(1, &args)?;
+                check_argument_count(1, args)?;
                 function(args.pop().unwrap())
             },
             NativeHandle::DoubleArg(function) => {
-                check_argument_count(2, &args)?;
+                check_argument_count(2, args)?;
                 let second = args.pop().unwrap();
                 let first = args.pop().unwrap();
                 function(first, second)
@@ -128,6 +129,8 @@ impl DefinedFunction {
             }
         }
 
+        hooks::will_apply_function(&self.name, &self.body, env, &context);
+
         let result = eval(&self.body, env, &context);
 
         // if the error wasn't actually an error, but a function return,
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+
+use vm::errors::Error;
+use vm::database::ClaritySerializable;
+use vm::representations::SymbolicExpression;
+use vm::types::{QualifiedContractIdentifier, Value};
+
+/// A public function call made while tracing, with the calls it made in turn.  The call tree
+/// of a transaction shows which of its inter-contract calls failed, and with what.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractCallTrace {
+    pub contract_identifier: QualifiedContractIdentifier,
+    pub function: String,
+    pub args: Vec<Value>,
+    /// the call's return value, or why it failed
+    pub result: Result<Value, String>,
+    /// the calls this call made, in order
+    pub calls: Vec<ContractCallTrace>,
+}
+
+impl ContractCallTrace {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        let raw_args : Vec<String> = self.args.iter().map(|arg| format!("0x{}", arg.serialize())).collect();
+        let (result, raw_result, error) = match self.result {
+            Ok(ref value) => (json!(value), json!(format!("0x{}", value.serialize())), json!(null)),
+            Err(ref e) => (json!(null), json!(null), json!(e))
+        };
+        let calls : Vec<serde_json::Value> = self.calls.iter().map(|call| call.json_serialize()).collect();
+        json!({
+            "contract_identifier": self.contract_identifier.to_string(),
+            "function": self.function,
+            "args": self.args,
+            "raw_args": raw_args,
+            "result": result,
+            "raw_result": raw_result,
+            "error": error,
+            "calls": calls,
+        })
+    }
+}
+
+/// Records the calls this thread's VM makes between `begin` and `finish`
+#[derive(Default)]
+struct CallTracer {
+    /// calls that haven't returned yet, outermost first
+    stack: Vec<ContractCallTrace>,
+    /// calls that have returned, and weren't made by another traced call
+    finished: Vec<ContractCallTrace>,
+}
+
+// the call tracer recording this thread's calls, if any
+thread_local!(static CALL_TRACER: RefCell<Option<CallTracer>> = RefCell::new(None));
+
+/// Start recording the public function calls this thread's VM makes.  Any calls recorded since
+/// an earlier `begin` are dropped.
+pub fn begin() {
+    CALL_TRACER.with(|t| *t.borrow_mut() = Some(CallTracer::default()));
+}
+
+/// Stop recording calls, and get the calls made since `begin`, outermost first.  Calls that
+/// hadn't returned are dropped.
+pub fn finish() -> Vec<ContractCallTrace> {
+    CALL_TRACER.with(|t| t.borrow_mut().take())
+        .map(|tracer| tracer.finished)
+        .unwrap_or(vec![])
+}
+
+/// Called by the VM before it calls a public function.  The arguments are expected to be values.
+pub fn will_call_contract(contract_identifier: &QualifiedContractIdentifier, function: &str, args: &[SymbolicExpression]) {
+    CALL_TRACER.with(|t| {
+        if let Some(ref mut tracer) = *t.borrow_mut() {
+            tracer.stack.push(ContractCallTrace {
+                contract_identifier: contract_identifier.clone(),
+                function: function.to_string(),
+                args: args.iter().filter_map(|arg| arg.match_atom_value().cloned()).collect(),
+                result: Err("did not return".to_string()),
+                calls: vec![],
+            });
+        }
+    })
+}
+
+/// Called by the VM once a public function call returns
+pub fn did_call_contract(result: &Result<Value, Error>) {
+    CALL_TRACER.with(|t| {
+        if let Some(ref mut tracer) = *t.borrow_mut() {
+            if let Some(mut call) = tracer.stack.pop() {
+                call.result = match result {
+                    Ok(value) => Ok(value.clone()),
+                    // the call tree already says where the error happened
+                    Err(Error::Runtime(e, _stack_trace)) => Err(e.to_string()),
+                    Err(e) => Err(e.to_string())
+                };
+                match tracer.stack.last_mut() {
+                    Some(caller) => caller.calls.push(call),
+                    None => tracer.finished.push(call)
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use vm::contexts::OwnedEnvironment;
+    use vm::database::MemoryBackingStore;
+    use vm::tests::symbols_from_values;
+    use vm::types::{PrincipalData, StandardPrincipalData};
+
+    #[test]
+    fn test_call_trace() {
+        let callee = "(define-public (check (x int)) (if (> x 10) (ok x) (err u1)))
+                      (define-public (boom (x int)) (ok (/ x 0)))";
+        let caller = "(define-public (run (x int))
+                        (begin
+                          (unwrap-panic (contract-call? .callee check x))
+                          (contract-call? .callee boom x)))";
+
+        let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+        let callee_id = QualifiedContractIdentifier::local("callee").unwrap();
+        let caller_id = QualifiedContractIdentifier::local("caller").unwrap();
+
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+        owned_env.initialize_contract(callee_id.clone(), callee).unwrap();
+        owned_env.initialize_contract(caller_id.clone(), caller).unwrap();
+
+        // not recorded unless tracing
+        owned_env.execute_transaction(sender.clone(), caller_id.clone(), "run", &symbols_from_values(vec![Value::Int(1)])).unwrap_err();
+        assert_eq!(finish(), vec![]);
+
+        // the inner call fails, so the outer call does too
+        begin();
+        owned_env.execute_transaction(sender.clone(), caller_id.clone(), "run", &symbols_from_values(vec![Value::Int(20)])).unwrap_err();
+        let trace = finish();
+
+        assert_eq!(trace.len(), 1);
+        let run = &trace[0];
+        assert_eq!(run.contract_identifier, caller_id);
+        assert_eq!(run.function, "run");
+        assert_eq!(run.args, vec![Value::Int(20)]);
+        assert_eq!(run.result, Err("DivisionByZero".to_string()));
+        assert_eq!(run.calls.len(), 2);
+
+        assert_eq!(run.calls[0].contract_identifier, callee_id);
+        assert_eq!(run.calls[0].function, "check");
+        assert_eq!(run.calls[0].result, Ok(Value::okay(Value::Int(20)).unwrap()));
+        assert_eq!(run.calls[0].calls, vec![]);
+
+        assert_eq!(run.calls[1].function, "boom");
+        assert_eq!(run.calls[1].args, vec![Value::Int(20)]);
+        assert_eq!(run.calls[1].result, Err("DivisionByZero".to_string()));
+
+        let json = run.json_serialize();
+        assert_eq!(json["function"], "run");
+        assert_eq!(json["raw_args"][0], format!("0x{}", Value::Int(20).serialize()));
+        assert_eq!(json["result"], serde_json::Value::Null);
+        assert_eq!(json["calls"][0]["raw_result"], format!("0x{}", Value::okay(Value::Int(20)).unwrap().serialize()));
+        assert_eq!(json["calls"][1]["function"], "boom");
+
+        // calls that return an `err` return normally
+        begin();
+        owned_env.execute_transaction(sender.clone(), callee_id.clone(), "check", &symbols_from_values(vec![Value::Int(1)])).unwrap();
+        let trace = finish();
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].result, Ok(Value::error(Value::UInt(1)).unwrap()));
+    }
+}
@@ -157,6 +157,13 @@ impl ClarityBlockConnection<'_> {
             cost_tracker.set_total(cost);
         }
     }
+
+    /// Stop counting the block's costs and memory use, and so stop enforcing its limits.  Used
+    /// for ancestors of a trusted checkpoint, whose costs were checked when they were mined.
+    /// Its transactions' costs all come to zero.
+    pub fn disable_cost_tracking(&mut self) -> () {
+        self.cost_track = Some(LimitedCostTracker::new_free());
+    }
 }
 
 impl ClarityInstance {
@@ -164,6 +171,11 @@ impl ClarityInstance {
         ClarityInstance { datastore: Some(datastore), block_limit }
     }
 
+    /// The execution cost limit of each block
+    pub fn block_limit(&self) -> ExecutionCost {
+        self.block_limit.clone()
+    }
+
     pub fn begin_block<'a> (&'a mut self, current: &StacksBlockId, next: &StacksBlockId,
                             header_db: &'a dyn HeadersDB) -> ClarityBlockConnection<'a> {
         let mut datastore = self.datastore.take()
@@ -211,6 +223,27 @@ impl ClarityInstance {
             .map_err(Error::from)
     }
 
+    /// Get the inner MARF, to read committed state
+    pub fn get_marf(&mut self) -> &mut MARF<StacksBlockId> {
+        self.datastore.as_mut()
+            .expect("FAIL: use of get_marf while a block is open.")
+            .get_marf()
+    }
+
+    /// The keys written in a committed block
+    pub fn get_written_keys(&mut self, bhh: &StacksBlockId) -> Vec<String> {
+        self.datastore.as_mut()
+            .expect("FAIL: use of get_written_keys while a block is open.")
+            .get_written_keys(bhh)
+    }
+
+    /// Read a key's committed value as of a block
+    pub fn get_value_at(&mut self, bhh: &StacksBlockId, key: &str) -> Option<String> {
+        self.datastore.as_mut()
+            .expect("FAIL: use of get_value_at while a block is open.")
+            .get_at(bhh, key)
+    }
+
     pub fn destroy(mut self) -> MarfedKV {
         let datastore = self.datastore.take()
             .expect("FAIL: attempt to recover database connection from clarity instance which is still open");
@@ -630,6 +663,7 @@ mod tests {
     use vm::types::{Value, StandardPrincipalData};
     use vm::database::{NULL_HEADER_DB, ClarityBackingStore, MarfedKV};
     use chainstate::stacks::index::storage::{TrieFileStorage};
+    use chainstate::stacks::events::TransactionAbortCode;
     use rusqlite::NO_PARAMS;
 
     #[test]
@@ -749,6 +783,60 @@ mod tests {
         assert!(marf.get_contract_hash(&contract_identifier).is_ok());
     }
 
+    #[test]
+    pub fn test_free_cost_tracking_same_state() {
+        let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
+        let sender : PrincipalData = StandardPrincipalData::transient().into();
+        let contract = "
+            (define-data-var total int 0)
+            (define-map seen ((n int)) ((count int)))
+            (define-private (bump (n int) (acc int))
+              (begin
+                (map-set seen {n: n} {count: (+ 1 (default-to 0 (get count (map-get? seen {n: n}))))})
+                (+ n acc)))
+            (define-public (run (n int))
+              (let ((sum (fold bump (list n (* 2 n) (* 3 n)) 0)))
+                (var-set total (+ sum (var-get total)))
+                (if (> sum 100) (err sum) (ok (concat (list sum) (list (var-get total)))))))
+            ";
+
+        // a block's state is the same whether or not its costs are counted
+        let run_block = |free: bool| {
+            let mut clarity_instance = ClarityInstance::new(MarfedKV::temporary(), ExecutionCost::max_value());
+            let mut conn = clarity_instance.begin_block(&StacksBlockId::sentinel(),
+                                                        &StacksBlockId([0 as u8; 32]),
+                                                        &NULL_HEADER_DB);
+            if free {
+                conn.disable_cost_tracking();
+            }
+
+            conn.as_transaction(|tx| {
+                let (ct_ast, ct_analysis) = tx.analyze_smart_contract(&contract_identifier, &contract).unwrap();
+                tx.initialize_smart_contract(&contract_identifier, &ct_ast, &contract, |_,_| false).unwrap();
+                tx.save_analysis(&contract_identifier, &ct_analysis).unwrap();
+            });
+
+            let mut results = vec![];
+            for n in [1, 5, 20, 5].iter() {
+                let (result, _, _) = conn.as_transaction(|tx| tx.run_contract_call(&sender, &contract_identifier, "run", &[Value::Int(*n)], |_, _| false)).unwrap();
+                results.push(result);
+            }
+
+            let cost = conn.as_transaction(|tx| tx.cost_so_far());
+            let root_hash = conn.get_root_hash();
+            conn.commit_block();
+            (results, cost, root_hash)
+        };
+
+        let (metered_results, metered_cost, metered_root_hash) = run_block(false);
+        let (free_results, free_cost, free_root_hash) = run_block(true);
+
+        assert!(metered_cost.runtime > 0);
+        assert_eq!(free_cost, ExecutionCost::zero());
+        assert_eq!(free_results, metered_results);
+        assert_eq!(free_root_hash, metered_root_hash);
+    }
+
     #[test]
     pub fn test_block_roll_back() {
         let marf = MarfedKV::temporary();
@@ -919,6 +1007,7 @@ mod tests {
                         &tx1,
                         &account).unwrap();
                 assert_eq!(receipt.post_condition_aborted, true);
+                assert_eq!(receipt.abort_code, Some(TransactionAbortCode::PostCondition));
             });
             conn.as_transaction(|clarity_tx| {
                 StacksChainState::process_transaction_payload(
@@ -935,6 +1024,7 @@ mod tests {
                         &account).unwrap();
 
                 assert_eq!(receipt.post_condition_aborted, true);
+                assert_eq!(receipt.abort_code, Some(TransactionAbortCode::PostCondition));
             });
 
             conn.commit_block();
@@ -998,5 +1088,20 @@ mod tests {
 
             conn.commit_block();
         }
+
+        // an ancestor of a trusted checkpoint isn't held to the limit, since its costs aren't
+        // counted at all
+        {
+            let mut conn = clarity_instance.begin_block(&StacksBlockId([1 as u8; 32]),
+                                                        &StacksBlockId([2 as u8; 32]),
+                                                        &NULL_HEADER_DB);
+            conn.disable_cost_tracking();
+            conn.as_transaction(|tx| {
+                tx.run_contract_call(&sender, &contract_identifier, "do-expand", &[], |_, _| false).unwrap();
+                assert_eq!(tx.cost_so_far(), ExecutionCost::zero());
+            });
+
+            conn.commit_block();
+        }
     }
 }
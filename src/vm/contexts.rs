@@ -13,6 +13,9 @@ use vm::ast::ContractAST;
 use vm::costs::{CostTracker, ExecutionCost, LimitedCostTracker, cost_functions, CostErrors};
 use vm::ast;
 use vm::{eval, is_reserved};
+use vm::hooks;
+use vm::calltrace;
+use vm::pool::ValuePool;
 
 use chainstate::burn::{VRFSeed, BlockHeaderHash};
 use chainstate::stacks::events::*;
@@ -77,6 +80,8 @@ pub struct GlobalContext<'a> {
     pub database: ClarityDatabase<'a>,
     read_only: Vec<bool>,
     pub cost_track: LimitedCostTracker,
+    // reusable buffers for evaluated function arguments
+    pub value_pool: ValuePool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -495,6 +500,9 @@ impl CostTracker for Environment<'_,'_> {
     fn reset_memory(&mut self) {
         self.global_context.cost_track.reset_memory()
     }
+    fn counts_costs(&self) -> bool {
+        self.global_context.cost_track.counts_costs()
+    }
 }
 
 impl CostTracker for GlobalContext<'_> {
@@ -510,6 +518,9 @@ impl CostTracker for GlobalContext<'_> {
     fn reset_memory(&mut self) {
         self.cost_track.reset_memory()
     }
+    fn counts_costs(&self) -> bool {
+        self.cost_track.counts_costs()
+    }
 }
 
 impl <'a,'b> Environment <'a,'b> {
@@ -597,6 +608,14 @@ impl <'a,'b> Environment <'a,'b> {
 
     pub fn execute_contract(&mut self, contract_identifier: &QualifiedContractIdentifier, 
                             tx_name: &str, args: &[SymbolicExpression], read_only: bool) -> Result<Value> {
+        calltrace::will_call_contract(contract_identifier, tx_name, args);
+        let result = self.inner_execute_contract(contract_identifier, tx_name, args, read_only);
+        calltrace::did_call_contract(&result);
+        result
+    }
+
+    fn inner_execute_contract(&mut self, contract_identifier: &QualifiedContractIdentifier, 
+                              tx_name: &str, args: &[SymbolicExpression], read_only: bool) -> Result<Value> {
         let contract_size = self.global_context.database.get_contract_size(contract_identifier)?;
         runtime_cost!(cost_functions::LOAD_CONTRACT, self, contract_size)?;
 
@@ -718,6 +737,7 @@ impl <'a,'b> Environment <'a,'b> {
                     &contract_identifier, data_size)?;
 
                 self.global_context.commit()?;
+                hooks::did_initialize_contract(&contract_identifier, contract_content);
                 Ok(())
             },
             Err(e) => {
@@ -828,6 +848,7 @@ impl <'a> GlobalContext<'a> {
             read_only: Vec::new(),
             asset_maps: Vec::new(),
             event_batches: Vec::new(),
+            value_pool: ValuePool::new(),
         }
     }
 
@@ -1097,6 +1118,11 @@ impl CallStack {
         }
     }
 
+    /// The functions being applied, outermost first
+    pub fn frames(&self) -> &[FunctionIdentifier] {
+        &self.stack
+    }
+
     #[cfg(feature = "developer-mode")]
     pub fn make_stack_trace(&self) -> StackTrace {
         self.stack.clone()
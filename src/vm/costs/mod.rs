@@ -2,7 +2,9 @@ pub mod cost_functions;
 pub mod constants;
 
 use std::{fmt, cmp};
-use vm::types::TypeSignature;
+use std::collections::BTreeMap;
+use vm::types::{TypeSignature, QualifiedContractIdentifier};
+use vm::representations::SymbolicExpression;
 use vm::Value;
 use std::convert::TryFrom;
 
@@ -10,19 +12,28 @@ type Result<T> = std::result::Result<T, CostErrors>;
 
 pub const CLARITY_MEMORY_LIMIT: u64 = 100 * 1000 * 1000;
 
+/// Longest expression source kept in a cost profile
+pub const COST_PROFILE_EXPRESSION_MAX_LEN: usize = 80;
+
 macro_rules! runtime_cost {
     ( $cost_spec:expr, $env:expr, $input:expr ) => {
         {
             use vm::costs::{CostTracker, CostErrors};
             use std::convert::TryInto;
-            let input = $input.try_into()
-                .map_err(|_| CostErrors::CostOverflow)
-                .and_then(|input| {
-                    ($cost_spec).compute_cost(input)
-                });
-            match input {
-                Ok(cost) => CostTracker::add_cost($env, cost),
-                Err(e) => Err(e)
+            if !CostTracker::counts_costs(&*$env) {
+                // not even worth working out
+                Ok(())
+            }
+            else {
+                let input = $input.try_into()
+                    .map_err(|_| CostErrors::CostOverflow)
+                    .and_then(|input| {
+                        ($cost_spec).compute_cost(input)
+                    });
+                match input {
+                    Ok(cost) => CostTracker::add_cost($env, cost),
+                    Err(e) => Err(e)
+                }
             }
         }
     }
@@ -64,6 +75,9 @@ pub trait CostTracker {
     fn add_memory(&mut self, memory: u64) -> Result<()>;
     fn drop_memory(&mut self, memory: u64);
     fn reset_memory(&mut self);
+    /// Whether charging this tracker does anything.  If not, callers can skip working out what
+    /// to charge it.
+    fn counts_costs(&self) -> bool;
 }
 
 // Don't track!
@@ -76,6 +90,9 @@ impl CostTracker for () {
     }
     fn drop_memory(&mut self, _memory: u64) {}
     fn reset_memory(&mut self) {}
+    fn counts_costs(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -83,7 +100,45 @@ pub struct LimitedCostTracker {
     total: ExecutionCost,
     limit: ExecutionCost,
     memory: u64,
-    memory_limit: u64
+    memory_limit: u64,
+    profile: Option<CostProfile>,
+    free: bool
+}
+
+/// What an expression cost, over all of its evaluations in a profiled execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpressionCost {
+    pub contract: String,
+    pub expression_id: u64,
+    /// the expression's source, cut short if it's long
+    pub expression: String,
+    /// the expression's line in the contract source, if known
+    pub line: Option<u32>,
+    /// how many times the expression was evaluated
+    pub count: u64,
+    /// costs charged by the expression itself, not counting its subexpressions
+    pub self_cost: ExecutionCost,
+    /// costs charged by the expression and its subexpressions
+    pub total_cost: ExecutionCost,
+}
+
+/// A profiled execution's costs, broken down by expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostProfileReport {
+    pub total: ExecutionCost,
+    /// costs charged outside of any expression, like the ones for checking a call's arguments
+    pub unattributed: ExecutionCost,
+    /// the expressions evaluated, highest self runtime cost first
+    pub expressions: Vec<ExpressionCost>,
+}
+
+/// Attributes the costs a tracker is charged to the expressions being evaluated
+#[derive(Debug, Clone, PartialEq)]
+struct CostProfile {
+    unattributed: ExecutionCost,
+    expressions: BTreeMap<(String, u64), ExpressionCost>,
+    /// the expressions being evaluated, innermost last, and the tracker's total when each began
+    stack: Vec<((String, u64), ExecutionCost)>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -96,11 +151,20 @@ pub enum CostErrors {
 impl LimitedCostTracker {
     pub fn new(limit: ExecutionCost) -> LimitedCostTracker {
         LimitedCostTracker { limit, memory_limit: CLARITY_MEMORY_LIMIT,
-                             total: ExecutionCost::zero(), memory: 0 }
+                             total: ExecutionCost::zero(), memory: 0, profile: None, free: false }
     }
     pub fn new_max_limit() -> LimitedCostTracker {
         LimitedCostTracker { limit: ExecutionCost::max_value(), total: ExecutionCost::zero(),
-                             memory: 0, memory_limit: CLARITY_MEMORY_LIMIT }
+                             memory: 0, memory_limit: CLARITY_MEMORY_LIMIT, profile: None, free: false }
+    }
+    /// A tracker that neither counts nor limits costs and memory use, for executing blocks that
+    /// are already trusted to be within their limits.  Its total stays at zero.
+    pub fn new_free() -> LimitedCostTracker {
+        LimitedCostTracker { limit: ExecutionCost::max_value(), total: ExecutionCost::zero(),
+                             memory: 0, memory_limit: u64::max_value(), profile: None, free: true }
+    }
+    pub fn is_free(&self) -> bool {
+        self.free
     }
     pub fn get_total(&self) -> ExecutionCost {
         self.total.clone()
@@ -109,10 +173,107 @@ impl LimitedCostTracker {
         // used by the miner to "undo" the cost of a transaction when trying to pack a block.
         self.total = total;
     }
+
+    /// Start attributing the costs this tracker is charged to the expressions being evaluated
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(CostProfile::new());
+    }
+
+    /// Stop profiling, and report what each profiled expression cost
+    pub fn take_profile(&mut self) -> Option<CostProfileReport> {
+        let total = self.total.clone();
+        self.profile.take()
+            .map(|profile| profile.into_report(total))
+    }
+
+    /// Called by the VM as it begins evaluating an expression
+    pub fn begin_expression(&mut self, contract: &QualifiedContractIdentifier, exp: &SymbolicExpression) {
+        if let Some(ref mut profile) = self.profile {
+            profile.begin(contract, exp, &self.total);
+        }
+    }
+
+    /// Called by the VM once it's done evaluating an expression
+    pub fn end_expression(&mut self) {
+        if let Some(ref mut profile) = self.profile {
+            profile.end(&self.total);
+        }
+    }
+}
+
+impl CostProfile {
+    fn new() -> CostProfile {
+        CostProfile {
+            unattributed: ExecutionCost::zero(),
+            expressions: BTreeMap::new(),
+            stack: vec![],
+        }
+    }
+
+    fn begin(&mut self, contract: &QualifiedContractIdentifier, exp: &SymbolicExpression, total: &ExecutionCost) {
+        let key = (contract.to_string(), exp.id);
+        let entry = self.expressions.entry(key.clone())
+            .or_insert_with(|| {
+                let mut expression = exp.to_string();
+                if expression.chars().count() > COST_PROFILE_EXPRESSION_MAX_LEN {
+                    expression = format!("{}...", expression.chars().take(COST_PROFILE_EXPRESSION_MAX_LEN).collect::<String>());
+                }
+                ExpressionCost {
+                    contract: key.0.clone(),
+                    expression_id: exp.id,
+                    expression,
+                    line: exp.start_line(),
+                    count: 0,
+                    self_cost: ExecutionCost::zero(),
+                    total_cost: ExecutionCost::zero(),
+                }
+            });
+        entry.count += 1;
+        self.stack.push((key, total.clone()));
+    }
+
+    fn end(&mut self, total: &ExecutionCost) {
+        if let Some((key, start)) = self.stack.pop() {
+            let mut spent = total.clone();
+            if spent.sub(&start).is_ok() {
+                if let Some(entry) = self.expressions.get_mut(&key) {
+                    // the tracker's total didn't overflow, so neither can this
+                    let _ = entry.total_cost.add(&spent);
+                }
+            }
+        }
+    }
+
+    fn charge(&mut self, cost: &ExecutionCost) {
+        let charged = match self.stack.last() {
+            Some(&(ref key, _)) => self.expressions.get_mut(key).map(|entry| &mut entry.self_cost),
+            None => None
+        };
+        let _ = charged.unwrap_or(&mut self.unattributed).add(cost);
+    }
+
+    fn into_report(self, total: ExecutionCost) -> CostProfileReport {
+        let mut expressions: Vec<_> = self.expressions.into_iter()
+            .map(|(_, entry)| entry)
+            .collect();
+        expressions.sort_by(|a, b| b.self_cost.runtime.cmp(&a.self_cost.runtime));
+
+        CostProfileReport {
+            total,
+            unattributed: self.unattributed,
+            expressions,
+        }
+    }
 }
 
 fn add_cost(s: &mut LimitedCostTracker, cost: ExecutionCost) -> std::result::Result<(), CostErrors> {
+    if s.free {
+        return Ok(())
+    }
     s.total.add(&cost)?;
+    if let Some(ref mut profile) = s.profile {
+        profile.charge(&cost);
+    }
     if s.total.exceeds(&s.limit) {
         Err(CostErrors::CostBalanceExceeded(s.total.clone(), s.limit.clone()))
     } else {
@@ -121,6 +282,9 @@ fn add_cost(s: &mut LimitedCostTracker, cost: ExecutionCost) -> std::result::Res
 }
 
 fn add_memory(s: &mut LimitedCostTracker, memory: u64) -> std::result::Result<(), CostErrors> {
+    if s.free {
+        return Ok(())
+    }
     s.memory = s.memory.cost_overflow_add(memory)?;
     if s.memory > s.memory_limit {
         Err(CostErrors::MemoryBalanceExceeded(s.memory, s.memory_limit))
@@ -130,6 +294,9 @@ fn add_memory(s: &mut LimitedCostTracker, memory: u64) -> std::result::Result<()
 }
 
 fn drop_memory(s: &mut LimitedCostTracker, memory: u64) {
+    if s.free {
+        return
+    }
     s.memory = s.memory.checked_sub(memory)
         .expect("Underflowed dropped memory");
 }
@@ -147,6 +314,9 @@ impl CostTracker for LimitedCostTracker {
     fn reset_memory(&mut self) {
         self.memory = 0;
     }
+    fn counts_costs(&self) -> bool {
+        !self.free
+    }
 }
 
 impl CostTracker for &mut LimitedCostTracker {
@@ -162,6 +332,9 @@ impl CostTracker for &mut LimitedCostTracker {
     fn reset_memory(&mut self) {
         self.memory = 0;
     }
+    fn counts_costs(&self) -> bool {
+        !self.free
+    }
 }
 
 impl TypeCheckCost {
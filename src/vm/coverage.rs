@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use vm::ast::ContractAST;
+use vm::contexts::{Environment, LocalContext};
+use vm::hooks::{self, EvalHook, HookSlot};
+use vm::functions::define::DefineFunctionsParsed;
+use vm::representations::{SymbolicExpression, ClarityName};
+use vm::types::QualifiedContractIdentifier;
+
+/// What ran of one contract.  Coverage is counted in applications -- list expressions like
+/// `(+ a b)` -- and a line is covered if an application starting on it ran.
+#[derive(Debug, Clone, Default)]
+struct ContractCoverage {
+    /// the line each application starts on, by expression ID
+    expressions: HashMap<u64, u32>,
+    /// how many times each application ran, by expression ID
+    expression_hits: HashMap<u64, u64>,
+    /// each function's line, and how many times it was applied
+    functions: BTreeMap<String, (u32, u64)>,
+}
+
+/// Collects which contract expressions and functions the VM runs, for contracts deployed
+/// while it is attached.  Collectors are shared between the thread running the VM and
+/// whatever reports on it.
+#[derive(Clone, Default)]
+pub struct CoverageCollector {
+    contracts: Arc<Mutex<BTreeMap<String, ContractCoverage>>>,
+}
+
+/// Collect coverage for the Clarity code this thread evaluates
+pub fn attach(collector: CoverageCollector) {
+    hooks::attach_to(HookSlot::Coverage, Box::new(collector));
+}
+
+/// Stop collecting coverage for the Clarity code this thread evaluates
+pub fn detach() {
+    hooks::detach_from(HookSlot::Coverage);
+}
+
+impl EvalHook for CoverageCollector {
+    fn will_eval(&mut self, env: &mut Environment, _context: &LocalContext, expr: &SymbolicExpression) {
+        if expr.match_list().is_some() {
+            self.hit_expression(&env.contract_context.contract_identifier, expr);
+        }
+    }
+
+    fn will_apply_function(&mut self, env: &mut Environment, _context: &LocalContext, name: &ClarityName, _body: &SymbolicExpression) {
+        self.hit_function(&env.contract_context.contract_identifier, name);
+    }
+
+    fn did_initialize_contract(&mut self, contract_identifier: &QualifiedContractIdentifier, contract_ast: &ContractAST) {
+        self.add_contract(contract_identifier, contract_ast);
+    }
+}
+
+/// Find the applications the VM can evaluate in an expression, and the lines they start on
+fn collect_applications(exp: &SymbolicExpression, expressions: &mut HashMap<u64, u32>) {
+    let list = match exp.match_list() {
+        Some(list) => list,
+        None => return
+    };
+    if let Some(line) = exp.start_line() {
+        expressions.insert(exp.id, line);
+    }
+
+    let (head, args) = match list.split_first() {
+        Some(split) => split,
+        None => return
+    };
+
+    // binding pairs like `(a 1)` aren't evaluated, but the values bound in them are
+    let bindings = match head.match_atom().map(|name| name.as_str()) {
+        Some("let") => args.first().and_then(|bindings| bindings.match_list()).map(|bindings| (bindings, &args[1..])),
+        Some("tuple") => Some((args, &[][..])),
+        _ => None
+    };
+
+    match bindings {
+        Some((bindings, body)) => {
+            for binding in bindings.iter() {
+                match binding.match_list() {
+                    Some(pair) => {
+                        for value in pair.iter().skip(1) {
+                            collect_applications(value, expressions);
+                        }
+                    },
+                    None => collect_applications(binding, expressions)
+                }
+            }
+            for exp in body.iter() {
+                collect_applications(exp, expressions);
+            }
+        },
+        None => {
+            for arg in args.iter() {
+                collect_applications(arg, expressions);
+            }
+        }
+    }
+}
+
+impl CoverageCollector {
+    pub fn new() -> CoverageCollector {
+        CoverageCollector::default()
+    }
+
+    fn add_contract(&self, contract_identifier: &QualifiedContractIdentifier, contract_ast: &ContractAST) {
+        let mut contracts = self.contracts.lock().expect("FATAL: coverage collector lock poisoned");
+        let contract_id = contract_identifier.to_string();
+        if contracts.contains_key(&contract_id) {
+            // blocks are evaluated again when they're processed
+            return;
+        }
+
+        let mut coverage = ContractCoverage::default();
+        for exp in contract_ast.expressions.iter() {
+            let evaluated = match DefineFunctionsParsed::try_parse(exp) {
+                Ok(Some(DefineFunctionsParsed::PrivateFunction { signature, body })) |
+                Ok(Some(DefineFunctionsParsed::ReadOnlyFunction { signature, body })) |
+                Ok(Some(DefineFunctionsParsed::PublicFunction { signature, body })) => {
+                    if let Some(name) = signature.first().and_then(|name| name.match_atom()) {
+                        coverage.functions.insert(name.to_string(), (exp.start_line().unwrap_or(0), 0));
+                    }
+                    Some(body)
+                },
+                Ok(Some(DefineFunctionsParsed::Constant { value, .. })) => Some(value),
+                Ok(Some(DefineFunctionsParsed::BoundedFungibleToken { max_supply, .. })) => Some(max_supply),
+                Ok(Some(DefineFunctionsParsed::PersistedVariable { initial, .. })) => Some(initial),
+                Ok(Some(_)) => None,
+                Ok(None) => Some(exp),
+                Err(_) => None
+            };
+            if let Some(evaluated) = evaluated {
+                collect_applications(evaluated, &mut coverage.expressions);
+            }
+        }
+
+        contracts.insert(contract_id, coverage);
+    }
+
+    fn hit_expression(&self, contract_identifier: &QualifiedContractIdentifier, exp: &SymbolicExpression) {
+        let mut contracts = self.contracts.lock().expect("FATAL: coverage collector lock poisoned");
+        if let Some(coverage) = contracts.get_mut(&contract_identifier.to_string()) {
+            // code evaluated in a contract's context, like a read-only call's, has its own IDs
+            match (coverage.expressions.get(&exp.id), exp.start_line()) {
+                (Some(line), Some(exp_line)) if *line == exp_line => {
+                    *coverage.expression_hits.entry(exp.id).or_insert(0) += 1;
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn hit_function(&self, contract_identifier: &QualifiedContractIdentifier, name: &ClarityName) {
+        let mut contracts = self.contracts.lock().expect("FATAL: coverage collector lock poisoned");
+        if let Some(coverage) = contracts.get_mut(&contract_identifier.to_string()) {
+            if let Some((_line, count)) = coverage.functions.get_mut(name.as_str()) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// Report what ran of each contract, in lcov's tracefile format.  Each contract is a source
+    /// file named for its identifier.  A line's count is how many times its most-run
+    /// application ran.
+    pub fn lcov_report(&self) -> String {
+        let contracts = self.contracts.lock().expect("FATAL: coverage collector lock poisoned");
+        let mut report = String::new();
+        for (contract_id, coverage) in contracts.iter() {
+            let mut lines : BTreeMap<u32, u64> = BTreeMap::new();
+            for (id, line) in coverage.expressions.iter() {
+                let hits = coverage.expression_hits.get(id).cloned().unwrap_or(0);
+                let line_hits = lines.entry(*line).or_insert(0);
+                *line_hits = (*line_hits).max(hits);
+            }
+
+            let functions_hit = coverage.functions.values().filter(|(_line, count)| *count > 0).count();
+            let lines_hit = lines.values().filter(|count| **count > 0).count();
+            let function_lines : BTreeSet<(u32, &String)> = coverage.functions.iter()
+                .map(|(name, (line, _count))| (*line, name))
+                .collect();
+
+            // writing to a String can't fail
+            let _ = writeln!(report, "TN:");
+            let _ = writeln!(report, "SF:{}", contract_id);
+            for (line, name) in function_lines.iter() {
+                let _ = writeln!(report, "FN:{},{}", line, name);
+            }
+            for (_line, name) in function_lines.iter() {
+                let _ = writeln!(report, "FNDA:{},{}", coverage.functions[*name].1, name);
+            }
+            let _ = writeln!(report, "FNF:{}", coverage.functions.len());
+            let _ = writeln!(report, "FNH:{}", functions_hit);
+            for (line, count) in lines.iter() {
+                let _ = writeln!(report, "DA:{},{}", line, count);
+            }
+            let _ = writeln!(report, "LF:{}", lines.len());
+            let _ = writeln!(report, "LH:{}", lines_hit);
+            let _ = writeln!(report, "end_of_record");
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use vm::Value;
+    use vm::contexts::OwnedEnvironment;
+    use vm::database::MemoryBackingStore;
+    use vm::tests::symbols_from_values;
+    use vm::types::{PrincipalData, StandardPrincipalData};
+
+    #[test]
+    #[cfg(feature = "developer-mode")]
+    fn test_coverage_report() {
+        let contract = "(define-data-var total int 0)
+(define-private (bump (a int))
+  (let ((b
+          2))
+    (+ a b)))
+(define-public (run (x int))
+  (if (> x 0)
+      (ok (var-set total (bump x)))
+      (err (tuple
+             (code
+               u1)))))
+(define-read-only (get-total) (var-get total))";
+
+        let contract_identifier = QualifiedContractIdentifier::local("covered").unwrap();
+        let collector = CoverageCollector::new();
+        attach(collector.clone());
+
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+        owned_env.initialize_contract(contract_identifier.clone(), contract).unwrap();
+
+        let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+        owned_env.execute_transaction(sender, contract_identifier.clone(), "run", &symbols_from_values(vec![Value::Int(40)])).unwrap();
+
+        detach();
+
+        // binding pairs and atoms aren't applications, so lines 4, 10 and 11 aren't counted
+        assert_eq!(collector.lcov_report(),
+                   format!("TN:\nSF:{}\n\
+                            FN:2,bump\nFN:6,run\nFN:12,get-total\n\
+                            FNDA:1,bump\nFNDA:1,run\nFNDA:0,get-total\n\
+                            FNF:3\nFNH:2\n\
+                            DA:3,1\nDA:5,1\nDA:7,1\nDA:8,1\nDA:9,0\nDA:12,0\n\
+                            LF:6\nLH:4\nend_of_record\n", &contract_identifier));
+    }
+}
@@ -355,13 +355,17 @@ impl <'a> ClarityDatabase <'a> {
             .ok_or(CheckErrors::NoSuchDataVariable(variable_name.to_string()).into())
     }
 
+    pub fn make_key_for_data_var(contract_identifier: &QualifiedContractIdentifier, variable_name: &str) -> String {
+        ClarityDatabase::make_key_for_trip(contract_identifier, StoreType::Variable, variable_name)
+    }
+
     pub fn set_variable(&mut self, contract_identifier: &QualifiedContractIdentifier, variable_name: &str, value: Value) -> Result<Value> {
         let variable_descriptor = self.load_variable(contract_identifier, variable_name)?;
         if !variable_descriptor.value_type.admits(&value) {
             return Err(CheckErrors::TypeValueError(variable_descriptor.value_type, value).into())
         }
 
-        let key = ClarityDatabase::make_key_for_trip(contract_identifier, StoreType::Variable, variable_name);
+        let key = ClarityDatabase::make_key_for_data_var(contract_identifier, variable_name);
 
         self.put(&key, &value);
 
@@ -371,7 +375,7 @@ impl <'a> ClarityDatabase <'a> {
     pub fn lookup_variable(&mut self, contract_identifier: &QualifiedContractIdentifier, variable_name: &str) -> Result<Value>  {
         let variable_descriptor = self.load_variable(contract_identifier, variable_name)?;
 
-        let key = ClarityDatabase::make_key_for_trip(contract_identifier, StoreType::Variable, variable_name);
+        let key = ClarityDatabase::make_key_for_data_var(contract_identifier, variable_name);
 
         let result = self.get_value(&key, &variable_descriptor.value_type);
 
@@ -539,10 +543,18 @@ impl <'a> ClarityDatabase <'a> {
         }
     }
 
+    pub fn make_key_for_ft_balance(contract_identifier: &QualifiedContractIdentifier, token_name: &str, principal: &PrincipalData) -> String {
+        ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::FungibleToken, token_name, principal.serialize())
+    }
+
+    pub fn make_key_for_nft_owner(contract_identifier: &QualifiedContractIdentifier, asset_name: &str, asset: &Value) -> String {
+        ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::NonFungibleToken, asset_name, asset.serialize())
+    }
+
     pub fn get_ft_balance(&mut self, contract_identifier: &QualifiedContractIdentifier, token_name: &str, principal: &PrincipalData) -> Result<u128> {
         self.load_ft(contract_identifier, token_name)?;
 
-        let key =  ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::FungibleToken, token_name, principal.serialize());
+        let key =  ClarityDatabase::make_key_for_ft_balance(contract_identifier, token_name, principal);
 
         let result = self.get(&key);
         match result {
@@ -552,7 +564,7 @@ impl <'a> ClarityDatabase <'a> {
     }
 
     pub fn set_ft_balance(&mut self, contract_identifier: &QualifiedContractIdentifier, token_name: &str, principal: &PrincipalData, balance: u128) -> Result<()> {
-        let key =  ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::FungibleToken, token_name, principal.serialize());
+        let key =  ClarityDatabase::make_key_for_ft_balance(contract_identifier, token_name, principal);
         self.put(&key, &balance);
 
         Ok(())
@@ -564,7 +576,7 @@ impl <'a> ClarityDatabase <'a> {
             return Err(CheckErrors::TypeValueError(descriptor.key_type, (*asset).clone()).into())
         }
 
-        let key = ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::NonFungibleToken, asset_name, asset.serialize());
+        let key = ClarityDatabase::make_key_for_nft_owner(contract_identifier, asset_name, asset);
 
         let result = self.get(&key);
         result.ok_or(RuntimeErrorType::NoSuchToken.into())
@@ -581,7 +593,7 @@ impl <'a> ClarityDatabase <'a> {
             return Err(CheckErrors::TypeValueError(descriptor.key_type, (*asset).clone()).into())
         }
 
-        let key = ClarityDatabase::make_key_for_quad(contract_identifier, StoreType::NonFungibleToken, asset_name, asset.serialize());
+        let key = ClarityDatabase::make_key_for_nft_owner(contract_identifier, asset_name, asset);
 
         self.put(&key, principal);
 
@@ -1,5 +1,7 @@
 use super::{MarfedKV, ClarityBackingStore, ClarityDeserializable};
 use vm::Value;
+use vm::statetrace;
+use vm::hooks;
 use vm::errors::{ InterpreterResult as Result };
 use chainstate::{ stacks::StacksBlockId, burn::BlockHeaderHash, stacks::index::proofs::TrieMerkleProof };
 use std::collections::{HashMap};
@@ -194,12 +196,14 @@ impl <'a> RollbackWrapper <'a> {
             let all_edits = rollback_check_pre_bottom_commit(
                 last_item.edits, &mut self.lookup_map);
             if all_edits.len() > 0 {
+                statetrace::will_write(&all_edits);
                 self.store.put_all(all_edits);
             }
 
             let metadata_edits = rollback_check_pre_bottom_commit(
                 last_item.metadata_edits, &mut self.metadata_lookup_map);
             if metadata_edits.len() > 0 {
+                statetrace::will_write_metadata(&metadata_edits);
                 self.store.put_all_metadata(metadata_edits);
             }
         } else {
@@ -230,6 +234,7 @@ impl <'a> RollbackWrapper <'a> {
         let current = self.stack.last_mut()
             .expect("ERROR: Clarity VM attempted PUT on non-nested context.");
 
+        hooks::did_write(key, value);
         inner_put(&mut self.lookup_map, &mut current.edits, key.to_string(), value.to_string())
     }
 
@@ -244,28 +249,39 @@ impl <'a> RollbackWrapper <'a> {
             .map(|(value, proof)| (T::deserialize(&value), proof))
     }
 
+    /// Read a key from the backing store, for the state tracer to see
+    fn get_from_store(&mut self, key: &str) -> Option<String> {
+        let value = self.store.get(key);
+        statetrace::did_read(key, value.as_ref());
+        value
+    }
+
     pub fn get<T>(&mut self, key: &str) -> Option<T> where T: ClarityDeserializable<T> {
         self.stack.last()
             .expect("ERROR: Clarity VM attempted GET on non-nested context.");
 
-        let lookup_result = self.lookup_map.get(key)
-            .and_then(|x| x.last())
-            .map(|x| T::deserialize(x));
+        if let Some(value) = self.lookup_map.get(key).and_then(|x| x.last()) {
+            hooks::did_read(key, Some(value.as_str()));
+            return Some(T::deserialize(value));
+        }
 
-        lookup_result
-            .or_else(|| self.store.get(key).map(|x| T::deserialize(&x)))
+        let value = self.get_from_store(key);
+        hooks::did_read(key, value.as_ref().map(|x| x.as_str()));
+        value.map(|x| T::deserialize(&x))
     }
 
     pub fn get_value(&mut self, key: &str, expected: &TypeSignature) -> Option<Value> {
         self.stack.last()
             .expect("ERROR: Clarity VM attempted GET on non-nested context.");
 
-        let lookup_result = self.lookup_map.get(key)
-            .and_then(|x| x.last())
-            .map(|x| Value::deserialize(x, expected));
+        if let Some(value) = self.lookup_map.get(key).and_then(|x| x.last()) {
+            hooks::did_read(key, Some(value.as_str()));
+            return Some(Value::deserialize(value, expected));
+        }
 
-        lookup_result
-            .or_else(|| self.store.get(key).map(|x| Value::deserialize(&x, expected)))
+        let value = self.get_from_store(key);
+        hooks::did_read(key, value.as_ref().map(|x| x.as_str()));
+        value.map(|x| Value::deserialize(&x, expected))
     }
 
     pub fn get_current_block_height(&mut self) -> u32 {
@@ -306,7 +322,9 @@ impl <'a> RollbackWrapper <'a> {
         match lookup_result {
             Some(x) => Ok(Some(x)),
             None => {
-                self.store.get_metadata(contract, key)
+                let value = self.store.get_metadata(contract, key)?;
+                statetrace::did_read_metadata(contract, key, value.as_ref());
+                Ok(value)
             }
         }
     }
@@ -317,7 +335,7 @@ impl <'a> RollbackWrapper <'a> {
         if self.lookup_map.contains_key(key) {
             true
         } else {
-            self.store.has_entry(key)
+            self.get_from_store(key).is_some()
         }
     }
 
@@ -327,4 +345,19 @@ impl <'a> RollbackWrapper <'a> {
             _ => false
         }
     }
+
+    /// Contracts in this fork with a committed metadata entry for `key`.  The side store holds
+    ///   the entries of every fork, so each candidate is checked against this fork's view.
+    pub fn get_contracts_with_metadata_entry(&mut self, key: &str) -> Vec<QualifiedContractIdentifier> {
+        let candidates = self.store.get_side_store().get_metadata_contracts(key);
+        let mut contracts = vec![];
+        for candidate in candidates.iter() {
+            if let Ok(contract) = QualifiedContractIdentifier::parse(candidate) {
+                if self.has_metadata_entry(&contract, key) {
+                    contracts.push(contract);
+                }
+            }
+        }
+        contracts
+    }
 }
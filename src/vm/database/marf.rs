@@ -9,11 +9,13 @@ use chainstate::stacks::StacksBlockId;
 use chainstate::stacks::index::marf::MARF;
 use chainstate::stacks::index::{MARFValue, Error as MarfError, MarfTrieId, TrieHash};
 use chainstate::stacks::index::storage::{TrieFileStorage};
+use chainstate::stacks::index::backend::TrieBackend;
 use chainstate::stacks::index::proofs::{TrieMerkleProof};
 use chainstate::burn::{VRFSeed, BlockHeaderHash};
 use burnchains::BurnchainHeaderHash;
 use std::convert::TryInto;
 use util::hash::{to_hex, hex_bytes, Sha512Trunc256Sum};
+use util::db::SqlitePragmas;
 
 /// The MarfedKV struct is used to wrap a MARF data structure and side-storage
 ///   for use as a K/V store for ClarityDB or the AnalysisDB.
@@ -124,6 +126,13 @@ impl ClarityDeserializable<ContractCommitment> for ContractCommitment {
 
 impl MarfedKV {
     pub fn open(path_str: &str, miner_tip: Option<&StacksBlockId>) -> Result<MarfedKV> {
+        MarfedKV::open_with_backend(path_str, miner_tip, TrieBackend::Sqlite, &SqlitePragmas::default())
+    }
+
+    /// Open the MARF and side store under path_str, creating the MARF with the given backend if
+    /// it isn't there yet.  An existing MARF keeps the backend it was created with.  The sqlite
+    /// databases are tuned with `pragmas`.
+    pub fn open_with_backend(path_str: &str, miner_tip: Option<&StacksBlockId>, backend: TrieBackend, pragmas: &SqlitePragmas) -> Result<MarfedKV> {
         let mut path = PathBuf::from(path_str);
 
         std::fs::create_dir_all(&path)
@@ -140,8 +149,8 @@ impl MarfedKV {
             .ok_or_else(|| InterpreterError::BadFileName)?
             .to_string();
 
-        let side_store = SqliteConnection::initialize(&data_path)?;
-        let marf = MARF::from_path(&marf_path, miner_tip)
+        let side_store = SqliteConnection::initialize_with_pragmas(&data_path, pragmas)?;
+        let marf = MARF::from_path_with_backend(&marf_path, miner_tip, backend, pragmas)
             .map_err(|err| InterpreterError::MarfFailure(IncomparableError{ err }))?;
 
         let chain_tip = match miner_tip {
@@ -222,6 +231,7 @@ impl MarfedKV {
     pub fn commit_to(&mut self, final_bhh: &StacksBlockId) {
         debug!("commit_to({})", final_bhh); 
         self.side_store.commit_metadata_to(&self.chain_tip, final_bhh);
+        self.side_store.commit_written_keys_to(&self.chain_tip, final_bhh);
         self.side_store.commit(&self.chain_tip);
         self.marf.commit_to(final_bhh)
             .expect("ERROR: Failed to commit MARF block");
@@ -246,14 +256,42 @@ impl MarfedKV {
         &mut self.marf
     }
 
+    // Writes aren't batched here, since each one is already part of a per-block write:
+    //   the MARF insert goes to the open block's trie in RAM, which is only stored, in one
+    //   sqlite transaction, when the block is committed, and the side store writes run inside
+    //   the block's savepoint (see begin()), which is one sqlite transaction.
     pub fn put(&mut self, key: &str, value: &str) {
         let marf_value = MARFValue::from_value(value);
         self.side_store.put(&marf_value.to_hex(), value);
+        self.side_store.insert_written_key(&self.chain_tip, key);
 
         self.marf.insert(key, marf_value)
             .expect("ERROR: Unexpected MARF Failure")
     }
 
+    /// The keys written in a committed block
+    pub fn get_written_keys(&mut self, bhh: &StacksBlockId) -> Vec<String> {
+        self.side_store.get_written_keys(bhh)
+    }
+
+    /// Read a key as of a committed block, without moving the chain tip
+    pub fn get_at(&mut self, bhh: &StacksBlockId, key: &str) -> Option<String> {
+        self.marf.get(bhh, key)
+            .or_else(|e| {
+                match e {
+                    MarfError::NotFoundError => Ok(None),
+                    _ => Err(e)
+                }
+            })
+            .expect("ERROR: Unexpected MARF Failure on GET")
+            .map(|marf_value| {
+                let side_key = marf_value.to_hex();
+                self.side_store.get(&side_key)
+                    .expect(&format!("ERROR: MARF contained value_hash not found in side storage: {}",
+                                     side_key))
+            })
+    }
+
     pub fn make_contract_hash_key(contract: &QualifiedContractIdentifier) -> String {
         format!("clarity-contract::{}", contract)
     }
@@ -345,6 +383,7 @@ impl ClarityBackingStore for MarfedKV {
         for (key, value) in items.drain(..) {
             let marf_value = MARFValue::from_value(&value);
             self.side_store.put(&marf_value.to_hex(), &value);
+            self.side_store.insert_written_key(&self.chain_tip, &key);
             keys.push(key);
             values.push(marf_value);
         }
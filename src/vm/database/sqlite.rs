@@ -1,9 +1,9 @@
-use rusqlite::{ErrorCode as SqliteErrorCode, Error as SqliteError, Connection, OptionalExtension, NO_PARAMS, Row, Savepoint};
+use rusqlite::{ErrorCode as SqliteErrorCode, Error as SqliteError, Connection, OpenFlags, OptionalExtension, NO_PARAMS, Row, Savepoint};
 use rusqlite::types::{ToSql, FromSql};
 
 use chainstate::stacks::StacksBlockId;
 
-use util::db::tx_busy_handler;
+use util::db::{sqlite_open, SqlitePragmas};
 
 use vm::contracts::Contract;
 use vm::errors::{Error, InterpreterError, RuntimeErrorType, InterpreterResult as Result, IncomparableError};
@@ -71,10 +71,63 @@ impl SqliteConnection {
             .expect(SQL_FAIL_MESSAGE)
     }
 
+    /// Names of all contracts with a metadata entry for `key`, in any fork.
+    pub fn get_metadata_contracts(&mut self, key: &str) -> Vec<String> {
+        let escaped_key = key.replace("\\", "\\\\").replace("%", "\\%").replace("_", "\\_");
+        let pattern = format!("clr-meta::%::{}", escaped_key);
+        let suffix = format!("::{}", key);
+        let params: [&dyn ToSql; 1] = [&pattern];
+
+        let mut stmt = self.conn.prepare("SELECT DISTINCT key FROM metadata_table WHERE key LIKE ? ESCAPE '\\'")
+            .expect(SQL_FAIL_MESSAGE);
+        let rows = stmt.query_map(&params, |row| {
+            let metadata_key : String = row.get(0);
+            metadata_key
+        }).expect(SQL_FAIL_MESSAGE);
+
+        let mut contracts = vec![];
+        for row in rows {
+            let metadata_key = row.expect(SQL_FAIL_MESSAGE);
+            if metadata_key.starts_with("clr-meta::") && metadata_key.ends_with(&suffix) {
+                contracts.push(metadata_key["clr-meta::".len()..(metadata_key.len() - suffix.len())].to_string());
+            }
+        }
+        contracts
+    }
+
     pub fn has_entry(&mut self, key: &str) -> bool {
         sqlite_has_entry(&self.conn, key)
     }
 
+    /// Note that `key` was written in the block `bhh`.
+    pub fn insert_written_key(&mut self, bhh: &StacksBlockId, key: &str) {
+        let params: [&dyn ToSql; 2] = [&bhh, &key];
+        self.conn.execute("INSERT OR IGNORE INTO written_keys_table (blockhash, key) VALUES (?, ?)", &params)
+            .expect(SQL_FAIL_MESSAGE);
+    }
+
+    pub fn commit_written_keys_to(&mut self, from: &StacksBlockId, to: &StacksBlockId) {
+        let params = [to, from];
+        self.conn.execute(
+            "UPDATE written_keys_table SET blockhash = ? WHERE blockhash = ?",
+            &params)
+            .expect(SQL_FAIL_MESSAGE);
+    }
+
+    /// The keys written in the block `bhh`, in order.  Blocks processed before keys were noted
+    /// have none.
+    pub fn get_written_keys(&mut self, bhh: &StacksBlockId) -> Vec<String> {
+        let params: [&dyn ToSql; 1] = [&bhh];
+        let mut stmt = self.conn.prepare("SELECT key FROM written_keys_table WHERE blockhash = ? ORDER BY key")
+            .expect(SQL_FAIL_MESSAGE);
+        let rows = stmt.query_map(&params, |row| {
+            let key : String = row.get(0);
+            key
+        }).expect(SQL_FAIL_MESSAGE);
+
+        rows.map(|row| row.expect(SQL_FAIL_MESSAGE)).collect()
+    }
+
     /// begin, commit, rollback a save point identified by key
     ///    this is used to clean up any data from aborted blocks
     ///     (NOT aborted transactions that is handled by the clarity vm directly).
@@ -103,7 +156,10 @@ impl SqliteConnection {
 
 impl SqliteConnection {
     pub fn initialize(filename: &str) -> Result<Self> {
-        let contract_db = Self::inner_open(filename)?;
+        Self::initialize_with_pragmas(filename, &SqlitePragmas::default())
+    }
+    pub fn initialize_with_pragmas(filename: &str, pragmas: &SqlitePragmas) -> Result<Self> {
+        let contract_db = Self::inner_open(filename, pragmas)?;
         contract_db.conn.execute("CREATE TABLE IF NOT EXISTS data_table
                       (key TEXT PRIMARY KEY, value TEXT)", NO_PARAMS)
             .map_err(|x| InterpreterError::SqliteError(IncomparableError{ err: x }))?;
@@ -113,6 +169,11 @@ impl SqliteConnection {
                        UNIQUE (key, blockhash))", NO_PARAMS)
             .map_err(|x| InterpreterError::SqliteError(IncomparableError{ err: x }))?;
 
+        contract_db.conn.execute("CREATE TABLE IF NOT EXISTS written_keys_table
+                      (blockhash TEXT NOT NULL, key TEXT NOT NULL,
+                       UNIQUE (blockhash, key))", NO_PARAMS)
+            .map_err(|x| InterpreterError::SqliteError(IncomparableError{ err: x }))?;
+
         contract_db.check_schema()?;
 
         Ok(contract_db)
@@ -121,7 +182,7 @@ impl SqliteConnection {
         Self::initialize(":memory:")
     }
     pub fn open(filename: &str) -> Result<Self> {
-        let contract_db = Self::inner_open(filename)?;
+        let contract_db = Self::inner_open(filename, &SqlitePragmas::default())?;
 
         contract_db.check_schema()?;
         Ok(contract_db)
@@ -137,11 +198,9 @@ impl SqliteConnection {
         Ok(())
     }
 
-    pub fn inner_open(filename: &str) -> Result<Self> {
-        let conn = Connection::open(filename)
-            .map_err(|x| InterpreterError::SqliteError(IncomparableError{ err: x }))?;
-        
-        conn.busy_handler(Some(tx_busy_handler))
+    pub fn inner_open(filename: &str, pragmas: &SqlitePragmas) -> Result<Self> {
+        // in WAL mode, so that read-only connections don't block block processing, or wait on it
+        let conn = sqlite_open(filename, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE, Some(pragmas))
             .map_err(|x| InterpreterError::SqliteError(IncomparableError{ err: x }))?;
 
         Ok(SqliteConnection { conn })
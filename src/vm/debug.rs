@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+
+use vm::contexts::{Environment, LocalContext};
+use vm::hooks::{self, EvalHook, HookSlot};
+use vm::representations::{SymbolicExpression, ClarityName};
+use vm::types::Value;
+
+/// Where the VM should stop.  Contracts are named by their fully-qualified identifiers.
+/// Expression IDs are the ones the parser assigns, and are reported whenever the VM stops.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Breakpoint {
+    /// stop when a contract's function is applied, once its arguments are bound
+    Function { contract: String, function: String },
+    /// stop when a contract's expression is about to be evaluated
+    Expression { contract: String, expression_id: u64 },
+}
+
+/// A request to a debug session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DebugRequest {
+    SetBreakpoint { breakpoint: Breakpoint },
+    ClearBreakpoint { breakpoint: Breakpoint },
+    ListBreakpoints,
+    /// is the VM stopped, and where?
+    Status,
+    /// wait up to `timeout` seconds for the VM to stop
+    Wait { timeout: u64 },
+    /// stop at the next expression
+    Pause,
+    /// resume until the next breakpoint
+    Continue,
+    /// resume until the next expression, including ones in functions the current one calls
+    StepIn,
+    /// resume until the next expression that isn't part of the current one
+    StepOver,
+    /// read one of the current contract's data vars
+    ReadDataVar { name: String },
+    /// read an entry of one of the current contract's maps, given its hex-encoded key
+    ReadMapEntry { map: String, key: String },
+}
+
+/// A debug session's reply to a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum DebugResponse {
+    Ok,
+    Breakpoints { breakpoints: Vec<Breakpoint> },
+    Running,
+    Stopped { stop: DebugStop },
+    Value { value: String },
+    Error { message: String },
+}
+
+/// Where the VM stopped, and what it could see there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebugStop {
+    /// "breakpoint" or "step"
+    pub reason: String,
+    pub contract: String,
+    /// the function being applied, if stopped at a function breakpoint
+    pub function: Option<String>,
+    pub expression_id: u64,
+    pub expression: String,
+    /// the expression's line in the contract source, if known
+    pub line: Option<u32>,
+    /// the functions being applied, outermost first
+    pub call_stack: Vec<String>,
+    /// the variables in scope, and their values
+    pub locals: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepMode {
+    Run,
+    Next,
+    /// stop at the next expression evaluated at this call stack depth or shallower
+    Over(usize),
+}
+
+struct DebugState {
+    breakpoints: Vec<Breakpoint>,
+    step: StepMode,
+    /// where the VM is stopped, and its call stack depth there
+    stopped: Option<(DebugStop, usize)>,
+    /// a read for the stopped VM to do, and its result
+    inspect_request: Option<DebugRequest>,
+    inspect_response: Option<DebugResponse>,
+}
+
+/// A debug session, shared between the thread running the VM and the client controlling it.
+/// When the VM stops, its thread blocks until the client resumes it, and does the client's
+/// data-space reads in the meantime.
+#[derive(Clone)]
+pub struct DebugSession {
+    shared: Arc<(Mutex<DebugState>, Condvar)>,
+}
+
+/// Debug the Clarity code this thread evaluates in the given session
+pub fn attach(session: DebugSession) {
+    hooks::attach_to(HookSlot::Debugger, Box::new(session));
+}
+
+/// Stop debugging the Clarity code this thread evaluates
+pub fn detach() {
+    hooks::detach_from(HookSlot::Debugger);
+}
+
+// the VM blocks in these while it's stopped
+impl EvalHook for DebugSession {
+    fn will_eval(&mut self, env: &mut Environment, context: &LocalContext, expr: &SymbolicExpression) {
+        self.on_eval(expr, None, env, context);
+    }
+
+    fn will_apply_function(&mut self, env: &mut Environment, context: &LocalContext, name: &ClarityName, body: &SymbolicExpression) {
+        self.on_eval(body, Some(name), env, context);
+    }
+}
+
+/// The variables visible from a local context, innermost scope first
+fn collect_locals(context: &LocalContext) -> BTreeMap<String, String> {
+    let mut locals = BTreeMap::new();
+    let mut next = Some(context);
+    while let Some(scope) = next {
+        for (name, value) in scope.variables.iter() {
+            locals.entry(name.to_string()).or_insert_with(|| value.to_string());
+        }
+        next = scope.parent;
+    }
+    locals
+}
+
+/// Do a data-space read for the client, in the current contract
+fn inspect(request: &DebugRequest, env: &mut Environment) -> DebugResponse {
+    let contract_identifier = env.contract_context.contract_identifier.clone();
+    let result = match request {
+        DebugRequest::ReadDataVar { name } => env.global_context.database.lookup_variable(&contract_identifier, name),
+        DebugRequest::ReadMapEntry { map, key } => match Value::try_deserialize_hex_untyped(key) {
+            Ok(key_value) => env.global_context.database.fetch_entry(&contract_identifier, map, &key_value),
+            Err(e) => return DebugResponse::Error { message: format!("Failed to parse map key: {:?}", e) }
+        },
+        _ => return DebugResponse::Error { message: "Not a data-space read".to_string() }
+    };
+
+    match result {
+        Ok(value) => DebugResponse::Value { value: value.to_string() },
+        Err(e) => DebugResponse::Error { message: format!("{:?}", e) }
+    }
+}
+
+impl Breakpoint {
+    fn matches(&self, contract: &str, exp: &SymbolicExpression, function: Option<&ClarityName>) -> bool {
+        match (self, function) {
+            (Breakpoint::Function { contract: bp_contract, function: bp_function }, Some(function)) =>
+                bp_contract == contract && bp_function.as_str() == function.as_str(),
+            (Breakpoint::Expression { contract: bp_contract, expression_id }, None) =>
+                bp_contract == contract && *expression_id == exp.id,
+            _ => false
+        }
+    }
+}
+
+impl DebugState {
+    fn status(&self) -> DebugResponse {
+        match self.stopped {
+            Some((ref stop, _)) => DebugResponse::Stopped { stop: stop.clone() },
+            None => DebugResponse::Running
+        }
+    }
+}
+
+impl DebugSession {
+    pub fn new() -> DebugSession {
+        DebugSession {
+            shared: Arc::new((Mutex::new(DebugState {
+                breakpoints: vec![],
+                step: StepMode::Run,
+                stopped: None,
+                inspect_request: None,
+                inspect_response: None,
+            }), Condvar::new()))
+        }
+    }
+
+    /// Handle a client's request.  Resuming and reading the data space only work while the VM
+    /// is stopped.
+    pub fn request(&self, request: DebugRequest) -> DebugResponse {
+        let (ref lock, ref cvar) = *self.shared;
+        let mut state = lock.lock().expect("FATAL: debug session lock poisoned");
+
+        match request {
+            DebugRequest::SetBreakpoint { breakpoint } => {
+                if !state.breakpoints.contains(&breakpoint) {
+                    state.breakpoints.push(breakpoint);
+                }
+                DebugResponse::Ok
+            },
+            DebugRequest::ClearBreakpoint { breakpoint } => {
+                state.breakpoints.retain(|bp| *bp != breakpoint);
+                DebugResponse::Ok
+            },
+            DebugRequest::ListBreakpoints => DebugResponse::Breakpoints { breakpoints: state.breakpoints.clone() },
+            DebugRequest::Status => state.status(),
+            DebugRequest::Wait { timeout } => {
+                let deadline = Instant::now() + Duration::from_secs(timeout);
+                while state.stopped.is_none() {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    state = cvar.wait_timeout(state, deadline - now).expect("FATAL: debug session lock poisoned").0;
+                }
+                state.status()
+            },
+            DebugRequest::Pause => {
+                state.step = StepMode::Next;
+                DebugResponse::Ok
+            },
+            DebugRequest::Continue | DebugRequest::StepIn | DebugRequest::StepOver => {
+                let depth = match state.stopped.take() {
+                    Some((_, depth)) => depth,
+                    None => return DebugResponse::Error { message: "Not stopped".to_string() }
+                };
+                state.step = match request {
+                    DebugRequest::StepIn => StepMode::Next,
+                    DebugRequest::StepOver => StepMode::Over(depth),
+                    _ => StepMode::Run
+                };
+                cvar.notify_all();
+                DebugResponse::Ok
+            },
+            DebugRequest::ReadDataVar { .. } | DebugRequest::ReadMapEntry { .. } => {
+                if state.stopped.is_none() {
+                    return DebugResponse::Error { message: "Not stopped".to_string() };
+                }
+
+                // the VM's thread does the read
+                state.inspect_response = None;
+                state.inspect_request = Some(request);
+                cvar.notify_all();
+
+                while state.inspect_response.is_none() && state.stopped.is_some() {
+                    state = cvar.wait(state).expect("FATAL: debug session lock poisoned");
+                }
+                state.inspect_response.take()
+                    .unwrap_or(DebugResponse::Error { message: "Resumed before the read was done".to_string() })
+            }
+        }
+    }
+
+    /// Stop the VM here if a breakpoint or a step says to, and block until the client resumes
+    /// it.  With `function` set, only function breakpoints are checked -- stepping stops on
+    /// the function's body once it's evaluated.
+    fn on_eval(&self, exp: &SymbolicExpression, function: Option<&ClarityName>, env: &mut Environment, context: &LocalContext) {
+        let (ref lock, ref cvar) = *self.shared;
+        let mut state = lock.lock().expect("FATAL: debug session lock poisoned");
+
+        let contract = env.contract_context.contract_identifier.to_string();
+        let depth = env.call_stack.depth();
+
+        let stepped = match (state.step, function) {
+            (StepMode::Next, None) => true,
+            (StepMode::Over(stop_depth), None) => depth <= stop_depth,
+            _ => false
+        };
+
+        let reason =
+            if stepped {
+                "step"
+            }
+            else if state.breakpoints.iter().any(|bp| bp.matches(&contract, exp, function)) {
+                "breakpoint"
+            }
+            else {
+                return;
+            };
+
+        let stop = DebugStop {
+            reason: reason.to_string(),
+            contract: contract,
+            function: function.map(|f| f.to_string()),
+            expression_id: exp.id,
+            expression: exp.to_string(),
+            line: exp.start_line(),
+            call_stack: env.call_stack.frames().iter().map(|f| f.to_string()).collect(),
+            locals: collect_locals(context),
+        };
+
+        debug!("Clarity debugger: stopped at {} ({}): {}", &stop.expression_id, &stop.reason, &stop.expression);
+
+        state.step = StepMode::Run;
+        state.stopped = Some((stop, depth));
+        cvar.notify_all();
+
+        while state.stopped.is_some() {
+            if let Some(request) = state.inspect_request.take() {
+                state.inspect_response = Some(inspect(&request, env));
+                cvar.notify_all();
+            }
+            state = cvar.wait(state).expect("FATAL: debug session lock poisoned");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+    use serde_json;
+
+    use vm::contexts::OwnedEnvironment;
+    use vm::database::MemoryBackingStore;
+    use vm::tests::symbols_from_values;
+    use vm::types::{QualifiedContractIdentifier, PrincipalData, StandardPrincipalData};
+
+    fn expect_stop(response: DebugResponse) -> DebugStop {
+        match response {
+            DebugResponse::Stopped { stop } => stop,
+            _ => panic!("Expected the VM to stop, got {:?}", &response)
+        }
+    }
+
+    #[test]
+    fn test_debug_session_function_breakpoint_and_steps() {
+        let contract = "(define-data-var total int 0)
+                        (define-private (bump (a int) (b int)) (+ a b))
+                        (define-public (run (x int))
+                            (begin
+                                (var-set total (bump x 2))
+                                (ok (var-get total))))";
+
+        let contract_identifier = QualifiedContractIdentifier::local("debugged").unwrap();
+        let session = DebugSession::new();
+
+        assert_eq!(session.request(DebugRequest::Status), DebugResponse::Running);
+        assert_eq!(session.request(DebugRequest::Continue), DebugResponse::Error { message: "Not stopped".to_string() });
+
+        let breakpoint = Breakpoint::Function { contract: contract_identifier.to_string(), function: "bump".to_string() };
+        assert_eq!(session.request(DebugRequest::SetBreakpoint { breakpoint: breakpoint.clone() }), DebugResponse::Ok);
+        assert_eq!(session.request(DebugRequest::ListBreakpoints), DebugResponse::Breakpoints { breakpoints: vec![breakpoint.clone()] });
+
+        let vm_session = session.clone();
+        let vm_contract_identifier = contract_identifier.clone();
+        let vm_thread = thread::spawn(move || {
+            attach(vm_session);
+
+            let mut marf = MemoryBackingStore::new();
+            let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+            owned_env.initialize_contract(vm_contract_identifier.clone(), contract).unwrap();
+
+            let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+            let (result, _, _) = owned_env.execute_transaction(sender, vm_contract_identifier, "run", &symbols_from_values(vec![Value::Int(40)])).unwrap();
+
+            detach();
+            result
+        });
+
+        // stopped in bump(), with its arguments bound
+        let stop = expect_stop(session.request(DebugRequest::Wait { timeout: 30 }));
+        assert_eq!(stop.reason, "breakpoint");
+        assert_eq!(stop.contract, contract_identifier.to_string());
+        assert_eq!(stop.function, Some("bump".to_string()));
+        assert_eq!(stop.locals.get("a"), Some(&"40".to_string()));
+        assert_eq!(stop.locals.get("b"), Some(&"2".to_string()));
+        assert!(stop.call_stack.iter().any(|frame| frame.ends_with(":bump")));
+
+        assert_eq!(session.request(DebugRequest::ReadDataVar { name: "total".to_string() }), DebugResponse::Value { value: "0".to_string() });
+        match session.request(DebugRequest::ReadDataVar { name: "no-such-var".to_string() }) {
+            DebugResponse::Error { .. } => {},
+            x => panic!("Expected an error reading a missing data var, got {:?}", &x)
+        }
+
+        // step into bump()'s body
+        assert_eq!(session.request(DebugRequest::StepIn), DebugResponse::Ok);
+        let body_stop = expect_stop(session.request(DebugRequest::Wait { timeout: 30 }));
+        assert_eq!(body_stop.reason, "step");
+        assert_eq!(body_stop.function, None);
+        assert!(body_stop.expression.contains("+"));
+
+        // step over the rest of bump(), and the var-set that called it
+        assert_eq!(session.request(DebugRequest::StepOver), DebugResponse::Ok);
+        let next_stop = expect_stop(session.request(DebugRequest::Wait { timeout: 30 }));
+        assert_eq!(next_stop.reason, "step");
+        assert!(next_stop.expression.contains("ok"));
+        assert!(next_stop.call_stack.len() < body_stop.call_stack.len());
+        assert_eq!(session.request(DebugRequest::ReadDataVar { name: "total".to_string() }), DebugResponse::Value { value: "42".to_string() });
+
+        assert_eq!(session.request(DebugRequest::Continue), DebugResponse::Ok);
+        let result = vm_thread.join().unwrap();
+        assert_eq!(result, Value::okay(Value::Int(42)).unwrap());
+        assert_eq!(session.request(DebugRequest::Status), DebugResponse::Running);
+    }
+
+    #[test]
+    fn test_debug_session_not_attached() {
+        // other threads' VMs aren't affected by a session
+        let session = DebugSession::new();
+        session.request(DebugRequest::Pause);
+
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+        let contract_identifier = QualifiedContractIdentifier::local("undebugged").unwrap();
+        owned_env.initialize_contract(contract_identifier.clone(), "(define-read-only (get-one) 1)").unwrap();
+        let (result, _, _) = owned_env.eval_read_only(&contract_identifier, "(get-one)").unwrap();
+        assert_eq!(result, Value::Int(1));
+
+        assert_eq!(session.request(DebugRequest::Status), DebugResponse::Running);
+    }
+
+    #[test]
+    fn test_debug_requests_json() {
+        let request : DebugRequest = serde_json::from_str(r#"{"command":"set_breakpoint","breakpoint":{"type":"expression","contract":"S1G2081040G2081040G2081040G208105NK8PE5.debugged","expression_id":7}}"#).unwrap();
+        assert_eq!(request, DebugRequest::SetBreakpoint { breakpoint: Breakpoint::Expression { contract: "S1G2081040G2081040G2081040G208105NK8PE5.debugged".to_string(), expression_id: 7 } });
+
+        let request : DebugRequest = serde_json::from_str(r#"{"command":"step_over"}"#).unwrap();
+        assert_eq!(request, DebugRequest::StepOver);
+
+        assert_eq!(serde_json::to_string(&DebugResponse::Running).unwrap(), r#"{"result":"running"}"#);
+    }
+}
@@ -157,16 +157,16 @@ pub fn native_ge(a: Value, b: Value) -> InterpreterResult<Value> {
 pub fn native_le(a: Value, b: Value) -> InterpreterResult<Value> {
     type_force_binary_arithmetic!(less, a, b)
 }
-pub fn native_add(mut args: Vec<Value>) -> InterpreterResult<Value> {
+pub fn native_add(args: &mut Vec<Value>) -> InterpreterResult<Value> {
     type_force_variadic_arithmetic!(add, args)
 }
-pub fn native_sub(mut args: Vec<Value>) -> InterpreterResult<Value> {
+pub fn native_sub(args: &mut Vec<Value>) -> InterpreterResult<Value> {
     type_force_variadic_arithmetic!(sub, args)
 }
-pub fn native_mul(mut args: Vec<Value>) -> InterpreterResult<Value> {
+pub fn native_mul(args: &mut Vec<Value>) -> InterpreterResult<Value> {
     type_force_variadic_arithmetic!(mul, args)
 }
-pub fn native_div(mut args: Vec<Value>) -> InterpreterResult<Value> {
+pub fn native_div(args: &mut Vec<Value>) -> InterpreterResult<Value> {
     type_force_variadic_arithmetic!(div, args)
 }
 pub fn native_pow(a: Value, b: Value) -> InterpreterResult<Value> {
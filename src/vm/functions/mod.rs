@@ -173,7 +173,7 @@ pub fn lookup_reserved_functions(name: &str) -> Option<CallableType> {
     }
 }
 
-fn native_eq(args: Vec<Value>) -> Result<Value> {
+fn native_eq(args: &mut Vec<Value>) -> Result<Value> {
     // TODO: this currently uses the derived equality checks of Value,
     //   however, that's probably not how we want to implement equality
     //   checks on the ::ListTypes
@@ -215,7 +215,7 @@ native_hash_func!(native_sha512, hash::Sha512Sum);
 native_hash_func!(native_sha512trunc256, hash::Sha512Trunc256Sum);
 native_hash_func!(native_keccak256, hash::Keccak256Hash);
 
-fn native_begin(mut args: Vec<Value>) -> Result<Value> {
+fn native_begin(args: &mut Vec<Value>) -> Result<Value> {
     match args.pop() {
         Some(v) => Ok(v),
         None => Err(CheckErrors::RequiresAtLeastArguments(1,0).into())
@@ -0,0 +1,440 @@
+use std::convert::TryFrom;
+
+use arbitrary::{Arbitrary, Unstructured, Error as ArbitraryError, Result as ArbitraryResult};
+
+use vm::analysis::{type_check, run_analysis_with_constant_folding};
+use vm::ast::build_ast;
+use vm::ast::types::ContractAST;
+use vm::contexts::OwnedEnvironment;
+use vm::costs::{ExecutionCost, LimitedCostTracker};
+use vm::errors::InterpreterResult;
+use vm::database::{ClaritySerializable, MemoryBackingStore};
+use vm::representations::{ClarityName, SymbolicExpression};
+use vm::types::{Value, TypeSignature, TupleData, PrincipalData, StandardPrincipalData, QualifiedContractIdentifier};
+
+/*
+
+Entry points for fuzzing the Clarity VM, built with the `fuzzing` feature.  The cargo-fuzz
+targets in `fuzz/` feed these with bytes from libFuzzer; the generators here turn those bytes
+into Clarity values and contracts, so that the fuzzer spends its time on code that gets past
+the parser.
+
+Each `check_*` function panics if it finds a bug: the VM panicking, a value that doesn't survive
+being serialized, or a contract that costs something different to run depending on whether its
+constant expressions were folded.  Everything else, like a contract that doesn't type-check or
+runs out of budget, is an expected outcome.
+
+*/
+
+// how deep generated values and expressions nest
+const MAX_DEPTH: u32 = 3;
+// how many elements a generated list, tuple or buffer has at most
+const MAX_ELEMENTS: usize = 5;
+// how many functions a generated contract defines at most
+const MAX_FUNCTIONS: usize = 4;
+// the runtime budget a generated contract gets at most
+const MAX_RUNTIME: u64 = 1_000_000;
+
+/// A Clarity value, generated from fuzzer input
+#[derive(Debug, Clone)]
+pub struct ArbitraryValue(pub Value);
+
+impl Arbitrary for ArbitraryValue {
+    fn arbitrary(u: &mut Unstructured) -> ArbitraryResult<Self> {
+        arbitrary_value(u, MAX_DEPTH).map(ArbitraryValue)
+    }
+}
+
+// values that can't be constructed (e.g., a list whose elements don't share a type) are
+//  rejected as badly formatted input
+fn checked(value: InterpreterResult<Value>) -> ArbitraryResult<Value> {
+    value.map_err(|_| ArbitraryError::IncorrectFormat)
+}
+
+fn arbitrary_value(u: &mut Unstructured, depth: u32) -> ArbitraryResult<Value> {
+    let max_kind = if depth == 0 { 4 } else { 10 };
+    let value = match u.int_in_range(0..=max_kind)? {
+        0 => Value::Int(i128::arbitrary(u)?),
+        1 => Value::UInt(u128::arbitrary(u)?),
+        2 => Value::Bool(bool::arbitrary(u)?),
+        3 => {
+            let len = u.int_in_range(0..=MAX_ELEMENTS)?;
+            let mut bytes = vec![0u8; len];
+            u.fill_buffer(&mut bytes)?;
+            checked(Value::buff_from(bytes))?
+        },
+        4 => Value::Principal(PrincipalData::Standard(StandardPrincipalData(u.int_in_range(0..=31)?, <[u8; 20]>::arbitrary(u)?))),
+        5 => {
+            let len = u.int_in_range(0..=MAX_ELEMENTS)?;
+            // lists are homogeneous, so all of their elements come from the first one's kind
+            let first = arbitrary_value(u, depth - 1)?;
+            let mut items = vec![];
+            for _ in 0..len {
+                items.push(arbitrary_value_like(u, &first)?);
+            }
+            checked(Value::list_from(items))?
+        },
+        6 => {
+            let len = u.int_in_range(1..=MAX_ELEMENTS)?;
+            let mut fields = vec![];
+            for i in 0..len {
+                let name = ClarityName::try_from(format!("f{}", i)).expect("valid field name");
+                fields.push((name, arbitrary_value(u, depth - 1)?));
+            }
+            Value::Tuple(TupleData::from_data(fields).map_err(|_| ArbitraryError::IncorrectFormat)?)
+        },
+        7 => checked(Value::some(arbitrary_value(u, depth - 1)?))?,
+        8 => Value::none(),
+        9 => checked(Value::okay(arbitrary_value(u, depth - 1)?))?,
+        _ => checked(Value::error(arbitrary_value(u, depth - 1)?))?,
+    };
+    Ok(value)
+}
+
+// a value of the same kind as the given one, for the elements of a list
+fn arbitrary_value_like(u: &mut Unstructured, like: &Value) -> ArbitraryResult<Value> {
+    let value = match like {
+        Value::Int(_) => Value::Int(i128::arbitrary(u)?),
+        Value::UInt(_) => Value::UInt(u128::arbitrary(u)?),
+        Value::Bool(_) => Value::Bool(bool::arbitrary(u)?),
+        _ => like.clone()
+    };
+    Ok(value)
+}
+
+/// The Clarity types generated contracts work with
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    Int,
+    UInt,
+    Bool,
+}
+
+impl ValueType {
+    fn choose(u: &mut Unstructured) -> ArbitraryResult<ValueType> {
+        Ok(*u.choose(&[ValueType::Int, ValueType::UInt, ValueType::Bool])?)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ValueType::Int => "int",
+            ValueType::UInt => "uint",
+            ValueType::Bool => "bool",
+        }
+    }
+
+    fn arbitrary_value(&self, u: &mut Unstructured) -> ArbitraryResult<Value> {
+        let value = match self {
+            ValueType::Int => Value::Int(i128::arbitrary(u)?),
+            ValueType::UInt => Value::UInt(u128::arbitrary(u)?),
+            ValueType::Bool => Value::Bool(bool::arbitrary(u)?),
+        };
+        Ok(value)
+    }
+}
+
+/// A call to one of a generated contract's functions
+#[derive(Debug, Clone)]
+pub struct ArbitraryCall {
+    pub function: String,
+    pub args: Vec<Value>,
+    pub read_only: bool,
+}
+
+/// A Clarity contract, generated from fuzzer input, along with calls to make to it and the
+/// runtime budget to run them with.  Its expressions are well-typed, except now and then a
+/// public function sets a data var to a value of the wrong type, for the type checker.
+#[derive(Debug, Clone)]
+pub struct ArbitraryContract {
+    pub source: String,
+    pub calls: Vec<ArbitraryCall>,
+    pub runtime_limit: u64,
+}
+
+// what's in scope while generating an expression
+struct Scope {
+    vars: Vec<(String, ValueType)>,
+    locals: Vec<(String, ValueType)>,
+    next_local: usize,
+}
+
+impl Arbitrary for ArbitraryContract {
+    fn arbitrary(u: &mut Unstructured) -> ArbitraryResult<Self> {
+        let mut scope = Scope { vars: vec![], locals: vec![], next_local: 0 };
+        let mut source = String::new();
+
+        for (i, var_type) in [ValueType::Int, ValueType::UInt, ValueType::Bool].iter().enumerate() {
+            let name = format!("v{}", i);
+            let initial = arbitrary_expression(u, &mut scope, *var_type, MAX_DEPTH)?;
+            source.push_str(&format!("(define-data-var {} {} {})\n", name, var_type.name(), initial));
+            scope.vars.push((name, *var_type));
+        }
+
+        let mut calls = vec![];
+        for i in 0..u.int_in_range(1..=MAX_FUNCTIONS)? {
+            let name = format!("f{}", i);
+            let read_only = bool::arbitrary(u)?;
+
+            let mut params = vec![];
+            let mut args = vec![];
+            for j in 0..u.int_in_range(0..=3)? {
+                let param_type = ValueType::choose(u)?;
+                params.push(format!("(a{} {})", j, param_type.name()));
+                scope.locals.push((format!("a{}", j), param_type));
+                args.push(param_type.arbitrary_value(u)?);
+            }
+
+            let result = arbitrary_expression(u, &mut scope, ValueType::choose(u)?, MAX_DEPTH)?;
+            let body = if read_only {
+                result
+            } else {
+                let (var, mut var_type) = u.choose(&scope.vars)?.clone();
+                // now and then, a value of the wrong type, for the type checker
+                if u.int_in_range(0..=7)? == 0 {
+                    var_type = ValueType::choose(u)?;
+                }
+                let value = arbitrary_expression(u, &mut scope, var_type, MAX_DEPTH)?;
+                format!("(begin (var-set {} {}) (ok {}))", var, value, result)
+            };
+            scope.locals.clear();
+
+            let define = if read_only { "define-read-only" } else { "define-public" };
+            source.push_str(&format!("({} ({} {}) {})\n", define, name, params.join(" "), body));
+            calls.push(ArbitraryCall { function: name, args, read_only });
+        }
+
+        let runtime_limit = u.int_in_range(0..=MAX_RUNTIME)?;
+        Ok(ArbitraryContract { source, calls, runtime_limit })
+    }
+}
+
+fn arbitrary_literal(u: &mut Unstructured, value_type: ValueType) -> ArbitraryResult<String> {
+    let literal = match value_type {
+        // small numbers, so that arithmetic doesn't always overflow
+        ValueType::Int => format!("{}", i8::arbitrary(u)?),
+        ValueType::UInt => format!("u{}", u8::arbitrary(u)?),
+        ValueType::Bool => format!("{}", bool::arbitrary(u)?),
+    };
+    Ok(literal)
+}
+
+fn arbitrary_expression(u: &mut Unstructured, scope: &mut Scope, value_type: ValueType, depth: u32) -> ArbitraryResult<String> {
+    if depth == 0 {
+        return arbitrary_leaf(u, scope, value_type);
+    }
+
+    let expr = match u.int_in_range(0..=7)? {
+        0 | 1 => arbitrary_leaf(u, scope, value_type)?,
+        2 => {
+            let condition = arbitrary_expression(u, scope, ValueType::Bool, depth - 1)?;
+            let then_branch = arbitrary_expression(u, scope, value_type, depth - 1)?;
+            let else_branch = arbitrary_expression(u, scope, value_type, depth - 1)?;
+            format!("(if {} {} {})", condition, then_branch, else_branch)
+        },
+        3 => {
+            let name = format!("l{}", scope.next_local);
+            scope.next_local += 1;
+            let bound_type = ValueType::choose(u)?;
+            let bound = arbitrary_expression(u, scope, bound_type, depth - 1)?;
+            scope.locals.push((name.clone(), bound_type));
+            let body = arbitrary_expression(u, scope, value_type, depth - 1)?;
+            scope.locals.pop();
+            format!("(let (({} {})) {})", name, bound, body)
+        },
+        4 => {
+            let inner = arbitrary_expression(u, scope, value_type, depth - 1)?;
+            let default = arbitrary_expression(u, scope, value_type, depth - 1)?;
+            match u.int_in_range(0..=2)? {
+                0 => format!("(unwrap-panic (some {}))", inner),
+                1 => format!("(default-to {} (if {} (some {}) none))", default, bool::arbitrary(u)?, inner),
+                _ => format!("(get f0 (tuple (f0 {}) (f1 {})))", inner, default),
+            }
+        },
+        _ => arbitrary_application(u, scope, value_type, depth)?,
+    };
+    Ok(expr)
+}
+
+// a literal, or a variable in scope of the right type
+fn arbitrary_leaf(u: &mut Unstructured, scope: &Scope, value_type: ValueType) -> ArbitraryResult<String> {
+    let locals : Vec<&String> = scope.locals.iter()
+        .filter(|(_, local_type)| *local_type == value_type)
+        .map(|(name, _)| name)
+        .collect();
+    let var = scope.vars.iter().find(|(_, var_type)| *var_type == value_type);
+
+    let leaf = match u.int_in_range(0..=2)? {
+        0 if !locals.is_empty() => u.choose(&locals)?.to_string(),
+        1 if var.is_some() => format!("(var-get {})", var.expect("checked above").0),
+        _ => arbitrary_literal(u, value_type)?
+    };
+    Ok(leaf)
+}
+
+fn arbitrary_application(u: &mut Unstructured, scope: &mut Scope, value_type: ValueType, depth: u32) -> ArbitraryResult<String> {
+    let expr = match value_type {
+        ValueType::Int | ValueType::UInt => {
+            match u.int_in_range(0..=2)? {
+                0 => {
+                    // `len` and `to-uint`/`to-int` cross between the number types
+                    let other = if value_type == ValueType::Int { ValueType::UInt } else { ValueType::Int };
+                    let converted = arbitrary_expression(u, scope, other, depth - 1)?;
+                    if value_type == ValueType::Int { format!("(to-int {})", converted) } else { format!("(to-uint {})", converted) }
+                },
+                1 if value_type == ValueType::UInt => {
+                    let mut items = vec![];
+                    for _ in 0..u.int_in_range(0..=MAX_ELEMENTS)? {
+                        items.push(arbitrary_expression(u, scope, ValueType::Int, depth - 1)?);
+                    }
+                    format!("(len (list {}))", items.join(" "))
+                },
+                _ => {
+                    let op = u.choose(&["+", "-", "*", "/", "mod", "pow"])?;
+                    let a = arbitrary_expression(u, scope, value_type, depth - 1)?;
+                    let b = arbitrary_expression(u, scope, value_type, depth - 1)?;
+                    format!("({} {} {})", op, a, b)
+                }
+            }
+        },
+        ValueType::Bool => {
+            match u.int_in_range(0..=2)? {
+                0 => {
+                    let compared = *u.choose(&[ValueType::Int, ValueType::UInt])?;
+                    let op = u.choose(&["<", "<=", ">", ">=", "is-eq"])?;
+                    let a = arbitrary_expression(u, scope, compared, depth - 1)?;
+                    let b = arbitrary_expression(u, scope, compared, depth - 1)?;
+                    format!("({} {} {})", op, a, b)
+                },
+                1 => format!("(not {})", arbitrary_expression(u, scope, ValueType::Bool, depth - 1)?),
+                _ => {
+                    let op = u.choose(&["and", "or"])?;
+                    let a = arbitrary_expression(u, scope, ValueType::Bool, depth - 1)?;
+                    let b = arbitrary_expression(u, scope, ValueType::Bool, depth - 1)?;
+                    format!("({} {} {})", op, a, b)
+                }
+            }
+        }
+    };
+    Ok(expr)
+}
+
+fn contract_identifier() -> QualifiedContractIdentifier {
+    QualifiedContractIdentifier::local("fuzzed").expect("valid contract name")
+}
+
+/// Parse Clarity source.  It may fail to parse, but the parser must not panic.
+pub fn check_parse(source: &str) {
+    let _ = build_ast(&contract_identifier(), source, &mut ());
+}
+
+/// Parse and type-check Clarity source.  It may fail either, but neither may panic.
+pub fn check_type_check(source: &str) {
+    if let Ok(mut contract_ast) = build_ast(&contract_identifier(), source, &mut ()) {
+        let mut marf = MemoryBackingStore::new();
+        let _ = type_check(&contract_identifier(), &mut contract_ast.expressions, &mut marf.as_analysis_db(), false);
+    }
+}
+
+// deploy a contract and make its calls with the given budget, returning what each step came
+//  to and what it had cost so far
+fn run(contract_ast: &ContractAST, contract: &ArbitraryContract) -> Vec<(String, ExecutionCost)> {
+    let mut limit = ExecutionCost::max_value();
+    limit.runtime = contract.runtime_limit;
+
+    let mut marf = MemoryBackingStore::new();
+    let mut owned_env = OwnedEnvironment::new_cost_limited(marf.as_clarity_db(), LimitedCostTracker::new(limit));
+    let contract_identifier = contract_identifier();
+    let sender = Value::from(contract_identifier.issuer.clone());
+
+    let mut outcomes = vec![];
+    let deployed = owned_env.initialize_contract_from_ast(contract_identifier.clone(), contract_ast, &contract.source);
+    outcomes.push((format!("{:?}", deployed.as_ref().map(|_| ())), owned_env.get_exec_environment(None).global_context.cost_track.get_total()));
+    if deployed.is_err() {
+        return outcomes;
+    }
+
+    for call in contract.calls.iter() {
+        let args : Vec<SymbolicExpression> = call.args.iter().map(|arg| SymbolicExpression::atom_value(arg.clone())).collect();
+        let result = owned_env.execute_in_env(sender.clone(), |env| {
+            env.execute_contract(&contract_identifier, &call.function, &args, call.read_only)
+        });
+        outcomes.push((format!("{:?}", result.map(|(value, _, _)| value)), owned_env.get_exec_environment(None).global_context.cost_track.get_total()));
+    }
+    outcomes
+}
+
+/// Deploy a contract that type-checks and make its calls, once as analyzed (with its constant
+/// expressions folded) and once as parsed.  Both must evaluate to the same thing and cost the
+/// same, step for step, including running out of budget at the same point.
+pub fn check_eval(contract: &ArbitraryContract) {
+    let mut analyzed = match build_ast(&contract_identifier(), &contract.source, &mut ()) {
+        Ok(contract_ast) => contract_ast,
+        Err(_) => return
+    };
+    let mut marf = MemoryBackingStore::new();
+    if run_analysis_with_constant_folding(&contract_identifier(), &mut analyzed.expressions, &mut marf.as_analysis_db(),
+                                          false, LimitedCostTracker::new_max_limit()).is_err() {
+        return;
+    }
+    let parsed = build_ast(&contract_identifier(), &contract.source, &mut ())
+        .expect("contract parsed once, but not twice");
+
+    assert_eq!(run(&analyzed, contract), run(&parsed, contract),
+               "analyzed and parsed contract diverged:\n{}", contract.source);
+}
+
+/// Serialize a value and deserialize it again, both with and without its type.  It must come
+/// back the same.
+pub fn check_value_serialization(value: &Value) {
+    let serialized = value.serialize();
+    assert_eq!(&Value::try_deserialize_hex_untyped(&serialized).expect("failed to deserialize value"), value);
+    assert_eq!(&Value::try_deserialize_hex(&serialized, &TypeSignature::type_of(value)).expect("failed to deserialize typed value"), value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // fuzzer input, from a fixed pattern
+    fn input(seed: u8) -> Vec<u8> {
+        (0..4096u32).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    #[test]
+    fn test_fuzz_entry_points() {
+        for seed in 0..32u8 {
+            let bytes = input(seed);
+
+            let value = ArbitraryValue::arbitrary(&mut Unstructured::new(&bytes));
+            if let Ok(ArbitraryValue(value)) = value {
+                check_value_serialization(&value);
+            }
+
+            let contract = ArbitraryContract::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            check_parse(&contract.source);
+            check_type_check(&contract.source);
+            check_eval(&contract);
+        }
+
+        check_parse("(define-public (f) (ok (list 1 2");
+        check_parse("\u{0}(((");
+        check_type_check("(define-read-only (f) (+ 1 u1))");
+    }
+
+    #[test]
+    fn test_generated_contracts_run() {
+        // generated contracts should get past the type checker, or the eval target is only
+        //  fuzzing the type checker
+        let mut checked = 0;
+        for seed in 0..32u8 {
+            let bytes = input(seed);
+            let contract = ArbitraryContract::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let mut contract_ast = build_ast(&contract_identifier(), &contract.source, &mut ()).unwrap();
+            let mut marf = MemoryBackingStore::new();
+            if type_check(&contract_identifier(), &mut contract_ast.expressions, &mut marf.as_analysis_db(), false).is_ok() {
+                checked += 1;
+            }
+        }
+        assert!(checked > 0, "none of the generated contracts type-checked");
+    }
+}
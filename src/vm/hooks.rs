@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+
+use vm::ast::ContractAST;
+use vm::contexts::{Environment, LocalContext};
+use vm::errors::InterpreterResult as Result;
+use vm::representations::{SymbolicExpression, ClarityName};
+use vm::types::{Value, QualifiedContractIdentifier};
+
+/// Callbacks an embedder gets from the VM as it evaluates Clarity code, one expression at a
+/// time.  Evaluation waits on each callback, so a hook can step through a contract by blocking
+/// in `will_eval` until it's told to go on (as the debugger does), or follow along to analyze
+/// what runs.  Every callback does nothing by default.
+///
+/// A hook is attached to a thread, and sees everything that thread's VM evaluates.  It isn't
+/// called back while one of its callbacks is running, so it can evaluate or read through the
+/// environment it's given without seeing its own work.  A hook that needs to report what it saw
+/// should share its state with whatever attached it, e.g. through an `Rc<RefCell<...>>`.
+///
+/// The debugger (`vm::debug`) and coverage collection (`vm::coverage`) are hooks too, each in
+/// a slot of its own, so they can be attached alongside an embedder's hook.
+pub trait EvalHook {
+    /// Called before the VM evaluates an expression
+    fn will_eval(&mut self, _env: &mut Environment, _context: &LocalContext, _expr: &SymbolicExpression) {}
+
+    /// Called before the VM evaluates a user function's body, once its arguments are bound
+    fn will_apply_function(&mut self, _env: &mut Environment, _context: &LocalContext, _name: &ClarityName, _body: &SymbolicExpression) {}
+
+    /// Called once the VM has evaluated an expression, with what it evaluated to
+    fn did_eval(&mut self, _env: &mut Environment, _context: &LocalContext, _expr: &SymbolicExpression, _result: &Result<Value>) {}
+
+    /// Called when the VM reads a key from its database, with the serialized value it got.
+    /// Reads of values written earlier in the same transaction are included.
+    fn did_read(&mut self, _key: &str, _value: Option<&str>) {}
+
+    /// Called when the VM writes a serialized value to its database.  The write is only kept
+    /// if what made it commits: e.g., a public function that returns an `err` rolls its writes back.
+    fn did_write(&mut self, _key: &str, _value: &str) {}
+
+    /// Called once the VM has deployed a contract
+    fn did_initialize_contract(&mut self, _contract_identifier: &QualifiedContractIdentifier, _contract_ast: &ContractAST) {}
+}
+
+/// Where a hook is attached.  Each slot holds one hook, and the VM calls them back in this order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookSlot {
+    /// the debugger's session
+    Debugger,
+    /// the embedder's hook
+    Embedder,
+    /// the coverage collector
+    Coverage,
+}
+
+const HOOK_SLOTS: [HookSlot; 3] = [HookSlot::Debugger, HookSlot::Embedder, HookSlot::Coverage];
+
+// the hooks attached to this thread's VM, by slot
+thread_local!(static EVAL_HOOKS: RefCell<[Option<Box<dyn EvalHook>>; 3]> = RefCell::new([None, None, None]));
+
+/// Call back the given hook for the Clarity code this thread evaluates, instead of the one
+/// attached before it, if any
+pub fn attach(hook: Box<dyn EvalHook>) {
+    attach_to(HookSlot::Embedder, hook);
+}
+
+/// Stop calling back this thread's hook, and give it back
+pub fn detach() -> Option<Box<dyn EvalHook>> {
+    detach_from(HookSlot::Embedder)
+}
+
+/// Attach a hook to one of this thread's slots, instead of the one attached there before it
+pub fn attach_to(slot: HookSlot, hook: Box<dyn EvalHook>) {
+    EVAL_HOOKS.with(|h| h.borrow_mut()[slot as usize] = Some(hook));
+}
+
+/// Detach the hook in one of this thread's slots, and give it back
+pub fn detach_from(slot: HookSlot) -> Option<Box<dyn EvalHook>> {
+    EVAL_HOOKS.with(|h| h.borrow_mut()[slot as usize].take())
+}
+
+/// Is any hook attached to this thread's VM?  A hook isn't, while it's being called back.
+pub fn is_attached() -> bool {
+    EVAL_HOOKS.with(|h| h.borrow().iter().any(|hook| hook.is_some()))
+}
+
+/// Run a callback on each attached hook, in slot order.  Each hook is taken out of its slot
+/// while the callback runs, so that the VM doesn't call it back re-entrantly.
+fn with_hooks<F>(mut callback: F) where F: FnMut(&mut dyn EvalHook) {
+    for slot in HOOK_SLOTS.iter() {
+        let hook = EVAL_HOOKS.with(|h| h.borrow_mut()[*slot as usize].take());
+        if let Some(mut hook) = hook {
+            callback(&mut *hook);
+            EVAL_HOOKS.with(|h| {
+                let mut hooks = h.borrow_mut();
+                let held = &mut hooks[*slot as usize];
+                // the callback may have attached another hook, which stays
+                if held.is_none() {
+                    *held = Some(hook);
+                }
+            });
+        }
+    }
+}
+
+/// Called by the VM before it evaluates an expression
+pub fn will_eval(expr: &SymbolicExpression, env: &mut Environment, context: &LocalContext) {
+    with_hooks(|hook| hook.will_eval(env, context, expr));
+}
+
+/// Called by the VM once it has evaluated an expression
+pub fn did_eval(expr: &SymbolicExpression, env: &mut Environment, context: &LocalContext, result: &Result<Value>) {
+    with_hooks(|hook| hook.did_eval(env, context, expr, result));
+}
+
+/// Called by the VM's database when it reads a key
+pub fn did_read(key: &str, value: Option<&str>) {
+    with_hooks(|hook| hook.did_read(key, value));
+}
+
+/// Called by the VM's database when it writes a key
+pub fn did_write(key: &str, value: &str) {
+    with_hooks(|hook| hook.did_write(key, value));
+}
+
+/// Called by the VM before it evaluates a user function's body, once its arguments are bound
+pub fn will_apply_function(name: &ClarityName, body: &SymbolicExpression, env: &mut Environment, context: &LocalContext) {
+    with_hooks(|hook| hook.will_apply_function(env, context, name, body));
+}
+
+/// Called by the VM once it has deployed a contract
+pub fn did_initialize_contract(contract_identifier: &QualifiedContractIdentifier, contract_ast: &ContractAST) {
+    with_hooks(|hook| hook.did_initialize_contract(contract_identifier, contract_ast));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    use vm::contexts::OwnedEnvironment;
+    use vm::database::MemoryBackingStore;
+    use vm::tests::symbols_from_values;
+    use vm::types::{QualifiedContractIdentifier, PrincipalData, StandardPrincipalData};
+
+    #[derive(Default)]
+    struct Recorded {
+        evals: Vec<String>,
+        results: Vec<String>,
+        reads: Vec<String>,
+        writes: Vec<String>,
+        nested_evals: u64,
+    }
+
+    struct RecordingHook {
+        recorded: Rc<RefCell<Recorded>>,
+    }
+
+    // the function an expression applies, or the expression itself if it's not an application
+    fn describe(expr: &SymbolicExpression) -> String {
+        match expr.match_list().and_then(|list| list.first()).and_then(|f| f.match_atom()) {
+            Some(function) => function.to_string(),
+            None => expr.to_string()
+        }
+    }
+
+    impl EvalHook for RecordingHook {
+        fn will_eval(&mut self, env: &mut Environment, _context: &LocalContext, expr: &SymbolicExpression) {
+            self.recorded.borrow_mut().evals.push(describe(expr));
+            // the hook isn't called back for its own evaluations
+            if describe(expr) == "var-get" {
+                assert_eq!(env.eval_raw("(+ 1 2)").unwrap(), Value::Int(3));
+                self.recorded.borrow_mut().nested_evals += 1;
+            }
+        }
+
+        fn did_eval(&mut self, _env: &mut Environment, _context: &LocalContext, expr: &SymbolicExpression, result: &Result<Value>) {
+            if let Ok(value) = result {
+                self.recorded.borrow_mut().results.push(format!("{} => {}", describe(expr), value));
+            }
+        }
+
+        fn did_read(&mut self, key: &str, _value: Option<&str>) {
+            self.recorded.borrow_mut().reads.push(key.to_string());
+        }
+
+        fn did_write(&mut self, key: &str, _value: &str) {
+            self.recorded.borrow_mut().writes.push(key.to_string());
+        }
+    }
+
+    #[test]
+    fn test_eval_hook() {
+        let contract = "(define-data-var total int 0)
+                        (define-public (run (x int))
+                            (begin
+                                (var-set total (+ x 2))
+                                (ok (var-get total))))";
+
+        let contract_identifier = QualifiedContractIdentifier::local("hooked").unwrap();
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+        owned_env.initialize_contract(contract_identifier.clone(), contract).unwrap();
+
+        let recorded = Rc::new(RefCell::new(Recorded::default()));
+        attach(Box::new(RecordingHook { recorded: recorded.clone() }));
+
+        let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+        let (result, _, _) = owned_env.execute_transaction(sender, contract_identifier, "run", &symbols_from_values(vec![Value::Int(40)])).unwrap();
+        assert_eq!(result, Value::okay(Value::Int(42)).unwrap());
+
+        assert!(detach().is_some());
+        assert!(detach().is_none());
+
+        let recorded = recorded.borrow();
+        // the function's body comes first, then its parts, outermost first
+        assert_eq!(recorded.evals[0], "begin");
+        assert_eq!(recorded.evals.iter().filter(|f| *f == "+").count(), 1);
+        assert_eq!(recorded.nested_evals, 1);
+
+        // results come innermost first
+        assert!(recorded.results.contains(&"+ => 42".to_string()));
+        assert_eq!(recorded.results.last(), Some(&"begin => (ok 42)".to_string()));
+
+        assert!(recorded.writes.iter().any(|key| key.contains("total")));
+        assert!(recorded.reads.iter().any(|key| key.contains("total")));
+    }
+
+    #[test]
+    fn test_hooks_share_the_vm() {
+        use vm::coverage::{self, CoverageCollector};
+
+        let contract = "(define-read-only (double (x int)) (* x 2))";
+        let contract_identifier = QualifiedContractIdentifier::local("shared").unwrap();
+        let recorded = Rc::new(RefCell::new(Recorded::default()));
+        let collector = CoverageCollector::new();
+        attach(Box::new(RecordingHook { recorded: recorded.clone() }));
+        coverage::attach(collector.clone());
+
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+        owned_env.initialize_contract(contract_identifier.clone(), contract).unwrap();
+        let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+        owned_env.execute_transaction(sender, contract_identifier, "double", &symbols_from_values(vec![Value::Int(2)])).unwrap();
+
+        coverage::detach();
+        assert!(detach().is_some());
+        assert!(!is_attached());
+
+        // attaching coverage didn't take the embedder's slot, or the other way around
+        assert!(recorded.borrow().results.contains(&"* => 4".to_string()));
+        assert!(collector.lcov_report().contains("FNDA:1,double"));
+    }
+}
@@ -15,6 +15,12 @@ pub mod ast;
 pub mod contexts;
 pub mod database;
 pub mod clarity;
+pub mod debug;
+pub mod hooks;
+pub mod coverage;
+pub mod calltrace;
+pub mod statetrace;
+pub mod pool;
 
 mod functions;
 mod variables;
@@ -23,6 +29,9 @@ mod callables;
 pub mod docs;
 pub mod analysis;
 
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+
 #[cfg(test)]
 pub mod tests;
 
@@ -37,11 +46,12 @@ use vm::types::{QualifiedContractIdentifier, TraitIdentifier, PrincipalData, Typ
 use vm::costs::{cost_functions, CostOverflowingMath, LimitedCostTracker, MemoryConsumer, CostTracker};
 
 pub use vm::representations::{SymbolicExpression, SymbolicExpressionType, ClarityName, ContractName};
+use vm::representations::{FoldedExpression, FoldedStep};
 
 pub use vm::contexts::MAX_CONTEXT_DEPTH;
 use std::convert::TryInto;
 
-const MAX_CALL_STACK_DEPTH: usize = 64;
+pub const MAX_CALL_STACK_DEPTH: usize = 64;
 
 fn lookup_variable(name: &str, context: &LocalContext, env: &mut Environment) -> Result<Value> {
     if name.starts_with(char::is_numeric) || name.starts_with('\'') {
@@ -115,37 +125,41 @@ pub fn apply(function: &CallableType, args: &[SymbolicExpression],
         env.call_stack.insert(&identifier, track_recursion);
 
         let mut used_memory = 0;
-        let mut evaluated_args = vec![];
+        let mut evaluated_args = env.global_context.value_pool.take_value_vec();
         for arg_x in args.iter() {
             let arg_value = match eval(arg_x, env, context) {
                 Ok(x) => x,
                 Err(e) => {
+                    env.global_context.value_pool.return_value_vec(evaluated_args);
                     env.drop_memory(used_memory);
                     env.call_stack.remove(&identifier, track_recursion)?;
                     return Err(e)
                 }
             };
-            let arg_use = arg_value.get_memory_use();
+            // sizing a value isn't free, so it's skipped if memory use isn't counted
+            let arg_use = if env.counts_costs() { arg_value.get_memory_use() } else { 0 };
             match env.add_memory(arg_use) {
                 Ok(_x) => {},
                 Err(e) => {
+                    env.global_context.value_pool.return_value_vec(evaluated_args);
                     env.drop_memory(used_memory);
                     env.call_stack.remove(&identifier, track_recursion)?;
                     return Err(Error::from(e))
                 }
             };
-            used_memory += arg_value.get_memory_use();
+            used_memory += arg_use;
             evaluated_args.push(arg_value);
         }
         let mut resp = match function {
             CallableType::NativeFunction(_, function, cost_function) => {
                 let arg_size = evaluated_args.len();
                 runtime_cost!(cost_function, env, arg_size)?;
-                function.apply(evaluated_args)
+                function.apply(&mut evaluated_args)
             },
             CallableType::UserFunction(function) => function.apply(&evaluated_args, env),
             _ => panic!("Should be unreachable.")
         };
+        env.global_context.value_pool.return_value_vec(evaluated_args);
         add_stack_trace(&mut resp, env);
         env.drop_memory(used_memory);
         env.call_stack.remove(&identifier, track_recursion)?;
@@ -154,7 +168,23 @@ pub fn apply(function: &CallableType, args: &[SymbolicExpression],
 }
 
 pub fn eval <'a> (exp: &SymbolicExpression, env: &'a mut Environment, context: &LocalContext) -> Result<Value> {
-    use vm::representations::SymbolicExpressionType::{AtomValue, Atom, List, LiteralValue, TraitReference, Field};
+    // the debugger and coverage collection are hooks too
+    let hooked = hooks::is_attached();
+    if hooked {
+        hooks::will_eval(exp, env, context);
+    }
+
+    env.global_context.cost_track.begin_expression(&env.contract_context.contract_identifier, exp);
+    let result = eval_expression(exp, env, context);
+    env.global_context.cost_track.end_expression();
+    if hooked {
+        hooks::did_eval(exp, env, context, &result);
+    }
+    result
+}
+
+fn eval_expression(exp: &SymbolicExpression, env: &mut Environment, context: &LocalContext) -> Result<Value> {
+    use vm::representations::SymbolicExpressionType::{AtomValue, Atom, List, LiteralValue, TraitReference, Field, Folded};
 
     match exp.expr {
         AtomValue(ref value) | LiteralValue(ref value) => Ok(value.clone()),
@@ -167,10 +197,60 @@ pub fn eval <'a> (exp: &SymbolicExpression, env: &'a mut Environment, context: &
             let f = lookup_function(&function_name, env)?;
             apply(&f, &rest, env, context)
         },
+        // the debugger, hooks and coverage see the expressions a contract was written with
+        Folded(ref folded) if hooks::is_attached() =>
+            eval_expression(&folded.original, env, context),
+        Folded(ref folded) => eval_folded(folded, env, context),
         TraitReference(_, _) | Field(_) => unreachable!("can't be evaluated"),
     }
 }
 
+/// Evaluate an expression folded at analysis time: charge and check what evaluating the
+/// original expression did, in the same order, so that it fails the same way if it would
+/// have, and then evaluate what it was folded to.
+fn eval_folded(folded: &FoldedExpression, env: &mut Environment, context: &LocalContext) -> Result<Value> {
+    let mut calls = vec![];
+    let mut used_memory = 0;
+    let mut replayed = Ok(());
+    for step in folded.steps.iter() {
+        replayed = match step {
+            FoldedStep::Cost(cost) => env.add_cost(cost.clone()).map_err(Error::from),
+            FoldedStep::AddMemory(memory) => env.add_memory(*memory)
+                .map(|_| used_memory += memory)
+                .map_err(Error::from),
+            FoldedStep::DropMemory(memory) => {
+                env.drop_memory(*memory);
+                used_memory -= memory;
+                Ok(())
+            },
+            FoldedStep::Call(identifier) => {
+                if env.call_stack.depth() >= MAX_CALL_STACK_DEPTH {
+                    Err(RuntimeErrorType::MaxStackDepthReached.into())
+                } else {
+                    env.call_stack.insert(identifier, false);
+                    calls.push(identifier);
+                    Ok(())
+                }
+            },
+            FoldedStep::Return => match calls.pop() {
+                Some(identifier) => env.call_stack.remove(identifier, false),
+                None => Ok(())
+            }
+        };
+        if replayed.is_err() {
+            break
+        }
+    }
+
+    // the branch of a folded `if` is evaluated within its call
+    let result = replayed.and_then(|_| eval(&folded.expr, env, context));
+    env.drop_memory(used_memory);
+    while let Some(identifier) = calls.pop() {
+        env.call_stack.remove(identifier, false)?;
+    }
+    result
+}
+
 
 pub fn is_reserved(name: &str) -> bool {
     if let Some(_result) = functions::lookup_reserved_functions(name) {
@@ -351,5 +431,9 @@ mod test {
         let mut call_stack = CallStack::new();
         let mut env = Environment::new(&mut global_context, &contract_context, &mut call_stack, None, None);
         assert_eq!(Ok(Value::Int(64)), eval(&content[0], &mut env, &context));
+
+        // evaluating it again reuses the argument vectors the first evaluation allocated
+        assert_eq!(Ok(Value::Int(64)), eval(&content[0], &mut env, &context));
+        assert_eq!(env.global_context.value_pool.get_stats(), (4, 2));
     }
 }
@@ -0,0 +1,99 @@
+use vm::types::Value;
+
+/*
+
+Every application of a native or user-defined function evaluates its arguments into a
+fresh vector, which is dropped as soon as the function returns. Over a block's worth of
+transactions, that's a great many short-lived allocations. A ValuePool keeps the vectors
+that have been used and emptied, and hands them back out for later applications, so that a
+transaction's evaluation allocates argument vectors only as deep as its calls nest, rather
+than once per application.
+
+A pool lives in a transaction's GlobalContext, so everything it holds is freed once the
+transaction is done evaluating. Vectors that have grown past MAX_POOLED_CAPACITY (e.g., for
+a variadic call with many arguments) aren't kept, so one such call can't pin their memory
+for the rest of the transaction.
+
+*/
+
+// the most emptied vectors a pool keeps. applications nest at most MAX_CALL_STACK_DEPTH
+//  deep, and each holds one vector while its arguments are evaluated.
+pub const MAX_POOLED_VALUE_VECS: usize = 128;
+// the largest capacity a vector can have and still be kept
+pub const MAX_POOLED_CAPACITY: usize = 16;
+
+#[derive(Debug, Default)]
+pub struct ValuePool {
+    value_vecs: Vec<Vec<Value>>,
+    // how many vectors were handed out, and how many of those were newly allocated
+    taken: u64,
+    allocated: u64,
+}
+
+impl ValuePool {
+    pub fn new() -> ValuePool {
+        ValuePool::default()
+    }
+
+    /// Get an empty vector of values, reusing a pooled one if there is one
+    pub fn take_value_vec(&mut self) -> Vec<Value> {
+        self.taken += 1;
+        match self.value_vecs.pop() {
+            Some(values) => values,
+            None => {
+                self.allocated += 1;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Return a vector to the pool, dropping whatever values are still in it
+    pub fn return_value_vec(&mut self, mut values: Vec<Value>) {
+        if values.capacity() == 0 || values.capacity() > MAX_POOLED_CAPACITY
+            || self.value_vecs.len() >= MAX_POOLED_VALUE_VECS {
+            return
+        }
+        values.clear();
+        self.value_vecs.push(values);
+    }
+
+    /// How many vectors have been handed out, and how many of those had to be allocated
+    pub fn get_stats(&self) -> (u64, u64) {
+        (self.taken, self.allocated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_value_pool() {
+        let mut pool = ValuePool::new();
+
+        let mut values = pool.take_value_vec();
+        values.push(Value::Int(1));
+        values.push(Value::Int(2));
+        let capacity = values.capacity();
+        pool.return_value_vec(values);
+
+        // the vector comes back out empty, with its allocation
+        let values = pool.take_value_vec();
+        assert!(values.is_empty());
+        assert_eq!(values.capacity(), capacity);
+        assert_eq!(pool.get_stats(), (2, 1));
+
+        // vectors that grew too large aren't kept
+        let mut values = values;
+        values.extend((0..(MAX_POOLED_CAPACITY as i128 + 1)).map(Value::Int));
+        pool.return_value_vec(values);
+        pool.take_value_vec();
+        assert_eq!(pool.get_stats(), (3, 2));
+
+        // and neither are vectors past the pool's limit
+        for _ in 0..(MAX_POOLED_VALUE_VECS + 1) {
+            pool.return_value_vec(Vec::with_capacity(1));
+        }
+        assert_eq!(pool.value_vecs.len(), MAX_POOLED_VALUE_VECS);
+    }
+}
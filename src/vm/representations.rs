@@ -5,6 +5,8 @@ use std::convert::TryFrom;
 use regex::{Regex};
 use vm::types::{Value, TraitIdentifier, QualifiedContractIdentifier};
 use vm::errors::{RuntimeErrorType};
+use vm::callables::FunctionIdentifier;
+use vm::costs::ExecutionCost;
 
 pub const MAX_STRING_LEN: u8 = 128;
 
@@ -261,6 +263,34 @@ pub enum SymbolicExpressionType {
     LiteralValue(Value),
     Field(TraitIdentifier),
     TraitReference(ClarityName, TraitDefinition),
+    Folded(Box<FoldedExpression>),
+}
+
+/// An expression that was worked out ahead of time, when its contract was analyzed (see
+/// `vm::analysis::constant_folder`).  Evaluating it takes the steps that evaluating the
+/// original expression took before it got to `expr`, and then evaluates `expr`: a constant,
+/// or the branch of an `if` whose condition is constant.  The original is kept for the
+/// debugger, evaluation hooks and coverage, which evaluate it instead.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FoldedExpression {
+    pub steps: Vec<FoldedStep>,
+    pub expr: SymbolicExpression,
+    pub original: SymbolicExpression,
+}
+
+/// What evaluating an expression did, as far as anything outside of it can tell
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum FoldedStep {
+    /// charge a cost
+    Cost(ExecutionCost),
+    /// hold on to the memory of an evaluated argument
+    AddMemory(u64),
+    /// let go of the memory of a function's arguments
+    DropMemory(u64),
+    /// call a built-in function, if the call stack isn't too deep
+    Call(FunctionIdentifier),
+    /// return from the innermost function called
+    Return,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -331,6 +361,20 @@ impl SymbolicExpression {
     #[cfg(not(feature = "developer-mode"))]
     pub fn set_span(&mut self, _start_line: u32, _start_column: u32, _end_line: u32, _end_column: u32) {
     }
+
+    /// The line this expression starts on in its source, if known
+    #[cfg(feature = "developer-mode")]
+    pub fn start_line(&self) -> Option<u32> {
+        match self.span.start_line {
+            0 => None,
+            line => Some(line)
+        }
+    }
+
+    #[cfg(not(feature = "developer-mode"))]
+    pub fn start_line(&self) -> Option<u32> {
+        None
+    }
     
     pub fn atom_value(val: Value) -> SymbolicExpression {
         SymbolicExpression {
@@ -446,6 +490,7 @@ impl fmt::Display for SymbolicExpression {
             },
             SymbolicExpressionType::TraitReference(ref value, _) => { write!(f, "<{}>", &**value)?; },
             SymbolicExpressionType::Field(ref value) => { write!(f, "<{}>", value)?; },
+            SymbolicExpressionType::Folded(ref folded) => { write!(f, "{}", folded.original)?; },
         };
         
         Ok(())
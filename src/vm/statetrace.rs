@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use vm::types::QualifiedContractIdentifier;
+
+/// The reads and writes of the backing store made while tracing.  Values are recorded as they
+/// are stored, so a trace of a block's transactions is enough to check what they did to the
+/// chain state without running them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StateTrace {
+    /// keys read from the backing store, with the values they had, in the order they were
+    /// first read.  Keys read again are not recorded again.
+    pub reads: Vec<(String, Option<String>)>,
+    /// keys written to the backing store, with their new values, in order
+    pub writes: Vec<(String, String)>,
+    /// contract metadata read from the backing store, as `reads`
+    pub metadata_reads: Vec<(QualifiedContractIdentifier, String, Option<String>)>,
+    /// contract metadata written to the backing store, in order
+    pub metadata_writes: Vec<(QualifiedContractIdentifier, String, String)>,
+}
+
+impl StateTrace {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        let reads : Vec<serde_json::Value> = self.reads.iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        let writes : Vec<serde_json::Value> = self.writes.iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        let metadata_reads : Vec<serde_json::Value> = self.metadata_reads.iter()
+            .map(|(contract, key, value)| json!({ "contract_identifier": contract.to_string(), "key": key, "value": value }))
+            .collect();
+        let metadata_writes : Vec<serde_json::Value> = self.metadata_writes.iter()
+            .map(|(contract, key, value)| json!({ "contract_identifier": contract.to_string(), "key": key, "value": value }))
+            .collect();
+        json!({
+            "reads": reads,
+            "writes": writes,
+            "metadata_reads": metadata_reads,
+            "metadata_writes": metadata_writes,
+        })
+    }
+}
+
+/// Records the backing store accesses this thread's VM makes between `begin` and `finish`
+#[derive(Default)]
+struct StateTracer {
+    trace: StateTrace,
+    read_keys: HashSet<String>,
+    read_metadata_keys: HashSet<(QualifiedContractIdentifier, String)>,
+}
+
+// the state tracers of this thread, outermost first.  Only the innermost one records.
+thread_local!(static STATE_TRACERS: RefCell<Vec<StateTracer>> = RefCell::new(vec![]));
+
+/// Start recording the backing store accesses this thread's VM makes.  If a trace is already
+/// being recorded, it doesn't see the accesses made until the matching `finish`.
+pub fn begin() {
+    STATE_TRACERS.with(|t| t.borrow_mut().push(StateTracer::default()));
+}
+
+/// Stop recording, and get the accesses made since the matching `begin`
+pub fn finish() -> StateTrace {
+    STATE_TRACERS.with(|t| t.borrow_mut().pop())
+        .map(|tracer| tracer.trace)
+        .unwrap_or_default()
+}
+
+/// Is this thread's VM recording a trace?
+pub fn is_tracing() -> bool {
+    STATE_TRACERS.with(|t| !t.borrow().is_empty())
+}
+
+/// Called by the VM when it reads a key from the backing store
+pub fn did_read(key: &str, value: Option<&String>) {
+    STATE_TRACERS.with(|t| {
+        if let Some(tracer) = t.borrow_mut().last_mut() {
+            if tracer.read_keys.insert(key.to_string()) {
+                tracer.trace.reads.push((key.to_string(), value.cloned()));
+            }
+        }
+    })
+}
+
+/// Called by the VM when it reads contract metadata from the backing store
+pub fn did_read_metadata(contract: &QualifiedContractIdentifier, key: &str, value: Option<&String>) {
+    STATE_TRACERS.with(|t| {
+        if let Some(tracer) = t.borrow_mut().last_mut() {
+            if tracer.read_metadata_keys.insert((contract.clone(), key.to_string())) {
+                tracer.trace.metadata_reads.push((contract.clone(), key.to_string(), value.cloned()));
+            }
+        }
+    })
+}
+
+/// Called by the VM before it writes to the backing store
+pub fn will_write(edits: &[(String, String)]) {
+    STATE_TRACERS.with(|t| {
+        if let Some(tracer) = t.borrow_mut().last_mut() {
+            tracer.trace.writes.extend_from_slice(edits);
+        }
+    })
+}
+
+/// Called by the VM before it writes contract metadata to the backing store
+pub fn will_write_metadata(edits: &[((QualifiedContractIdentifier, String), String)]) {
+    STATE_TRACERS.with(|t| {
+        if let Some(tracer) = t.borrow_mut().last_mut() {
+            tracer.trace.metadata_writes.extend(
+                edits.iter().map(|((contract, key), value)| (contract.clone(), key.clone(), value.clone())));
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use vm::contexts::OwnedEnvironment;
+    use vm::database::{ClaritySerializable, MemoryBackingStore};
+    use vm::tests::symbols_from_values;
+    use vm::types::{PrincipalData, StandardPrincipalData, Value};
+
+    #[test]
+    fn test_state_trace() {
+        let contract = "(define-data-var counter int 0)
+                        (define-public (bump) (begin (var-set counter (+ (var-get counter) 1)) (ok (var-get counter))))";
+
+        let sender = Value::Principal(PrincipalData::Standard(StandardPrincipalData::transient()));
+        let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db());
+
+        // not recorded unless tracing
+        assert!(!is_tracing());
+        owned_env.initialize_contract(contract_id.clone(), contract).unwrap();
+        assert_eq!(finish(), StateTrace::default());
+
+        begin();
+        assert!(is_tracing());
+        owned_env.execute_transaction(sender.clone(), contract_id.clone(), "bump", &symbols_from_values(vec![])).unwrap();
+
+        // a nested trace records its own accesses
+        begin();
+        owned_env.execute_transaction(sender.clone(), contract_id.clone(), "bump", &symbols_from_values(vec![])).unwrap();
+        let inner = finish();
+        let outer = finish();
+        assert!(!is_tracing());
+
+        let counter_key = format!("vm::{}::", contract_id);
+        for (trace, before, after) in [(&outer, 0, 1), (&inner, 1, 2)].iter() {
+            let reads: Vec<_> = trace.reads.iter().filter(|(key, _)| key.starts_with(&counter_key) && key.ends_with("::counter")).collect();
+            assert_eq!(reads.len(), 1);
+            assert_eq!(reads[0].1, Some(Value::Int(*before).serialize()));
+
+            let writes: Vec<_> = trace.writes.iter().filter(|(key, _)| key.starts_with(&counter_key) && key.ends_with("::counter")).collect();
+            assert_eq!(writes.len(), 1);
+            assert_eq!(writes[0].1, Value::Int(*after).serialize());
+
+            assert!(trace.metadata_reads.iter().any(|(contract, _, value)| *contract == contract_id && value.is_some()));
+            assert_eq!(trace.metadata_writes, vec![]);
+        }
+
+        let json = outer.json_serialize();
+        assert_eq!(json["writes"].as_array().unwrap().len(), outer.writes.len());
+        assert_eq!(json["metadata_reads"][0]["contract_identifier"], contract_id.to_string());
+    }
+}
@@ -10,7 +10,7 @@ use vm::tests::{with_memory_environment, with_marfed_environment, symbols_from_v
                 execute, is_err_code, is_committed};
 
 use vm::contexts::{Environment};
-use vm::costs::{ExecutionCost};
+use vm::costs::{ExecutionCost, LimitedCostTracker};
 use vm::database::{ClarityDatabase, MarfedKV, MemoryBackingStore,
                    NULL_HEADER_DB};
 use chainstate::stacks::events::StacksTransactionEvent;
@@ -171,3 +171,53 @@ fn test_all() {
         assert!(cost.exceeds(&baseline));
     }
 }
+
+#[test]
+fn test_cost_profile() {
+    let contract = "(define-map scores ((who int)) ((score int)))
+                    (define-private (lookup (who int)) (default-to 0 (get score (map-get? scores {who: who}))))
+                    (define-public (total) (ok (+ (lookup 1) (lookup 2) (lookup 3))))";
+    let contract_identifier = QualifiedContractIdentifier::local("profiled").unwrap();
+    let sender = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+
+    let mut tracker = LimitedCostTracker::new_max_limit();
+    tracker.enable_profiling();
+
+    let mut marf = MemoryBackingStore::new();
+    let mut owned_env = OwnedEnvironment::new_cost_limited(marf.as_clarity_db(), tracker);
+    owned_env.initialize_contract(contract_identifier.clone(), contract).unwrap();
+    execute_transaction(&mut owned_env, sender, &contract_identifier, "total", &[]).unwrap();
+
+    let (_db, mut tracker) = owned_env.destruct().unwrap();
+    let report = tracker.take_profile().unwrap();
+    assert!(tracker.take_profile().is_none());
+    assert_eq!(report.total, tracker.get_total());
+
+    // every cost is charged to exactly one expression, or to none
+    let mut charged = report.unattributed.clone();
+    for entry in report.expressions.iter() {
+        charged.add(&entry.self_cost).unwrap();
+        assert!(!entry.self_cost.exceeds(&entry.total_cost));
+    }
+    assert_eq!(charged, report.total);
+
+    // the map read is charged to the map-get?, and to the expressions it's part of
+    let map_get = report.expressions.iter()
+        .find(|entry| entry.expression.starts_with("( map-get?"))
+        .expect("map-get? should be profiled");
+    assert_eq!(map_get.contract, contract_identifier.to_string());
+    assert_eq!(map_get.count, 3);
+    assert_eq!(map_get.self_cost.read_count, 3);
+
+    let lookup_body = report.expressions.iter()
+        .find(|entry| entry.expression.starts_with("( default-to"))
+        .expect("lookup body should be profiled");
+    assert_eq!(lookup_body.count, 3);
+    assert_eq!(lookup_body.self_cost.read_count, 0);
+    assert_eq!(lookup_body.total_cost.read_count, 3);
+
+    // most expensive first
+    for pair in report.expressions.windows(2) {
+        assert!(pair[0].self_cost.runtime >= pair[1].self_cost.runtime);
+    }
+}
@@ -555,6 +555,111 @@ impl ClarityDeserializable<Value> for Value {
     }
 }
 
+/// The JSON encoding of Clarity values, for the RPC interface.  Every value is an object with
+///   its `type`, and, unless it's `none`, its `value`:
+///     ints and uints are decimal strings, since they don't fit in a JSON number,
+///     buffers are 0x-prefixed hex strings, principals are strings,
+///     lists are arrays, tuples are objects, and optionals and responses hold the encoding of
+///     their contents.
+///   Unlike the consensus serialization, it isn't meant to be stored, but it is stable.
+impl Value {
+    pub fn json_serialize(&self) -> JSONValue {
+        use super::Value::*;
+        match self {
+            Int(value) => json!({ "type": "int", "value": value.to_string() }),
+            UInt(value) => json!({ "type": "uint", "value": value.to_string() }),
+            Bool(value) => json!({ "type": "bool", "value": value }),
+            Buffer(value) => json!({ "type": "buffer", "value": format!("0x{}", to_hex(&value.data)) }),
+            Principal(value) => json!({ "type": "principal", "value": value.to_string() }),
+            List(data) => {
+                let items: Vec<JSONValue> = data.data.iter().map(|item| item.json_serialize()).collect();
+                json!({ "type": "list", "value": items })
+            },
+            Tuple(data) => {
+                let fields: serde_json::Map<String, JSONValue> = data.data_map.iter()
+                    .map(|(name, value)| (name.to_string(), value.json_serialize()))
+                    .collect();
+                json!({ "type": "tuple", "value": fields })
+            },
+            Optional(OptionalData{ data: None }) => json!({ "type": "none" }),
+            Optional(OptionalData{ data: Some(value) }) => json!({ "type": "some", "value": value.json_serialize() }),
+            Response(response) => {
+                let type_name = if response.committed { "ok" } else { "err" };
+                json!({ "type": type_name, "value": response.data.json_serialize() })
+            }
+        }
+    }
+
+    /// Decode a value from its JSON encoding.  As with `try_deserialize_hex_untyped`, lists and
+    ///   tuples are typed as if they were literals in a contract.
+    pub fn json_deserialize(json: &JSONValue) -> Result<Value, SerializationError> {
+        Value::inner_json_deserialize(json, 0)
+    }
+
+    fn inner_json_deserialize(json: &JSONValue, depth: u8) -> Result<Value, SerializationError> {
+        if depth >= 16 {
+            return Err(CheckErrors::TypeSignatureTooDeep.into())
+        }
+
+        let type_name = json.get("type").and_then(|t| t.as_str())
+            .ok_or("Expected an object with a string `type`")?;
+        if type_name == "none" {
+            return Ok(Value::none());
+        }
+
+        let value = json.get("value")
+            .ok_or("Expected an object with a `value`")?;
+        let as_str = move || value.as_str()
+            .ok_or_else(|| SerializationError::DeserializationError(format!("Expected a string value for {}", type_name)));
+
+        match type_name {
+            "int" => as_str()?.parse::<i128>()
+                .map(Value::Int)
+                .map_err(|_| "Bad int".into()),
+            "uint" => as_str()?.parse::<u128>()
+                .map(Value::UInt)
+                .map_err(|_| "Bad uint".into()),
+            "bool" => value.as_bool()
+                .map(Value::Bool)
+                .ok_or_else(|| "Expected a boolean value for bool".into()),
+            "buffer" => {
+                let hex = as_str()?;
+                let hex = if hex.starts_with("0x") { &hex[2..] } else { hex };
+                let data = hex_bytes(hex).map_err(|_| "Bad hex string")?;
+                Value::buff_from(data).map_err(|_| "Illegal buffer type".into())
+            },
+            "principal" => PrincipalData::parse(as_str()?)
+                .map(Value::from)
+                .map_err(|_| "Bad principal".into()),
+            "list" => {
+                let items = value.as_array()
+                    .ok_or("Expected an array value for list")?
+                    .iter()
+                    .map(|item| Value::inner_json_deserialize(item, depth + 1))
+                    .collect::<Result<Vec<Value>, SerializationError>>()?;
+                Value::list_from(items).map_err(|_| "Illegal list type".into())
+            },
+            "tuple" => {
+                let mut items = vec![];
+                for (name, field) in value.as_object().ok_or("Expected an object value for tuple")?.iter() {
+                    let name = ClarityName::try_from(name.clone()).map_err(|_| "Bad tuple field name")?;
+                    items.push((name, Value::inner_json_deserialize(field, depth + 1)?));
+                }
+                TupleData::from_data(items)
+                    .map(Value::from)
+                    .map_err(|_| "Illegal tuple type".into())
+            },
+            "some" => Value::some(Value::inner_json_deserialize(value, depth + 1)?)
+                .map_err(|_| "Value too large".into()),
+            "ok" => Value::okay(Value::inner_json_deserialize(value, depth + 1)?)
+                .map_err(|_| "Value too large".into()),
+            "err" => Value::error(Value::inner_json_deserialize(value, depth + 1)?)
+                .map_err(|_| "Value too large".into()),
+            _ => Err(SerializationError::DeserializationError(format!("Unknown type '{}'", type_name)))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -863,4 +968,67 @@ mod tests {
         test_bad_expectation(contract_p2, TypeSignature::BoolType);
         test_bad_expectation(standard_p, TypeSignature::BoolType);
     }
+
+    #[test]
+    fn test_json_encoding() {
+        let issuer = PrincipalData::parse_standard_principal("SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G").unwrap();
+        let contract_p = Value::from(PrincipalData::Contract(QualifiedContractIdentifier::new(issuer.clone(), "foo".into())));
+        let tuple = Value::from(TupleData::from_data(vec![
+            ("a".into(), Value::Int(i128::min_value())),
+            ("b".into(), Value::list_from(vec![Value::UInt(u128::max_value()), Value::UInt(0)]).unwrap())]).unwrap());
+
+        let values = vec![
+            Value::Int(-12),
+            Value::Bool(true),
+            Value::buff_from(vec![0xde, 0xad]).unwrap(),
+            Value::from(issuer),
+            contract_p,
+            Value::none(),
+            Value::some(tuple.clone()).unwrap(),
+            Value::okay(Value::list_from(vec![]).unwrap()).unwrap(),
+            Value::error(Value::UInt(3)).unwrap(),
+            tuple.clone()
+        ];
+        for value in values.into_iter() {
+            let json = value.json_serialize();
+            assert_eq!(Value::json_deserialize(&json).unwrap(), value);
+            // and it survives being sent as text
+            let text = serde_json::to_string(&json).unwrap();
+            assert_eq!(Value::json_deserialize(&serde_json::from_str(&text).unwrap()).unwrap(), value);
+        }
+
+        assert_eq!(tuple.json_serialize(), json!({
+            "type": "tuple",
+            "value": {
+                "a": { "type": "int", "value": "-170141183460469231731687303715884105728" },
+                "b": { "type": "list", "value": [
+                    { "type": "uint", "value": "340282366920938463463374607431768211455" },
+                    { "type": "uint", "value": "0" }] }
+            }
+        }));
+        assert_eq!(Value::none().json_serialize(), json!({ "type": "none" }));
+        assert_eq!(Value::buff_from(vec![0xde, 0xad]).unwrap().json_serialize(), json!({ "type": "buffer", "value": "0xdead" }));
+
+        let bad = vec![
+            json!({ "value": "1" }),
+            json!({ "type": "int", "value": 1 }),
+            json!({ "type": "int", "value": "one" }),
+            json!({ "type": "uint", "value": "-1" }),
+            json!({ "type": "buffer", "value": "0xzz" }),
+            json!({ "type": "principal", "value": "not-a-principal" }),
+            json!({ "type": "some" }),
+            json!({ "type": "list", "value": [{ "type": "int", "value": "1" }, { "type": "uint", "value": "1" }] }),
+            json!({ "type": "tuple", "value": { "not a name": { "type": "none" } } }),
+            json!({ "type": "string", "value": "hello" }),
+        ];
+        for json in bad.iter() {
+            assert!(Value::json_deserialize(json).is_err(), "{}", json);
+        }
+
+        let mut deep = json!({ "type": "none" });
+        for _i in 0..16 {
+            deep = json!({ "type": "some", "value": deep });
+        }
+        assert_eq!(Value::json_deserialize(&deep), Err(CheckErrors::TypeSignatureTooDeep.into()));
+    }
 }
@@ -0,0 +1,303 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use stacks::util::db::sqlite_backup;
+use stacks::util::get_epoch_time_secs;
+
+use crate::db_maintenance::is_sqlite_file;
+use crate::snapshot::list_snapshot_files;
+use crate::Config;
+use crate::run_loop::sleep_while_running;
+
+/// Back up once a day by default
+pub const DEFAULT_BACKUP_INTERVAL: u64 = 24 * 3600;
+
+/// Keep a week of daily backups by default
+pub const DEFAULT_BACKUP_KEEP: usize = 7;
+
+/// Each backup is a directory named for when it was started
+const BACKUP_PREFIX: &str = "backup-";
+
+/// A backup is made under this suffix, and renamed once it's complete
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Seconds between looks at whether a backup is due
+const BACKUP_TICK_SECS: u64 = 60;
+
+/// Where and how often to back up the node while it runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupConfig {
+    /// Directory the backups are made in
+    pub backup_dir: String,
+    /// Seconds between backups
+    pub interval: u64,
+    /// How many of the newest backups to keep
+    pub keep: usize,
+}
+
+/// What making a backup came to
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupSummary {
+    pub path: PathBuf,
+    /// databases copied with sqlite's backup API
+    pub num_databases: u64,
+    /// other files copied from the node
+    pub num_copied: u64,
+    /// block files hardlinked from the previous backup
+    pub num_linked: u64,
+    /// bytes copied from the node, databases included
+    pub bytes_copied: u64,
+}
+
+/// When in a backup a file is copied.  A database is copied after the ones it refers to: the
+/// sortition DB's rows before its MARF, the headers before the Clarity state, the Clarity state
+/// before the staging DB, and the staging DB before the block files.  Whatever the node commits
+/// while the backup is made, each database's copy then holds at least what the earlier copies
+/// point to, which is the state an unclean shutdown leaves behind, and the node repairs it when
+/// it starts.
+fn backup_rank(path: &str) -> u8 {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    if path.starts_with("burnchain/") {
+        if file_name == "marf" { 1 } else { 0 }
+    }
+    else if path.ends_with("/vm/headers.db") {
+        2
+    }
+    else if path.contains("/vm/index") {
+        3
+    }
+    else if path.ends_with("/vm/clarity/marf") {
+        4
+    }
+    else if path.contains("/vm/clarity/") {
+        5
+    }
+    else if path.ends_with("/blocks/staging.db") {
+        6
+    }
+    else {
+        7
+    }
+}
+
+/// Is this one of the chunk store's block files?  They're written once, and only ever changed
+/// by pruning, which truncates them, so one that's the same size as in the previous backup is
+/// the same file.
+fn is_block_file(path: &str) -> bool {
+    let parts : Vec<&str> = path.split('/').collect();
+    parts.windows(2).any(|pair| pair[0] == "blocks" && pair[1].len() == 4 && pair[1].chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// List the files of a working directory that go in a backup, relative to it, in the order
+/// they're copied.  Write-ahead logs and journals are left out:  what's committed in them is
+/// copied along with their databases.
+pub fn list_backup_files(working_dir: &Path) -> Result<Vec<String>, String> {
+    let mut files : Vec<String> = list_snapshot_files(working_dir)?
+        .into_iter()
+        .filter(|path| !path.ends_with("-wal") && !path.ends_with("-journal"))
+        .collect();
+    if let Some(path) = files.iter().find(|path| path.contains("/vm/clarity/marf/")) {
+        return Err(format!("{} is part of a RocksDB MARF, which can't be backed up while the node runs", path));
+    }
+    files.sort_by(|a, b| backup_rank(a).cmp(&backup_rank(b)).then(a.cmp(b)));
+    Ok(files)
+}
+
+fn backup_time(path: &Path) -> Option<u64> {
+    path.file_name()?.to_str()?.strip_prefix(BACKUP_PREFIX)?.parse::<u64>().ok()
+}
+
+/// List the complete backups in a backup directory, oldest first
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !backup_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let entries = fs::read_dir(backup_dir)
+        .map_err(|e| format!("Failed to read {}: {:?}", backup_dir.display(), &e))?;
+    let mut backups : Vec<(u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| backup_time(&path).map(|time| (time, path)))
+        .collect();
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+fn copy_backup_files(working_dir: &Path, dest_dir: &Path, previous: Option<&Path>) -> Result<BackupSummary, String> {
+    let mut summary = BackupSummary {
+        path: dest_dir.to_path_buf(),
+        num_databases: 0,
+        num_copied: 0,
+        num_linked: 0,
+        bytes_copied: 0,
+    };
+
+    for path in list_backup_files(working_dir)?.iter() {
+        let src = working_dir.join(path);
+        let dest = dest_dir.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {:?}", parent.display(), &e))?;
+        }
+
+        if is_sqlite_file(&src) {
+            sqlite_backup(&src.to_string_lossy(), &dest.to_string_lossy())
+                .map_err(|e| format!("Failed to back up {}: {:?}", path, &e))?;
+            summary.num_databases += 1;
+            summary.bytes_copied += fs::metadata(&dest).map(|md| md.len()).unwrap_or(0);
+            continue;
+        }
+
+        // the node's own block files are never linked to, since pruning truncates them in place
+        let size = fs::metadata(&src)
+            .map_err(|e| format!("Failed to read {}: {:?}", path, &e))?
+            .len();
+        let unchanged = previous
+            .map(|previous| previous.join(path))
+            .filter(|prev_file| is_block_file(path) && fs::metadata(prev_file).map(|md| md.len() == size).unwrap_or(false));
+        if let Some(prev_file) = unchanged {
+            if fs::hard_link(&prev_file, &dest).is_ok() {
+                summary.num_linked += 1;
+                continue;
+            }
+        }
+        summary.bytes_copied += fs::copy(&src, &dest)
+            .map_err(|e| format!("Failed to copy {}: {:?}", path, &e))?;
+        summary.num_copied += 1;
+    }
+    Ok(summary)
+}
+
+/// Back up a running node's chainstate and sortition DB into a new directory under
+/// `backup_dir`, laid out like the node's working directory.  Databases are copied with sqlite's
+/// online backup API, so the node doesn't have to stop; block files that haven't changed since
+/// the previous backup are hardlinked from it instead of copied again.  The backup only appears
+/// under its final name, `backup-<backup_time>`, once it's complete.
+pub fn create_backup(working_dir: &Path, backup_dir: &Path, backup_time: u64) -> Result<BackupSummary, String> {
+    fs::create_dir_all(backup_dir)
+        .map_err(|e| format!("Failed to create {}: {:?}", backup_dir.display(), &e))?;
+    let previous = list_backups(backup_dir)?.pop();
+
+    let name = format!("{}{}", BACKUP_PREFIX, backup_time);
+    let dest = backup_dir.join(&name);
+    if dest.exists() {
+        return Err(format!("{} already exists", dest.display()));
+    }
+    let partial = backup_dir.join(format!("{}{}", &name, PARTIAL_SUFFIX));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)
+            .map_err(|e| format!("Failed to clear {}: {:?}", partial.display(), &e))?;
+    }
+
+    match copy_backup_files(working_dir, &partial, previous.as_ref().map(|path| path.as_path())) {
+        Ok(mut summary) => {
+            fs::rename(&partial, &dest)
+                .map_err(|e| format!("Failed to move {} into place: {:?}", dest.display(), &e))?;
+            summary.path = dest;
+            Ok(summary)
+        },
+        Err(e) => {
+            let _ = fs::remove_dir_all(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Delete all but the newest `keep` backups, and any backup left partial by a failed run.
+/// Returns the backups deleted.
+pub fn prune_backups(backup_dir: &Path, keep: usize) -> Result<Vec<PathBuf>, String> {
+    if let Ok(entries) = fs::read_dir(backup_dir) {
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let is_partial = path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(PARTIAL_SUFFIX))
+                .unwrap_or(false);
+            if is_partial {
+                let _ = fs::remove_dir_all(&path);
+            }
+        }
+    }
+
+    let backups = list_backups(backup_dir)?;
+    let num_old = backups.len().saturating_sub(keep);
+    let mut pruned = vec![];
+    for path in backups.into_iter().take(num_old) {
+        fs::remove_dir_all(&path)
+            .map_err(|e| format!("Failed to delete {}: {:?}", path.display(), &e))?;
+        pruned.push(path);
+    }
+    Ok(pruned)
+}
+
+/// Back up a node, running or not, into `backup_dir` if given, or else its `node.backup_dir`,
+/// and then delete the backups beyond its `node.backup_keep`
+pub fn backup_node(config: &Config, backup_dir: Option<&str>) -> Result<BackupSummary, String> {
+    let (backup_dir, keep) = match (backup_dir, config.node.backup.as_ref()) {
+        (Some(backup_dir), backup) => (backup_dir.to_string(), backup.map(|backup| backup.keep).unwrap_or(DEFAULT_BACKUP_KEEP)),
+        (None, Some(backup)) => (backup.backup_dir.clone(), backup.keep),
+        (None, None) => return Err("No backup directory; pass --backup-dir, or set `node.backup_dir`".to_string())
+    };
+    let backup_dir = PathBuf::from(backup_dir);
+    let summary = create_backup(Path::new(&config.node.working_dir), &backup_dir, get_epoch_time_secs())?;
+    prune_backups(&backup_dir, keep)?;
+    Ok(summary)
+}
+
+/// Back up the node under `working_dir` every `config.interval` seconds, as measured from its
+/// newest backup, so that restarting the node doesn't put the next one off.  A backup that
+/// fails isn't tried again until an interval later.  Stops once `should_keep_running` is cleared.
+pub fn spawn_backups(working_dir: &str, config: BackupConfig, should_keep_running: Arc<AtomicBool>) -> Result<JoinHandle<()>, String> {
+    let working_dir = PathBuf::from(working_dir);
+    let backup_dir = PathBuf::from(&config.backup_dir);
+    thread::Builder::new()
+        .name("backup".to_string())
+        .spawn(move || {
+            let mut last_failure = None;
+            while sleep_while_running(Duration::from_secs(BACKUP_TICK_SECS), &should_keep_running) {
+
+                let last_backup = match list_backups(&backup_dir) {
+                    Ok(backups) => backups.last().and_then(|path| backup_time(path)),
+                    Err(e) => {
+                        warn!("Backup: {}", &e);
+                        continue;
+                    }
+                };
+                let last_attempt = last_backup.max(last_failure);
+                if let Some(last_attempt) = last_attempt {
+                    if get_epoch_time_secs().saturating_sub(last_attempt) < config.interval {
+                        continue;
+                    }
+                }
+
+                info!("Backup: backing up to {}", backup_dir.display());
+                let start_time = Instant::now();
+                match create_backup(&working_dir, &backup_dir, get_epoch_time_secs()) {
+                    Ok(summary) => {
+                        info!("Backup: made {} in {}s: {} databases and {} files copied ({} bytes), {} block files linked",
+                              summary.path.display(), start_time.elapsed().as_secs(), summary.num_databases,
+                              summary.num_copied, summary.bytes_copied, summary.num_linked);
+                    },
+                    Err(e) => {
+                        warn!("Backup: {}", &e);
+                        last_failure = Some(get_epoch_time_secs());
+                        continue;
+                    }
+                }
+                match prune_backups(&backup_dir, config.keep) {
+                    Ok(pruned) => {
+                        for path in pruned.iter() {
+                            info!("Backup: deleted {}", path.display());
+                        }
+                    },
+                    Err(e) => warn!("Backup: {}", &e)
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start the backup thread: {:?}", &e))
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::db::compression::{ChunkStoreMigration, DEFAULT_BLOCK_COMPRESSION_LEVEL};
+
+use crate::clarity_repl::find_vm_dir;
+use crate::Config;
+
+/// Find the chunk store of the chainstate under `chainstate_dir`, next to its `vm` directory
+pub fn find_blocks_dir(chainstate_dir: &str) -> Result<PathBuf, String> {
+    let vm_dir = find_vm_dir(chainstate_dir)?;
+    let blocks_dir = vm_dir.with_file_name("blocks");
+    if !blocks_dir.is_dir() {
+        return Err(format!("No blocks found in {}", chainstate_dir));
+    }
+    Ok(blocks_dir)
+}
+
+/// Compress the blocks and microblock streams a stopped node has already stored, at its
+/// `node.block_compression_level`, or with `decompress`, store them all uncompressed again
+pub fn migrate_blocks(config: &Config, decompress: bool) -> Result<ChunkStoreMigration, String> {
+    let blocks_dir = find_blocks_dir(&config.get_chainstate_path())?;
+    let level = if decompress {
+        None
+    }
+    else {
+        Some(config.node.block_compression.unwrap_or(DEFAULT_BLOCK_COMPRESSION_LEVEL))
+    };
+    let blocks_path = blocks_dir.to_string_lossy().to_string();
+    StacksChainState::migrate_chunk_store(&blocks_path, level)
+        .map_err(|e| format!("Failed to rewrite the blocks in {}: {:?}", &blocks_path, &e))
+}
@@ -1,6 +1,11 @@
+use std::cmp;
 use std::io::Cursor;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
 use async_std::io::ReadExt;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_h1::{client};
 use async_std::net::{TcpStream};
@@ -13,10 +18,14 @@ use serde_json::value::RawValue;
 use secp256k1::{Secp256k1};
 
 use super::{BurnchainController, BurnchainTip};
+use super::endpoints::EndpointStatus;
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
+use super::super::config::{BurnchainConfig, BurnchainEndpoint};
+use super::super::run_loop::sleep_while_running;
 
 use stacks::burnchains::Burnchain;
+use stacks::burnchains::Txid;
 use stacks::burnchains::BurnchainStateTransition;
 use stacks::burnchains::Error as burnchain_error;
 use stacks::burnchains::bitcoin::BitcoinNetworkType;
@@ -40,22 +49,60 @@ use stacks::deps::bitcoin::util::hash::Sha256dHash;
 use stacks::net::StacksMessageCodec;
 use stacks::util::hash::{Hash160, hex_bytes};
 use stacks::util::secp256k1::Secp256k1PublicKey;
-use stacks::util::sleep_ms;
+use stacks::util::{get_epoch_time_secs, sleep_ms};
 
 use stacks::monitoring::{
     increment_btc_blocks_received_counter, 
-    increment_btc_ops_sent_counter
+    increment_btc_ops_sent_counter,
+    increment_btc_block_commits_unconfirmed_counter,
+    observe_block_commit_confirmation_latency,
+    set_last_block_commit_fee_rate
 };
 
 pub struct BitcoinRegtestController {
     config: Config,
     indexer_config: BitcoinIndexerConfig,
+    /// which of the configured bitcoinds the indexer syncs from
+    indexer_endpoint: usize,
+    /// which of the configured bitcoinds are down, and which addresses they've imported
+    endpoint_status: Arc<EndpointStatus>,
     db: Option<BurnDB>,
     chain_tip: Option<BurnchainTip>,
+    pending_commits: Vec<PendingBlockCommit>,
+    /// cleared when the node is shutting down, so a sync that's waiting on bitcoind gives up
+    should_keep_running: Option<Arc<AtomicBool>>,
+}
+
+/// A block-commit we sent, which we're waiting to see mined.
+struct PendingBlockCommit {
+    txid: Txid,
+    sats_per_byte: u64,
+    submitted_at_height: u64,
 }
 
 const DUST_UTXO_LIMIT: u64 = 5500;
 
+/// Upper bound on the virtual size of a block-commit transaction: one P2PKH input, the OP_RETURN
+/// payload, the burn output, and a change output.
+const BLOCK_COMMIT_TX_ESTIMATED_SIZE: u64 = 380;
+
+/// Feerates (sats/vbyte) to use when bitcoind can't give us an estimate, keyed by the
+/// confirmation target (in blocks) they are expected to meet.
+const FALLBACK_FEE_RATES: [(u16, u64); 5] = [
+    (1, 50),
+    (2, 30),
+    (6, 15),
+    (12, 8),
+    (1008, 1),
+];
+
+/// How many burnchain blocks we wait for a block-commit to be mined before giving up on it.
+const MAX_BLOCK_COMMIT_CONFIRMATION_LATENCY: u64 = 12;
+
+/// How many times the indexer tries to connect to a bitcoind before failing over to the next one,
+/// backing off for up to a minute between tries.
+const MAX_INDEXER_CONNECT_ATTEMPTS: u64 = 5;
+
 impl BitcoinRegtestController {
 
     pub fn generic(config: Config) -> Box<dyn BurnchainController> {
@@ -73,57 +120,102 @@ impl BitcoinRegtestController {
             panic!()
         }
 
-        let indexer_config = {
-            let burnchain_config = config.burnchain.clone();
-            BitcoinIndexerConfig {
-                peer_host: burnchain_config.peer_host,
-                peer_port: burnchain_config.peer_port,
-                rpc_port: burnchain_config.rpc_port,
-                rpc_ssl: burnchain_config.rpc_ssl,
-                username: burnchain_config.username,
-                password: burnchain_config.password,
-                timeout: burnchain_config.timeout,
-                spv_headers_path: burnchain_config.spv_headers_path,
-                first_block: burnchain_config.first_block,
-                magic_bytes: burnchain_config.magic_bytes
-            }
-        };
+        let indexer_config = BitcoinRegtestController::make_indexer_config(&config, &config.burnchain.get_endpoints()[0]);
                 
         Self {
             config: config,
             indexer_config,
+            indexer_endpoint: 0,
+            endpoint_status: Arc::new(EndpointStatus::new()),
             db: None,
             chain_tip: None,
+            pending_commits: vec![],
+            should_keep_running: None,
         }
     }
 
     /// create a dummy bitcoin regtest controller.
     ///   used just for submitting bitcoin ops.
     pub fn new_dummy(config: Config) -> Self {
-        let indexer_config = {
-            let burnchain_config = config.burnchain.clone();
-            BitcoinIndexerConfig {
-                peer_host: burnchain_config.peer_host,
-                peer_port: burnchain_config.peer_port,
-                rpc_port: burnchain_config.rpc_port,
-                rpc_ssl: burnchain_config.rpc_ssl,
-                username: burnchain_config.username,
-                password: burnchain_config.password,
-                timeout: burnchain_config.timeout,
-                spv_headers_path: burnchain_config.spv_headers_path,
-                first_block: burnchain_config.first_block,
-                magic_bytes: burnchain_config.magic_bytes
-            }
-        };
+        let indexer_config = BitcoinRegtestController::make_indexer_config(&config, &config.burnchain.get_endpoints()[0]);
                 
         Self {
             config: config,
             indexer_config,
+            indexer_endpoint: 0,
+            endpoint_status: Arc::new(EndpointStatus::new()),
             db: None,
             chain_tip: None,
+            pending_commits: vec![],
+            should_keep_running: None,
         }        
     }
 
+    /// Have syncing give up, instead of retrying, once `should_keep_running` is cleared
+    pub fn set_termination_switch(&mut self, should_keep_running: Arc<AtomicBool>) {
+        self.should_keep_running = Some(should_keep_running);
+    }
+
+    /// Sleep for `ms` milliseconds, or until the termination switch is cleared.  Returns whether
+    /// to keep going.
+    fn sleep_unless_stopped(&self, ms: u64) -> bool {
+        match self.should_keep_running {
+            Some(ref should_keep_running) => sleep_while_running(Duration::from_millis(ms), should_keep_running),
+            None => {
+                sleep_ms(ms);
+                true
+            }
+        }
+    }
+
+    fn make_indexer_config(config: &Config, endpoint: &BurnchainEndpoint) -> BitcoinIndexerConfig {
+        BitcoinIndexerConfig {
+            peer_host: endpoint.peer_host.clone(),
+            peer_port: endpoint.peer_port,
+            rpc_port: endpoint.rpc_port,
+            rpc_ssl: endpoint.rpc_ssl,
+            username: endpoint.username.clone(),
+            password: endpoint.password.clone(),
+            timeout: config.burnchain.timeout,
+            spv_headers_path: config.burnchain.spv_headers_path.clone(),
+            first_block: config.burnchain.first_block,
+            magic_bytes: config.burnchain.magic_bytes.clone()
+        }
+    }
+
+    /// Have the indexer sync from the next configured bitcoind, if there's more than one
+    fn fail_over_indexer(&mut self) {
+        let endpoints = self.config.burnchain.get_endpoints();
+        if endpoints.len() < 2 {
+            return;
+        }
+        self.indexer_endpoint = (self.indexer_endpoint + 1) % endpoints.len();
+        let endpoint = &endpoints[self.indexer_endpoint];
+        warn!("Failing over to syncing the burnchain from {}:{}", &endpoint.peer_host, endpoint.peer_port);
+        self.indexer_config = BitcoinRegtestController::make_indexer_config(&self.config, endpoint);
+    }
+
+    /// Check on the bitcoinds that are down, so that the ones that have recovered are preferred
+    /// again.  The checks run on their own thread, so a bitcoind that's still down doesn't hold
+    /// up syncing.
+    fn check_endpoints(&self) {
+        let endpoints = self.config.burnchain.get_endpoints();
+        let due = self.endpoint_status.start_endpoint_checks(&endpoints, get_epoch_time_secs());
+        if due.len() == 0 {
+            return;
+        }
+
+        let config = self.config.clone();
+        let endpoint_status = self.endpoint_status.clone();
+        thread::spawn(move || {
+            for i in due.into_iter() {
+                if let Err(e) = BitcoinRPCRequest::get_block_count(&config, &endpoint_status, &endpoints[i]) {
+                    debug!("Bitcoin RPC: {} is still unavailable: {:?}", endpoints[i].get_rpc_url(), &e);
+                }
+            }
+        });
+    }
+
     fn setup_indexer_runtime(&mut self) -> (Burnchain, BitcoinIndexer) {
         let network = "regtest".to_string();
         let working_dir = self.config.get_burn_db_path();
@@ -135,7 +227,11 @@ impl BitcoinRegtestController {
             }
         };
 
-        let indexer_runtime = BitcoinIndexerRuntime::new(BitcoinNetworkType::Regtest);
+        let mut indexer_runtime = BitcoinIndexerRuntime::new(BitcoinNetworkType::Regtest);
+        if self.config.burnchain.get_endpoints().len() > 1 {
+            // give up on a bitcoind that's down, so the next one can be tried
+            indexer_runtime.max_connect_attempts = Some(MAX_INDEXER_CONNECT_ATTEMPTS);
+        }
         let burnchain_indexer = BitcoinIndexer {
             config: self.indexer_config.clone(),
             runtime: indexer_runtime
@@ -144,10 +240,18 @@ impl BitcoinRegtestController {
     }
 
     fn receive_blocks(&mut self) -> BurnchainTip {
+        self.try_receive_blocks()
+            .expect("BUG: burnchain sync stopped without a termination switch")
+    }
+
+    /// Sync the burnchain, retrying until it succeeds.  Returns None if the termination switch is
+    /// cleared while retrying.
+    fn try_receive_blocks(&mut self) -> Option<BurnchainTip> {
+        self.check_endpoints();
         let (mut burnchain, mut burnchain_indexer) = self.setup_indexer_runtime();
 
         let (block_snapshot, state_transition) = loop {
-            match burnchain.sync_with_indexer(&mut burnchain_indexer) {
+            match burnchain.sync_with_indexer_and_pragmas(&mut burnchain_indexer, &self.config.node.sqlite_pragmas) {
                 Ok(x) => {
                     increment_btc_blocks_received_counter();
                     break x;
@@ -160,15 +264,25 @@ impl BitcoinRegtestController {
                             // try again immediately
                             continue;
                         },
-                        burnchain_error::BurnchainPeerBroken => {
-                            // remote burnchain peer broke, and produced a shorter blockchain fork.
-                            // just keep trying
-                            sleep_ms(5000);
+                        burnchain_error::BurnchainPeerBroken |
+                        burnchain_error::Bitcoin(_) |
+                        burnchain_error::DownloadError(_) => {
+                            // remote burnchain peer broke, produced a shorter blockchain fork, or
+                            // can't be reached.  try the next one, if there is one.
+                            self.fail_over_indexer();
+                            let (next_burnchain, next_burnchain_indexer) = self.setup_indexer_runtime();
+                            burnchain = next_burnchain;
+                            burnchain_indexer = next_burnchain_indexer;
+                            if !self.sleep_unless_stopped(5000) {
+                                return None;
+                            }
                             continue;
                         },
                         _ => {
                             // delay and try again
-                            sleep_ms(5000);
+                            if !self.sleep_unless_stopped(5000) {
+                                return None;
+                            }
                             continue;
                         }
                     }
@@ -199,8 +313,93 @@ impl BitcoinRegtestController {
             }
         };
 
+        self.observe_block_commit_confirmations(&rest);
+
         debug!("Done receiving blocks");
-        rest
+        Some(rest)
+    }
+
+    /// Like `start()`, but gives up if the termination switch is cleared before the burnchain
+    /// can be synced.
+    pub fn start_unless_stopped(&mut self) -> Option<BurnchainTip> {
+        self.try_receive_blocks()
+    }
+
+    /// Like `sync()`, but gives up if the termination switch is cleared while waiting on the
+    /// next burnchain block.
+    pub fn sync_unless_stopped(&mut self) -> Option<BurnchainTip> {
+        let burnchain_tip = if self.config.burnchain.mode == "helium" {
+            // Helium: this node is responsible for mining new burnchain blocks
+            self.build_next_block(1);
+            self.try_receive_blocks()?
+        } else {
+            // Neon: this node is waiting on a block to be produced
+            let current_height = self.get_chain_tip().block_snapshot.block_height;
+            loop {
+                let burnchain_tip = self.try_receive_blocks()?;
+                if burnchain_tip.block_snapshot.block_height > current_height {
+                    break burnchain_tip;
+                }
+                if !self.sleep_unless_stopped(5000) {
+                    return None;
+                }
+            }
+        };
+
+        // Evaluate process_exit_at_block_height setting
+        if let Some(cap) = self.config.burnchain.process_exit_at_block_height {
+            if burnchain_tip.block_snapshot.block_height >= cap {
+                info!("Node succesfully reached the end of the ongoing {} blocks epoch!", cap);
+                info!("This process will automatically terminate in 30s, restart your node for participating in the next epoch.");
+                sleep_ms(30000);
+                std::process::exit(0);
+            }    
+        }
+        Some(burnchain_tip)
+    }
+
+    /// Match the block-commits mined in the new burnchain tip against the ones we sent, and
+    /// record how long they took to confirm.  Commits that have been outstanding for too long
+    /// are dropped.
+    fn observe_block_commit_confirmations(&mut self, burnchain_tip: &BurnchainTip) {
+        if self.pending_commits.len() == 0 {
+            return;
+        }
+
+        let tip_height = burnchain_tip.block_snapshot.block_height;
+        for op in burnchain_tip.state_transition.accepted_ops.iter() {
+            if let BlockstackOperationType::LeaderBlockCommit(ref commit) = op {
+                if let Some(i) = self.pending_commits.iter().position(|p| p.txid == commit.txid) {
+                    let pending = self.pending_commits.remove(i);
+                    let latency = tip_height.saturating_sub(pending.submitted_at_height);
+                    debug!("Block-commit {:?} confirmed after {} block(s) at {} sats/vbyte", &pending.txid, latency, pending.sats_per_byte);
+                    observe_block_commit_confirmation_latency(latency, pending.sats_per_byte);
+                }
+            }
+        }
+
+        let num_pending = self.pending_commits.len();
+        self.pending_commits.retain(|p| p.submitted_at_height + MAX_BLOCK_COMMIT_CONFIRMATION_LATENCY > tip_height);
+        for _ in self.pending_commits.len()..num_pending {
+            increment_btc_block_commits_unconfirmed_counter();
+        }
+    }
+
+    /// Choose the fee for a block-commit, and the feerate (sats/vbyte) it comes to.  With
+    /// `burnchain.fee_estimation` on, bitcoind is asked for a feerate first.
+    fn get_block_commit_fee(&self) -> (u64, u64) {
+        let estimate = if self.config.burnchain.fee_estimation {
+            match BitcoinRPCRequest::estimate_smart_fee(&self.config, &self.endpoint_status, self.config.burnchain.fee_estimation_conf_target) {
+                Ok(estimate) => estimate,
+                Err(e) => {
+                    warn!("Bitcoin RPC failure: unable to estimate fee - {:?}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        block_commit_fee(&self.config.burnchain, estimate)
     }
 
     pub fn get_utxos(&self, public_key: &Secp256k1PublicKey, amount_required: u64) -> Option<Vec<UTXO>> {
@@ -216,6 +415,7 @@ impl BitcoinRegtestController {
         let mut utxos = loop {
             let result = BitcoinRPCRequest::list_unspent(
                 &self.config,
+                &self.endpoint_status,
                 filter_addresses.clone(), 
                 false, 
                 amount_required);
@@ -238,12 +438,14 @@ impl BitcoinRegtestController {
                 loop {
                     let _result = BitcoinRPCRequest::import_public_key(
                         &self.config,
+                        &self.endpoint_status,
                         &public_key);
 
                     sleep_ms(1000);
 
                     let result = BitcoinRPCRequest::list_unspent(
                         &self.config,
+                        &self.endpoint_status,
                         filter_addresses.clone(), 
                         false, 
                         amount_required);
@@ -282,7 +484,8 @@ impl BitcoinRegtestController {
         
         let public_key = signer.get_public_key();
 
-        let (mut tx, utxos) = self.prepare_tx(&public_key, DUST_UTXO_LIMIT)?;
+        let tx_fee = self.config.burnchain.burnchain_op_tx_fee;
+        let (mut tx, utxos) = self.prepare_tx(&public_key, DUST_UTXO_LIMIT, tx_fee)?;
 
         // Serialize the payload
         let op_bytes = {
@@ -320,6 +523,7 @@ impl BitcoinRegtestController {
         self.finalize_tx(
             &mut tx, 
             DUST_UTXO_LIMIT,
+            tx_fee,
             utxos,
             signer)?;
 
@@ -330,11 +534,11 @@ impl BitcoinRegtestController {
         Some(tx)
     }
 
-    fn build_leader_block_commit_tx(&mut self, payload: LeaderBlockCommitOp, signer: &mut BurnchainOpSigner) -> Option<Transaction> {
+    fn build_leader_block_commit_tx(&mut self, payload: LeaderBlockCommitOp, signer: &mut BurnchainOpSigner, tx_fee: u64, sats_per_byte: u64) -> Option<Transaction> {
 
         let public_key = signer.get_public_key();
 
-        let (mut tx, utxos) = self.prepare_tx(&public_key, payload.burn_fee)?;
+        let (mut tx, utxos) = self.prepare_tx(&public_key, payload.burn_fee, tx_fee)?;
 
         // Serialize the payload
         let op_bytes = {
@@ -370,19 +574,19 @@ impl BitcoinRegtestController {
         self.finalize_tx(
             &mut tx,
             payload.burn_fee,
+            tx_fee,
             utxos,
             signer)?;
 
         increment_btc_ops_sent_counter();
 
-        info!("Miner node: submitting leader_block_commit op - {}", public_key.to_hex());
+        info!("Miner node: submitting leader_block_commit op - {} ({} sats/vbyte, fee {})", public_key.to_hex(), sats_per_byte, tx_fee);
 
         Some(tx)
     }
 
-    fn prepare_tx(&self, public_key: &Secp256k1PublicKey, ops_fee: u64) -> Option<(Transaction, Vec<UTXO>)> {
+    fn prepare_tx(&self, public_key: &Secp256k1PublicKey, ops_fee: u64, tx_fee: u64) -> Option<(Transaction, Vec<UTXO>)> {
         
-        let tx_fee = self.config.burnchain.burnchain_op_tx_fee;
         let amount_required = tx_fee + ops_fee;
 
         // Fetch some UTXOs
@@ -423,9 +627,7 @@ impl BitcoinRegtestController {
         Some((transaction, utxos))
     }
 
-    fn finalize_tx(&self, tx: &mut Transaction, total_spent: u64, utxos: Vec<UTXO>, signer: &mut BurnchainOpSigner) -> Option<()> {
-
-        let tx_fee = self.config.burnchain.burnchain_op_tx_fee;
+    fn finalize_tx(&self, tx: &mut Transaction, total_spent: u64, tx_fee: u64, utxos: Vec<UTXO>, signer: &mut BurnchainOpSigner) -> Option<()> {
 
         // Append the change output
         let total_unspent: u64 = utxos.iter().map(|o| o.amount).sum();
@@ -488,6 +690,7 @@ impl BitcoinRegtestController {
     fn send_transaction(&self, transaction: SerializedTx) -> bool {
         let result = BitcoinRPCRequest::send_raw_transaction(
             &self.config,
+            &self.endpoint_status,
             transaction.to_hex());
         match result {
             Ok(_) => {
@@ -516,6 +719,7 @@ impl BitcoinRegtestController {
 
         let result = BitcoinRPCRequest::generate_to_address(
             &self.config,
+            &self.endpoint_status,
             num_blocks,
             address.to_b58());
 
@@ -546,7 +750,8 @@ impl BurnchainController for BitcoinRegtestController {
             }
         };
 
-        let db = burnchain.open_db(true).unwrap();
+        let mut db = burnchain.open_db_with_pragmas(true, &self.config.node.sqlite_pragmas).unwrap();
+        db.set_marf_node_cache_size(self.config.node.marf_cache_size);
         self.db = Some(db);
 
         match self.db {
@@ -571,56 +776,53 @@ impl BurnchainController for BitcoinRegtestController {
         self.receive_blocks()
     }
 
-    fn sync(&mut self) -> BurnchainTip {        
-        let burnchain_tip = if self.config.burnchain.mode == "helium" {
-            // Helium: this node is responsible for mining new burnchain blocks
-            self.build_next_block(1);
-            self.receive_blocks()
-        } else {
-            // Neon: this node is waiting on a block to be produced
-            let current_height = self.get_chain_tip().block_snapshot.block_height;
-            loop {
-                let burnchain_tip = self.receive_blocks();
-                if burnchain_tip.block_snapshot.block_height > current_height {
-                    break burnchain_tip;
-                }
-                sleep_ms(5000);
-            }
-        };
-
-        // Evaluate process_exit_at_block_height setting
-        if let Some(cap) = self.config.burnchain.process_exit_at_block_height {
-            if burnchain_tip.block_snapshot.block_height >= cap {
-                info!("Node succesfully reached the end of the ongoing {} blocks epoch!", cap);
-                info!("This process will automatically terminate in 30s, restart your node for participating in the next epoch.");
-                sleep_ms(30000);
-                std::process::exit(0);
-            }    
-        }
-        burnchain_tip
+    fn sync(&mut self) -> BurnchainTip {
+        self.sync_unless_stopped()
+            .expect("BUG: burnchain sync stopped without a termination switch")
     }
 
     // returns true if the operation was submitted successfully, false otherwise 
     fn submit_operation(&mut self, operation: BlockstackOperationType, op_signer: &mut BurnchainOpSigner) -> bool {
+        let mut commit_fee_rate = None;
         let transaction = match operation {
-            BlockstackOperationType::LeaderBlockCommit(payload) 
-                => self.build_leader_block_commit_tx(payload, op_signer),
-                BlockstackOperationType::LeaderKeyRegister(payload) 
+            BlockstackOperationType::LeaderBlockCommit(payload) => {
+                let (tx_fee, sats_per_byte) = self.get_block_commit_fee();
+                commit_fee_rate = Some(sats_per_byte);
+                self.build_leader_block_commit_tx(payload, op_signer, tx_fee, sats_per_byte)
+            },
+            BlockstackOperationType::LeaderKeyRegister(payload) 
                 => self.build_leader_key_register_tx(payload, op_signer),
-                BlockstackOperationType::UserBurnSupport(payload) 
+            BlockstackOperationType::UserBurnSupport(payload) 
                 => self.build_user_burn_support_tx(payload, op_signer)
         };
 
-        let transaction = match transaction {
-            Some(tx) => SerializedTx::new(tx),
+        let (txid, transaction) = match transaction {
+            Some(tx) => (Txid::from_vec_be(&tx.txid().as_bytes().to_vec()).expect("BUG: invalid txid"), SerializedTx::new(tx)),
             _ => {
                 return false
             }
         };
 
-        self.send_transaction(transaction)
+        let sent = self.send_transaction(transaction);
+        if let (true, Some(sats_per_byte)) = (sent, commit_fee_rate) {
+            set_last_block_commit_fee_rate(sats_per_byte);
+            let submitted_at_height = self.chain_tip.as_ref().map(|tip| tip.block_snapshot.block_height).unwrap_or(0);
+            self.pending_commits.push(PendingBlockCommit { txid, sats_per_byte, submitted_at_height });
+        }
+        sent
     }
     
+    #[cfg(test)]
+    fn schedule_empty_block(&mut self, _block_height: u64) -> Result<(), String> {
+        // bitcoind mines whatever is in its mempool, so ops can't be held back here
+        Err("Empty burnchain blocks can only be scheduled in mocknet mode".to_string())
+    }
+
+    #[cfg(test)]
+    fn schedule_flash_blocks(&mut self, _block_height: u64, _count: u64) -> Result<(), String> {
+        Err("Flash burnchain blocks can only be scheduled in mocknet mode".to_string())
+    }
+
     #[cfg(test)]
     fn bootstrap_chain(&mut self, num_blocks: u64) {
 
@@ -635,10 +837,11 @@ impl BurnchainController for BitcoinRegtestController {
                 .expect("Public key incorrect");
 
             let _result = BitcoinRPCRequest::import_public_key(
-                &self.config, &Secp256k1PublicKey::from_hex(local_mining_pubkey).unwrap());
+                &self.config, &self.endpoint_status, &Secp256k1PublicKey::from_hex(local_mining_pubkey).unwrap());
     
             let result = BitcoinRPCRequest::generate_to_address(
                 &self.config, 
+                &self.endpoint_status,
                 num_blocks,
                 address.to_b58());
 
@@ -761,6 +964,38 @@ impl ParsedUTXO {
     }
 }
 
+/// The fee for a block-commit, and the feerate (sats/vbyte) it comes to.  With fee estimation
+/// off, this is the flat `burnchain_op_tx_fee`.  With it on, the feerate is bitcoind's
+/// `estimate`, or a static one for the confirmation target if bitcoind has no estimate (e.g. on
+/// regtest, or right after startup), capped at `max_satoshis_per_byte`; the fee is never less
+/// than the flat one.
+pub fn block_commit_fee(burnchain: &BurnchainConfig, estimate: Option<u64>) -> (u64, u64) {
+    if !burnchain.fee_estimation {
+        let tx_fee = burnchain.burnchain_op_tx_fee;
+        return (tx_fee, cmp::max(1, tx_fee / BLOCK_COMMIT_TX_ESTIMATED_SIZE));
+    }
+
+    let sats_per_byte = estimate.unwrap_or_else(|| {
+        FALLBACK_FEE_RATES.iter()
+            .find(|(target, _)| *target >= burnchain.fee_estimation_conf_target)
+            .map(|(_, rate)| *rate)
+            .unwrap_or(1)
+    });
+    let sats_per_byte = cmp::min(sats_per_byte, burnchain.max_satoshis_per_byte);
+    let tx_fee = cmp::max(burnchain.burnchain_op_tx_fee, sats_per_byte * BLOCK_COMMIT_TX_ESTIMATED_SIZE);
+    (tx_fee, sats_per_byte)
+}
+
+/// Read the feerate, in sats/vbyte, out of bitcoind's answer to `estimatesmartfee`.  bitcoind
+/// gives it in BTC/kvB, as a JSON number that may be written in exponent form.
+pub fn parse_fee_rate_estimate(json_resp: &serde_json::Value) -> Option<u64> {
+    json_resp.get("result")
+        .and_then(|result| result.get("feerate"))
+        .and_then(|feerate| feerate.as_f64())
+        .filter(|btc_per_kvb| *btc_per_kvb > 0.0)
+        .map(|btc_per_kvb| cmp::max(1, (btc_per_kvb * 100_000_000.0 / 1000.0).round() as u64))
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 struct BitcoinRPCRequest {
     /// The name of the RPC call
@@ -776,6 +1011,8 @@ struct BitcoinRPCRequest {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 enum RPCError {
     Network(String),
+    /// The bitcoind timed out, couldn't be reached, or answered with a server error
+    Unavailable(String),
     Parsing(String),
     Bitcoind(String),
 }
@@ -784,19 +1021,19 @@ type RPCResult<T> = Result<T, RPCError>;
 
 impl BitcoinRPCRequest {
 
-    fn build_rpc_request(config: &Config) -> Request {
+    fn build_rpc_request(endpoint: &BurnchainEndpoint) -> Request {
         let url = {
-            let url = config.burnchain.get_rpc_url();
+            let url = endpoint.get_rpc_url();
             Url::parse(&url).expect(&format!("Unable to parse {} as a URL", url))
         };
         debug!("BitcoinRPC builder: {:?}:{:?}@{}", 
-            &config.burnchain.username, 
-            &config.burnchain.password,
+            &endpoint.username, 
+            &endpoint.password,
             &url);
 
         let mut req = Request::new(Method::Post, url);
 
-        match (&config.burnchain.username, &config.burnchain.password) {
+        match (&endpoint.username, &endpoint.password) {
             (Some(username), Some(password)) => {
                 let auth_token = format!("Basic {}", encode(format!("{}:{}", username, password)));
                 req.append_header("Authorization", auth_token).expect("Unable to set header");
@@ -806,7 +1043,7 @@ impl BitcoinRPCRequest {
         req
     }
 
-    pub fn generate_to_address(config: &Config, num_blocks: u64, address: String) -> RPCResult<()> {
+    pub fn generate_to_address(config: &Config, endpoint_status: &EndpointStatus, num_blocks: u64, address: String) -> RPCResult<()> {
         debug!("Generate {} blocks to {}", num_blocks, address);
         let payload = BitcoinRPCRequest {
             method: "generatetoaddress".to_string(),
@@ -815,11 +1052,11 @@ impl BitcoinRPCRequest {
             jsonrpc: "2.0".to_string(),
         };
 
-        BitcoinRPCRequest::send(&config, payload)?;
+        BitcoinRPCRequest::send(&config, endpoint_status, payload)?;
         Ok(())
     }
 
-    pub fn list_unspent(config: &Config, addresses: Vec<String>, include_unsafe: bool, minimum_sum_amount: u64) -> RPCResult<Vec<UTXO>> {
+    pub fn list_unspent(config: &Config, endpoint_status: &EndpointStatus, addresses: Vec<String>, include_unsafe: bool, minimum_sum_amount: u64) -> RPCResult<Vec<UTXO>> {
         let min_conf = 0;
         let max_conf = 9999999;
         let minimum_amount = ParsedUTXO::sat_to_serialized_btc(minimum_sum_amount);
@@ -838,7 +1075,7 @@ impl BitcoinRPCRequest {
             jsonrpc: "2.0".to_string(),
         };
 
-        let mut res = BitcoinRPCRequest::send(&config, payload)?;
+        let mut res = BitcoinRPCRequest::send(&config, endpoint_status, payload)?;
 
         match res.as_object_mut() {
             Some(ref mut object) => {
@@ -890,7 +1127,26 @@ impl BitcoinRPCRequest {
         Ok(vec![])
     }
 
-    pub fn send_raw_transaction(config: &Config, tx: String) -> RPCResult<()> {
+    /// Ask bitcoind for a feerate that should get a transaction mined within `conf_target`
+    /// blocks.  Returns None if bitcoind doesn't have enough data to make an estimate.
+    pub fn estimate_smart_fee(config: &Config, endpoint_status: &EndpointStatus, conf_target: u16) -> RPCResult<Option<u64>> {
+        let payload = BitcoinRPCRequest {
+            method: "estimatesmartfee".to_string(),
+            params: vec![conf_target.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let json_resp = BitcoinRPCRequest::send(&config, endpoint_status, payload)?;
+
+        let sats_per_byte = parse_fee_rate_estimate(&json_resp);
+        if sats_per_byte.is_none() {
+            debug!("No fee estimate available from bitcoind: {}", json_resp);
+        }
+        Ok(sats_per_byte)
+    }
+
+    pub fn send_raw_transaction(config: &Config, endpoint_status: &EndpointStatus, tx: String) -> RPCResult<()> {
         let payload = BitcoinRPCRequest {
             method: "sendrawtransaction".to_string(),
             params: vec![tx.into()],
@@ -898,7 +1154,7 @@ impl BitcoinRPCRequest {
             jsonrpc: "2.0".to_string(),
         };
 
-        let json_resp = BitcoinRPCRequest::send(&config, payload)?;
+        let json_resp = BitcoinRPCRequest::send(&config, endpoint_status, payload)?;
 
         if let Some(e) = json_resp.get("error") {
             if !e.is_null() {
@@ -909,30 +1165,139 @@ impl BitcoinRPCRequest {
         Ok(())
     }
 
-    pub fn import_public_key(config: &Config, public_key: &Secp256k1PublicKey) -> RPCResult<()> {
-        let rescan = true;
-        let label = "";
-
+    /// Have every configured bitcoind watch this public key's address.  A bitcoind that can't
+    /// import it now imports it before it's next sent a call.  Succeeds if any bitcoind
+    /// imported it.
+    pub fn import_public_key(config: &Config, endpoint_status: &EndpointStatus, public_key: &Secp256k1PublicKey) -> RPCResult<()> {
         let pkh = Hash160::from_data(&public_key.to_bytes()).to_bytes().to_vec();
         let address = BitcoinAddress::from_bytes(
             BitcoinNetworkType::Regtest,
             BitcoinAddressType::PublicKeyHash,
             &pkh)
             .expect("Public key incorrect");        
+        let address = address.to_b58();
+        endpoint_status.watch_address(&address);
+
+        let mut result = Err(RPCError::Network("Bitcoin RPC: no bitcoind configured".to_string()));
+        for endpoint in config.burnchain.get_endpoints().iter() {
+            match BitcoinRPCRequest::import_address(config, endpoint_status, endpoint, &address) {
+                Ok(()) => {
+                    result = Ok(());
+                },
+                Err(e) => {
+                    warn!("Bitcoin RPC: {} failed to import {}: {:?}", endpoint.get_rpc_url(), &address, &e);
+                    if result.is_err() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Have one bitcoind watch an address, rescanning the chain for its past transactions
+    fn import_address(config: &Config, endpoint_status: &EndpointStatus, endpoint: &BurnchainEndpoint, address: &str) -> RPCResult<()> {
+        let rescan = true;
+        let label = "";
 
         let payload = BitcoinRPCRequest {
             method: "importaddress".to_string(),
-            params: vec![address.to_b58().into(), label.into(), rescan.into()],
+            params: vec![address.into(), label.into(), rescan.into()],
             id: "stacks".to_string(),
             jsonrpc: "2.0".to_string(),
         };
 
-        BitcoinRPCRequest::send(&config, payload)?;
+        BitcoinRPCRequest::send_to(config, endpoint_status, endpoint, &payload)?;
+        endpoint_status.mark_address_imported(endpoint, address);
         Ok(())
     }
 
-    fn send(config: &Config, payload: BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
-        let mut request = BitcoinRPCRequest::build_rpc_request(&config);
+    /// Ask one bitcoind for its block height.  This is how a bitcoind that's down is checked on.
+    fn get_block_count(config: &Config, endpoint_status: &EndpointStatus, endpoint: &BurnchainEndpoint) -> RPCResult<u64> {
+        let payload = BitcoinRPCRequest {
+            method: "getblockcount".to_string(),
+            params: vec![],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let json_resp = BitcoinRPCRequest::send_to(config, endpoint_status, endpoint, &payload)?;
+        json_resp.get("result")
+            .and_then(|result| result.as_u64())
+            .ok_or_else(|| RPCError::Parsing(format!("Bitcoin RPC: no block count in {}", json_resp)))
+    }
+
+    /// Send a call to the configured bitcoinds, failing over from each one that's unavailable
+    /// to the next
+    fn send(config: &Config, endpoint_status: &EndpointStatus, payload: BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
+        let endpoints = config.burnchain.get_endpoints();
+        let mut last_error = None;
+        for i in endpoint_status.endpoint_order(&endpoints).into_iter() {
+            match BitcoinRPCRequest::send_with_imports(config, endpoint_status, &endpoints[i], &payload) {
+                Err(RPCError::Unavailable(e)) => {
+                    last_error = Some(RPCError::Unavailable(e));
+                },
+                result => return result
+            }
+        }
+        Err(last_error.unwrap_or_else(|| RPCError::Network("Bitcoin RPC: no bitcoind configured".to_string())))
+    }
+
+    /// Send a call to one bitcoind, once it has imported every watched address.  A bitcoind that
+    /// was down, or not yet used, when an address was imported would otherwise not know about
+    /// its UTXOs.
+    fn send_with_imports(config: &Config, endpoint_status: &EndpointStatus, endpoint: &BurnchainEndpoint, payload: &BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
+        for address in endpoint_status.missing_addresses(endpoint).iter() {
+            match BitcoinRPCRequest::import_address(config, endpoint_status, endpoint, address) {
+                Err(RPCError::Unavailable(e)) => {
+                    return Err(RPCError::Unavailable(e));
+                },
+                Err(e) => {
+                    warn!("Bitcoin RPC: {} failed to import {}: {:?}", endpoint.get_rpc_url(), address, &e);
+                },
+                Ok(()) => {}
+            }
+        }
+        BitcoinRPCRequest::send_to(config, endpoint_status, endpoint, payload)
+    }
+
+    /// Send a call to one bitcoind, and note whether it's up or down
+    fn send_to(config: &Config, endpoint_status: &EndpointStatus, endpoint: &BurnchainEndpoint, payload: &BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
+        let result = BitcoinRPCRequest::send_request(config, endpoint, payload);
+        match result {
+            Err(RPCError::Unavailable(ref e)) => endpoint_status.mark_endpoint_down(endpoint, get_epoch_time_secs(), e),
+            _ => endpoint_status.mark_endpoint_up(endpoint)
+        }
+        result
+    }
+
+    /// How long to wait on a call before giving up on the bitcoind.  With only one bitcoind
+    /// there's nothing to fail over to, so calls take as long as they take.  Importing an
+    /// address rescans the chain, which can take far longer than any other call, so it isn't
+    /// timed out either.
+    fn rpc_timeout(config: &Config, payload: &BitcoinRPCRequest) -> Option<Duration> {
+        if config.burnchain.backup_endpoints.len() == 0 || payload.method == "importaddress" {
+            None
+        }
+        else {
+            Some(Duration::from_secs(config.burnchain.rpc_timeout))
+        }
+    }
+
+    /// Wait for part of a call, for no longer than `timeout` if it's given
+    fn block_on_rpc<F: Future>(timeout: Option<Duration>, what: &str, future: F) -> RPCResult<F::Output> {
+        match timeout {
+            Some(timeout) => async_std::task::block_on(async_std::future::timeout(timeout, future))
+                .map_err(|_| RPCError::Unavailable(format!("Bitcoin RPC: no {} in {}s", what, timeout.as_secs()))),
+            None => Ok(async_std::task::block_on(future))
+        }
+    }
+
+    fn send_request(config: &Config, endpoint: &BurnchainEndpoint, payload: &BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
+        let mut request = BitcoinRPCRequest::build_rpc_request(endpoint);
+        let socket_addr = endpoint.get_rpc_socket_addr()
+            .map_err(|e| RPCError::Unavailable(format!("Bitcoin RPC: {}", e)))?;
+        let timeout = BitcoinRPCRequest::rpc_timeout(config, payload);
 
         let body = match serde_json::to_vec(&json!(payload)) {
             Ok(body) => body,
@@ -944,11 +1309,11 @@ impl BitcoinRPCRequest {
         request.append_header("Content-Length", format!("{}", body.len())).expect("Unable to set header");
         request.set_body(body);
 
-        let mut response = async_std::task::block_on(async move {
-            let stream = match TcpStream::connect(config.burnchain.get_rpc_socket_addr()).await {
+        let mut response = BitcoinRPCRequest::block_on_rpc(timeout, "response", async move {
+            let stream = match TcpStream::connect(socket_addr).await {
                 Ok(stream) => stream,
                 Err(err) => {
-                    return Err(RPCError::Network(
+                    return Err(RPCError::Unavailable(
                         format!("Bitcoin RPC: connection failed - {:?}", err)))    
                 }
             };    
@@ -956,26 +1321,38 @@ impl BitcoinRPCRequest {
             match client::connect(stream, request).await {
                 Ok(response) => Ok(response),
                 Err(err) => {
-                    return Err(RPCError::Network(
+                    return Err(RPCError::Unavailable(
                         format!("Bitcoin RPC: invoking procedure failed - {:?}", err)))    
                 }
             }
-        })?;
+        })??;
 
-        if !response.status().is_success() {
-            return Err(RPCError::Network(
-                format!("Bitcoin RPC: status({}) != success, {:?}", response.status(), response)))
-        }
-        
-        let (res, buffer) = async_std::task::block_on(async move {
+        let status = response.status();
+        let (res, buffer) = BitcoinRPCRequest::block_on_rpc(timeout, "response body", async move {
             let mut buffer = Vec::new();
             let mut body = response.take_body();
             let res = body.read_to_end(&mut buffer).await;
             (res, buffer)
-        });
+        })?;
 
-        if res.is_err() {
+        if !status.is_success() {
+            // bitcoind answers calls that fail, like a rejected transaction, with a server error
+            // and the reason in the body.  Those aren't a reason to fail over.
+            let rpc_error = serde_json::from_slice::<serde_json::Value>(&buffer[..]).ok()
+                .filter(|payload| payload.get("error").map(|e| !e.is_null()).unwrap_or(false));
+            if let Some(payload) = rpc_error {
+                return Err(RPCError::Bitcoind(payload.to_string()))
+            }
+            if status.is_server_error() {
+                return Err(RPCError::Unavailable(
+                    format!("Bitcoin RPC: status({}) != success", status)))
+            }
             return Err(RPCError::Network(
+                format!("Bitcoin RPC: status({}) != success", status)))
+        }
+
+        if res.is_err() {
+            return Err(RPCError::Unavailable(
                 format!("Bitcoin RPC: unable to read body - {:?}", res)))
         }
         
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::config::BurnchainEndpoint;
+
+/// Seconds a bitcoind that failed is passed over before it's checked again
+pub const ENDPOINT_RETRY_SECS: u64 = 60;
+
+/// What a burnchain controller knows about its bitcoinds:  which ones are down, and which of the
+/// addresses it watches each one has imported.  Shared with the thread that checks on the ones
+/// that are down.
+#[derive(Debug, Default)]
+pub struct EndpointStatus {
+    inner: Mutex<EndpointStatusInner>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointStatusInner {
+    /// when each bitcoind that's down, by RPC URL, last failed
+    failures: HashMap<String, u64>,
+    /// the addresses every bitcoind should watch, in the order they were first imported
+    watched_addresses: Vec<String>,
+    /// the watched addresses each bitcoind, by RPC URL, has imported
+    imported_addresses: HashMap<String, HashSet<String>>,
+}
+
+impl EndpointStatus {
+    pub fn new() -> EndpointStatus {
+        EndpointStatus::default()
+    }
+
+    /// The order to try bitcoinds in:  the ones that are up, in the order they're configured,
+    /// and then the ones that are down, the one that failed longest ago first.  Every one is
+    /// tried, so that a call only fails if they're all down.
+    pub fn endpoint_order(&self, endpoints: &[BurnchainEndpoint]) -> Vec<usize> {
+        let inner = self.inner.lock().unwrap();
+        let (mut up, mut down) : (Vec<usize>, Vec<usize>) = (0..endpoints.len())
+            .partition(|i| !inner.failures.contains_key(&endpoints[*i].get_rpc_url()));
+        down.sort_by_key(|i| inner.failures.get(&endpoints[*i].get_rpc_url()).cloned());
+        up.extend(down);
+        up
+    }
+
+    /// The bitcoinds that are down and haven't been checked for `ENDPOINT_RETRY_SECS`
+    pub fn endpoints_due_for_check(&self, endpoints: &[BurnchainEndpoint], now: u64) -> Vec<usize> {
+        let inner = self.inner.lock().unwrap();
+        (0..endpoints.len())
+            .filter(|i| {
+                inner.failures.get(&endpoints[*i].get_rpc_url())
+                    .map(|failed_at| now.saturating_sub(*failed_at) >= ENDPOINT_RETRY_SECS)
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Take the bitcoinds that are due for a check, and put off checking them again for another
+    /// `ENDPOINT_RETRY_SECS`, so that only one check of each is under way at a time
+    pub fn start_endpoint_checks(&self, endpoints: &[BurnchainEndpoint], now: u64) -> Vec<usize> {
+        let due = self.endpoints_due_for_check(endpoints, now);
+        let mut inner = self.inner.lock().unwrap();
+        for i in due.iter() {
+            inner.failures.insert(endpoints[*i].get_rpc_url(), now);
+        }
+        due
+    }
+
+    /// Note that a bitcoind timed out, couldn't be reached, or answered with a server error
+    pub fn mark_endpoint_down(&self, endpoint: &BurnchainEndpoint, now: u64, reason: &str) {
+        let url = endpoint.get_rpc_url();
+        if self.inner.lock().unwrap().failures.insert(url.clone(), now).is_none() {
+            warn!("Bitcoin RPC: {} is unavailable, failing over: {}", &url, reason);
+        }
+    }
+
+    /// Note that a bitcoind answered
+    pub fn mark_endpoint_up(&self, endpoint: &BurnchainEndpoint) {
+        let url = endpoint.get_rpc_url();
+        if self.inner.lock().unwrap().failures.remove(&url).is_some() {
+            info!("Bitcoin RPC: {} is available again", &url);
+        }
+    }
+
+    /// Start watching an address on every bitcoind
+    pub fn watch_address(&self, address: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.watched_addresses.iter().any(|watched| watched == address) {
+            inner.watched_addresses.push(address.to_string());
+        }
+    }
+
+    /// The watched addresses a bitcoind has yet to import
+    pub fn missing_addresses(&self, endpoint: &BurnchainEndpoint) -> Vec<String> {
+        let inner = self.inner.lock().unwrap();
+        let imported = inner.imported_addresses.get(&endpoint.get_rpc_url());
+        inner.watched_addresses.iter()
+            .filter(|address| imported.map(|imported| !imported.contains(*address)).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    /// Note that a bitcoind imported an address
+    pub fn mark_address_imported(&self, endpoint: &BurnchainEndpoint, address: &str) {
+        self.inner.lock().unwrap().imported_addresses
+            .entry(endpoint.get_rpc_url())
+            .or_insert_with(HashSet::new)
+            .insert(address.to_string());
+    }
+}
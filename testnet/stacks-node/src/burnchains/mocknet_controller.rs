@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashMap, HashSet};
 use std::time::Instant;
 
 use super::super::{Config};
@@ -25,6 +25,10 @@ pub struct MocknetController {
     db: Option<BurnDB>,
     chain_tip: Option<BurnchainTip>,
     queued_operations: VecDeque<BlockstackOperationType>,
+    /// heights of burnchain blocks that must not carry any ops
+    empty_blocks: HashSet<u64>,
+    /// heights at which a run of back-to-back empty blocks starts, and how long it is
+    flash_blocks: HashMap<u64, u64>,
 }
 
 impl MocknetController {
@@ -44,10 +48,12 @@ impl MocknetController {
             db: None,
             queued_operations: VecDeque::new(),
             chain_tip: None,
+            empty_blocks: HashSet::new(),
+            flash_blocks: HashMap::new(),
         }
     }
 
-    fn build_next_block_header(current_block: &BlockSnapshot) -> BurnchainBlockHeader {
+    fn build_next_block_header(current_block: &BlockSnapshot, timestamp: u64) -> BurnchainBlockHeader {
         let curr_hash = &current_block.burn_header_hash.to_bytes()[..];
         let next_hash = Sha256Sum::from_data(&curr_hash);
 
@@ -56,9 +62,29 @@ impl MocknetController {
             &BurnchainHeaderHash::from_bytes(next_hash.as_bytes()).unwrap(), 
             &current_block.burn_header_hash, 
             &vec![],
-            get_epoch_time_secs()));
+            timestamp));
         block.header(&current_block)
     }
+
+    /// Process a block with the given ops on top of `parent_snapshot`
+    fn process_block(&mut self, parent_snapshot: &BlockSnapshot, block_header: &BurnchainBlockHeader, ops: &Vec<BlockstackOperationType>) -> (BlockSnapshot, BurnchainStateTransition) {
+        match self.db {
+            None => {
+                unreachable!();
+            },
+            Some(ref mut burn_db) => {
+                let mut burn_tx = burn_db.tx_begin().unwrap();
+                let new_chain_tip = Burnchain::process_block_ops(
+                    &mut burn_tx, 
+                    &self.burnchain, 
+                    parent_snapshot, 
+                    block_header, 
+                    ops).unwrap();
+                burn_tx.commit().unwrap();
+                new_chain_tip
+            }
+        }
+    }
 }
 
 impl BurnchainController for MocknetController {
@@ -88,10 +114,11 @@ impl BurnchainController for MocknetController {
     }
    
     fn start(&mut self) -> BurnchainTip {
-        let db = match BurnDB::connect(&self.config.get_burn_db_file_path(), 0, &BurnchainHeaderHash([0u8; 32]), get_epoch_time_secs(), true) {
+        let mut db = match BurnDB::connect_with_pragmas(&self.config.get_burn_db_file_path(), 0, &BurnchainHeaderHash([0u8; 32]), get_epoch_time_secs(), true, &self.config.node.sqlite_pragmas) {
             Ok(db) => db,
             Err(_) => panic!("Error while connecting to burnchain db")
         };
+        db.set_marf_node_cache_size(self.config.node.marf_cache_size);
         let block_snapshot = BurnDB::get_canonical_burn_chain_tip(db.conn())
             .expect("FATAL: failed to get canonical chain tip");
 
@@ -117,10 +144,39 @@ impl BurnchainController for MocknetController {
     }
 
     fn sync(&mut self) -> BurnchainTip {
-        let chain_tip = self.get_chain_tip();
+        let mut chain_tip = self.get_chain_tip();
+        let now = get_epoch_time_secs();
+
+        // Scheduled empty blocks: nothing gets mined, and the queued ops wait for the next block
+        let next_height = chain_tip.block_snapshot.block_height + 1;
+        let (num_empty_blocks, hold_ops) = 
+            if self.empty_blocks.remove(&next_height) {
+                (1, true)
+            }
+            else {
+                // flash blocks: empty blocks in quick succession, then the block with the ops
+                (self.flash_blocks.remove(&next_height).unwrap_or(0), false)
+            };
+
+        for _ in 0..num_empty_blocks {
+            let empty_block_header = Self::build_next_block_header(&chain_tip.block_snapshot, now);
+            debug!("Mocknet: mining empty block {} ({})", empty_block_header.block_height, &empty_block_header.block_hash);
+            let (block_snapshot, state_transition) = self.process_block(&chain_tip.block_snapshot, &empty_block_header, &vec![]);
+            chain_tip = BurnchainTip {
+                block_snapshot,
+                state_transition,
+                received_at: Instant::now()
+            };
+        }
+
+        if hold_ops {
+            // the ops go in a later block
+            self.chain_tip = Some(chain_tip.clone());
+            return chain_tip;
+        }
 
         // Simulating mining
-        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot);
+        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot, now);
         let mut vtxindex = 1;
         let mut ops = vec![];
 
@@ -177,24 +233,7 @@ impl BurnchainController for MocknetController {
         }
 
         // Include txs in a new block   
-        let (block_snapshot, state_transition) = {
-            match self.db {
-                None => {
-                    unreachable!();
-                },
-                Some(ref mut burn_db) => {
-                    let mut burn_tx = burn_db.tx_begin().unwrap();
-                    let new_chain_tip = Burnchain::process_block_ops(
-                        &mut burn_tx, 
-                        &self.burnchain, 
-                        &chain_tip.block_snapshot, 
-                        &next_block_header, 
-                        &ops).unwrap();
-                    burn_tx.commit().unwrap();
-                    new_chain_tip
-                }
-            }
-        };
+        let (block_snapshot, state_transition) = self.process_block(&chain_tip.block_snapshot, &next_block_header, &ops);
 
         // Transmit the new state
         let new_state = BurnchainTip {
@@ -209,5 +248,17 @@ impl BurnchainController for MocknetController {
 
     #[cfg(test)]
     fn bootstrap_chain(&mut self, _num_blocks: u64) {}
+
+    #[cfg(test)]
+    fn schedule_empty_block(&mut self, block_height: u64) -> Result<(), String> {
+        self.empty_blocks.insert(block_height);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn schedule_flash_blocks(&mut self, block_height: u64, count: u64) -> Result<(), String> {
+        self.flash_blocks.insert(block_height, count);
+        Ok(())
+    }
 }
 
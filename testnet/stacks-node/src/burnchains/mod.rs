@@ -1,5 +1,6 @@
 pub mod mocknet_controller;
 pub mod bitcoin_regtest_controller;
+pub mod endpoints;
 
 pub use self::mocknet_controller::{MocknetController};
 pub use self::bitcoin_regtest_controller::{BitcoinRegtestController};
@@ -23,6 +24,17 @@ pub trait BurnchainController {
 
     #[cfg(test)]
     fn bootstrap_chain(&mut self, blocks_count: u64);
+
+    /// Have the burnchain block at `block_height` carry no Stacks operations.  Operations
+    /// submitted in the meantime are held back for the next block.  Only mocknet can do this.
+    #[cfg(test)]
+    fn schedule_empty_block(&mut self, block_height: u64) -> Result<(), String>;
+
+    /// Have `count` empty burnchain blocks mined back-to-back, starting at `block_height`, ahead
+    /// of the block that carries the queued operations.  The run loop only sees the last one.
+    /// Only mocknet can do this.
+    #[cfg(test)]
+    fn schedule_flash_blocks(&mut self, block_height: u64, count: u64) -> Result<(), String>;
 }
 
 #[derive(Debug, Clone)]
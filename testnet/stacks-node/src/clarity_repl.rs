@@ -0,0 +1,436 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use rand::RngCore;
+
+use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::stacks::index::MarfTrieId;
+use stacks::chainstate::stacks::index::backend::TrieBackend;
+use stacks::util::db::DBConn;
+use stacks::util::hash::to_hex;
+use stacks::vm::{ContractName, SymbolicExpression, Value, execute as vm_execute};
+use stacks::vm::analysis;
+use stacks::vm::analysis::ContractAnalysis;
+use stacks::vm::analysis::lint::Linter;
+use stacks::vm::ast::build_ast;
+use stacks::vm::contexts::OwnedEnvironment;
+use stacks::vm::costs::LimitedCostTracker;
+use stacks::vm::database::{HeadersDB, MarfedKV, NULL_HEADER_DB};
+use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier};
+
+/// The sender of deploys and calls until `:sender` changes it
+pub const DEFAULT_REPL_SENDER: &str = "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM";
+
+const REPL_HELP: &str = "\
+Enter a Clarity expression to evaluate it, or one of these commands:
+
+  :deploy <name> <file.clar>              deploy a contract as the sender
+  :lint <name> <file.clar>                check a contract for likely mistakes, without deploying it
+  :call <contract> <function> [args...]   call a public function as the sender
+  :contract [contract]                    evaluate expressions in a contract, or outside of any
+  :sender [principal]                     show or set the sender
+  :help                                   show this help
+  :quit                                   exit, discarding everything
+";
+
+/// An interactive Clarity session over a scratch datastore.  The datastore is empty, or a copy
+/// of a node's Clarity state; either way, nothing is ever written back, and it is deleted when
+/// the session ends.
+pub struct ClarityRepl {
+    marf: MarfedKV,
+    /// the forked node's headers, for `get-block-info?`
+    headers_db: Option<DBConn>,
+    sender: PrincipalData,
+    /// the contract expressions are evaluated in, if any
+    contract_context: Option<QualifiedContractIdentifier>,
+    scratch_dir: PathBuf,
+}
+
+fn make_scratch_dir() -> Result<PathBuf, String> {
+    let mut random_bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut random_bytes);
+
+    let mut scratch_dir = std::env::temp_dir();
+    scratch_dir.push(format!("clarity-repl-{}", to_hex(&random_bytes)));
+    fs::create_dir_all(&scratch_dir)
+        .map_err(|e| format!("Failed to create {}: {:?}", scratch_dir.display(), &e))?;
+    Ok(scratch_dir)
+}
+
+/// Find the `vm` directory of a chainstate directory: either the node's `chainstate` directory,
+/// or the `chain-*` directory in it.
+pub fn find_vm_dir(chainstate_dir: &str) -> Result<PathBuf, String> {
+    let vm_dir = Path::new(chainstate_dir).join("vm");
+    if vm_dir.is_dir() {
+        return Ok(vm_dir);
+    }
+
+    let entries = fs::read_dir(chainstate_dir)
+        .map_err(|e| format!("Failed to read {}: {:?}", chainstate_dir, &e))?;
+    let mut vm_dirs = vec![];
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read {}: {:?}", chainstate_dir, &e))?.path();
+        let is_chain = path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with("chain-"))
+            .unwrap_or(false);
+        if is_chain && path.join("vm").is_dir() {
+            vm_dirs.push(path.join("vm"));
+        }
+    }
+
+    match vm_dirs.len() {
+        1 => Ok(vm_dirs.pop().unwrap()),
+        0 => Err(format!("No chainstate found in {}", chainstate_dir)),
+        _ => Err(format!("More than one chainstate found in {}; pass the one to fork", chainstate_dir))
+    }
+}
+
+/// Copy a sqlite database, holding a read transaction on it so that the copy is consistent even
+/// if a node is writing to it
+fn copy_sqlite_db(from: &Path, to: &Path) -> Result<(), String> {
+    let conn = DBConn::open(from)
+        .map_err(|e| format!("Failed to open {}: {:?}", from.display(), &e))?;
+    conn.execute_batch("BEGIN; SELECT count(*) FROM sqlite_master;")
+        .map_err(|e| format!("Failed to lock {}: {:?}", from.display(), &e))?;
+    let copied = fs::copy(from, to)
+        .map_err(|e| format!("Failed to copy {}: {:?}", from.display(), &e));
+    let _ = conn.execute_batch("COMMIT;");
+    copied.map(|_| ())
+}
+
+/// Split a command's arguments on whitespace, keeping parenthesized expressions and strings whole
+pub fn split_repl_args(args: &str) -> Vec<String> {
+    let mut split = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in args.chars() {
+        if in_string {
+            current.push(c);
+            if escaped {
+                escaped = false;
+            }
+            else if c == '\\' {
+                escaped = true;
+            }
+            else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                current.push(c);
+            },
+            '(' | '{' => {
+                depth += 1;
+                current.push(c);
+            },
+            ')' | '}' => {
+                depth -= 1;
+                current.push(c);
+            },
+            c if c.is_whitespace() && depth <= 0 => {
+                if !current.is_empty() {
+                    split.push(current.clone());
+                    current.clear();
+                }
+            },
+            c => current.push(c)
+        }
+    }
+    if !current.is_empty() {
+        split.push(current);
+    }
+    split
+}
+
+impl ClarityRepl {
+    /// Start a session over an empty datastore
+    pub fn new() -> Result<ClarityRepl, String> {
+        let scratch_dir = make_scratch_dir()?;
+        let mut marf = MarfedKV::open(&scratch_dir.to_string_lossy(), None)
+            .map_err(|e| format!("Failed to open a scratch datastore: {:?}", &e))?;
+        marf.begin(&StacksBlockId::sentinel(), &StacksBlockId([1u8; 32]));
+
+        Ok(ClarityRepl::from_parts(marf, None, scratch_dir))
+    }
+
+    /// Start a session over a copy of a node's Clarity state, as of its highest block or the
+    /// given index block hash.  The node can keep running.
+    pub fn fork(chainstate_dir: &str, tip: Option<&str>) -> Result<ClarityRepl, String> {
+        let vm_dir = find_vm_dir(chainstate_dir)?;
+
+        // the node's headers DB is in WAL mode, so it can be read in place
+        let headers_path = vm_dir.join("headers.db");
+        let headers_db = DBConn::open(&headers_path)
+            .map_err(|e| format!("Failed to open {}: {:?}", headers_path.display(), &e))?;
+
+        let tip = match tip {
+            Some(tip) => StacksBlockId::from_hex(tip)
+                .map_err(|e| format!("Invalid tip {}: {:?}", tip, &e))?,
+            None => {
+                // the node writes a block's Clarity state before its header, so the state of
+                // any block found here has already been written
+                let no_params : [&str; 0] = [];
+                let tip_hex : String = headers_db.query_row("SELECT index_block_hash FROM block_headers ORDER BY block_height DESC LIMIT 1",
+                                                            no_params.iter(), |row| row.get(0))
+                    .map_err(|e| format!("Failed to find the chain tip in {}: {:?}", headers_path.display(), &e))?;
+                StacksBlockId::from_hex(&tip_hex)
+                    .map_err(|e| format!("Invalid chain tip {}: {:?}", &tip_hex, &e))?
+            }
+        };
+
+        let clarity_dir = vm_dir.join("clarity");
+        let marf_path = clarity_dir.join("marf");
+        if TrieBackend::detect(&marf_path.to_string_lossy()) == Some(TrieBackend::RocksDB) {
+            return Err(format!("{} is stored in RocksDB; only sqlite Clarity state can be forked", marf_path.display()));
+        }
+
+        let scratch_dir = make_scratch_dir()?;
+        for db_name in ["marf", "data.sqlite"].iter() {
+            if let Err(e) = copy_sqlite_db(&clarity_dir.join(db_name), &scratch_dir.join(db_name)) {
+                let _ = fs::remove_dir_all(&scratch_dir);
+                return Err(e);
+            }
+        }
+
+        let mut marf = MarfedKV::open(&scratch_dir.to_string_lossy(), None)
+            .map_err(|e| format!("Failed to open the forked datastore: {:?}", &e))?;
+        marf.begin(&tip, &StacksBlockId([2u8; 32]));
+
+        Ok(ClarityRepl::from_parts(marf, Some(headers_db), scratch_dir))
+    }
+
+    fn from_parts(marf: MarfedKV, headers_db: Option<DBConn>, scratch_dir: PathBuf) -> ClarityRepl {
+        ClarityRepl {
+            marf,
+            headers_db,
+            sender: PrincipalData::parse(DEFAULT_REPL_SENDER).expect("FATAL: bad default REPL sender"),
+            contract_context: None,
+            scratch_dir,
+        }
+    }
+
+    fn with_env<F, R>(&mut self, f: F) -> R
+    where F: FnOnce(&mut OwnedEnvironment) -> R {
+        let headers_db: &dyn HeadersDB = match self.headers_db {
+            Some(ref conn) => conn,
+            None => &NULL_HEADER_DB
+        };
+        let mut vm_env = OwnedEnvironment::new_cost_limited(self.marf.as_clarity_db(headers_db), LimitedCostTracker::new_max_limit());
+        f(&mut vm_env)
+    }
+
+    /// Handle one line of input.  Returns what to print, or None to quit.
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Some(String::new());
+        }
+        if !line.starts_with(':') {
+            return Some(self.eval(line));
+        }
+
+        let (command, args) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, "")
+        };
+        let output = match command {
+            ":quit" | ":q" => return None,
+            ":help" => REPL_HELP.to_string(),
+            ":sender" => self.set_sender(args),
+            ":contract" => self.set_contract_context(args),
+            ":deploy" => self.deploy(args),
+            ":lint" => self.lint(args),
+            ":call" => self.call(args),
+            _ => format!("Unknown command {}; try :help", command)
+        };
+        Some(output)
+    }
+
+    fn eval(&mut self, program: &str) -> String {
+        let sender = Value::Principal(self.sender.clone());
+        let result = match self.contract_context.clone() {
+            Some(contract_identifier) => self.with_env(|env| {
+                env.execute_in_env(sender, |exec_env| exec_env.eval_read_only(&contract_identifier, program))
+            }),
+            None => self.with_env(|env| {
+                env.execute_in_env(sender, |exec_env| exec_env.eval_raw(program))
+            })
+        };
+        match result {
+            Ok((value, _, _)) => format!("{}", value),
+            Err(e) => format!("Error: {}", e)
+        }
+    }
+
+    fn set_sender(&mut self, args: &str) -> String {
+        if args.is_empty() {
+            return format!("{}", &self.sender);
+        }
+        match PrincipalData::parse(args) {
+            Ok(sender) => {
+                self.sender = sender;
+                format!("Sender is {}", &self.sender)
+            },
+            Err(e) => format!("Invalid principal {}: {}", args, e)
+        }
+    }
+
+    fn set_contract_context(&mut self, args: &str) -> String {
+        if args.is_empty() {
+            self.contract_context = None;
+            return "Evaluating outside of any contract".to_string();
+        }
+        match QualifiedContractIdentifier::parse(args) {
+            Ok(contract_identifier) => {
+                let output = format!("Evaluating in {}", &contract_identifier);
+                self.contract_context = Some(contract_identifier);
+                output
+            },
+            Err(e) => format!("Invalid contract {}: {}", args, e)
+        }
+    }
+
+    /// Parse and analyze the contract named by `:deploy` or `:lint` arguments, as the sender
+    /// would deploy it
+    fn check_contract(&mut self, args: &str, usage: &str) -> Result<(QualifiedContractIdentifier, String, ContractAnalysis), String> {
+        let args = split_repl_args(args);
+        if args.len() != 2 {
+            return Err(usage.to_string());
+        }
+        let issuer = match self.sender {
+            PrincipalData::Standard(ref issuer) => issuer.clone(),
+            PrincipalData::Contract(_) => return Err("Contracts can't deploy contracts; set a standard principal as the sender".to_string())
+        };
+        let contract_name = ContractName::try_from(args[0].clone())
+            .map_err(|e| format!("Invalid contract name {}: {}", &args[0], e))?;
+        let contract_identifier = QualifiedContractIdentifier::new(issuer, contract_name);
+        let source = fs::read_to_string(&args[1])
+            .map_err(|e| format!("Failed to read {}: {}", &args[1], e))?;
+
+        // the same checks a deploy transaction goes through
+        let mut expressions = build_ast(&contract_identifier, &source, &mut ())
+            .map_err(|e| format!("Parse error: {}", e.diagnostic))?
+            .expressions;
+        let mut analysis_db = self.marf.as_analysis_db();
+        let contract_analysis = analysis::run_analysis(&contract_identifier, &mut expressions, &mut analysis_db, false, LimitedCostTracker::new_max_limit())
+            .map_err(|(e, _)| format!("Analysis error: {}", e.diagnostic))?;
+        Ok((contract_identifier, source, contract_analysis))
+    }
+
+    fn deploy(&mut self, args: &str) -> String {
+        let (contract_identifier, source, contract_analysis) = match self.check_contract(args, "Usage: :deploy <name> <file.clar>") {
+            Ok(checked) => checked,
+            Err(e) => return e
+        };
+        let warnings = Linter::run(&contract_analysis);
+
+        let init_identifier = contract_identifier.clone();
+        if let Err(e) = self.with_env(|env| env.initialize_contract(init_identifier, &source)) {
+            return format!("Error: {}", e);
+        }
+
+        let mut analysis_db = self.marf.as_analysis_db();
+        if let Err(e) = analysis_db.execute(|db| db.insert_contract(&contract_identifier, &contract_analysis)) {
+            return format!("Error: {}", e);
+        }
+        let mut output = format!("Deployed {}", &contract_identifier);
+        for warning in warnings.iter() {
+            output.push('\n');
+            output.push_str(warning.to_string().trim_end());
+        }
+        output
+    }
+
+    fn lint(&mut self, args: &str) -> String {
+        let contract_analysis = match self.check_contract(args, "Usage: :lint <name> <file.clar>") {
+            Ok((_, _, contract_analysis)) => contract_analysis,
+            Err(e) => return e
+        };
+        let warnings = Linter::run(&contract_analysis);
+        if warnings.is_empty() {
+            return "No warnings".to_string();
+        }
+        let warnings: Vec<String> = warnings.iter().map(|warning| warning.to_string().trim_end().to_string()).collect();
+        warnings.join("\n")
+    }
+
+    fn call(&mut self, args: &str) -> String {
+        let args = split_repl_args(args);
+        if args.len() < 2 {
+            return "Usage: :call <contract> <function> [args...]".to_string();
+        }
+        let contract_identifier = match QualifiedContractIdentifier::parse(&args[0]) {
+            Ok(contract_identifier) => contract_identifier,
+            Err(e) => return format!("Invalid contract {}: {}", &args[0], e)
+        };
+
+        let mut function_args = vec![];
+        for arg in args[2..].iter() {
+            match vm_execute(arg) {
+                Ok(Some(value)) => function_args.push(SymbolicExpression::atom_value(value)),
+                Ok(None) => return format!("Argument {} has no value", arg),
+                Err(e) => return format!("Invalid argument {}: {}", arg, e)
+            }
+        }
+
+        let sender = Value::Principal(self.sender.clone());
+        let function_name = args[1].clone();
+        match self.with_env(|env| env.execute_transaction(sender, contract_identifier, &function_name, &function_args)) {
+            Ok((value, _, events)) => {
+                let mut output = format!("{}", value);
+                for event in events.iter() {
+                    output.push_str(&format!("\n  {:?}", event));
+                }
+                output
+            },
+            Err(e) => format!("Error: {}", e)
+        }
+    }
+
+    /// Read lines from stdin and answer them on stdout, until `:quit` or the end of input
+    pub fn run(&mut self) {
+        println!("Clarity REPL; type :help for help.");
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        loop {
+            let _ = stdout.write_all(b"> ");
+            let _ = stdout.flush();
+
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {},
+                Err(e) => {
+                    eprintln!("Error reading from stdin: {}", e);
+                    break;
+                }
+            }
+
+            match self.handle_line(&line) {
+                Some(output) => {
+                    if !output.is_empty() {
+                        println!("{}", output);
+                    }
+                },
+                None => break
+            }
+        }
+    }
+}
+
+impl Drop for ClarityRepl {
+    fn drop(&mut self) {
+        self.marf.rollback();
+        let _ = fs::remove_dir_all(&self.scratch_dir);
+    }
+}
@@ -1,25 +1,44 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::{BufReader, Read};
 use std::fs::File;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::thread;
 
 use rand::RngCore;
 
 use stacks::burnchains::{
     MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
 use stacks::burnchains::bitcoin::indexer::FIRST_BLOCK_MAINNET;
+use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::stacks::db::StacksBlockCheckpoint;
+use stacks::chainstate::stacks::db::prune::{MIN_PRUNE_BURN_BLOCKS, MIN_PRUNE_FORK_DEPTH};
+use stacks::chainstate::stacks::db::compression::{is_block_compression_available, DEFAULT_BLOCK_COMPRESSION_LEVEL};
+use stacks::chainstate::stacks::index::backend::TrieBackend;
 use stacks::net::connection::ConnectionOptions;
+use stacks::net::http::HttpRequestType;
+use stacks::net::overload::RequestPriority;
 use stacks::net::{Neighbor, NeighborKey, PeerAddress};
 use stacks::util::secp256k1::Secp256k1PublicKey;
+use stacks::util::db::SqlitePragmas;
 use stacks::util::hash::{to_hex, hex_bytes};
 use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier, AssetIdentifier} ;
 use stacks::vm::costs::ExecutionCost;
 
+use super::keychain::WatchOnlyKeychain;
+use super::event_dispatcher::{EVENT_SCHEMA_DEFAULT_VERSION, EVENT_SCHEMA_SUPPORTED_VERSIONS};
 use super::node::TESTNET_CHAIN_ID;
 use super::neon_node::TESTNET_PEER_VERSION;
+use super::db_maintenance::{
+    DbMaintenanceConfig, parse_vacuum_hours,
+    DEFAULT_DB_ANALYZE_INTERVAL, DEFAULT_DB_VACUUM_INTERVAL, DEFAULT_DB_VACUUM_HOURS};
+use super::backup::{BackupConfig, DEFAULT_BACKUP_INTERVAL, DEFAULT_BACKUP_KEEP};
 
 const MINIMUM_DUST_FEE: u64 = 5500;
 
+/// Keep up to 64 MB of each MARF's trie nodes in RAM by default
+pub const DEFAULT_MARF_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
 #[derive(Clone, Deserialize, Default)]
 pub struct ConfigFile {
     pub burnchain: Option<BurnchainConfigFile>,
@@ -220,6 +239,10 @@ lazy_static! {
     };
 }
 
+/// The burnchain modes whose nodes can set their own `block_limit`
+pub const PRIVATE_TESTNET_MODES: &[&str] = &["helium", "mocknet"];
+
+/// The cost limit of a block, unless a private testnet sets its own
 pub const HELIUM_BLOCK_LIMIT: ExecutionCost = ExecutionCost {
     write_length: 15_0_000_000,
     write_count: 5_0_000,
@@ -264,8 +287,78 @@ impl Config {
                     mine_microblocks: node.mine_microblocks.unwrap_or(default_node_config.mine_microblocks),
                     wait_time_for_microblocks: node.wait_time_for_microblocks.unwrap_or(default_node_config.wait_time_for_microblocks),
                     prometheus_bind: node.prometheus_bind,
+                    watch_keychain: None,
+                    checkpoints: vec![],
+                    assume_valid: false,
+                    version_check_url: node.version_check_url,
+                    version_check_interval: node.version_check_interval.unwrap_or(default_node_config.version_check_interval),
+                    debug_bind: node.debug_bind,
+                    coverage_output: node.coverage_output,
+                    trace_contract_calls: node.trace_contract_calls.unwrap_or(default_node_config.trace_contract_calls),
+                    execution_trace_dir: node.execution_trace_dir,
+                    prune_burn_blocks: None,
+                    prune_fork_depth: None,
+                    marf_backend: default_node_config.marf_backend,
+                    marf_cache_size: node.marf_cache_size.unwrap_or(default_node_config.marf_cache_size),
+                    archive: node.archive.unwrap_or(default_node_config.archive),
+                    block_validation_threads: default_node_config.block_validation_threads,
+                    db_maintenance: None,
+                    backup: None,
+                    sqlite_pragmas: default_node_config.sqlite_pragmas.clone(),
+                    block_compression: None,
+                    light_sync: node.light_sync.unwrap_or(default_node_config.light_sync),
+                    index_addresses: node.index_addresses.unwrap_or(default_node_config.index_addresses),
                 };
                 node_config.set_bootstrap_node(node.bootstrap_node);
+                node_config.set_checkpoints(node.checkpoints.unwrap_or(vec![]), node.assume_valid.unwrap_or(false));
+                if let Some(prune_burn_blocks) = node.prune_burn_blocks {
+                    if node_config.archive {
+                        panic!("Config cannot set `node.prune_burn_blocks` together with `node.archive`")
+                    }
+                    node_config.set_prune_burn_blocks(prune_burn_blocks);
+                }
+                if let Some(prune_fork_depth) = node.prune_fork_depth {
+                    if node_config.archive {
+                        panic!("Config cannot set `node.prune_fork_depth` together with `node.archive`")
+                    }
+                    node_config.set_prune_fork_depth(prune_fork_depth);
+                }
+                if let Some(marf_backend) = node.marf_backend {
+                    node_config.set_marf_backend(&marf_backend);
+                }
+                if let Some(block_validation_threads) = node.block_validation_threads {
+                    node_config.set_block_validation_threads(block_validation_threads);
+                }
+                if node.db_maintenance.unwrap_or(false) {
+                    node_config.set_db_maintenance(
+                        node.db_analyze_interval.unwrap_or(DEFAULT_DB_ANALYZE_INTERVAL),
+                        node.db_vacuum_interval.unwrap_or(DEFAULT_DB_VACUUM_INTERVAL),
+                        node.db_vacuum_hours.as_ref().map(|hours| hours.as_str()).unwrap_or(DEFAULT_DB_VACUUM_HOURS));
+                }
+                if let Some(backup_dir) = node.backup_dir {
+                    node_config.set_backup(
+                        backup_dir,
+                        node.backup_interval.unwrap_or(DEFAULT_BACKUP_INTERVAL),
+                        node.backup_keep.unwrap_or(DEFAULT_BACKUP_KEEP));
+                }
+                if node.compress_blocks.unwrap_or(false) {
+                    node_config.set_block_compression(node.block_compression_level.unwrap_or(DEFAULT_BLOCK_COMPRESSION_LEVEL));
+                }
+                node_config.set_sqlite_pragmas(SqlitePragmas {
+                    page_size: node.db_page_size,
+                    cache_size: node.db_cache_size,
+                    mmap_size: node.db_mmap_size,
+                    synchronous: node.db_synchronous.unwrap_or(default_node_config.sqlite_pragmas.synchronous.clone()),
+                });
+                if node_config.light_sync && (node_config.miner || node_config.archive) {
+                    panic!("Config cannot set `node.light_sync` together with `node.miner` or `node.archive`")
+                }
+                if let Some(watch_public_keys) = node.watch_public_keys {
+                    if node.seed.is_some() || node_config.miner {
+                        panic!("Config cannot set `node.watch_public_keys` together with `node.seed` or `node.miner`")
+                    }
+                    node_config.set_watch_keychain(watch_public_keys, node.watch_threshold.unwrap_or(1));
+                }
                 node_config
             },
             None => default_node_config
@@ -274,7 +367,7 @@ impl Config {
         let default_burnchain_config = BurnchainConfig::default();
         let burnchain = match config_file.burnchain {
             Some(burnchain) => {
-                BurnchainConfig {
+                let mut burnchain_config = BurnchainConfig {
                     chain: burnchain.chain.unwrap_or(default_burnchain_config.chain),
                     mode: burnchain.mode.unwrap_or(default_burnchain_config.mode),
                     burn_fee_cap: burnchain.burn_fee_cap.unwrap_or(default_burnchain_config.burn_fee_cap),
@@ -301,8 +394,20 @@ impl Config {
                     magic_bytes: default_burnchain_config.magic_bytes,
                     local_mining_public_key: burnchain.local_mining_public_key,
                     burnchain_op_tx_fee: burnchain.burnchain_op_tx_fee.unwrap_or(default_burnchain_config.burnchain_op_tx_fee),
-                    process_exit_at_block_height: burnchain.process_exit_at_block_height
+                    process_exit_at_block_height: burnchain.process_exit_at_block_height,
+                    fee_estimation: burnchain.fee_estimation.unwrap_or(default_burnchain_config.fee_estimation),
+                    fee_estimation_conf_target: burnchain.fee_estimation_conf_target.unwrap_or(default_burnchain_config.fee_estimation_conf_target),
+                    max_satoshis_per_byte: burnchain.max_satoshis_per_byte.unwrap_or(default_burnchain_config.max_satoshis_per_byte),
+                    backup_endpoints: vec![],
+                    rpc_timeout: default_burnchain_config.rpc_timeout,
+                };
+                if let Some(rpc_timeout) = burnchain.rpc_timeout {
+                    burnchain_config.set_rpc_timeout(rpc_timeout);
+                }
+                if let Some(endpoints) = burnchain.endpoints {
+                    burnchain_config.set_backup_endpoints(endpoints);
                 }
+                burnchain_config
             },
             None => default_burnchain_config
         };
@@ -341,9 +446,16 @@ impl Config {
                         format!("{}/", observer.endpoint)
                     };
 
+                    let schema_version = observer.schema_version.unwrap_or(EVENT_SCHEMA_DEFAULT_VERSION);
+                    if !EVENT_SCHEMA_SUPPORTED_VERSIONS.contains(&schema_version) {
+                        panic!("Event observer {} requested unsupported schema_version {} (supported: {:?})",
+                               endpoint, schema_version, EVENT_SCHEMA_SUPPORTED_VERSIONS);
+                    }
+
                     observers.push(EventObserverConfig {
                         endpoint,
-                        events_keys
+                        events_keys,
+                        schema_version,
                     });
                 }
                 observers
@@ -357,6 +469,7 @@ impl Config {
                 events_observers.push(EventObserverConfig {
                     endpoint: val,
                     events_keys: vec![EventKeyType::AnyEvent],
+                    schema_version: EVENT_SCHEMA_DEFAULT_VERSION,
                 })
             },
             _ => ()
@@ -370,6 +483,21 @@ impl Config {
                 opts.read_only_call_limit_read_length.map(|x| { read_only_call_limit.read_length = x; });
                 opts.read_only_call_limit_read_count.map(|x| { read_only_call_limit.read_count = x; });
                 opts.read_only_call_limit_runtime.map(|x| { read_only_call_limit.runtime = x; });
+                let rpc_priority_overrides = match opts.rpc_request_priorities {
+                    Some(priorities) => priorities.into_iter().map(|(request_class, priority)| {
+                        if !HttpRequestType::is_request_class(&request_class) {
+                            panic!("Invalid `connection_options.rpc_request_priorities`: unknown RPC request class {}", request_class)
+                        }
+                        let priority = RequestPriority::from_str(&priority)
+                            .expect(&format!("Invalid priority '{}' for RPC request class {} (expected low, normal, or critical)", priority, request_class));
+                        (request_class, priority)
+                    }).collect(),
+                    None => HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_priority_overrides.clone()
+                };
+                let rpc_rate_limit_per_endpoint = match opts.rpc_rate_limit_per_endpoint {
+                    Some(limits) => limits.into_iter().collect(),
+                    None => HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_rate_limit_per_endpoint.clone()
+                };
                 ConnectionOptions {
                     read_only_call_limit,
                     inbox_maxlen: opts.inbox_maxlen.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.inbox_maxlen.clone()),
@@ -391,6 +519,28 @@ impl Config {
                     dns_timeout: opts.dns_timeout.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.dns_timeout.clone()),
                     max_inflight_blocks: opts.max_inflight_blocks.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_inflight_blocks.clone()),
                     maximum_call_argument_size: opts.maximum_call_argument_size.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.maximum_call_argument_size.clone()),
+                    rpc_overload_queue_depth: opts.rpc_overload_queue_depth.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_overload_queue_depth.clone()),
+                    rpc_overload_latency_ms: opts.rpc_overload_latency_ms.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_overload_latency_ms.clone()),
+                    rpc_priority_overrides,
+                    rpc_rate_limit_per_ip: opts.rpc_rate_limit_per_ip.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_rate_limit_per_ip.clone()),
+                    rpc_rate_limit_per_ip_burst: opts.rpc_rate_limit_per_ip_burst.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_rate_limit_per_ip_burst.clone()),
+                    rpc_rate_limit_per_endpoint,
+                    rpc_metrics_enabled: opts.rpc_metrics_enabled.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_metrics_enabled.clone()),
+                    mined_block_acceptance_budget: opts.mined_block_acceptance_budget.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.mined_block_acceptance_budget.clone()),
+                    rpc_api_key: opts.rpc_api_key.or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_api_key.clone()),
+                    mempool_relay_capacity: opts.mempool_relay_capacity.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.mempool_relay_capacity.clone()),
+                    mempool_relay_fee_rate_delta: opts.mempool_relay_fee_rate_delta.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.mempool_relay_fee_rate_delta.clone()),
+                    rpc_tls_cert_path: opts.rpc_tls_cert_path.or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_tls_cert_path.clone()),
+                    rpc_tls_key_path: opts.rpc_tls_key_path.or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_tls_key_path.clone()),
+                    rpc_cors_allowed_origins: opts.rpc_cors_allowed_origins.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_cors_allowed_origins.clone()),
+                    rpc_cors_allowed_methods: opts.rpc_cors_allowed_methods.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_cors_allowed_methods.clone()),
+                    rpc_cors_allowed_headers: opts.rpc_cors_allowed_headers.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_cors_allowed_headers.clone()),
+                    rpc_slow_request_ms: opts.rpc_slow_request_ms.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_slow_request_ms.clone()),
+                    rpc_compression_enabled: opts.rpc_compression_enabled.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_compression_enabled.clone()),
+                    rpc_batch_max_requests: opts.rpc_batch_max_requests.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_batch_max_requests.clone()),
+                    rpc_serve_sunset_routes: opts.rpc_serve_sunset_routes.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_serve_sunset_routes.clone()),
+                    rpc_heavy_request_workers: opts.rpc_heavy_request_workers.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_heavy_request_workers.clone()),
+                    rpc_max_heavy_requests_per_connection: opts.rpc_max_heavy_requests_per_connection.unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_max_heavy_requests_per_connection.clone()),
                     ..ConnectionOptions::default() 
                 }
             },
@@ -400,12 +550,18 @@ impl Config {
         };
 
         let block_limit = match config_file.block_limit {
-            Some(opts) => ExecutionCost {
-                write_length: opts.write_length.unwrap_or(HELIUM_BLOCK_LIMIT.write_length.clone()),
-                write_count:  opts.write_count.unwrap_or(HELIUM_BLOCK_LIMIT.write_count.clone()),
-                read_length:  opts.read_length.unwrap_or(HELIUM_BLOCK_LIMIT.read_length.clone()),
-                read_count:  opts.read_count.unwrap_or(HELIUM_BLOCK_LIMIT.read_count.clone()),
-                runtime:  opts.runtime.unwrap_or(HELIUM_BLOCK_LIMIT.runtime.clone()),
+            Some(opts) => {
+                // every node of a public network has to agree on what fits in a block
+                if !PRIVATE_TESTNET_MODES.contains(&burnchain.mode.as_str()) {
+                    panic!("Setting `block_limit` is only supported for private testnets (burnchain.mode should be: {})", PRIVATE_TESTNET_MODES.join(", "))
+                }
+                ExecutionCost {
+                    write_length: opts.write_length.unwrap_or(HELIUM_BLOCK_LIMIT.write_length.clone()),
+                    write_count:  opts.write_count.unwrap_or(HELIUM_BLOCK_LIMIT.write_count.clone()),
+                    read_length:  opts.read_length.unwrap_or(HELIUM_BLOCK_LIMIT.read_length.clone()),
+                    read_count:  opts.read_count.unwrap_or(HELIUM_BLOCK_LIMIT.read_count.clone()),
+                    runtime:  opts.runtime.unwrap_or(HELIUM_BLOCK_LIMIT.runtime.clone()),
+                }
             },
             None => HELIUM_BLOCK_LIMIT.clone()
         };
@@ -492,7 +648,20 @@ pub struct BurnchainConfig {
     pub magic_bytes: MagicBytes,
     pub local_mining_public_key: Option<String>,
     pub burnchain_op_tx_fee: u64,
-    pub process_exit_at_block_height: Option<u64>
+    pub process_exit_at_block_height: Option<u64>,
+    /// query bitcoind's `estimatesmartfee` to size block-commit fees, instead of paying the flat
+    /// `burnchain_op_tx_fee`
+    pub fee_estimation: bool,
+    /// number of burnchain blocks within which a block-commit should confirm
+    pub fee_estimation_conf_target: u16,
+    /// upper bound on the feerate a block-commit will ever pay
+    pub max_satoshis_per_byte: u64,
+    /// bitcoinds to fail over to when the one above is unavailable, in the order they're tried
+    pub backup_endpoints: Vec<BurnchainEndpoint>,
+    /// seconds to wait on a bitcoind RPC call before failing over.  Only applies if there are
+    /// backup endpoints to fail over to, and never to importing an address, which rescans the
+    /// chain.
+    pub rpc_timeout: u64,
 }
 
 impl BurnchainConfig {
@@ -515,6 +684,51 @@ impl BurnchainConfig {
             local_mining_public_key: None,
             burnchain_op_tx_fee: MINIMUM_DUST_FEE,
             process_exit_at_block_height: None,
+            fee_estimation: false,
+            fee_estimation_conf_target: 1,
+            max_satoshis_per_byte: 500,
+            backup_endpoints: vec![],
+            rpc_timeout: 30,
+        }
+    }
+
+    /// The bitcoinds the node talks to, the one it prefers first
+    pub fn get_endpoints(&self) -> Vec<BurnchainEndpoint> {
+        let mut endpoints = vec![BurnchainEndpoint {
+            peer_host: self.peer_host.clone(),
+            peer_port: self.peer_port,
+            rpc_port: self.rpc_port,
+            rpc_ssl: self.rpc_ssl,
+            username: self.username.clone(),
+            password: self.password.clone(),
+        }];
+        endpoints.extend(self.backup_endpoints.iter().cloned());
+        endpoints
+    }
+
+    pub fn set_rpc_timeout(&mut self, rpc_timeout: u64) {
+        if rpc_timeout == 0 {
+            panic!("Invalid `burnchain.rpc_timeout`: must be at least 1 second")
+        }
+        self.rpc_timeout = rpc_timeout;
+    }
+
+    /// Add the bitcoinds listed under `[[burnchain.endpoints]]`.  Whatever an entry leaves out is
+    /// the same as for the bitcoind configured above it.
+    pub fn set_backup_endpoints(&mut self, endpoints: Vec<BurnchainEndpointFile>) {
+        for endpoint in endpoints.into_iter() {
+            let endpoint = BurnchainEndpoint {
+                peer_host: endpoint.peer_host,
+                peer_port: endpoint.peer_port.unwrap_or(self.peer_port),
+                rpc_port: endpoint.rpc_port.unwrap_or(self.rpc_port),
+                rpc_ssl: endpoint.rpc_ssl.unwrap_or(self.rpc_ssl),
+                username: endpoint.username.or(self.username.clone()),
+                password: endpoint.password.or(self.password.clone()),
+            };
+            if self.get_endpoints().iter().any(|other| other.get_rpc_url() == endpoint.get_rpc_url()) {
+                panic!("Invalid `burnchain.endpoints`: {} is listed more than once", endpoint.get_rpc_url())
+            }
+            self.backup_endpoints.push(endpoint);
         }
     }
 
@@ -552,6 +766,49 @@ pub struct BurnchainConfigFile {
     pub local_mining_public_key: Option<String>,
     pub burnchain_op_tx_fee: Option<u64>,
     pub process_exit_at_block_height: Option<u64>,
+    pub fee_estimation: Option<bool>,
+    pub fee_estimation_conf_target: Option<u16>,
+    pub max_satoshis_per_byte: Option<u64>,
+    pub rpc_timeout: Option<u64>,
+    pub endpoints: Option<Vec<BurnchainEndpointFile>>,
+}
+
+/// A bitcoind the node can sync from and send transactions to
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnchainEndpoint {
+    pub peer_host: String,
+    pub peer_port: u16,
+    pub rpc_port: u16,
+    pub rpc_ssl: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl BurnchainEndpoint {
+    pub fn get_rpc_url(&self) -> String {
+        let scheme = match self.rpc_ssl {
+            true => "https://",
+            false => "http://"
+        };
+        format!("{}{}:{}", scheme, self.peer_host, self.rpc_port)
+    }
+
+    pub fn get_rpc_socket_addr(&self) -> Result<SocketAddr, String> {
+        format!("{}:{}", self.peer_host, self.rpc_port).to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve {}: {:?}", self.peer_host, &e))?
+            .next()
+            .ok_or_else(|| format!("No address for {}", self.peer_host))
+    }
+}
+
+#[derive(Clone, Deserialize, Default)]
+pub struct BurnchainEndpointFile {
+    pub peer_host: String,
+    pub peer_port: Option<u16>,
+    pub rpc_port: Option<u16>,
+    pub rpc_ssl: Option<bool>,
+    pub username: Option<String>,
+    pub password: Option<String>,
 }
 
 #[derive(Clone, Default)]
@@ -569,6 +826,55 @@ pub struct NodeConfig {
     pub mine_microblocks: bool,
     pub wait_time_for_microblocks: u64,
     pub prometheus_bind: Option<String>,
+    /// Public keys of the address this node watches, for a node that runs without a seed
+    pub watch_keychain: Option<WatchOnlyKeychain>,
+    /// Trusted Stacks block checkpoints; blocks at these heights must have these index block hashes
+    pub checkpoints: Vec<StacksBlockCheckpoint>,
+    /// Skip transaction signature checks and cost counting for the last checkpoint and the
+    /// blocks it descends from
+    pub assume_valid: bool,
+    /// Release manifest to check for new releases, if any
+    pub version_check_url: Option<String>,
+    /// Seconds between release manifest checks
+    pub version_check_interval: u64,
+    /// Loopback address for the Clarity debugger to listen on, in mocknet and helium modes
+    pub debug_bind: Option<String>,
+    /// File to keep an lcov report of which contract code ran in, in mocknet and helium modes
+    pub coverage_output: Option<String>,
+    /// Record the contract call tree of each processed transaction, and send it to event observers
+    pub trace_contract_calls: bool,
+    /// Directory to write the execution trace of each processed block to, as
+    /// `<index block hash>.json`
+    pub execution_trace_dir: Option<String>,
+    /// Discard block and microblock data selected more than this many burn blocks ago
+    pub prune_burn_blocks: Option<u64>,
+    /// Discard the data of blocks off the canonical Stacks chain, more than this many blocks below
+    /// its tip
+    pub prune_fork_depth: Option<u64>,
+    /// Backend to create the Clarity MARF with, if the chainstate is new
+    pub marf_backend: TrieBackend,
+    /// Bytes of recently-read trie nodes each MARF keeps in RAM; 0 turns the cache off
+    pub marf_cache_size: usize,
+    /// Keep every block and the state at every block, and check that the state of blocks
+    /// processed before this was turned on is all there
+    pub archive: bool,
+    /// Threads to check a block's transaction signatures on while its transactions are executed
+    pub block_validation_threads: usize,
+    /// When to analyze and vacuum the node's sqlite databases in the background, if at all
+    pub db_maintenance: Option<DbMaintenanceConfig>,
+    /// Where and how often to back up the chainstate and sortition DB while the node runs, if
+    /// at all
+    pub backup: Option<BackupConfig>,
+    /// How the chainstate, sortition DB and mempool's sqlite databases are tuned
+    pub sqlite_pragmas: SqlitePragmas,
+    /// zstd level to compress the blocks and microblock streams stored from now on at, if at all
+    pub block_compression: Option<i32>,
+    /// Check blocks against the burnchain and attach them to the headers, without executing
+    /// them or keeping any Clarity state
+    pub light_sync: bool,
+    /// Index processed transactions by the principals they involve, to serve
+    /// `/v2/addresses/{principal}/transactions`
+    pub index_addresses: bool,
 }
 
 impl NodeConfig {
@@ -603,6 +909,29 @@ impl NodeConfig {
             mine_microblocks: false,
             wait_time_for_microblocks: 0,
             prometheus_bind: None,
+            watch_keychain: None,
+            checkpoints: vec![],
+            assume_valid: false,
+            version_check_url: None,
+            version_check_interval: 3600,
+            debug_bind: None,
+            coverage_output: None,
+            trace_contract_calls: false,
+            execution_trace_dir: None,
+            prune_burn_blocks: None,
+            prune_fork_depth: None,
+            marf_backend: TrieBackend::Sqlite,
+            marf_cache_size: DEFAULT_MARF_CACHE_SIZE,
+            archive: false,
+            block_validation_threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            db_maintenance: None,
+            backup: None,
+            sqlite_pragmas: SqlitePragmas::default(),
+            block_compression: None,
+            light_sync: false,
+            index_addresses: false,
         }
     }
 
@@ -614,6 +943,101 @@ impl NodeConfig {
         format!("{}/spv-headers.dat", self.get_burnchain_path())
     }
 
+    pub fn set_watch_keychain(&mut self, public_keys: Vec<String>, threshold: u16) {
+        if public_keys.is_empty() || threshold == 0 || threshold as usize > public_keys.len() {
+            panic!("Invalid `node.watch_public_keys`: `node.watch_threshold` must be between 1 and the number of keys, not {}", threshold)
+        }
+        let public_keys = public_keys.iter()
+            .map(|pubk| Secp256k1PublicKey::from_hex(pubk).expect("Watched public key should be a hex encoded secp256k1 public key"))
+            .collect();
+        self.watch_keychain = Some(WatchOnlyKeychain::from_public_keys(public_keys, threshold));
+    }
+
+    pub fn set_checkpoints(&mut self, checkpoints: Vec<CheckpointConfigFile>, assume_valid: bool) {
+        if assume_valid && checkpoints.is_empty() {
+            panic!("Config cannot set `node.assume_valid` without any `node.checkpoints`")
+        }
+        self.checkpoints = checkpoints.iter()
+            .map(|checkpoint| StacksBlockCheckpoint {
+                block_height: checkpoint.block_height,
+                index_block_hash: StacksBlockId::from_hex(&checkpoint.index_block_hash)
+                    .expect("Checkpoint `index_block_hash` should be a hex encoded 32-byte hash")
+            })
+            .collect();
+        self.checkpoints.sort_by_key(|checkpoint| checkpoint.block_height);
+        self.assume_valid = assume_valid;
+    }
+
+    pub fn set_prune_burn_blocks(&mut self, prune_burn_blocks: u64) {
+        if prune_burn_blocks < MIN_PRUNE_BURN_BLOCKS {
+            panic!("Invalid `node.prune_burn_blocks`: must keep at least {} burn blocks, not {}", MIN_PRUNE_BURN_BLOCKS, prune_burn_blocks)
+        }
+        self.prune_burn_blocks = Some(prune_burn_blocks);
+    }
+
+    pub fn set_prune_fork_depth(&mut self, prune_fork_depth: u64) {
+        if prune_fork_depth < MIN_PRUNE_FORK_DEPTH {
+            panic!("Invalid `node.prune_fork_depth`: must keep at least {} blocks of forks, not {}", MIN_PRUNE_FORK_DEPTH, prune_fork_depth)
+        }
+        self.prune_fork_depth = Some(prune_fork_depth);
+    }
+
+    pub fn set_marf_backend(&mut self, marf_backend: &str) {
+        let backend = match TrieBackend::from_name(marf_backend) {
+            Some(backend) => backend,
+            None => panic!("Invalid `node.marf_backend`: expected \"sqlite\" or \"rocksdb\", not \"{}\"", marf_backend)
+        };
+        if !backend.is_available() {
+            panic!("Invalid `node.marf_backend`: \"{}\" needs a build with the `marf_rocksdb` feature", marf_backend)
+        }
+        self.marf_backend = backend;
+    }
+
+    pub fn set_block_validation_threads(&mut self, block_validation_threads: usize) {
+        if block_validation_threads == 0 {
+            panic!("Invalid `node.block_validation_threads`: must be at least 1")
+        }
+        self.block_validation_threads = block_validation_threads;
+    }
+
+    pub fn set_db_maintenance(&mut self, analyze_interval: u64, vacuum_interval: u64, vacuum_hours: &str) {
+        if analyze_interval == 0 || vacuum_interval == 0 {
+            panic!("Invalid `node.db_analyze_interval` or `node.db_vacuum_interval`: must be at least 1 second")
+        }
+        let vacuum_hours = match parse_vacuum_hours(vacuum_hours) {
+            Some(vacuum_hours) => vacuum_hours,
+            None => panic!("Invalid `node.db_vacuum_hours`: expected UTC hours like \"2-5\", not \"{}\"", vacuum_hours)
+        };
+        self.db_maintenance = Some(DbMaintenanceConfig { analyze_interval, vacuum_interval, vacuum_hours });
+    }
+
+    pub fn set_backup(&mut self, backup_dir: String, interval: u64, keep: usize) {
+        if interval == 0 {
+            panic!("Invalid `node.backup_interval`: must be at least 1 second")
+        }
+        if keep == 0 {
+            panic!("Invalid `node.backup_keep`: must keep at least 1 backup")
+        }
+        self.backup = Some(BackupConfig { backup_dir, interval, keep });
+    }
+
+    pub fn set_block_compression(&mut self, level: i32) {
+        if !is_block_compression_available() {
+            panic!("Invalid `node.compress_blocks`: needs a build with the `block_compression` feature")
+        }
+        if level < 1 || level > 22 {
+            panic!("Invalid `node.block_compression_level`: must be from 1 to 22")
+        }
+        self.block_compression = Some(level);
+    }
+
+    pub fn set_sqlite_pragmas(&mut self, sqlite_pragmas: SqlitePragmas) {
+        self.sqlite_pragmas = match sqlite_pragmas.validate() {
+            Ok(sqlite_pragmas) => sqlite_pragmas,
+            Err(e) => panic!("Invalid `node.db_page_size` or `node.db_synchronous`: {}", e)
+        };
+    }
+
     pub fn set_bootstrap_node(&mut self, bootstrap_node: Option<String>) {
         if let Some(bootstrap_node) = bootstrap_node {
             let comps: Vec<&str> = bootstrap_node.split("@").collect();
@@ -673,8 +1097,31 @@ pub struct ConnectionOptionsFile {
     pub read_only_call_limit_read_count: Option<u64>,
     pub read_only_call_limit_runtime: Option<u64>,
     pub maximum_call_argument_size: Option<u32>,
+    pub rpc_overload_queue_depth: Option<u64>,
+    pub rpc_overload_latency_ms: Option<u64>,
+    pub rpc_request_priorities: Option<HashMap<String, String>>,
+    pub rpc_rate_limit_per_ip: Option<u64>,
+    pub rpc_rate_limit_per_ip_burst: Option<u64>,
+    pub rpc_rate_limit_per_endpoint: Option<HashMap<String, u64>>,
+    pub rpc_metrics_enabled: Option<bool>,
+    pub mined_block_acceptance_budget: Option<u64>,
+    pub rpc_api_key: Option<String>,
+    pub mempool_relay_capacity: Option<u64>,
+    pub mempool_relay_fee_rate_delta: Option<u64>,
+    pub rpc_tls_cert_path: Option<String>,
+    pub rpc_tls_key_path: Option<String>,
+    pub rpc_cors_allowed_origins: Option<Vec<String>>,
+    pub rpc_cors_allowed_methods: Option<Vec<String>>,
+    pub rpc_cors_allowed_headers: Option<Vec<String>>,
+    pub rpc_slow_request_ms: Option<u64>,
+    pub rpc_compression_enabled: Option<bool>,
+    pub rpc_batch_max_requests: Option<u32>,
+    pub rpc_serve_sunset_routes: Option<bool>,
+    pub rpc_heavy_request_workers: Option<u64>,
+    pub rpc_max_heavy_requests_per_connection: Option<u64>,
 }
 
+/// Overrides of the block cost limit, for private testnets only.  Unset limits keep their defaults.
 #[derive(Clone, Default, Deserialize)]
 pub struct BlockLimitFile {
     pub write_length: Option<u64>,
@@ -700,18 +1147,58 @@ pub struct NodeConfigFile {
     pub mine_microblocks: Option<bool>,
     pub wait_time_for_microblocks: Option<u64>,
     pub prometheus_bind: Option<String>,
+    pub watch_public_keys: Option<Vec<String>>,
+    pub watch_threshold: Option<u16>,
+    pub checkpoints: Option<Vec<CheckpointConfigFile>>,
+    pub assume_valid: Option<bool>,
+    pub version_check_url: Option<String>,
+    pub version_check_interval: Option<u64>,
+    pub debug_bind: Option<String>,
+    pub coverage_output: Option<String>,
+    pub trace_contract_calls: Option<bool>,
+    pub execution_trace_dir: Option<String>,
+    pub prune_burn_blocks: Option<u64>,
+    pub prune_fork_depth: Option<u64>,
+    pub marf_backend: Option<String>,
+    pub marf_cache_size: Option<usize>,
+    pub archive: Option<bool>,
+    pub block_validation_threads: Option<usize>,
+    pub db_maintenance: Option<bool>,
+    pub db_analyze_interval: Option<u64>,
+    pub db_vacuum_interval: Option<u64>,
+    pub db_vacuum_hours: Option<String>,
+    pub backup_dir: Option<String>,
+    pub backup_interval: Option<u64>,
+    pub backup_keep: Option<usize>,
+    pub db_page_size: Option<u32>,
+    pub db_cache_size: Option<i64>,
+    pub db_mmap_size: Option<u64>,
+    pub db_synchronous: Option<String>,
+    pub compress_blocks: Option<bool>,
+    pub block_compression_level: Option<i32>,
+    pub light_sync: Option<bool>,
+    pub index_addresses: Option<bool>,
+}
+
+#[derive(Clone, Deserialize, Default)]
+pub struct CheckpointConfigFile {
+    pub block_height: u64,
+    pub index_block_hash: String,
 }
 
 #[derive(Clone, Deserialize, Default)]
 pub struct EventObserverConfigFile {
     pub endpoint: String,
     pub events_keys: Vec<String>,
+    pub schema_version: Option<u32>,
 }
 
 #[derive(Clone, Default)]
 pub struct EventObserverConfig {
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    /// Major version of the payload schema this observer consumes
+    pub schema_version: u32,
 }
 
 #[derive(Clone)]
@@ -720,6 +1207,7 @@ pub enum EventKeyType {
     AssetEvent(AssetIdentifier),
     STXEvent,
     MemPoolTransactions,
+    BurnchainBlocks,
     AnyEvent,
 }
 
@@ -737,6 +1225,10 @@ impl EventKeyType {
             return Some(EventKeyType::MemPoolTransactions);
         }
 
+        if raw_key == "burn_blocks" {
+            return Some(EventKeyType::BurnchainBlocks);
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() ==  1 {
             let split: Vec<_> = comps[0].split(".").collect();
@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use stacks::chainstate::burn::db::burndb::BurnDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::db::blocks::ChainstateInconsistency;
+use stacks::chainstate::stacks::db::check::IntegrityError;
+
+use crate::clarity_repl::find_vm_dir;
+use crate::node::TESTNET_CHAIN_ID;
+use crate::Config;
+
+const RESYNC_ADVICE: &str = "restore it from a snapshot, or sync it again from scratch";
+
+/// What checking a node's databases found
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbCheckReport {
+    /// damage that can't be repaired in place
+    pub integrity_errors: Vec<IntegrityError>,
+    /// what an unclean shutdown left half-written, which the node repairs when it starts
+    pub inconsistencies: Vec<ChainstateInconsistency>,
+}
+
+impl DbCheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.integrity_errors.is_empty()
+    }
+}
+
+/// Check a stopped node's chainstate and sortition DB:  the chainstate's integrity (see
+/// StacksChainState::check_integrity()) and its consistency with the sortition DB.  Neither is
+/// written to, nor created if it's missing.
+pub fn check_databases(config: &Config) -> Result<DbCheckReport, String> {
+    let chainstate_path = config.get_chainstate_path();
+    find_vm_dir(&chainstate_path)?;
+
+    let burn_db_path = config.get_burn_db_file_path();
+    if !Path::new(&burn_db_path).exists() {
+        return Err(format!("No sortition DB found at {}", &burn_db_path));
+    }
+    let burndb = BurnDB::open(&burn_db_path, false)
+        .map_err(|e| format!("Failed to open the sortition DB at {}: {:?}; {}", &burn_db_path, &e, RESYNC_ADVICE))?;
+    let mut chainstate = StacksChainState::open_with_block_limit(
+        false, TESTNET_CHAIN_ID, &chainstate_path, config.block_limit.clone())
+        .map_err(|e| format!("Failed to open the chainstate at {}: {:?}; {}", &chainstate_path, &e, RESYNC_ADVICE))?;
+
+    let inconsistencies = chainstate.check_consistency(&burndb)
+        .map_err(|e| format!("The sortition DB's canonical Stacks chain tip is not in the chainstate ({:?}); {}", &e, RESYNC_ADVICE))?;
+    let integrity_errors = chainstate.check_integrity(&burndb)
+        .map_err(|e| format!("Failed to check the chainstate at {}: {:?}", &chainstate_path, &e))?;
+
+    Ok(DbCheckReport { integrity_errors, inconsistencies })
+}
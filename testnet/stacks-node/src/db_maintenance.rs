@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use stacks::util::db::{tx_busy_handler, DBConn};
+use stacks::util::get_epoch_time_secs;
+
+use crate::run_loop::sleep_while_running;
+
+/// First bytes of every sqlite database file
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// Analyze every hour by default
+pub const DEFAULT_DB_ANALYZE_INTERVAL: u64 = 3600;
+
+/// Vacuum every week by default
+pub const DEFAULT_DB_VACUUM_INTERVAL: u64 = 7 * 24 * 3600;
+
+/// Vacuum between 2:00 and 5:00 UTC by default
+pub const DEFAULT_DB_VACUUM_HOURS: &str = "2-5";
+
+/// Seconds between looks at whether any database is due for maintenance
+const MAINTENANCE_TICK_SECS: u64 = 60;
+
+/// When to maintain the node's sqlite databases
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbMaintenanceConfig {
+    /// Seconds between `PRAGMA optimize` runs on each database, which re-analyze the tables
+    /// whose statistics have gone stale
+    pub analyze_interval: u64,
+    /// Seconds between vacuums of each database
+    pub vacuum_interval: u64,
+    /// UTC hours in which vacuums can start, from the first up to (not including) the second.
+    /// The window can wrap around midnight.
+    pub vacuum_hours: (u8, u8),
+}
+
+/// Parse a vacuum window like `2-5` (2:00 to 5:00 UTC) or `22-4`
+pub fn parse_vacuum_hours(hours: &str) -> Option<(u8, u8)> {
+    let mut parts = hours.trim().splitn(2, '-');
+    let start = parts.next()?.trim().parse::<u8>().ok()?;
+    let end = parts.next()?.trim().parse::<u8>().ok()?;
+    if start > 23 || end > 24 || start == end {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Is this time, in seconds since the epoch, in the vacuum window?
+pub fn in_vacuum_window(vacuum_hours: (u8, u8), now: u64) -> bool {
+    let hour = ((now / 3600) % 24) as u8;
+    let (start, end) = vacuum_hours;
+    if start < end {
+        start <= hour && hour < end
+    }
+    else {
+        hour >= start || hour < end
+    }
+}
+
+/// Does this file start like a sqlite database?
+pub fn is_sqlite_file(path: &Path) -> bool {
+    let mut magic = [0u8; 16];
+    match fs::File::open(path) {
+        Ok(mut fd) => fd.read_exact(&mut magic).is_ok() && &magic == SQLITE_MAGIC,
+        Err(_) => false
+    }
+}
+
+/// Is this one of the chunk store's directories of block files, named for the first bytes of
+/// the blocks' index hashes?  There are a lot of them, and they hold no databases.
+fn is_block_files_dir(path: &Path) -> bool {
+    let in_blocks_dir = path.parent()
+        .and_then(|parent| parent.file_name())
+        .map(|name| name == "blocks")
+        .unwrap_or(false);
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    in_blocks_dir && name.len() == 4 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn list_sqlite_dbs(dir: &Path, dbs: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("DB maintenance: failed to read {}: {:?}", dir.display(), &e);
+            return;
+        }
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if !is_block_files_dir(&path) {
+                list_sqlite_dbs(&path, dbs);
+            }
+        }
+        else if is_sqlite_file(&path) {
+            dbs.push(path);
+        }
+    }
+}
+
+/// Find the sqlite databases under a node's working directory, in order.  Write-ahead logs and
+/// journals don't start like a database, so they're left out.
+pub fn find_sqlite_dbs(working_dir: &Path) -> Vec<PathBuf> {
+    let mut dbs = vec![];
+    list_sqlite_dbs(working_dir, &mut dbs);
+    dbs.sort();
+    dbs
+}
+
+fn open_db(path: &Path) -> Result<DBConn, String> {
+    let conn = DBConn::open(path)
+        .map_err(|e| format!("failed to open: {:?}", &e))?;
+    conn.busy_handler(Some(tx_busy_handler))
+        .map_err(|e| format!("failed to set a busy handler: {:?}", &e))?;
+    Ok(conn)
+}
+
+fn db_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|md| md.len()).unwrap_or(0)
+}
+
+/// Re-analyze the tables of a database whose statistics have gone stale
+pub fn analyze_db(path: &Path) -> Result<(), String> {
+    let conn = open_db(path)?;
+    conn.execute_batch("PRAGMA optimize;")
+        .map_err(|e| format!("failed to analyze: {:?}", &e))
+}
+
+/// Rebuild a database to reclaim the space its deleted rows left behind.  Writers to the
+/// database wait until it's done.  Returns its size before and after.
+pub fn vacuum_db(path: &Path) -> Result<(u64, u64), String> {
+    let size_before = db_size(path);
+    let conn = open_db(path)?;
+    conn.execute_batch("VACUUM;")
+        .map_err(|e| format!("failed to vacuum: {:?}", &e))?;
+    drop(conn);
+    Ok((size_before, db_size(path)))
+}
+
+/// When each database was last maintained
+#[derive(Debug, Clone, Copy)]
+struct DbMaintenanceState {
+    last_analyze: u64,
+    last_vacuum: u64,
+}
+
+/// Periodically analyze and vacuum the sqlite databases under `working_dir`, as `config` says.
+/// Nothing is due until one interval after this is started, so that restarting the node doesn't
+/// kick off maintenance.  At most one database is vacuumed per pass, so a window isn't spent on
+/// one long stall of the node.  Stops once `should_keep_running` is cleared.
+pub fn spawn_db_maintenance(working_dir: &str, config: DbMaintenanceConfig, should_keep_running: Arc<AtomicBool>) -> Result<JoinHandle<()>, String> {
+    let working_dir = PathBuf::from(working_dir);
+    thread::Builder::new()
+        .name("db-maintenance".to_string())
+        .spawn(move || {
+            let started = get_epoch_time_secs();
+            let mut states : HashMap<PathBuf, DbMaintenanceState> = HashMap::new();
+            while sleep_while_running(Duration::from_secs(MAINTENANCE_TICK_SECS), &should_keep_running) {
+                let mut vacuumed = false;
+
+                for path in find_sqlite_dbs(&working_dir).into_iter() {
+                    let now = get_epoch_time_secs();
+                    let state = states.entry(path.clone())
+                        .or_insert(DbMaintenanceState { last_analyze: started, last_vacuum: started });
+
+                    if now.saturating_sub(state.last_analyze) >= config.analyze_interval {
+                        let start_time = Instant::now();
+                        match analyze_db(&path) {
+                            Ok(()) => debug!("DB maintenance: analyzed {} in {}ms", path.display(), start_time.elapsed().as_millis()),
+                            Err(e) => warn!("DB maintenance: {}: {}", path.display(), &e)
+                        }
+                        state.last_analyze = now;
+                    }
+
+                    if !vacuumed && in_vacuum_window(config.vacuum_hours, now) && now.saturating_sub(state.last_vacuum) >= config.vacuum_interval {
+                        info!("DB maintenance: vacuuming {}", path.display());
+                        let start_time = Instant::now();
+                        match vacuum_db(&path) {
+                            Ok((size_before, size_after)) => {
+                                info!("DB maintenance: vacuumed {} in {}s, from {} to {} bytes",
+                                      path.display(), start_time.elapsed().as_secs(), size_before, size_after);
+                            },
+                            Err(e) => warn!("DB maintenance: {}: {}", path.display(), &e)
+                        }
+                        state.last_vacuum = now;
+                        vacuumed = true;
+                    }
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start the DB maintenance thread: {:?}", &e))
+}
@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use stacks::chainstate::burn::db::burndb::BURNDB_MIGRATIONS;
+use stacks::chainstate::stacks::db::{HEADERS_DB_MIGRATIONS, StacksChainState};
+use stacks::chainstate::stacks::db::blocks::BLOCKS_DB_MIGRATIONS;
+use stacks::core::mempool::MEMPOOL_DB_MIGRATIONS;
+use stacks::util::db::DBConn;
+use stacks::util::migrations::{
+    MigrationStep, SchemaMigration, get_schema_version, latest_schema_version, migrate_schema,
+};
+
+use crate::clarity_repl::find_vm_dir;
+use crate::Config;
+
+/// A node database whose schema is migrated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeDatabase {
+    Headers,
+    Blocks,
+    Sortition,
+    Mempool,
+}
+
+impl NodeDatabase {
+    pub fn all() -> Vec<NodeDatabase> {
+        vec![NodeDatabase::Headers, NodeDatabase::Blocks, NodeDatabase::Sortition, NodeDatabase::Mempool]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NodeDatabase::Headers => "headers",
+            NodeDatabase::Blocks => "blocks",
+            NodeDatabase::Sortition => "sortition",
+            NodeDatabase::Mempool => "mempool",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<NodeDatabase> {
+        NodeDatabase::all().into_iter().find(|database| database.name() == name)
+    }
+
+    pub fn migrations(&self) -> &'static [SchemaMigration] {
+        match *self {
+            NodeDatabase::Headers => HEADERS_DB_MIGRATIONS,
+            NodeDatabase::Blocks => BLOCKS_DB_MIGRATIONS,
+            NodeDatabase::Sortition => BURNDB_MIGRATIONS,
+            NodeDatabase::Mempool => MEMPOOL_DB_MIGRATIONS,
+        }
+    }
+
+    /// Where the database is kept under a node's working directory
+    pub fn path(&self, config: &Config) -> Result<PathBuf, String> {
+        if *self == NodeDatabase::Sortition {
+            return Ok(Path::new(&config.get_burn_db_file_path()).join("data.db"));
+        }
+        let vm_dir = find_vm_dir(&config.get_chainstate_path())?;
+        let chain_dir = vm_dir.parent().map(|dir| dir.to_path_buf()).unwrap_or_default();
+        Ok(match *self {
+            NodeDatabase::Headers => vm_dir.join("headers.db"),
+            NodeDatabase::Blocks => chain_dir.join("blocks").join("staging.db"),
+            _ => chain_dir.join("mempool.db"),
+        })
+    }
+}
+
+/// What migrating one database came to
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbMigration {
+    pub database: NodeDatabase,
+    /// the schema version it was at
+    pub from_version: u32,
+    /// the schema version it's at now, or would be after a dry run
+    pub to_version: u32,
+    /// the migrations applied or rolled back, in order
+    pub steps: Vec<MigrationStep>,
+}
+
+/// Parse a comma-separated list of databases, like `headers,mempool`.  No list means all of them.
+pub fn parse_databases(names: Option<&str>) -> Result<Vec<NodeDatabase>, String> {
+    let names = match names {
+        Some(names) => names,
+        None => return Ok(NodeDatabase::all())
+    };
+    let mut databases = vec![];
+    for name in names.split(',').map(|name| name.trim()) {
+        let database = NodeDatabase::from_name(name).ok_or_else(|| {
+            let known : Vec<&str> = NodeDatabase::all().iter().map(|database| database.name()).collect();
+            format!("Unknown database '{}'; the databases are {}", name, known.join(", "))
+        })?;
+        if !databases.contains(&database) {
+            databases.push(database);
+        }
+    }
+    Ok(databases)
+}
+
+/// Bring the schemas of a stopped node's `databases` to schema version `target`, or the newest
+/// this node knows of, applying or rolling back migrations.  A version is only meaningful for
+/// one database, so `target` needs exactly one.  With `dry_run`, each database's migrations are
+/// run and rolled back.  Databases the node hasn't created are skipped.
+pub fn migrate_databases(config: &Config, databases: &[NodeDatabase], target: Option<u32>, dry_run: bool) -> Result<Vec<DbMigration>, String> {
+    if target.is_some() && databases.len() != 1 {
+        return Err("A schema version can only be given for one database; pass --db".to_string());
+    }
+
+    let mut ret = vec![];
+    for database in databases.iter() {
+        if *database != NodeDatabase::Sortition && !Path::new(&config.get_chainstate_path()).exists() {
+            continue;
+        }
+        let path = database.path(config)?;
+        if !path.exists() {
+            continue;
+        }
+        let mut conn = DBConn::open(&path)
+            .map_err(|e| format!("Failed to open {}: {:?}", path.display(), &e))?;
+        let from_version = get_schema_version(&conn)
+            .map_err(|e| format!("Failed to read the schema version of {}: {:?}", path.display(), &e))?;
+        if *database == NodeDatabase::Headers && !dry_run {
+            StacksChainState::upgrade_legacy_transaction_receipts(&mut conn)
+                .map_err(|e| format!("Failed to upgrade {}: {:?}", path.display(), &e))?;
+        }
+        let steps = migrate_schema(&mut conn, database.migrations(), target, dry_run)
+            .map_err(|e| format!("Failed to migrate {}: {:?}", path.display(), &e))?;
+        ret.push(DbMigration {
+            database: *database,
+            from_version,
+            to_version: target.unwrap_or(latest_schema_version(database.migrations())),
+            steps,
+        });
+    }
+    Ok(ret)
+}
@@ -0,0 +1,61 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use stacks::vm::debug::{DebugSession, DebugRequest, DebugResponse};
+
+/// Bind the Clarity debugger's listener.  The debugger can stop the node and read its contracts'
+/// data, so it only listens on loopback addresses.
+pub fn bind_debugger(bind_address: &str) -> Result<TcpListener, String> {
+    let addrs: Vec<_> = bind_address.to_socket_addrs()
+        .map_err(|e| format!("Invalid `node.debug_bind` {}: {:?}", bind_address, &e))?
+        .collect();
+
+    if addrs.is_empty() || addrs.iter().any(|addr| !addr.ip().is_loopback()) {
+        return Err(format!("Refusing to bind the Clarity debugger to non-loopback address {}", bind_address));
+    }
+
+    TcpListener::bind(&addrs[..])
+        .map_err(|e| format!("Failed to bind the Clarity debugger to {}: {:?}", bind_address, &e))
+}
+
+/// Serve a debug session to one client at a time.  Each line a client sends is a JSON
+/// `DebugRequest`, and is answered with a line holding a JSON `DebugResponse`.
+pub fn serve_debugger(listener: TcpListener, session: DebugSession) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = serve_debug_client(stream, &session) {
+                    debug!("Clarity debugger: client disconnected: {:?}", &e);
+                }
+            },
+            Err(e) => {
+                warn!("Clarity debugger: failed to accept a client: {:?}", &e);
+            }
+        }
+    }
+}
+
+fn serve_debug_client(stream: TcpStream, session: &DebugSession) -> std::io::Result<()> {
+    info!("Clarity debugger: client connected from {:?}", stream.peer_addr());
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<DebugRequest>(&line) {
+            Ok(request) => session.request(request),
+            Err(e) => DebugResponse::Error { message: format!("Failed to parse request: {}", &e) }
+        };
+
+        let mut response_json = serde_json::to_string(&response)
+            .expect("FATAL: failed to serialize a debug response");
+        response_json.push('\n');
+        writer.write_all(response_json.as_bytes())?;
+        writer.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use stacks::vm::types::Value;
+use stacks::vm::database::ClaritySerializable;
+use stacks::vm::execute as vm_execute;
+
+/// Encode a Clarity literal, such as `u10`, `'SP2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKNRV9EJ7`, or
+/// `(list (some 1) none)`, as the `0x`-prefixed hex of its consensus serialization: the encoding
+/// contract-call arguments take in transactions and in `/v2/contracts/call-read`.
+pub fn encode_arg(literal: &str) -> Result<String, String> {
+    match vm_execute(literal) {
+        Ok(Some(value)) => Ok(format!("0x{}", value.serialize())),
+        Ok(None) => Err(format!("Argument {} has no value", literal)),
+        Err(e) => Err(format!("Invalid argument {}: {}", literal, e))
+    }
+}
+
+/// Decode a consensus-serialized Clarity value, with or without a `0x` prefix, into a literal
+/// that `encode_arg` encodes back into it.
+pub fn decode_arg(hex: &str) -> Result<String, String> {
+    Value::try_deserialize_hex_untyped(hex)
+        .map(|value| clarity_literal(&value))
+        .map_err(|e| format!("Invalid encoded argument {}: {}", hex, e))
+}
+
+/// Write a value in Clarity's literal syntax
+pub fn clarity_literal(value: &Value) -> String {
+    match value {
+        Value::Int(_) | Value::UInt(_) | Value::Bool(_) | Value::Buffer(_) => value.to_string(),
+        Value::Principal(principal) => format!("'{}", principal),
+        Value::Optional(optional) => match optional.data {
+            Some(ref data) => format!("(some {})", clarity_literal(data)),
+            None => "none".to_string()
+        },
+        Value::Response(response) => format!("({} {})", if response.committed { "ok" } else { "err" },
+                                             clarity_literal(&response.data)),
+        Value::List(list) => {
+            let mut literal = "(list".to_string();
+            for item in list.data.iter() {
+                literal.push(' ');
+                literal.push_str(&clarity_literal(item));
+            }
+            literal.push(')');
+            literal
+        },
+        Value::Tuple(tuple) => {
+            let fields: Vec<String> = tuple.data_map.iter()
+                .map(|(name, value)| format!("{}: {}", name.as_str(), clarity_literal(value)))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+    }
+}
+
+/// Encode each of the arguments of a call to a contract, one per line
+pub fn encode_args(literals: &[String]) -> Result<String, String> {
+    let encoded: Result<Vec<String>, String> = literals.iter().map(|literal| encode_arg(literal)).collect();
+    Ok(encoded?.join("\n"))
+}
+
+/// Decode each of the arguments of a call to a contract, one per line
+pub fn decode_args(hexes: &[String]) -> Result<String, String> {
+    let decoded: Result<Vec<String>, String> = hexes.iter().map(|hex| decode_arg(hex)).collect();
+    Ok(decoded?.join("\n"))
+}
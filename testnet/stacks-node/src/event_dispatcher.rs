@@ -1,5 +1,6 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::Sender;
 use std::time::Duration;
 use std::thread::sleep;
 
@@ -17,13 +18,31 @@ use stacks::vm::types::{Value, QualifiedContractIdentifier, AssetIdentifier};
 use stacks::vm::analysis::{contract_interface_builder::build_contract_interface};
 use stacks::util::hash::{bytes_to_hex};
 use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::chainstate::burn::operations::BlockstackOperationType;
 
 use super::config::{EventObserverConfig, EventKeyType};
 use super::node::{ChainTip};
 
+/// An event delivered to an in-process observer.  The payloads are the same JSON documents
+/// that HTTP observers receive at the corresponding paths.
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    NewBlock(serde_json::Value),
+    NewMempoolTxs(serde_json::Value),
+    NewBurnBlock(serde_json::Value),
+}
+
+#[derive(Debug, Clone)]
+enum ObserverTarget {
+    Http(String),
+    Channel(Sender<NodeEvent>),
+}
+
 #[derive(Debug, Clone)]
 struct EventObserver {
-    endpoint: String,
+    target: ObserverTarget,
+    schema_version: u32,
 }
 
 const STATUS_RESP_TRUE: &str = "success";
@@ -32,11 +51,46 @@ const STATUS_RESP_POST_CONDITION: &str  = "abort_by_post_condition";
 
 pub const PATH_MEMPOOL_TX_SUBMIT: &str = "new_mempool_tx";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
+pub const PATH_BURN_BLOCK_PROCESSED: &str = "new_burn_block";
+
+/// Major versions of the observer payload schemas this node can emit.  A major version is
+/// bumped whenever a payload changes in a way that existing consumers could misread:
+///  * 1: mempool payloads are a bare JSON array of raw transactions.
+///  * 2: mempool payloads are an object carrying `schema_version` and `transactions`.
+pub const EVENT_SCHEMA_SUPPORTED_VERSIONS: [u32; 2] = [1, 2];
+/// Major version used for observers that don't request one.
+pub const EVENT_SCHEMA_DEFAULT_VERSION: u32 = 1;
+/// Bumped for backwards-compatible additions (i.e. new fields) within a major version:
+///  * 1: `/new_block` transactions carry a `call_trace`.
+pub const EVENT_SCHEMA_MINOR_VERSION: u32 = 1;
+
+pub const HEADER_EVENT_SCHEMA_VERSION: &str = "X-Stacks-Event-Schema-Version";
+
+pub fn event_schema_version_string(major: u32) -> String {
+    format!("{}.{}", major, EVENT_SCHEMA_MINOR_VERSION)
+}
 
 impl EventObserver {
 
     fn send_payload(&self, payload: &serde_json::Value, path: &str) {
+        match self.target {
+            ObserverTarget::Http(ref endpoint) => self.send_http_payload(endpoint, payload, path),
+            ObserverTarget::Channel(ref sender) => {
+                let event = match path {
+                    PATH_BLOCK_PROCESSED => NodeEvent::NewBlock(payload.clone()),
+                    PATH_MEMPOOL_TX_SUBMIT => NodeEvent::NewMempoolTxs(payload.clone()),
+                    PATH_BURN_BLOCK_PROCESSED => NodeEvent::NewBurnBlock(payload.clone()),
+                    _ => unreachable!("BUG: no in-process event for path {}", path)
+                };
+                if sender.send(event).is_err() {
+                    // not fatal -- the embedding process may simply have stopped listening
+                    warn!("Event dispatcher: in-process observer hung up");
+                }
+            }
+        }
+    }
 
+    fn send_http_payload(&self, endpoint: &str, payload: &serde_json::Value, path: &str) {
         let body = match serde_json::to_vec(&payload) {
             Ok(body) => body,
             Err(err) => {
@@ -46,7 +100,7 @@ impl EventObserver {
         };
 
         let url = {
-            let url = format!("{}{}", &self.endpoint, path);
+            let url = format!("{}{}", endpoint, path);
             Url::parse(&url).expect(&format!("Unable to parse {} as a URL", url))
         };
 
@@ -57,10 +111,11 @@ impl EventObserver {
             let mut req = Request::new(Method::Post, url.clone());
             req.append_header("Content-Type", "application/json").expect("Unable to set header");
             req.append_header("Content-Length", format!("{}", body.len())).expect("Unable to set header");
+            req.append_header(HEADER_EVENT_SCHEMA_VERSION, event_schema_version_string(self.schema_version)).expect("Unable to set header");
             req.set_body(body);
 
             let response = async_std::task::block_on(async {
-                let stream = match TcpStream::connect(self.endpoint.clone()).await {
+                let stream = match TcpStream::connect(endpoint.to_string()).await {
                     Ok(stream) => stream,
                     Err(err) => {
                         println!("Event dispatcher: connection failed  - {:?}", err);
@@ -81,26 +136,102 @@ impl EventObserver {
                 if response.status().is_success() {
                     break;
                 } else {
-                    error!("Event dispatcher: POST {} failed with error {:?}", self.endpoint, response);
+                    error!("Event dispatcher: POST {} failed with error {:?}", endpoint, response);
                 }
             }
             sleep(backoff);
         };
     }
 
-    fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
-        let raw_txs = transactions.into_iter().map(|tx| {
+    fn make_new_mempool_txs_payload(transactions: &Vec<StacksTransaction>, schema_version: u32) -> serde_json::Value {
+        let raw_txs = transactions.iter().map(|tx| {
             serde_json::Value::String(
                 format!("0x{}", &bytes_to_hex(&tx.serialize_to_vec())))
         }).collect();
 
-        serde_json::Value::Array(raw_txs)
+        match schema_version {
+            1 => serde_json::Value::Array(raw_txs),
+            _ => json!({
+                "schema_version": event_schema_version_string(schema_version),
+                "transactions": serde_json::Value::Array(raw_txs),
+            })
+        }
     }
 
     fn send_new_mempool_txs(&self, payload: &serde_json::Value) {
         self.send_payload(payload, PATH_MEMPOOL_TX_SUBMIT);
     }
 
+    fn make_new_burn_block_payload(block_snapshot: &BlockSnapshot, ops: &[BlockstackOperationType], schema_version: u32) -> serde_json::Value {
+        let mut key_registrations = vec![];
+        let mut block_commits = vec![];
+        let mut user_burn_supports = vec![];
+        for op in ops.iter() {
+            match op {
+                BlockstackOperationType::LeaderKeyRegister(ref op) => {
+                    key_registrations.push(json!({
+                        "txid": format!("0x{}", op.txid.to_hex()),
+                        "vtxindex": op.vtxindex,
+                        "address": op.address.to_string(),
+                        "vrf_public_key": format!("0x{}", op.public_key.to_hex()),
+                        "consensus_hash": format!("0x{}", op.consensus_hash.to_hex()),
+                    }));
+                },
+                BlockstackOperationType::LeaderBlockCommit(ref op) => {
+                    block_commits.push(json!({
+                        "txid": format!("0x{}", op.txid.to_hex()),
+                        "vtxindex": op.vtxindex,
+                        "sender": op.input.to_testnet_address(),
+                        "block_hash": format!("0x{}", op.block_header_hash.to_hex()),
+                        "burn_fee": op.burn_fee,
+                        "parent_block_ptr": op.parent_block_ptr,
+                        "parent_vtxindex": op.parent_vtxindex,
+                        "key_block_ptr": op.key_block_ptr,
+                        "key_vtxindex": op.key_vtxindex,
+                        "won_sortition": block_snapshot.sortition && op.txid == block_snapshot.winning_block_txid,
+                    }));
+                },
+                BlockstackOperationType::UserBurnSupport(ref op) => {
+                    user_burn_supports.push(json!({
+                        "txid": format!("0x{}", op.txid.to_hex()),
+                        "vtxindex": op.vtxindex,
+                        "address": op.address.to_string(),
+                        "block_hash_160": format!("0x{}", op.block_header_hash_160.to_hex()),
+                        "burn_fee": op.burn_fee,
+                        "key_block_ptr": op.key_block_ptr,
+                        "key_vtxindex": op.key_vtxindex,
+                    }));
+                }
+            }
+        }
+
+        let (winning_block_txid, winning_block_hash) = match block_snapshot.sortition {
+            true => (json!(format!("0x{}", block_snapshot.winning_block_txid.to_hex())),
+                     json!(format!("0x{}", block_snapshot.winning_stacks_block_hash.to_hex()))),
+            false => (json!(null), json!(null))
+        };
+
+        json!({
+            "schema_version": event_schema_version_string(schema_version),
+            "burn_block_hash": format!("0x{}", block_snapshot.burn_header_hash.to_hex()),
+            "burn_block_height": block_snapshot.block_height,
+            "burn_block_time": block_snapshot.burn_header_timestamp,
+            "parent_burn_block_hash": format!("0x{}", block_snapshot.parent_burn_header_hash.to_hex()),
+            "consensus_hash": format!("0x{}", block_snapshot.consensus_hash.to_hex()),
+            "total_burn": block_snapshot.total_burn,
+            "sortition": block_snapshot.sortition,
+            "winning_block_txid": winning_block_txid,
+            "winning_block_hash": winning_block_hash,
+            "leader_key_registrations": key_registrations,
+            "block_commits": block_commits,
+            "user_burn_supports": user_burn_supports,
+        })
+    }
+
+    fn send_new_burn_block(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_BURN_BLOCK_PROCESSED);
+    }
+
     fn send(&mut self, filtered_events: Vec<&(bool, Txid, &StacksTransactionEvent)>, chain_tip: &ChainTip,
             parent_index_hash: &StacksBlockId) {
         // Serialize events to JSON
@@ -146,6 +277,7 @@ impl EventObserver {
                     None => json!(null)
                 }
             };
+            let call_trace: Vec<serde_json::Value> = receipt.call_trace.iter().map(|call| call.json_serialize()).collect();
             let val = json!({
                 "txid": format!("0x{}", tx.txid()),
                 "tx_index": tx_index,
@@ -153,6 +285,8 @@ impl EventObserver {
                 "raw_result": format!("0x{}", raw_result.join("")),
                 "raw_tx": format!("0x{}", raw_tx.join("")),
                 "contract_abi": contract_interface_json,
+                "call_trace": call_trace,
+                "abort_code": receipt.abort_code.map(|code| code.code()),
             });
             tx_index += 1;
             val
@@ -160,6 +294,7 @@ impl EventObserver {
         
         // Wrap events
         let payload = json!({
+            "schema_version": event_schema_version_string(self.schema_version),
             "block_hash": format!("0x{}", chain_tip.block.block_hash()),
             "block_height": chain_tip.metadata.block_height,
             "burn_block_time": chain_tip.metadata.burn_header_timestamp,
@@ -182,6 +317,7 @@ pub struct EventDispatcher {
     contract_events_observers_lookup: HashMap<(QualifiedContractIdentifier, String), HashSet<u16>>,
     assets_observers_lookup: HashMap<AssetIdentifier, HashSet<u16>>,
     mempool_observers_lookup: HashSet<u16>,
+    burn_block_observers_lookup: HashSet<u16>,
     stx_observers_lookup: HashSet<u16>,
     any_event_observers_lookup: HashSet<u16>,
 }
@@ -196,6 +332,7 @@ impl EventDispatcher {
             stx_observers_lookup: HashSet::new(),
             any_event_observers_lookup: HashSet::new(),
             mempool_observers_lookup: HashSet::new(),
+            burn_block_observers_lookup: HashSet::new(),
         }
     }
 
@@ -263,10 +400,32 @@ impl EventDispatcher {
             return;
         }
 
-        let payload = EventObserver::make_new_mempool_txs_payload(txs);
+        // observers on the same schema version share a payload
+        let mut payloads: HashMap<u32, serde_json::Value> = HashMap::new();
+        for (_, observer) in interested_observers.iter() {
+            let payload = payloads.entry(observer.schema_version)
+                .or_insert_with(|| EventObserver::make_new_mempool_txs_payload(&txs, observer.schema_version));
+            observer.send_new_mempool_txs(payload);
+        }
+    }
+
+    /// Announce a processed burnchain block, along with the leader key registrations, block
+    /// commits and user burn supports it accepted, and how its sortition went.
+    pub fn process_burn_block(&self, block_snapshot: &BlockSnapshot, ops: &[BlockstackOperationType]) {
+        let interested_observers: Vec<_> = self.registered_observers.iter().enumerate().filter(
+            |(obs_id, _observer)| {
+                self.burn_block_observers_lookup.contains(&(*obs_id as u16)) ||
+                    self.any_event_observers_lookup.contains(&(*obs_id as u16))
+            }).collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
 
+        let mut payloads: HashMap<u32, serde_json::Value> = HashMap::new();
         for (_, observer) in interested_observers.iter() {
-            observer.send_new_mempool_txs(&payload);
+            let payload = payloads.entry(observer.schema_version)
+                .or_insert_with(|| EventObserver::make_new_burn_block_payload(block_snapshot, ops, observer.schema_version));
+            observer.send_new_burn_block(payload);
         }
     }
 
@@ -280,14 +439,29 @@ impl EventDispatcher {
 
     pub fn register_observer(&mut self, conf: &EventObserverConfig) {
         // let event_observer = EventObserver::new(&conf.address, conf.port);
-        info!("Registering event observer at: {}", conf.endpoint);
+        info!("Registering event observer at: {} (schema version {})", conf.endpoint, conf.schema_version);
         let event_observer = EventObserver { 
-            endpoint: conf.endpoint.clone(),
+            target: ObserverTarget::Http(conf.endpoint.clone()),
+            schema_version: conf.schema_version,
         };
+        self.add_observer(event_observer, &conf.events_keys);
+    }
 
+    /// Register an observer living in this process.  Events matching `events_keys` are sent
+    /// down `sender` instead of being POSTed to an endpoint.
+    pub fn register_channel_observer(&mut self, events_keys: &[EventKeyType], schema_version: u32, sender: Sender<NodeEvent>) {
+        info!("Registering in-process event observer (schema version {})", schema_version);
+        let event_observer = EventObserver {
+            target: ObserverTarget::Channel(sender),
+            schema_version,
+        };
+        self.add_observer(event_observer, events_keys);
+    }
+
+    fn add_observer(&mut self, event_observer: EventObserver, events_keys: &[EventKeyType]) {
         let observer_index = self.registered_observers.len() as u16;
 
-        for event_key_type in conf.events_keys.iter() {
+        for event_key_type in events_keys.iter() {
             match event_key_type {
                 EventKeyType::SmartContractEvent(event_key) => {
                     match self.contract_events_observers_lookup.entry(event_key.clone()) {
@@ -304,6 +478,9 @@ impl EventDispatcher {
                 EventKeyType::MemPoolTransactions => {
                     self.mempool_observers_lookup.insert(observer_index);
                 },
+                EventKeyType::BurnchainBlocks => {
+                    self.burn_block_observers_lookup.insert(observer_index);
+                },
                 EventKeyType::STXEvent => {
                     self.stx_observers_lookup.insert(observer_index);
                 },
@@ -0,0 +1,128 @@
+//! Run a follower node inside another process.  Instead of POSTing to observer endpoints, the
+//! node delivers events to channels owned by the embedding process:
+//!
+//! ```ignore
+//! let (sender, receiver) = std::sync::mpsc::channel();
+//! let node = Node::builder()
+//!     .config(config)
+//!     .subscribe(vec![EventKeyType::AnyEvent], sender)
+//!     .start()?;
+//! while let Ok(event) = receiver.recv() {
+//!     // index the event
+//! }
+//! node.stop();
+//! node.join()?;
+//! ```
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use crate::{Config, EventDispatcher, Node, NodeEvent, WatchOnlyKeychain};
+use crate::config::EventKeyType;
+use crate::event_dispatcher::{EVENT_SCHEMA_DEFAULT_VERSION, EVENT_SCHEMA_SUPPORTED_VERSIONS};
+use crate::run_loop::{neon, panic_message};
+
+pub struct FollowerNodeBuilder {
+    config: Option<Config>,
+    watch_keychain: Option<WatchOnlyKeychain>,
+    event_dispatcher: EventDispatcher,
+    error: Option<String>,
+}
+
+pub struct FollowerNode {
+    should_keep_running: Arc<AtomicBool>,
+    run_loop_thread: JoinHandle<Result<(), String>>,
+}
+
+impl FollowerNodeBuilder {
+    /// Use this node config.  It must be for a neon (or argon) burnchain; mining is disabled.
+    pub fn config(mut self, config: Config) -> FollowerNodeBuilder {
+        self.config = Some(config);
+        self
+    }
+
+    /// Watch the burnchain operations of this keychain's address.  No secret keys are needed.
+    pub fn watch(mut self, watch_keychain: WatchOnlyKeychain) -> FollowerNodeBuilder {
+        self.watch_keychain = Some(watch_keychain);
+        self
+    }
+
+    /// Send events matching `events_keys` to `sender`, using the default payload schema version.
+    pub fn subscribe(self, events_keys: Vec<EventKeyType>, sender: Sender<NodeEvent>) -> FollowerNodeBuilder {
+        self.subscribe_with_schema_version(events_keys, EVENT_SCHEMA_DEFAULT_VERSION, sender)
+    }
+
+    /// Send events matching `events_keys` to `sender`, using the given payload schema major version.
+    pub fn subscribe_with_schema_version(mut self, events_keys: Vec<EventKeyType>, schema_version: u32, sender: Sender<NodeEvent>) -> FollowerNodeBuilder {
+        if !EVENT_SCHEMA_SUPPORTED_VERSIONS.contains(&schema_version) {
+            self.error = Some(format!("Unsupported event schema version {} (supported: {:?})", schema_version, EVENT_SCHEMA_SUPPORTED_VERSIONS));
+            return self;
+        }
+        self.event_dispatcher.register_channel_observer(&events_keys, schema_version, sender);
+        self
+    }
+
+    /// Start the node's run loop in a new thread.
+    pub fn start(self) -> Result<FollowerNode, String> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let mut config = self.config.ok_or("No config given".to_string())?;
+        if config.burnchain.mode != "neon" && config.burnchain.mode != "argon" {
+            return Err(format!("Burnchain mode '{}' cannot run a follower node", config.burnchain.mode));
+        }
+        config.node.miner = false;
+        if self.watch_keychain.is_some() {
+            config.node.watch_keychain = self.watch_keychain;
+        }
+
+        let mut run_loop = neon::RunLoop::new(config);
+        run_loop.event_dispatcher = self.event_dispatcher;
+        let should_keep_running = run_loop.get_termination_switch();
+
+        let run_loop_thread = thread::Builder::new()
+            .name("follower-run-loop".to_string())
+            .spawn(move || run_loop.start(0))
+            .map_err(|e| format!("Failed to spawn run loop thread: {:?}", e))?;
+
+        Ok(FollowerNode {
+            should_keep_running,
+            run_loop_thread,
+        })
+    }
+}
+
+impl Node {
+    /// Set up a follower node to run inside this process.
+    pub fn builder() -> FollowerNodeBuilder {
+        FollowerNode::builder()
+    }
+}
+
+impl FollowerNode {
+    pub fn builder() -> FollowerNodeBuilder {
+        FollowerNodeBuilder {
+            config: None,
+            watch_keychain: None,
+            event_dispatcher: EventDispatcher::new(),
+            error: None,
+        }
+    }
+
+    /// Ask the node to stop.  The run loop stops waiting on the burnchain, and stops and joins
+    /// all of the node's threads before it returns.
+    pub fn stop(&self) {
+        self.should_keep_running.store(false, Ordering::SeqCst);
+    }
+
+    /// Wait for the node to finish.  Returns an error if the run loop or any of the node's
+    /// threads failed.
+    pub fn join(self) -> Result<(), String> {
+        match self.run_loop_thread.join() {
+            Ok(result) => result,
+            Err(panic) => Err(format!("Run loop thread panicked: {}", panic_message(&panic)))
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use stacks::chainstate::burn::db::burndb::BurnDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::db::forks::ForkTip;
+
+use crate::clarity_repl::find_vm_dir;
+use crate::node::TESTNET_CHAIN_ID;
+use crate::Config;
+
+/// Describe a fork tip, for printing:  whether it's canonical, its weight, and where it left the
+/// canonical chain
+pub fn describe_fork_tip(tip: &ForkTip) -> String {
+    let status =
+        if tip.canonical {
+            "canonical"
+        }
+        else if tip.on_canonical_burn_chain {
+            "on the canonical burn chain"
+        }
+        else {
+            "off the canonical burn chain"
+        };
+    let total_work = &tip.header_info.anchored_header.total_work;
+    let mut description = format!("{} {}: {}, burn {}, length {}",
+                                  tip.header_info.block_height, &tip.index_block_hash(), status, total_work.burn, total_work.work);
+    if !tip.canonical {
+        description.push_str(&format!(", {} blocks since height {} ({})", tip.fork_length(), tip.fork_point_height, &tip.fork_point));
+    }
+    description
+}
+
+/// List the tip of every Stacks fork a node has processed blocks in, canonical tip first (see
+/// StacksChainState::get_fork_tips()).  Neither the chainstate nor the sortition DB is written.
+pub fn list_fork_tips(config: &Config) -> Result<Vec<ForkTip>, String> {
+    let chainstate_path = config.get_chainstate_path();
+    find_vm_dir(&chainstate_path)?;
+
+    let burn_db_path = config.get_burn_db_file_path();
+    if !Path::new(&burn_db_path).exists() {
+        return Err(format!("No sortition DB found at {}", &burn_db_path));
+    }
+    let burndb = BurnDB::open(&burn_db_path, false)
+        .map_err(|e| format!("Failed to open the sortition DB at {}: {:?}", &burn_db_path, &e))?;
+    let mut chainstate = StacksChainState::open_with_block_limit(
+        false, TESTNET_CHAIN_ID, &chainstate_path, config.block_limit.clone())
+        .map_err(|e| format!("Failed to open the chainstate at {}: {:?}", &chainstate_path, &e))?;
+
+    chainstate.get_fork_tips(&burndb)
+        .map_err(|e| format!("Failed to list the fork tips: {:?}", &e))
+}
@@ -20,6 +20,55 @@ pub struct Keychain {
     vrf_map: HashMap<VRFPublicKey, VRFPrivateKey>,
 }
 
+/// The public half of a keychain.  It can derive the keychain's addresses and recognize its
+/// burnchain operations, but holds no secret keys, so it cannot sign anything.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchOnlyKeychain {
+    public_keys: Vec<StacksPublicKey>,
+    threshold: u16,
+    hash_mode: AddressHashMode,
+}
+
+impl WatchOnlyKeychain {
+
+    pub fn new(public_keys: Vec<StacksPublicKey>, threshold: u16, hash_mode: AddressHashMode) -> WatchOnlyKeychain {
+        Self {
+            public_keys,
+            threshold,
+            hash_mode,
+        }
+    }
+
+    /// Watch a single-sig (P2PKH) address if given one key, or a multisig (P2SH) address
+    /// otherwise.
+    pub fn from_public_keys(public_keys: Vec<StacksPublicKey>, threshold: u16) -> WatchOnlyKeychain {
+        let hash_mode = if public_keys.len() == 1 {
+            AddressHashMode::SerializeP2PKH
+        } else {
+            AddressHashMode::SerializeP2SH
+        };
+        WatchOnlyKeychain::new(public_keys, threshold, hash_mode)
+    }
+
+    /// Computes and returns the Stacks address of the watched keys.
+    /// Note: Testnet bit is hardcoded.
+    pub fn get_address(&self) -> StacksAddress {
+        StacksAddress::from_public_keys(
+            self.hash_mode.to_version_testnet(),
+            &self.hash_mode, 
+            self.threshold as usize, 
+            &self.public_keys).unwrap()
+    }
+
+    pub fn get_burnchain_signer(&self) -> BurnchainSigner {
+        BurnchainSigner {
+            hash_mode: self.hash_mode,
+            num_sigs: self.threshold as usize,
+            public_keys: self.public_keys.clone()
+        }
+    }
+}
+
 impl Keychain {
 
     pub fn new(secret_keys: Vec<StacksPrivateKey>, threshold: u16, hash_mode: AddressHashMode) -> Keychain {
@@ -141,12 +190,7 @@ impl Keychain {
     /// Given the keychain's secret keys, computes and returns the corresponding Stack address.
     /// Note: Testnet bit is hardcoded.
     pub fn get_address(&self) -> StacksAddress {
-        let public_keys = self.secret_keys.iter().map(|ref pk| StacksPublicKey::from_private(pk)).collect();
-        StacksAddress::from_public_keys(
-            self.hash_mode.to_version_testnet(),
-            &self.hash_mode, 
-            self.threshold as usize, 
-            &public_keys).unwrap()
+        self.watch_only().get_address()
     }
 
     pub fn address_from_burnchain_signer(signer: &BurnchainSigner) -> StacksAddress {
@@ -158,12 +202,13 @@ impl Keychain {
     }
 
     pub fn get_burnchain_signer(&self) -> BurnchainSigner {
+        self.watch_only().get_burnchain_signer()
+    }
+
+    /// Drops the secret keys, keeping what's needed to watch this keychain's addresses.
+    pub fn watch_only(&self) -> WatchOnlyKeychain {
         let public_keys = self.secret_keys.iter().map(|ref pk| StacksPublicKey::from_private(pk)).collect();
-        BurnchainSigner {
-            hash_mode: self.hash_mode,
-            num_sigs: self.threshold as usize,
-            public_keys
-        }
+        WatchOnlyKeychain::new(public_keys, self.threshold, self.hash_mode)
     }
 
     pub fn get_transaction_auth(&self) -> Option<TransactionAuth> {
@@ -0,0 +1,49 @@
+extern crate rand;
+extern crate serde;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_derive;
+#[macro_use] extern crate serde_json;
+#[macro_use] extern crate stacks;
+
+pub use stacks::util;
+
+pub mod monitoring;
+
+pub mod run_loop; 
+pub mod keychain;
+pub mod node;
+pub mod tenure;
+pub mod config;
+pub mod event_dispatcher;
+pub mod operations;
+pub mod burnchains;
+pub mod neon_node;
+pub mod follower;
+pub mod version_check;
+pub mod debugger;
+pub mod clarity_repl;
+pub mod encode_args;
+pub mod snapshot;
+pub mod replay;
+pub mod db_check;
+pub mod db_migrate;
+pub mod db_maintenance;
+pub mod state_diff;
+pub mod reindex;
+pub mod forks;
+pub mod backup;
+pub mod block_compression;
+
+pub use self::keychain::{Keychain, WatchOnlyKeychain};
+pub use self::node::{Node, ChainTip};
+pub use self::neon_node::{InitializedNeonNode, NeonGenesisNode};
+pub use self::burnchains::{MocknetController, BitcoinRegtestController, BurnchainTip, BurnchainController};
+pub use self::tenure::{Tenure};
+pub use self::config::{Config, ConfigFile};
+pub use self::event_dispatcher::{EventDispatcher, NodeEvent};
+pub use self::run_loop::{neon, helium};
+pub use self::follower::{FollowerNode, FollowerNodeBuilder};
+
+#[cfg(test)]
+pub mod tests;
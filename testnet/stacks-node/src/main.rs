@@ -1,36 +1,21 @@
-extern crate rand;
-extern crate serde;
-
-#[macro_use] extern crate lazy_static;
-#[macro_use] extern crate serde_derive;
-#[macro_use] extern crate serde_json;
-#[macro_use] extern crate stacks;
-
-pub use stacks::util;
-
-pub mod monitoring;
-
-pub mod run_loop; 
-pub mod keychain;
-pub mod node;
-pub mod tenure;
-pub mod config;
-pub mod event_dispatcher;
-pub mod operations;
-pub mod burnchains;
-pub mod neon_node;
-
-pub use self::keychain::{Keychain};
-pub use self::node::{Node, ChainTip};
-pub use self::neon_node::{InitializedNeonNode, NeonGenesisNode};
-pub use self::burnchains::{MocknetController, BitcoinRegtestController, BurnchainTip, BurnchainController};
-pub use self::tenure::{Tenure};
-pub use self::config::{Config, ConfigFile};
-pub use self::event_dispatcher::{EventDispatcher};
-pub use self::run_loop::{neon, helium};
+extern crate stacks_node;
+
+use stacks_node::{Config, ConfigFile, neon, helium};
+use stacks_node::clarity_repl::ClarityRepl;
+use stacks_node::encode_args::{encode_args, decode_args};
+use stacks_node::snapshot::{create_snapshot, restore_snapshot};
+use stacks_node::replay::replay_blocks;
+use stacks_node::db_check::check_databases;
+use stacks_node::db_migrate::{migrate_databases, parse_databases};
+use stacks_node::state_diff::{describe_state_key, diff_state};
+use stacks_node::reindex::{default_indexes, parse_indexes, reindex};
+use stacks_node::forks::{describe_fork_tip, list_fork_tips};
+use stacks_node::backup::backup_node;
+use stacks_node::block_compression::migrate_blocks;
 
 use pico_args::Arguments;
 use std::env;
+use std::process;
 
 fn main() {
 
@@ -60,6 +45,274 @@ fn main() {
             println!("==> {}", config_path);
             ConfigFile::from_path(&config_path)
         }
+        "clarity-repl" => {
+            let fork: Option<String> = args.opt_value_from_str("--fork").unwrap();
+            let tip: Option<String> = args.opt_value_from_str("--tip").unwrap();
+            args.finish().unwrap();
+
+            let repl = match fork {
+                Some(chainstate_dir) => ClarityRepl::fork(&chainstate_dir, tip.as_ref().map(|tip| tip.as_str())),
+                None => ClarityRepl::new()
+            };
+            match repl {
+                Ok(mut repl) => repl.run(),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "encode-args" | "decode-args" => {
+            let values = args.free().unwrap();
+            let result = if subcommand == "encode-args" {
+                encode_args(&values)
+            } else {
+                decode_args(&values)
+            };
+            match result {
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "snapshot" => {
+            let action = args.subcommand().unwrap().unwrap_or_default();
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            let result = match action.as_str() {
+                "create" => {
+                    let output: String = args.value_from_str("--output").unwrap();
+                    args.finish().unwrap();
+                    create_snapshot(&conf, &output)
+                }
+                "restore" => {
+                    let input: String = args.value_from_str("--input").unwrap();
+                    let digest: Option<String> = args.opt_value_from_str("--digest").unwrap();
+                    args.finish().unwrap();
+                    restore_snapshot(&conf, &input, digest.as_ref().map(|digest| digest.as_str()))
+                }
+                _ => {
+                    print_help();
+                    return
+                }
+            };
+            match result {
+                Ok((manifest, digest)) => {
+                    println!("Snapshot at block {} (height {}, burn block height {})", &manifest.index_block_hash, manifest.block_height, manifest.burn_block_height);
+                    println!("Digest: {}", &digest);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "replay" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let from: u64 = args.value_from_str("--from").unwrap();
+            let to: u64 = args.value_from_str("--to").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            let result = replay_blocks(&conf, from, to, |replayed| {
+                match replayed.state_root {
+                    Ok(ref state_root) if replayed.matches() => {
+                        println!("{} {}: ok ({})", replayed.block_height, &replayed.index_block_hash, state_root.to_hex());
+                    }
+                    Ok(ref state_root) => {
+                        println!("{} {}: MISMATCH: state root {}, but the header has {}", replayed.block_height, &replayed.index_block_hash,
+                                 state_root.to_hex(), replayed.expected_state_root.to_hex());
+                    }
+                    Err(ref e) => {
+                        println!("{} {}: FAILED: {}", replayed.block_height, &replayed.index_block_hash, e);
+                    }
+                }
+            });
+            match result {
+                Ok(0) => {}
+                Ok(num_mismatched) => {
+                    eprintln!("{} blocks did not replay to the state root in their headers", num_mismatched);
+                    process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "db" => {
+            let action = args.subcommand().unwrap().unwrap_or_default();
+            if action == "migrate" {
+                let config_path: String = args.value_from_str("--config").unwrap();
+                let databases: Option<String> = args.opt_value_from_str("--db").unwrap();
+                let target: Option<u32> = args.opt_value_from_str("--to").unwrap();
+                let dry_run = args.contains("--dry-run");
+                args.finish().unwrap();
+                let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+                let result = parse_databases(databases.as_ref().map(|names| names.as_str()))
+                    .and_then(|databases| migrate_databases(&conf, &databases, target, dry_run));
+                match result {
+                    Ok(migrations) => {
+                        for migration in migrations.iter() {
+                            for step in migration.steps.iter() {
+                                println!("{}: {}", migration.database.name(), step);
+                            }
+                            if dry_run && migration.to_version != migration.from_version {
+                                println!("{}: schema version {}, would be {} (dry run)", migration.database.name(), migration.from_version, migration.to_version);
+                            }
+                            else {
+                                println!("{}: schema version {}", migration.database.name(), migration.to_version);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    }
+                }
+                return;
+            }
+            if action != "check" {
+                print_help();
+                return
+            }
+            let config_path: String = args.value_from_str("--config").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match check_databases(&conf) {
+                Ok(report) => {
+                    for inconsistency in report.inconsistencies.iter() {
+                        println!("Repairable: {} (the node repairs this when it starts)", inconsistency);
+                    }
+                    for integrity_error in report.integrity_errors.iter() {
+                        println!("Damaged: {}: {}", integrity_error, integrity_error.remedy());
+                    }
+                    if !report.is_ok() {
+                        eprintln!("Found {} problems that can't be repaired in place", report.integrity_errors.len());
+                        process::exit(1);
+                    }
+                    println!("Databases are intact");
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "state-diff" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let from: u64 = args.value_from_str("--from").unwrap();
+            let to: u64 = args.value_from_str("--to").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match diff_state(&conf, from, to) {
+                Ok(changes) => {
+                    for change in changes.iter() {
+                        println!("{}: {}: {} -> {}", describe_state_key(&change.state_key), &change.key,
+                                 change.before.as_ref().map(|v| v.as_str()).unwrap_or("(unset)"),
+                                 change.after.as_ref().map(|v| v.as_str()).unwrap_or("(unset)"));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "reindex" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let index_names: Option<String> = args.opt_value_from_str("--index").unwrap();
+            let from: Option<u64> = args.opt_value_from_str("--from").unwrap();
+            let to: Option<u64> = args.opt_value_from_str("--to").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            let indexes = match index_names {
+                Some(ref names) => parse_indexes(Some(names)),
+                None => Ok(default_indexes(&conf))
+            };
+            let result = indexes
+                .and_then(|indexes| reindex(&conf, &indexes, from, to, |reindexed| {
+                    match reindexed.result {
+                        Ok(()) => println!("{} {}: ok", reindexed.block_height, &reindexed.index_block_hash),
+                        Err(ref e) => println!("{} {}: FAILED: {}", reindexed.block_height, &reindexed.index_block_hash, e)
+                    }
+                }));
+            match result {
+                Ok(0) => {}
+                Ok(num_failed) => {
+                    eprintln!("{} blocks could not be reindexed", num_failed);
+                    process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "forks" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match list_fork_tips(&conf) {
+                Ok(tips) => {
+                    for tip in tips.iter() {
+                        println!("{}", describe_fork_tip(tip));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "backup" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let backup_dir: Option<String> = args.opt_value_from_str("--backup-dir").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match backup_node(&conf, backup_dir.as_ref().map(|dir| dir.as_str())) {
+                Ok(summary) => {
+                    println!("Backup in {}", summary.path.display());
+                    println!("{} databases and {} files copied ({} bytes), {} block files linked from the previous backup",
+                             summary.num_databases, summary.num_copied, summary.bytes_copied, summary.num_linked);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "blocks" => {
+            let action = args.subcommand().unwrap().unwrap_or_default();
+            if action != "compress" && action != "decompress" {
+                print_help();
+                return
+            }
+            let config_path: String = args.value_from_str("--config").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match migrate_blocks(&conf, action == "decompress") {
+                Ok(migration) => {
+                    println!("{} files rewritten, from {} to {} bytes; {} left as they were",
+                             migration.num_rewritten, migration.bytes_before, migration.bytes_after, migration.num_skipped);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
         "version" => {
             println!("{}", &stacks::version_string(
                 option_env!("CARGO_PKG_NAME").unwrap_or("stacks-node"),
@@ -81,7 +334,10 @@ fn main() {
         run_loop.start(num_round);
     } else if conf.burnchain.mode == "neon" || conf.burnchain.mode == "argon" {
         let mut run_loop = neon::RunLoop::new(conf);
-        run_loop.start(num_round);
+        if let Err(e) = run_loop.start(num_round) {
+            eprintln!("Node stopped: {}", e);
+            process::exit(1);
+        }
     } else {
         println!("Burnchain mode '{}' not supported", conf.burnchain.mode);
     }
@@ -119,12 +375,99 @@ start\t\tStart a node with a config of your own. Can be used for joining a netwo
 \t\tExample:
 \t\t  stacks-node start --config=/path/to/config.toml
 
+clarity-repl\tDeploy contracts and evaluate Clarity expressions interactively, over a scratch datastore.
+\t\tArguments:
+\t\t  --fork: start from a copy of the Clarity state in a node's chainstate directory (optional).
+\t\t  --tip: with --fork, the index block hash to start from (defaults to the highest block).
+\t\tExample:
+\t\t  stacks-node clarity-repl --fork=/path/to/working_dir/chainstate
+
+encode-args\tEncode Clarity literals as the hex consensus serializations that contract-call arguments are given in, one per line.
+\t\tExample:
+\t\t  stacks-node encode-args u10 \"'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM\" \"(list 1 2)\"
+
+decode-args\tDecode hex consensus serializations of Clarity values back into Clarity literals, one per line.
+\t\tExample:
+\t\t  stacks-node decode-args 0x010000000000000000000000000000000a
+
+snapshot\tPackage a stopped node's chainstate and sortition DB into a verifiable archive, or bootstrap a new node from one.
+\t\tArguments:
+\t\t  create --config --output: write the node's state, as of its canonical Stacks chain tip, to the output file.
+\t\t  restore --config --input: unpack an archive into the node's empty working directory.
+\t\t  --digest: with restore, the archive's expected digest, as printed by create (optional).
+\t\tExample:
+\t\t  stacks-node snapshot create --config=/path/to/config.toml --output=/path/to/snapshot.gz
+
+replay\t\tExecute a stopped node's canonical blocks again, in a scratch copy of its chainstate, and check that
+\t\teach comes to the state root in its header.  Exits with an error if any block doesn't.
+\t\tArguments:
+\t\t  --config: the node's config.
+\t\t  --from, --to: the first and last block heights to replay.
+\t\tExample:
+\t\t  stacks-node replay --config=/path/to/config.toml --from=100 --to=200
+
+db\t\tCheck a stopped node's databases, before starting it:  that every block's header is linked to its parent,
+\t\tits Clarity state has the state root in its header, its block file holds it, and it won its sortition.
+\t\tArguments:
+\t\t  check --config: the node's config.
+\t\tExample:
+\t\t  stacks-node db check --config=/path/to/config.toml
+\t\tOr bring the schemas of a stopped node's databases up to date, or roll them back before downgrading the
+\t\tnode.  The node applies pending migrations itself when it starts.
+\t\tArguments:
+\t\t  migrate --config: the node's config.
+\t\t  --db: optional, a comma-separated list of headers, blocks, sortition and mempool.  Defaults to all.
+\t\t  --to: optional, the schema version to migrate to; needs exactly one --db.  Defaults to the newest.
+\t\t  --dry-run: optional, run the migrations and roll them back.
+\t\tExample:
+\t\t  stacks-node db migrate --config=/path/to/config.toml --db=mempool --to=0 --dry-run
+
+state-diff\tList the Clarity state that differs between two blocks of a node's canonical chain:  each changed
+\t\taccount, contract, data map entry and contract variable or token, with its value at both blocks.
+\t\tArguments:
+\t\t  --config: the node's config.
+\t\t  --from, --to: the block heights to compare.
+\t\tExample:
+\t\t  stacks-node state-diff --config=/path/to/config.toml --from=100 --to=101
+
+reindex\t\tRebuild the indexes a stopped node derives from its blocks (transfer memos, transaction receipts,
+\t\tcontract costs, the STX supply, account balances and transactions by address) from the blocks it
+\t\talready has, without syncing again.
+\t\tArguments:
+\t\t  --config: the node's config.
+\t\t  --index: the indexes to rebuild, comma-separated, from memos, receipts, contract-costs, supply, balances and addresses (optional; all of them by default, but addresses only if `node.index_addresses` is set).
+\t\t  --from, --to: the first and last block heights to reindex (optional; the whole chain by default).
+\t\tExample:
+\t\t  stacks-node reindex --config=/path/to/config.toml --index=receipts,contract-costs
+
+forks\t\tList the tip of every Stacks fork a stopped node has processed blocks in, canonical tip first:  its
+\t\theight, its weight (the burn spent on the fork and its length), whether it was mined on the canonical
+\t\tburn chain, and where it left the canonical chain.
+\t\tArguments:
+\t\t  --config: the node's config.
+\t\tExample:
+\t\t  stacks-node forks --config=/path/to/config.toml
+
+backup\t\tBack up a node's chainstate and sortition DB while it runs, into a new directory under its backup
+\t\tdirectory, and delete the backups beyond its `node.backup_keep`.  Block files that haven't changed
+\t\tsince the previous backup are hardlinked from it.
+\t\tArguments:
+\t\t  --config: the node's config.
+\t\t  --backup-dir: the directory to back up into (optional; `node.backup_dir` by default).
+\t\tExample:
+\t\t  stacks-node backup --config=/path/to/config.toml
+
+blocks\t\tCompress the blocks and microblock streams a stopped node has already stored, at its
+\t\t`node.block_compression_level`, or store them all uncompressed again.
+\t\tArguments:
+\t\t  compress --config: the node's config.
+\t\t  decompress --config: the node's config.
+\t\tExample:
+\t\t  stacks-node blocks compress --config=/path/to/config.toml
+
 version\t\tDisplay informations about the current version and our release cycle.
 
 help\t\tDisplay this help.
 
 ", argv[0]);
 }
-
-#[cfg(test)]
-pub mod tests;
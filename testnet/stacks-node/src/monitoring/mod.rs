@@ -1,4 +1,5 @@
-#![allow(unused_variables)]
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
 pub use stacks::monitoring::{
     increment_warning_emitted_counter, 
@@ -8,7 +9,9 @@ pub use stacks::monitoring::{
 #[cfg(feature = "monitoring_prom")]
 mod prometheus;
 
-pub fn start_serving_monitoring_metrics(bind_address: String) {
+/// Serve the node's metrics on `bind_address`, until `should_keep_running` is cleared
+#[cfg_attr(not(feature = "monitoring_prom"), allow(unused_variables))]
+pub fn start_serving_monitoring_metrics(bind_address: String, should_keep_running: Arc<AtomicBool>) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::start_serving_prometheus_metrics(bind_address);
+    prometheus::start_serving_prometheus_metrics(bind_address, should_keep_running);
 }
\ No newline at end of file
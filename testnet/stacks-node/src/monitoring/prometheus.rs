@@ -1,6 +1,10 @@
 use async_std::net::{TcpListener, TcpStream};
 use async_std::prelude::*;
 use async_std::task;
+use async_std::future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use stacks::prometheus::{Encoder, TextEncoder, gather};
 
 use http_types::{
@@ -9,7 +13,10 @@ use http_types::{
     Body,
 };
 
-pub fn start_serving_prometheus_metrics(bind_address: String) {
+/// How often the server checks whether it's been asked to stop
+const STOP_POLL_MS: u64 = 500;
+
+pub fn start_serving_prometheus_metrics(bind_address: String, should_keep_running: Arc<AtomicBool>) {
     let addr = bind_address.clone();
 
     async_std::task::block_on(async {
@@ -18,7 +25,13 @@ pub fn start_serving_prometheus_metrics(bind_address: String) {
         println!("Prometheus server listening on {}", addr);
     
         let mut incoming = listener.incoming();
-        while let Some(stream) = incoming.next().await {
+        while should_keep_running.load(Ordering::SeqCst) {
+            // wake up now and then to check whether to stop
+            let stream = match future::timeout(Duration::from_millis(STOP_POLL_MS), incoming.next()).await {
+                Ok(Some(stream)) => stream,
+                Ok(None) => break,
+                Err(_timed_out) => continue
+            };
             let stream = match stream {
                 Ok(stream) => stream,
                 Err(err) => {
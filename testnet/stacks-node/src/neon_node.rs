@@ -1,16 +1,22 @@
 use super::{Keychain, Config, BurnchainController, BurnchainTip, EventDispatcher};
-use crate::config::HELIUM_BLOCK_LIMIT;
-use crate::run_loop::RegisteredKey;
+use crate::run_loop::{RegisteredKey, panic_message};
+use crate::version_check::spawn_version_checker;
+use crate::db_maintenance::spawn_db_maintenance;
+use crate::backup::spawn_backups;
 
 use std::convert::{ TryFrom, TryInto };
 use std::{thread, thread::JoinHandle};
 use std::net::SocketAddr;
 use std::collections::VecDeque;
 use std::default::Default;
+use std::path::PathBuf;
 
 use stacks::burnchains::{Burnchain, BurnchainHeaderHash, Txid, PublicKey};
 use stacks::chainstate::burn::db::burndb::{BurnDB};
 use stacks::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo, ClarityTx};
+use stacks::chainstate::stacks::db::blocks::ChainstateInconsistency;
+use stacks::chainstate::stacks::db::contract_costs::contract_costs;
+use stacks::chainstate::stacks::db::prune::PruneProgress;
 use stacks::chainstate::stacks::events::StacksTransactionReceipt;
 use stacks::chainstate::stacks::{
     StacksBlock, TransactionPayload, StacksAddress, StacksTransactionSigner,
@@ -38,8 +44,10 @@ use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::net::{
     db::{ PeerDB, LocalPeer }, relay::Relayer,
     p2p::PeerNetwork, Error as NetError, PeerAddress,
-    NetworkResult, rpc::RPCHandlerArgs
+    NetworkResult, RPCUpdateStatus, ServiceFlags, rpc::RPCHandlerArgs
 };
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::{sync_channel, TrySendError, TryRecvError, SyncSender, Receiver};
 
@@ -50,6 +58,7 @@ use stacks::core::FIRST_BURNCHAIN_BLOCK_HASH;
 use stacks::vm::costs::ExecutionCost;
 
 use stacks::monitoring::{
+    increment_contract_execution_cost_counters,
     increment_stx_blocks_mined_counter,
     increment_stx_blocks_processed_counter,
 };
@@ -71,7 +80,9 @@ enum RelayerDirective {
     ProcessTenure(BurnchainHeaderHash, BurnchainHeaderHash, BlockHeaderHash),
     RunTenure(RegisteredKey, BlockSnapshot),
     RegisterKey(BlockSnapshot),
-    TryProcessAttachable
+    TryProcessAttachable,
+    RepairChainstate(Vec<ChainstateInconsistency>),
+    Exit
 }
 
 
@@ -82,6 +93,11 @@ pub struct InitializedNeonNode {
     active_keys: Vec<RegisteredKey>,
     sleep_before_tenure: u64,
     is_miner: bool,
+    event_dispatcher: EventDispatcher,
+    /// cleared to ask the node's threads to stop
+    should_keep_running: Arc<AtomicBool>,
+    /// the node's threads, and what to call them if they fail
+    threads: Vec<(&'static str, JoinHandle<()>)>,
 }
 
 pub struct NeonGenesisNode {
@@ -229,34 +245,44 @@ fn inner_generate_block_commit_op(
 
 fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAddr,
               config: Config,
-              poll_timeout: u64, relay_channel: SyncSender<RelayerDirective>) -> Result<JoinHandle<()>, NetError> {
+              poll_timeout: u64, relay_channel: SyncSender<RelayerDirective>,
+              safe_mode: Arc<AtomicBool>,
+              update_status: Arc<Mutex<Option<RPCUpdateStatus>>>,
+              should_keep_running: Arc<AtomicBool>) -> Result<(JoinHandle<()>, JoinHandle<()>), NetError> {
 
     let burn_db_path = config.get_burn_db_file_path();
     let stacks_chainstate_path = config.get_chainstate_path();
     let block_limit = config.block_limit;
     let exit_at_block_height = config.burnchain.process_exit_at_block_height;
+    let is_miner = config.node.miner;
+    let light_sync = config.node.light_sync;
+    let marf_cache_size = config.node.marf_cache_size;
 
     this.bind(p2p_sock, rpc_sock).unwrap();
     let (mut dns_resolver, mut dns_client) = DNSResolver::new(10);
-    let burndb = BurnDB::open(&burn_db_path, false)
+    let mut burndb = BurnDB::open_with_pragmas(&burn_db_path, false, &config.node.sqlite_pragmas)
         .map_err(NetError::DBError)?;
+    burndb.set_marf_node_cache_size(marf_cache_size);
 
-    let mut chainstate = StacksChainState::open_with_block_limit(
-        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, block_limit)
+    let mut chainstate = StacksChainState::open_with_pragmas(
+        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, block_limit, &config.node.sqlite_pragmas)
         .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.set_marf_node_cache_size(marf_cache_size);
     
-    let mut mem_pool = MemPoolDB::open(
-        false, TESTNET_CHAIN_ID, &stacks_chainstate_path)
+    let mut mem_pool = MemPoolDB::open_with_pragmas(
+        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, &config.node.sqlite_pragmas)
         .map_err(NetError::DBError)?;
 
     // buffer up blocks to store without stalling the p2p thread
     let mut results_with_data = VecDeque::new();
 
-    let server_thread = thread::spawn(move || {
+    let server_thread = thread::Builder::new().name("p2p".to_string()).spawn(move || {
         let handler_args = RPCHandlerArgs { exit_at_block_height: exit_at_block_height.as_ref(),
+                                            is_miner,
+                                            light_sync,
                                             .. RPCHandlerArgs::default() };
 
-        loop {
+        'p2p: while should_keep_running.load(Ordering::SeqCst) {
             let download_backpressure = results_with_data.len() > 0;
             let poll_ms = 
                 if !download_backpressure && this.has_more_downloads() {
@@ -268,6 +294,13 @@ fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAdd
                     poll_timeout
                 };
 
+            let last_update_status = match update_status.lock() {
+                Ok(update_status) => update_status.clone(),
+                Err(_) => None
+            };
+            let handler_args = RPCHandlerArgs { safe_mode: safe_mode.load(Ordering::SeqCst),
+                                                update_status: last_update_status.as_ref(),
+                                                .. handler_args.clone() };
             let network_result = this.run(&burndb, &mut chainstate, &mut mem_pool, Some(&mut dns_client),
                                           download_backpressure, poll_ms,
                                           &handler_args)
@@ -289,19 +322,20 @@ fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAdd
                         },
                         TrySendError::Disconnected(_) => {
                             info!("Relayer hang up with p2p channel");
-                            break;
+                            break 'p2p;
                         }
                     }
                 }
             }
         }
-    });
+        // dropping the DNS client stops the resolver thread
+    }).map_err(|e| NetError::ChainstateError(format!("Failed to spawn p2p thread: {}", e)))?;
 
-    let _jh = thread::spawn(move || {
+    let dns_thread = thread::Builder::new().name("dns-resolver".to_string()).spawn(move || {
         dns_resolver.thread_main();
-    });
+    }).map_err(|e| NetError::ChainstateError(format!("Failed to spawn DNS resolver thread: {}", e)))?;
 
-    Ok(server_thread)
+    Ok((server_thread, dns_thread))
 }
 
 fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
@@ -309,34 +343,50 @@ fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
                        burn_db_path: String, stacks_chainstate_path: String, 
                        relay_channel: Receiver<RelayerDirective>,
                        mut event_dispatcher: EventDispatcher,
-                       blocks_processed: BlocksProcessedCounter) -> Result<(), NetError> {
+                       blocks_processed: BlocksProcessedCounter,
+                       safe_mode: Arc<AtomicBool>) -> Result<JoinHandle<()>, NetError> {
     // Note: the relayer is *the* block processor, it is responsible for writes to the chainstate --
     //   no other codepaths should be writing once this is spawned.
     //
     // the relayer _should not_ be modifying the burndb,
     //   however, it needs a mut reference to create read TXs.
     //   should address via #1449
-    let mut burndb = BurnDB::open(&burn_db_path, true)
+    let mut burndb = BurnDB::open_with_pragmas(&burn_db_path, true, &config.node.sqlite_pragmas)
         .map_err(NetError::DBError)?;
+    burndb.set_marf_node_cache_size(config.node.marf_cache_size);
 
-    let mut chainstate = StacksChainState::open_with_block_limit(
-        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, config.block_limit.clone())
+    let mut chainstate = StacksChainState::open_with_pragmas(
+        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, config.block_limit.clone(), &config.node.sqlite_pragmas)
         .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.set_checkpoints(config.node.checkpoints.clone(), config.node.assume_valid);
+    chainstate.set_contract_call_tracing(config.node.trace_contract_calls);
+    chainstate.set_block_validation_threads(config.node.block_validation_threads);
+    chainstate.set_block_compression_level(config.node.block_compression);
+    chainstate.set_marf_node_cache_size(config.node.marf_cache_size);
+    chainstate.set_execution_trace_path(config.node.execution_trace_dir.as_ref().map(PathBuf::from));
+    chainstate.set_address_indexing(config.node.index_addresses);
+    if config.node.light_sync {
+        chainstate.enable_light_sync()
+            .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    }
     
-    let mut mem_pool = MemPoolDB::open(
-        false, TESTNET_CHAIN_ID, &stacks_chainstate_path)
+    let mut mem_pool = MemPoolDB::open_with_pragmas(
+        false, TESTNET_CHAIN_ID, &stacks_chainstate_path, &config.node.sqlite_pragmas)
         .map_err(NetError::DBError)?;
 
     let mut last_mined_block: Option<AssembledAnchorBlock> = None;
     let burn_fee_cap = config.burnchain.burn_fee_cap;
     let mine_microblocks = config.node.mine_microblocks;
+    let prune_burn_blocks = config.node.prune_burn_blocks;
+    let prune_fork_depth = config.node.prune_fork_depth;
+    let mut verify_archive = config.node.archive;
 
     let mut bitcoin_controller = BitcoinRegtestController::new_dummy(config);
 
     let blocks_path = chainstate.blocks_path.clone();
     let mut block_on_recv = false;
 
-    let _relayer_handle = thread::spawn(move || {
+    let relayer_thread = thread::Builder::new().name("relayer".to_string()).spawn(move || {
         while let Ok(mut directive) =
             if block_on_recv {
                 relay_channel.recv()
@@ -358,6 +408,14 @@ fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
                     for (headers_and_receipts_opt, _poison_microblock_opt) in block_receipts.into_iter() {
                         // TODO: pass the poison microblock transaction off to the miner!
                         if let Some((header_info, receipts)) = headers_and_receipts_opt {
+                            // blocks below a trusted checkpoint don't count their costs
+                            let unmetered = StacksChainState::is_block_unmetered(&chainstate.headers_db, &header_info.index_block_hash())
+                                .expect("BUG: failure querying chainstate");
+                            if !unmetered {
+                                for contract_cost in contract_costs(&receipts).iter() {
+                                    increment_contract_execution_cost_counters(&contract_cost.contract_id.to_string(), contract_cost.transactions, contract_cost.cost.runtime);
+                                }
+                            }
                             dispatcher_announce_block(&blocks_path, &mut event_dispatcher, header_info, None, &mut burndb, receipts);
                             num_processed += 1;
 
@@ -365,8 +423,41 @@ fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
                         }
                     }
                     if num_processed == 0 {
-                        // out of blocks to process.
-                        block_on_recv = true;
+                        // out of blocks to process, so prune old ones while we're idle
+                        // a batch can scan heights without pruning anything, so pruning is
+                        // only done once a batch has nothing left to scan
+                        let prune_progress = match prune_burn_blocks {
+                            Some(prune_burn_blocks) => chainstate.prune_blocks(&burndb, prune_burn_blocks)
+                                .unwrap_or_else(|e| {
+                                    warn!("Failed to prune blocks: {:?}", &e);
+                                    PruneProgress::default()
+                                }),
+                            None => PruneProgress::default()
+                        };
+                        let fork_prune_progress = match prune_fork_depth {
+                            Some(prune_fork_depth) => chainstate.prune_fork_blocks(&burndb, prune_fork_depth)
+                                .unwrap_or_else(|e| {
+                                    warn!("Failed to prune fork blocks: {:?}", &e);
+                                    PruneProgress::default()
+                                }),
+                            None => PruneProgress::default()
+                        };
+                        // likewise, check the state of blocks from before archive mode was turned on
+                        let num_verified =
+                            if verify_archive {
+                                chainstate.verify_archive()
+                                    .unwrap_or_else(|e| {
+                                        error!("Archive check failed, so queries pinned to some past blocks may fail: {:?}", &e);
+                                        verify_archive = false;
+                                        0
+                                    })
+                            }
+                            else {
+                                0
+                            };
+                        if prune_progress.heights_scanned == 0 && fork_prune_progress.heights_scanned == 0 && num_verified == 0 {
+                            block_on_recv = true;
+                        }
                     }
                 },
                 RelayerDirective::HandleNetResult(ref mut net_result) => {
@@ -413,6 +504,11 @@ fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
                             // advertize _and_ push blocks for now
                             let blocks_available = Relayer::load_blocks_available_data(&burndb, vec![burn_header_hash.clone()])
                                 .expect("Failed to obtain block information for a block we mined.");
+                            if let Some((burn_block_height, _)) = blocks_available.get(&burn_header_hash) {
+                                if let Err(e) = relayer.track_mined_block(burn_header_hash.clone(), mined_block.block_hash(), *burn_block_height) {
+                                    warn!("Failed to track new block: {}", e);
+                                }
+                            }
                             if let Err(e) = relayer.advertize_blocks(blocks_available) {
                                 warn!("Failed to advertise new block: {}", e);
                             }
@@ -471,12 +567,33 @@ fn spawn_miner_relayer(mut relayer: Relayer, local_peer: LocalPeer,
                 RelayerDirective::RegisterKey(ref last_burn_block) => {
                     rotate_vrf_and_register(&mut keychain, last_burn_block, &mut bitcoin_controller);
                     bump_processed_counter(&blocks_processed);
+                },
+                RelayerDirective::RepairChainstate(ref inconsistencies) => {
+                    let mut repaired = true;
+                    for inconsistency in inconsistencies.iter() {
+                        if let Err(e) = chainstate.repair_inconsistency(&mut burndb, inconsistency) {
+                            error!("Failed to repair chainstate ({}): {}", inconsistency, e);
+                            repaired = false;
+                            break;
+                        }
+                    }
+                    if repaired {
+                        info!("Repaired {} chainstate inconsistencies; leaving safe mode", inconsistencies.len());
+                        safe_mode.store(false, Ordering::SeqCst);
+                    }
+                    else {
+                        error!("Staying in safe mode; restart the node to retry the repair");
+                    }
+                },
+                RelayerDirective::Exit => {
+                    info!("Relayer exiting");
+                    break;
                 }
             }
         }
-    });
+    }).map_err(|e| NetError::ChainstateError(format!("Failed to spawn relayer thread: {}", e)))?;
 
-    Ok(())
+    Ok(relayer_thread)
 }
 
 fn dispatcher_announce_block(blocks_path: &str, event_dispatcher: &mut EventDispatcher,
@@ -518,7 +635,7 @@ impl InitializedNeonNode {
            miner: bool, blocks_processed: BlocksProcessedCounter) -> InitializedNeonNode {
         // we can call _open_ here rather than _connect_, since connect is first called in
         //   make_genesis_block
-        let burndb = BurnDB::open(&config.get_burn_db_file_path(), false)
+        let burndb = BurnDB::open_with_pragmas(&config.get_burn_db_file_path(), false, &config.node.sqlite_pragmas)
             .expect("Error while instantiating burnchain db");
 
         let burnchain = Burnchain::new(
@@ -531,6 +648,61 @@ impl InitializedNeonNode {
             BurnDB::get_burnchain_view(&ic, &burnchain).unwrap()
         };
 
+        // look for anything an unclean shutdown left half-written.  If it can be repaired, start
+        // in safe mode: serve reads from the last fully-processed tip while the relayer repairs it.
+        let (inconsistencies, has_pruned) = {
+            let mut chainstate = StacksChainState::open_with_pragmas(
+                false, TESTNET_CHAIN_ID, &config.get_chainstate_path(), config.block_limit.clone(), &config.node.sqlite_pragmas)
+                .expect("Error while opening chain state");
+            let inconsistencies = match chainstate.check_consistency(&burndb) {
+                Ok(inconsistencies) => inconsistencies,
+                Err(e) => panic!("Unrecoverable chain state inconsistency at path {}: {:?}", config.get_chainstate_path(), e)
+            };
+            let has_pruned = StacksChainState::get_pruned_burn_height(&chainstate.blocks_db)
+                .expect("Error while reading block pruning progress") > 0;
+            if config.node.archive {
+                if has_pruned {
+                    panic!("Chain state at path {} has pruned blocks, so it can't be an archive; sync an archive node from scratch", config.get_chainstate_path());
+                }
+                chainstate.enable_archive_mode().expect("Error while turning on archive mode");
+            }
+            else if (config.node.prune_burn_blocks.is_some() || config.node.prune_fork_depth.is_some()) && StacksChainState::is_archive(&chainstate.blocks_db).expect("Error while reading archive mode") {
+                panic!("Chain state at path {} is an archive, so it can't be pruned; remove `node.prune_burn_blocks` and `node.prune_fork_depth`", config.get_chainstate_path());
+            }
+            if config.node.light_sync {
+                chainstate.enable_light_sync().expect("Error while turning on light sync");
+            }
+            else if StacksChainState::is_light_sync(&chainstate.blocks_db).expect("Error while reading light sync") {
+                panic!("Chain state at path {} was light-synced, so it has no Clarity state to execute blocks on; set `node.light_sync`, or sync a full node from scratch", config.get_chainstate_path());
+            }
+            (inconsistencies, has_pruned)
+        };
+        let safe_mode = Arc::new(AtomicBool::new(inconsistencies.len() > 0));
+
+        let should_keep_running = Arc::new(AtomicBool::new(true));
+        let mut threads = vec![];
+
+        // filled in by the version checker, if it's enabled, and reported on /v2/status
+        let update_status = Arc::new(Mutex::new(None));
+        if let Some(ref version_check_url) = config.node.version_check_url {
+            let handle = spawn_version_checker(version_check_url, config.node.version_check_interval, update_status.clone(),
+                                               should_keep_running.clone())
+                .expect("Failed to initialize version check thread");
+            threads.push(("version check", handle));
+        }
+
+        if let Some(ref db_maintenance) = config.node.db_maintenance {
+            let handle = spawn_db_maintenance(&config.node.working_dir, db_maintenance.clone(), should_keep_running.clone())
+                .expect("Failed to initialize DB maintenance thread");
+            threads.push(("DB maintenance", handle));
+        }
+
+        if let Some(ref backup) = config.node.backup {
+            let handle = spawn_backups(&config.node.working_dir, backup.clone(), should_keep_running.clone())
+                .expect("Failed to initialize backup thread");
+            threads.push(("backup", handle));
+        }
+
         // create a new peerdb
         let data_url = UrlString::try_from(format!("{}", &config.node.data_url)).unwrap();
         let mut initial_neighbors = vec![];
@@ -557,7 +729,7 @@ impl InitializedNeonNode {
             my_private_key
         };
 
-        let peerdb = PeerDB::connect(
+        let mut peerdb = PeerDB::connect(
             &config.get_peer_db_path(), 
             true, 
             TESTNET_CHAIN_ID, 
@@ -570,6 +742,24 @@ impl InitializedNeonNode {
             &vec![], 
             Some(&initial_neighbors)).unwrap();
 
+        // tell peers if we can't serve the whole chain.  Pruned data stays gone even if pruning
+        // is turned off again.
+        {
+            let mut services = ServiceFlags::RELAY as u16;
+            if config.node.prune_burn_blocks.is_some() || has_pruned {
+                services |= ServiceFlags::PRUNED as u16;
+            }
+            if config.node.archive {
+                services |= ServiceFlags::ARCHIVE as u16;
+            }
+            if config.node.light_sync {
+                services |= ServiceFlags::LIGHT as u16;
+            }
+            let mut tx = peerdb.tx_begin().unwrap();
+            PeerDB::set_local_services(&mut tx, services).unwrap();
+            tx.commit().unwrap();
+        }
+
         let local_peer = match PeerDB::get_local_peer(peerdb.conn()) {
             Ok(local_peer) => local_peer,
             _ => panic!("Unable to retrieve local peer")
@@ -582,22 +772,40 @@ impl InitializedNeonNode {
         // setup the relayer channel
         let (relay_send, relay_recv) = sync_channel(RELAYER_MAX_BUFFER);
 
-        let burnchain_signer = keychain.get_burnchain_signer();
+        if inconsistencies.len() > 0 {
+            warn!("Found {} chainstate inconsistencies; starting in safe mode", inconsistencies.len());
+            for inconsistency in inconsistencies.iter() {
+                warn!("Chainstate inconsistency: {}", inconsistency);
+            }
+            // goes ahead of any blocks the relayer is asked to process
+            relay_send.send(RelayerDirective::RepairChainstate(inconsistencies))
+                .expect("Failed to queue chainstate repair");
+        }
+
+        // a watch-only node tracks the watched keys' operations instead of its own
+        let burnchain_signer = match config.node.watch_keychain {
+            Some(ref watch_keychain) => watch_keychain.get_burnchain_signer(),
+            None => keychain.get_burnchain_signer()
+        };
         let relayer = Relayer::from_p2p(&mut p2p_net);
 
         let sleep_before_tenure = config.node.wait_time_for_microblocks;
 
-        spawn_miner_relayer(relayer, local_peer,
+        let relayer_thread = spawn_miner_relayer(relayer, local_peer,
                             config.clone(), keychain,
                             config.get_burn_db_file_path(),
                             config.get_chainstate_path(),
-                            relay_recv, event_dispatcher,
-                            blocks_processed.clone())
+                            relay_recv, event_dispatcher.clone(),
+                            blocks_processed.clone(), safe_mode.clone())
             .expect("Failed to initialize mine/relay thread");
+        threads.push(("relayer", relayer_thread));
 
-        spawn_peer(p2p_net, &p2p_sock, &rpc_sock,
-                   config.clone(), 5000, relay_send.clone())
-            .expect("Failed to initialize mine/relay thread");
+        let (p2p_thread, dns_thread) = spawn_peer(p2p_net, &p2p_sock, &rpc_sock,
+                                                  config.clone(), 5000, relay_send.clone(), safe_mode, update_status,
+                                                  should_keep_running.clone())
+            .expect("Failed to initialize p2p thread");
+        threads.push(("p2p", p2p_thread));
+        threads.push(("DNS resolver", dns_thread));
 
 
         info!("Bound HTTP server on: {}", &config.node.rpc_bind);
@@ -616,6 +824,31 @@ impl InitializedNeonNode {
             is_miner,
             sleep_before_tenure,
             active_keys,
+            event_dispatcher,
+            should_keep_running,
+            threads,
+        }
+    }
+
+    /// Stop all of the node's threads, and wait for them to exit.  Returns an error naming each
+    /// thread that panicked.
+    pub fn shutdown(self) -> Result<(), String> {
+        self.should_keep_running.store(false, Ordering::SeqCst);
+        // the relayer blocks on its channel, so it has to be told directly.  It's fine if it's
+        // already gone.
+        self.relayer_exit();
+
+        let mut errors = vec![];
+        for (name, handle) in self.threads.into_iter() {
+            if let Err(panic) = handle.join() {
+                errors.push(format!("{} thread panicked: {}", name, panic_message(&panic)));
+            }
+        }
+        if errors.len() > 0 {
+            Err(errors.join("; "))
+        }
+        else {
+            Ok(())
         }
     }
 
@@ -648,6 +881,14 @@ impl InitializedNeonNode {
         }
     }
 
+    /// Tell the relayer thread to stop.  Once it hangs up, the p2p thread follows.
+    /// returns _false_ if the relayer already hung up the channel.
+    pub fn relayer_exit(&self) -> bool {
+        self.relay_channel
+            .send(RelayerDirective::Exit)
+            .is_ok()
+    }
+
     /// Notify the relayer of a sortition, telling it to process the block
     ///  and advertize it if it was mined by the node.
     /// returns _false_ if the relayer hung up the channel.
@@ -779,7 +1020,7 @@ impl InitializedNeonNode {
 
         let (anchored_block, consumed_execution, bytes_so_far) = match StacksBlockBuilder::build_anchored_block(
             chain_state, mem_pool, &stacks_parent_header, parent_block_total_burn,
-            vrf_proof.clone(), mblock_pubkey_hash, &coinbase_tx, HELIUM_BLOCK_LIMIT.clone()) {
+            vrf_proof.clone(), mblock_pubkey_hash, &coinbase_tx, chain_state.block_limit()) {
             Ok(block) => block,
             Err(e) => {
                 error!("Failure mining anchored block: {}", e);
@@ -830,6 +1071,15 @@ impl InitializedNeonNode {
 
         let block_commits = BurnDB::get_block_commits_by_block(&ic, block_height, burn_hash)
             .expect("Unexpected BurnDB error fetching block commits");
+        let key_registers = BurnDB::get_leader_keys_by_block(&ic, block_height, burn_hash)
+            .expect("Unexpected BurnDB error fetching key registers");
+
+        // announce the block and its ops to observers
+        let burn_ops: Vec<_> = key_registers.iter().cloned().map(BlockstackOperationType::LeaderKeyRegister)
+            .chain(block_commits.iter().cloned().map(BlockstackOperationType::LeaderBlockCommit))
+            .collect();
+        self.event_dispatcher.process_burn_block(&block_snapshot, &burn_ops);
+
         for op in block_commits.into_iter() {
             if op.txid == block_snapshot.winning_block_txid {
                 info!("Received burnchain block #{} including block_commit_op (winning) - {}", block_height, op.input.to_testnet_address());
@@ -843,18 +1093,20 @@ impl InitializedNeonNode {
                 if self.is_miner {
                     info!("Received burnchain block #{} including block_commit_op - {}", block_height, op.input.to_testnet_address());
                 }
+                else if op.input == self.burnchain_signer {
+                    info!("Received burnchain block #{} including block_commit_op from watched address - {}", block_height, op.input.to_testnet_address());
+                }
             }
         }
 
-
-
-        let key_registers = BurnDB::get_leader_keys_by_block(&ic, block_height, burn_hash)
-            .expect("Unexpected BurnDB error fetching key registers");
         for op in key_registers.into_iter() {
             if self.is_miner {
                 info!("Received burnchain block #{} including key_register_op - {}", block_height, op.address);
             }
             if op.address == Keychain::address_from_burnchain_signer(&self.burnchain_signer) {
+                if !self.is_miner {
+                    info!("Received burnchain block #{} including key_register_op from watched address - {}", block_height, op.address);
+                }
                 // Registered key has been mined
                 self.active_keys.push(
                     RegisteredKey {
@@ -877,24 +1129,32 @@ impl NeonGenesisNode {
 
     /// Instantiate and initialize a new node, given a config
     pub fn new<F>(config: Config, boot_block_exec: F) -> Self
+    where F: FnOnce(&mut ClarityTx) -> () {
+        NeonGenesisNode::new_with_event_dispatcher(config, EventDispatcher::new(), boot_block_exec)
+    }
+
+    /// Instantiate and initialize a new node, given a config and an event dispatcher that may
+    /// already have (in-process) observers registered.  The config's observers are added to it.
+    pub fn new_with_event_dispatcher<F>(config: Config, mut event_dispatcher: EventDispatcher, boot_block_exec: F) -> Self
     where F: FnOnce(&mut ClarityTx) -> () {
 
         let keychain = Keychain::default(config.node.seed.clone());
         let initial_balances = config.initial_balances.iter().map(|e| (e.address.clone(), e.amount)).collect();
 
         // do the initial open!
-        let _chain_state = match StacksChainState::open_and_exec(
+        let _chain_state = match StacksChainState::open_and_exec_with_marf_backend(
             false, 
             TESTNET_CHAIN_ID, 
             &config.get_chainstate_path(), 
             Some(initial_balances), 
             boot_block_exec,
-            config.block_limit.clone()) {
+            config.block_limit.clone(),
+            config.node.marf_backend,
+            &config.node.sqlite_pragmas) {
             Ok(res) => res,
             Err(err) => panic!("Error while opening chain state at path {}: {:?}", config.get_chainstate_path(), err)
         };
 
-        let mut event_dispatcher = EventDispatcher::new();
         for observer in config.events_observers.iter() {
             event_dispatcher.register_observer(observer);
         }
@@ -5,6 +5,7 @@ use std::convert::TryFrom;
 use std::{thread, time, thread::JoinHandle};
 use std::net::SocketAddr;
 use std::default::Default;
+use std::path::PathBuf;
 
 use stacks::burnchains::{Burnchain, BurnchainHeaderHash, Txid};
 use stacks::chainstate::burn::db::burndb::{BurnDB};
@@ -27,6 +28,8 @@ use stacks::net::{
 };
 
 use stacks::util::vrf::VRFPublicKey;
+use stacks::util::db::SqlitePragmas;
+use stacks::vm::costs::ExecutionCost;
 use stacks::util::get_epoch_time_secs;
 use stacks::util::strings::UrlString;
 use stacks::util::hash::Sha256Sum;
@@ -71,7 +74,8 @@ pub struct Node {
 
 fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAddr,
               burn_db_path: String, stacks_chainstate_path: String, event_dispatcher: EventDispatcher,
-              exit_at_block_height: Option<u64>, poll_timeout: u64) -> Result<JoinHandle<()>, NetError> {
+              exit_at_block_height: Option<u64>, poll_timeout: u64,
+              sqlite_pragmas: SqlitePragmas) -> Result<JoinHandle<()>, NetError> {
     this.bind(p2p_sock, rpc_sock).unwrap();
     let server_thread = thread::spawn(move || {
         let handler_args = RPCHandlerArgs { exit_at_block_height: exit_at_block_height.as_ref(),
@@ -79,7 +83,7 @@ fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAdd
 
 
         loop {
-            let burndb = match BurnDB::open(&burn_db_path, false) {
+            let burndb = match BurnDB::open_with_pragmas(&burn_db_path, false, &sqlite_pragmas) {
                 Ok(x) => x,
                 Err(e) => {
                     warn!("Error while connecting burnchain db in peer loop: {}", e);
@@ -87,8 +91,8 @@ fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAdd
                     continue;
                 },
             };
-            let mut chainstate = match StacksChainState::open(
-                false, TESTNET_CHAIN_ID, &stacks_chainstate_path) {
+            let mut chainstate = match StacksChainState::open_with_pragmas(
+                false, TESTNET_CHAIN_ID, &stacks_chainstate_path, ExecutionCost::max_value(), &sqlite_pragmas) {
                 Ok(x) => x,
                 Err(e) => {
                     warn!("Error while connecting chainstate db in peer loop: {}", e);
@@ -97,8 +101,8 @@ fn spawn_peer(mut this: PeerNetwork, p2p_sock: &SocketAddr, rpc_sock: &SocketAdd
                 },
             };
 
-            let mut mem_pool = match MemPoolDB::open(
-                false, TESTNET_CHAIN_ID, &stacks_chainstate_path) {
+            let mut mem_pool = match MemPoolDB::open_with_pragmas(
+                false, TESTNET_CHAIN_ID, &stacks_chainstate_path, &sqlite_pragmas) {
                 Ok(x) => x,
                 Err(e) => {
                     warn!("Error while connecting to mempool db in peer loop: {}", e);
@@ -129,14 +133,22 @@ impl Node {
 
         let initial_balances = config.initial_balances.iter().map(|e| (e.address.clone(), e.amount)).collect();
 
-        let chain_state_result = StacksChainState::open_and_exec(
+        let chain_state_result = StacksChainState::open_and_exec_with_marf_backend(
             false, TESTNET_CHAIN_ID, &config.get_chainstate_path(),
-            Some(initial_balances), boot_block_exec, config.block_limit.clone());
+            Some(initial_balances), boot_block_exec, config.block_limit.clone(),
+            config.node.marf_backend, &config.node.sqlite_pragmas);
 
-        let chain_state = match chain_state_result {
+        let mut chain_state = match chain_state_result {
             Ok(res) => res,
             Err(err) => panic!("Error while opening chain state at path {}: {:?}", config.get_chainstate_path(), err)
         };
+        chain_state.set_checkpoints(config.node.checkpoints.clone(), config.node.assume_valid);
+        chain_state.set_contract_call_tracing(config.node.trace_contract_calls);
+        chain_state.set_block_validation_threads(config.node.block_validation_threads);
+        chain_state.set_block_compression_level(config.node.block_compression);
+        chain_state.set_marf_node_cache_size(config.node.marf_cache_size);
+        chain_state.set_execution_trace_path(config.node.execution_trace_dir.as_ref().map(PathBuf::from));
+        chain_state.set_address_indexing(config.node.index_addresses);
         let mut event_dispatcher = EventDispatcher::new();
 
         for observer in &config.events_observers {
@@ -172,15 +184,24 @@ impl Node {
         let chainstate_path = config.get_chainstate_path();
         let burndb_path = config.get_burn_db_file_path();
 
-        let chain_state = match StacksChainState::open(
+        let mut chain_state = match StacksChainState::open_with_pragmas(
             false, 
             TESTNET_CHAIN_ID, 
-            &chainstate_path) {
+            &chainstate_path,
+            ExecutionCost::max_value(),
+            &config.node.sqlite_pragmas) {
             Ok(x) => x,
             Err(_e) => {
                 panic!()
             },
         };
+        chain_state.set_checkpoints(config.node.checkpoints.clone(), config.node.assume_valid);
+        chain_state.set_contract_call_tracing(config.node.trace_contract_calls);
+        chain_state.set_block_validation_threads(config.node.block_validation_threads);
+        chain_state.set_block_compression_level(config.node.block_compression);
+        chain_state.set_marf_node_cache_size(config.node.marf_cache_size);
+        chain_state.set_execution_trace_path(config.node.execution_trace_dir.as_ref().map(PathBuf::from));
+        chain_state.set_address_indexing(config.node.index_addresses);
 
         let mut node = Node {
             active_registered_key: None,
@@ -198,7 +219,7 @@ impl Node {
         node.spawn_peer_server();
 
         loop {
-            let burndb = BurnDB::open(&burndb_path, false).expect("BUG: failed to open burn database");
+            let burndb = BurnDB::open_with_pragmas(&burndb_path, false, &node.config.node.sqlite_pragmas).expect("BUG: failed to open burn database");
             if let Ok(Some(ref chain_tip)) = node.chain_state.get_stacks_chain_tip(&burndb) {
                 if chain_tip.burn_header_hash == burnchain_tip.block_snapshot.burn_header_hash {
                     info!("Syncing Stacks blocks - completed");
@@ -217,7 +238,7 @@ impl Node {
     pub fn spawn_peer_server(&mut self) {
         // we can call _open_ here rather than _connect_, since connect is first called in
         //   make_genesis_block
-        let burndb = BurnDB::open(&self.config.get_burn_db_file_path(), true)
+        let burndb = BurnDB::open_with_pragmas(&self.config.get_burn_db_file_path(), true, &self.config.node.sqlite_pragmas)
             .expect("Error while instantiating burnchain db");
 
         let burnchain = Burnchain::new(
@@ -287,7 +308,8 @@ impl Node {
             self.config.get_chainstate_path(),
             event_dispatcher,
             exit_at_block_height,
-            1000).unwrap();
+            1000,
+            self.config.node.sqlite_pragmas.clone()).unwrap();
 
         info!("Bound HTTP server on: {}", &self.config.node.rpc_bind);
         info!("Bound P2P server on: {}", &self.config.node.p2p_bind);
@@ -355,6 +377,8 @@ impl Node {
         // Keep a pointer of the burnchain's chain tip.
         self.burnchain_tip = Some(burnchain_tip.clone());
 
+        self.event_dispatcher.process_burn_block(&burnchain_tip.block_snapshot, ops);
+
         (self.last_sortitioned_block.clone(), won_sortition)
     }
 
@@ -408,7 +432,7 @@ impl Node {
             }
         };
 
-        let mem_pool = MemPoolDB::open(false, TESTNET_CHAIN_ID, &self.chain_state.root_path).expect("FATAL: failed to open mempool");
+        let mem_pool = MemPoolDB::open_with_pragmas(false, TESTNET_CHAIN_ID, &self.chain_state.root_path, &self.config.node.sqlite_pragmas).expect("FATAL: failed to open mempool");
 
         // Construct the coinbase transaction - 1st txn that should be handled and included in 
         // the upcoming tenure.
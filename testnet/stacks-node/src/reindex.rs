@@ -0,0 +1,99 @@
+use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::db::reindex::DerivedIndex;
+
+use crate::clarity_repl::find_vm_dir;
+use crate::node::TESTNET_CHAIN_ID;
+use crate::snapshot::find_snapshot_tip;
+use crate::Config;
+
+/// What reindexing one block came to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReindexedBlock {
+    pub block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    /// why the block couldn't be reindexed, if it couldn't
+    pub result: Result<(), String>,
+}
+
+/// Parse a comma-separated list of derived indexes, like `receipts,contract-costs`.  No list
+/// means all of them.
+pub fn parse_indexes(names: Option<&str>) -> Result<Vec<DerivedIndex>, String> {
+    let names = match names {
+        Some(names) => names,
+        None => return Ok(DerivedIndex::all())
+    };
+    let mut indexes = vec![];
+    for name in names.split(',').map(|name| name.trim()) {
+        let index = DerivedIndex::from_name(name).ok_or_else(|| {
+            let known : Vec<&str> = DerivedIndex::all().iter().map(|index| index.name()).collect();
+            format!("Unknown index '{}'; the indexes are {}", name, known.join(", "))
+        })?;
+        if !indexes.contains(&index) {
+            indexes.push(index);
+        }
+    }
+    Ok(indexes)
+}
+
+/// The indexes to rebuild when none are named:  all of them, but the address index only if the
+/// node keeps it
+pub fn default_indexes(config: &Config) -> Vec<DerivedIndex> {
+    DerivedIndex::all()
+        .into_iter()
+        .filter(|index| *index != DerivedIndex::AddressTransactions || config.node.index_addresses)
+        .collect()
+}
+
+/// Check that `from` through `to` are block heights that can be reindexed, in a chain whose tip
+/// is at `tip_height`.  Nothing is derived from the boot block at height 0.
+pub fn check_reindex_range(from: u64, to: u64, tip_height: u64) -> Result<(), String> {
+    if from == 0 {
+        return Err("Blocks can only be reindexed from height 1".to_string());
+    }
+    if from > to {
+        return Err(format!("--from ({}) is above --to ({})", from, to));
+    }
+    if to > tip_height {
+        return Err(format!("--to ({}) is above the canonical chain tip, at height {}", to, tip_height));
+    }
+    Ok(())
+}
+
+/// Rebuild `indexes` for every processed block, in every fork, at heights `from` (by default 1)
+/// through `to` (by default the canonical chain tip), in a stopped node's chainstate, from the
+/// blocks it has already stored.  Blocks are reindexed in height order, so the STX supply of
+/// each is rebuilt from its parent's.  Each block is passed to `on_block` once it's reindexed.
+/// Returns how many blocks couldn't be.
+pub fn reindex<F>(config: &Config, indexes: &[DerivedIndex], from: Option<u64>, to: Option<u64>, mut on_block: F) -> Result<u64, String>
+where F: FnMut(&ReindexedBlock) {
+    let chainstate_path = config.get_chainstate_path();
+    find_vm_dir(&chainstate_path)?;
+
+    let tip = find_snapshot_tip(config)?;
+    let from = from.unwrap_or(1);
+    let to = to.unwrap_or(tip.block_height);
+    check_reindex_range(from, to, tip.block_height)?;
+
+    let mut chainstate = StacksChainState::open_with_block_limit(
+        false, TESTNET_CHAIN_ID, &chainstate_path, config.block_limit.clone())
+        .map_err(|e| format!("Failed to open the chainstate at {}: {:?}", &chainstate_path, &e))?;
+
+    let headers = chainstate.get_processed_headers_in_range(from, to)
+        .map_err(|e| format!("Failed to find the blocks at heights {}-{}: {:?}", from, to, &e))?;
+
+    let mut num_failed = 0;
+    for header_info in headers.iter() {
+        let index_block_hash = header_info.index_block_hash();
+        let reindexed = ReindexedBlock {
+            block_height: header_info.block_height,
+            index_block_hash: index_block_hash.clone(),
+            result: chainstate.reindex_block(&index_block_hash, indexes).map_err(|e| e.to_string()),
+        };
+        if reindexed.result.is_err() {
+            num_failed += 1;
+        }
+        on_block(&reindexed);
+    }
+    Ok(num_failed)
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::index::TrieHash;
+
+use crate::node::TESTNET_CHAIN_ID;
+use crate::snapshot::{find_snapshot_tip, list_snapshot_files};
+use crate::Config;
+
+/// What replaying one block of the canonical chain came to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayedBlock {
+    pub block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    /// the state root in the block's header
+    pub expected_state_root: TrieHash,
+    /// the state root replaying the block came to, or why it couldn't be replayed
+    pub state_root: Result<TrieHash, String>,
+}
+
+impl ReplayedBlock {
+    pub fn matches(&self) -> bool {
+        self.state_root.as_ref() == Ok(&self.expected_state_root)
+    }
+}
+
+/// Check that `from` through `to` are block heights that can be replayed, in a chain whose tip
+/// is at `tip_height`.  The boot block at height 0 isn't mined, so it can't be.
+pub fn check_replay_range(from: u64, to: u64, tip_height: u64) -> Result<(), String> {
+    if from == 0 {
+        return Err("Blocks can only be replayed from height 1".to_string());
+    }
+    if from > to {
+        return Err(format!("--from ({}) is above --to ({})", from, to));
+    }
+    if to > tip_height {
+        return Err(format!("--to ({}) is above the canonical chain tip, at height {}", to, tip_height));
+    }
+    Ok(())
+}
+
+/// Copy a node's chainstate into a scratch directory, laid out the same way
+fn copy_chainstate(working_dir: &Path, scratch_dir: &Path) -> Result<(), String> {
+    let files = list_snapshot_files(working_dir)?;
+    for path in files.iter().filter(|path| path.starts_with("chainstate/")) {
+        let dest = scratch_dir.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {:?}", parent.display(), &e))?;
+        }
+        fs::copy(working_dir.join(path), &dest)
+            .map_err(|e| format!("Failed to copy {}: {:?}", path, &e))?;
+    }
+    Ok(())
+}
+
+fn replay_scratch_blocks<F>(config: &Config, chainstate_dir: &Path, tip: &StacksBlockId, from: u64, to: u64, on_block: &mut F) -> Result<u64, String>
+where F: FnMut(&ReplayedBlock) {
+    let mut chainstate = StacksChainState::open_with_block_limit(
+        false, TESTNET_CHAIN_ID, &chainstate_dir.to_string_lossy(), config.block_limit.clone())
+        .map_err(|e| format!("Failed to open the scratch chainstate: {:?}", &e))?;
+
+    let headers = {
+        let mut headers_tx = chainstate.headers_tx_begin()
+            .map_err(|e| format!("Failed to read the scratch chainstate: {:?}", &e))?;
+        StacksChainState::get_index_tip_ancestors(&mut headers_tx, tip, from, to)
+            .map_err(|e| format!("Failed to find the blocks at heights {}-{}: {:?}", from, to, &e))?
+    };
+
+    let mut num_mismatched = 0;
+    for header_info in headers.iter() {
+        let index_block_hash = header_info.index_block_hash();
+        let replayed = ReplayedBlock {
+            block_height: header_info.block_height,
+            index_block_hash: index_block_hash.clone(),
+            expected_state_root: header_info.anchored_header.state_index_root.clone(),
+            state_root: chainstate.replay_block(&index_block_hash).map_err(|e| e.to_string()),
+        };
+        if !replayed.matches() {
+            num_mismatched += 1;
+        }
+        on_block(&replayed);
+    }
+    Ok(num_mismatched)
+}
+
+/// Execute the canonical chain's blocks at heights `from` through `to` again, each on top of its
+/// parent's state, in a scratch copy of a stopped node's chainstate, and compare the state roots
+/// they come to with the ones in their headers.  Each block is passed to `on_block` once it's
+/// replayed.  The node's own chainstate isn't touched.  Returns how many blocks didn't match.
+pub fn replay_blocks<F>(config: &Config, from: u64, to: u64, mut on_block: F) -> Result<u64, String>
+where F: FnMut(&ReplayedBlock) {
+    let tip = find_snapshot_tip(config)?;
+    check_replay_range(from, to, tip.block_height)?;
+
+    let working_dir = PathBuf::from(&config.node.working_dir);
+    let scratch_dir = working_dir.join("replay-scratch");
+    if scratch_dir.exists() {
+        fs::remove_dir_all(&scratch_dir)
+            .map_err(|e| format!("Failed to clear {}: {:?}", scratch_dir.display(), &e))?;
+    }
+
+    let replayed = copy_chainstate(&working_dir, &scratch_dir)
+        .and_then(|_| replay_scratch_blocks(config, &scratch_dir.join("chainstate"), &tip.index_block_hash, from, to, &mut on_block));
+
+    let _ = fs::remove_dir_all(&scratch_dir);
+    replayed
+}
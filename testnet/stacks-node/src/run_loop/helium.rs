@@ -1,6 +1,12 @@
+use std::fs;
+use std::thread;
+
 use crate::{Config, Node, BurnchainController, MocknetController, BitcoinRegtestController, ChainTip};
+use crate::debugger::{bind_debugger, serve_debugger};
 
 use stacks::chainstate::stacks::db::ClarityTx;
+use stacks::vm::debug::{self, DebugSession};
+use stacks::vm::coverage::{self, CoverageCollector};
 
 use super::RunLoopCallbacks;
 
@@ -39,6 +45,25 @@ impl RunLoop {
     /// the nodes, taking turns on tenures.  
     pub fn start(&mut self, expected_num_rounds: u64) {
 
+        // Blocks are mined and processed on this thread, so this is where the debugger attaches.
+        if let Some(ref debug_bind) = self.config.node.debug_bind {
+            let listener = bind_debugger(debug_bind).unwrap_or_else(|e| panic!("{}", e));
+            let session = DebugSession::new();
+            let server_session = session.clone();
+            thread::spawn(move || serve_debugger(listener, server_session));
+
+            info!("Clarity debugger listening on {}", debug_bind);
+            debug::attach(session);
+        }
+
+        // Likewise for the coverage collector.  Contracts deployed from here on are covered.
+        let coverage_collector = self.config.node.coverage_output.as_ref().map(|coverage_output| {
+            info!("Writing Clarity coverage reports to {}", coverage_output);
+            let collector = CoverageCollector::new();
+            coverage::attach(collector.clone());
+            collector
+        });
+
         // Initialize and start the burnchain.
         let mut burnchain: Box<dyn BurnchainController> = match &self.config.burnchain.mode[..] {
             "helium" => {
@@ -122,12 +147,16 @@ impl RunLoop {
             &burnchain_tip, 
             &chain_tip, 
             &mut self.node.chain_state);
+        self.write_coverage_report(&coverage_collector);
 
         // If the node we're looping on won the sortition, initialize and configure the next tenure
         if won_sortition {
             leader_tenure = self.node.initiate_new_tenure();
         }
 
+        // Artifacts whose block-commit was sent, but hasn't been through a sortition yet
+        let mut pending_artifacts = None;
+
         // Start the runloop
         round_index = 1;
         loop {
@@ -156,6 +185,10 @@ impl RunLoop {
                 None => {}
             }
 
+            if artifacts_from_tenure.is_some() {
+                pending_artifacts = artifacts_from_tenure;
+            }
+
             burnchain_tip = burnchain.sync();
             self.callbacks.invoke_new_burn_chain_state(round_index, &burnchain_tip, &chain_tip);
     
@@ -167,16 +200,25 @@ impl RunLoop {
                 (None, _) => panic!("Node should have a sortitioned block")
             };
 
-            match artifacts_from_tenure {
+            if !burnchain_tip.block_snapshot.sortition {
+                // Empty burnchain block: our block-commit hasn't been mined yet, so hold on to
+                // the artifacts (and don't start a new tenure) until a sortition happens.
+                info!("Burnchain block #{} had no sortition; waiting for the next one", burnchain_tip.block_snapshot.block_height);
+                continue;
+            }
+
+            match pending_artifacts.take() {
                 // Pass if we're missing the artifacts from the current tenure.
                 None => continue,
-                Some(ref artifacts) => {
+                Some(artifacts) => {
                     // Have the node process its tenure.
                     // We should have some additional checks here, and ensure that the previous artifacts are legit.
+                    // The parent's burn block is the one the tenure built on -- with empty burn blocks in
+                    // between, that's not the sortition's parent burn block.
                     chain_tip = self.node.process_tenure(
                         &artifacts.anchored_block, 
                         &last_sortitioned_block.block_snapshot.burn_header_hash, 
-                        &last_sortitioned_block.block_snapshot.parent_burn_header_hash,             
+                        &artifacts.parent_block.block_snapshot.burn_header_hash,             
                         artifacts.microblocks.clone(),
                         burnchain.burndb_mut());
 
@@ -185,6 +227,7 @@ impl RunLoop {
                             &burnchain_tip, 
                             &chain_tip, 
                             &mut self.node.chain_state);
+                        self.write_coverage_report(&coverage_collector);
                 },
             };
             
@@ -196,4 +239,13 @@ impl RunLoop {
             round_index += 1;
         }
     }
+
+    /// Rewrite the coverage report, if the node is collecting coverage
+    fn write_coverage_report(&self, coverage_collector: &Option<CoverageCollector>) {
+        if let (Some(collector), Some(coverage_output)) = (coverage_collector, &self.config.node.coverage_output) {
+            if let Err(e) = fs::write(coverage_output, collector.lcov_report()) {
+                warn!("Failed to write the Clarity coverage report to {}: {:?}", coverage_output, &e);
+            }
+        }
+    }
 }
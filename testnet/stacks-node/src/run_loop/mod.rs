@@ -1,6 +1,12 @@
 pub mod helium;
 pub mod neon;
 
+use std::any::Any;
+use std::cmp;
+use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
 use crate::{BurnchainController, BurnchainTip, ChainTip, Tenure};
 
 use stacks::chainstate::stacks::{TransactionAuth, TransactionSpendingCondition, TransactionPayload};
@@ -26,6 +32,36 @@ macro_rules! info_green {
     })
 }
 
+/// How often a sleeping thread checks whether it's been asked to stop
+const STOP_POLL_MS: u64 = 500;
+
+/// Sleep for `duration`, or until `should_keep_running` is cleared.  Returns whether the caller
+/// should keep running.
+pub fn sleep_while_running(duration: Duration, should_keep_running: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    while should_keep_running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(cmp::min(deadline - now, Duration::from_millis(STOP_POLL_MS)));
+    }
+    false
+}
+
+/// The message a thread panicked with, for reporting it once the thread's been joined
+pub fn panic_message(panic: &Box<dyn Any + Send>) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg.to_string()
+    }
+    else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg.clone()
+    }
+    else {
+        "unknown panic".to_string()
+    }
+}
+
 pub struct RunLoopCallbacks {
     on_burn_chain_initialized: Option<fn(&mut Box<dyn BurnchainController>)>,
     on_new_burn_chain_state: Option<fn(u64, &BurnchainTip, &ChainTip)>,
@@ -1,15 +1,16 @@
-use std::process;
 use std::thread;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::{Config, NeonGenesisNode, BurnchainController, 
-            BitcoinRegtestController, Keychain};
+            BitcoinRegtestController, Keychain, EventDispatcher};
 use stacks::chainstate::burn::db::burndb::BurnDB;
 use stacks::burnchains::bitcoin::address::BitcoinAddress;
 use stacks::burnchains::Address;
 use stacks::burnchains::bitcoin::{BitcoinNetworkType, 
                                   address::{BitcoinAddressType}};
 
-use super::RunLoopCallbacks;
+use super::{RunLoopCallbacks, panic_message};
 
 use crate::monitoring::start_serving_monitoring_metrics;
 
@@ -18,6 +19,9 @@ use crate::monitoring::start_serving_monitoring_metrics;
 pub struct RunLoop {
     config: Config,
     pub callbacks: RunLoopCallbacks,
+    /// Dispatcher handed to the node; observers from the config are added to it at startup.
+    pub event_dispatcher: EventDispatcher,
+    should_keep_running: Arc<AtomicBool>,
     blocks_processed: std::sync::Arc<std::sync::atomic::AtomicU64>,
 }
 
@@ -25,6 +29,9 @@ pub struct RunLoop {
 pub struct RunLoop {
     config: Config,
     pub callbacks: RunLoopCallbacks,
+    /// Dispatcher handed to the node; observers from the config are added to it at startup.
+    pub event_dispatcher: EventDispatcher,
+    should_keep_running: Arc<AtomicBool>,
 }
 
 impl RunLoop {
@@ -35,6 +42,8 @@ impl RunLoop {
         Self {
             config,
             callbacks: RunLoopCallbacks::new(),
+            event_dispatcher: EventDispatcher::new(),
+            should_keep_running: Arc::new(AtomicBool::new(true)),
         }
     }
 
@@ -43,10 +52,18 @@ impl RunLoop {
         Self {
             config,
             callbacks: RunLoopCallbacks::new(),
+            event_dispatcher: EventDispatcher::new(),
+            should_keep_running: Arc::new(AtomicBool::new(true)),
             blocks_processed: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
         }
     }
 
+    /// Get a handle that stops the run loop when set to false.  Once it's cleared, the run loop
+    /// stops waiting on the burnchain, shuts down the node's threads, and returns.
+    pub fn get_termination_switch(&self) -> Arc<AtomicBool> {
+        self.should_keep_running.clone()
+    }
+
     #[cfg(test)]
     pub fn get_blocks_processed_arc(&self) -> std::sync::Arc<std::sync::atomic::AtomicU64> {
         self.blocks_processed.clone()
@@ -67,14 +84,17 @@ impl RunLoop {
 
     /// Starts the testnet runloop.
     /// 
-    /// This function will block by looping infinitely.
+    /// This function will block by looping until the termination switch is flipped.
     /// It will start the burnchain (separate thread), set-up a channel in
     /// charge of coordinating the new blocks coming from the burnchain and 
     /// the nodes, taking turns on tenures.  
-    pub fn start(&mut self, _expected_num_rounds: u64) {
+    ///
+    /// Returns an error if one of the node's threads failed.
+    pub fn start(&mut self, _expected_num_rounds: u64) -> Result<(), String> {
 
         // Initialize and start the burnchain.
         let mut burnchain = BitcoinRegtestController::new(self.config.clone());
+        burnchain.set_termination_switch(self.should_keep_running.clone());
 
         let is_miner = if self.config.node.miner {
             let keychain = Keychain::default(self.config.node.seed.clone());
@@ -96,15 +116,24 @@ impl RunLoop {
             }
         } else {
             info!("Follower node: starting up");
+            if let Some(ref watch_keychain) = self.config.node.watch_keychain {
+                info!("Follower node: watching address {}", watch_keychain.get_address());
+            }
             false
         };
 
-        let mut burnchain_tip = burnchain.start();
+        let mut burnchain_tip = match burnchain.start_unless_stopped() {
+            Some(burnchain_tip) => burnchain_tip,
+            None => {
+                info!("Run loop stopped before the burnchain was synced");
+                return Ok(());
+            }
+        };
 
         let mut block_height = burnchain_tip.block_snapshot.block_height;
 
         // setup genesis
-        let node = NeonGenesisNode::new(self.config.clone(), |_| {});
+        let node = NeonGenesisNode::new_with_event_dispatcher(self.config.clone(), self.event_dispatcher.clone(), |_| {});
         let mut node = if is_miner {
             node.into_initialized_leader_node(burnchain_tip.clone(), self.get_blocks_processed_arc())
         } else {
@@ -118,15 +147,31 @@ impl RunLoop {
         info!("Begin run loop");
         self.bump_blocks_processed();
         
-        let prometheus_bind = self.config.node.prometheus_bind.clone();
-        if let Some(prometheus_bind) = prometheus_bind {
-            thread::spawn(move || {
-                start_serving_monitoring_metrics(prometheus_bind);
-            });
+        if self.config.node.debug_bind.is_some() {
+            warn!("The Clarity debugger is only available in mocknet and helium modes; ignoring `node.debug_bind`");
+        }
+
+        if self.config.node.coverage_output.is_some() {
+            warn!("Clarity coverage reports are only available in mocknet and helium modes; ignoring `node.coverage_output`");
         }
 
-        loop {
-            burnchain_tip = burnchain.sync();
+        let prometheus_thread = match self.config.node.prometheus_bind.clone() {
+            Some(prometheus_bind) => {
+                let should_keep_running = self.should_keep_running.clone();
+                let handle = thread::Builder::new().name("prometheus".to_string()).spawn(move || {
+                    start_serving_monitoring_metrics(prometheus_bind, should_keep_running);
+                }).map_err(|e| format!("Failed to spawn prometheus thread: {}", e))?;
+                Some(handle)
+            },
+            None => None
+        };
+
+        let mut result = Ok(());
+        'run: while self.should_keep_running.load(Ordering::SeqCst) {
+            burnchain_tip = match burnchain.sync_unless_stopped() {
+                Some(burnchain_tip) => burnchain_tip,
+                None => break
+            };
 
             let next_height = burnchain_tip.block_snapshot.block_height;
             if next_height <= block_height {
@@ -153,20 +198,39 @@ impl RunLoop {
                 //
                 // _this will block if the relayer's buffer is full_
                 if !node.relayer_sortition_notify() {
-                    // relayer hung up, exit.
+                    // relayer hung up
                     error!("Block relayer and miner hung up, exiting.");
-                    process::exit(1);
+                    result = Err("Block relayer and miner hung up".to_string());
+                    break 'run;
                 }
             }
             // now, let's tell the miner to try and mine.
             if !node.relayer_issue_tenure() {
-                // relayer hung up, exit.
+                // relayer hung up
                 error!("Block relayer and miner hung up, exiting.");
-                process::exit(1);
+                result = Err("Block relayer and miner hung up".to_string());
+                break;
             }
 
             block_height = next_height;
 
         }
+
+        info!("Run loop stopped");
+        self.should_keep_running.store(false, Ordering::SeqCst);
+
+        // a thread's panic explains a hang-up better than the hang-up does
+        if let Err(e) = node.shutdown() {
+            error!("Node failed: {}", &e);
+            result = Err(e);
+        }
+        if let Some(handle) = prometheus_thread {
+            if let Err(panic) = handle.join() {
+                let e = format!("prometheus thread panicked: {}", panic_message(&panic));
+                error!("{}", &e);
+                result = Err(e);
+            }
+        }
+        result
     }
 }
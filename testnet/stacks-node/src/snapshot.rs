@@ -0,0 +1,385 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+
+use stacks::chainstate::burn::db::burndb::BurnDB;
+use stacks::chainstate::stacks::{StacksBlockHeader, StacksBlockId};
+use stacks::util::db::DBConn;
+use stacks::util::hash::to_hex;
+use stacks::vm::database::MarfedKV;
+
+use crate::clarity_repl::find_vm_dir;
+use crate::Config;
+
+/// First bytes of a snapshot archive, once decompressed
+const SNAPSHOT_MAGIC: &[u8; 8] = b"STXSNAP\x01";
+
+/// Version of the manifest and archive layout
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Largest manifest we'll read from an archive
+const MAX_MANIFEST_LEN: u32 = 64 * 1024 * 1024;
+
+/// The directories of a node's working directory that a snapshot holds
+const SNAPSHOT_DIRS: &[&str] = &["burnchain", "chainstate"];
+
+/// A file in a snapshot, relative to the node's working directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// What a snapshot holds, and the Stacks block it was taken at.  The files' hashes are part of
+/// the manifest, so the manifest's digest covers the whole snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub server_version: String,
+    /// the `burnchain.chain` the node follows
+    pub burnchain: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+    pub burn_header_hash: String,
+    pub burn_block_height: u64,
+    /// the root hash of the Clarity state as of the block
+    pub state_index_root: String,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// The Stacks block a snapshot is taken at
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotTip {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_header_hash: String,
+    pub burn_block_height: u64,
+    pub state_index_root: String,
+}
+
+/// Passes writes through, hashing and counting what's written
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> HashingWriter<W> {
+        HashingWriter { inner, hasher: Sha256::new(), len: 0 }
+    }
+
+    fn finish(self) -> (W, u64, String) {
+        (self.inner, self.len, to_hex(self.hasher.result().as_slice()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.input(&buf[..written]);
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(Sha256::digest(bytes).as_slice())
+}
+
+/// Should this file go in a snapshot?  The mempool is this node's own business, and sqlite
+/// shared-memory files are rebuilt when a database is opened.
+fn is_snapshot_file(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    !file_name.starts_with("mempool.db") && !file_name.ends_with("-shm")
+}
+
+fn list_files(working_dir: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {:?}", dir.display(), &e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read {}: {:?}", dir.display(), &e))?.path();
+        if path.is_dir() {
+            list_files(working_dir, &path, files)?;
+        }
+        else {
+            let relative = path.strip_prefix(working_dir)
+                .map_err(|_| format!("{} is not in {}", path.display(), working_dir.display()))?;
+            let relative = relative.components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            if is_snapshot_file(&relative) {
+                files.push(relative);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// List the files of a working directory that go in a snapshot, relative to it, in order
+pub fn list_snapshot_files(working_dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = vec![];
+    for dir in SNAPSHOT_DIRS.iter() {
+        let dir = working_dir.join(dir);
+        if !dir.is_dir() {
+            return Err(format!("{} not found; is this a node's working directory?", dir.display()));
+        }
+        list_files(working_dir, &dir, &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Is this manifest path one that a snapshot could hold, and safe to restore?
+fn is_restorable_path(path: &str) -> bool {
+    let path = Path::new(path);
+    let in_snapshot_dir = path.components().next()
+        .map(|c| SNAPSHOT_DIRS.iter().any(|dir| c == Component::Normal(OsStr::new(dir))))
+        .unwrap_or(false);
+    in_snapshot_dir && path.components().all(|c| match c { Component::Normal(_) => true, _ => false })
+}
+
+/// Find the Stacks block a node's chainstate is at: the canonical Stacks chain tip, as recorded
+/// in its sortition DB
+pub fn find_snapshot_tip(config: &Config) -> Result<SnapshotTip, String> {
+    let burndb = BurnDB::open(&config.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open the sortition DB: {:?}", &e))?;
+    let burn_tip = BurnDB::get_canonical_burn_chain_tip(burndb.conn())
+        .map_err(|e| format!("Failed to find the burnchain tip: {:?}", &e))?;
+    if burn_tip.canonical_stacks_tip_height == 0 {
+        return Err("The node hasn't processed any Stacks blocks yet".to_string());
+    }
+
+    let index_block_hash = StacksBlockHeader::make_index_block_hash(&burn_tip.canonical_stacks_tip_burn_hash, &burn_tip.canonical_stacks_tip_hash);
+    let state_index_root = read_state_index_root(Path::new(&config.get_chainstate_path()), &index_block_hash)?;
+
+    Ok(SnapshotTip {
+        index_block_hash,
+        block_height: burn_tip.canonical_stacks_tip_height,
+        burn_header_hash: burn_tip.canonical_stacks_tip_burn_hash.to_hex(),
+        burn_block_height: burn_tip.block_height,
+        state_index_root,
+    })
+}
+
+/// Read a block's state root from the headers DB in a chainstate directory
+fn read_state_index_root(chainstate_dir: &Path, index_block_hash: &StacksBlockId) -> Result<String, String> {
+    let vm_dir = find_vm_dir(&chainstate_dir.to_string_lossy())?;
+    let headers_path = vm_dir.join("headers.db");
+    let headers_db = DBConn::open(&headers_path)
+        .map_err(|e| format!("Failed to open {}: {:?}", headers_path.display(), &e))?;
+    headers_db.query_row("SELECT state_index_root FROM block_headers WHERE index_block_hash = ?1",
+                         &[&index_block_hash.to_hex()], |row| row.get(0))
+        .map_err(|e| format!("Failed to find block {} in {}: {:?}", index_block_hash, headers_path.display(), &e))
+}
+
+/// Check that a restored chainstate is at the snapshot's block: its headers DB has the block,
+/// with the manifest's state root, and its Clarity state has that root hash at the block.
+pub fn verify_snapshot_tip(chainstate_dir: &Path, manifest: &SnapshotManifest) -> Result<(), String> {
+    let index_block_hash = StacksBlockId::from_hex(&manifest.index_block_hash)
+        .map_err(|e| format!("Invalid index block hash {} in manifest: {:?}", &manifest.index_block_hash, &e))?;
+
+    let state_index_root = read_state_index_root(chainstate_dir, &index_block_hash)?;
+    if state_index_root != manifest.state_index_root {
+        return Err(format!("Block {} has state root {}, but the manifest says {}", &index_block_hash, &state_index_root, &manifest.state_index_root));
+    }
+
+    let clarity_dir = find_vm_dir(&chainstate_dir.to_string_lossy())?.join("clarity");
+    let mut marf = MarfedKV::open(&clarity_dir.to_string_lossy(), None)
+        .map_err(|e| format!("Failed to open the Clarity state in {}: {:?}", clarity_dir.display(), &e))?;
+    let root_hash = marf.get_marf().get_root_hash_at(&index_block_hash)
+        .map_err(|e| format!("Failed to read the Clarity state root at {}: {:?}", &index_block_hash, &e))?;
+    if root_hash.to_hex() != manifest.state_index_root {
+        return Err(format!("The Clarity state has root {} at block {}, but the manifest says {}", root_hash.to_hex(), &index_block_hash, &manifest.state_index_root));
+    }
+    Ok(())
+}
+
+/// Write the given files of a working directory to a snapshot archive at the given tip.  Each
+/// file is hashed as it's copied, and the archive is only kept if no file changed in between.
+/// Returns the manifest and its digest.
+pub fn write_snapshot(working_dir: &Path, files: &[String], tip: &SnapshotTip, burnchain: &str, output: &Path) -> Result<(SnapshotManifest, String), String> {
+    let mut snapshot_files = vec![];
+    for path in files.iter() {
+        let mut fd = fs::File::open(working_dir.join(path))
+            .map_err(|e| format!("Failed to open {}: {:?}", path, &e))?;
+        let mut hasher = HashingWriter::new(io::sink());
+        io::copy(&mut fd, &mut hasher)
+            .map_err(|e| format!("Failed to read {}: {:?}", path, &e))?;
+        let (_, size, sha256) = hasher.finish();
+        snapshot_files.push(SnapshotFile { path: path.clone(), size, sha256 });
+    }
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_VERSION,
+        server_version: stacks::version_string(
+            option_env!("CARGO_PKG_NAME").unwrap_or("stacks-node"),
+            option_env!("CARGO_PKG_VERSION").unwrap_or("0.0.0.0")),
+        burnchain: burnchain.to_string(),
+        index_block_hash: tip.index_block_hash.to_hex(),
+        block_height: tip.block_height,
+        burn_header_hash: tip.burn_header_hash.clone(),
+        burn_block_height: tip.burn_block_height,
+        state_index_root: tip.state_index_root.clone(),
+        files: snapshot_files,
+    };
+    let manifest_bytes = serde_json::to_vec(&manifest)
+        .map_err(|e| format!("Failed to serialize the manifest: {:?}", &e))?;
+    let digest = sha256_hex(&manifest_bytes);
+
+    let tmp_output = output.with_extension("partial");
+    let written = write_archive(working_dir, &manifest, &manifest_bytes, &tmp_output)
+        .and_then(|_| fs::rename(&tmp_output, output)
+                  .map_err(|e| format!("Failed to move {} to {}: {:?}", tmp_output.display(), output.display(), &e)));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_output);
+        return Err(e);
+    }
+    Ok((manifest, digest))
+}
+
+fn write_archive(working_dir: &Path, manifest: &SnapshotManifest, manifest_bytes: &[u8], output: &Path) -> Result<(), String> {
+    let fd = fs::File::create(output)
+        .map_err(|e| format!("Failed to create {}: {:?}", output.display(), &e))?;
+    let mut encoder = GzEncoder::new(io::BufWriter::new(fd), Compression::default());
+
+    let write_err = |e: io::Error| format!("Failed to write {}: {:?}", output.display(), &e);
+    encoder.write_all(SNAPSHOT_MAGIC).map_err(write_err)?;
+    encoder.write_all(&(manifest_bytes.len() as u32).to_be_bytes()).map_err(write_err)?;
+    encoder.write_all(manifest_bytes).map_err(write_err)?;
+
+    for file in manifest.files.iter() {
+        let fd = fs::File::open(working_dir.join(&file.path))
+            .map_err(|e| format!("Failed to open {}: {:?}", &file.path, &e))?;
+        let mut hasher = HashingWriter::new(&mut encoder);
+        io::copy(&mut fd.take(file.size + 1), &mut hasher).map_err(write_err)?;
+        let (_, size, sha256) = hasher.finish();
+        if size != file.size || sha256 != file.sha256 {
+            return Err(format!("{} changed while the snapshot was being written; stop the node first", &file.path));
+        }
+    }
+
+    encoder.finish()
+        .and_then(|mut fd| fd.flush())
+        .map_err(write_err)
+}
+
+/// Read a snapshot archive's manifest, and unpack its files into the given directory, checking
+/// each file's size and hash.  If a digest is given, the manifest must have it.  Returns the
+/// manifest and its digest.
+pub fn read_snapshot(input: &Path, dest_dir: &Path, expected_digest: Option<&str>) -> Result<(SnapshotManifest, String), String> {
+    let fd = fs::File::open(input)
+        .map_err(|e| format!("Failed to open {}: {:?}", input.display(), &e))?;
+    let mut decoder = GzDecoder::new(io::BufReader::new(fd));
+    let read_err = |e: io::Error| format!("Failed to read {}: {:?}", input.display(), &e);
+
+    let mut magic = [0u8; 8];
+    decoder.read_exact(&mut magic).map_err(read_err)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(format!("{} is not a snapshot archive", input.display()));
+    }
+
+    let mut len_bytes = [0u8; 4];
+    decoder.read_exact(&mut len_bytes).map_err(read_err)?;
+    let manifest_len = u32::from_be_bytes(len_bytes);
+    if manifest_len > MAX_MANIFEST_LEN {
+        return Err(format!("Snapshot manifest is too big ({} bytes)", manifest_len));
+    }
+    let mut manifest_bytes = vec![0u8; manifest_len as usize];
+    decoder.read_exact(&mut manifest_bytes).map_err(read_err)?;
+
+    let digest = sha256_hex(&manifest_bytes);
+    if let Some(expected_digest) = expected_digest {
+        if digest != expected_digest.trim_start_matches("0x") {
+            return Err(format!("Snapshot digest is {}, not {}", &digest, expected_digest));
+        }
+    }
+
+    let manifest : SnapshotManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|e| format!("Invalid snapshot manifest: {:?}", &e))?;
+    if manifest.version != SNAPSHOT_VERSION {
+        return Err(format!("Unsupported snapshot version {}", manifest.version));
+    }
+
+    for file in manifest.files.iter() {
+        if !is_restorable_path(&file.path) {
+            return Err(format!("Snapshot holds an unexpected file {}", &file.path));
+        }
+        let path = dest_dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {:?}", parent.display(), &e))?;
+        }
+        let fd = fs::File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {:?}", path.display(), &e))?;
+        let mut hasher = HashingWriter::new(io::BufWriter::new(fd));
+        io::copy(&mut (&mut decoder).take(file.size), &mut hasher).map_err(read_err)?;
+        let (mut fd, size, sha256) = hasher.finish();
+        fd.flush().map_err(|e| format!("Failed to write {}: {:?}", path.display(), &e))?;
+        if size != file.size {
+            return Err(format!("Snapshot archive ends in the middle of {}", &file.path));
+        }
+        if sha256 != file.sha256 {
+            return Err(format!("{} has hash {}, but the manifest says {}", &file.path, &sha256, &file.sha256));
+        }
+    }
+
+    Ok((manifest, digest))
+}
+
+/// Package a stopped node's chainstate and sortition DB, as of its canonical Stacks chain tip,
+/// into a snapshot archive.  Returns the manifest and its digest.
+pub fn create_snapshot(config: &Config, output: &str) -> Result<(SnapshotManifest, String), String> {
+    let working_dir = PathBuf::from(&config.node.working_dir);
+    let tip = find_snapshot_tip(config)?;
+    let files = list_snapshot_files(&working_dir)?;
+    write_snapshot(&working_dir, &files, &tip, &config.burnchain.chain, Path::new(output))
+}
+
+/// Restore a snapshot archive into a node's working directory, which must not have a
+/// chainstate or sortition DB yet.  Nothing is moved into place until every file checks out
+/// and the restored chainstate is at the snapshot's block.  Returns the manifest and its digest.
+pub fn restore_snapshot(config: &Config, input: &str, expected_digest: Option<&str>) -> Result<(SnapshotManifest, String), String> {
+    let working_dir = PathBuf::from(&config.node.working_dir);
+    for dir in SNAPSHOT_DIRS.iter() {
+        if working_dir.join(dir).exists() {
+            return Err(format!("{} already exists; restore into an empty working directory", working_dir.join(dir).display()));
+        }
+    }
+
+    let staging_dir = working_dir.join("snapshot-restore");
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)
+            .map_err(|e| format!("Failed to clear {}: {:?}", staging_dir.display(), &e))?;
+    }
+    fs::create_dir_all(&staging_dir)
+        .map_err(|e| format!("Failed to create {}: {:?}", staging_dir.display(), &e))?;
+
+    let restored = read_snapshot(Path::new(input), &staging_dir, expected_digest)
+        .and_then(|(manifest, digest)| {
+            if manifest.burnchain != config.burnchain.chain {
+                return Err(format!("Snapshot is of a node following {}, not {}", &manifest.burnchain, &config.burnchain.chain));
+            }
+            verify_snapshot_tip(&staging_dir.join("chainstate"), &manifest)?;
+            for dir in SNAPSHOT_DIRS.iter() {
+                fs::rename(staging_dir.join(dir), working_dir.join(dir))
+                    .map_err(|e| format!("Failed to move {} into place: {:?}", dir, &e))?;
+            }
+            Ok((manifest, digest))
+        });
+
+    let _ = fs::remove_dir_all(&staging_dir);
+    restored
+}
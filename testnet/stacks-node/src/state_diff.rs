@@ -0,0 +1,59 @@
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::db::state_diff::{StateChange, StateKey};
+
+use crate::clarity_repl::find_vm_dir;
+use crate::node::TESTNET_CHAIN_ID;
+use crate::snapshot::find_snapshot_tip;
+use crate::Config;
+
+/// Check that `from` through `to` are block heights to diff the state between, in a chain whose
+/// tip is at `tip_height`
+pub fn check_diff_range(from: u64, to: u64, tip_height: u64) -> Result<(), String> {
+    if from > to {
+        return Err(format!("--from ({}) is above --to ({})", from, to));
+    }
+    if to > tip_height {
+        return Err(format!("--to ({}) is above the canonical chain tip, at height {}", to, tip_height));
+    }
+    Ok(())
+}
+
+/// Describe what a changed key holds, for printing
+pub fn describe_state_key(state_key: &StateKey) -> String {
+    match state_key {
+        StateKey::Account(principal) => format!("account {}", principal),
+        StateKey::Contract(contract) => format!("contract {}", contract),
+        StateKey::DataMapEntry(contract, map_name) => format!("map {} {}", contract, map_name),
+        StateKey::ContractData(contract, name) => format!("data {} {}", contract, name),
+        StateKey::Other => "other".to_string(),
+    }
+}
+
+/// Find the Clarity state that differs between the canonical chain's blocks at heights `from`
+/// and `to` (see StacksChainState::get_state_diff()).  The chainstate is only read.
+pub fn diff_state(config: &Config, from: u64, to: u64) -> Result<Vec<StateChange>, String> {
+    let chainstate_path = config.get_chainstate_path();
+    find_vm_dir(&chainstate_path)?;
+
+    let tip = find_snapshot_tip(config)?;
+    check_diff_range(from, to, tip.block_height)?;
+
+    let mut chainstate = StacksChainState::open_with_block_limit(
+        false, TESTNET_CHAIN_ID, &chainstate_path, config.block_limit.clone())
+        .map_err(|e| format!("Failed to open the chainstate at {}: {:?}", &chainstate_path, &e))?;
+
+    let (from_block, to_block) = {
+        let mut headers_tx = chainstate.headers_tx_begin()
+            .map_err(|e| format!("Failed to read the chainstate: {:?}", &e))?;
+        let mut find_block = |height: u64| {
+            StacksChainState::get_index_tip_ancestor(&mut headers_tx, &tip.index_block_hash, height)
+                .map_err(|e| format!("Failed to find the block at height {}: {:?}", height, &e))?
+                .map(|header_info| header_info.index_block_hash())
+                .ok_or_else(|| format!("No block at height {}", height))
+        };
+        (find_block(from)?, find_block(to)?)
+    };
+
+    chainstate.get_state_diff(&from_block, &to_block)
+        .map_err(|e| format!("Failed to diff the state between heights {} and {}: {:?}", from, to, &e))
+}
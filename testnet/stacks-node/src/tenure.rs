@@ -75,11 +75,12 @@ impl <'a> Tenure {
         } 
 
 
-        let mut chain_state = StacksChainState::open_with_block_limit(
+        let mut chain_state = StacksChainState::open_with_pragmas(
             false, 
             TESTNET_CHAIN_ID, 
             &self.config.get_chainstate_path(),
-            self.config.block_limit.clone()).unwrap();
+            self.config.block_limit.clone(),
+            &self.config.node.sqlite_pragmas).unwrap();
 
         let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block(
             &mut chain_state, &mut self.mem_pool, &self.parent_block.metadata,
@@ -0,0 +1,83 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use stacks::util::db::DBConn;
+
+use crate::backup::*;
+
+fn make_db(path: &Path) -> DBConn {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let conn = DBConn::open(path).unwrap();
+    conn.execute_batch("PRAGMA journal_mode = WAL; CREATE TABLE t(x INT); INSERT INTO t VALUES (1);").unwrap();
+    conn
+}
+
+#[test]
+fn test_backup() {
+    let test_dir = std::env::temp_dir().join("stacks-node-backup");
+    if test_dir.exists() {
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+    let working_dir = test_dir.join("node");
+    let backup_dir = test_dir.join("backups");
+
+    let burn_db = make_db(&working_dir.join("burnchain/db/regtest/burn.db/data.db"));
+    make_db(&working_dir.join("burnchain/db/regtest/burn.db/marf"));
+    make_db(&working_dir.join("chainstate/chain-00/vm/headers.db"));
+    make_db(&working_dir.join("chainstate/chain-00/vm/clarity/marf"));
+    make_db(&working_dir.join("chainstate/chain-00/vm/clarity/data.sqlite"));
+    make_db(&working_dir.join("chainstate/chain-00/blocks/staging.db"));
+    make_db(&working_dir.join("chainstate/chain-00/mempool.db"));
+    let block_path = "chainstate/chain-00/blocks/00ff/".to_string() + &"ab".repeat(32);
+    fs::create_dir_all(working_dir.join("chainstate/chain-00/blocks/00ff")).unwrap();
+    fs::write(working_dir.join(&block_path), b"a block").unwrap();
+
+    // databases go before the databases and block files that refer to them
+    let files = list_backup_files(&working_dir).unwrap();
+    assert_eq!(files, vec![
+        "burnchain/db/regtest/burn.db/data.db".to_string(),
+        "burnchain/db/regtest/burn.db/marf".to_string(),
+        "chainstate/chain-00/vm/headers.db".to_string(),
+        "chainstate/chain-00/vm/clarity/marf".to_string(),
+        "chainstate/chain-00/vm/clarity/data.sqlite".to_string(),
+        "chainstate/chain-00/blocks/staging.db".to_string(),
+        block_path.clone(),
+    ]);
+
+    // the node keeps writing while it's backed up; what it committed is in the backup, even if
+    // it's still in the write-ahead log
+    burn_db.execute_batch("INSERT INTO t VALUES (2);").unwrap();
+    let first = create_backup(&working_dir, &backup_dir, 1000).unwrap();
+    assert_eq!(first.path, backup_dir.join("backup-1000"));
+    assert_eq!(first.num_databases, 6);
+    assert_eq!(first.num_copied, 1);
+    assert_eq!(first.num_linked, 0);
+    let backed_up = DBConn::open(first.path.join("burnchain/db/regtest/burn.db/data.db")).unwrap();
+    let no_params : [&str; 0] = [];
+    let count : i64 = backed_up.query_row("SELECT COUNT(*) FROM t", no_params.iter(), |row| row.get(0)).unwrap();
+    assert_eq!(count, 2);
+    assert!(!first.path.join("chainstate/chain-00/mempool.db").exists());
+
+    // an unchanged block file is linked from the previous backup, not copied from the node
+    let second = create_backup(&working_dir, &backup_dir, 2000).unwrap();
+    assert_eq!(second.num_copied, 0);
+    assert_eq!(second.num_linked, 1);
+    assert_eq!(fs::metadata(first.path.join(&block_path)).unwrap().ino(),
+               fs::metadata(second.path.join(&block_path)).unwrap().ino());
+    assert!(create_backup(&working_dir, &backup_dir, 2000).is_err());
+
+    // pruning truncates the node's block file; the backups keep their copies
+    fs::write(working_dir.join(&block_path), b"").unwrap();
+    let third = create_backup(&working_dir, &backup_dir, 3000).unwrap();
+    assert_eq!(third.num_copied, 1);
+    assert_eq!(third.num_linked, 0);
+    assert_eq!(fs::read(second.path.join(&block_path)).unwrap(), b"a block".to_vec());
+
+    fs::create_dir_all(backup_dir.join("backup-4000.partial")).unwrap();
+    assert_eq!(list_backups(&backup_dir).unwrap(), vec![first.path.clone(), second.path.clone(), third.path.clone()]);
+    assert_eq!(prune_backups(&backup_dir, 2).unwrap(), vec![first.path.clone()]);
+    assert_eq!(list_backups(&backup_dir).unwrap(), vec![second.path.clone(), third.path.clone()]);
+    assert!(!backup_dir.join("backup-4000.partial").exists());
+    assert_eq!(fs::read(second.path.join(&block_path)).unwrap(), b"a block".to_vec());
+}
@@ -0,0 +1,66 @@
+use std::fs;
+
+use stacks::chainstate::stacks::db::compression::{is_compressed_chunk, DEFAULT_BLOCK_COMPRESSION_LEVEL};
+
+use crate::block_compression::*;
+use crate::{Config, ConfigFile};
+use crate::config::NodeConfigFile;
+
+#[test]
+fn block_compression_config_parses() {
+    assert_eq!(Config::from_config_file(ConfigFile::default()).node.block_compression, None);
+
+    let config_file = |compress_blocks, block_compression_level| ConfigFile {
+        node: Some(NodeConfigFile {
+            compress_blocks,
+            block_compression_level,
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    assert_eq!(Config::from_config_file(config_file(Some(true), None)).node.block_compression, Some(DEFAULT_BLOCK_COMPRESSION_LEVEL));
+    assert_eq!(Config::from_config_file(config_file(Some(true), Some(19))).node.block_compression, Some(19));
+    assert_eq!(Config::from_config_file(config_file(Some(false), Some(19))).node.block_compression, None);
+}
+
+#[test]
+#[should_panic]
+fn block_compression_config_rejects_bad_level() {
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            compress_blocks: Some(true),
+            block_compression_level: Some(23),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    Config::from_config_file(config_file);
+}
+
+#[test]
+fn test_migrate_blocks() {
+    let working_dir = std::env::temp_dir().join("stacks-node-block-compression");
+    if working_dir.exists() {
+        fs::remove_dir_all(&working_dir).unwrap();
+    }
+    let mut conf = Config::default();
+    conf.node.working_dir = working_dir.to_string_lossy().to_string();
+    assert!(migrate_blocks(&conf, false).is_err());
+
+    fs::create_dir_all(working_dir.join("chainstate/chain-00/vm")).unwrap();
+    fs::create_dir_all(working_dir.join("chainstate/chain-00/blocks/00ff/0102")).unwrap();
+    let block_path = working_dir.join("chainstate/chain-00/blocks/00ff/0102").join("ab".repeat(32));
+    let block : Vec<u8> = b"a block".iter().cycle().take(1000).cloned().collect();
+    fs::write(&block_path, &block).unwrap();
+    assert_eq!(find_blocks_dir(&conf.get_chainstate_path()).unwrap(), working_dir.join("chainstate/chain-00/blocks"));
+
+    let migration = migrate_blocks(&conf, false).unwrap();
+    assert_eq!(migration.num_rewritten, 1);
+    assert_eq!(migration.bytes_before, 1000);
+    assert!(migration.bytes_after < 1000);
+    assert!(is_compressed_chunk(&fs::read(&block_path).unwrap()));
+
+    let migration = migrate_blocks(&conf, true).unwrap();
+    assert_eq!(migration.num_rewritten, 1);
+    assert_eq!(fs::read(&block_path).unwrap(), block);
+}
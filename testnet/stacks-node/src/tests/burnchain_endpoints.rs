@@ -0,0 +1,197 @@
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use warp;
+use warp::Filter;
+use tokio;
+
+use stacks::util::secp256k1::Secp256k1PublicKey;
+
+use crate::{BitcoinRegtestController, Config, ConfigFile};
+use crate::burnchains::endpoints::{EndpointStatus, ENDPOINT_RETRY_SECS};
+use crate::config::{BurnchainConfigFile, BurnchainEndpoint, BurnchainEndpointFile};
+
+fn burnchain_config_file(endpoints: Vec<BurnchainEndpointFile>) -> ConfigFile {
+    ConfigFile {
+        burnchain: Some(BurnchainConfigFile {
+            peer_host: Some("127.0.0.1".to_string()),
+            peer_port: Some(18444),
+            rpc_port: Some(18443),
+            username: Some("helium".to_string()),
+            password: Some("helium".to_string()),
+            rpc_timeout: Some(10),
+            endpoints: Some(endpoints),
+            ..BurnchainConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    }
+}
+
+#[test]
+fn burnchain_endpoints_config_parses() {
+    let config = Config::from_config_file(ConfigFile::default());
+    assert_eq!(config.burnchain.get_endpoints().len(), 1);
+    assert_eq!(config.burnchain.rpc_timeout, 30);
+
+    let config = Config::from_config_file(burnchain_config_file(vec![
+        BurnchainEndpointFile {
+            peer_host: "bitcoind-2.local".to_string(),
+            ..BurnchainEndpointFile::default()
+        },
+        BurnchainEndpointFile {
+            peer_host: "bitcoind-3.local".to_string(),
+            rpc_port: Some(8332),
+            rpc_ssl: Some(true),
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            ..BurnchainEndpointFile::default()
+        },
+    ]));
+    assert_eq!(config.burnchain.rpc_timeout, 10);
+
+    let endpoints = config.burnchain.get_endpoints();
+    assert_eq!(endpoints.len(), 3);
+    assert_eq!(endpoints[0].get_rpc_url(), config.burnchain.get_rpc_url());
+
+    // what an endpoint leaves out is the same as for the first one
+    assert_eq!(endpoints[1], BurnchainEndpoint {
+        peer_host: "bitcoind-2.local".to_string(),
+        peer_port: 18444,
+        rpc_port: 18443,
+        rpc_ssl: false,
+        username: Some("helium".to_string()),
+        password: Some("helium".to_string()),
+    });
+    assert_eq!(endpoints[2].get_rpc_url(), "https://bitcoind-3.local:8332");
+    assert_eq!(endpoints[2].username, Some("user".to_string()));
+}
+
+#[test]
+#[should_panic]
+fn burnchain_endpoints_config_rejects_duplicates() {
+    Config::from_config_file(burnchain_config_file(vec![
+        BurnchainEndpointFile {
+            peer_host: "127.0.0.1".to_string(),
+            ..BurnchainEndpointFile::default()
+        },
+    ]));
+}
+
+#[test]
+#[should_panic]
+fn burnchain_endpoints_config_rejects_zero_rpc_timeout() {
+    let mut config_file = burnchain_config_file(vec![]);
+    config_file.burnchain.as_mut().unwrap().rpc_timeout = Some(0);
+    Config::from_config_file(config_file);
+}
+
+#[test]
+fn burnchain_endpoints_fail_over() {
+    let endpoint = |peer_host: &str| BurnchainEndpoint {
+        peer_host: peer_host.to_string(),
+        peer_port: 18444,
+        rpc_port: 18443,
+        rpc_ssl: false,
+        username: None,
+        password: None,
+    };
+    let endpoints = vec![endpoint("failover-a.local"), endpoint("failover-b.local"), endpoint("failover-c.local")];
+    let status = EndpointStatus::new();
+    assert_eq!(status.endpoint_order(&endpoints), vec![0, 1, 2]);
+
+    // the ones that are down are tried last, the one that failed longest ago first
+    status.mark_endpoint_down(&endpoints[1], 1000, "timed out");
+    status.mark_endpoint_down(&endpoints[0], 1010, "status(503) != success");
+    assert_eq!(status.endpoint_order(&endpoints), vec![2, 1, 0]);
+
+    // each controller keeps track of its own
+    assert_eq!(EndpointStatus::new().endpoint_order(&endpoints), vec![0, 1, 2]);
+
+    // and checked on once they've been down a while
+    assert_eq!(status.endpoints_due_for_check(&endpoints, 1010), vec![]);
+    assert_eq!(status.endpoints_due_for_check(&endpoints, 1000 + ENDPOINT_RETRY_SECS), vec![1]);
+    assert_eq!(status.endpoints_due_for_check(&endpoints, 1010 + ENDPOINT_RETRY_SECS), vec![0, 1]);
+
+    // a check that's under way isn't started again
+    assert_eq!(status.start_endpoint_checks(&endpoints, 1000 + ENDPOINT_RETRY_SECS), vec![1]);
+    assert_eq!(status.endpoints_due_for_check(&endpoints, 1010 + ENDPOINT_RETRY_SECS), vec![0]);
+
+    // one that's up again is preferred in the order it's configured
+    status.mark_endpoint_up(&endpoints[0]);
+    assert_eq!(status.endpoint_order(&endpoints), vec![0, 2, 1]);
+    status.mark_endpoint_up(&endpoints[1]);
+    assert_eq!(status.endpoint_order(&endpoints), vec![0, 1, 2]);
+
+    // every bitcoind has to import each watched address
+    status.watch_address("mzYBtAjNzuEvEMAp2ahx8oT9kWWvb5L2Rj");
+    status.mark_address_imported(&endpoints[0], "mzYBtAjNzuEvEMAp2ahx8oT9kWWvb5L2Rj");
+    assert_eq!(status.missing_addresses(&endpoints[0]), Vec::<String>::new());
+    assert_eq!(status.missing_addresses(&endpoints[1]), vec!["mzYBtAjNzuEvEMAp2ahx8oT9kWWvb5L2Rj".to_string()]);
+}
+
+/// Serve bitcoind's RPC interface on `port`, recording the methods called.  `listunspent` only
+/// finds a UTXO once `importaddress` has been called.
+fn spawn_mock_bitcoind(port: u16, calls: Arc<Mutex<Vec<String>>>) {
+    thread::spawn(move || {
+        let rpc = warp::post()
+            .and(warp::body::json())
+            .map(move |request: serde_json::Value| {
+                let method = request.get("method").and_then(|method| method.as_str()).unwrap_or("").to_string();
+                let mut calls = calls.lock().unwrap();
+                calls.push(method.clone());
+                let result = match method.as_str() {
+                    "listunspent" if calls.iter().any(|call| call == "importaddress") => json!([{
+                        "txid": "2b2fe12ee0b8ff8b1ed7dbbe6d76ad1d08d3a5e8a1b1b7f63dd9e18b0bc29fc8",
+                        "vout": 0,
+                        "scriptPubKey": "76a914ce5d1c8d3e1fd6d1b0ae06fcf2df3b5eb3c8d53a88ac",
+                        "amount": 1.5,
+                        "confirmations": 6,
+                        "spendable": true,
+                        "solvable": true,
+                        "safe": true
+                    }]),
+                    "listunspent" => json!([]),
+                    _ => serde_json::Value::Null
+                };
+                warp::reply::json(&json!({ "result": result, "error": null, "id": "stacks" }))
+            });
+        let mut rt = tokio::runtime::Runtime::new().expect("Failed to initialize tokio");
+        rt.block_on(warp::serve(rpc).run(([127, 0, 0, 1], port)));
+    });
+
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("Mock bitcoind did not start on port {}", port);
+}
+
+#[test]
+fn burnchain_endpoints_fail_over_from_unreachable_primary() {
+    // nothing listens on the primary's RPC port
+    let calls = Arc::new(Mutex::new(vec![]));
+    spawn_mock_bitcoind(50322, calls.clone());
+
+    let mut config_file = burnchain_config_file(vec![
+        BurnchainEndpointFile {
+            peer_host: "127.0.0.1".to_string(),
+            rpc_port: Some(50322),
+            ..BurnchainEndpointFile::default()
+        },
+    ]);
+    config_file.burnchain.as_mut().unwrap().rpc_port = Some(50321);
+    let config = Config::from_config_file(config_file);
+
+    let controller = BitcoinRegtestController::new_dummy(config);
+    let public_key = Secp256k1PublicKey::from_hex("03e2ed46873d0db820e8c6001aabc082d72b5b900b53b7a1b9714fe7bde3037b81").unwrap();
+
+    // the backup answers in the primary's place, and imports the address when it turns out
+    // not to know about it
+    let utxos = controller.get_utxos(&public_key, 10_000).expect("no UTXOs from the backup bitcoind");
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(*calls.lock().unwrap(), vec!["listunspent".to_string(), "importaddress".to_string(), "listunspent".to_string()]);
+}
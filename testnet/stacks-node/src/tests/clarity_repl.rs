@@ -0,0 +1,97 @@
+use std::fs;
+
+use stacks::chainstate::stacks::StacksPrivateKey;
+
+use crate::clarity_repl::*;
+use crate::helium::RunLoop;
+
+use super::{make_contract_publish, to_addr, SK_1};
+
+const COUNTER_CONTRACT: &str = "(define-data-var counter int 0)
+(define-public (increment (by int))
+  (begin
+    (var-set counter (+ (var-get counter) by))
+    (ok (var-get counter))))";
+
+#[test]
+fn test_split_repl_args() {
+    assert_eq!(split_repl_args("  ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter increment 2 "),
+               vec!["ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter", "increment", "2"]);
+    assert_eq!(split_repl_args("(list 1 2) \"a \\\" b\" { a: u1, b: (some 2) } u3"),
+               vec!["(list 1 2)", "\"a \\\" b\"", "{ a: u1, b: (some 2) }", "u3"]);
+    assert!(split_repl_args("   ").is_empty());
+}
+
+#[test]
+fn test_clarity_repl() {
+    let mut repl = ClarityRepl::new().unwrap();
+
+    let contract_path = std::env::temp_dir().join("test_clarity_repl_counter.clar");
+    fs::write(&contract_path, COUNTER_CONTRACT).unwrap();
+    let unused_path = std::env::temp_dir().join("test_clarity_repl_unused.clar");
+    fs::write(&unused_path, "(define-private (unused) 1)").unwrap();
+
+    let mut input = |line: &str| repl.handle_line(line).unwrap();
+    let contract_id = format!("{}.counter", DEFAULT_REPL_SENDER);
+
+    assert_eq!(input("(+ 1 2)"), "3");
+    assert!(input("(+ 1").starts_with("Error: "));
+    assert_eq!(input(&format!(":deploy counter {}", contract_path.display())), format!("Deployed {}", &contract_id));
+    assert!(input(&format!(":deploy counter {}", contract_path.display())).starts_with("Error: "));
+    assert_eq!(input(&format!(":lint counter-2 {}", contract_path.display())), "No warnings");
+    assert!(input(&format!(":lint unused {}", unused_path.display()))
+            .starts_with("Warning (line 1, column 1): private function 'unused' is never called."));
+    assert_eq!(input(&format!(":call {} increment 2", &contract_id)), "(ok 2)");
+    assert_eq!(input(&format!(":call {} increment 3", &contract_id)), "(ok 5)");
+    assert!(input(&format!(":call {} decrement 3", &contract_id)).starts_with("Error: "));
+
+    assert_eq!(input(&format!(":contract {}", &contract_id)), format!("Evaluating in {}", &contract_id));
+    assert_eq!(input("(var-get counter)"), "5");
+    assert_eq!(input(":contract"), "Evaluating outside of any contract");
+
+    assert_eq!(input(":sender"), DEFAULT_REPL_SENDER);
+    assert!(input(":sender nobody").starts_with("Invalid principal"));
+    assert_eq!(input(":sender ST2VHM28V9E5QCRD6C73215KAPSBKQGPWTEE5CMQT"), "Sender is ST2VHM28V9E5QCRD6C73215KAPSBKQGPWTEE5CMQT");
+    assert_eq!(input("tx-sender"), "ST2VHM28V9E5QCRD6C73215KAPSBKQGPWTEE5CMQT");
+
+    assert!(input(":frobnicate").starts_with("Unknown command"));
+    assert_eq!(repl.handle_line(":quit"), None);
+
+    fs::remove_file(&contract_path).unwrap();
+    fs::remove_file(&unused_path).unwrap();
+}
+
+#[test]
+fn test_clarity_repl_fork() {
+    let mut conf = super::new_test_conf();
+    conf.burnchain.commit_anchor_block_within = 1000;
+    let chainstate_path = conf.get_chainstate_path();
+
+    let num_rounds = 3;
+
+    let mut run_loop = RunLoop::new(conf);
+    run_loop.callbacks.on_new_tenure(|round, _burnchain_tip, chain_tip, tenure| {
+        if round == 1 {
+            let contract_sk = StacksPrivateKey::from_hex(SK_1).unwrap();
+            let publish_tx = make_contract_publish(&contract_sk, 0, 0, "counter", COUNTER_CONTRACT);
+            tenure.mem_pool.submit_raw(&chain_tip.metadata.burn_header_hash, &chain_tip.block.block_hash(), publish_tx).unwrap();
+        }
+    });
+    run_loop.start(num_rounds);
+
+    let contract_id = format!("{}.counter", to_addr(&StacksPrivateKey::from_hex(SK_1).unwrap()));
+
+    {
+        let mut repl = ClarityRepl::fork(&chainstate_path, None).unwrap();
+        assert_eq!(repl.handle_line(&format!(":call {} increment 5", &contract_id)).unwrap(), "(ok 5)");
+        repl.handle_line(&format!(":contract {}", &contract_id)).unwrap();
+        assert_eq!(repl.handle_line("(var-get counter)").unwrap(), "5");
+    }
+
+    // the fork's changes never reach the node
+    let mut repl = ClarityRepl::fork(&chainstate_path, None).unwrap();
+    repl.handle_line(&format!(":contract {}", &contract_id)).unwrap();
+    assert_eq!(repl.handle_line("(var-get counter)").unwrap(), "0");
+
+    assert!(ClarityRepl::fork("/nonexistent/chainstate", None).is_err());
+}
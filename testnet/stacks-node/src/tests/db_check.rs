@@ -0,0 +1,28 @@
+use std::fs;
+use std::path::Path;
+
+use crate::db_check::check_databases;
+
+use super::new_test_conf;
+
+#[test]
+fn test_db_check_missing_databases() {
+    let mut conf = new_test_conf();
+    let working_dir = std::env::temp_dir().join("stacks-node-db-check-missing");
+    if working_dir.exists() {
+        fs::remove_dir_all(&working_dir).unwrap();
+    }
+    fs::create_dir_all(&working_dir).unwrap();
+    conf.node.working_dir = working_dir.to_string_lossy().to_string();
+
+    // there's nothing to check, and nothing gets created
+    assert!(check_databases(&conf).is_err());
+    assert!(!Path::new(&conf.get_burn_db_file_path()).exists());
+    assert_eq!(fs::read_dir(&working_dir).unwrap().count(), 0);
+
+    // a chainstate without a sortition DB
+    fs::create_dir_all(Path::new(&conf.get_chainstate_path()).join("vm")).unwrap();
+    let err = check_databases(&conf).unwrap_err();
+    assert!(err.contains("No sortition DB"));
+    assert!(!Path::new(&conf.get_burn_db_file_path()).exists());
+}
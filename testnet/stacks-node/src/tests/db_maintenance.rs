@@ -0,0 +1,58 @@
+use std::fs;
+
+use stacks::util::db::DBConn;
+
+use crate::db_maintenance::*;
+
+#[test]
+fn test_vacuum_hours() {
+    assert_eq!(parse_vacuum_hours("2-5"), Some((2, 5)));
+    assert_eq!(parse_vacuum_hours(" 22 - 4 "), Some((22, 4)));
+    assert_eq!(parse_vacuum_hours("0-24"), Some((0, 24)));
+    assert_eq!(parse_vacuum_hours("5"), None);
+    assert_eq!(parse_vacuum_hours("3-3"), None);
+    assert_eq!(parse_vacuum_hours("24-2"), None);
+    assert_eq!(parse_vacuum_hours("2-25"), None);
+    assert_eq!(parse_vacuum_hours("two-five"), None);
+
+    let hour = |h: u64| 1_600_000_000 / 86400 * 86400 + h * 3600 + 59;
+    assert!(in_vacuum_window((2, 5), hour(2)));
+    assert!(in_vacuum_window((2, 5), hour(4)));
+    assert!(!in_vacuum_window((2, 5), hour(5)));
+    assert!(!in_vacuum_window((2, 5), hour(1)));
+
+    // windows can wrap around midnight
+    assert!(in_vacuum_window((22, 4), hour(23)));
+    assert!(in_vacuum_window((22, 4), hour(0)));
+    assert!(!in_vacuum_window((22, 4), hour(4)));
+    assert!(!in_vacuum_window((22, 4), hour(12)));
+}
+
+#[test]
+fn test_db_maintenance() {
+    let working_dir = std::env::temp_dir().join("stacks-node-db-maintenance");
+    if working_dir.exists() {
+        fs::remove_dir_all(&working_dir).unwrap();
+    }
+    fs::create_dir_all(working_dir.join("chainstate/blocks/00ff")).unwrap();
+    fs::write(working_dir.join("chainstate/blocks/00ff").join("ab".repeat(32)), b"a block").unwrap();
+    fs::write(working_dir.join("notes.txt"), b"not a database").unwrap();
+
+    let db_path = working_dir.join("chainstate/blocks/staging.db");
+    {
+        let conn = DBConn::open(&db_path).unwrap();
+        conn.execute_batch("CREATE TABLE t(x TEXT); CREATE INDEX t_x ON t(x);").unwrap();
+        for i in 0..1000 {
+            conn.execute("INSERT INTO t VALUES (?1)", &[&format!("{:0>1000}", i)]).unwrap();
+        }
+        conn.execute_batch("DELETE FROM t;").unwrap();
+    }
+    let peer_db_path = working_dir.join("peer_db.sqlite");
+    DBConn::open(&peer_db_path).unwrap().execute_batch("CREATE TABLE p(x INT);").unwrap();
+
+    assert_eq!(find_sqlite_dbs(&working_dir), vec![db_path.clone(), peer_db_path]);
+
+    analyze_db(&db_path).unwrap();
+    let (size_before, size_after) = vacuum_db(&db_path).unwrap();
+    assert!(size_after < size_before);
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::Path;
+
+use stacks::util::db::DBConn;
+use stacks::chainstate::stacks::db::HEADERS_DB_MIGRATIONS;
+use stacks::util::migrations::{get_schema_version, latest_schema_version};
+
+use crate::db_migrate::{NodeDatabase, migrate_databases, parse_databases};
+
+use super::new_test_conf;
+
+#[test]
+fn test_parse_databases() {
+    assert_eq!(parse_databases(None).unwrap(), NodeDatabase::all());
+    assert_eq!(parse_databases(Some("mempool, headers,mempool")).unwrap(), vec![NodeDatabase::Mempool, NodeDatabase::Headers]);
+    assert!(parse_databases(Some("clarity")).unwrap_err().contains("Unknown database"));
+}
+
+#[test]
+fn test_migrate_databases() {
+    let mut conf = new_test_conf();
+    let working_dir = std::env::temp_dir().join("stacks-node-db-migrate");
+    if working_dir.exists() {
+        fs::remove_dir_all(&working_dir).unwrap();
+    }
+    fs::create_dir_all(&working_dir).unwrap();
+    conf.node.working_dir = working_dir.to_string_lossy().to_string();
+
+    // nothing has been created yet, so there's nothing to migrate
+    assert_eq!(migrate_databases(&conf, &NodeDatabase::all(), None, false).unwrap(), vec![]);
+
+    // a chainstate with only its headers DB
+    fs::create_dir_all(Path::new(&conf.get_chainstate_path()).join("vm")).unwrap();
+    let headers_path = NodeDatabase::Headers.path(&conf).unwrap();
+    DBConn::open(&headers_path).unwrap().execute_batch("CREATE TABLE block_headers(block_height INTEGER);").unwrap();
+
+    let migrations = migrate_databases(&conf, &NodeDatabase::all(), None, true).unwrap();
+    assert_eq!(migrations.len(), 1);
+    assert_eq!(migrations[0].database, NodeDatabase::Headers);
+    assert_eq!(migrations[0].from_version, 0);
+    assert_eq!(migrations[0].to_version, latest_schema_version(HEADERS_DB_MIGRATIONS));
+    assert_eq!(migrations[0].steps.len(), HEADERS_DB_MIGRATIONS.len());
+    assert_eq!(get_schema_version(&DBConn::open(&headers_path).unwrap()).unwrap(), 0);
+
+    migrate_databases(&conf, &[NodeDatabase::Headers], None, false).unwrap();
+    let conn = DBConn::open(&headers_path).unwrap();
+    assert_eq!(get_schema_version(&conn).unwrap(), latest_schema_version(HEADERS_DB_MIGRATIONS));
+    assert!(conn.prepare("SELECT tx_index FROM transaction_receipts").is_ok());
+
+    // ...and back
+    migrate_databases(&conf, &[NodeDatabase::Headers], Some(0), false).unwrap();
+    let conn = DBConn::open(&headers_path).unwrap();
+    assert_eq!(get_schema_version(&conn).unwrap(), 0);
+    assert!(conn.prepare("SELECT txid FROM transaction_receipts").is_err());
+
+    // a headers DB with a receipt index from before abort codes and block order were recorded
+    // is brought up to date before it's migrated
+    conn.execute_batch("CREATE TABLE transaction_receipts(txid TEXT NOT NULL, index_block_hash TEXT NOT NULL);").unwrap();
+    migrate_databases(&conf, &[NodeDatabase::Headers], None, false).unwrap();
+    let conn = DBConn::open(&headers_path).unwrap();
+    assert!(conn.prepare("SELECT abort_code, tx_index FROM transaction_receipts").is_ok());
+
+    // a version is only meaningful for one database, and has to exist
+    assert!(migrate_databases(&conf, &NodeDatabase::all(), Some(0), false).is_err());
+    assert!(migrate_databases(&conf, &[NodeDatabase::Headers], Some(1000), false).is_err());
+}
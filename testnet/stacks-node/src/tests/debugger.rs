@@ -0,0 +1,42 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+
+use stacks::vm::debug::{DebugSession, DebugResponse};
+
+use crate::debugger::*;
+
+#[test]
+fn test_bind_debugger_loopback_only() {
+    assert!(bind_debugger("0.0.0.0:0").is_err());
+    assert!(bind_debugger("not an address").is_err());
+    assert!(bind_debugger("127.0.0.1:0").is_ok());
+}
+
+#[test]
+fn test_serve_debugger() {
+    let listener = bind_debugger("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || serve_debugger(listener, DebugSession::new()));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request = |line: &str| {
+        stream.write_all(line.as_bytes()).unwrap();
+        stream.write_all(b"\n").unwrap();
+        let mut response = String::new();
+        reader.read_line(&mut response).unwrap();
+        serde_json::from_str::<DebugResponse>(&response).unwrap()
+    };
+
+    assert_eq!(request(r#"{"command":"status"}"#), DebugResponse::Running);
+    assert_eq!(request(r#"{"command":"set_breakpoint","breakpoint":{"type":"function","contract":"ST000000000000000000002AMW42H.pox","function":"stack-stx"}}"#), DebugResponse::Ok);
+    match request(r#"{"command":"list_breakpoints"}"#) {
+        DebugResponse::Breakpoints { breakpoints } => assert_eq!(breakpoints.len(), 1),
+        x => panic!("Expected breakpoints, got {:?}", &x)
+    }
+    match request("not json") {
+        DebugResponse::Error { .. } => {},
+        x => panic!("Expected an error, got {:?}", &x)
+    }
+}
@@ -0,0 +1,51 @@
+use stacks::vm::Value;
+use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier};
+
+use crate::encode_args::*;
+
+#[test]
+fn test_encode_args() {
+    assert_eq!(encode_arg("u10").unwrap(), "0x010000000000000000000000000000000a");
+    assert_eq!(encode_arg("'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM").unwrap(),
+               format!("0x{}", hex_of(Value::Principal(PrincipalData::parse("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM").unwrap()))));
+
+    assert!(encode_arg("(+ u1").is_err());
+    assert!(encode_arg("(define-data-var a int 1)").is_err());
+
+    assert_eq!(encode_args(&["1".to_string(), "true".to_string()]).unwrap(),
+               "0x0000000000000000000000000000000001\n0x03");
+}
+
+#[test]
+fn test_decode_args() {
+    let literals = [
+        "-1", "u2", "true", "0x00ff",
+        "'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM",
+        "'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter",
+        "none", "(some u3)", "(ok 1)", "(err u2)",
+        "(list (list 1 2) (list))",
+        "{ a: (some 0x01), b: (list u1) }",
+    ];
+
+    // decoding an encoded literal gives back the literal, which encodes the same way
+    for literal in literals.iter() {
+        let encoded = encode_arg(literal).unwrap();
+        let decoded = decode_arg(&encoded).unwrap();
+        assert_eq!(&decoded, literal);
+        assert_eq!(encode_arg(&decoded).unwrap(), encoded);
+        // with or without the 0x prefix
+        assert_eq!(decode_arg(&encoded[2..]).unwrap(), decoded);
+    }
+
+    assert!(decode_arg("0x01").is_err());
+    assert!(decode_arg("zz").is_err());
+
+    let contract = QualifiedContractIdentifier::parse("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter").unwrap();
+    assert_eq!(decode_args(&[hex_of(Value::Bool(false)), hex_of(Value::from(contract))]).unwrap(),
+               "false\n'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.counter");
+}
+
+fn hex_of(value: Value) -> String {
+    use stacks::vm::database::ClaritySerializable;
+    value.serialize()
+}
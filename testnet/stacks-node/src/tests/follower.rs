@@ -0,0 +1,235 @@
+use std::env;
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use stacks::chainstate::stacks::{StacksPrivateKey, StacksPublicKey, StacksTransaction};
+use stacks::net::StacksMessageCodec;
+use stacks::util::hash::bytes_to_hex;
+use stacks::vm::types::PrincipalData;
+use stacks::burnchains::{BurnchainHeaderHash, Txid};
+use stacks::chainstate::burn::{BlockSnapshot, BlockHeaderHash, ConsensusHash, OpsHash, SortitionHash, VRFSeed};
+use stacks::chainstate::burn::operations::{BlockstackOperationType, LeaderKeyRegisterOp, LeaderBlockCommitOp};
+use stacks::chainstate::stacks::index::TrieHash;
+
+use crate::{Config, ConfigFile, EventDispatcher, NodeEvent, FollowerNode, Keychain, Node, BitcoinRegtestController};
+use crate::config::{EventKeyType, NodeConfigFile};
+use crate::tests::{make_stacks_transfer, new_test_conf, to_addr, SK_1};
+use crate::tests::bitcoin_regtest::BitcoinCoreController;
+use crate::tests::neon_integrations::neon_integration_test_conf;
+
+fn make_test_tx() -> (StacksTransaction, Vec<u8>) {
+    let sk = StacksPrivateKey::from_hex(SK_1).unwrap();
+    let recipient: PrincipalData = to_addr(&sk).into();
+    let tx_bytes = make_stacks_transfer(&sk, 0, 0, &recipient, 1000);
+    let tx = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).unwrap();
+    (tx, tx_bytes)
+}
+
+#[test]
+fn channel_observers_receive_mempool_txs() {
+    let (tx, tx_bytes) = make_test_tx();
+    let raw_tx = format!("0x{}", bytes_to_hex(&tx_bytes));
+
+    let (v1_send, v1_recv) = channel();
+    let (v2_send, v2_recv) = channel();
+    let (stx_send, stx_recv) = channel();
+
+    let mut dispatcher = EventDispatcher::new();
+    dispatcher.register_channel_observer(&[EventKeyType::MemPoolTransactions], 1, v1_send);
+    dispatcher.register_channel_observer(&[EventKeyType::AnyEvent], 2, v2_send);
+    dispatcher.register_channel_observer(&[EventKeyType::STXEvent], 1, stx_send);
+
+    dispatcher.process_new_mempool_txs(vec![tx]);
+
+    match v1_recv.try_recv().unwrap() {
+        NodeEvent::NewMempoolTxs(payload) => assert_eq!(payload, json!([raw_tx])),
+        event => panic!("Unexpected event {:?}", event)
+    }
+    match v2_recv.try_recv().unwrap() {
+        NodeEvent::NewMempoolTxs(payload) => assert_eq!(payload, json!({ "schema_version": "2.1", "transactions": [raw_tx] })),
+        event => panic!("Unexpected event {:?}", event)
+    }
+    // not subscribed to mempool transactions
+    assert!(stx_recv.try_recv().is_err());
+}
+
+#[test]
+fn channel_observers_receive_burn_blocks() {
+    let mut keychain = Keychain::default(vec![0x02; 32]);
+    let vrf_public_key = keychain.rotate_vrf_keypair(1);
+
+    let burn_header_hash = BurnchainHeaderHash([0x03; 32]);
+    let key_op = LeaderKeyRegisterOp {
+        consensus_hash: ConsensusHash([0x04; 20]),
+        public_key: vrf_public_key.clone(),
+        memo: vec![],
+        address: keychain.get_address(),
+        txid: Txid([0x05; 32]),
+        vtxindex: 1,
+        block_height: 2,
+        burn_header_hash: burn_header_hash.clone(),
+    };
+    let commit_op = LeaderBlockCommitOp {
+        block_header_hash: BlockHeaderHash([0x06; 32]),
+        new_seed: VRFSeed([0x07; 32]),
+        parent_block_ptr: 0,
+        parent_vtxindex: 0,
+        key_block_ptr: 1,
+        key_vtxindex: 1,
+        memo: vec![],
+        burn_fee: 12345,
+        input: keychain.get_burnchain_signer(),
+        txid: Txid([0x08; 32]),
+        vtxindex: 2,
+        block_height: 2,
+        burn_header_hash: burn_header_hash.clone(),
+    };
+    let snapshot = BlockSnapshot {
+        block_height: 2,
+        burn_header_timestamp: 1591301733,
+        burn_header_hash: burn_header_hash.clone(),
+        parent_burn_header_hash: BurnchainHeaderHash([0x09; 32]),
+        consensus_hash: ConsensusHash([0x0a; 20]),
+        ops_hash: OpsHash([0x0b; 32]),
+        total_burn: 12345,
+        sortition: true,
+        sortition_hash: SortitionHash([0x0c; 32]),
+        winning_block_txid: commit_op.txid.clone(),
+        winning_stacks_block_hash: commit_op.block_header_hash.clone(),
+        index_root: TrieHash([0x0d; 32]),
+        num_sortitions: 1,
+        stacks_block_accepted: false,
+        stacks_block_height: 0,
+        arrival_index: 2,
+        canonical_stacks_tip_height: 0,
+        canonical_stacks_tip_hash: BlockHeaderHash([0x00; 32]),
+        canonical_stacks_tip_burn_hash: BurnchainHeaderHash([0x00; 32]),
+    };
+    let ops = vec![BlockstackOperationType::LeaderKeyRegister(key_op), BlockstackOperationType::LeaderBlockCommit(commit_op)];
+
+    let (burn_send, burn_recv) = channel();
+    let (any_send, any_recv) = channel();
+    let (memtx_send, memtx_recv) = channel();
+
+    let mut dispatcher = EventDispatcher::new();
+    dispatcher.register_channel_observer(&[EventKeyType::BurnchainBlocks], 1, burn_send);
+    dispatcher.register_channel_observer(&[EventKeyType::AnyEvent], 1, any_send);
+    dispatcher.register_channel_observer(&[EventKeyType::MemPoolTransactions], 1, memtx_send);
+
+    dispatcher.process_burn_block(&snapshot, &ops);
+
+    let payload = match burn_recv.try_recv().unwrap() {
+        NodeEvent::NewBurnBlock(payload) => payload,
+        event => panic!("Unexpected event {:?}", event)
+    };
+    assert_eq!(payload["burn_block_hash"], json!(format!("0x{}", burn_header_hash.to_hex())));
+    assert_eq!(payload["burn_block_height"], json!(2));
+    assert_eq!(payload["sortition"], json!(true));
+    assert_eq!(payload["winning_block_txid"], json!(format!("0x{}", Txid([0x08; 32]).to_hex())));
+    assert_eq!(payload["leader_key_registrations"][0]["address"], json!(keychain.get_address().to_string()));
+    assert_eq!(payload["leader_key_registrations"][0]["vrf_public_key"], json!(format!("0x{}", vrf_public_key.to_hex())));
+    assert_eq!(payload["block_commits"][0]["burn_fee"], json!(12345));
+    assert_eq!(payload["block_commits"][0]["won_sortition"], json!(true));
+    assert_eq!(payload["user_burn_supports"], json!([]));
+
+    match any_recv.try_recv().unwrap() {
+        NodeEvent::NewBurnBlock(any_payload) => assert_eq!(any_payload, payload),
+        event => panic!("Unexpected event {:?}", event)
+    }
+    // not subscribed to burnchain blocks
+    assert!(memtx_recv.try_recv().is_err());
+}
+
+#[test]
+fn follower_builder_rejects_bad_setup() {
+    let (sender, _receiver) = channel();
+
+    // no config
+    assert!(FollowerNode::builder().start().is_err());
+
+    // helium nodes can't follow
+    assert!(FollowerNode::builder().config(new_test_conf()).start().is_err());
+
+    // unsupported schema version
+    let mut conf = new_test_conf();
+    conf.burnchain.mode = "neon".to_string();
+    assert!(FollowerNode::builder()
+            .config(conf)
+            .subscribe_with_schema_version(vec![EventKeyType::AnyEvent], 99, sender)
+            .start()
+            .is_err());
+}
+
+#[test]
+#[ignore]
+fn follower_delivers_events_and_stops() {
+    if env::var("BITCOIND_TEST") != Ok("1".into()) {
+        return
+    }
+
+    let (conf, _) = neon_integration_test_conf();
+
+    let mut btcd_controller = BitcoinCoreController::new(conf.clone());
+    btcd_controller.start_bitcoind().map_err(|_e| ()).expect("Failed starting bitcoind");
+
+    let mut btc_regtest_controller = BitcoinRegtestController::new(conf.clone());
+    btc_regtest_controller.bootstrap_chain(201);
+
+    let (sender, receiver) = channel();
+    let node = Node::builder()
+        .config(conf)
+        .subscribe(vec![EventKeyType::BurnchainBlocks], sender)
+        .start()
+        .unwrap();
+
+    // the follower only reports blocks mined after it syncs, so keep mining until one shows up
+    let start = Instant::now();
+    let payload = loop {
+        assert!(start.elapsed() < Duration::from_secs(120), "Timed out waiting for a burnchain block event");
+        btc_regtest_controller.build_next_block(1);
+        match receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(NodeEvent::NewBurnBlock(payload)) => break payload,
+            Ok(event) => panic!("Unexpected event {:?}", event),
+            Err(_) => continue
+        }
+    };
+    assert!(payload.get("burn_block_hash").is_some());
+
+    node.stop();
+    node.join().unwrap();
+}
+
+#[test]
+fn watch_only_keychain_matches_signing_keychain() {
+    let keychain = Keychain::default(vec![0x01; 32]);
+    let watch_keychain = keychain.watch_only();
+    assert_eq!(watch_keychain.get_address(), keychain.get_address());
+    assert_eq!(watch_keychain.get_burnchain_signer(), keychain.get_burnchain_signer());
+
+    // the same keys, given as config, watch the same address
+    let pubk = keychain.get_burnchain_signer().public_keys[0].clone();
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            watch_public_keys: Some(vec![pubk.to_hex()]),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    let config = Config::from_config_file(config_file);
+    assert_eq!(config.node.watch_keychain, Some(watch_keychain));
+}
+
+#[test]
+#[should_panic]
+fn watch_only_config_rejects_miner() {
+    let pubk = StacksPublicKey::from_private(&StacksPrivateKey::from_hex(SK_1).unwrap());
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            watch_public_keys: Some(vec![pubk.to_hex()]),
+            miner: Some(true),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    Config::from_config_file(config_file);
+}
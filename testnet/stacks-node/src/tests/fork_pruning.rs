@@ -0,0 +1,26 @@
+use stacks::chainstate::stacks::db::prune::MIN_PRUNE_FORK_DEPTH;
+
+use crate::{Config, ConfigFile};
+use crate::config::NodeConfigFile;
+
+fn config_with_prune_fork_depth(prune_fork_depth: u64) -> ConfigFile {
+    ConfigFile {
+        node: Some(NodeConfigFile {
+            prune_fork_depth: Some(prune_fork_depth),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    }
+}
+
+#[test]
+fn prune_fork_depth_config_parses() {
+    assert_eq!(Config::from_config_file(ConfigFile::default()).node.prune_fork_depth, None);
+    assert_eq!(Config::from_config_file(config_with_prune_fork_depth(1000)).node.prune_fork_depth, Some(1000));
+}
+
+#[test]
+#[should_panic]
+fn prune_fork_depth_too_shallow() {
+    Config::from_config_file(config_with_prune_fork_depth(MIN_PRUNE_FORK_DEPTH - 1));
+}
@@ -0,0 +1,36 @@
+use stacks::chainstate::stacks::{StacksBlockId, StacksWorkScore};
+use stacks::chainstate::stacks::db::StacksHeaderInfo;
+use stacks::chainstate::stacks::db::forks::ForkTip;
+use stacks::chainstate::stacks::index::TrieHash;
+
+use crate::forks::*;
+
+fn make_fork_tip(block_height: u64, canonical: bool, on_canonical_burn_chain: bool, fork_point_height: u64) -> ForkTip {
+    let mut header_info = StacksHeaderInfo::genesis_block_header_info(TrieHash([0u8; 32]));
+    header_info.block_height = block_height;
+    header_info.anchored_header.total_work = StacksWorkScore { burn: 1000, work: block_height };
+    ForkTip {
+        header_info,
+        canonical,
+        on_canonical_burn_chain,
+        fork_point: StacksBlockId([1u8; 32]),
+        fork_point_height,
+    }
+}
+
+#[test]
+fn test_describe_fork_tip() {
+    let canonical = make_fork_tip(10, true, true, 10);
+    assert_eq!(canonical.fork_length(), 0);
+    assert_eq!(describe_fork_tip(&canonical),
+               format!("10 {}: canonical, burn 1000, length 10", &canonical.index_block_hash()));
+
+    let behind = make_fork_tip(8, false, true, 6);
+    assert_eq!(behind.fork_length(), 2);
+    assert_eq!(describe_fork_tip(&behind),
+               format!("8 {}: on the canonical burn chain, burn 1000, length 8, 2 blocks since height 6 ({})",
+                       &behind.index_block_hash(), StacksBlockId([1u8; 32])));
+
+    let orphaned = make_fork_tip(12, false, false, 9);
+    assert!(describe_fork_tip(&orphaned).contains("off the canonical burn chain"));
+}
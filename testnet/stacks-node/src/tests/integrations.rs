@@ -1,9 +1,10 @@
 use std::collections::HashMap;
+use std::fs;
 
 use std::fmt::Write;
 use stacks::vm::{
     database::ClaritySerializable,
-    types::{QualifiedContractIdentifier, TupleData},
+    types::{PrincipalData, QualifiedContractIdentifier, TupleData},
     analysis::{mem_type_check, contract_interface_builder::{build_contract_interface, ContractInterface}},
     Value };
 use stacks::chainstate::stacks::{
@@ -14,7 +15,7 @@ use stacks::net::{AccountEntryResponse, ContractSrcResponse, CallReadOnlyRequest
 use stacks::net::StacksMessageCodec;
 use stacks::vm::clarity::ClarityConnection;
 
-use crate::config::InitialBalance;
+use crate::config::{BlockLimitFile, Config, ConfigFile, InitialBalance, HELIUM_BLOCK_LIMIT};
 use crate::helium::RunLoop;
 
 use super::{make_contract_publish, make_contract_call, make_stacks_transfer, SK_1, SK_2, SK_3, ADDR_4, to_addr};
@@ -57,6 +58,8 @@ const GET_INFO_CONTRACT: &'static str = "
         (define-read-only (get-exotic-data-info? (height uint))
           (unwrap-panic (map-get? block-data { height: height })))
 
+        (define-read-only (get-sender) tx-sender)
+
         (define-private (exotic-data-checks (height uint))
           (let ((block-to-check (unwrap-panic (get-block-info? id-header-hash height)))
                 (block-info (unwrap-panic (map-get? block-data ((height (- height u1)))))))
@@ -404,6 +407,34 @@ fn integration_test_get_info() {
                 assert!(res.nonce_proof.is_some());
                 assert!(res.balance_proof.is_some());
 
+                // accounts as of a past block, by its height or its index block hash
+                let tip_height = chain_tip.metadata.block_height;
+                let path = format!("{}/v2/accounts/{}?proof=0&height={}",
+                                   &http_origin, &sender_addr, tip_height);
+                eprintln!("Test: GET {}", path);
+                let res = client.get(&path).send().unwrap().json::<AccountEntryResponse>().unwrap();
+                assert_eq!(res.nonce, 3);
+
+                let path = format!("{}/v2/accounts/{}?proof=0&tip={}",
+                                   &http_origin, &sender_addr, bhh);
+                eprintln!("Test: GET {}", path);
+                let res = client.get(&path).send().unwrap().json::<AccountEntryResponse>().unwrap();
+                assert_eq!(res.nonce, 3);
+
+                let path = format!("{}/v2/accounts/{}?proof=0&height={}",
+                                   &http_origin, &sender_addr, tip_height - 1);
+                eprintln!("Test: GET {}", path);
+                let res = client.get(&path).send().unwrap().json::<AccountEntryResponse>().unwrap();
+                assert_eq!(u128::from_str_radix(&res.balance[2..], 16).unwrap(), 100100);
+                assert_eq!(res.nonce, 2);
+
+                // blocks that don't exist yet aren't found
+                let path = format!("{}/v2/accounts/{}?height={}",
+                                   &http_origin, &sender_addr, tip_height + 1);
+                eprintln!("Test: GET {}", path);
+                let res = client.get(&path).send().unwrap();
+                assert_eq!(res.status().as_u16(), 404);
+
                 // let's try getting the transfer cost
                 let path = format!("{}/v2/fees/transfer", &http_origin);
                 eprintln!("Test: GET {}", path);
@@ -460,8 +491,8 @@ fn integration_test_get_info() {
                 eprintln!("Test: POST {}", path);
 
                 let body = CallReadOnlyRequestBody {
-                    sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
-                    arguments: vec![Value::UInt(1).serialize()]
+                    sender: Some("'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into()),
+                    arguments: vec![Value::UInt(1).serialize().into()]
                 };
 
                 let res = client.post(&path)
@@ -482,8 +513,9 @@ fn integration_test_get_info() {
                 eprintln!("Test: POST {}", path);
 
                 let body = CallReadOnlyRequestBody {
-                    sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
-                    arguments: vec![Value::UInt(1).serialize()]
+                    sender: Some("'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into()),
+                    // arguments can be JSON-encoded, too
+                    arguments: vec![Value::UInt(1).json_serialize()]
                 };
 
                 let res = client.post(&path)
@@ -498,14 +530,15 @@ fn integration_test_get_info() {
                 let expected_data = chain_state.clarity_eval_read_only(bhh, &contract_identifier,
                                                                        "(get-exotic-data-info? u1)");
                 assert_eq!(result_data, expected_data);
+                assert_eq!(Value::json_deserialize(&res["result_json"]).unwrap(), expected_data);
 
                 // let's have a runtime error!
                 let path = format!("{}/v2/contracts/call-read/{}/{}/{}", &http_origin, &contract_addr, "get-info", "get-exotic-data-info");
                 eprintln!("Test: POST {}", path);
 
                 let body = CallReadOnlyRequestBody {
-                    sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
-                    arguments: vec![Value::UInt(100).serialize()]
+                    sender: Some("'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into()),
+                    arguments: vec![Value::UInt(100).serialize().into()]
                 };
 
                 let res = client.post(&path)
@@ -522,7 +555,7 @@ fn integration_test_get_info() {
                 eprintln!("Test: POST {}", path);
 
                 let body = CallReadOnlyRequestBody {
-                    sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
+                    sender: Some("'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into()),
                     arguments: vec![]
                 };
 
@@ -536,6 +569,32 @@ fn integration_test_get_info() {
                 assert!(!res["okay"].as_bool().unwrap());
                 assert!(res["cause"].as_str().unwrap().contains("NotReadOnly"));
 
+                // whose tx-sender is it?  the given sender's, or else the contract's
+                let path = format!("{}/v2/contracts/call-read/{}/{}/{}", &http_origin, &contract_addr, "get-info", "get-sender");
+                let tests = vec![
+                    (Some("SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".to_string()),
+                     PrincipalData::parse("SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5").unwrap()),
+                    (Some(contract_identifier.to_string()), PrincipalData::Contract(contract_identifier.clone())),
+                    (None, PrincipalData::Contract(contract_identifier.clone())),
+                ];
+                for (sender, expected_sender) in tests.into_iter() {
+                    eprintln!("Test: POST {} (sender {:?})", path, &sender);
+
+                    let body = CallReadOnlyRequestBody {
+                        sender,
+                        arguments: vec![]
+                    };
+
+                    let res = client.post(&path)
+                        .json(&body)
+                        .send()
+                        .unwrap().json::<serde_json::Value>().unwrap();
+                    assert!(res["okay"].as_bool().unwrap());
+
+                    let result_data = Value::try_deserialize_hex_untyped(&res["result"].as_str().unwrap()[2..]).unwrap();
+                    assert_eq!(result_data, Value::Principal(expected_sender));
+                }
+
                 // let's submit a valid transaction!
                 let spender_sk = StacksPrivateKey::from_hex(SK_3).unwrap();
                 let path = format!("{}/v2/transactions", &http_origin);
@@ -786,6 +845,54 @@ fn mine_contract_twice() {
     run_loop.start(num_rounds);
 }
 
+#[test]
+fn contract_coverage_report() {
+    let mut conf = super::new_test_conf();
+    let coverage_output = format!("{}/coverage.info", &conf.node.working_dir);
+    conf.node.coverage_output = Some(coverage_output.clone());
+
+    conf.burnchain.commit_anchor_block_within = 1000;
+
+    let num_rounds = 4;
+
+    let contract = "(define-data-var counter int 0)
+(define-public (increment)
+  (begin
+    (var-set counter (+ (var-get counter) 1))
+    (ok (var-get counter))))
+(define-public (reset)
+  (begin
+    (var-set counter 0)
+    (ok 0)))";
+
+    let mut run_loop = RunLoop::new(conf);
+    run_loop.callbacks.on_new_tenure(move |round, _burnchain_tip, chain_tip, tenure| {
+        let contract_sk = StacksPrivateKey::from_hex(SK_1).unwrap();
+        let header_hash = chain_tip.block.block_hash();
+        let burn_header_hash = chain_tip.metadata.burn_header_hash;
+
+        if round == 1 {
+            let publish_tx = make_contract_publish(&contract_sk, 0, 0, "counter", contract);
+            tenure.mem_pool.submit_raw(&burn_header_hash, &header_hash, publish_tx).unwrap();
+        } else if round == 2 {
+            let call_tx = make_contract_call(&contract_sk, 1, 0, &to_addr(&contract_sk), "counter", "increment", &[]);
+            tenure.mem_pool.submit_raw(&burn_header_hash, &header_hash, call_tx).unwrap();
+        }
+    });
+
+    run_loop.start(num_rounds);
+
+    let report = fs::read_to_string(&coverage_output).unwrap();
+    let contract_id = format!("{}.counter", to_addr(&StacksPrivateKey::from_hex(SK_1).unwrap()));
+    assert!(report.contains(&format!("SF:{}\n", &contract_id)));
+    assert!(report.contains("FN:2,increment\nFN:6,reset\n"));
+    assert!(!report.contains("FNDA:0,increment\n"));
+    assert!(report.contains("FNDA:0,reset\n"));
+    assert!(report.contains("FNF:2\nFNH:1\n"));
+    assert!(report.contains("DA:7,0\nDA:8,0\nDA:9,0\n"));
+    assert!(report.contains("LF:6\nLH:3\n"));
+}
+
 #[test]
 fn bad_contract_tx_rollback() {
     let mut conf = super::new_test_conf();
@@ -974,6 +1081,27 @@ fn block_limit_runtime_test() {
     run_loop.start(num_rounds);
 }
 
+#[test]
+fn block_limit_config_test() {
+    let config_file = ConfigFile {
+        block_limit: Some(BlockLimitFile { runtime: Some(1_000_000), ..BlockLimitFile::default() }),
+        ..ConfigFile::mocknet()
+    };
+    let conf = Config::from_config_file(config_file);
+    assert_eq!(conf.block_limit.runtime, 1_000_000);
+    assert_eq!(conf.block_limit.write_count, HELIUM_BLOCK_LIMIT.write_count);
+}
+
+#[test]
+#[should_panic(expected = "only supported for private testnets")]
+fn block_limit_config_rejects_public_testnet() {
+    let config_file = ConfigFile {
+        block_limit: Some(BlockLimitFile { runtime: Some(1_000_000), ..BlockLimitFile::default() }),
+        ..ConfigFile::neon()
+    };
+    Config::from_config_file(config_file);
+}
+
 
 #[test]
 fn mempool_errors() {
@@ -1098,3 +1226,76 @@ fn mempool_errors() {
 
     run_loop.start(num_rounds);
 }
+
+#[test]
+fn mocknet_empty_burn_blocks() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static STACKS_BLOCKS: AtomicU64 = AtomicU64::new(0);
+
+    let conf = super::new_test_conf();
+    let num_rounds = 5;
+
+    let mut run_loop = RunLoop::new(conf);
+    run_loop.callbacks.on_burn_chain_initialized(|burnchain_controller| {
+        // no Stacks ops at all in burn blocks #4 and #6 -- the commits sent for them
+        // get mined one block late
+        burnchain_controller.schedule_empty_block(4).unwrap();
+        burnchain_controller.schedule_empty_block(6).unwrap();
+    });
+
+    run_loop.callbacks.on_new_burn_chain_state(|_round, burnchain_tip, _chain_tip| {
+        let height = burnchain_tip.block_snapshot.block_height;
+        if height == 4 || height == 6 {
+            assert!(!burnchain_tip.block_snapshot.sortition);
+            assert_eq!(burnchain_tip.state_transition.accepted_ops.len(), 0);
+        }
+        else if height > 1 {
+            assert!(burnchain_tip.block_snapshot.sortition);
+        }
+    });
+
+    run_loop.callbacks.on_new_stacks_chain_state(|round, burnchain_tip, chain_tip, _chain_state| {
+        // one Stacks block per round, despite the empty burn blocks
+        assert_eq!(chain_tip.metadata.block_height, round + 1);
+        assert_eq!(chain_tip.metadata.burn_header_hash, burnchain_tip.block_snapshot.burn_header_hash);
+
+        let skipped = match burnchain_tip.block_snapshot.block_height {
+            0..=3 => 0,
+            4..=5 => 1,
+            _ => 2
+        };
+        assert_eq!(burnchain_tip.block_snapshot.block_height, round + 2 + skipped);
+        STACKS_BLOCKS.fetch_add(1, Ordering::SeqCst);
+    });
+
+    run_loop.start(num_rounds);
+    assert_eq!(STACKS_BLOCKS.load(Ordering::SeqCst), num_rounds);
+}
+
+#[test]
+fn mocknet_flash_burn_blocks() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static STACKS_BLOCKS: AtomicU64 = AtomicU64::new(0);
+
+    let conf = super::new_test_conf();
+    let num_rounds = 4;
+
+    let mut run_loop = RunLoop::new(conf);
+    run_loop.callbacks.on_burn_chain_initialized(|burnchain_controller| {
+        // burn blocks #4, #5 and #6 show up all at once, before the block with our commit
+        burnchain_controller.schedule_flash_blocks(4, 3).unwrap();
+    });
+
+    run_loop.callbacks.on_new_stacks_chain_state(|round, burnchain_tip, chain_tip, _chain_state| {
+        assert_eq!(chain_tip.metadata.block_height, round + 1);
+        assert_eq!(chain_tip.metadata.burn_header_hash, burnchain_tip.block_snapshot.burn_header_hash);
+
+        // the run loop never saw the flash blocks, and the tip is right after them
+        let expected_burn_height = if round < 2 { round + 2 } else { round + 5 };
+        assert_eq!(burnchain_tip.block_snapshot.block_height, expected_burn_height);
+        STACKS_BLOCKS.fetch_add(1, Ordering::SeqCst);
+    });
+
+    run_loop.start(num_rounds);
+    assert_eq!(STACKS_BLOCKS.load(Ordering::SeqCst), num_rounds);
+}
@@ -0,0 +1,34 @@
+use crate::{Config, ConfigFile};
+use crate::config::NodeConfigFile;
+
+fn light_sync_config(miner: bool, archive: bool) -> Config {
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            light_sync: Some(true),
+            miner: Some(miner),
+            archive: Some(archive),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    Config::from_config_file(config_file)
+}
+
+#[test]
+fn light_sync_config_parses() {
+    let conf = light_sync_config(false, false);
+    assert!(conf.node.light_sync);
+    assert!(!Config::from_config_file(ConfigFile::default()).node.light_sync);
+}
+
+#[test]
+#[should_panic]
+fn light_sync_config_rejects_miner() {
+    light_sync_config(true, false);
+}
+
+#[test]
+#[should_panic]
+fn light_sync_config_rejects_archive() {
+    light_sync_config(false, true);
+}
@@ -0,0 +1,16 @@
+use crate::{Config, ConfigFile};
+use crate::config::{NodeConfigFile, DEFAULT_MARF_CACHE_SIZE};
+
+#[test]
+fn marf_cache_config_parses() {
+    assert_eq!(Config::from_config_file(ConfigFile::default()).node.marf_cache_size, DEFAULT_MARF_CACHE_SIZE);
+
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            marf_cache_size: Some(0),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    assert_eq!(Config::from_config_file(config_file).node.marf_cache_size, 0);
+}
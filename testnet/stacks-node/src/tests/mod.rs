@@ -2,6 +2,22 @@ mod neon_integrations;
 mod integrations;
 mod bitcoin_regtest;
 mod mempool;
+mod follower;
+mod version_check;
+mod debugger;
+mod clarity_repl;
+mod encode_args;
+mod snapshot;
+mod replay;
+mod db_check;
+mod db_maintenance;
+mod state_diff;
+mod light_sync;
+mod reindex;
+mod marf_cache;
+mod forks;
+mod fork_pruning;
+mod backup;
 
 use stacks::chainstate::stacks::events::{StacksTransactionEvent, STXEventType};
 use stacks::chainstate::stacks::{TransactionPayload, StacksTransactionSigner, StacksPublicKey,TransactionPostConditionMode, TransactionSmartContract, TransactionAuth,TransactionVersion, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
@@ -19,7 +35,7 @@ use rand::RngCore;
 use super::{Config};
 use crate::helium::RunLoop;
 use super::node::{TESTNET_CHAIN_ID};
-use super::burnchains::bitcoin_regtest_controller::ParsedUTXO;
+use super::burnchains::bitcoin_regtest_controller::{ParsedUTXO, block_commit_fee, parse_fee_rate_estimate};
 
 // $ cat /tmp/out.clar 
 pub const STORE_CONTRACT: &str =  r#"(define-map store ((key (buff 32))) ((value (buff 32))))
@@ -589,3 +605,45 @@ fn test_btc_to_sat_errors() {
     assert!(ParsedUTXO::serialized_btc_to_sat("7.4e-7").is_none());
     assert!(ParsedUTXO::serialized_btc_to_sat("5.96e-6").is_none());
 }
+
+#[test]
+fn test_parse_fee_rate_estimate() {
+    let parse = |json: &str| parse_fee_rate_estimate(&serde_json::from_str(json).unwrap());
+
+    assert_eq!(parse(r#"{"result": {"feerate": 0.00020000, "blocks": 2}}"#), Some(20));
+    assert_eq!(parse(r#"{"result": {"feerate": 1e-5, "blocks": 2}}"#), Some(1));
+    assert_eq!(parse(r#"{"result": {"feerate": 1.234e-4, "blocks": 6}}"#), Some(12));
+    // below 1 sat/vbyte rounds up to the minimum relay fee
+    assert_eq!(parse(r#"{"result": {"feerate": 1e-8, "blocks": 2}}"#), Some(1));
+    assert_eq!(parse(r#"{"result": {"errors": ["Insufficient data or no feerate found"], "blocks": 2}}"#), None);
+    assert_eq!(parse(r#"{"result": {"feerate": -1, "blocks": 2}}"#), None);
+    assert_eq!(parse(r#"{"result": null, "error": {"code": -32601}}"#), None);
+}
+
+#[test]
+fn test_block_commit_fee() {
+    let mut burnchain = Config::default().burnchain;
+    burnchain.burnchain_op_tx_fee = 1000;
+    burnchain.max_satoshis_per_byte = 50;
+    burnchain.fee_estimation_conf_target = 6;
+
+    // estimation is opt-in: the flat fee is paid no matter what bitcoind says
+    assert!(!burnchain.fee_estimation);
+    assert_eq!(block_commit_fee(&burnchain, Some(40)).0, 1000);
+    assert_eq!(block_commit_fee(&burnchain, None).0, 1000);
+
+    burnchain.fee_estimation = true;
+    assert_eq!(block_commit_fee(&burnchain, Some(40)), (40 * 380, 40));
+    // capped at max_satoshis_per_byte
+    assert_eq!(block_commit_fee(&burnchain, Some(400)), (50 * 380, 50));
+    // never less than the flat fee
+    assert_eq!(block_commit_fee(&burnchain, Some(1)), (1000, 1));
+    // no estimate: falls back to the static table
+    assert_eq!(block_commit_fee(&burnchain, None), (15 * 380, 15));
+}
+mod sqlite_pragmas;
+#[cfg(feature = "block_compression")]
+mod block_compression;
+mod db_migrate;
+mod burnchain_endpoints;
+mod rpc_priorities;
@@ -9,6 +9,7 @@ use stacks::vm::types::PrincipalData;
 use crate::{
     neon, Config, Keychain, config::InitialBalance, BitcoinRegtestController, BurnchainController,
     config::EventObserverConfig, config::EventKeyType,
+    event_dispatcher::EVENT_SCHEMA_DEFAULT_VERSION,
 };
 use stacks::net::{AccountEntryResponse, RPCPeerInfoData};
 use super::bitcoin_regtest::BitcoinCoreController;
@@ -18,7 +19,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Instant, Duration};
 use stacks::util::hash::bytes_to_hex;
 
-fn neon_integration_test_conf() -> (Config, StacksAddress) {
+pub fn neon_integration_test_conf() -> (Config, StacksAddress) {
     let mut conf = super::new_test_conf();
 
     let keychain = Keychain::default(conf.node.seed.clone());
@@ -199,6 +200,7 @@ fn microblock_integration_test() {
         EventObserverConfig {
             endpoint: format!("http://localhost:{}/", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![ EventKeyType::AnyEvent ],
+            schema_version: EVENT_SCHEMA_DEFAULT_VERSION,
         });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -299,6 +301,7 @@ fn microblock_integration_test() {
 
     let mut prior = None;
     for block in blocks_observed.iter() {
+        assert_eq!(block.get("schema_version").unwrap().as_str().unwrap(), "1.1");
         let parent_index_hash = block.get("parent_index_block_hash")
             .unwrap().as_str().unwrap().to_string();
         let my_index_hash = block.get("index_block_hash")
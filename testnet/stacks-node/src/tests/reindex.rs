@@ -0,0 +1,35 @@
+use stacks::chainstate::stacks::db::reindex::DerivedIndex;
+
+use crate::reindex::*;
+use crate::Config;
+
+#[test]
+fn test_parse_indexes() {
+    assert_eq!(parse_indexes(None).unwrap(), DerivedIndex::all());
+    assert_eq!(parse_indexes(Some("receipts, contract-costs,receipts")).unwrap(),
+               vec![DerivedIndex::TransactionReceipts, DerivedIndex::ContractCosts]);
+    assert_eq!(parse_indexes(Some("memos")).unwrap(), vec![DerivedIndex::TransferMemos]);
+
+    assert!(parse_indexes(Some("tx-by-address")).is_err());
+    assert!(parse_indexes(Some("")).is_err());
+}
+
+#[test]
+fn test_default_indexes() {
+    let mut conf = Config::default();
+    assert!(!default_indexes(&conf).contains(&DerivedIndex::AddressTransactions));
+    assert_eq!(default_indexes(&conf).len(), DerivedIndex::all().len() - 1);
+
+    conf.node.index_addresses = true;
+    assert_eq!(default_indexes(&conf), DerivedIndex::all());
+}
+
+#[test]
+fn test_reindex_range() {
+    assert!(check_reindex_range(1, 1, 1).is_ok());
+    assert!(check_reindex_range(5, 10, 20).is_ok());
+
+    assert!(check_reindex_range(0, 10, 20).is_err());
+    assert!(check_reindex_range(10, 5, 20).is_err());
+    assert!(check_reindex_range(5, 21, 20).is_err());
+}
@@ -0,0 +1,32 @@
+use stacks::chainstate::stacks::StacksBlockId;
+use stacks::chainstate::stacks::index::TrieHash;
+
+use crate::replay::*;
+
+#[test]
+fn test_replay_range() {
+    assert!(check_replay_range(1, 1, 1).is_ok());
+    assert!(check_replay_range(5, 10, 20).is_ok());
+
+    // the boot block can't be replayed
+    assert!(check_replay_range(0, 10, 20).is_err());
+    assert!(check_replay_range(10, 5, 20).is_err());
+    assert!(check_replay_range(5, 21, 20).is_err());
+}
+
+#[test]
+fn test_replayed_block_matches() {
+    let mut replayed = ReplayedBlock {
+        block_height: 5,
+        index_block_hash: StacksBlockId([1u8; 32]),
+        expected_state_root: TrieHash([2u8; 32]),
+        state_root: Ok(TrieHash([2u8; 32])),
+    };
+    assert!(replayed.matches());
+
+    replayed.state_root = Ok(TrieHash([3u8; 32]));
+    assert!(!replayed.matches());
+
+    replayed.state_root = Err("No such Stacks block".to_string());
+    assert!(!replayed.matches());
+}
@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::{Config, ConfigFile};
+use crate::config::ConnectionOptionsFile;
+
+use stacks::net::overload::RequestPriority;
+
+fn config_file(priorities: Vec<(&str, &str)>) -> ConfigFile {
+    let priorities : HashMap<String, String> = priorities.into_iter()
+        .map(|(request_class, priority)| (request_class.to_string(), priority.to_string()))
+        .collect();
+    ConfigFile {
+        connection_options: Some(ConnectionOptionsFile {
+            rpc_request_priorities: Some(priorities),
+            ..ConnectionOptionsFile::default()
+        }),
+        ..ConfigFile::default()
+    }
+}
+
+#[test]
+fn rpc_request_priorities_config_parses() {
+    let config = Config::from_config_file(config_file(vec![("GetContractSrc", "critical")]));
+    assert_eq!(config.connection_options.rpc_priority_overrides, vec![("GetContractSrc".to_string(), RequestPriority::Critical)]);
+}
+
+#[test]
+#[should_panic]
+fn rpc_request_priorities_config_rejects_unknown_class() {
+    Config::from_config_file(config_file(vec![("GetContractSource", "critical")]));
+}
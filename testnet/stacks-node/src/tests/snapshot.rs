@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::PathBuf;
+
+use stacks::chainstate::stacks::StacksBlockId;
+
+use crate::snapshot::*;
+
+fn make_working_dir(name: &str) -> PathBuf {
+    let working_dir = std::env::temp_dir().join(format!("stacks-node-snapshot-{}", name));
+    if working_dir.exists() {
+        fs::remove_dir_all(&working_dir).unwrap();
+    }
+    fs::create_dir_all(working_dir.join("burnchain/db")).unwrap();
+    fs::create_dir_all(working_dir.join("chainstate/chain-00000080-testnet/vm")).unwrap();
+    fs::write(working_dir.join("burnchain/db/burn.db"), vec![1u8; 5000]).unwrap();
+    fs::write(working_dir.join("burnchain/spv-headers.dat"), b"headers").unwrap();
+    fs::write(working_dir.join("chainstate/chain-00000080-testnet/vm/headers.db"), b"some headers").unwrap();
+    fs::write(working_dir.join("chainstate/chain-00000080-testnet/vm/headers.db-shm"), b"shared memory").unwrap();
+    fs::write(working_dir.join("chainstate/chain-00000080-testnet/mempool.db"), b"mempool").unwrap();
+    fs::write(working_dir.join("peer_db.sqlite"), b"peers").unwrap();
+    working_dir
+}
+
+fn make_tip() -> SnapshotTip {
+    SnapshotTip {
+        index_block_hash: StacksBlockId([3u8; 32]),
+        block_height: 12,
+        burn_header_hash: "04".repeat(32),
+        burn_block_height: 20,
+        state_index_root: "05".repeat(32),
+    }
+}
+
+#[test]
+fn test_snapshot_files() {
+    let working_dir = make_working_dir("files");
+
+    // the mempool, peer DB and sqlite shared memory are left out
+    assert_eq!(list_snapshot_files(&working_dir).unwrap(),
+               vec!["burnchain/db/burn.db",
+                    "burnchain/spv-headers.dat",
+                    "chainstate/chain-00000080-testnet/vm/headers.db"]);
+
+    fs::remove_dir_all(working_dir.join("burnchain")).unwrap();
+    assert!(list_snapshot_files(&working_dir).is_err());
+}
+
+#[test]
+fn test_snapshot_roundtrip() {
+    let working_dir = make_working_dir("roundtrip");
+    let files = list_snapshot_files(&working_dir).unwrap();
+    let archive = working_dir.join("snapshot.gz");
+
+    let (manifest, digest) = write_snapshot(&working_dir, &files, &make_tip(), "bitcoin", &archive).unwrap();
+    assert_eq!(manifest.index_block_hash, StacksBlockId([3u8; 32]).to_hex());
+    assert_eq!(manifest.files.len(), 3);
+    assert_eq!(manifest.files[0].size, 5000);
+
+    // restores exactly what was there
+    let dest_dir = working_dir.join("restored");
+    let (restored_manifest, restored_digest) = read_snapshot(&archive, &dest_dir, Some(&digest)).unwrap();
+    assert_eq!(restored_manifest, manifest);
+    assert_eq!(restored_digest, digest);
+    for file in files.iter() {
+        assert_eq!(fs::read(dest_dir.join(file)).unwrap(), fs::read(working_dir.join(file)).unwrap());
+    }
+    assert!(!dest_dir.join("chainstate/chain-00000080-testnet/mempool.db").exists());
+
+    // a digest other than the expected one is refused
+    let other_dir = working_dir.join("refused");
+    assert!(read_snapshot(&archive, &other_dir, Some(&"00".repeat(32))).is_err());
+    assert!(!other_dir.exists());
+
+    // so is something that isn't a snapshot
+    assert!(read_snapshot(&working_dir.join("peer_db.sqlite"), &other_dir, None).is_err());
+}
@@ -0,0 +1,54 @@
+use crate::{Config, ConfigFile};
+use crate::config::NodeConfigFile;
+
+use stacks::util::db::SqlitePragmas;
+
+#[test]
+fn sqlite_pragmas_config_parses() {
+    assert_eq!(Config::from_config_file(ConfigFile::default()).node.sqlite_pragmas, SqlitePragmas::default());
+
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            db_page_size: Some(16384),
+            db_cache_size: Some(-65536),
+            db_mmap_size: Some(1 << 30),
+            db_synchronous: Some("full".to_string()),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    let sqlite_pragmas = Config::from_config_file(config_file).node.sqlite_pragmas;
+    assert_eq!(sqlite_pragmas, SqlitePragmas {
+        page_size: Some(16384),
+        cache_size: Some(-65536),
+        mmap_size: Some(1 << 30),
+        synchronous: "FULL".to_string(),
+    });
+}
+
+#[test]
+fn sqlite_pragmas_validate() {
+    let pragmas = |page_size: Option<u32>, synchronous: &str| SqlitePragmas {
+        page_size,
+        synchronous: synchronous.to_string(),
+        ..SqlitePragmas::default()
+    };
+    assert!(pragmas(Some(4096), "normal").validate().is_ok());
+    assert!(pragmas(Some(65536), "OFF").validate().is_ok());
+    assert!(pragmas(Some(256), "NORMAL").validate().is_err());
+    assert!(pragmas(Some(5000), "NORMAL").validate().is_err());
+    assert!(pragmas(None, "SOMETIMES").validate().is_err());
+}
+
+#[test]
+#[should_panic]
+fn sqlite_pragmas_config_rejects_bad_synchronous() {
+    let config_file = ConfigFile {
+        node: Some(NodeConfigFile {
+            db_synchronous: Some("sometimes".to_string()),
+            ..NodeConfigFile::default()
+        }),
+        ..ConfigFile::default()
+    };
+    Config::from_config_file(config_file);
+}
@@ -0,0 +1,21 @@
+use stacks::chainstate::stacks::db::state_diff::StateKey;
+
+use crate::state_diff::*;
+
+#[test]
+fn test_diff_range() {
+    assert!(check_diff_range(0, 0, 0).is_ok());
+    assert!(check_diff_range(5, 10, 20).is_ok());
+
+    assert!(check_diff_range(10, 5, 20).is_err());
+    assert!(check_diff_range(5, 21, 20).is_err());
+}
+
+#[test]
+fn test_describe_state_key() {
+    assert_eq!(describe_state_key(&StateKey::Account("SP000000000000000000002Q6VF78".to_string())),
+               "account SP000000000000000000002Q6VF78");
+    assert_eq!(describe_state_key(&StateKey::DataMapEntry("SP000000000000000000002Q6VF78.names".to_string(), "owners".to_string())),
+               "map SP000000000000000000002Q6VF78.names owners");
+    assert_eq!(describe_state_key(&StateKey::Other), "other");
+}
@@ -0,0 +1,48 @@
+use std::cmp::Ordering;
+
+use crate::version_check::*;
+
+fn manifest() -> ReleaseManifest {
+    serde_json::from_str(r#"{
+        "latest_version": "0.3.0",
+        "mandatory_version": "v0.2.0",
+        "mandatory_burn_height": 660000,
+        "release_url": "http://example.com/releases/0.3.0",
+        "artifacts": {
+            "linux-x86_64": "http://example.com/releases/0.3.0/stacks-node-linux-x86_64.zip"
+        }
+    }"#).unwrap()
+}
+
+#[test]
+fn test_compare_versions() {
+    assert_eq!(parse_version("v1.2.3"), Some(vec![1, 2, 3]));
+    assert_eq!(parse_version("1.2.3-rc1"), Some(vec![1, 2, 3]));
+    assert_eq!(parse_version("1.2.x"), None);
+
+    assert_eq!(compare_versions(&[1, 2], &[1, 2, 0]), Ordering::Equal);
+    assert_eq!(compare_versions(&[1, 2, 3], &[1, 10]), Ordering::Less);
+    assert_eq!(compare_versions(&[2], &[1, 99, 99]), Ordering::Greater);
+}
+
+#[test]
+fn test_update_status() {
+    let manifest = manifest();
+
+    let status = manifest.update_status("0.1.0", "linux-x86_64", 1000);
+    assert!(status.update_available);
+    assert!(status.mandatory);
+    assert_eq!(status.mandatory_burn_height, Some(660000));
+    assert_eq!(status.download_url, Some("http://example.com/releases/0.3.0/stacks-node-linux-x86_64.zip".to_string()));
+    assert_eq!(status.last_checked, 1000);
+
+    // no artifact for this platform, so point at the release page
+    let status = manifest.update_status("0.2.1", "windows-x86_64", 1000);
+    assert!(status.update_available);
+    assert!(!status.mandatory);
+    assert_eq!(status.download_url, Some("http://example.com/releases/0.3.0".to_string()));
+
+    let status = manifest.update_status("0.3.0", "linux-x86_64", 1000);
+    assert!(!status.update_available);
+    assert!(!status.mandatory);
+}
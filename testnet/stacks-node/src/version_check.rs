@@ -0,0 +1,196 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{self, AtomicBool};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::net::RPCUpdateStatus;
+use stacks::monitoring::set_update_status;
+use stacks::util::get_epoch_time_secs;
+
+use crate::run_loop::sleep_while_running;
+
+/// Version of this build, as compared against the release manifest
+pub const NODE_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// The release manifest served at `node.version_check_url`.  For example:
+///
+/// ```json
+/// {
+///   "latest_version": "0.2.0",
+///   "mandatory_version": "0.2.0",
+///   "mandatory_burn_height": 660000,
+///   "release_url": "http://example.com/releases/0.2.0",
+///   "artifacts": {
+///     "linux-x86_64": "http://example.com/releases/0.2.0/stacks-node-linux-x86_64.zip",
+///     "macos-x86_64": "http://example.com/releases/0.2.0/stacks-node-macos-x86_64.zip"
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReleaseManifest {
+    pub latest_version: String,
+    /// oldest release that nodes must run once the burnchain reaches `mandatory_burn_height`
+    pub mandatory_version: Option<String>,
+    pub mandatory_burn_height: Option<u64>,
+    pub release_url: Option<String>,
+    /// release artifacts, keyed by `<os>-<arch>`
+    #[serde(default)]
+    pub artifacts: HashMap<String, String>,
+}
+
+/// Name of this node's platform, as used to key the manifest's artifacts
+pub fn platform_name() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Parse a release version like `v1.2.3` or `1.2.3-rc1` into its numeric components.  Any
+/// pre-release or build suffix is ignored.
+pub fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches('v');
+    let release = version.split(|c: char| c == '-' || c == '+').next()?;
+    release.split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
+/// Compare two release versions, treating missing components as 0 (so `1.2` == `1.2.0`)
+pub fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    let len = std::cmp::max(a.len(), b.len());
+    for i in 0..len {
+        let x = a.get(i).cloned().unwrap_or(0);
+        let y = b.get(i).cloned().unwrap_or(0);
+        match x.cmp(&y) {
+            Ordering::Equal => continue,
+            ordering => return ordering
+        }
+    }
+    Ordering::Equal
+}
+
+/// Is `version` older than `other`?  Versions that can't be parsed are never considered older.
+fn is_older(version: &str, other: &str) -> bool {
+    match (parse_version(version), parse_version(other)) {
+        (Some(a), Some(b)) => compare_versions(&a, &b) == Ordering::Less,
+        _ => {
+            warn!("Version check: cannot compare versions {} and {}", version, other);
+            false
+        }
+    }
+}
+
+impl ReleaseManifest {
+    /// Compare a running build against this manifest
+    pub fn update_status(&self, current_version: &str, platform: &str, now: u64) -> RPCUpdateStatus {
+        let update_available = is_older(current_version, &self.latest_version);
+        let mandatory = match self.mandatory_version {
+            Some(ref mandatory_version) => is_older(current_version, mandatory_version),
+            None => false
+        };
+        let download_url = self.artifacts.get(platform).cloned()
+            .or(self.release_url.clone());
+
+        RPCUpdateStatus {
+            current_version: current_version.to_string(),
+            latest_version: self.latest_version.clone(),
+            update_available,
+            mandatory_version: self.mandatory_version.clone(),
+            mandatory_burn_height: self.mandatory_burn_height,
+            mandatory,
+            download_url,
+            last_checked: now,
+        }
+    }
+}
+
+/// Fetch the release manifest.  Only plain HTTP is supported.
+fn fetch_manifest(url: &Url) -> Result<ReleaseManifest, String> {
+    let host = url.host_str().ok_or(format!("no host in {}", url))?;
+    let port = url.port_or_known_default().ok_or(format!("no port in {}", url))?;
+    let req = Request::new(Method::Get, url.clone());
+
+    async_std::task::block_on(async {
+        let stream = TcpStream::connect(format!("{}:{}", host, port)).await
+            .map_err(|e| format!("connection failed: {:?}", e))?;
+        let mut response = client::connect(stream, req).await
+            .map_err(|e| format!("request failed: {:?}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("manifest request returned {}", response.status()));
+        }
+        let body = response.body_string().await
+            .map_err(|e| format!("failed to read manifest: {:?}", e))?;
+        serde_json::from_str(&body)
+            .map_err(|e| format!("failed to parse manifest: {:?}", e))
+    })
+}
+
+/// Log an update status, and record it in the metrics
+fn report_update_status(status: &RPCUpdateStatus) {
+    if status.mandatory {
+        match status.mandatory_burn_height {
+            Some(height) => error!("Version check: this node runs {}, but {} is mandatory before burn block {}; download it from {}",
+                                   &status.current_version, status.mandatory_version.as_ref().unwrap_or(&status.latest_version), height,
+                                   status.download_url.as_ref().map(|url| url.as_str()).unwrap_or("the release page")),
+            None => error!("Version check: this node runs {}, but {} is mandatory; download it from {}",
+                           &status.current_version, status.mandatory_version.as_ref().unwrap_or(&status.latest_version),
+                           status.download_url.as_ref().map(|url| url.as_str()).unwrap_or("the release page"))
+        }
+    }
+    else if status.update_available {
+        warn!("Version check: this node runs {}, and {} is available from {}",
+              &status.current_version, &status.latest_version,
+              status.download_url.as_ref().map(|url| url.as_str()).unwrap_or("the release page"));
+    }
+    else {
+        debug!("Version check: this node runs {}, which is up to date", &status.current_version);
+    }
+
+    let mandatory_burn_height = if status.mandatory { Some(status.mandatory_burn_height.unwrap_or(0)) } else { None };
+    set_update_status(status.update_available, mandatory_burn_height);
+}
+
+/// Periodically check the release manifest at `manifest_url`, and publish the result to
+/// `update_status`, the logs and the metrics.  A failed check keeps the last result.  Stops once
+/// `should_keep_running` is cleared.
+pub fn spawn_version_checker(manifest_url: &str, interval_secs: u64,
+                             update_status: Arc<Mutex<Option<RPCUpdateStatus>>>,
+                             should_keep_running: Arc<AtomicBool>) -> Result<JoinHandle<()>, String> {
+    let url = Url::parse(manifest_url)
+        .map_err(|e| format!("Unable to parse {} as a URL: {:?}", manifest_url, e))?;
+    if url.scheme() != "http" {
+        return Err(format!("Unsupported version check URL {}: only http:// is supported", manifest_url));
+    }
+
+    let platform = platform_name();
+    let handle = thread::Builder::new()
+        .name("version-check".to_string())
+        .spawn(move || {
+            while should_keep_running.load(atomic::Ordering::SeqCst) {
+                match fetch_manifest(&url) {
+                    Ok(manifest) => {
+                        let status = manifest.update_status(NODE_VERSION, &platform, get_epoch_time_secs());
+                        report_update_status(&status);
+                        match update_status.lock() {
+                            Ok(mut update_status) => *update_status = Some(status),
+                            Err(_) => {
+                                error!("Version check: update status lock poisoned; exiting");
+                                return;
+                            }
+                        }
+                    },
+                    Err(e) => warn!("Version check: failed to fetch {}: {}", &url, &e)
+                }
+                if !sleep_while_running(Duration::from_secs(interval_secs), &should_keep_running) {
+                    break;
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start the version check thread: {:?}", &e))?;
+    Ok(handle)
+}